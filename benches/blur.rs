@@ -0,0 +1,64 @@
+//! Serial versus parallel timing of the 1024x1024 directional blur and
+//! normalization, the slowest stages of the texture pipeline.
+//!
+//! The serial numbers run the same code on a single-thread rayon pool, so both
+//! variants produce identical images.
+//!
+//! Run with `cargo bench --bench blur`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use image::GrayImage;
+use rayon::ThreadPoolBuilder;
+use std::hint::black_box;
+use std::time::Duration;
+
+use cells::filter::{directional_blur, normalize_image};
+use cells::noise::{generate_perlin_field, FbmParams};
+use cells::voronoi::{generate_voronoi_field, VoronoiParams};
+
+const SIZE: u32 = 1024;
+
+fn inputs() -> (GrayImage, GrayImage) {
+    let voronoi = generate_voronoi_field(&VoronoiParams {
+        size: SIZE,
+        num_points: 500,
+        seed: 1,
+        ..Default::default()
+    });
+    let direction = generate_perlin_field(SIZE, &FbmParams { frequency: 4.0, seed: 1, ..Default::default() });
+    (voronoi.to_luma_image(), direction.to_luma_image())
+}
+
+fn bench_blur(c: &mut Criterion) {
+    let (voronoi, direction) = inputs();
+    let serial = ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+
+    let parallel_result = directional_blur(&voronoi, &direction, 6);
+    let serial_result = serial.install(|| directional_blur(&voronoi, &direction, 6));
+    assert_eq!(parallel_result, serial_result, "parallel blur differs from the serial one");
+
+    let mut group = c.benchmark_group("directional_blur_1024");
+    group.sample_size(10);
+    group.bench_function("serial", |b| {
+        b.iter(|| serial.install(|| directional_blur(black_box(&voronoi), black_box(&direction), 6)))
+    });
+    group.bench_function("parallel", |b| {
+        b.iter(|| directional_blur(black_box(&voronoi), black_box(&direction), 6))
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("normalize_image_1024");
+    group.bench_function("serial", |b| b.iter(|| serial.install(|| normalize_image(black_box(&parallel_result)))));
+    group.bench_function("parallel", |b| b.iter(|| normalize_image(black_box(&parallel_result))));
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default()
+        .warm_up_time(Duration::from_millis(500))
+        .measurement_time(Duration::from_secs(1))
+        .without_plots();
+    targets = bench_blur
+}
+criterion_main!(benches);
@@ -0,0 +1,57 @@
+//! Timing of the toroidal distance between scattered point pairs: the plain
+//! Euclidean distance against the squared fast path, and every metric.
+//!
+//! Run with `cargo bench --bench distance`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rand::{Rng, SeedableRng};
+use std::hint::black_box;
+use std::time::Duration;
+
+use cells::{toroidal_distance, toroidal_distance_sq, toroidal_distance_with, DistanceMetric, Point};
+
+/// The number of point pairs measured per iteration
+const PAIRS: usize = 4096;
+
+fn pairs() -> Vec<(Point, Point)> {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+    let mut point = || Point { x: rng.gen(), y: rng.gen() };
+    (0..PAIRS).map(|_| (point(), point())).collect()
+}
+
+/// The distance `distance` of every pair, summed
+fn sum(pairs: &[(Point, Point)], distance: impl Fn(Point, Point) -> f32) -> f32 {
+    pairs.iter().map(|&(p1, p2)| distance(black_box(p1), black_box(p2))).sum()
+}
+
+fn bench_distance(c: &mut Criterion) {
+    let pairs = pairs();
+
+    let mut group = c.benchmark_group("toroidal_distance");
+    group.bench_function("distance", |b| b.iter(|| sum(&pairs, toroidal_distance)));
+    group.bench_function("squared", |b| b.iter(|| sum(&pairs, toroidal_distance_sq)));
+    group.finish();
+
+    let mut group = c.benchmark_group("toroidal_distance_with");
+    for (name, metric) in [
+        ("euclidean", DistanceMetric::Euclidean),
+        ("manhattan", DistanceMetric::Manhattan),
+        ("chebyshev", DistanceMetric::Chebyshev),
+        ("minkowski_3", DistanceMetric::Minkowski(3.0)),
+    ] {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &metric, |b, &metric| {
+            b.iter(|| sum(&pairs, |p1, p2| toroidal_distance_with(p1, p2, metric)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default()
+        .warm_up_time(Duration::from_millis(500))
+        .measurement_time(Duration::from_secs(1))
+        .without_plots();
+    targets = bench_distance
+}
+criterion_main!(benches);
@@ -0,0 +1,54 @@
+//! Timing of the directional blur at several radii and of the normalization
+//! at a few sizes, on a Voronoi texture steered by Perlin noise with fixed
+//! seeds.
+//!
+//! Run with `cargo bench --bench filter`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use image::GrayImage;
+use std::hint::black_box;
+use std::time::Duration;
+
+use cells::filter::{directional_blur, normalize_image};
+use cells::noise::{generate_perlin_field, FbmParams};
+use cells::voronoi::{generate_voronoi_field, VoronoiParams};
+
+/// A Voronoi texture and a Perlin direction map of `size` pixels across
+fn inputs(size: u32) -> (GrayImage, GrayImage) {
+    let voronoi = generate_voronoi_field(&VoronoiParams { size, num_points: 200, seed: 1, ..Default::default() });
+    let direction = generate_perlin_field(size, &FbmParams { frequency: 4.0, seed: 1, ..Default::default() });
+    (voronoi.to_luma_image(), direction.to_luma_image())
+}
+
+fn bench_directional_blur(c: &mut Criterion) {
+    let (voronoi, direction) = inputs(256);
+    let mut group = c.benchmark_group("directional_blur_256");
+    group.sample_size(10);
+    for radius in [1, 4, 16] {
+        group.bench_with_input(BenchmarkId::from_parameter(radius), &radius, |b, &radius| {
+            b.iter(|| directional_blur(black_box(&voronoi), black_box(&direction), radius))
+        });
+    }
+    group.finish();
+}
+
+fn bench_normalize(c: &mut Criterion) {
+    let mut group = c.benchmark_group("normalize_image");
+    for size in [256, 512] {
+        let (voronoi, _) = inputs(size);
+        group.bench_with_input(BenchmarkId::from_parameter(size), &voronoi, |b, voronoi| {
+            b.iter(|| normalize_image(black_box(voronoi)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default()
+        .warm_up_time(Duration::from_millis(500))
+        .measurement_time(Duration::from_secs(1))
+        .without_plots();
+    targets = bench_directional_blur, bench_normalize
+}
+criterion_main!(benches);
@@ -0,0 +1,49 @@
+//! Timing of the Voronoi and fBm generators at a few sizes, point counts and
+//! octave counts, all with fixed seeds.
+//!
+//! Run with `cargo bench --bench generate`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::hint::black_box;
+use std::time::Duration;
+
+use cells::noise::{generate_fbm_field, FbmParams};
+use cells::voronoi::{generate_voronoi_field, Feature, VoronoiParams};
+
+fn bench_voronoi(c: &mut Criterion) {
+    let mut group = c.benchmark_group("voronoi");
+    group.sample_size(10);
+    for size in [128, 256] {
+        for num_points in [100, 1000] {
+            let params = VoronoiParams { size, num_points, seed: 1, feature: Feature::F2MinusF1, ..Default::default() };
+            group.bench_with_input(BenchmarkId::new(format!("{size}"), num_points), &params, |b, params| {
+                b.iter(|| generate_voronoi_field(black_box(params)))
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_fbm(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fbm");
+    group.sample_size(10);
+    for size in [128, 256] {
+        for octaves in [1, 6] {
+            let params = FbmParams { octaves, frequency: 4.0, seed: 1, ..Default::default() };
+            group.bench_with_input(BenchmarkId::new(format!("{size}"), octaves), &params, |b, params| {
+                b.iter(|| generate_fbm_field(size, black_box(params)))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default()
+        .warm_up_time(Duration::from_millis(500))
+        .measurement_time(Duration::from_secs(1))
+        .without_plots();
+    targets = bench_voronoi, bench_fbm
+}
+criterion_main!(benches);
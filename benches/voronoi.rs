@@ -0,0 +1,75 @@
+//! Scalar versus SIMD timing of the nearest-point search of a 1024x1024
+//! Voronoi texture with 1000 points, one query per pixel, and of a brute-force
+//! scan over the same points comparing distances or squared distances.
+//!
+//! Both kernels find the same points; the SIMD numbers need an x86-64 CPU
+//! with AVX and equal the scalar ones elsewhere.
+//!
+//! Run with `cargo bench --bench voronoi`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use rand::{Rng, SeedableRng};
+use std::hint::black_box;
+use std::time::Duration;
+
+use cells::grid::PointGrid;
+use cells::{toroidal_distance, toroidal_distance_sq, Point};
+
+const SIZE: u32 = 1024;
+
+/// The index of the nearest point of every pixel center, summed
+fn search(grid: &PointGrid) -> usize {
+    let step = 1.0 / SIZE as f32;
+    let mut sum = 0;
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let p = Point { x: x as f32 * step, y: y as f32 * step };
+            sum += grid.nearest(black_box(p)).map_or(0, |(index, _)| index);
+        }
+    }
+    sum
+}
+
+/// The distance to the nearest of `points` from `queries` scattered
+/// positions, summed, with `reduced` ordering the points and `expand` turning
+/// the smallest value into the distance
+fn brute_force(points: &[Point], queries: u32, reduced: impl Fn(Point, Point) -> f32, expand: fn(f32) -> f32) -> f32 {
+    (0..queries)
+        .map(|i| {
+            let p = Point { x: i as f32 / queries as f32, y: (i as f32 * 0.618) % 1.0 };
+            expand(points.iter().map(|&q| reduced(black_box(p), q)).fold(f32::INFINITY, f32::min))
+        })
+        .sum()
+}
+
+fn bench_nearest(c: &mut Criterion) {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+    let points: Vec<Point> = (0..1000).map(|_| Point { x: rng.gen(), y: rng.gen() }).collect();
+    let simd = PointGrid::new(&points);
+    let scalar = simd.clone().with_simd(false);
+    assert_eq!(search(&simd), search(&scalar), "the SIMD search differs from the scalar one");
+
+    let mut group = c.benchmark_group("nearest_1024_1000");
+    group.sample_size(10);
+    group.bench_function("scalar", |b| b.iter(|| search(&scalar)));
+    group.bench_function("simd", |b| b.iter(|| search(&simd)));
+    group.finish();
+
+    let distance = || brute_force(&points, 1024, toroidal_distance, |d| d);
+    let squared = || brute_force(&points, 1024, toroidal_distance_sq, f32::sqrt);
+    assert_eq!(distance(), squared(), "squared distances find other points");
+    let mut group = c.benchmark_group("brute_force_1000");
+    group.bench_function("distance", |b| b.iter(distance));
+    group.bench_function("squared", |b| b.iter(squared));
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default()
+        .warm_up_time(Duration::from_millis(500))
+        .measurement_time(Duration::from_secs(1))
+        .without_plots();
+    targets = bench_nearest
+}
+criterion_main!(benches);
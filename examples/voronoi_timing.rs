@@ -0,0 +1,36 @@
+//! Time Voronoi generation on a single thread versus the full rayon pool.
+//!
+//! ```text
+//! cargo run --release --example voronoi_timing -- 2048 1000
+//! ```
+
+use std::env;
+use std::time::Instant;
+
+use cells::voronoi::{generate_tileable_voronoi, VoronoiParams};
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let size = args.next().map_or(2048, |s| s.parse().expect("size must be a number"));
+    let points = args.next().map_or(1000, |s| s.parse().expect("points must be a number"));
+
+    let params = VoronoiParams { size, num_points: points, ..Default::default() };
+
+    let serial_pool = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+    let start = Instant::now();
+    let serial = serial_pool.install(|| generate_tileable_voronoi(&params));
+    let serial_time = start.elapsed();
+
+    let start = Instant::now();
+    let parallel = generate_tileable_voronoi(&params);
+    let parallel_time = start.elapsed();
+
+    assert!(serial == parallel, "parallel output differs from serial output");
+    println!("{size}x{size}, {points} points");
+    println!("1 thread:   {serial_time:.2?}");
+    println!(
+        "{} threads: {parallel_time:.2?} ({:.1}x)",
+        rayon::current_num_threads(),
+        serial_time.as_secs_f64() / parallel_time.as_secs_f64()
+    );
+}
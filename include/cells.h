@@ -0,0 +1,168 @@
+#ifndef CELLS_H
+#define CELLS_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit by hand */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// [`Feature::F1`], the distance to the nearest point
+#define CELLS_FEATURE_F1 0
+
+// [`Feature::F2`], the distance to the second-nearest point
+#define CELLS_FEATURE_F2 1
+
+// [`Feature::F2MinusF1`], zero on the cell boundaries
+#define CELLS_FEATURE_F2_MINUS_F1 2
+
+// [`Feature::CellValue`], a random value per cell
+#define CELLS_FEATURE_CELL_VALUE 3
+
+// [`DistanceMetric::Euclidean`]
+#define CELLS_METRIC_EUCLIDEAN 0
+
+// [`DistanceMetric::Manhattan`]
+#define CELLS_METRIC_MANHATTAN 1
+
+// [`DistanceMetric::Chebyshev`]
+#define CELLS_METRIC_CHEBYSHEV 2
+
+// [`DistanceMetric::Minkowski`] with the exponent `minkowski_p`
+#define CELLS_METRIC_MINKOWSKI 3
+
+// The outcome of a call, `CELLS_STATUS_OK` on success
+typedef enum CellsStatus {
+  // The texture was generated
+  CELLS_STATUS_OK = 0,
+  // A required pointer argument was null
+  CELLS_STATUS_NULL_POINTER = 1,
+  // A parameter is outside its supported range
+  CELLS_STATUS_INVALID_PARAMETER = 2,
+  // The generator panicked; no buffer was allocated
+  CELLS_STATUS_PANIC = 3,
+} CellsStatus;
+
+// The parameters of [`cells_voronoi`]
+typedef struct CellsVoronoiParams {
+  // The width and height of the texture in pixels
+  uint32_t size;
+  // The number of Voronoi cells
+  uint32_t num_points;
+  // The seed of the point positions
+  uint64_t seed;
+  // One of the `CELLS_FEATURE_*` constants
+  uint32_t feature;
+  // One of the `CELLS_METRIC_*` constants
+  uint32_t metric;
+  // The exponent of `CELLS_METRIC_MINKOWSKI`, at least 1
+  float minkowski_p;
+  // Whether to invert the texture, making the cell centers bright
+  bool invert;
+} CellsVoronoiParams;
+
+// The parameters of [`cells_fbm`], see [`FbmParams`]
+typedef struct CellsFbmParams {
+  // The width and height of the texture in pixels
+  uint32_t size;
+  // The number of noise layers summed
+  uint32_t octaves;
+  // The amplitude factor from one octave to the next
+  double persistence;
+  // The frequency factor from one octave to the next
+  double lacunarity;
+  // The number of noise features across the texture in the first octave
+  double frequency;
+  // The seed of the noise permutation table
+  uint64_t seed;
+} CellsFbmParams;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// The default parameters of [`cells_voronoi`], those of [`VoronoiParams`]
+CellsVoronoiParams cells_voronoi_default_params(void);
+
+// The default parameters of [`cells_fbm`], those of [`FbmParams`] for a
+// 512 pixel texture
+CellsFbmParams cells_fbm_default_params(void);
+
+// Generate a tileable Voronoi texture
+//
+// On success `*out_buf` points to `*out_len` samples owned by the caller,
+// to be released with [`cells_free`]. On failure both are left unchanged.
+//
+// # Safety
+//
+// `params` must point to a valid [`CellsVoronoiParams`], and `out_buf` and
+// `out_len` to writable locations; null pointers are reported as
+// [`CellsStatus::NullPointer`].
+CellsStatus cells_voronoi(const CellsVoronoiParams *params, float **out_buf, size_t *out_len);
+
+// Generate tileable fractal Perlin noise
+//
+// On success `*out_buf` points to `*out_len` samples owned by the caller,
+// to be released with [`cells_free`]. On failure both are left unchanged.
+//
+// # Safety
+//
+// `params` must point to a valid [`CellsFbmParams`], and `out_buf` and
+// `out_len` to writable locations; null pointers are reported as
+// [`CellsStatus::NullPointer`].
+CellsStatus cells_fbm(const CellsFbmParams *params, float **out_buf, size_t *out_len);
+
+// Blur `width * height` samples with a wrapping Gaussian of standard
+// deviation `sigma`, see [`gaussian_blur`]
+//
+// On success `*out_buf` points to `*out_len` samples owned by the caller,
+// to be released with [`cells_free`]. On failure both are left unchanged.
+//
+// # Safety
+//
+// `samples` must point to `width * height` readable samples in row order,
+// and `out_buf` and `out_len` to writable locations.
+CellsStatus cells_gaussian_blur(const float *samples,
+                                uint32_t width,
+                                uint32_t height,
+                                float sigma,
+                                float **out_buf,
+                                size_t *out_len);
+
+// Blur `width * height` samples along the directions of a second field of
+// the same size, see [`directional_blur_field`]
+//
+// On success `*out_buf` points to `*out_len` samples owned by the caller,
+// to be released with [`cells_free`]. On failure both are left unchanged.
+//
+// # Safety
+//
+// `samples` and `direction` must each point to `width * height` readable
+// samples in row order, and `out_buf` and `out_len` to writable locations.
+CellsStatus cells_directional_blur(const float *samples,
+                                   const float *direction,
+                                   uint32_t width,
+                                   uint32_t height,
+                                   int32_t radius,
+                                   float **out_buf,
+                                   size_t *out_len);
+
+// Release a buffer returned by one of the generators or filters; null
+// buffers are ignored
+//
+// # Safety
+//
+// `buf` and `len` must be exactly as returned by one of the functions, and
+// the buffer must not be used or released again afterwards.
+void cells_free(float *buf, size_t len);
+
+// A static, null-terminated description of a status
+const char *cells_status_message(CellsStatus status);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* CELLS_H */
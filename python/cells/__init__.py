@@ -0,0 +1,12 @@
+"""Seamless, tileable textures from the cells crate as numpy arrays.
+
+The generators return square ``float32`` arrays of shape ``(size, size)``,
+the filters take and return arrays of any 2D shape. The functions come from
+the PyO3 module ``cells._native`` built with the ``python`` feature of the
+crate, which releases the GIL while the texture is computed, so the rayon
+threads of a call run in parallel to other Python threads.
+"""
+
+from ._native import CellsError, directional_blur, fbm, gaussian_blur, voronoi
+
+__all__ = ["CellsError", "voronoi", "fbm", "gaussian_blur", "directional_blur"]
@@ -0,0 +1,49 @@
+"""The Python package must be deterministic for a fixed seed and hand back
+the samples of the crate unchanged."""
+
+import numpy as np
+import pytest
+
+import cells
+
+
+def test_voronoi_is_deterministic():
+    a = cells.voronoi(64, 30, seed=5)
+    b = cells.voronoi(64, 30, seed=5)
+    assert a.shape == (64, 64) and a.dtype == np.float32
+    assert np.array_equal(a, b)
+    assert not np.array_equal(a, cells.voronoi(64, 30, seed=6))
+
+
+def test_fbm_is_deterministic():
+    a = cells.fbm(48, octaves=4, seed=9, frequency=2.0)
+    assert np.array_equal(a, cells.fbm(48, octaves=4, seed=9, frequency=2.0))
+    assert 0.0 <= a.min() and a.max() <= 1.0
+
+
+def test_textures_tile():
+    field = cells.fbm(64, octaves=3, seed=1)
+    # The step across the wrapped edge is no larger than the steps inside
+    inside = np.abs(np.diff(field, axis=1)).max()
+    assert np.abs(field[:, 0] - field[:, -1]).max() <= inside * 1.5
+
+
+def test_filters_take_arrays():
+    field = cells.voronoi(32, 10, seed=2)
+    blurred = cells.gaussian_blur(field, 2.0)
+    assert blurred.shape == field.shape
+    assert blurred.std() < field.std()
+    assert np.isclose(blurred.mean(), field.mean(), atol=1e-4)
+    directional = cells.directional_blur(field.astype(np.float64), field, 4)
+    assert directional.shape == field.shape and directional.dtype == np.float32
+
+
+def test_invalid_parameters_raise():
+    with pytest.raises(cells.CellsError):
+        cells.voronoi(64, 0)
+    with pytest.raises(cells.CellsError, match="feature"):
+        cells.voronoi(64, 10, feature="f3")
+    with pytest.raises(cells.CellsError):
+        cells.fbm(64, octaves=0)
+    with pytest.raises(cells.CellsError):
+        cells.directional_blur(np.zeros((4, 4)), np.zeros((4, 5)), 2)
@@ -0,0 +1,99 @@
+//! Frame sequences that loop in time: numbered frame files and flipbooks.
+//!
+//! The looping frames themselves come from [`generate_fbm_frame`] and
+//! [`generate_voronoi_frame`], which take the position in the loop as a phase
+//! from 0 to 1; frame `i` of `n` is rendered at phase `i / n`, so the frame
+//! after the last one would be the first again.
+//!
+//! [`generate_fbm_frame`]: crate::noise::generate_fbm_frame
+//! [`generate_voronoi_frame`]: crate::voronoi::generate_voronoi_frame
+
+use crate::error::CellsError;
+use crate::field::FieldBuffer;
+
+/// The file name of frame `index` from a pattern with one placeholder
+///
+/// `{}` is replaced by the frame number and `{:0N}` by the frame number
+/// zero-padded to `N` digits, like Rust's format strings.
+///
+/// # Returns
+///
+/// [`CellsError::InvalidParameter`] unless the pattern holds exactly one
+/// placeholder of these forms
+///
+/// # Example
+///
+/// ```rust
+/// use cells::animate::frame_path;
+///
+/// assert_eq!(frame_path("frames/noise_{:03}.png", 7).unwrap(), "frames/noise_007.png");
+/// assert_eq!(frame_path("noise_{}.png", 1234).unwrap(), "noise_1234.png");
+/// assert!(frame_path("noise.png", 0).is_err());
+/// assert!(frame_path("noise_{:x}.png", 0).is_err());
+/// ```
+pub fn frame_path(pattern: &str, index: u32) -> Result<String, CellsError> {
+    let invalid = |reason: String| CellsError::InvalidParameter { name: "frame pattern", reason };
+    let start = pattern
+        .find('{')
+        .ok_or_else(|| invalid(format!("`{pattern}` has no {{}} or {{:0N}} placeholder for the frame number")))?;
+    let end = pattern[start..]
+        .find('}')
+        .map(|end| start + end)
+        .ok_or_else(|| invalid(format!("`{pattern}` has an unclosed placeholder")))?;
+    let (prefix, suffix) = (&pattern[..start], &pattern[end + 1..]);
+    if suffix.contains(['{', '}']) {
+        return Err(invalid(format!("`{pattern}` has more than one placeholder")));
+    }
+    let width = match &pattern[start + 1..end] {
+        "" => 0,
+        spec => spec
+            .strip_prefix(":0")
+            .and_then(|width| width.parse().ok())
+            .ok_or_else(|| invalid(format!("unsupported placeholder `{{{spec}}}`, expected {{}} or {{:0N}}")))?,
+    };
+    Ok(format!("{prefix}{index:0width$}{suffix}"))
+}
+
+/// Pack frames into one sprite sheet of `cols` x `rows` cells
+///
+/// The frames fill the sheet row by row from the top left, the layout game
+/// engines play flipbooks in.
+///
+/// # Returns
+///
+/// [`CellsError::InvalidParameter`] if there are not exactly `cols * rows`
+/// frames, or [`CellsError::DimensionMismatch`] if they differ in size
+///
+/// # Example
+///
+/// ```rust
+/// use cells::animate::flipbook;
+/// use cells::FieldBuffer;
+///
+/// let frames: Vec<FieldBuffer> = (0..6).map(|i| FieldBuffer::from_vec(2, 2, vec![i as f32; 4])).collect();
+/// let sheet = flipbook(&frames, 3, 2).unwrap();
+/// assert_eq!(sheet.dimensions(), (6, 4));
+/// assert_eq!([sheet.get(0, 0), sheet.get(5, 1), sheet.get(0, 2), sheet.get(5, 3)], [0.0, 2.0, 3.0, 5.0]);
+/// assert!(flipbook(&frames, 2, 2).is_err());
+/// ```
+pub fn flipbook(frames: &[FieldBuffer], cols: u32, rows: u32) -> Result<FieldBuffer, CellsError> {
+    let cells = cols as u64 * rows as u64;
+    if frames.len() as u64 != cells || frames.is_empty() {
+        return Err(CellsError::InvalidParameter {
+            name: "frames",
+            reason: format!("a {cols}x{rows} flipbook holds {cells} frames, got {}", frames.len()),
+        });
+    }
+    let (width, height) = frames[0].dimensions();
+    if let Some(frame) = frames.iter().find(|frame| frame.dimensions() != (width, height)) {
+        return Err(CellsError::DimensionMismatch {
+            input: "frame",
+            expected: (width, height),
+            found: frame.dimensions(),
+        });
+    }
+    Ok(FieldBuffer::from_par_fn(width * cols, height * rows, |x, y| {
+        let frame = &frames[(y / height * cols + x / width) as usize];
+        frame.get(x % width, y % height)
+    }))
+}
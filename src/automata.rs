@@ -0,0 +1,85 @@
+//! Cellular automata over binary masks, such as the smoothing of cave
+//! generators.
+//!
+//! Cells count their 8 neighbors across the wrap-around edges, so smoothing
+//! keeps masks tileable.
+
+use crate::field::FieldBuffer;
+use crate::tone::threshold;
+
+/// Samples at or above this value are solid cells
+const SOLID: f32 = 0.5;
+
+/// The classic cave rule: empty cells with at least 5 solid neighbors fill
+pub const DEFAULT_BIRTH_LIMIT: u32 = 5;
+
+/// The classic cave rule: solid cells with fewer than 4 solid neighbors empty
+pub const DEFAULT_DEATH_LIMIT: u32 = 4;
+
+/// Smooth a mask with a cellular automaton
+///
+/// The mask is first reduced to solid (1) and empty (0) cells at 0.5. Every
+/// iteration, an empty cell becomes solid when at least `birth_limit` of its
+/// 8 neighbors are solid, and a solid cell stays solid only when at least
+/// `death_limit` of them are. With the classic 5 and 4, noise melts into
+/// rounded caves within a few iterations.
+///
+/// # Arguments
+///
+/// * `mask` - The mask to smooth, e.g. a thresholded Voronoi texture
+/// * `iterations` - How many times the rule is applied
+/// * `birth_limit` - Solid neighbors an empty cell needs to fill, 0-8
+/// * `death_limit` - Solid neighbors a solid cell needs to stay, 0-8
+///
+/// # Returns
+///
+/// A `FieldBuffer` of 0 and 1 values
+///
+/// # Example
+///
+/// ```rust
+/// use cells::automata::cellular_smooth;
+/// use cells::FieldBuffer;
+///
+/// // A notch in a solid field fills, a speck in an empty field vanishes
+/// let notched = FieldBuffer::from_par_fn(4, 4, |x, y| if (x, y) == (1, 2) { 0.0 } else { 1.0 });
+/// assert_eq!(cellular_smooth(&notched, 1, 5, 4).as_slice(), [1.0; 16]);
+/// let speck = FieldBuffer::from_par_fn(4, 4, |x, y| if (x, y) == (1, 2) { 1.0 } else { 0.0 });
+/// assert_eq!(cellular_smooth(&speck, 1, 5, 4).as_slice(), [0.0; 16]);
+/// ```
+pub fn cellular_smooth(mask: &FieldBuffer, iterations: u32, birth_limit: u32, death_limit: u32) -> FieldBuffer {
+    cellular_smooth_with(mask, iterations, birth_limit, death_limit, |_, _| {})
+}
+
+/// [`cellular_smooth`], calling `on_iteration` with the index and result of
+/// every iteration, e.g. to save them while tuning the limits
+pub fn cellular_smooth_with<F>(
+    mask: &FieldBuffer,
+    iterations: u32,
+    birth_limit: u32,
+    death_limit: u32,
+    mut on_iteration: F,
+) -> FieldBuffer
+where
+    F: FnMut(u32, &FieldBuffer),
+{
+    let mut cells = threshold(mask, SOLID);
+    for i in 0..iterations {
+        let (width, height) = cells.dimensions();
+        cells = FieldBuffer::from_par_fn(width, height, |x, y| {
+            let (x, y) = (x as i64, y as i64);
+            let neighbors = (-1..=1)
+                .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+                .filter(|&offset| offset != (0, 0) && cells.get_wrapped(x + offset.0, y + offset.1) >= SOLID)
+                .count() as u32;
+            let limit = if cells.get_wrapped(x, y) >= SOLID { death_limit } else { birth_limit };
+            if neighbors >= limit {
+                1.0
+            } else {
+                0.0
+            }
+        });
+        on_iteration(i, &cells);
+    }
+    cells
+}
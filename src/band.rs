@@ -0,0 +1,197 @@
+//! Rendering textures in bands of rows, for textures too large to hold in
+//! memory at once.
+//!
+//! A [`BandSource`] renders any range of rows of a texture on request. The
+//! Voronoi diagram and the noise are pure functions of the pixel position, so
+//! [`VoronoiBands`](crate::voronoi::VoronoiBands) and
+//! [`PerlinBands`](crate::noise::PerlinBands) render bands directly.
+//! [`Stretched`] and [`GaussianBlurred`] wrap other sources: the stretch
+//! finds the range of the whole texture in a first pass, and the blur renders
+//! the halo rows it needs above and below every band, wrapping around the
+//! edges. Every source renders exactly the samples of the corresponding
+//! whole-texture function, so a texture rendered in bands equals the texture
+//! rendered at once.
+//!
+//! [`save_png_bands`](crate::io::save_png_bands) streams the bands of a
+//! source into a PNG file, holding only one band at a time.
+
+use std::ops::Range;
+
+use crate::field::FieldBuffer;
+use crate::filter::{gaussian_radius, BlurKernel};
+
+/// A texture rendered on request, any range of rows at a time
+pub trait BandSource: Sync {
+    /// The width and height of the whole texture
+    fn dimensions(&self) -> (u32, u32);
+
+    /// Render the rows `rows` of the texture, a field of the full width and
+    /// `rows.len()` rows
+    ///
+    /// The rows lie within the height of the texture.
+    fn render(&self, rows: Range<u32>) -> FieldBuffer;
+}
+
+impl BandSource for FieldBuffer {
+    fn dimensions(&self) -> (u32, u32) {
+        FieldBuffer::dimensions(self)
+    }
+
+    fn render(&self, rows: Range<u32>) -> FieldBuffer {
+        let width = self.width() as usize;
+        let data = self.as_slice()[rows.start as usize * width..rows.end as usize * width].to_vec();
+        FieldBuffer::from_vec(self.width(), rows.len() as u32, data)
+    }
+}
+
+/// The ranges of rows of the bands of `band_rows` rows covering `height`
+/// rows, top to bottom
+pub fn bands(height: u32, band_rows: u32) -> impl Iterator<Item = Range<u32>> {
+    let band_rows = band_rows.max(1);
+    (0..height.div_ceil(band_rows)).map(move |i| i * band_rows..((i + 1) * band_rows).min(height))
+}
+
+/// Render a whole source, `band_rows` rows at a time
+///
+/// # Example
+///
+/// ```rust
+/// use cells::band::{render_all, GaussianBlurred};
+/// use cells::filter::gaussian_blur;
+/// use cells::FieldBuffer;
+///
+/// let field = FieldBuffer::from_par_fn(24, 20, |x, y| ((x * 7 + y * 13) % 10) as f32 / 10.0);
+/// // Bands of 3 rows need halo rows from the bands above and below, and
+/// // wrap around at the top and the bottom
+/// let blurred = GaussianBlurred::new(field.clone(), 1.5);
+/// assert_eq!(render_all(&blurred, 3), gaussian_blur(&field, 1.5));
+/// ```
+pub fn render_all(source: &impl BandSource, band_rows: u32) -> FieldBuffer {
+    let (width, height) = source.dimensions();
+    let mut data = Vec::with_capacity(width as usize * height as usize);
+    for rows in bands(height, band_rows) {
+        data.extend_from_slice(source.render(rows).as_slice());
+    }
+    FieldBuffer::from_vec(width, height, data)
+}
+
+/// Render rows `start..end` of a source, which may reach past the top and
+/// the bottom and wrap around
+fn render_wrapped(source: &impl BandSource, start: i64, end: i64) -> FieldBuffer {
+    let (width, height) = source.dimensions();
+    let mut data = Vec::with_capacity(width as usize * (end - start) as usize);
+    let mut y = start;
+    while y < end {
+        // The longest run of rows without a wrap
+        let first = y.rem_euclid(height as i64);
+        let len = (end - y).min(height as i64 - first);
+        data.extend_from_slice(source.render(first as u32..(first + len) as u32).as_slice());
+        y += len;
+    }
+    FieldBuffer::from_vec(width, (end - start) as u32, data)
+}
+
+/// A source stretched to 0-1 by the smallest and largest sample of the whole
+/// texture, rendered band by band like [`normalize_field`](crate::filter::normalize_field)
+#[derive(Debug)]
+pub struct Stretched<S> {
+    source: S,
+    /// The smallest sample and the range of the samples, if it is not empty
+    range: Option<(f32, f32)>,
+}
+
+impl<S: BandSource> Stretched<S> {
+    /// Find the smallest and largest sample of `source`, rendering it once
+    /// in bands of `band_rows` rows
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::band::{render_all, Stretched};
+    /// use cells::filter::normalize_field;
+    /// use cells::FieldBuffer;
+    ///
+    /// let field = FieldBuffer::from_par_fn(16, 16, |x, y| (x as f32 - 4.0) * 0.3 + y as f32 * 0.01);
+    /// assert_eq!(render_all(&Stretched::new(field.clone(), 5), 7), normalize_field(&field));
+    /// ```
+    pub fn new(source: S, band_rows: u32) -> Stretched<S> {
+        let (_, height) = source.dimensions();
+        let min_max = bands(height, band_rows)
+            .filter_map(|rows| source.render(rows).min_max())
+            .reduce(|a, b| (a.0.min(b.0), a.1.max(b.1)));
+        Stretched {
+            source,
+            range: min_max.filter(|(min, max)| max > min).map(|(min, max)| (min, max - min)),
+        }
+    }
+}
+
+impl<S: BandSource> BandSource for Stretched<S> {
+    fn dimensions(&self) -> (u32, u32) {
+        self.source.dimensions()
+    }
+
+    fn render(&self, rows: Range<u32>) -> FieldBuffer {
+        let band = self.source.render(rows);
+        match self.range {
+            Some((min, range)) => band.map(|value| (value - min) / range),
+            None => band,
+        }
+    }
+}
+
+/// A source blurred like [`gaussian_blur`](crate::filter::gaussian_blur),
+/// rendering every band with `3 * sigma` halo rows above and below, at most
+/// the larger dimension of the source
+#[derive(Debug)]
+pub struct GaussianBlurred<S> {
+    source: S,
+    radius: i32,
+    weights: Vec<f32>,
+}
+
+impl<S: BandSource> GaussianBlurred<S> {
+    /// Blur `source` with the standard deviation `sigma` in pixels
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sigma` is negative or not finite.
+    pub fn new(source: S, sigma: f32) -> GaussianBlurred<S> {
+        assert!(sigma.is_finite() && sigma >= 0.0, "Gaussian sigma must be non-negative, got {sigma}");
+        let (width, height) = source.dimensions();
+        let radius = gaussian_radius(sigma, width, height);
+        let weights = match radius {
+            0 => Vec::new(),
+            _ => BlurKernel::Gaussian { sigma }.weights(radius),
+        };
+        GaussianBlurred { source, radius, weights }
+    }
+
+    /// The rows rendered above and below every band
+    pub fn halo(&self) -> u32 {
+        self.radius as u32
+    }
+}
+
+impl<S: BandSource> BandSource for GaussianBlurred<S> {
+    fn dimensions(&self) -> (u32, u32) {
+        self.source.dimensions()
+    }
+
+    fn render(&self, rows: Range<u32>) -> FieldBuffer {
+        if self.radius == 0 {
+            return self.source.render(rows);
+        }
+        let radius = self.radius as i64;
+        let (width, band_height) = (self.source.dimensions().0, rows.len() as u32);
+        let halo = render_wrapped(&self.source, rows.start as i64 - radius, rows.end as i64 + radius);
+        // The same sums in the same order as the passes of `gaussian_blur`
+        let taps = || (-radius..=radius).zip(&self.weights);
+        let horizontal = FieldBuffer::from_par_fn(width, halo.height(), |x, y| {
+            taps().map(|(i, weight)| weight * halo.get_wrapped(x as i64 + i, y as i64)).sum()
+        });
+        FieldBuffer::from_par_fn(width, band_height, |x, y| {
+            taps().map(|(i, weight)| weight * horizontal.get(x, (y as i64 + radius + i) as u32)).sum()
+        })
+    }
+}
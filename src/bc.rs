@@ -0,0 +1,165 @@
+//! BC4 and BC5 block compression of one and two channel textures.
+//!
+//! Both formats split the texture into blocks of 4x4 texels. A BC4 block
+//! stores two 8-bit endpoints and a 3-bit index per texel picking one of eight
+//! levels between them, 8 bytes for 16 samples; BC5 is two BC4 blocks, one
+//! for the red and one for the green channel, which suits the X and Y of
+//! tangent-space normal maps. Blocks along the right and bottom edges of
+//! textures whose size is not a multiple of 4 repeat the edge texels.
+//!
+//! The encoder picks the extremes of every block as its endpoints, which
+//! keeps hard edges exact and costs little on smooth noise. This module needs
+//! the `block-compression` feature, enabled by default.
+
+use rayon::prelude::*;
+
+use crate::error::CellsError;
+use crate::field::FieldBuffer;
+
+/// Bytes of one BC4 block
+const BLOCK_BYTES: usize = 8;
+
+/// Compress a field into BC4 blocks, row by row from the top left
+///
+/// Values are clamped to [0, 1] and quantized to the 8-bit endpoints and
+/// interpolated levels of the format.
+///
+/// # Returns
+///
+/// 8 bytes for every 4x4 block, `ceil(width / 4) * ceil(height / 4)` blocks
+///
+/// # Example
+///
+/// ```rust
+/// use cells::bc::{decode_bc4, encode_bc4};
+/// use cells::FieldBuffer;
+///
+/// let field = FieldBuffer::from_par_fn(6, 5, |x, y| (x + y) as f32 / 9.0);
+/// let blocks = encode_bc4(&field);
+/// assert_eq!(blocks.len(), 2 * 2 * 8);
+/// let decoded = decode_bc4(&blocks, 6, 5).unwrap();
+/// assert!(decoded.as_slice().iter().zip(field.as_slice()).all(|(a, b)| (a - b).abs() < 0.05));
+/// ```
+pub fn encode_bc4(field: &FieldBuffer) -> Vec<u8> {
+    let (cols, rows) = block_counts(field.width(), field.height());
+    let mut data = vec![0; (cols * rows) as usize * BLOCK_BYTES];
+    data.par_chunks_mut(BLOCK_BYTES).enumerate().for_each(|(i, block)| {
+        let (bx, by) = (i as u32 % cols, i as u32 / cols);
+        block.copy_from_slice(&encode_block(&block_texels(field, bx, by)));
+    });
+    data
+}
+
+/// Compress two fields, typically the X and Y of a normal map, into BC5
+/// blocks of a red and a green BC4 block each
+///
+/// # Returns
+///
+/// 16 bytes for every 4x4 block, or [`CellsError::DimensionMismatch`] if the
+/// fields differ in size
+pub fn encode_bc5(red: &FieldBuffer, green: &FieldBuffer) -> Result<Vec<u8>, CellsError> {
+    if green.dimensions() != red.dimensions() {
+        return Err(CellsError::DimensionMismatch {
+            input: "green",
+            expected: red.dimensions(),
+            found: green.dimensions(),
+        });
+    }
+    let (red, green) = (encode_bc4(red), encode_bc4(green));
+    Ok(red.chunks(BLOCK_BYTES).zip(green.chunks(BLOCK_BYTES)).flat_map(|(r, g)| r.iter().chain(g)).copied().collect())
+}
+
+/// Decompress BC4 blocks into a field of `width` x `height`
+///
+/// # Returns
+///
+/// [`CellsError::InvalidParameter`] if `data` does not hold exactly the
+/// blocks of a texture of that size
+pub fn decode_bc4(data: &[u8], width: u32, height: u32) -> Result<FieldBuffer, CellsError> {
+    decode_channel(data, width, height, 0, 1)
+}
+
+/// Decompress BC5 blocks into the red and green fields of `width` x `height`
+///
+/// # Returns
+///
+/// [`CellsError::InvalidParameter`] if `data` does not hold exactly the
+/// blocks of a texture of that size
+pub fn decode_bc5(data: &[u8], width: u32, height: u32) -> Result<(FieldBuffer, FieldBuffer), CellsError> {
+    Ok((decode_channel(data, width, height, 0, 2)?, decode_channel(data, width, height, 1, 2)?))
+}
+
+/// The number of block columns and rows covering a texture
+fn block_counts(width: u32, height: u32) -> (u32, u32) {
+    (width.div_ceil(4), height.div_ceil(4))
+}
+
+/// The 16 texels of block `(bx, by)` in row order, repeating the edge texels
+/// past the right and bottom edges
+fn block_texels(field: &FieldBuffer, bx: u32, by: u32) -> [f32; 16] {
+    std::array::from_fn(|i| {
+        let x = (bx * 4 + i as u32 % 4).min(field.width() - 1);
+        let y = (by * 4 + i as u32 / 4).min(field.height() - 1);
+        field.get(x, y).clamp(0.0, 1.0)
+    })
+}
+
+/// The eight levels a block with endpoints `red0` and `red1` can pick from
+///
+/// With `red0 > red1` six levels are interpolated between them, otherwise
+/// four, followed by 0 and 1.
+fn palette(red0: u8, red1: u8) -> [f32; 8] {
+    let (r0, r1) = (red0 as f32 / 255.0, red1 as f32 / 255.0);
+    std::array::from_fn(|i| match i {
+        0 => r0,
+        1 => r1,
+        _ if red0 > red1 => ((8 - i) as f32 * r0 + (i - 1) as f32 * r1) / 7.0,
+        6 => 0.0,
+        7 => 1.0,
+        _ => ((6 - i) as f32 * r0 + (i - 1) as f32 * r1) / 5.0,
+    })
+}
+
+/// Encode one block of texels in [0, 1] with its extremes as endpoints
+fn encode_block(texels: &[f32; 16]) -> [u8; BLOCK_BYTES] {
+    let quantize = |v: f32| (v * 255.0).round() as u8;
+    let max = quantize(texels.iter().copied().fold(0.0, f32::max));
+    let min = quantize(texels.iter().copied().fold(1.0, f32::min));
+    let levels = palette(max, min);
+    let mut indices = 0u64;
+    for (i, &texel) in texels.iter().enumerate() {
+        let nearest = (0..8).min_by(|&a, &b| (levels[a] - texel).abs().total_cmp(&(levels[b] - texel).abs()));
+        indices |= (nearest.unwrap_or(0) as u64) << (3 * i);
+    }
+    let mut block = [0; BLOCK_BYTES];
+    block[0] = max;
+    block[1] = min;
+    block[2..].copy_from_slice(&indices.to_le_bytes()[..6]);
+    block
+}
+
+/// Decode channel `channel` of blocks made of `channels` BC4 blocks each
+fn decode_channel(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    channel: usize,
+    channels: usize,
+) -> Result<FieldBuffer, CellsError> {
+    let (cols, rows) = block_counts(width, height);
+    let expected = (cols * rows) as usize * BLOCK_BYTES * channels;
+    if data.len() != expected {
+        return Err(CellsError::InvalidParameter {
+            name: "block data",
+            reason: format!("a {width}x{height} texture has {expected} bytes of blocks, got {}", data.len()),
+        });
+    }
+    Ok(FieldBuffer::from_par_fn(width, height, |x, y| {
+        let start = ((y / 4 * cols + x / 4) as usize * channels + channel) * BLOCK_BYTES;
+        let block = &data[start..start + BLOCK_BYTES];
+        let mut bytes = [0; 8];
+        bytes[..6].copy_from_slice(&block[2..]);
+        let index = u64::from_le_bytes(bytes) >> (3 * (y % 4 * 4 + x % 4)) & 7;
+        palette(block[0], block[1])[index as usize]
+    }))
+}
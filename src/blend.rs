@@ -0,0 +1,203 @@
+//! Compositing two fields with standard blend modes.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::CellsError;
+use crate::field::FieldBuffer;
+
+/// How a blend layer combines with the base field
+///
+/// The names follow image editors: `a` is the base and `b` the layer blended
+/// on top of it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    /// `a + b`, brightening
+    Add,
+    /// `a * b`, darkening
+    Multiply,
+    /// `1 - (1 - a) * (1 - b)`, brightening without clipping as early as Add
+    Screen,
+    /// Multiply in the dark half and Screen in the bright half of the base,
+    /// adding contrast
+    Overlay,
+    /// The darker of both fields
+    Min,
+    /// The brighter of both fields
+    Max,
+    /// `|a - b|`
+    Difference,
+    /// The layer replacing the base, so the opacity cross-fades between them
+    #[default]
+    Lerp,
+}
+
+impl BlendMode {
+    /// Combine a base sample `a` with a layer sample `b`, before opacity and
+    /// clamping
+    pub fn combine(self, a: f32, b: f32) -> f32 {
+        match self {
+            BlendMode::Add => a + b,
+            BlendMode::Multiply => a * b,
+            BlendMode::Screen => 1.0 - (1.0 - a) * (1.0 - b),
+            BlendMode::Overlay if a < 0.5 => 2.0 * a * b,
+            BlendMode::Overlay => 1.0 - 2.0 * (1.0 - a) * (1.0 - b),
+            BlendMode::Min => a.min(b),
+            BlendMode::Max => a.max(b),
+            BlendMode::Difference => (a - b).abs(),
+            BlendMode::Lerp => b,
+        }
+    }
+}
+
+impl fmt::Display for BlendMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            BlendMode::Add => "add",
+            BlendMode::Multiply => "multiply",
+            BlendMode::Screen => "screen",
+            BlendMode::Overlay => "overlay",
+            BlendMode::Min => "min",
+            BlendMode::Max => "max",
+            BlendMode::Difference => "difference",
+            BlendMode::Lerp => "lerp",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for BlendMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "add" => Ok(BlendMode::Add),
+            "multiply" => Ok(BlendMode::Multiply),
+            "screen" => Ok(BlendMode::Screen),
+            "overlay" => Ok(BlendMode::Overlay),
+            "min" => Ok(BlendMode::Min),
+            "max" => Ok(BlendMode::Max),
+            "difference" => Ok(BlendMode::Difference),
+            "lerp" => Ok(BlendMode::Lerp),
+            _ => Err(format!(
+                "unknown blend mode `{s}`, expected add, multiply, screen, overlay, min, max, difference or lerp"
+            )),
+        }
+    }
+}
+
+/// Blend the layer `b` onto the base `a`
+///
+/// Every sample is `a + (mode(a, b) - a) * opacity`, clamped to 0-1, so an
+/// opacity of 0 returns the base and 1 the full blend.
+///
+/// # Arguments
+///
+/// * `a` - The base field
+/// * `b` - The layer blended onto the base
+/// * `mode` - How the layer combines with the base
+/// * `opacity` - The strength of the blend, usually 0-1
+///
+/// # Returns
+///
+/// The blended field, or [`CellsError::DimensionMismatch`] when the fields
+/// differ in size
+///
+/// # Example
+///
+/// ```rust
+/// use cells::blend::{blend, BlendMode};
+/// use cells::FieldBuffer;
+///
+/// let a = FieldBuffer::from_vec(2, 1, vec![0.25, 0.75]);
+/// let b = FieldBuffer::from_vec(2, 1, vec![0.5, 0.5]);
+/// let expected = [
+///     (BlendMode::Add, [0.75, 1.0]),
+///     (BlendMode::Multiply, [0.125, 0.375]),
+///     (BlendMode::Screen, [0.625, 0.875]),
+///     (BlendMode::Overlay, [0.25, 0.75]),
+///     (BlendMode::Min, [0.25, 0.5]),
+///     (BlendMode::Max, [0.5, 0.75]),
+///     (BlendMode::Difference, [0.25, 0.25]),
+///     (BlendMode::Lerp, [0.5, 0.5]),
+/// ];
+/// for (mode, values) in expected {
+///     assert_eq!(blend(&a, &b, mode, 1.0).unwrap().as_slice(), values, "{mode}");
+/// }
+/// assert_eq!(blend(&a, &b, BlendMode::Multiply, 0.5).unwrap().as_slice(), [0.1875, 0.5625]);
+///
+/// let err = blend(&a, &FieldBuffer::new(1, 1), BlendMode::Add, 1.0).unwrap_err();
+/// assert_eq!(err.to_string(), "layer is 1x1 but the other inputs are 2x1");
+/// ```
+pub fn blend(a: &FieldBuffer, b: &FieldBuffer, mode: BlendMode, opacity: f32) -> Result<FieldBuffer, CellsError> {
+    if b.dimensions() != a.dimensions() {
+        return Err(CellsError::DimensionMismatch {
+            input: "layer",
+            expected: a.dimensions(),
+            found: b.dimensions(),
+        });
+    }
+    let (width, height) = a.dimensions();
+    let data = a
+        .as_slice()
+        .iter()
+        .zip(b.as_slice())
+        .map(|(&a, &b)| (a + (mode.combine(a, b) - a) * opacity).clamp(0.0, 1.0))
+        .collect();
+    Ok(FieldBuffer::from_vec(width, height, data))
+}
+
+/// Blend `a` and `b` per sample, using `mask` as the share of `b`
+///
+/// Every sample is `a * (1 - m) + b * m` with the mask value `m` clamped to
+/// 0-1, so the mask selects where each field shows, e.g. a thresholded
+/// large-scale noise choosing between cells and noise.
+///
+/// # Arguments
+///
+/// * `a` - The field shown where the mask is 0
+/// * `b` - The field shown where the mask is 1
+/// * `mask` - The per-sample share of `b`
+///
+/// # Returns
+///
+/// The blended field, or [`CellsError::DimensionMismatch`] when the fields
+/// differ in size
+///
+/// # Example
+///
+/// ```rust
+/// use cells::blend::blend_masked;
+/// use cells::FieldBuffer;
+///
+/// let a = FieldBuffer::from_vec(3, 1, vec![0.2, 0.2, 0.2]);
+/// let b = FieldBuffer::from_vec(3, 1, vec![1.0, 1.0, 1.0]);
+/// let mask = FieldBuffer::from_vec(3, 1, vec![0.0, 0.5, 2.0]);
+/// assert_eq!(blend_masked(&a, &b, &mask).unwrap().as_slice(), [0.2, 0.6, 1.0]);
+///
+/// let err = blend_masked(&a, &b, &FieldBuffer::new(3, 3)).unwrap_err();
+/// assert_eq!(err.to_string(), "mask is 3x3 but the other inputs are 3x1");
+/// ```
+pub fn blend_masked(a: &FieldBuffer, b: &FieldBuffer, mask: &FieldBuffer) -> Result<FieldBuffer, CellsError> {
+    for (input, field) in [("layer", b), ("mask", mask)] {
+        if field.dimensions() != a.dimensions() {
+            return Err(CellsError::DimensionMismatch {
+                input,
+                expected: a.dimensions(),
+                found: field.dimensions(),
+            });
+        }
+    }
+    let (width, height) = a.dimensions();
+    let data = a
+        .as_slice()
+        .iter()
+        .zip(b.as_slice())
+        .zip(mask.as_slice())
+        .map(|((&a, &b), &m)| {
+            let m = m.clamp(0.0, 1.0);
+            a * (1.0 - m) + b * m
+        })
+        .collect();
+    Ok(FieldBuffer::from_vec(width, height, data))
+}
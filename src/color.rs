@@ -0,0 +1,575 @@
+//! Colors of cell mosaics and of colormapped fields: palettes, random HSV
+//! ranges, gradients and the transfer functions samples are saved with.
+
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use image::{Rgb, RgbImage};
+use rand::Rng;
+
+use crate::error::CellsError;
+use crate::field::FieldBuffer;
+
+/// Parse a hex color such as `#c0ffee`, with or without the `#`
+///
+/// # Example
+///
+/// ```rust
+/// use cells::color::parse_hex_color;
+/// use image::Rgb;
+///
+/// assert_eq!(parse_hex_color("#c0ffee"), Ok(Rgb([0xc0, 0xff, 0xee])));
+/// assert_eq!(parse_hex_color("FF8800"), Ok(Rgb([0xff, 0x88, 0x00])));
+/// assert!(parse_hex_color("#c0ffe").is_err());
+/// ```
+pub fn parse_hex_color(s: &str) -> Result<Rgb<u8>, String> {
+    let digits = s.strip_prefix('#').unwrap_or(s);
+    let invalid = || format!("`{s}` is not a hex color such as #c0ffee");
+    if digits.len() != 6 || !digits.is_ascii() {
+        return Err(invalid());
+    }
+    let channel = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).map_err(|_| invalid());
+    Ok(Rgb([channel(0)?, channel(2)?, channel(4)?]))
+}
+
+/// A list of colors the cells of a mosaic pick from
+///
+/// # Example
+///
+/// ```rust
+/// use cells::color::Palette;
+/// use cells::CellsError;
+///
+/// let palette = Palette::parse("#335c67\n\n  fff3b0\n#e09f3e\n").unwrap();
+/// assert_eq!(palette.colors().len(), 3);
+/// let err = Palette::parse("#335c67\n#fff3b\n").unwrap_err();
+/// assert!(matches!(err, CellsError::InvalidPalette { line: 2, .. }));
+/// assert_eq!(err.to_string(), "line 2 of the palette: `#fff3b` is not a hex color such as #c0ffee");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Palette {
+    colors: Vec<Rgb<u8>>,
+}
+
+impl Palette {
+    /// A palette of `colors`
+    ///
+    /// # Returns
+    ///
+    /// The palette, or [`CellsError::EmptyInput`] without colors
+    pub fn new(colors: Vec<Rgb<u8>>) -> Result<Palette, CellsError> {
+        if colors.is_empty() {
+            return Err(CellsError::EmptyInput { input: "palette" });
+        }
+        Ok(Palette { colors })
+    }
+
+    /// Parse a palette of one hex color per line, see [`parse_hex_color`];
+    /// blank lines are skipped
+    ///
+    /// # Returns
+    ///
+    /// The palette, [`CellsError::InvalidPalette`] with the number of the
+    /// first line that is not a color, counted from 1, or
+    /// [`CellsError::EmptyInput`] without colors
+    pub fn parse(text: &str) -> Result<Palette, CellsError> {
+        let colors = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                parse_hex_color(line.trim()).map_err(|reason| CellsError::InvalidPalette { line: i + 1, reason })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Palette::new(colors)
+    }
+
+    /// The colors of the middle row of `strip`, such as a gradient exported
+    /// as a small PNG, from left to right
+    ///
+    /// # Returns
+    ///
+    /// The palette, or [`CellsError::EmptyInput`] for an empty image
+    pub fn from_image(strip: &RgbImage) -> Result<Palette, CellsError> {
+        if strip.width() == 0 || strip.height() == 0 {
+            return Err(CellsError::EmptyInput { input: "palette image" });
+        }
+        let row = strip.height() / 2;
+        Palette::new((0..strip.width()).map(|x| *strip.get_pixel(x, row)).collect())
+    }
+
+    /// The colors, at least one
+    pub fn colors(&self) -> &[Rgb<u8>] {
+        &self.colors
+    }
+}
+
+/// Ranges random HSV colors are drawn from, see [`CellColoring::RandomHsv`]
+///
+/// A hue range whose start is above its end wraps around red, so `330..30`
+/// spans the reds and pinks.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::color::HsvRanges;
+///
+/// let ranges: HsvRanges = "h=0..360,s=0.3..0.6,v=0.7..0.9".parse().unwrap();
+/// assert_eq!(ranges.saturation, 0.3..=0.6);
+/// // Omitted ranges keep their defaults
+/// let reds: HsvRanges = "h=330..30".parse().unwrap();
+/// assert_eq!(reds.value, HsvRanges::default().value);
+/// assert!("s=0.5..1.5".parse::<HsvRanges>().is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct HsvRanges {
+    /// The hues in degrees, from 0 to 360
+    pub hue: RangeInclusive<f32>,
+    /// The saturations, from 0 to 1
+    pub saturation: RangeInclusive<f32>,
+    /// The values, from 0 to 1
+    pub value: RangeInclusive<f32>,
+}
+
+impl Default for HsvRanges {
+    fn default() -> Self {
+        HsvRanges { hue: 0.0..=360.0, saturation: 0.3..=0.7, value: 0.6..=0.9 }
+    }
+}
+
+impl HsvRanges {
+    /// Check that the hues lie in 0-360 and the saturations and values in
+    /// 0-1, with the saturation and value ranges not decreasing
+    pub fn validate(&self) -> Result<(), CellsError> {
+        let invalid = |name, reason: String| Err(CellsError::InvalidParameter { name, reason });
+        let within = |range: &RangeInclusive<f32>, max| {
+            [range.start(), range.end()].iter().all(|bound| (0.0..=max).contains(*bound))
+        };
+        if !within(&self.hue, 360.0) {
+            return invalid("hue", format!("must lie in 0..360, got {}..{}", self.hue.start(), self.hue.end()));
+        }
+        for (name, range) in [("saturation", &self.saturation), ("value", &self.value)] {
+            if !(within(range, 1.0) && range.start() <= range.end()) {
+                return invalid(name, format!("must be a range in 0..1, got {}..{}", range.start(), range.end()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Draw a color from the ranges
+    pub fn sample(&self, rng: &mut impl Rng) -> Rgb<u8> {
+        let (start, end) = (*self.hue.start(), *self.hue.end());
+        let end = if start > end { end + 360.0 } else { end };
+        let hue = rng.gen_range(start..=end) % 360.0;
+        let saturation = rng.gen_range(self.saturation.clone());
+        let value = rng.gen_range(self.value.clone());
+        hsv_to_rgb(hue, saturation, value)
+    }
+}
+
+impl fmt::Display for HsvRanges {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let range = |range: &RangeInclusive<f32>| format!("{}..{}", range.start(), range.end());
+        write!(f, "h={},s={},v={}", range(&self.hue), range(&self.saturation), range(&self.value))
+    }
+}
+
+impl FromStr for HsvRanges {
+    type Err = String;
+
+    /// Parse `h=MIN..MAX,s=MIN..MAX,v=MIN..MAX` such as
+    /// `h=0..360,s=0.3..0.6,v=0.7..0.9`, in any order and each optional
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut ranges = HsvRanges::default();
+        for part in s.split(',') {
+            let invalid = || format!("expected h=MIN..MAX, s=MIN..MAX or v=MIN..MAX such as s=0.3..0.6, got `{part}`");
+            let (key, range) = part.trim().split_once('=').ok_or_else(invalid)?;
+            let (min, max) = range.split_once("..").ok_or_else(invalid)?;
+            let range = min.trim().parse().map_err(|_| invalid())?..=max.trim().parse().map_err(|_| invalid())?;
+            match key.trim() {
+                "h" => ranges.hue = range,
+                "s" => ranges.saturation = range,
+                "v" => ranges.value = range,
+                _ => return Err(invalid()),
+            }
+        }
+        ranges.validate().map_err(|err| err.to_string())?;
+        Ok(ranges)
+    }
+}
+
+/// Convert a hue in degrees and a saturation and value from 0 to 1 to an
+/// 8-bit RGB color
+///
+/// # Example
+///
+/// ```rust
+/// use cells::color::hsv_to_rgb;
+/// use image::Rgb;
+///
+/// assert_eq!(hsv_to_rgb(0.0, 1.0, 1.0), Rgb([255, 0, 0]));
+/// assert_eq!(hsv_to_rgb(120.0, 1.0, 0.5), Rgb([0, 128, 0]));
+/// assert_eq!(hsv_to_rgb(200.0, 0.0, 1.0), Rgb([255, 255, 255]));
+/// ```
+pub fn hsv_to_rgb(hue: f32, saturation: f32, value: f32) -> Rgb<u8> {
+    let sector = (hue.rem_euclid(360.0)) / 60.0;
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    let quantize = |c: f32| ((c + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+    Rgb([quantize(r), quantize(g), quantize(b)])
+}
+
+/// How the cells of [`crate::voronoi::generate_colored_cells`] are colored
+#[derive(Clone, Debug, PartialEq)]
+pub enum CellColoring {
+    /// Every cell picks a color of the palette at random
+    Palette(Palette),
+    /// Every cell gets a random color from the HSV ranges
+    RandomHsv(HsvRanges),
+}
+
+impl CellColoring {
+    /// Check the HSV ranges; palettes are valid once built
+    pub fn validate(&self) -> Result<(), CellsError> {
+        match self {
+            CellColoring::Palette(_) => Ok(()),
+            CellColoring::RandomHsv(ranges) => ranges.validate(),
+        }
+    }
+
+    /// Draw the color of one cell
+    pub fn sample(&self, rng: &mut impl Rng) -> Rgb<u8> {
+        match self {
+            CellColoring::Palette(palette) => palette.colors[rng.gen_range(0..palette.colors.len())],
+            CellColoring::RandomHsv(ranges) => ranges.sample(rng),
+        }
+    }
+}
+
+/// Decode an sRGB-encoded channel from 0 to 1 to linear light
+///
+/// # Example
+///
+/// ```rust
+/// use cells::color::{linear_to_srgb, srgb_to_linear};
+///
+/// assert!((srgb_to_linear(0.5) - 0.214).abs() < 1e-3);
+/// assert!((linear_to_srgb(srgb_to_linear(0.3)) - 0.3).abs() < 1e-6);
+/// ```
+pub fn srgb_to_linear(encoded: f32) -> f32 {
+    if encoded <= 0.04045 {
+        encoded / 12.92
+    } else {
+        ((encoded + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encode a linear channel from 0 to 1 with the sRGB transfer function,
+/// the inverse of [`srgb_to_linear`]
+pub fn linear_to_srgb(linear: f32) -> f32 {
+    if linear <= 0.003_130_8 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// The transfer function samples are encoded with when they are quantized
+///
+/// Fields hold linear values and every filter works on them as they are; the
+/// transfer function only applies when a field is saved into integer samples.
+/// Heights, masks and other data maps stay linear, while textures meant to be
+/// looked at can be encoded to sRGB or a plain gamma, which spends more of the
+/// integer steps on the dark values.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::color::TransferFunction;
+///
+/// assert_eq!(TransferFunction::Linear.encode(0.5), 0.5);
+/// assert_eq!((TransferFunction::Srgb.encode(0.5) * 255.0).round(), 188.0);
+/// let gamma: TransferFunction = "gamma:2.2".parse().unwrap();
+/// assert_eq!(gamma, TransferFunction::Gamma(2.2));
+/// assert!((gamma.encode(0.5) - 0.5f32.powf(1.0 / 2.2)).abs() < 1e-6);
+/// assert!("gamma:0".parse::<TransferFunction>().is_err());
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TransferFunction {
+    /// Samples are quantized as they are
+    #[default]
+    Linear,
+    /// The piecewise sRGB curve of [`linear_to_srgb`]
+    Srgb,
+    /// A pure power curve: samples are raised to `1 / gamma`
+    Gamma(f32),
+}
+
+impl TransferFunction {
+    /// Encode a linear sample from 0 to 1
+    pub fn encode(self, linear: f32) -> f32 {
+        match self {
+            TransferFunction::Linear => linear,
+            TransferFunction::Srgb => linear_to_srgb(linear),
+            TransferFunction::Gamma(gamma) => linear.powf(1.0 / gamma),
+        }
+    }
+
+    /// Clamp the samples of a field to 0-1 and encode them, ready to be
+    /// quantized
+    pub fn encode_field(self, field: &FieldBuffer) -> FieldBuffer {
+        field.map(|value| self.encode(value.clamp(0.0, 1.0)))
+    }
+
+    /// Check that the gamma of [`TransferFunction::Gamma`] is a finite
+    /// number > 0
+    pub fn validate(self) -> Result<(), CellsError> {
+        match self {
+            TransferFunction::Gamma(gamma) if !(gamma > 0.0 && gamma.is_finite()) => {
+                Err(CellsError::InvalidParameter {
+                    name: "transfer",
+                    reason: format!("gamma must be a finite number > 0, got {gamma}"),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for TransferFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransferFunction::Linear => f.write_str("linear"),
+            TransferFunction::Srgb => f.write_str("srgb"),
+            TransferFunction::Gamma(gamma) => write!(f, "gamma:{gamma}"),
+        }
+    }
+}
+
+impl FromStr for TransferFunction {
+    type Err = String;
+
+    /// Parse `linear`, `srgb` or `gamma:<gamma>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "linear" => Ok(TransferFunction::Linear),
+            "srgb" => Ok(TransferFunction::Srgb),
+            _ => {
+                let gamma = s
+                    .strip_prefix("gamma:")
+                    .ok_or_else(|| format!("unknown transfer function `{s}`, expected linear, srgb or gamma:<gamma>"))?
+                    .parse::<f32>()
+                    .map_err(|err| format!("invalid gamma in `{s}`: {err}"))?;
+                let transfer = TransferFunction::Gamma(gamma);
+                transfer.validate().map_err(|err| err.to_string())?;
+                Ok(transfer)
+            }
+        }
+    }
+}
+
+serde_via_str!(TransferFunction);
+
+/// Colors at positions from 0 to 1 that [`colormap`] interpolates between
+///
+/// The stops are sRGB colors, interpolated in linear light and encoded back
+/// to sRGB, so the blends between stops keep their brightness. Positions
+/// before the first stop take its color and positions after the last one
+/// the color of the last.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::color::Gradient;
+/// use image::Rgb;
+///
+/// let gradient: Gradient = "0.0:#000000,0.5:#ff8800,1.0:#ffffff".parse().unwrap();
+/// assert_eq!(gradient.sample(0.5), Rgb([0xff, 0x88, 0x00]));
+/// assert_eq!(gradient.sample(-1.0), Rgb([0, 0, 0]));
+/// // Halfway between black and white in linear light is brighter than 128
+/// assert_eq!("0:#000000,1:#ffffff".parse::<Gradient>().unwrap().sample(0.5), Rgb([188, 188, 188]));
+/// assert!("0.5:#000000,0.2:#ffffff".parse::<Gradient>().is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    stops: Vec<(f32, Rgb<u8>)>,
+}
+
+impl Gradient {
+    /// A gradient through `stops` of a position and an sRGB color
+    ///
+    /// # Returns
+    ///
+    /// The gradient, [`CellsError::EmptyInput`] without stops, or
+    /// [`CellsError::InvalidParameter`] for positions that are not finite or
+    /// decrease
+    pub fn new(stops: Vec<(f32, Rgb<u8>)>) -> Result<Gradient, CellsError> {
+        if stops.is_empty() {
+            return Err(CellsError::EmptyInput { input: "gradient" });
+        }
+        let positions = || stops.iter().map(|&(position, _)| position);
+        if let Some(position) = positions().find(|position| !position.is_finite()) {
+            return Err(CellsError::InvalidParameter {
+                name: "gradient",
+                reason: format!("stop positions must be finite, got {position}"),
+            });
+        }
+        if let Some((before, after)) = positions().zip(positions().skip(1)).find(|(before, after)| after < before) {
+            return Err(CellsError::InvalidParameter {
+                name: "gradient",
+                reason: format!("stop positions must not decrease, got {after} after {before}"),
+            });
+        }
+        Ok(Gradient { stops })
+    }
+
+    /// The stops of a position and an sRGB color, in order
+    pub fn stops(&self) -> &[(f32, Rgb<u8>)] {
+        &self.stops
+    }
+
+    /// The color at position `t`
+    pub fn sample(&self, t: f32) -> Rgb<u8> {
+        let after = self.stops.partition_point(|&(position, _)| position <= t);
+        let (start, end) = match after {
+            0 => return self.stops[0].1,
+            n if n == self.stops.len() => return self.stops[n - 1].1,
+            n => (self.stops[n - 1], self.stops[n]),
+        };
+        let mix = (t - start.0) / (end.0 - start.0);
+        Rgb(std::array::from_fn(|c| {
+            let (from, to) = (srgb_to_linear(start.1[c] as f32 / 255.0), srgb_to_linear(end.1[c] as f32 / 255.0));
+            (linear_to_srgb(from + (to - from) * mix) * 255.0).round().clamp(0.0, 255.0) as u8
+        }))
+    }
+}
+
+impl fmt::Display for Gradient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (position, Rgb([r, g, b]))) in self.stops.iter().enumerate() {
+            let separator = if i == 0 { "" } else { "," };
+            write!(f, "{separator}{position}:#{r:02x}{g:02x}{b:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Gradient {
+    type Err = String;
+
+    /// Parse `POSITION:COLOR` stops separated by commas, such as
+    /// `0.0:#000000,0.5:#ff8800,1.0:#ffffff`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let stops = s
+            .split(',')
+            .map(|stop| {
+                let invalid = || format!("expected POSITION:COLOR stops such as 0.5:#ff8800, got `{stop}`");
+                let (position, color) = stop.split_once(':').ok_or_else(invalid)?;
+                Ok((position.trim().parse().map_err(|_| invalid())?, parse_hex_color(color.trim())?))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Gradient::new(stops).map_err(|err| err.to_string())
+    }
+}
+
+/// A named or custom gradient mapping field values to colors, see
+/// [`colormap`]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Colormap {
+    /// Matplotlib's perceptually uniform dark blue to yellow
+    #[default]
+    Viridis,
+    /// Matplotlib's perceptually uniform black to pale yellow through purple
+    Magma,
+    /// Black to white
+    Grayscale,
+    /// Custom stops
+    Custom(Gradient),
+}
+
+impl Colormap {
+    /// The stops of the map
+    pub fn gradient(&self) -> Gradient {
+        let evenly = |colors: &[u32]| {
+            let last = (colors.len() - 1) as f32;
+            let stops = colors.iter().enumerate().map(|(i, &hex)| {
+                let [_, r, g, b] = hex.to_be_bytes();
+                (i as f32 / last, Rgb([r, g, b]))
+            });
+            Gradient { stops: stops.collect() }
+        };
+        match self {
+            Colormap::Viridis => evenly(&VIRIDIS),
+            Colormap::Magma => evenly(&MAGMA),
+            Colormap::Grayscale => evenly(&[0x000000, 0xffffff]),
+            Colormap::Custom(gradient) => gradient.clone(),
+        }
+    }
+}
+
+/// Viridis at nine even positions
+const VIRIDIS: [u32; 9] = [0x440154, 0x472c7a, 0x3b518b, 0x2c718e, 0x21908d, 0x27ad81, 0x5cc863, 0xaadc32, 0xfde725];
+
+/// Magma at nine even positions
+const MAGMA: [u32; 9] = [0x000004, 0x1c1044, 0x4f127b, 0x812581, 0xb5367a, 0xe55964, 0xfb8761, 0xfec287, 0xfcfdbf];
+
+impl fmt::Display for Colormap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Colormap::Viridis => f.write_str("viridis"),
+            Colormap::Magma => f.write_str("magma"),
+            Colormap::Grayscale => f.write_str("grayscale"),
+            Colormap::Custom(gradient) => gradient.fmt(f),
+        }
+    }
+}
+
+impl FromStr for Colormap {
+    type Err = String;
+
+    /// Parse `viridis`, `magma`, `grayscale` or the stops of a [`Gradient`]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "viridis" => Ok(Colormap::Viridis),
+            "magma" => Ok(Colormap::Magma),
+            "grayscale" => Ok(Colormap::Grayscale),
+            _ if s.contains(':') => Ok(Colormap::Custom(s.parse()?)),
+            _ => Err(format!(
+                "unknown colormap `{s}`, expected viridis, magma, grayscale or stops such as 0:#000000,1:#ffffff"
+            )),
+        }
+    }
+}
+
+serde_via_str!(Colormap);
+
+/// Map the values of a field through a gradient into an RGB image
+///
+/// The values are clamped to 0-1, so normalized fields use the whole map.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::color::{colormap, Colormap};
+/// use cells::FieldBuffer;
+/// use image::Rgb;
+///
+/// let ramp = FieldBuffer::from_par_fn(3, 1, |x, _| x as f32 / 2.0);
+/// let image = colormap(&ramp, &Colormap::Viridis);
+/// assert_eq!(*image.get_pixel(0, 0), Rgb([0x44, 0x01, 0x54]));
+/// assert_eq!(*image.get_pixel(2, 0), Rgb([0xfd, 0xe7, 0x25]));
+/// ```
+pub fn colormap(field: &FieldBuffer, map: &Colormap) -> RgbImage {
+    let gradient = map.gradient();
+    RgbImage::from_par_fn(field.width(), field.height(), |x, y| gradient.sample(field.get(x, y).clamp(0.0, 1.0)))
+}
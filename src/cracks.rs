@@ -0,0 +1,162 @@
+//! Crack patterns along the borders of Voronoi cells.
+//!
+//! Cracks are drawn where the F2 − F1 feature is small, so they follow the
+//! cell borders. A large-scale noise field thins them out in places, and
+//! whole borders can be left out: the decision is keyed on the pair of cells
+//! meeting at a pixel, so it holds along the entire border of two cells.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::error::CellsError;
+use crate::field::FieldBuffer;
+use crate::filter::normalize_field;
+use crate::geometry::{DistanceMetric, Point};
+use crate::grid::PointGrid;
+use crate::noise::{generate_perlin_field, FbmParams};
+use crate::sampling::{lloyd_relax, PointDistribution};
+
+/// Parameters of a crack mask
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CrackParams {
+    /// Width and height of the texture in pixels
+    pub size: u32,
+    /// Number of cells the cracks surround
+    pub num_points: usize,
+    /// Lloyd relaxation iterations evening out the cells, 0 to disable
+    pub relax_iterations: u32,
+    /// Width of the cracks as a fraction of the texture size
+    pub width: f32,
+    /// Frequency of the noise thinning the cracks, in cycles across the
+    /// texture
+    pub fade_scale: f64,
+    /// How far the noise thins the cracks, from 0 (even width) to 1 (fading
+    /// out entirely where the noise is lowest)
+    pub fade: f32,
+    /// Probability of leaving out the border between two cells, from 0 to 1
+    pub removal: f32,
+    /// Seed of the cells, the fade noise and the removed borders
+    pub seed: u64,
+}
+
+impl Default for CrackParams {
+    fn default() -> Self {
+        CrackParams {
+            size: 512,
+            num_points: 60,
+            relax_iterations: 2,
+            width: 0.006,
+            fade_scale: 3.0,
+            fade: 0.7,
+            removal: 0.2,
+            seed: 0,
+        }
+    }
+}
+
+impl CrackParams {
+    /// Check that there are cells and the cracks have a valid width and fade
+    ///
+    /// # Returns
+    ///
+    /// [`CellsError::InvalidParameter`] if the size or point count is 0, the
+    /// width or fade scale is not positive and finite, or the fade or removal
+    /// is outside 0-1
+    pub fn validate(&self) -> Result<(), CellsError> {
+        let invalid = |name, reason: String| Err(CellsError::InvalidParameter { name, reason });
+        if self.size == 0 {
+            return invalid("size", "must be above 0".to_string());
+        }
+        if self.num_points == 0 {
+            return invalid("num_points", "must be above 0".to_string());
+        }
+        if !(self.width.is_finite() && self.width > 0.0) {
+            return invalid("width", format!("must be positive and finite, got {}", self.width));
+        }
+        if !(self.fade_scale.is_finite() && self.fade_scale > 0.0) {
+            return invalid("fade_scale", format!("must be positive and finite, got {}", self.fade_scale));
+        }
+        for (name, value) in [("fade", self.fade), ("removal", self.removal)] {
+            if !(0.0..=1.0).contains(&value) {
+                return invalid(name, format!("must be between 0 and 1, got {value}"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Generate a tileable crack mask
+///
+/// Cracks are 1 and the intact surface 0, with anti-aliased edges. A crack
+/// covers the pixels whose F2 − F1 feature is below its local width, which
+/// the fade noise scales between `1 - fade` and 1 times `params.width`.
+/// Every border between two cells is left out with probability
+/// `params.removal`, so some cells merge into larger fragments.
+///
+/// # Arguments
+///
+/// * `params` - The cells and the look of the cracks
+///
+/// # Returns
+///
+/// A `FieldBuffer` with values from 0 to 1
+///
+/// # Panics
+///
+/// Panics if the parameters fail [`CrackParams::validate`].
+///
+/// # Example
+///
+/// ```rust
+/// use cells::cracks::{generate_cracks, CrackParams};
+/// use cells::tiling::{assert_tileable, interior_discontinuity};
+///
+/// let params = CrackParams { size: 128, num_points: 12, ..Default::default() };
+/// let cracks = generate_cracks(&params);
+/// assert_tileable(&cracks, interior_discontinuity(&cracks));
+/// // With every border removed the surface is intact
+/// assert!(generate_cracks(&CrackParams { removal: 1.0, ..params }).as_slice().iter().all(|&v| v == 0.0));
+/// ```
+pub fn generate_cracks(params: &CrackParams) -> FieldBuffer {
+    if let Err(err) = params.validate() {
+        panic!("{err}");
+    }
+    let mut rng = StdRng::seed_from_u64(params.seed);
+    let points = PointDistribution::Uniform.generate(params.num_points, &mut rng);
+    let points = lloyd_relax(&points, params.relax_iterations, DistanceMetric::Euclidean);
+    let grid = PointGrid::new(&points);
+    let fade_noise = FbmParams {
+        frequency: params.fade_scale,
+        seed: rng.gen(),
+        ..Default::default()
+    };
+    let fade = normalize_field(&generate_perlin_field(params.size, &fade_noise));
+    let edge_key: u64 = rng.gen();
+
+    let size = params.size as f32;
+    FieldBuffer::from_par_fn(params.size, params.size, |x, y| {
+        let p = Point { x: x as f32 / size, y: y as f32 / size };
+        let Some([(a, f1), (b, f2)]) = grid.nearest_two(p) else {
+            // A single cell has no borders
+            return 0.0;
+        };
+        if edge_hash(a, b, edge_key) < params.removal {
+            return 0.0;
+        }
+        let width = params.width * (1.0 - params.fade * (1.0 - fade.get(x, y)));
+        // F2 - F1 grows about twice as fast as the distance to the border
+        let distance = (f2 - f1) / 2.0;
+        ((width / 2.0 - distance) * size + 0.5).clamp(0.0, 1.0)
+    })
+}
+
+/// A uniform value in 0-1 for the border between cells `a` and `b`, the same
+/// whichever of them is nearer (SplitMix64 finalizer)
+fn edge_hash(a: usize, b: usize, key: u64) -> f32 {
+    let (low, high) = (a.min(b) as u64, a.max(b) as u64);
+    let mut h = (low << 32 | high) ^ key;
+    h = (h ^ (h >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    h ^= h >> 31;
+    (h >> 40) as f32 / (1u64 << 24) as f32
+}
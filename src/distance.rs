@@ -0,0 +1,122 @@
+//! Distance transforms turning masks back into smooth falloff fields.
+//!
+//! Distances are measured across the wrap-around edges, so the distances of
+//! a tileable mask tile as well. The transform is exact and runs in linear
+//! time: a pass down every column and one along every row, each taking the
+//! lower envelope of parabolas over three copies of the line (Felzenszwalb &
+//! Huttenlocher).
+
+use rayon::prelude::*;
+
+use crate::field::FieldBuffer;
+
+/// Samples at or above this value are on
+const ON: f32 = 0.5;
+
+/// The Euclidean distance in pixels from every pixel to the nearest pixel
+/// that is on, with wraparound
+///
+/// Pixels at or above 0.5 are on and get 0; distances are measured between
+/// pixel centers. Divide by a radius and clamp to turn the distances into a
+/// falloff, e.g. for wear along the edges of a mask.
+///
+/// # Arguments
+///
+/// * `mask` - The mask, e.g. a thresholded crack or cell texture
+///
+/// # Returns
+///
+/// A `FieldBuffer` of distances in pixels, infinite everywhere if no pixel
+/// is on
+///
+/// # Example
+///
+/// ```rust
+/// use cells::distance::distance_transform;
+/// use cells::FieldBuffer;
+///
+/// let dot = FieldBuffer::from_par_fn(8, 8, |x, y| if (x, y) == (1, 1) { 1.0 } else { 0.0 });
+/// let distances = distance_transform(&dot);
+/// assert_eq!(distances.get(1, 1), 0.0);
+/// assert_eq!(distances.get(4, 5), 5.0);
+/// // Across the edges the dot is close
+/// assert_eq!(distances.get(7, 7), 8f32.sqrt());
+/// ```
+pub fn distance_transform(mask: &FieldBuffer) -> FieldBuffer {
+    let (width, height) = mask.dimensions();
+    let (width, height) = (width as usize, height as usize);
+    if width == 0 || height == 0 {
+        return mask.clone();
+    }
+
+    // Squared distances to the nearest on pixel in the same column, stored
+    // column by column
+    let columns: Vec<Vec<f32>> = (0..width)
+        .into_par_iter()
+        .map(|x| {
+            let on: Vec<f32> = (0..height)
+                .map(|y| if mask.as_slice()[y * width + x] >= ON { 0.0 } else { f32::INFINITY })
+                .collect();
+            let mut squared = vec![0.0; height];
+            wrapped_squared_distances(&on, &mut squared);
+            squared
+        })
+        .collect();
+
+    let mut values = vec![0.0; width * height];
+    values.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+        let column_distances: Vec<f32> = columns.iter().map(|column| column[y]).collect();
+        wrapped_squared_distances(&column_distances, row);
+        for value in row.iter_mut() {
+            *value = value.sqrt();
+        }
+    });
+    FieldBuffer::from_vec(width as u32, height as u32, values)
+}
+
+/// The smallest `(x - q)² + f[q]` over all samples `q` of the periodic line
+/// `f`, for every `x`, where `x - q` is the shortest offset around the line
+///
+/// Infinite samples are never nearest; if all are infinite, so is `out`.
+fn wrapped_squared_distances(f: &[f32], out: &mut [f32]) {
+    let n = f.len() as i64;
+    let value = |q: i64| f[q.rem_euclid(n) as usize] as f64;
+    // The parabolas of the lower envelope, rooted at positions in -n..2n so
+    // every sample is also seen from one line length either way, and the
+    // positions where each starts being lowest
+    let mut roots: Vec<i64> = Vec::new();
+    let mut starts: Vec<f64> = Vec::new();
+    for q in -n..2 * n {
+        if !value(q).is_finite() {
+            continue;
+        }
+        while let Some(&p) = roots.last() {
+            let crossing = ((value(q) + (q * q) as f64) - (value(p) + (p * p) as f64)) / (2 * (q - p)) as f64;
+            if crossing > *starts.last().unwrap() {
+                roots.push(q);
+                starts.push(crossing);
+                break;
+            }
+            roots.pop();
+            starts.pop();
+        }
+        if roots.is_empty() {
+            roots.push(q);
+            starts.push(f64::NEG_INFINITY);
+        }
+    }
+    if roots.is_empty() {
+        out.fill(f32::INFINITY);
+        return;
+    }
+
+    let mut k = 0;
+    for (x, out) in out.iter_mut().enumerate() {
+        let x = x as f64;
+        while k + 1 < roots.len() && starts[k + 1] < x {
+            k += 1;
+        }
+        let offset = x - roots[k] as f64;
+        *out = (offset * offset + value(roots[k])) as f32;
+    }
+}
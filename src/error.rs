@@ -0,0 +1,79 @@
+//! Errors reported by the texture operations.
+
+use std::io;
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+/// An error from combining or validating textures, their parameters and pipelines
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum CellsError {
+    /// Inputs that must share their dimensions do not
+    #[error("{input} is {}x{} but the other inputs are {}x{}", found.0, found.1, expected.0, expected.1)]
+    DimensionMismatch {
+        /// Name of the offending input
+        input: &'static str,
+        /// The `(width, height)` of the first input
+        expected: (u32, u32),
+        /// The `(width, height)` of the offending input
+        found: (u32, u32),
+    },
+    /// A generator parameter is outside the supported range
+    #[error("invalid {name}: {reason}")]
+    InvalidParameter {
+        /// Name of the parameter
+        name: &'static str,
+        /// Why the value is rejected
+        reason: String,
+    },
+    /// A pipeline description or one of its nodes is invalid
+    #[error("{}", match node {
+        Some(node) => format!("node `{node}`: {reason}"),
+        None => format!("invalid pipeline: {reason}"),
+    })]
+    InvalidPipeline {
+        /// Name of the offending node, if the error concerns one
+        node: Option<String>,
+        /// What is wrong
+        reason: String,
+    },
+    /// An input has no samples, so there is nothing to work on
+    #[error("{input} is empty")]
+    EmptyInput {
+        /// Name of the empty input
+        input: &'static str,
+    },
+    /// A line of a palette is not a color
+    #[error("line {line} of the palette: {reason}")]
+    InvalidPalette {
+        /// The number of the line, counted from 1
+        line: usize,
+        /// What is wrong with it
+        reason: String,
+    },
+    /// A file cannot be written
+    #[error("cannot write {}: {reason}", path.display())]
+    Io {
+        /// The file
+        path: PathBuf,
+        /// The kind of the failure, such as [`io::ErrorKind::NotFound`]
+        kind: io::ErrorKind,
+        /// What the operating system reported
+        reason: String,
+    },
+    /// A texture cannot be encoded in its file format
+    #[error("cannot encode {}: {reason}", path.display())]
+    Encode {
+        /// The file the texture was encoded for
+        path: PathBuf,
+        /// What the encoder reported
+        reason: String,
+    },
+}
+
+impl CellsError {
+    /// The [`CellsError::Io`] of a failure to write `path`
+    pub fn io(path: impl Into<PathBuf>, err: &io::Error) -> CellsError {
+        CellsError::Io { path: path.into(), kind: err.kind(), reason: err.to_string() }
+    }
+}
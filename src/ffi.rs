@@ -0,0 +1,340 @@
+//! A C interface to the generators, for calling them from other languages.
+//!
+//! The generators take a plain-C parameter struct and the filters a buffer of
+//! samples; both write their texture into a buffer allocated by the crate and
+//! return a [`CellsStatus`]. The samples of a texture are `f32` values in row
+//! order, `size * size` of them for the generators, and the buffer is released
+//! with [`cells_free`]. Start from the defaults of
+//! [`cells_voronoi_default_params`] and [`cells_fbm_default_params`] so new
+//! fields keep working when the structs grow.
+//!
+//! `include/cells.h` declares the interface for C and C++; regenerate it with
+//! `cbindgen --config cbindgen.toml --output include/cells.h` after changing
+//! this module. `cargo build --release --lib --no-default-features` builds
+//! the library as `libcells.so`, `cells.dll` or `libcells.dylib`.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cells::ffi::{cells_free, cells_voronoi, cells_voronoi_default_params, CellsStatus};
+//!
+//! let mut params = cells_voronoi_default_params();
+//! params.size = 32;
+//! let (mut buffer, mut len) = (std::ptr::null_mut(), 0);
+//! let status = unsafe { cells_voronoi(&params, &mut buffer, &mut len) };
+//! assert_eq!(status, CellsStatus::Ok);
+//! assert_eq!(len, 32 * 32);
+//! unsafe { cells_free(buffer, len) };
+//! ```
+
+use std::ffi::{c_char, CStr};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use crate::field::FieldBuffer;
+use crate::filter::{directional_blur_field, gaussian_blur};
+use crate::geometry::DistanceMetric;
+use crate::noise::{generate_perlin_field, FbmParams};
+use crate::voronoi::{generate_voronoi_field, Feature, VoronoiParams};
+
+/// [`Feature::F1`], the distance to the nearest point
+pub const CELLS_FEATURE_F1: u32 = 0;
+/// [`Feature::F2`], the distance to the second-nearest point
+pub const CELLS_FEATURE_F2: u32 = 1;
+/// [`Feature::F2MinusF1`], zero on the cell boundaries
+pub const CELLS_FEATURE_F2_MINUS_F1: u32 = 2;
+/// [`Feature::CellValue`], a random value per cell
+pub const CELLS_FEATURE_CELL_VALUE: u32 = 3;
+
+/// [`DistanceMetric::Euclidean`]
+pub const CELLS_METRIC_EUCLIDEAN: u32 = 0;
+/// [`DistanceMetric::Manhattan`]
+pub const CELLS_METRIC_MANHATTAN: u32 = 1;
+/// [`DistanceMetric::Chebyshev`]
+pub const CELLS_METRIC_CHEBYSHEV: u32 = 2;
+/// [`DistanceMetric::Minkowski`] with the exponent `minkowski_p`
+pub const CELLS_METRIC_MINKOWSKI: u32 = 3;
+
+/// The outcome of a call, `CELLS_STATUS_OK` on success
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CellsStatus {
+    /// The texture was generated
+    Ok = 0,
+    /// A required pointer argument was null
+    NullPointer = 1,
+    /// A parameter is outside its supported range
+    InvalidParameter = 2,
+    /// The generator panicked; no buffer was allocated
+    Panic = 3,
+}
+
+/// The parameters of [`cells_voronoi`]
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CellsVoronoiParams {
+    /// The width and height of the texture in pixels
+    pub size: u32,
+    /// The number of Voronoi cells
+    pub num_points: u32,
+    /// The seed of the point positions
+    pub seed: u64,
+    /// One of the `CELLS_FEATURE_*` constants
+    pub feature: u32,
+    /// One of the `CELLS_METRIC_*` constants
+    pub metric: u32,
+    /// The exponent of `CELLS_METRIC_MINKOWSKI`, at least 1
+    pub minkowski_p: f32,
+    /// Whether to invert the texture, making the cell centers bright
+    pub invert: bool,
+}
+
+/// The parameters of [`cells_fbm`], see [`FbmParams`]
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CellsFbmParams {
+    /// The width and height of the texture in pixels
+    pub size: u32,
+    /// The number of noise layers summed
+    pub octaves: u32,
+    /// The amplitude factor from one octave to the next
+    pub persistence: f64,
+    /// The frequency factor from one octave to the next
+    pub lacunarity: f64,
+    /// The number of noise features across the texture in the first octave
+    pub frequency: f64,
+    /// The seed of the noise permutation table
+    pub seed: u64,
+}
+
+/// The default parameters of [`cells_voronoi`], those of [`VoronoiParams`]
+#[no_mangle]
+pub extern "C" fn cells_voronoi_default_params() -> CellsVoronoiParams {
+    let defaults = VoronoiParams::default();
+    CellsVoronoiParams {
+        size: defaults.size,
+        num_points: defaults.num_points as u32,
+        seed: defaults.seed,
+        feature: CELLS_FEATURE_F1,
+        metric: CELLS_METRIC_EUCLIDEAN,
+        minkowski_p: 2.0,
+        invert: defaults.invert,
+    }
+}
+
+/// The default parameters of [`cells_fbm`], those of [`FbmParams`] for a
+/// 512 pixel texture
+#[no_mangle]
+pub extern "C" fn cells_fbm_default_params() -> CellsFbmParams {
+    let defaults = FbmParams::default();
+    CellsFbmParams {
+        size: 512,
+        octaves: defaults.octaves,
+        persistence: defaults.persistence,
+        lacunarity: defaults.lacunarity,
+        frequency: defaults.frequency,
+        seed: defaults.seed,
+    }
+}
+
+/// Generate a tileable Voronoi texture
+///
+/// On success `*out_buf` points to `*out_len` samples owned by the caller,
+/// to be released with [`cells_free`]. On failure both are left unchanged.
+///
+/// # Safety
+///
+/// `params` must point to a valid [`CellsVoronoiParams`], and `out_buf` and
+/// `out_len` to writable locations; null pointers are reported as
+/// [`CellsStatus::NullPointer`].
+#[no_mangle]
+pub unsafe extern "C" fn cells_voronoi(
+    params: *const CellsVoronoiParams,
+    out_buf: *mut *mut f32,
+    out_len: *mut usize,
+) -> CellsStatus {
+    let Some(params) = params.as_ref() else {
+        return CellsStatus::NullPointer;
+    };
+    let feature = match params.feature {
+        CELLS_FEATURE_F1 => Feature::F1,
+        CELLS_FEATURE_F2 => Feature::F2,
+        CELLS_FEATURE_F2_MINUS_F1 => Feature::F2MinusF1,
+        CELLS_FEATURE_CELL_VALUE => Feature::CellValue,
+        _ => return CellsStatus::InvalidParameter,
+    };
+    let metric = match params.metric {
+        CELLS_METRIC_EUCLIDEAN => DistanceMetric::Euclidean,
+        CELLS_METRIC_MANHATTAN => DistanceMetric::Manhattan,
+        CELLS_METRIC_CHEBYSHEV => DistanceMetric::Chebyshev,
+        CELLS_METRIC_MINKOWSKI if params.minkowski_p >= 1.0 => DistanceMetric::Minkowski(params.minkowski_p),
+        _ => return CellsStatus::InvalidParameter,
+    };
+    let params = VoronoiParams {
+        size: params.size,
+        num_points: params.num_points as usize,
+        seed: params.seed,
+        feature,
+        metric,
+        invert: params.invert,
+        ..Default::default()
+    };
+    if params.validate().is_err() {
+        return CellsStatus::InvalidParameter;
+    }
+    generate_into(|| generate_voronoi_field(&params), out_buf, out_len)
+}
+
+/// Generate tileable fractal Perlin noise
+///
+/// On success `*out_buf` points to `*out_len` samples owned by the caller,
+/// to be released with [`cells_free`]. On failure both are left unchanged.
+///
+/// # Safety
+///
+/// `params` must point to a valid [`CellsFbmParams`], and `out_buf` and
+/// `out_len` to writable locations; null pointers are reported as
+/// [`CellsStatus::NullPointer`].
+#[no_mangle]
+pub unsafe extern "C" fn cells_fbm(
+    params: *const CellsFbmParams,
+    out_buf: *mut *mut f32,
+    out_len: *mut usize,
+) -> CellsStatus {
+    let Some(params) = params.as_ref() else {
+        return CellsStatus::NullPointer;
+    };
+    let size = params.size;
+    let params = FbmParams {
+        octaves: params.octaves,
+        persistence: params.persistence,
+        lacunarity: params.lacunarity,
+        frequency: params.frequency,
+        seed: params.seed,
+        ..Default::default()
+    };
+    if size == 0 || params.validate().is_err() {
+        return CellsStatus::InvalidParameter;
+    }
+    generate_into(|| generate_perlin_field(size, &params), out_buf, out_len)
+}
+
+/// Blur `width * height` samples with a wrapping Gaussian of standard
+/// deviation `sigma`, see [`gaussian_blur`]
+///
+/// On success `*out_buf` points to `*out_len` samples owned by the caller,
+/// to be released with [`cells_free`]. On failure both are left unchanged.
+///
+/// # Safety
+///
+/// `samples` must point to `width * height` readable samples in row order,
+/// and `out_buf` and `out_len` to writable locations.
+#[no_mangle]
+pub unsafe extern "C" fn cells_gaussian_blur(
+    samples: *const f32,
+    width: u32,
+    height: u32,
+    sigma: f32,
+    out_buf: *mut *mut f32,
+    out_len: *mut usize,
+) -> CellsStatus {
+    let Some(field) = field_from(samples, width, height) else {
+        return CellsStatus::NullPointer;
+    };
+    if !(sigma.is_finite() && sigma >= 0.0) {
+        return CellsStatus::InvalidParameter;
+    }
+    generate_into(|| gaussian_blur(&field, sigma), out_buf, out_len)
+}
+
+/// Blur `width * height` samples along the directions of a second field of
+/// the same size, see [`directional_blur_field`]
+///
+/// On success `*out_buf` points to `*out_len` samples owned by the caller,
+/// to be released with [`cells_free`]. On failure both are left unchanged.
+///
+/// # Safety
+///
+/// `samples` and `direction` must each point to `width * height` readable
+/// samples in row order, and `out_buf` and `out_len` to writable locations.
+#[no_mangle]
+pub unsafe extern "C" fn cells_directional_blur(
+    samples: *const f32,
+    direction: *const f32,
+    width: u32,
+    height: u32,
+    radius: i32,
+    out_buf: *mut *mut f32,
+    out_len: *mut usize,
+) -> CellsStatus {
+    let (Some(field), Some(direction)) = (field_from(samples, width, height), field_from(direction, width, height))
+    else {
+        return CellsStatus::NullPointer;
+    };
+    if radius < 0 {
+        return CellsStatus::InvalidParameter;
+    }
+    generate_into(|| directional_blur_field(&field, &direction, radius), out_buf, out_len)
+}
+
+/// Release a buffer returned by one of the generators or filters; null
+/// buffers are ignored
+///
+/// # Safety
+///
+/// `buf` and `len` must be exactly as returned by one of the functions, and
+/// the buffer must not be used or released again afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn cells_free(buf: *mut f32, len: usize) {
+    if !buf.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(buf, len)));
+    }
+}
+
+/// A static, null-terminated description of a status
+#[no_mangle]
+pub extern "C" fn cells_status_message(status: CellsStatus) -> *const c_char {
+    let message: &CStr = match status {
+        CellsStatus::Ok => c"success",
+        CellsStatus::NullPointer => c"a required pointer argument was null",
+        CellsStatus::InvalidParameter => c"a parameter is outside its supported range",
+        CellsStatus::Panic => c"the generator panicked",
+    };
+    message.as_ptr()
+}
+
+/// Copy `width * height` samples into a field, or `None` for a null pointer
+///
+/// # Safety
+///
+/// `samples` must be null or point to `width * height` readable samples.
+unsafe fn field_from(samples: *const f32, width: u32, height: u32) -> Option<FieldBuffer> {
+    if samples.is_null() {
+        return None;
+    }
+    let samples = std::slice::from_raw_parts(samples, width as usize * height as usize);
+    Some(FieldBuffer::from_vec(width, height, samples.to_vec()))
+}
+
+/// Run a generator without letting a panic unwind into the caller, and hand
+/// its samples over through `out_buf` and `out_len`
+///
+/// # Safety
+///
+/// `out_buf` and `out_len` must be null or writable.
+unsafe fn generate_into(
+    generate: impl FnOnce() -> FieldBuffer,
+    out_buf: *mut *mut f32,
+    out_len: *mut usize,
+) -> CellsStatus {
+    if out_buf.is_null() || out_len.is_null() {
+        return CellsStatus::NullPointer;
+    }
+    let Ok(field) = panic::catch_unwind(AssertUnwindSafe(generate)) else {
+        return CellsStatus::Panic;
+    };
+    let samples = field.as_slice().to_vec().into_boxed_slice();
+    *out_len = samples.len();
+    *out_buf = Box::into_raw(samples).cast();
+    CellsStatus::Ok
+}
@@ -0,0 +1,381 @@
+//! Float-precision intermediate textures.
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+use image::{ImageBuffer, Luma, Pixel, Rgb};
+use rayon::prelude::*;
+
+/// Channel layout of 8-bit textures written from a field
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputChannels {
+    /// Single-channel grayscale, the layout heightmap tools expect
+    #[default]
+    Luma,
+    /// The value in the red channel of an RGB image, green and blue zero
+    RgbRed,
+}
+
+impl fmt::Display for OutputChannels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputChannels::Luma => write!(f, "luma"),
+            OutputChannels::RgbRed => write!(f, "rgb-red"),
+        }
+    }
+}
+
+impl FromStr for OutputChannels {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "luma" => Ok(OutputChannels::Luma),
+            "rgb-red" => Ok(OutputChannels::RgbRed),
+            _ => Err(format!("unknown channel layout `{s}`, expected luma or rgb-red")),
+        }
+    }
+}
+
+/// A single-channel texture of `f32` samples stored row by row
+///
+/// Generators write their raw values into a `FieldBuffer` before anything is
+/// quantized, so later stages can work on full precision data.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldBuffer {
+    width: u32,
+    height: u32,
+    data: Vec<f32>,
+}
+
+impl FieldBuffer {
+    /// Create a field of the given dimensions filled with zeros
+    pub fn new(width: u32, height: u32) -> Self {
+        FieldBuffer {
+            width,
+            height,
+            data: vec![0.0; width as usize * height as usize],
+        }
+    }
+
+    /// Create a field from row-major samples
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` does not hold exactly `width * height` samples.
+    pub fn from_vec(width: u32, height: u32, data: Vec<f32>) -> Self {
+        assert_eq!(
+            data.len(),
+            width as usize * height as usize,
+            "a {width}x{height} field needs {} samples",
+            width as usize * height as usize
+        );
+        FieldBuffer { width, height, data }
+    }
+
+    /// Create a field from the first channel of an 8-bit image, mapping 0-255
+    /// to 0-1
+    ///
+    /// This is the gray value of `Luma` images and the red channel of `Rgb`
+    /// images.
+    pub fn from_image<P: Pixel<Subpixel = u8>>(img: &ImageBuffer<P, Vec<u8>>) -> Self {
+        FieldBuffer::from_image_channel(img, 0)
+    }
+
+    /// Create a field from one channel of an 8-bit image, mapping 0-255 to
+    /// 0-1
+    ///
+    /// # Panics
+    ///
+    /// Panics if the image has no channel `channel`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::FieldBuffer;
+    /// use image::{Rgba, RgbaImage};
+    ///
+    /// let img = RgbaImage::from_pixel(2, 2, Rgba([0, 51, 255, 102]));
+    /// assert_eq!(FieldBuffer::from_image_channel(&img, 2).get(1, 1), 1.0);
+    /// assert_eq!(FieldBuffer::from_image_channel(&img, 3).get(0, 1), 0.4);
+    /// ```
+    pub fn from_image_channel<P: Pixel<Subpixel = u8>>(img: &ImageBuffer<P, Vec<u8>>, channel: usize) -> Self {
+        let channels = P::CHANNEL_COUNT as usize;
+        assert!(channel < channels, "{} images have no channel {channel}", P::COLOR_MODEL);
+        let (width, height) = img.dimensions();
+        let data = img.as_raw().par_chunks_exact(channels).map(|p| p[channel] as f32 / 255.0).collect();
+        FieldBuffer { width, height, data }
+    }
+
+    /// Quantize the field into the first channel of an 8-bit image
+    ///
+    /// Samples are clamped to 0-1 and mapped to 0-255 with rounding, all other
+    /// channels are zero. Pixels are written in parallel with rayon. This is the inverse of [`FieldBuffer::from_image`]
+    /// up to quantization, for any channel layout.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::FieldBuffer;
+    /// use image::{GrayImage, RgbImage};
+    ///
+    /// let field = FieldBuffer::from_par_fn(8, 8, |x, y| (x * 8 + y) as f32 / 63.0);
+    /// let gray: GrayImage = field.to_image();
+    /// let rgb: RgbImage = field.to_image();
+    /// assert_eq!(FieldBuffer::from_image(&gray), FieldBuffer::from_image(&rgb));
+    /// assert_eq!(rgb.get_pixel(7, 7).0, [255, 0, 0]);
+    /// ```
+    pub fn to_image<P: Pixel<Subpixel = u8>>(&self) -> ImageBuffer<P, Vec<u8>> {
+        let mut img = ImageBuffer::<P, Vec<u8>>::new(self.width, self.height);
+        img.par_chunks_exact_mut(P::CHANNEL_COUNT as usize)
+            .zip(&self.data)
+            .for_each(|(pixel, &value)| pixel[0] = quantize(value));
+        img
+    }
+
+    /// Quantize the field into one channel of an 8-bit image, keeping the
+    /// other channels
+    ///
+    /// # Panics
+    ///
+    /// Panics if the image does not have the dimensions of the field or has no
+    /// channel `channel`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::FieldBuffer;
+    /// use image::{Rgb, RgbImage};
+    ///
+    /// let mut img = RgbImage::from_pixel(2, 1, Rgb([10, 20, 30]));
+    /// FieldBuffer::from_vec(2, 1, vec![0.0, 1.0]).write_image_channel(&mut img, 1);
+    /// assert_eq!(img.get_pixel(1, 0).0, [10, 255, 30]);
+    /// ```
+    pub fn write_image_channel<P: Pixel<Subpixel = u8>>(&self, img: &mut ImageBuffer<P, Vec<u8>>, channel: usize) {
+        let channels = P::CHANNEL_COUNT as usize;
+        assert!(channel < channels, "{} images have no channel {channel}", P::COLOR_MODEL);
+        assert_eq!(img.dimensions(), self.dimensions(), "the image must have the dimensions of the field");
+        img.par_chunks_exact_mut(channels)
+            .zip(&self.data)
+            .for_each(|(pixel, &value)| pixel[channel] = quantize(value));
+    }
+
+    /// Quantize the field into an 8-bit grayscale image
+    ///
+    /// Samples are clamped to 0-1 and mapped to 0-255 with rounding.
+    pub fn to_luma_image(&self) -> ImageBuffer<Luma<u8>, Vec<u8>> {
+        self.to_image()
+    }
+
+    /// Quantize the field into the red channel of an image
+    ///
+    /// Samples are clamped to 0-1 and mapped to 0-255 with rounding. This is
+    /// the only place values lose precision, so it should happen at save time.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::FieldBuffer;
+    ///
+    /// let field = FieldBuffer::from_vec(3, 1, vec![-0.5, 0.5, 2.0]);
+    /// let img = field.to_rgb_image();
+    /// assert_eq!(img.get_pixel(1, 0).0, [128, 0, 0]);
+    /// assert_eq!(FieldBuffer::from_image(&img).get(2, 0), 1.0);
+    /// ```
+    pub fn to_rgb_image(&self) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        self.to_image()
+    }
+
+    /// Quantize the field into a 16-bit grayscale image
+    ///
+    /// Samples are clamped to 0-1 and mapped to 0-65535 with rounding, keeping
+    /// the precision 8-bit output loses, e.g. for heightmaps where 256 levels
+    /// show visible terracing.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::collections::HashSet;
+    /// use cells::FieldBuffer;
+    ///
+    /// let gradient = FieldBuffer::from_par_fn(1024, 1, |x, _| x as f32 / 1023.0);
+    /// let levels: HashSet<u16> = gradient.to_luma16_image().pixels().map(|p| p[0]).collect();
+    /// assert!(levels.len() > 256);
+    /// ```
+    pub fn to_luma16_image(&self) -> ImageBuffer<Luma<u16>, Vec<u16>> {
+        let mut img = ImageBuffer::<Luma<u16>, Vec<u16>>::new(self.width, self.height);
+        img.par_iter_mut()
+            .zip(&self.data)
+            .for_each(|(pixel, &value)| *pixel = (value.clamp(0.0, 1.0) * 65535.0).round() as u16);
+        img
+    }
+
+    /// Create a field by evaluating `f(x, y)` for every sample
+    ///
+    /// Rows are evaluated in parallel with rayon; the result does not depend
+    /// on the number of threads.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::FieldBuffer;
+    ///
+    /// let field = FieldBuffer::from_par_fn(4, 2, |x, y| (x + 10 * y) as f32);
+    /// assert_eq!(field.get(3, 1), 13.0);
+    /// ```
+    pub fn from_par_fn<F>(width: u32, height: u32, f: F) -> Self
+    where
+        F: Fn(u32, u32) -> f32 + Sync,
+    {
+        FieldBuffer::from_par_fn_with_progress(width, height, f, |_| {})
+    }
+
+    /// Create a field like [`FieldBuffer::from_par_fn`], reporting how many
+    /// rows are done
+    ///
+    /// `progress` is called with the finished share of the rows, from 0 to 1,
+    /// after every row. Rows finish on several threads, so the calls are
+    /// serialized through a lock; the reported share never decreases and the
+    /// last call reports 1.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::FieldBuffer;
+    ///
+    /// let mut reported = Vec::new();
+    /// let field = FieldBuffer::from_par_fn_with_progress(4, 64, |x, y| (x + y) as f32, |done| reported.push(done));
+    /// assert_eq!(field, FieldBuffer::from_par_fn(4, 64, |x, y| (x + y) as f32));
+    /// assert_eq!(reported.len(), 64);
+    /// assert!(reported.windows(2).all(|pair| pair[0] <= pair[1]));
+    /// assert_eq!(reported.last(), Some(&1.0));
+    /// ```
+    pub fn from_par_fn_with_progress<F, P>(width: u32, height: u32, f: F, progress: P) -> Self
+    where
+        F: Fn(u32, u32) -> f32 + Sync,
+        P: FnMut(f32) + Send,
+    {
+        let mut field = FieldBuffer::new(width, height);
+        let finished_rows = AtomicU32::new(0);
+        let progress = Mutex::new(progress);
+        if width > 0 {
+            field
+                .data
+                .par_chunks_mut(width as usize)
+                .enumerate()
+                .for_each(|(y, row)| {
+                    for (x, value) in row.iter_mut().enumerate() {
+                        *value = f(x as u32, y as u32);
+                    }
+                    finished_rows.fetch_add(1, Ordering::Relaxed);
+                    // Reading the count under the lock keeps the reported share growing
+                    let mut progress = progress.lock().unwrap_or_else(PoisonError::into_inner);
+                    progress(finished_rows.load(Ordering::Relaxed) as f32 / height as f32);
+                });
+        }
+        field
+    }
+
+    /// The width of the field in samples
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the field in samples
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The `(width, height)` of the field
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// The sample at `(x, y)`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the coordinates are outside the field.
+    pub fn get(&self, x: u32, y: u32) -> f32 {
+        assert!(x < self.width && y < self.height, "sample ({x}, {y}) out of bounds");
+        self.data[y as usize * self.width as usize + x as usize]
+    }
+
+    /// The sample at `(x, y)` with both coordinates wrapped around the edges
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::FieldBuffer;
+    ///
+    /// let field = FieldBuffer::from_par_fn(4, 4, |x, y| (x + 4 * y) as f32);
+    /// assert_eq!(field.get_wrapped(-1, 5), field.get(3, 1));
+    /// ```
+    pub fn get_wrapped(&self, x: i64, y: i64) -> f32 {
+        let x = x.rem_euclid(self.width as i64) as usize;
+        let y = y.rem_euclid(self.height as i64) as usize;
+        self.data[y * self.width as usize + x]
+    }
+
+    /// The bilinearly interpolated value at the fractional position `(x, y)`,
+    /// wrapping around the edges
+    ///
+    /// Integer positions return the sample at that position exactly.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::FieldBuffer;
+    ///
+    /// let field = FieldBuffer::from_vec(2, 2, vec![0.0, 1.0, 2.0, 3.0]);
+    /// assert_eq!(field.sample_bilinear_wrapped(0.5, 0.5), 1.5);
+    /// assert_eq!(field.sample_bilinear_wrapped(1.5, 0.0), 0.5);
+    /// assert_eq!(field.sample_bilinear_wrapped(-1.0, 3.0), field.get(1, 1));
+    /// ```
+    pub fn sample_bilinear_wrapped(&self, x: f32, y: f32) -> f32 {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let top = lerp(self.get_wrapped(x0, y0), self.get_wrapped(x0 + 1, y0), tx);
+        let bottom = lerp(self.get_wrapped(x0, y0 + 1), self.get_wrapped(x0 + 1, y0 + 1), tx);
+        lerp(top, bottom, ty)
+    }
+
+    /// The samples in row-major order
+    pub fn as_slice(&self) -> &[f32] {
+        &self.data
+    }
+
+    /// The samples in row-major order, mutably
+    pub fn as_mut_slice(&mut self) -> &mut [f32] {
+        &mut self.data
+    }
+
+    /// Create a field of the same size by applying `f` to every sample
+    pub fn map<F>(&self, f: F) -> FieldBuffer
+    where
+        F: Fn(f32) -> f32 + Sync,
+    {
+        FieldBuffer {
+            width: self.width,
+            height: self.height,
+            data: self.data.par_iter().map(|&v| f(v)).collect(),
+        }
+    }
+
+    /// The smallest and largest sample, or `None` for an empty field
+    pub fn min_max(&self) -> Option<(f32, f32)> {
+        self.data
+            .par_iter()
+            .map(|&v| (v, v))
+            .reduce_with(|a, b| (a.0.min(b.0), a.1.max(b.1)))
+    }
+}
+
+/// Map a 0-1 sample to 0-255, clamping out-of-range values
+pub(crate) fn quantize(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
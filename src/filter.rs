@@ -0,0 +1,1471 @@
+//! Image filters operating on generated textures.
+
+use std::fmt;
+use std::str::FromStr;
+
+use image::{ImageBuffer, Pixel};
+use serde::{Deserialize, Serialize};
+
+use crate::error::CellsError;
+use crate::field::FieldBuffer;
+
+/// How the directional blur reads the input between pixel centers
+///
+/// # Example
+///
+/// Bilinear taps avoid the staircase steps of rounded taps at short radii:
+///
+/// ```rust
+/// use cells::filter::{directional_blur_field_with, BlurParams, BlurSampling};
+/// use cells::tiling::interior_discontinuity;
+/// use cells::voronoi::{generate_voronoi_field, VoronoiParams};
+///
+/// let voronoi = generate_voronoi_field(&VoronoiParams { size: 128, num_points: 30, ..Default::default() });
+/// for radius in 1..=4 {
+///     let blur = |sampling| {
+///         let params = BlurParams { radius, sampling, ..Default::default() };
+///         directional_blur_field_with(&voronoi, &voronoi, &params)
+///     };
+///     let bilinear = interior_discontinuity(&blur(BlurSampling::Bilinear));
+///     assert!(bilinear < interior_discontinuity(&blur(BlurSampling::Nearest)));
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlurSampling {
+    /// Interpolate the four pixels around every tap, smooth at any radius
+    #[default]
+    Bilinear,
+    /// Round every tap to the nearest pixel, the original behavior; short
+    /// blurs only follow a few discrete directions
+    Nearest,
+}
+
+impl fmt::Display for BlurSampling {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BlurSampling::Bilinear => "bilinear",
+            BlurSampling::Nearest => "nearest",
+        })
+    }
+}
+
+impl FromStr for BlurSampling {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bilinear" => Ok(BlurSampling::Bilinear),
+            "nearest" => Ok(BlurSampling::Nearest),
+            _ => Err(format!("unknown blur sampling `{s}`, expected bilinear or nearest")),
+        }
+    }
+}
+
+serde_via_str!(BlurSampling);
+
+/// How the taps along the directional blur line are weighted
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BlurKernel {
+    /// Every tap counts the same, the original box average with hard
+    /// streak ends
+    #[default]
+    Box,
+    /// Taps fall off linearly towards the ends of the line
+    Triangle,
+    /// Taps follow a normal distribution with the given standard deviation
+    /// in taps, giving soft streak ends
+    Gaussian {
+        /// Standard deviation of the weights, in taps
+        sigma: f32,
+    },
+}
+
+impl BlurKernel {
+    /// The normalized weights of the `2 * radius + 1` taps, from `-radius`
+    /// to `radius`
+    ///
+    /// The weights are normalized over exactly these taps, so they sum to 1
+    /// even when a wide Gaussian is cut off by a short radius.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::filter::BlurKernel;
+    ///
+    /// for kernel in [BlurKernel::Box, BlurKernel::Triangle, BlurKernel::Gaussian { sigma: 1.5 }] {
+    ///     for radius in 0..64 {
+    ///         let weights = kernel.weights(radius);
+    ///         assert_eq!(weights.len(), 2 * radius as usize + 1);
+    ///         assert!((weights.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+    ///     }
+    /// }
+    /// assert_eq!(BlurKernel::Triangle.weights(1), vec![0.25, 0.5, 0.25]);
+    /// ```
+    pub fn weights(self, radius: i32) -> Vec<f32> {
+        let raw = self.raw_weights(radius);
+        let total: f32 = raw.iter().sum();
+        raw.iter().map(|w| w / total).collect()
+    }
+
+    /// The unnormalized tap weights, all positive
+    pub(crate) fn raw_weights(self, radius: i32) -> Vec<f32> {
+        (-radius..=radius)
+            .map(|i| match self {
+                BlurKernel::Box => 1.0,
+                BlurKernel::Triangle => (radius + 1 - i.abs()) as f32,
+                BlurKernel::Gaussian { sigma } => (-0.5 * (i as f32 / sigma).powi(2)).exp(),
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for BlurKernel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlurKernel::Box => write!(f, "box"),
+            BlurKernel::Triangle => write!(f, "triangle"),
+            BlurKernel::Gaussian { sigma } => write!(f, "gaussian:{sigma}"),
+        }
+    }
+}
+
+impl FromStr for BlurKernel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "box" => Ok(BlurKernel::Box),
+            None if s == "triangle" => Ok(BlurKernel::Triangle),
+            Some(("gaussian", sigma)) => {
+                let sigma: f32 = sigma
+                    .parse()
+                    .map_err(|err| format!("invalid Gaussian sigma `{sigma}`: {err}"))?;
+                // Tiny sigmas underflow every weight but the center one to 0
+                if !(sigma.is_finite() && sigma >= 0.01) {
+                    return Err(format!("Gaussian sigma must be at least 0.01, got {sigma}"));
+                }
+                Ok(BlurKernel::Gaussian { sigma })
+            }
+            _ => Err(format!(
+                "unknown blur kernel `{s}`, expected box, triangle or gaussian:<sigma>"
+            )),
+        }
+    }
+}
+
+serde_via_str!(BlurKernel);
+
+/// How direction map values are turned into blur directions
+///
+/// The taps of the symmetric mappings run from `-radius` to `radius`, so
+/// angles 180 degrees apart blur identically.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::filter::DirectionMapping;
+///
+/// let (x, y) = DirectionMapping::Half180.step(0.5);
+/// assert!(x.abs() < 1e-6 && (y - 1.0).abs() < 1e-6);
+/// assert_eq!(DirectionMapping::Half180.step(0.25), DirectionMapping::Full360.step(0.125));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DirectionMapping {
+    /// 0 to 1 covers a full turn, the original mapping used by the default
+    /// pipeline; half of the range repeats the other half
+    #[default]
+    Full360,
+    /// 0 to 1 covers half a turn, the distinct directions of a symmetric blur
+    /// at twice the precision
+    Half180,
+    /// 0 to 1 covers a full turn and taps only run forward from `0` to
+    /// `radius`, so streaks flow one way; meant to be paired with a length map
+    SignedFlow,
+}
+
+impl DirectionMapping {
+    /// The unit step between taps for the direction map value `value`
+    pub fn step(self, value: f32) -> (f32, f32) {
+        let turn = match self {
+            DirectionMapping::Full360 | DirectionMapping::SignedFlow => 360.0,
+            DirectionMapping::Half180 => 180.0,
+        };
+        let angle = (value * turn).to_radians();
+        (angle.cos(), angle.sin())
+    }
+
+    /// The first tap offset, the taps run from here to the radius
+    pub(crate) fn first_tap(self, radius: i32) -> i32 {
+        match self {
+            DirectionMapping::Full360 | DirectionMapping::Half180 => -radius,
+            DirectionMapping::SignedFlow => 0,
+        }
+    }
+}
+
+impl fmt::Display for DirectionMapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DirectionMapping::Full360 => "full360",
+            DirectionMapping::Half180 => "half180",
+            DirectionMapping::SignedFlow => "signed-flow",
+        })
+    }
+}
+
+impl FromStr for DirectionMapping {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full360" => Ok(DirectionMapping::Full360),
+            "half180" => Ok(DirectionMapping::Half180),
+            "signed-flow" => Ok(DirectionMapping::SignedFlow),
+            _ => Err(format!(
+                "unknown direction mapping `{s}`, expected full360, half180 or signed-flow"
+            )),
+        }
+    }
+}
+
+serde_via_str!(DirectionMapping);
+
+/// Parameters of the directional blur
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BlurParams {
+    /// The number of taps on either side of every sample
+    pub radius: i32,
+    /// How taps between pixel centers are read
+    pub sampling: BlurSampling,
+    /// How the taps are weighted
+    pub kernel: BlurKernel,
+    /// How direction map values become blur directions
+    pub mapping: DirectionMapping,
+}
+
+impl Default for BlurParams {
+    fn default() -> Self {
+        BlurParams {
+            radius: 3,
+            sampling: BlurSampling::Bilinear,
+            kernel: BlurKernel::Box,
+            mapping: DirectionMapping::Full360,
+        }
+    }
+}
+
+impl BlurParams {
+    /// Check that the blur has taps to weigh
+    ///
+    /// # Returns
+    ///
+    /// [`CellsError::InvalidParameter`] for a negative radius or a Gaussian
+    /// kernel whose sigma is below 0.01 or not finite
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::filter::{BlurKernel, BlurParams};
+    ///
+    /// assert!(BlurParams::default().validate().is_ok());
+    /// assert!(BlurParams { radius: -1, ..Default::default() }.validate().is_err());
+    /// assert!(BlurParams { kernel: BlurKernel::Gaussian { sigma: 0.0 }, ..Default::default() }.validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<(), CellsError> {
+        let invalid = |name, reason: String| Err(CellsError::InvalidParameter { name, reason });
+        if self.radius < 0 {
+            return invalid("radius", format!("must not be negative, got {}", self.radius));
+        }
+        if let BlurKernel::Gaussian { sigma } = self.kernel {
+            // Tiny sigmas underflow every weight but the center one to 0
+            if !(sigma.is_finite() && sigma >= 0.01) {
+                return invalid("kernel", format!("Gaussian sigma must be at least 0.01, got {sigma}"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Directional blur parameters set one at a time, checked by
+/// [`BlurBuilder::build`]
+///
+/// Unset parameters keep the defaults of [`BlurParams`].
+///
+/// # Example
+///
+/// ```rust
+/// use cells::filter::{directional_blur_field_with, BlurBuilder, BlurKernel};
+/// use cells::FieldBuffer;
+///
+/// let field = FieldBuffer::from_par_fn(16, 16, |x, y| ((x * 3 + y) % 5) as f32 / 4.0);
+/// let direction = FieldBuffer::from_par_fn(16, 16, |x, _| x as f32 / 16.0);
+/// let params = BlurBuilder::new().radius(4).kernel(BlurKernel::Triangle).build().unwrap();
+/// let blurred = BlurBuilder::new().radius(4).kernel(BlurKernel::Triangle).blur(&field, &direction).unwrap();
+/// assert_eq!(blurred, directional_blur_field_with(&field, &direction, &params));
+/// assert!(BlurBuilder::new().radius(-2).build().is_err());
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BlurBuilder {
+    params: BlurParams,
+}
+
+impl BlurBuilder {
+    /// A builder of the default [`BlurParams`]
+    pub fn new() -> BlurBuilder {
+        BlurBuilder::default()
+    }
+
+    /// The number of taps on either side of every sample, 0 or more
+    pub fn radius(mut self, radius: i32) -> BlurBuilder {
+        self.params.radius = radius;
+        self
+    }
+
+    /// How taps between pixel centers are read
+    pub fn sampling(mut self, sampling: BlurSampling) -> BlurBuilder {
+        self.params.sampling = sampling;
+        self
+    }
+
+    /// How the taps are weighted; Gaussian sigmas must be at least 0.01
+    pub fn kernel(mut self, kernel: BlurKernel) -> BlurBuilder {
+        self.params.kernel = kernel;
+        self
+    }
+
+    /// How direction map values become blur directions
+    pub fn mapping(mut self, mapping: DirectionMapping) -> BlurBuilder {
+        self.params.mapping = mapping;
+        self
+    }
+
+    /// The parameters, if [`BlurParams::validate`] accepts them
+    pub fn build(self) -> Result<BlurParams, CellsError> {
+        self.params.validate()?;
+        Ok(self.params)
+    }
+
+    /// Blur `field` along the directions of `direction` with
+    /// [`directional_blur_field_with`]
+    ///
+    /// # Returns
+    ///
+    /// The blurred field, the error of [`BlurBuilder::build`], or
+    /// [`CellsError::DimensionMismatch`] if the fields differ in size
+    pub fn blur(self, field: &FieldBuffer, direction: &FieldBuffer) -> Result<FieldBuffer, CellsError> {
+        let params = self.build()?;
+        if direction.dimensions() != field.dimensions() {
+            return Err(CellsError::DimensionMismatch {
+                input: "direction",
+                expected: field.dimensions(),
+                found: direction.dimensions(),
+            });
+        }
+        Ok(directional_blur_field_with(field, direction, &params))
+    }
+}
+
+/// Apply directional blur to a field
+///
+/// This is [`directional_blur_field_with`] with the default [`BlurParams`]
+/// and the given radius.
+///
+/// # Arguments
+///
+/// * `field` - The input field to be blurred
+/// * `direction` - The field used as a direction map for the blur
+/// * `blur_radius` - The radius of the blur effect
+///
+/// # Returns
+///
+/// A `FieldBuffer` containing the blurred field
+///
+/// # Example
+///
+/// Repeated blurring in float precision keeps gray levels that the 8-bit
+/// [`directional_blur`] loses to rounding after every step:
+///
+/// ```rust
+/// use std::collections::HashSet;
+/// use cells::filter::{directional_blur, directional_blur_field, normalize_field, normalize_image};
+/// use cells::voronoi::{generate_voronoi_field, VoronoiParams};
+///
+/// let voronoi = generate_voronoi_field(&VoronoiParams { size: 64, num_points: 16, ..Default::default() });
+/// let voronoi_image = voronoi.to_rgb_image();
+/// let (mut field, mut image) = (voronoi.clone(), voronoi_image.clone());
+/// for i in 0..4 {
+///     field = normalize_field(&directional_blur_field(&field, &voronoi, 3 << i));
+///     image = normalize_image(&directional_blur(&image, &voronoi_image, 3 << i));
+/// }
+/// let levels = |img: &image::RgbImage| img.pixels().map(|p| p[0]).collect::<HashSet<_>>().len();
+/// assert!(levels(&field.to_rgb_image()) > levels(&image));
+/// ```
+pub fn directional_blur_field(field: &FieldBuffer, direction: &FieldBuffer, blur_radius: i32) -> FieldBuffer {
+    let params = BlurParams {
+        radius: blur_radius,
+        ..Default::default()
+    };
+    directional_blur_field_with(field, direction, &params)
+}
+
+/// Apply directional blur to a field with explicit parameters
+///
+/// This function applies a directional blur to the input field, using another
+/// field as a direction map. The blur direction for each sample is determined
+/// by the corresponding value in the direction map, where 0 to 1 covers a full
+/// turn with the default [`DirectionMapping::Full360`].
+///
+/// # Algorithm
+///
+/// 1. For each sample in the input field:
+///    a. Determine the blur direction from the direction map
+///    b. Sample values along this direction within the blur radius
+///    c. Read taps between pixels bilinearly or from the nearest pixel
+///    d. Calculate the average of the sampled values, weighted by the kernel
+///    e. Set the output sample to this average value
+/// 2. Wrap around field edges to ensure seamless tiling
+///
+/// # Arguments
+///
+/// * `field` - The input field to be blurred
+/// * `direction` - The field used as a direction map for the blur
+/// * `params` - The radius, sampling, kernel and direction mapping of the blur
+///
+/// # Returns
+///
+/// A `FieldBuffer` containing the blurred field
+///
+/// # Performance
+///
+/// This function has O(width * height * radius) complexity. The kernel
+/// weights are computed once per call, and rows are processed in parallel
+/// with rayon.
+///
+/// # Example
+///
+/// A constant field stays exactly constant with any sampling and kernel:
+///
+/// ```rust
+/// use cells::filter::{directional_blur_field_with, BlurKernel, BlurParams, BlurSampling};
+/// use cells::noise::{generate_perlin_field, FbmParams};
+/// use cells::FieldBuffer;
+///
+/// let constant = FieldBuffer::from_par_fn(32, 32, |_, _| 0.5);
+/// let direction = generate_perlin_field(32, &FbmParams::default());
+/// for sampling in [BlurSampling::Bilinear, BlurSampling::Nearest] {
+///     for kernel in [BlurKernel::Box, BlurKernel::Triangle, BlurKernel::Gaussian { sigma: 1.5 }] {
+///         for radius in [1, 2, 4, 9] {
+///             let params = BlurParams { radius, sampling, kernel, ..Default::default() };
+///             let blurred = directional_blur_field_with(&constant, &direction, &params);
+///             assert!(blurred.as_slice().iter().all(|&v| v == 0.5));
+///         }
+///     }
+/// }
+/// ```
+pub fn directional_blur_field_with(field: &FieldBuffer, direction: &FieldBuffer, params: &BlurParams) -> FieldBuffer {
+    blur_along(field, direction, None, params, |_| {})
+}
+
+/// Apply directional blur to a field, scaling the blur length per sample
+///
+/// This is [`directional_blur_field_with`] with the spacing of the taps at
+/// every sample multiplied by the value of `length`, clamped to 0-1, so the
+/// streaks reach from none to the full radius. Samples of length 0 copy the
+/// input exactly. Together with
+/// [`DirectionMapping::SignedFlow`] the two maps describe a flow: where it
+/// goes and how far.
+///
+/// # Arguments
+///
+/// * `field` - The input field to be blurred
+/// * `direction` - The field used as a direction map for the blur
+/// * `length` - The field scaling the blur length of every sample
+/// * `params` - The radius, sampling, kernel and direction mapping of the blur
+///
+/// # Returns
+///
+/// A `FieldBuffer` containing the blurred field
+///
+/// # Panics
+///
+/// Panics if `length` does not have the dimensions of `field`.
+///
+/// # Example
+///
+/// Opposite directions blur differently with signed flow, and a zero length
+/// keeps the input:
+///
+/// ```rust
+/// use cells::filter::{directional_blur_field_with_length, BlurParams, DirectionMapping};
+/// use cells::FieldBuffer;
+///
+/// let ramp = FieldBuffer::from_par_fn(32, 32, |x, _| (x as f32 / 31.0).powi(2));
+/// let full = FieldBuffer::from_par_fn(32, 32, |_, _| 1.0);
+/// let blur = |angle: f32, mapping, length: &FieldBuffer| {
+///     let direction = FieldBuffer::from_par_fn(32, 32, |_, _| angle);
+///     let params = BlurParams { radius: 4, mapping, ..Default::default() };
+///     directional_blur_field_with_length(&ramp, &direction, length, &params)
+/// };
+/// let difference = |a: &FieldBuffer, b: &FieldBuffer| {
+///     a.as_slice().iter().zip(b.as_slice()).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max)
+/// };
+/// let symmetric = DirectionMapping::Full360;
+/// assert!(difference(&blur(0.0, symmetric, &full), &blur(0.5, symmetric, &full)) < 1e-5);
+/// let signed = DirectionMapping::SignedFlow;
+/// assert!(difference(&blur(0.0, signed, &full), &blur(0.5, signed, &full)) > 0.01);
+/// let zero = FieldBuffer::new(32, 32);
+/// assert_eq!(blur(0.3, signed, &zero), ramp);
+/// ```
+pub fn directional_blur_field_with_length(
+    field: &FieldBuffer,
+    direction: &FieldBuffer,
+    length: &FieldBuffer,
+    params: &BlurParams,
+) -> FieldBuffer {
+    assert_eq!(
+        length.dimensions(),
+        field.dimensions(),
+        "the length map must have the dimensions of the field"
+    );
+    blur_along(field, direction, Some(length), params, |_| {})
+}
+
+/// Apply directional blur to a field, reporting progress
+///
+/// This is [`directional_blur_field_with`], or
+/// [`directional_blur_field_with_length`] when a `length` map is given,
+/// calling `progress` with the finished share of the rows, from 0 to 1, as
+/// they complete.
+///
+/// # Panics
+///
+/// Panics if `length` does not have the dimensions of `field`.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::filter::{directional_blur_field_with, directional_blur_field_with_progress, BlurParams};
+/// use cells::voronoi::{generate_voronoi_field, VoronoiParams};
+///
+/// let voronoi = generate_voronoi_field(&VoronoiParams { size: 32, num_points: 8, ..Default::default() });
+/// let params = BlurParams::default();
+/// let mut calls = 0;
+/// let blurred = directional_blur_field_with_progress(&voronoi, &voronoi, None, &params, |_| calls += 1);
+/// assert_eq!(blurred, directional_blur_field_with(&voronoi, &voronoi, &params));
+/// assert_eq!(calls, 32);
+/// ```
+pub fn directional_blur_field_with_progress(
+    field: &FieldBuffer,
+    direction: &FieldBuffer,
+    length: Option<&FieldBuffer>,
+    params: &BlurParams,
+    progress: impl FnMut(f32) + Send,
+) -> FieldBuffer {
+    if let Some(length) = length {
+        assert_eq!(
+            length.dimensions(),
+            field.dimensions(),
+            "the length map must have the dimensions of the field"
+        );
+    }
+    blur_along(field, direction, length, params, progress)
+}
+
+/// The directional blur with an optional per-sample length scale
+fn blur_along(
+    field: &FieldBuffer,
+    direction: &FieldBuffer,
+    length: Option<&FieldBuffer>,
+    params: &BlurParams,
+    progress: impl FnMut(f32) + Send,
+) -> FieldBuffer {
+    let (width, height) = field.dimensions();
+    let blur_radius = params.radius;
+    warn_wide_blur("directional blur radius", blur_radius, width, height);
+    let first_tap = params.mapping.first_tap(blur_radius);
+    // Dividing by the sum of the raw weights keeps box blurs an exact average
+    let weights = params.kernel.raw_weights(blur_radius);
+    let weights = &weights[(first_tap + blur_radius) as usize..];
+    let total_weight: f32 = weights.iter().sum();
+    let blur = |x: u32, y: u32| {
+        let (mut step_x, mut step_y) = params.mapping.step(direction.get(x, y));
+        if let Some(length) = length {
+            let scale = length.get(x, y).clamp(0.0, 1.0);
+            // The weighted sum of equal taps is not exactly the tap
+            if scale == 0.0 {
+                return field.get(x, y);
+            }
+            (step_x, step_y) = (step_x * scale, step_y * scale);
+        }
+
+        let sum: f32 = (first_tap..=blur_radius)
+            .zip(weights)
+            .map(|(i, weight)| {
+                weight
+                    * match params.sampling {
+                        BlurSampling::Bilinear => field
+                            .sample_bilinear_wrapped(x as f32 + i as f32 * step_x, y as f32 + i as f32 * step_y),
+                        BlurSampling::Nearest => {
+                            let delta_x = (i as f32 * step_x).round() as i64;
+                            let delta_y = (i as f32 * step_y).round() as i64;
+                            field.get_wrapped(x as i64 + delta_x, y as i64 + delta_y)
+                        }
+                    }
+            })
+            .sum();
+
+        sum / total_weight
+    };
+    let details = format_args!(
+        "{width}x{height}, radius {blur_radius}, {} kernel, {} sampling, {} mapping{}",
+        params.kernel,
+        params.sampling,
+        params.mapping,
+        if length.is_some() { ", scaled by a length map" } else { "" }
+    );
+    crate::stage("directional blur", details, || FieldBuffer::from_par_fn_with_progress(width, height, blur, progress))
+}
+
+/// The blur radius of step `step` of an iterated blur
+///
+/// Step 0 uses `base_radius`, and every further step multiplies it by
+/// `growth`, rounded to whole taps.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::filter::step_radius;
+///
+/// let radii: Vec<i32> = (0..4).map(|step| step_radius(3, 2.0, step)).collect();
+/// assert_eq!(radii, [3, 6, 12, 24]);
+/// assert_eq!(step_radius(4, 1.5, 2), 9);
+/// ```
+pub fn step_radius(base_radius: i32, growth: f64, step: u32) -> i32 {
+    (base_radius as f64 * growth.powi(step as i32)).round() as i32
+}
+
+/// Blur a field repeatedly, normalizing after every step
+///
+/// This is the blur loop of the texture pipeline: step `i` blurs the result
+/// of the previous step with the radius [`step_radius`] of `params.radius`,
+/// and normalizes it so the next step works on the full 0-1 range. Radii are
+/// clamped to the larger dimension of the field, which truncates the kernel
+/// of wider steps there.
+///
+/// # Arguments
+///
+/// * `field` - The input field to be blurred
+/// * `direction` - The field used as a direction map for every step
+/// * `params` - The blur of the first step
+/// * `steps` - The number of blur steps, 0 returns the input unchanged
+/// * `growth` - The factor the radius grows by on every step
+/// * `normalization` - How the result of every step is normalized
+///
+/// # Returns
+///
+/// A `FieldBuffer` containing the field after the last step
+///
+/// # Panics
+///
+/// Panics if `growth` is not positive and finite.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::filter::{iterated_directional_blur, BlurParams, Normalization};
+/// use cells::voronoi::{generate_voronoi_field, VoronoiParams};
+///
+/// let voronoi = generate_voronoi_field(&VoronoiParams { size: 32, num_points: 8, ..Default::default() });
+/// let params = BlurParams::default();
+/// let blurred = iterated_directional_blur(&voronoi, &voronoi, &params, 0, 2.0, Normalization::Stretch);
+/// assert_eq!(blurred, voronoi);
+/// ```
+pub fn iterated_directional_blur(
+    field: &FieldBuffer,
+    direction: &FieldBuffer,
+    params: &BlurParams,
+    steps: u32,
+    growth: f64,
+    normalization: Normalization,
+) -> FieldBuffer {
+    let blur = |field: &FieldBuffer, step: &BlurParams| directional_blur_field_with(field, direction, step);
+    iterated_blur(field, params, steps, growth, normalization, blur, |_, _| {})
+}
+
+/// Blur a field repeatedly with any blur, normalizing after every step
+///
+/// This is [`iterated_directional_blur`] with the blur of every step left to
+/// `blur`, which receives the previous result and the parameters of the
+/// step. `on_step` sees the normalized result of every step with its 0-based
+/// index, e.g. to save intermediate textures.
+///
+/// # Panics
+///
+/// Panics if `growth` is not positive and finite.
+///
+/// # Example
+///
+/// A growth of 1 keeps the radius constant:
+///
+/// ```rust
+/// use cells::filter::{directional_blur_field_with, iterated_blur, BlurParams, Normalization};
+/// use cells::voronoi::{generate_voronoi_field, VoronoiParams};
+///
+/// let voronoi = generate_voronoi_field(&VoronoiParams { size: 32, num_points: 8, ..Default::default() });
+/// let params = BlurParams { radius: 5, ..Default::default() };
+/// let mut radii = Vec::new();
+/// let mut seen = Vec::new();
+/// iterated_blur(
+///     &voronoi,
+///     &params,
+///     4,
+///     1.0,
+///     Normalization::Stretch,
+///     |field, step| {
+///         radii.push(step.radius);
+///         directional_blur_field_with(field, &voronoi, step)
+///     },
+///     |index, _| seen.push(index),
+/// );
+/// assert_eq!(radii, [5, 5, 5, 5]);
+/// assert_eq!(seen, [0, 1, 2, 3]);
+/// ```
+pub fn iterated_blur<B, S>(
+    field: &FieldBuffer,
+    params: &BlurParams,
+    steps: u32,
+    growth: f64,
+    normalization: Normalization,
+    mut blur: B,
+    mut on_step: S,
+) -> FieldBuffer
+where
+    B: FnMut(&FieldBuffer, &BlurParams) -> FieldBuffer,
+    S: FnMut(u32, &FieldBuffer),
+{
+    assert!(growth.is_finite() && growth > 0.0, "blur growth must be positive, got {growth}");
+    let (width, height) = field.dimensions();
+    let max_radius = width.max(height).min(i32::MAX as u32) as i32;
+    let mut blurred = field.clone();
+    for i in 0..steps {
+        let step = BlurParams {
+            radius: step_radius(params.radius, growth, i).min(max_radius),
+            ..*params
+        };
+        blurred = normalization.apply(&blur(&blurred, &step));
+        on_step(i, &blurred);
+    }
+    blurred
+}
+
+/// Apply directional blur to an image
+///
+/// This is [`directional_blur_field`] on every color channel of an 8-bit
+/// image, each blurred on its own along the same directions and quantized
+/// back to 8 bits; alpha is copied unchanged. The directions are read from
+/// the first channel of `direction_channel`, the gray value of `Luma` and the
+/// red channel of `Rgb` images. The conversions and the blur all run in
+/// parallel, and the result does not depend on the number of threads.
+///
+/// # Arguments
+///
+/// * `img` - The input image to be blurred
+/// * `direction_channel` - The image used as a direction map for the blur
+/// * `blur_radius` - The radius of the blur effect
+///
+/// # Returns
+///
+/// An `ImageBuffer` containing the blurred image
+///
+/// # Example
+///
+/// ```rust
+/// use cells::filter::directional_blur;
+/// use cells::noise::{generate_perlin_field, generate_perlin_noise, FbmParams};
+/// use cells::voronoi::{generate_tileable_voronoi, generate_voronoi_field, VoronoiParams};
+///
+/// let params = VoronoiParams { size: 64, num_points: 16, ..Default::default() };
+/// let input_image = generate_tileable_voronoi(&params);
+/// let fbm = FbmParams { frequency: 4.0, seed: 1, ..Default::default() };
+/// let direction_map = generate_perlin_noise(64, &fbm);
+/// let blurred_image = directional_blur(&input_image, &direction_map, 5);
+/// assert_eq!(blurred_image.dimensions(), input_image.dimensions());
+///
+/// // Grayscale images blur to the same values
+/// let gray_input = generate_voronoi_field(&params).to_luma_image();
+/// let gray_direction = generate_perlin_field(64, &fbm).to_luma_image();
+/// let blurred_gray = directional_blur(&gray_input, &gray_direction, 5);
+/// assert!(blurred_gray.pixels().zip(blurred_image.pixels()).all(|(g, c)| g[0] == c[0]));
+/// // The empty green and blue channels of the tileable Voronoi stay empty
+/// assert!(blurred_image.pixels().all(|p| p[1] == 0 && p[2] == 0));
+/// ```
+pub fn directional_blur<P, Q>(
+    img: &ImageBuffer<P, Vec<u8>>,
+    direction_channel: &ImageBuffer<Q, Vec<u8>>,
+    blur_radius: i32,
+) -> ImageBuffer<P, Vec<u8>>
+where
+    P: Pixel<Subpixel = u8>,
+    Q: Pixel<Subpixel = u8>,
+{
+    let direction = FieldBuffer::from_image(direction_channel);
+    map_color_channels(img, |field| directional_blur_field(field, &direction, blur_radius))
+}
+
+/// Apply `filter` to every color channel of an 8-bit image, copying alpha
+fn map_color_channels<P>(
+    img: &ImageBuffer<P, Vec<u8>>,
+    filter: impl Fn(&FieldBuffer) -> FieldBuffer,
+) -> ImageBuffer<P, Vec<u8>>
+where
+    P: Pixel<Subpixel = u8>,
+{
+    let mut filtered = img.clone();
+    for channel in 0..color_channels::<P>() {
+        filter(&FieldBuffer::from_image_channel(img, channel)).write_image_channel(&mut filtered, channel);
+    }
+    filtered
+}
+
+/// The number of color channels of a pixel type, which come before its alpha
+fn color_channels<P: Pixel>() -> usize {
+    let alpha = P::COLOR_MODEL.ends_with('A');
+    P::CHANNEL_COUNT as usize - usize::from(alpha)
+}
+
+/// Blur a field with an isotropic Gaussian, wrapping around the edges
+///
+/// The blur is separable: a horizontal pass followed by a vertical pass,
+/// each with a normalized kernel of radius `ceil(3 * sigma)`. The kernel is
+/// truncated at the larger dimension of the field, so very wide blurs
+/// approach a box average of the wrapped field rather than a Gaussian. The
+/// weights sum to 1, so the mean of the field is kept, and the result tiles
+/// whenever the input does.
+///
+/// # Arguments
+///
+/// * `field` - The input field to be blurred
+/// * `sigma` - The standard deviation of the Gaussian in samples, 0 returns
+///   the input unchanged
+///
+/// # Returns
+///
+/// A `FieldBuffer` containing the blurred field
+///
+/// # Panics
+///
+/// Panics if `sigma` is negative or not finite.
+///
+/// # Performance
+///
+/// This function has O(width * height * sigma) complexity. The rows and then
+/// the columns are processed in parallel with rayon.
+///
+/// # Example
+///
+/// Blurring an impulse gives the symmetric kernel, with the energy of the
+/// impulse:
+///
+/// ```rust
+/// use cells::filter::gaussian_blur;
+/// use cells::FieldBuffer;
+///
+/// let impulse = FieldBuffer::from_par_fn(32, 32, |x, y| if (x, y) == (16, 16) { 1.0 } else { 0.0 });
+/// let kernel = gaussian_blur(&impulse, 2.0);
+/// let total: f32 = kernel.as_slice().iter().sum();
+/// assert!((total - 1.0).abs() < 1e-5);
+/// for (dx, dy) in [(1, 0), (3, 2), (5, 1)] {
+///     let value = kernel.get(16 + dx, 16 + dy);
+///     assert!(value > 0.0);
+///     assert_eq!(value, kernel.get(16 - dx, 16 - dy));
+///     assert_eq!(value, kernel.get(16 + dx, 16 - dy));
+///     assert!((value - kernel.get(16 + dy, 16 + dx)).abs() < 1e-7);
+/// }
+///
+/// // An impulse on the edge wraps into the opposite edges
+/// let corner = FieldBuffer::from_par_fn(32, 32, |x, y| if (x, y) == (0, 0) { 1.0 } else { 0.0 });
+/// let wrapped = gaussian_blur(&corner, 2.0);
+/// assert_eq!(wrapped.get(31, 30), kernel.get(15, 14));
+/// ```
+pub fn gaussian_blur(field: &FieldBuffer, sigma: f32) -> FieldBuffer {
+    assert!(sigma.is_finite() && sigma >= 0.0, "Gaussian sigma must be non-negative, got {sigma}");
+    let (width, height) = field.dimensions();
+    let radius = gaussian_radius(sigma, width, height);
+    if radius == 0 {
+        return field.clone();
+    }
+    let weights = BlurKernel::Gaussian { sigma }.weights(radius);
+    warn_wide_blur("Gaussian blur reach", radius, width, height);
+    let pass = |field: &FieldBuffer, (step_x, step_y): (i64, i64)| {
+        FieldBuffer::from_par_fn(width, height, |x, y| {
+            (-radius as i64..=radius as i64)
+                .zip(&weights)
+                .map(|(i, weight)| weight * field.get_wrapped(x as i64 + i * step_x, y as i64 + i * step_y))
+                .sum()
+        })
+    };
+    let details = format_args!("{width}x{height}, sigma {sigma}");
+    crate::stage("Gaussian blur", details, || pass(&pass(field, (1, 0)), (0, 1)))
+}
+
+/// The reach of a Gaussian blur of a `width` x `height` field, `3 * sigma`
+/// clamped to the larger dimension; the kernel is truncated there, so very
+/// wide blurs approach a box average of the wrapped field
+pub(crate) fn gaussian_radius(sigma: f32, width: u32, height: u32) -> i32 {
+    ((3.0 * sigma).ceil() as i64).min(width.max(height) as i64) as i32
+}
+
+/// Warn that a blur reaching `radius` pixels is wider than half of a
+/// `width` x `height` field, so its taps wrap around onto the other side
+pub(crate) fn warn_wide_blur(what: &str, radius: i32, width: u32, height: u32) {
+    if radius as i64 > width.min(height) as i64 / 2 {
+        log::warn!("{what} {radius} is more than half of the {width}x{height} field, so the blur wraps around it");
+    }
+}
+
+/// Sharpen a field by adding back what a Gaussian blur takes away
+///
+/// Every sample becomes `field + amount * (field - gaussian_blur(field,
+/// sigma))`, clamped to 0-1, so edges gain a dark and a bright rim and blur
+/// steps regain some crispness. Samples differing from the blur by
+/// `threshold` or less are kept, so faint noise is not amplified. The blur
+/// wraps around the edges, so tileable fields stay tileable.
+///
+/// # Arguments
+///
+/// * `field` - The 0-1 field to sharpen
+/// * `sigma` - The standard deviation of the blur in pixels, the width of the
+///   rims
+/// * `amount` - How strongly the details are amplified, 0 to keep the field
+/// * `threshold` - The difference from the blur up to which samples are kept
+///
+/// # Panics
+///
+/// Panics if `sigma` or `threshold` is negative or any is not finite.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::filter::unsharp_mask;
+/// use cells::FieldBuffer;
+///
+/// let step = FieldBuffer::from_par_fn(32, 4, |x, _| if (8..24).contains(&x) { 0.75 } else { 0.25 });
+/// let sharp = unsharp_mask(&step, 1.0, 1.0, 0.01);
+/// // The step overshoots on both sides, away from it the threshold keeps the field
+/// assert!(sharp.get(8, 0) > 0.75 && sharp.get(7, 0) < 0.25);
+/// assert_eq!((sharp.get(16, 0), sharp.get(0, 0)), (0.75, 0.25));
+/// ```
+pub fn unsharp_mask(field: &FieldBuffer, sigma: f32, amount: f32, threshold: f32) -> FieldBuffer {
+    assert!(amount.is_finite(), "the sharpening amount must be finite, got {amount}");
+    assert!(
+        threshold.is_finite() && threshold >= 0.0,
+        "the sharpening threshold must be non-negative, got {threshold}"
+    );
+    let blurred = gaussian_blur(field, sigma);
+    let (width, height) = field.dimensions();
+    FieldBuffer::from_par_fn(width, height, |x, y| {
+        let value = field.get(x, y);
+        let detail = value - blurred.get(x, y);
+        if detail.abs() <= threshold {
+            value
+        } else {
+            (value + amount * detail).clamp(0.0, 1.0)
+        }
+    })
+}
+
+/// The settings of an [`unsharp_mask`], parsed from `SIGMA,AMOUNT` or
+/// `SIGMA,AMOUNT,THRESHOLD`
+///
+/// # Example
+///
+/// ```rust
+/// use cells::filter::Sharpen;
+///
+/// let sharpen: Sharpen = "2,0.8".parse().unwrap();
+/// assert_eq!(sharpen, Sharpen { sigma: 2.0, amount: 0.8, threshold: 0.0 });
+/// assert_eq!(sharpen.to_string(), "2,0.8,0");
+/// assert!("2".parse::<Sharpen>().is_err());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sharpen {
+    /// The standard deviation of the blur in pixels
+    pub sigma: f32,
+    /// How strongly the details are amplified
+    pub amount: f32,
+    /// The difference from the blur up to which samples are kept
+    pub threshold: f32,
+}
+
+impl Sharpen {
+    /// Sharpen `field`
+    pub fn apply(&self, field: &FieldBuffer) -> FieldBuffer {
+        unsharp_mask(field, self.sigma, self.amount, self.threshold)
+    }
+}
+
+impl fmt::Display for Sharpen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{},{}", self.sigma, self.amount, self.threshold)
+    }
+}
+
+impl FromStr for Sharpen {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("expected SIGMA,AMOUNT or SIGMA,AMOUNT,THRESHOLD such as 2,0.8, got `{s}`");
+        let values = s
+            .split(',')
+            .map(|v| v.trim().parse::<f32>().ok().filter(|v| v.is_finite()))
+            .collect::<Option<Vec<f32>>>()
+            .ok_or_else(invalid)?;
+        let (sigma, amount, threshold) = match values[..] {
+            [sigma, amount] => (sigma, amount, 0.0),
+            [sigma, amount, threshold] => (sigma, amount, threshold),
+            _ => return Err(invalid()),
+        };
+        if sigma < 0.0 || threshold < 0.0 {
+            return Err(format!("the sharpening sigma and threshold must be at least 0, got `{s}`"));
+        }
+        Ok(Sharpen { sigma, amount, threshold })
+    }
+}
+
+/// How a field is brought into the 0-1 range between blur steps
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Normalization {
+    /// Stretch the minimum to 0 and the maximum to 1, see [`normalize_field`]
+    #[default]
+    Stretch,
+    /// Stretch between two percentiles, clipping the outliers beyond them,
+    /// see [`normalize_percentile`]
+    Percentile {
+        /// The percentile mapped to 0
+        low: f32,
+        /// The percentile mapped to 1
+        high: f32,
+    },
+    /// Flatten the histogram, see [`equalize_histogram`]
+    Equalize,
+    /// Keep the values as they are
+    None,
+}
+
+impl Normalization {
+    /// Normalize `field` this way
+    pub fn apply(self, field: &FieldBuffer) -> FieldBuffer {
+        match self {
+            Normalization::Stretch => normalize_field(field),
+            Normalization::Percentile { low, high } => normalize_percentile(field, low, high),
+            Normalization::Equalize => equalize_histogram(field),
+            Normalization::None => field.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Normalization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Normalization::Stretch => write!(f, "stretch"),
+            Normalization::Percentile { low, high } => write!(f, "clip:{low},{high}"),
+            Normalization::Equalize => write!(f, "equalize"),
+            Normalization::None => write!(f, "none"),
+        }
+    }
+}
+
+impl FromStr for Normalization {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "stretch" => Ok(Normalization::Stretch),
+            None if s == "equalize" => Ok(Normalization::Equalize),
+            None if s == "none" => Ok(Normalization::None),
+            Some(("clip", percentiles)) => {
+                let (low, high) = parse_percentiles(percentiles)?;
+                Ok(Normalization::Percentile { low, high })
+            }
+            _ => Err(format!(
+                "unknown normalization `{s}`, expected stretch, clip:<low>,<high>, equalize or none"
+            )),
+        }
+    }
+}
+
+/// Parse `LOW,HIGH` percentiles such as `1,99`, with `0 <= LOW < HIGH <= 100`
+///
+/// # Example
+///
+/// ```rust
+/// use cells::filter::parse_percentiles;
+///
+/// assert_eq!(parse_percentiles("1,99"), Ok((1.0, 99.0)));
+/// assert!(parse_percentiles("99,1").is_err());
+/// ```
+pub fn parse_percentiles(s: &str) -> Result<(f32, f32), String> {
+    let invalid = || format!("expected LOW,HIGH percentiles such as 1,99, got `{s}`");
+    let (low, high) = s.split_once(',').ok_or_else(invalid)?;
+    let low: f32 = low.trim().parse().map_err(|_| invalid())?;
+    let high: f32 = high.trim().parse().map_err(|_| invalid())?;
+    if !(0.0 <= low && low < high && high <= 100.0) {
+        return Err(format!("percentiles must satisfy 0 <= low < high <= 100, got {low},{high}"));
+    }
+    Ok((low, high))
+}
+
+/// Number of histogram bins of [`equalize_histogram`] and [`percentile_range`]
+const HISTOGRAM_BINS: usize = 1024;
+
+/// The values at two percentiles of a field
+///
+/// The percentiles are read from a histogram of the samples, refined once
+/// inside the bin holding each of them, so this stays O(width * height)
+/// while resolving 1/1024^2 of the value range even when a few outliers
+/// stretch it.
+///
+/// # Arguments
+///
+/// * `field` - The field to measure
+/// * `low_pct` - The lower percentile, from 0 to 100
+/// * `high_pct` - The upper percentile, from 0 to 100
+///
+/// # Returns
+///
+/// The values below which `low_pct` and `high_pct` percent of the samples
+/// lie, or `None` for an empty field
+///
+/// # Panics
+///
+/// Panics unless `0 <= low_pct <= high_pct <= 100`.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::filter::percentile_range;
+/// use cells::FieldBuffer;
+///
+/// let mut ramp = FieldBuffer::from_par_fn(101, 1, |x, _| x as f32 / 100.0);
+/// ramp.as_mut_slice()[100] = 1000.0;
+/// let (low, high) = percentile_range(&ramp, 10.0, 90.0).unwrap();
+/// assert!((low - 0.1).abs() < 1e-3 && (high - 0.9).abs() < 1e-3);
+/// ```
+pub fn percentile_range(field: &FieldBuffer, low_pct: f32, high_pct: f32) -> Option<(f32, f32)> {
+    assert!(
+        0.0 <= low_pct && low_pct <= high_pct && high_pct <= 100.0,
+        "percentiles must satisfy 0 <= low <= high <= 100, got {low_pct} and {high_pct}"
+    );
+    let (min_value, max_value) = field.min_max()?;
+    let samples = field.as_slice();
+    let width = max_value - min_value;
+    let quantile = |pct: f32| {
+        if width <= 0.0 {
+            return min_value;
+        }
+        // The fractional number of samples below the percentile
+        let rank = pct / 100.0 * (samples.len() - 1) as f32;
+        let (coarse, below, _) = rank_bin(samples.iter().copied(), min_value, width, rank);
+        // Refine inside the coarse bin
+        let coarse_width = width / HISTOGRAM_BINS as f32;
+        let coarse_low = min_value + coarse as f32 * coarse_width;
+        let in_coarse = samples.iter().copied().filter(|&v| histogram_bin(v, min_value, width) == coarse);
+        let (fine, fine_below, count) = rank_bin(in_coarse, coarse_low, coarse_width, rank - below as f32);
+        let within = (rank - (below + fine_below) as f32 + 0.5) / count as f32;
+        coarse_low + (fine as f32 + within.min(1.0)) * coarse_width / HISTOGRAM_BINS as f32
+    };
+    Some((quantile(low_pct), quantile(high_pct)))
+}
+
+/// The bin of `value` in a histogram over `low..low + width`
+fn histogram_bin(value: f32, low: f32, width: f32) -> usize {
+    (((value - low) / width * HISTOGRAM_BINS as f32) as usize).min(HISTOGRAM_BINS - 1)
+}
+
+/// The histogram bin holding the sample of fractional `rank` in sorted order,
+/// the number of samples in the bins before it, and the number in it
+fn rank_bin(samples: impl Iterator<Item = f32>, low: f32, width: f32, rank: f32) -> (usize, usize, usize) {
+    let mut histogram = [0usize; HISTOGRAM_BINS];
+    for value in samples {
+        histogram[histogram_bin(value, low, width)] += 1;
+    }
+    let mut below = 0;
+    for (bin, &count) in histogram.iter().enumerate() {
+        if count > 0 && (below + count) as f32 > rank {
+            return (bin, below, count);
+        }
+        below += count;
+    }
+    // Rounding put the rank past the last sample, which is in the last non-empty bin
+    let bin = histogram.iter().rposition(|&count| count > 0).unwrap_or(0);
+    (bin, below - histogram[bin], histogram[bin])
+}
+
+/// Stretch a field between two percentiles, clipping the outliers beyond
+///
+/// A few extreme samples, e.g. the pixels around one very distant point,
+/// dominate a min-max stretch and crush the contrast of all other samples.
+/// This maps the value at `low_pct` to 0 and the value at `high_pct` to 1
+/// instead, clamping the samples outside to 0-1. Fields whose percentiles
+/// are equal are returned unchanged.
+///
+/// # Arguments
+///
+/// * `field` - The input field to be normalized
+/// * `low_pct` - The percentile mapped to 0, e.g. 1
+/// * `high_pct` - The percentile mapped to 1, e.g. 99
+///
+/// # Returns
+///
+/// A `FieldBuffer` containing the normalized field
+///
+/// # Panics
+///
+/// Panics unless `0 <= low_pct <= high_pct <= 100`.
+///
+/// # Performance
+///
+/// This function has O(width * height) complexity, see [`percentile_range`].
+///
+/// # Example
+///
+/// A single outlier barely changes the percentile stretch, but ruins the
+/// min-max stretch:
+///
+/// ```rust
+/// use cells::filter::{normalize_field, normalize_percentile};
+/// use cells::voronoi::{generate_voronoi_field, VoronoiParams};
+/// use cells::FieldBuffer;
+///
+/// let voronoi = generate_voronoi_field(&VoronoiParams { size: 64, num_points: 20, ..Default::default() });
+/// let mut outlier = voronoi.clone();
+/// outlier.as_mut_slice()[0] = 10.0;
+/// let mean_change = |a: &FieldBuffer, b: &FieldBuffer| {
+///     a.as_slice().iter().zip(b.as_slice()).skip(1).map(|(a, b)| (a - b).abs()).sum::<f32>() / 4095.0
+/// };
+/// let clip = |field| normalize_percentile(field, 1.0, 99.0);
+/// assert!(mean_change(&clip(&voronoi), &clip(&outlier)) < 0.01);
+/// assert!(mean_change(&normalize_field(&voronoi), &normalize_field(&outlier)) > 0.2);
+/// ```
+pub fn normalize_percentile(field: &FieldBuffer, low_pct: f32, high_pct: f32) -> FieldBuffer {
+    match percentile_range(field, low_pct, high_pct) {
+        Some((low, high)) if high > low => field.map(|value| ((value - low) / (high - low)).clamp(0.0, 1.0)),
+        _ => field.clone(),
+    }
+}
+
+/// Remap a field so its values are spread evenly over the 0-1 range
+///
+/// Min-max stretching keeps the shape of the histogram, so a field whose
+/// values crowd together stays low in contrast. Equalization instead maps
+/// every value to the fraction of samples below it, from a 1024-bin
+/// cumulative histogram interpolated within the bins, so the output
+/// histogram is approximately flat. The order of values is kept and constant
+/// fields are returned unchanged.
+///
+/// # Arguments
+///
+/// * `field` - The input field to be equalized
+///
+/// # Returns
+///
+/// A `FieldBuffer` containing the equalized field, from 0 to 1
+///
+/// # Performance
+///
+/// This function has O(width * height) complexity.
+///
+/// # Example
+///
+/// The skewed blurred Voronoi ends up with evenly filled histogram bins:
+///
+/// ```rust
+/// use cells::filter::{directional_blur_field, equalize_histogram};
+/// use cells::voronoi::{generate_voronoi_field, VoronoiParams};
+///
+/// let voronoi = generate_voronoi_field(&VoronoiParams { size: 128, num_points: 40, ..Default::default() });
+/// let blurred = directional_blur_field(&voronoi, &voronoi, 6);
+/// let mut bins = [0u32; 16];
+/// for &v in equalize_histogram(&blurred).as_slice() {
+///     bins[((v * 16.0) as usize).min(15)] += 1;
+/// }
+/// let (fewest, most) = (bins.iter().min().unwrap(), bins.iter().max().unwrap());
+/// assert!(*most < 2 * fewest);
+/// ```
+pub fn equalize_histogram(field: &FieldBuffer) -> FieldBuffer {
+    let (min_value, max_value) = match field.min_max() {
+        Some((min_value, max_value)) if max_value > min_value => (min_value, max_value),
+        _ => return field.clone(),
+    };
+    let bins = HISTOGRAM_BINS as f32;
+    let position = |value: f32| ((value - min_value) / (max_value - min_value) * bins).clamp(0.0, bins);
+    let bin = |position: f32| (position as usize).min(HISTOGRAM_BINS - 1);
+
+    let mut histogram = [0u32; HISTOGRAM_BINS];
+    for &value in field.as_slice() {
+        histogram[bin(position(value))] += 1;
+    }
+    // below[b] is the number of samples in the bins before b
+    let mut below = [0u32; HISTOGRAM_BINS];
+    for b in 1..HISTOGRAM_BINS {
+        below[b] = below[b - 1] + histogram[b - 1];
+    }
+    let total = field.as_slice().len() as f32;
+    field.map(|value| {
+        let position = position(value);
+        let b = bin(position);
+        (below[b] as f32 + (position - b as f32) * histogram[b] as f32) / total
+    })
+}
+
+/// Normalize a field to use the full 0-1 range
+///
+/// This function adjusts the values of the input field to span the full 0-1
+/// range, improving contrast. Constant fields are returned unchanged.
+///
+/// # Algorithm
+///
+/// 1. Find the minimum and maximum values in the input field
+/// 2. For each sample apply the formula: new_value = (old_value - min) / (max - min)
+///
+/// # Arguments
+///
+/// * `field` - The input field to be normalized
+///
+/// # Returns
+///
+/// A `FieldBuffer` containing the normalized field
+///
+/// # Performance
+///
+/// This function has O(width * height) complexity. The min/max scan is a
+/// parallel reduce and the samples are mapped in parallel with rayon.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::filter::normalize_field;
+/// use cells::FieldBuffer;
+///
+/// let field = FieldBuffer::from_vec(3, 1, vec![0.25, 0.5, 0.75]);
+/// assert_eq!(normalize_field(&field).as_slice(), &[0.0, 0.5, 1.0]);
+/// ```
+pub fn normalize_field(field: &FieldBuffer) -> FieldBuffer {
+    match field.min_max() {
+        Some((min_value, max_value)) if max_value > min_value => {
+            field.map(|value| (value - min_value) / (max_value - min_value))
+        }
+        _ => field.clone(),
+    }
+}
+
+/// Normalize an image to use the full 0-255 range
+///
+/// This is [`normalize_image_with`] stretching every color channel on its
+/// own, [`ChannelNormalization::PerChannel`].
+///
+/// # Arguments
+///
+/// * `img` - The input image to be normalized
+///
+/// # Returns
+///
+/// An `ImageBuffer` containing the normalized image
+///
+/// # Example
+///
+/// ```rust
+/// use cells::filter::normalize_image;
+/// use cells::noise::{generate_perlin_noise, FbmParams};
+///
+/// let input_image = generate_perlin_noise(64, &FbmParams { frequency: 4.0, seed: 1, ..Default::default() });
+/// let normalized_image = normalize_image(&input_image);
+/// let values = normalized_image.pixels().map(|p| p[0]);
+/// assert_eq!(values.clone().min(), Some(0));
+/// assert_eq!(values.max(), Some(255));
+/// ```
+pub fn normalize_image<P: Pixel<Subpixel = u8>>(img: &ImageBuffer<P, Vec<u8>>) -> ImageBuffer<P, Vec<u8>> {
+    normalize_image_with(img, ChannelNormalization::PerChannel)
+}
+
+/// How [`normalize_image_with`] stretches the color channels of an image
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChannelNormalization {
+    /// Stretch every channel to 0-1 on its own, see [`normalize_field`];
+    /// this shifts the hues of colored images
+    #[default]
+    PerChannel,
+    /// Stretch all channels alike, so the Rec. 709 luminance of RGB images
+    /// spans 0-1 and the differences between the channels keep their
+    /// proportions up to clipping
+    Luminance,
+}
+
+impl fmt::Display for ChannelNormalization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChannelNormalization::PerChannel => write!(f, "per-channel"),
+            ChannelNormalization::Luminance => write!(f, "luminance"),
+        }
+    }
+}
+
+impl FromStr for ChannelNormalization {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "per-channel" => Ok(ChannelNormalization::PerChannel),
+            "luminance" => Ok(ChannelNormalization::Luminance),
+            _ => Err(format!("unknown channel normalization `{s}`, expected per-channel or luminance")),
+        }
+    }
+}
+
+/// Normalize the color channels of an image to use the full 0-255 range
+///
+/// Alpha is copied unchanged, and grayscale images normalize alike either
+/// way.
+///
+/// # Arguments
+///
+/// * `img` - The input image to be normalized
+/// * `mode` - Whether the channels are stretched on their own or together
+///
+/// # Returns
+///
+/// An `ImageBuffer` containing the normalized image
+///
+/// # Example
+///
+/// ```rust
+/// use cells::filter::{normalize_image_with, ChannelNormalization};
+/// use image::{Rgba, RgbaImage};
+///
+/// let img = RgbaImage::from_fn(2, 1, |x, _| Rgba([50 + 50 * x as u8, 100, 20 + 20 * x as u8, 7]));
+/// let per_channel = normalize_image_with(&img, ChannelNormalization::PerChannel);
+/// assert_eq!(per_channel.get_pixel(0, 0).0, [0, 100, 0, 7]);
+/// assert_eq!(per_channel.get_pixel(1, 0).0, [255, 100, 255, 7]);
+/// // Luminance keeps red above blue
+/// let luminance = normalize_image_with(&img, ChannelNormalization::Luminance);
+/// let [red, _, blue, alpha] = luminance.get_pixel(1, 0).0;
+/// assert!(red > blue && alpha == 7);
+/// ```
+pub fn normalize_image_with<P>(img: &ImageBuffer<P, Vec<u8>>, mode: ChannelNormalization) -> ImageBuffer<P, Vec<u8>>
+where
+    P: Pixel<Subpixel = u8>,
+{
+    if mode == ChannelNormalization::PerChannel || color_channels::<P>() != 3 {
+        return map_color_channels(img, normalize_field);
+    }
+    let (width, stride, raw) = (img.width(), P::CHANNEL_COUNT as usize, img.as_raw());
+    let luminance = FieldBuffer::from_par_fn(width, img.height(), |x, y| {
+        let pixel = &raw[(y * width + x) as usize * stride..];
+        (0.2126 * pixel[0] as f32 + 0.7152 * pixel[1] as f32 + 0.0722 * pixel[2] as f32) / 255.0
+    });
+    match luminance.min_max() {
+        Some((min, max)) if max > min => map_color_channels(img, |field| field.map(|v| (v - min) / (max - min))),
+        _ => img.clone(),
+    }
+}
@@ -0,0 +1,371 @@
+//! Vector fields derived from scalar fields, blurs that follow them, and
+//! warps that displace fields along them.
+//!
+//! Using a field's values directly as blur angles jumps wherever the values
+//! jump, e.g. at Voronoi cell borders. The flow of a smooth field, its
+//! gradient turned by 90 degrees, changes direction gradually and never
+//! converges or diverges, so streaks traced along it curve smoothly like line
+//! integral convolution.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::field::FieldBuffer;
+use crate::filter::{normalize_percentile, BlurParams, BlurSampling, DirectionMapping};
+
+/// Below this length a flow vector has no usable direction
+const MIN_FLOW: f32 = 1e-12;
+
+/// The gradient of a field by central differences, wrapping around the edges
+///
+/// # Arguments
+///
+/// * `field` - The field to differentiate
+///
+/// # Returns
+///
+/// The change per sample along x and along y; tileable fields give tileable
+/// gradients
+///
+/// # Example
+///
+/// ```rust
+/// use cells::flow::gradient;
+/// use cells::FieldBuffer;
+///
+/// let ramp = FieldBuffer::from_par_fn(8, 8, |x, y| (x + 2 * y) as f32);
+/// let (dx, dy) = gradient(&ramp);
+/// assert_eq!((dx.get(3, 3), dy.get(3, 3)), (1.0, 2.0));
+/// // The ramp falls back to 0 across the wrap-around edge
+/// assert_eq!(dx.get(0, 3), -3.0);
+/// ```
+pub fn gradient(field: &FieldBuffer) -> (FieldBuffer, FieldBuffer) {
+    let (width, height) = field.dimensions();
+    let difference = |x: u32, y: u32, dx: i64, dy: i64| {
+        let (x, y) = (x as i64, y as i64);
+        (field.get_wrapped(x + dx, y + dy) - field.get_wrapped(x - dx, y - dy)) / 2.0
+    };
+    (
+        FieldBuffer::from_par_fn(width, height, |x, y| difference(x, y, 1, 0)),
+        FieldBuffer::from_par_fn(width, height, |x, y| difference(x, y, 0, 1)),
+    )
+}
+
+/// The divergence-free flow along the contour lines of a field
+///
+/// This is the [`gradient`] rotated by 90 degrees, `(-dy, dx)`, so the flow
+/// runs along lines of equal value instead of across them.
+///
+/// # Arguments
+///
+/// * `field` - The field whose contours the flow follows
+///
+/// # Returns
+///
+/// The x and y components of the flow
+///
+/// # Example
+///
+/// ```rust
+/// use cells::flow::curl_flow;
+/// use cells::FieldBuffer;
+///
+/// // Values grow along y, so the flow runs along x
+/// let ramp = FieldBuffer::from_par_fn(8, 8, |_, y| y as f32);
+/// let (flow_x, flow_y) = curl_flow(&ramp);
+/// assert_eq!((flow_x.get(4, 4), flow_y.get(4, 4)), (-1.0, 0.0));
+/// ```
+pub fn curl_flow(field: &FieldBuffer) -> (FieldBuffer, FieldBuffer) {
+    let (dx, dy) = gradient(field);
+    (dy.map(|v| -v), dx)
+}
+
+/// What a Sobel node outputs, see [`sobel`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SobelOutput {
+    /// The edge strength
+    #[default]
+    Magnitude,
+    /// The direction across the edges
+    Direction,
+    /// The direction along the edges, a quarter turn from `Direction`
+    EdgeDirection,
+}
+
+impl fmt::Display for SobelOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SobelOutput::Magnitude => "magnitude",
+            SobelOutput::Direction => "direction",
+            SobelOutput::EdgeDirection => "edge-direction",
+        })
+    }
+}
+
+impl FromStr for SobelOutput {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "magnitude" => Ok(SobelOutput::Magnitude),
+            "direction" => Ok(SobelOutput::Direction),
+            "edge-direction" => Ok(SobelOutput::EdgeDirection),
+            _ => Err(format!(
+                "unknown Sobel output `{s}`, expected magnitude, direction or edge-direction"
+            )),
+        }
+    }
+}
+
+/// Detect edges with the 3x3 Sobel operator, wrapping around the edges
+///
+/// The magnitude is the length of the Sobel gradient, stretched between its
+/// 1st and 99th percentiles so a few sharp steps don't crush the rest. The
+/// direction is the angle of the gradient, uphill across the edges, encoded
+/// 0 to 1 over a full turn like [`DirectionMapping::Full360`]: feeding it to
+/// the directional blur as the direction map smears across the edges, and
+/// adding a quarter turn (or reading [`SobelOutput::EdgeDirection`]) blurs
+/// along them. Flat areas read 0.
+///
+/// [`DirectionMapping::Full360`]: crate::filter::DirectionMapping::Full360
+///
+/// # Arguments
+///
+/// * `field` - The field to detect edges in
+///
+/// # Returns
+///
+/// The magnitude from 0 to 1, and the direction
+///
+/// # Example
+///
+/// ```rust
+/// use cells::flow::sobel;
+/// use cells::FieldBuffer;
+///
+/// // A ramp up along y has its gradient pointing down the texture
+/// let ramp = FieldBuffer::from_par_fn(8, 8, |_, y| (y as f32 - 3.5).abs());
+/// let (magnitude, direction) = sobel(&ramp);
+/// assert_eq!((magnitude.get(2, 6), direction.get(2, 6)), (1.0, 0.25));
+/// assert_eq!(direction.get(2, 1), 0.75);
+/// ```
+pub fn sobel(field: &FieldBuffer) -> (FieldBuffer, FieldBuffer) {
+    let (width, height) = field.dimensions();
+    let gradient = |x: u32, y: u32| {
+        let (x, y) = (x as i64, y as i64);
+        let at = |dx: i64, dy: i64| field.get_wrapped(x + dx, y + dy);
+        let gx = (at(1, -1) + 2.0 * at(1, 0) + at(1, 1)) - (at(-1, -1) + 2.0 * at(-1, 0) + at(-1, 1));
+        let gy = (at(-1, 1) + 2.0 * at(0, 1) + at(1, 1)) - (at(-1, -1) + 2.0 * at(0, -1) + at(1, -1));
+        (gx / 8.0, gy / 8.0)
+    };
+    let magnitude = FieldBuffer::from_par_fn(width, height, |x, y| {
+        let (gx, gy) = gradient(x, y);
+        gx.hypot(gy)
+    });
+    let direction = FieldBuffer::from_par_fn(width, height, |x, y| {
+        let (gx, gy) = gradient(x, y);
+        // The blur steps by (cos, sin) in pixels, so y points down here too
+        (gy.atan2(gx).to_degrees() / 360.0).rem_euclid(1.0)
+    });
+    (normalize_percentile(&magnitude, 1.0, 99.0), direction)
+}
+
+/// Blur a field along the streamlines of a vector field
+///
+/// Instead of a straight line, the taps of every sample are traced step by
+/// step through the flow: each step moves one pixel along the normalized flow
+/// at the current position, read bilinearly and wrapping around the edges.
+/// The taps are weighted by the kernel of `params`, and with
+/// [`DirectionMapping::SignedFlow`](crate::filter::DirectionMapping::SignedFlow)
+/// only the forward half of the streamline is traced. Where the flow vanishes
+/// the trace stops and the remaining taps read its last position.
+///
+/// # Arguments
+///
+/// * `field` - The input field to be blurred
+/// * `flow_x` - The x components of the flow
+/// * `flow_y` - The y components of the flow
+/// * `params` - The radius, sampling, kernel and direction mapping of the blur
+///
+/// # Returns
+///
+/// A `FieldBuffer` containing the blurred field, tileable when the input and
+/// the flow are
+///
+/// # Panics
+///
+/// Panics if the flow components do not have the dimensions of `field`.
+///
+/// # Performance
+///
+/// This function has O(width * height * radius) complexity, with two extra
+/// bilinear reads of the flow for every tap. Rows are processed in parallel
+/// with rayon.
+///
+/// # Example
+///
+/// A uniform flow along x blurs exactly like the straight directional blur:
+///
+/// ```rust
+/// use cells::filter::{directional_blur_field_with, BlurParams};
+/// use cells::flow::flow_blur_field;
+/// use cells::voronoi::{generate_voronoi_field, VoronoiParams};
+/// use cells::FieldBuffer;
+///
+/// let voronoi = generate_voronoi_field(&VoronoiParams { size: 32, num_points: 8, ..Default::default() });
+/// let (flow_x, flow_y) = (FieldBuffer::from_par_fn(32, 32, |_, _| 2.0), FieldBuffer::new(32, 32));
+/// let params = BlurParams { radius: 4, ..Default::default() };
+/// let traced = flow_blur_field(&voronoi, &flow_x, &flow_y, &params);
+/// let straight = directional_blur_field_with(&voronoi, &FieldBuffer::new(32, 32), &params);
+/// let difference = traced.as_slice().iter().zip(straight.as_slice()).map(|(a, b)| (a - b).abs());
+/// assert!(difference.fold(0.0, f32::max) < 1e-6);
+/// ```
+pub fn flow_blur_field(
+    field: &FieldBuffer,
+    flow_x: &FieldBuffer,
+    flow_y: &FieldBuffer,
+    params: &BlurParams,
+) -> FieldBuffer {
+    flow_blur_field_with_progress(field, flow_x, flow_y, params, |_| {})
+}
+
+/// Blur a field along the streamlines of a flow field, reporting progress
+///
+/// This is [`flow_blur_field`] calling `progress` with the finished share of
+/// the rows, from 0 to 1, as they complete.
+///
+/// # Panics
+///
+/// Panics if the flow components do not have the dimensions of `field`.
+pub fn flow_blur_field_with_progress(
+    field: &FieldBuffer,
+    flow_x: &FieldBuffer,
+    flow_y: &FieldBuffer,
+    params: &BlurParams,
+    progress: impl FnMut(f32) + Send,
+) -> FieldBuffer {
+    let (width, height) = field.dimensions();
+    assert!(
+        flow_x.dimensions() == field.dimensions() && flow_y.dimensions() == field.dimensions(),
+        "the flow must have the dimensions of the field"
+    );
+    let blur_radius = params.radius;
+    let weights = params.kernel.raw_weights(blur_radius);
+    let first_tap = params.mapping.first_tap(blur_radius);
+    let total_weight: f32 = weights[(first_tap + blur_radius) as usize..].iter().sum();
+    let read = |x: f32, y: f32| match params.sampling {
+        BlurSampling::Bilinear => field.sample_bilinear_wrapped(x, y),
+        BlurSampling::Nearest => field.get_wrapped(x.round() as i64, y.round() as i64),
+    };
+    // Weighted sum of the taps along one half of the streamline, excluding the
+    // center, following the flow forwards or backwards
+    let trace = |x: f32, y: f32, sign: f32, taps: i32, weight: &dyn Fn(i32) -> f32| {
+        let (mut x, mut y) = (x, y);
+        let mut sum = 0.0;
+        for i in 1..=taps {
+            let (vx, vy) = (flow_x.sample_bilinear_wrapped(x, y), flow_y.sample_bilinear_wrapped(x, y));
+            let length = vx.hypot(vy);
+            if length > MIN_FLOW {
+                (x, y) = (x + sign * vx / length, y + sign * vy / length);
+            }
+            sum += weight(i) * read(x, y);
+        }
+        sum
+    };
+    let weight = |i: i32| weights[(i + blur_radius) as usize];
+
+    let blur = |x: u32, y: u32| {
+        let (x, y) = (x as f32, y as f32);
+        let forward = trace(x, y, 1.0, blur_radius, &weight);
+        let backward = trace(x, y, -1.0, -first_tap, &|i| weight(-i));
+        (weight(0) * read(x, y) + forward + backward) / total_weight
+    };
+    FieldBuffer::from_par_fn_with_progress(width, height, blur, progress)
+}
+
+/// Displace a field along a vector field, e.g. to melt crisp Voronoi cells
+/// into organic shapes with offsets derived from noise
+///
+/// The sample at `(x, y)` is read from `(x + strength * dx, y + strength *
+/// dy)`, bilinearly interpolated and wrapped around the edges, so tileable
+/// inputs and offsets give tileable results. Unlike a blur, every sample moves
+/// as a whole and the values keep their sharpness.
+///
+/// # Arguments
+///
+/// * `field` - The field to displace
+/// * `dx` - The x offsets, in pixels before scaling
+/// * `dy` - The y offsets, in pixels before scaling
+/// * `strength` - The factor of the offsets, 0 returns the input unchanged
+///
+/// # Panics
+///
+/// Panics if the offsets do not have the dimensions of `field`.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::flow::{curl_flow, warp};
+/// use cells::noise::{generate_perlin_field, FbmParams};
+/// use cells::voronoi::{generate_voronoi_field, VoronoiParams};
+///
+/// let cells = generate_voronoi_field(&VoronoiParams { size: 64, num_points: 12, ..Default::default() });
+/// let noise = generate_perlin_field(64, &FbmParams { frequency: 3.0, ..Default::default() });
+/// let (dx, dy) = curl_flow(&noise);
+/// let melted = warp(&cells, &dx, &dy, 40.0);
+/// assert_ne!(melted, cells);
+/// assert_eq!(warp(&cells, &dx, &dy, 0.0), cells);
+/// ```
+pub fn warp(field: &FieldBuffer, dx: &FieldBuffer, dy: &FieldBuffer, strength: f32) -> FieldBuffer {
+    let (width, height) = field.dimensions();
+    assert!(
+        dx.dimensions() == field.dimensions() && dy.dimensions() == field.dimensions(),
+        "the offsets must have the dimensions of the field"
+    );
+    let details = format_args!("{width}x{height}, strength {strength}");
+    crate::stage("warp", details, || {
+        FieldBuffer::from_par_fn(width, height, |x, y| {
+            let source_x = x as f32 + strength * dx.get(x, y);
+            let source_y = y as f32 + strength * dy.get(x, y);
+            field.sample_bilinear_wrapped(source_x, source_y)
+        })
+    })
+}
+
+/// The offsets of an offset map as images store them, 0 to 1 with 0.5
+/// meaning no offset, as -1 to 1 for [`warp`]
+///
+/// # Example
+///
+/// ```rust
+/// use cells::flow::signed_offsets;
+/// use cells::FieldBuffer;
+///
+/// let map = FieldBuffer::from_vec(3, 1, vec![0.0, 0.5, 1.0]);
+/// assert_eq!(signed_offsets(&map).as_slice(), [-1.0, 0.0, 1.0]);
+/// ```
+pub fn signed_offsets(map: &FieldBuffer) -> FieldBuffer {
+    map.map(|value| 2.0 * value - 1.0)
+}
+
+/// Unit offsets for [`warp`] pointing along the angles of a map, 0 to 1 over
+/// a full turn like [`DirectionMapping::Full360`]
+///
+/// # Returns
+///
+/// The x and y offsets, every pair one pixel long
+///
+/// # Example
+///
+/// ```rust
+/// use cells::flow::angle_offsets;
+/// use cells::FieldBuffer;
+///
+/// let (dx, dy) = angle_offsets(&FieldBuffer::from_vec(2, 1, vec![0.0, 0.25]));
+/// assert_eq!((dx.get(0, 0), dy.get(0, 0)), (1.0, 0.0));
+/// assert!(dx.get(1, 0).abs() < 1e-6 && (dy.get(1, 0) - 1.0).abs() < 1e-6);
+/// ```
+pub fn angle_offsets(angles: &FieldBuffer) -> (FieldBuffer, FieldBuffer) {
+    let step = |angle| DirectionMapping::Full360.step(angle);
+    (angles.map(|angle| step(angle).0), angles.map(|angle| step(angle).1))
+}
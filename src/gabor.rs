@@ -0,0 +1,249 @@
+//! Sparse Gabor noise: oriented, band-limited textures such as brushed metal
+//! or fabric.
+//!
+//! The noise is a sum of randomly placed Gabor kernels, cosine waves under a
+//! Gaussian window. Every kernel is evaluated at its nearest wrapped copy, so
+//! the noise tiles, and kernels are bucketed into a grid of cells no smaller
+//! than their radius, so every sample only visits the kernels nearby.
+
+use std::f32::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::error::CellsError;
+use crate::field::FieldBuffer;
+use crate::filter::normalize_field;
+
+/// How the waves of the kernels are oriented
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum GaborOrientation {
+    /// Uniformly random angles, giving noise without a direction
+    #[default]
+    Isotropic,
+    /// Waves travelling at this angle in degrees, counterclockwise from the
+    /// x axis, give streaks across it
+    Fixed(f32),
+}
+
+impl fmt::Display for GaborOrientation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GaborOrientation::Isotropic => write!(f, "isotropic"),
+            GaborOrientation::Fixed(angle) => write!(f, "fixed:{angle}"),
+        }
+    }
+}
+
+impl FromStr for GaborOrientation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "isotropic" => Ok(GaborOrientation::Isotropic),
+            Some(("fixed", angle)) => angle
+                .parse()
+                .ok()
+                .filter(|angle: &f32| angle.is_finite())
+                .map(GaborOrientation::Fixed)
+                .ok_or_else(|| format!("expected an angle in degrees after fixed:, got `{angle}`")),
+            _ => Err(format!("unknown orientation `{s}`, expected isotropic or fixed:<degrees>")),
+        }
+    }
+}
+
+/// Parameters of sparse Gabor noise
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GaborParams {
+    /// Width and height of the texture in pixels
+    pub size: u32,
+    /// Frequency of the waves in cycles across the texture
+    pub frequency: f32,
+    /// Radius of every kernel as a fraction of the texture size, at most 0.5;
+    /// the window falls to `e^-π` there and is cut off
+    pub radius: f32,
+    /// Number of kernels in the texture; on average `kernels * π * radius²`
+    /// of them overlap at every point
+    pub kernels: u32,
+    /// How the kernels are oriented
+    pub orientation: GaborOrientation,
+    /// Random deviation of every kernel from its orientation, up to this many
+    /// degrees either way
+    pub spread: f32,
+}
+
+impl Default for GaborParams {
+    fn default() -> Self {
+        GaborParams {
+            size: 512,
+            frequency: 24.0,
+            radius: 0.06,
+            kernels: 2000,
+            orientation: GaborOrientation::Isotropic,
+            spread: 0.0,
+        }
+    }
+}
+
+impl GaborParams {
+    /// Check that the kernels are finite and fit the texture
+    ///
+    /// # Returns
+    ///
+    /// [`CellsError::InvalidParameter`] if the size or kernel count is 0, the
+    /// frequency or spread is negative or not finite, or the radius is
+    /// outside (0, 0.5]
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::gabor::GaborParams;
+    ///
+    /// let err = GaborParams { radius: 0.75, ..Default::default() }.validate().unwrap_err();
+    /// assert_eq!(err.to_string(), "invalid radius: must be above 0 and at most 0.5, got 0.75");
+    /// ```
+    pub fn validate(&self) -> Result<(), CellsError> {
+        let invalid = |name, reason: String| Err(CellsError::InvalidParameter { name, reason });
+        for (name, value) in [("size", self.size), ("kernels", self.kernels)] {
+            if value == 0 {
+                return invalid(name, "must be above 0".to_string());
+            }
+        }
+        for (name, value) in [("frequency", self.frequency), ("spread", self.spread)] {
+            if !(value.is_finite() && value >= 0.0) {
+                return invalid(name, format!("must be at least 0 and finite, got {value}"));
+            }
+        }
+        if !(self.radius > 0.0 && self.radius <= 0.5) {
+            return invalid("radius", format!("must be above 0 and at most 0.5, got {}", self.radius));
+        }
+        Ok(())
+    }
+}
+
+/// One Gabor kernel
+struct Kernel {
+    x: f32,
+    y: f32,
+    weight: f32,
+    /// Unit direction the wave travels in
+    direction: (f32, f32),
+}
+
+/// Generate tileable Gabor noise
+///
+/// # Arguments
+///
+/// * `params` - The kernels and their orientation
+/// * `seed` - Seed of the kernel positions, weights and angles
+///
+/// # Returns
+///
+/// A `FieldBuffer` normalized to 0-1
+///
+/// # Panics
+///
+/// Panics if the parameters fail [`GaborParams::validate`].
+///
+/// # Example
+///
+/// ```rust
+/// use cells::gabor::{generate_gabor, GaborOrientation, GaborParams};
+/// use cells::tiling::{assert_tileable, interior_discontinuity};
+///
+/// let params = GaborParams {
+///     size: 64,
+///     kernels: 300,
+///     orientation: GaborOrientation::Fixed(90.0),
+///     ..Default::default()
+/// };
+/// let brushed = generate_gabor(&params, 3);
+/// assert_eq!(brushed, generate_gabor(&params, 3));
+/// assert_tileable(&brushed, interior_discontinuity(&brushed));
+/// ```
+pub fn generate_gabor(params: &GaborParams, seed: u64) -> FieldBuffer {
+    gabor(params, seed, None)
+}
+
+/// Generate tileable Gabor noise oriented along a field
+///
+/// Every kernel reads its angle from `orientation` at its center, mapping 0
+/// to 0° and 1 to 180°, e.g. to make streaks follow a flow or the Voronoi
+/// cells. [`GaborParams::orientation`] is ignored while
+/// [`GaborParams::spread`] still jitters the angles; the orientation field
+/// may have any size and is read wrapping around its edges.
+///
+/// # Panics
+///
+/// Panics if the parameters fail [`GaborParams::validate`].
+pub fn generate_gabor_along(params: &GaborParams, seed: u64, orientation: &FieldBuffer) -> FieldBuffer {
+    gabor(params, seed, Some(orientation))
+}
+
+/// Scatter the kernels and sum them at every pixel
+fn gabor(params: &GaborParams, seed: u64, orientation: Option<&FieldBuffer>) -> FieldBuffer {
+    if let Err(err) = params.validate() {
+        panic!("{err}");
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+    let kernels: Vec<Kernel> = (0..params.kernels)
+        .map(|_| {
+            let (x, y): (f32, f32) = (rng.gen(), rng.gen());
+            let weight = rng.gen_range(-1.0..=1.0);
+            let jitter = params.spread * rng.gen_range(-1.0..=1.0);
+            let isotropic = rng.gen_range(0.0..360.0);
+            let angle = match (orientation, params.orientation) {
+                (Some(field), _) => {
+                    let (width, height) = field.dimensions();
+                    field.sample_bilinear_wrapped(x * width as f32, y * height as f32) * 180.0
+                }
+                (None, GaborOrientation::Isotropic) => isotropic,
+                (None, GaborOrientation::Fixed(angle)) => angle,
+            };
+            let (sin, cos) = (angle + jitter).to_radians().sin_cos();
+            // The y axis points down, so counterclockwise angles go up
+            let direction = (cos, -sin);
+            Kernel { x, y, weight, direction }
+        })
+        .collect();
+
+    // Cells at least a radius wide, so the 3x3 cells around a sample hold all
+    // kernels reaching it; fewer than 3 cells across would visit cells twice
+    let cells = match (1.0 / params.radius).floor() as usize {
+        cells if cells >= 3 => cells,
+        _ => 1,
+    };
+    let mut grid: Vec<Vec<Kernel>> = (0..cells * cells).map(|_| Vec::new()).collect();
+    for kernel in kernels {
+        let cell = |c: f32| ((c * cells as f32) as usize).min(cells - 1);
+        grid[cell(kernel.y) * cells + cell(kernel.x)].push(kernel);
+    }
+    let neighbors: &[i64] = if cells >= 3 { &[-1, 0, 1] } else { &[0] };
+
+    let size = params.size as f32;
+    let field = FieldBuffer::from_par_fn(params.size, params.size, |x, y| {
+        let (u, v) = ((x as f32 + 0.5) / size, (y as f32 + 0.5) / size);
+        let (cx, cy) = ((u * cells as f32) as i64, (v * cells as f32) as i64);
+        let mut sum = 0.0;
+        for dy in neighbors {
+            for dx in neighbors {
+                let cell_x = (cx + dx).rem_euclid(cells as i64) as usize;
+                let cell_y = (cy + dy).rem_euclid(cells as i64) as usize;
+                for kernel in &grid[cell_y * cells + cell_x] {
+                    // The offset to the nearest wrapped copy of the kernel
+                    let ox = u - kernel.x - (u - kernel.x).round();
+                    let oy = v - kernel.y - (v - kernel.y).round();
+                    let r2 = (ox * ox + oy * oy) / (params.radius * params.radius);
+                    if r2 < 1.0 {
+                        let phase = 2.0 * PI * params.frequency * (ox * kernel.direction.0 + oy * kernel.direction.1);
+                        sum += kernel.weight * (-PI * r2).exp() * phase.cos();
+                    }
+                }
+            }
+        }
+        sum
+    });
+    normalize_field(&field)
+}
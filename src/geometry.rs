@@ -0,0 +1,331 @@
+//! Points and distances on the wrapping unit square.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// A point in the unit square `[0, 1) x [0, 1)`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Point {
+    /// Wrap the point back into the unit square, as the torus does
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::Point;
+    ///
+    /// let p = Point { x: 1.25, y: -0.25 }.wrapped();
+    /// assert_eq!(p, Point { x: 0.25, y: 0.75 });
+    /// ```
+    pub fn wrapped(self) -> Point {
+        let wrap = |v: f32| {
+            let v = v.rem_euclid(1.0);
+            // rem_euclid rounds tiny negative values up to exactly 1.0
+            if v >= 1.0 {
+                0.0
+            } else {
+                v
+            }
+        };
+        Point {
+            x: wrap(self.x),
+            y: wrap(self.y),
+        }
+    }
+}
+
+/// Calculate the toroidal distance between two points
+///
+/// This function ensures that the distance wraps around the edges of the texture,
+/// creating a seamless, tileable result. Coordinates may lie outside 0-1, so
+/// moving a point by whole textures keeps its distances up to rounding.
+///
+/// # Arguments
+///
+/// * `p1` - The first point
+/// * `p2` - The second point
+///
+/// # Returns
+///
+/// The toroidal distance between the two points
+///
+/// # Example
+///
+/// ```rust
+/// use cells::{toroidal_distance, Point};
+///
+/// let p1 = Point { x: 0.1, y: 0.1 };
+/// let p2 = Point { x: 0.9, y: 0.9 };
+/// let distance = toroidal_distance(p1, p2);
+/// assert!(distance < 0.3); // The wrapped distance should be small
+/// ```
+pub fn toroidal_distance(p1: Point, p2: Point) -> f32 {
+    toroidal_distance_with(p1, p2, DistanceMetric::Euclidean)
+}
+
+/// Calculate the squared toroidal distance between two points
+///
+/// Squared distances order points like the distances themselves, without a
+/// square root per point, so searches compare them and only take the root
+/// of the winner. The root is exactly [`toroidal_distance`].
+///
+/// # Example
+///
+/// ```rust
+/// use cells::geometry::toroidal_distance_sq;
+/// use cells::{toroidal_distance, Point};
+///
+/// let (p1, p2) = (Point { x: 0.1, y: 0.2 }, Point { x: 0.9, y: 0.6 });
+/// assert_eq!(toroidal_distance_sq(p1, p2).sqrt(), toroidal_distance(p1, p2));
+/// ```
+pub fn toroidal_distance_sq(p1: Point, p2: Point) -> f32 {
+    DistanceMetric::Euclidean.toroidal_reduced(p1, p2)
+}
+
+/// The metric used to measure distances between points
+///
+/// Euclidean distances produce the familiar round cells, Manhattan distances
+/// diamond-shaped cells and Chebyshev distances square cells. Minkowski
+/// distances interpolate between them with the exponent `p`: 1 is Manhattan,
+/// 2 is Euclidean and large values approach Chebyshev.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum DistanceMetric {
+    /// Straight-line distance, `sqrt(dx^2 + dy^2)`
+    #[default]
+    Euclidean,
+    /// Taxicab distance, `dx + dy`
+    Manhattan,
+    /// Chessboard distance, `max(dx, dy)`
+    Chebyshev,
+    /// `(dx^p + dy^p)^(1/p)` for an exponent `p >= 1`
+    Minkowski(f32),
+}
+
+impl DistanceMetric {
+    /// The reduced toroidal distance between two points: a value in the same
+    /// order as the distance that is cheaper to compute
+    ///
+    /// Euclidean distances reduce to their square and Minkowski distances to
+    /// the sum of the powers, skipping the final root; Manhattan and Chebyshev
+    /// distances are their own reductions. [`DistanceMetric::expand`] turns
+    /// the reduced distance into [`toroidal_distance_with`], bit for bit.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::geometry::{toroidal_distance_with, DistanceMetric};
+    /// use cells::Point;
+    ///
+    /// let (p1, p2) = (Point { x: 0.05, y: 0.5 }, Point { x: 0.8, y: 0.25 });
+    /// for metric in [DistanceMetric::Euclidean, DistanceMetric::Chebyshev, DistanceMetric::Minkowski(3.0)] {
+    ///     let reduced = metric.toroidal_reduced(p1, p2);
+    ///     assert_eq!(metric.expand(reduced), toroidal_distance_with(p1, p2, metric));
+    /// }
+    /// assert_eq!(DistanceMetric::Euclidean.toroidal_reduced(p1, p2), 0.25 * 0.25 + 0.25 * 0.25);
+    /// ```
+    pub fn toroidal_reduced(self, p1: Point, p2: Point) -> f32 {
+        self.toroidal_reduced_scaled(p1, p2, (1.0, 1.0))
+    }
+
+    /// The reduced toroidal distance of [`DistanceMetric::toroidal_reduced`]
+    /// with the wrapped offsets along x and y multiplied by `scale`
+    ///
+    /// Scaling the offsets measures the distance on a torus of `scale.0` by
+    /// `scale.1`, so an axis scaled up counts more and the cells stretch
+    /// along the other one. A scale of `(1.0, 1.0)` is the unscaled distance
+    /// bit for bit.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::geometry::DistanceMetric;
+    /// use cells::Point;
+    ///
+    /// let (p1, p2) = (Point { x: 0.125, y: 0.125 }, Point { x: 0.875, y: 0.375 });
+    /// assert_eq!(DistanceMetric::Manhattan.toroidal_reduced_scaled(p1, p2, (1.0, 3.0)), 0.25 + 3.0 * 0.25);
+    /// ```
+    pub fn toroidal_reduced_scaled(self, p1: Point, p2: Point, scale: (f32, f32)) -> f32 {
+        let wrap = |d: f32| {
+            // Coordinates outside the unit square are whole textures off
+            let d = if d < 1.0 { d } else { d % 1.0 };
+            d.min(1.0 - d)
+        };
+        let dx = wrap((p1.x - p2.x).abs()) * scale.0;
+        let dy = wrap((p1.y - p2.y).abs()) * scale.1;
+        match self {
+            DistanceMetric::Euclidean => dx * dx + dy * dy,
+            DistanceMetric::Manhattan => dx + dy,
+            DistanceMetric::Chebyshev => dx.max(dy),
+            DistanceMetric::Minkowski(p) => dx.powf(p) + dy.powf(p),
+        }
+    }
+
+    /// The distance of a reduced distance
+    pub fn expand(self, reduced: f32) -> f32 {
+        match self {
+            DistanceMetric::Euclidean => reduced.sqrt(),
+            DistanceMetric::Manhattan | DistanceMetric::Chebyshev => reduced,
+            DistanceMetric::Minkowski(p) => reduced.powf(1.0 / p),
+        }
+    }
+
+    /// The reduced distance of a distance, the inverse of
+    /// [`DistanceMetric::expand`] up to rounding
+    pub fn reduce(self, distance: f32) -> f32 {
+        match self {
+            DistanceMetric::Euclidean => distance * distance,
+            DistanceMetric::Manhattan | DistanceMetric::Chebyshev => distance,
+            DistanceMetric::Minkowski(p) => distance.powf(p),
+        }
+    }
+}
+
+impl fmt::Display for DistanceMetric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DistanceMetric::Euclidean => f.write_str("euclidean"),
+            DistanceMetric::Manhattan => f.write_str("manhattan"),
+            DistanceMetric::Chebyshev => f.write_str("chebyshev"),
+            DistanceMetric::Minkowski(p) => write!(f, "minkowski:{p}"),
+        }
+    }
+}
+
+impl FromStr for DistanceMetric {
+    type Err = String;
+
+    /// Parse `euclidean`, `manhattan`, `chebyshev` or `minkowski:<p>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "euclidean" => Ok(DistanceMetric::Euclidean),
+            "manhattan" => Ok(DistanceMetric::Manhattan),
+            "chebyshev" => Ok(DistanceMetric::Chebyshev),
+            _ => {
+                let p = s
+                    .strip_prefix("minkowski:")
+                    .ok_or_else(|| {
+                        format!("unknown metric `{s}`, expected euclidean, manhattan, chebyshev or minkowski:<p>")
+                    })?
+                    .parse::<f32>()
+                    .map_err(|err| format!("invalid Minkowski exponent in `{s}`: {err}"))?;
+                if !(p >= 1.0 && p.is_finite()) {
+                    return Err(format!("Minkowski exponent must be a finite number >= 1, got {p}"));
+                }
+                Ok(DistanceMetric::Minkowski(p))
+            }
+        }
+    }
+}
+
+serde_via_str!(DistanceMetric);
+
+/// How a per-point weight modifies the distance to that point
+///
+/// Weights let cells of one diagram differ in size. Additive weighting
+/// subtracts the weight from the distance, so heavier points grow larger cells
+/// bounded by gently curved (hyperbolic) edges. Multiplicative weighting
+/// divides the distance by the weight, which gives circular edges and lets
+/// heavy cells engulf light neighbors entirely.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Weighting {
+    /// Points carry no weight
+    #[default]
+    None,
+    /// `distance - weight`
+    Additive,
+    /// `distance / weight`, for positive weights
+    Multiplicative,
+}
+
+impl Weighting {
+    /// Apply `weight` to a `distance`
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::geometry::Weighting;
+    ///
+    /// assert_eq!(Weighting::None.apply(0.5, 0.25), 0.5);
+    /// assert_eq!(Weighting::Additive.apply(0.5, 0.25), 0.25);
+    /// assert_eq!(Weighting::Multiplicative.apply(0.5, 0.25), 2.0);
+    /// ```
+    pub fn apply(self, distance: f32, weight: f32) -> f32 {
+        match self {
+            Weighting::None => distance,
+            Weighting::Additive => distance - weight,
+            Weighting::Multiplicative => distance / weight,
+        }
+    }
+}
+
+impl fmt::Display for Weighting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Weighting::None => "none",
+            Weighting::Additive => "additive",
+            Weighting::Multiplicative => "multiplicative",
+        })
+    }
+}
+
+impl FromStr for Weighting {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Weighting::None),
+            "additive" => Ok(Weighting::Additive),
+            "multiplicative" => Ok(Weighting::Multiplicative),
+            _ => Err(format!("unknown weighting `{s}`, expected none, additive or multiplicative")),
+        }
+    }
+}
+
+serde_via_str!(Weighting);
+
+/// Calculate the toroidal distance between two points under any metric
+///
+/// The wraparound is applied to each axis before the metric is evaluated, so
+/// every metric keeps textures seamless. With [`DistanceMetric::Euclidean`] the
+/// result is identical to [`toroidal_distance`].
+///
+/// # Arguments
+///
+/// * `p1` - The first point
+/// * `p2` - The second point
+/// * `metric` - The metric combining the wrapped per-axis offsets
+///
+/// # Returns
+///
+/// The toroidal distance between the two points
+///
+/// # Example
+///
+/// ```rust
+/// use cells::geometry::{toroidal_distance_with, DistanceMetric};
+/// use cells::Point;
+/// use rand::{Rng, SeedableRng};
+///
+/// let mut rng = rand::rngs::StdRng::seed_from_u64(9);
+/// let mut point = || Point { x: rng.gen(), y: rng.gen() };
+/// for metric in [
+///     DistanceMetric::Euclidean,
+///     DistanceMetric::Manhattan,
+///     DistanceMetric::Chebyshev,
+///     DistanceMetric::Minkowski(3.0),
+/// ] {
+///     for _ in 0..1000 {
+///         let (a, b, c) = (point(), point(), point());
+///         let d = |p, q| toroidal_distance_with(p, q, metric);
+///         assert_eq!(d(a, b), d(b, a));
+///         assert!(d(a, c) <= d(a, b) + d(b, c) + 1e-6);
+///     }
+/// }
+/// ```
+pub fn toroidal_distance_with(p1: Point, p2: Point, metric: DistanceMetric) -> f32 {
+    metric.expand(metric.toroidal_reduced(p1, p2))
+}
@@ -0,0 +1,416 @@
+//! A wgpu compute backend for the Voronoi diagram and the directional blur.
+//!
+//! [`Gpu::new`] opens the first adapter that runs compute shaders, and its
+//! methods stand in for [`generate_voronoi_field`],
+//! [`generate_voronoi_frame`] and [`directional_blur_field_with_length`].
+//! The points are placed, relaxed and bucketed on the CPU exactly as for the
+//! CPU functions; the shaders then run the ring search of
+//! [`PointGrid`](crate::grid::PointGrid) and the blur taps once per pixel.
+//! The devices round differently and may fuse operations, so the results
+//! match the CPU up to rounding rather than bit for bit.
+//!
+//! Whatever the shaders cannot do runs on the CPU instead: the noise Worley
+//! backend, diagrams without points, and buffers beyond the limits of the
+//! device. This module needs the `gpu` feature.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cells::gpu::Gpu;
+//! use cells::voronoi::{generate_voronoi_field, VoronoiParams};
+//!
+//! let params = VoronoiParams { size: 64, num_points: 32, ..Default::default() };
+//! if let Some(gpu) = Gpu::new() {
+//!     let cpu = generate_voronoi_field(&params);
+//!     let gpu = gpu.voronoi_field(&params);
+//!     assert!(cpu.as_slice().iter().zip(gpu.as_slice()).all(|(a, b)| (a - b).abs() <= 1.0 / 255.0));
+//! }
+//! ```
+
+use std::sync::mpsc;
+
+use bytemuck::{Pod, Zeroable};
+use rayon::prelude::*;
+use wgpu::util::DeviceExt;
+
+use crate::field::FieldBuffer;
+use crate::filter::{directional_blur_field_with_progress, warn_wide_blur, BlurParams, BlurSampling};
+#[cfg(doc)]
+use crate::filter::directional_blur_field_with_length;
+use crate::geometry::{DistanceMetric, Weighting};
+use crate::voronoi::{
+    build_grid, fractal_field_with, grid_features, point_values, sample_positions, voronoi_features, Feature,
+    VoronoiBackend, VoronoiParams,
+};
+#[cfg(doc)]
+use crate::voronoi::{generate_voronoi_field, generate_voronoi_frame};
+
+/// Pixels along either side of a workgroup of the shaders
+const WORKGROUP_SIZE: u32 = 8;
+
+/// The uniforms of `shaders/voronoi.wgsl`
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct VoronoiUniforms {
+    size: u32,
+    count: u32,
+    resolution: u32,
+    feature: u32,
+    metric: u32,
+    weighting: u32,
+    warped: u32,
+    exponent: f32,
+    value_smoothing: f32,
+    scale_x: f32,
+    scale_y: f32,
+    max_weight: f32,
+}
+
+/// A point of `shaders/voronoi.wgsl`, in the cell order of the grid
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Site {
+    x: f32,
+    y: f32,
+    weight: f32,
+    value: f32,
+    index: u32,
+}
+
+/// The uniforms of `shaders/blur.wgsl`
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct BlurUniforms {
+    width: u32,
+    height: u32,
+    first_tap: i32,
+    last_tap: i32,
+    nearest: u32,
+    total_weight: f32,
+    padding: [u32; 2],
+}
+
+/// A GPU device with the compute pipelines of the backend
+pub struct Gpu {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    info: wgpu::AdapterInfo,
+    voronoi: wgpu::ComputePipeline,
+    blur: wgpu::ComputePipeline,
+}
+
+impl Gpu {
+    /// Open the preferred adapter of the system that runs compute shaders
+    ///
+    /// # Returns
+    ///
+    /// The device with its pipelines, or `None` when no adapter is found,
+    /// none runs compute shaders with enough storage buffers, or the device
+    /// cannot be opened
+    pub fn new() -> Option<Gpu> {
+        let instance = wgpu::Instance::default();
+        let options = wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..Default::default()
+        };
+        let adapter = pollster::block_on(instance.request_adapter(&options)).ok()?;
+        let limits = adapter.limits();
+        let compute = adapter.get_downlevel_capabilities().flags.contains(wgpu::DownlevelFlags::COMPUTE_SHADERS);
+        // Both shaders bind four storage buffers
+        if !compute || limits.max_storage_buffers_per_shader_stage < 4 {
+            return None;
+        }
+        let descriptor = wgpu::DeviceDescriptor {
+            label: Some("cells"),
+            required_limits: limits,
+            ..Default::default()
+        };
+        let (device, queue) = pollster::block_on(adapter.request_device(&descriptor)).ok()?;
+        let pipeline = |label, source: &str| {
+            let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(label),
+                source: wgpu::ShaderSource::Wgsl(source.into()),
+            });
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(label),
+                layout: None,
+                module: &module,
+                entry_point: Some("main"),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+        let voronoi = pipeline("voronoi", include_str!("shaders/voronoi.wgsl"));
+        let blur = pipeline("directional blur", include_str!("shaders/blur.wgsl"));
+        Some(Gpu {
+            device,
+            queue,
+            info: adapter.get_info(),
+            voronoi,
+            blur,
+        })
+    }
+
+    /// The name, backend and device type of the adapter
+    pub fn adapter_info(&self) -> &wgpu::AdapterInfo {
+        &self.info
+    }
+
+    /// [`generate_voronoi_field`] with the features measured on the GPU
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`VoronoiParams::validate`] rejects `params`.
+    pub fn voronoi_field(&self, params: &VoronoiParams) -> FieldBuffer {
+        fractal_field_with(params, None, |_| {}, |params, orbit, _| self.voronoi_features(params, orbit))
+    }
+
+    /// [`generate_voronoi_frame`] with the features measured on the GPU
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`VoronoiParams::validate`] rejects `params`.
+    pub fn voronoi_frame(&self, params: &VoronoiParams, phase: f64, orbit: f32) -> FieldBuffer {
+        let orbit = Some((phase, orbit));
+        fractal_field_with(params, orbit, |_| {}, |params, orbit, _| self.voronoi_features(params, orbit))
+    }
+
+    /// The raw features of one octave with the points moved along `orbit`
+    fn voronoi_features(&self, params: &VoronoiParams, orbit: Option<(f64, f32)>) -> FieldBuffer {
+        if params.backend != VoronoiBackend::Grid {
+            return voronoi_features(params, orbit, |_| {});
+        }
+        let size = params.size;
+        let details = format_args!(
+            "{size}x{size}, {} {} points, {} {} features, on {}",
+            params.num_points, params.distribution, params.feature, params.metric, self.info.name
+        );
+        crate::stage("voronoi", details, || {
+            let grid = build_grid(params, orbit);
+            let (resolution, starts, indices) = grid.cells();
+            let pixels = size as usize * size as usize;
+            let warped = params.warp_strength != 0.0;
+            let site_bytes = indices.len() * std::mem::size_of::<Site>();
+            let position_bytes = if warped { pixels * 8 } else { 0 };
+            let sizes = [site_bytes, starts.len() * 4, position_bytes, pixels * 4];
+            if indices.is_empty() || !self.fits(&sizes, size, size) {
+                return grid_features(params, &grid, |_| {});
+            }
+
+            let (points, weights) = (grid.points(), grid.weights());
+            let values = point_values(params, points.len());
+            let sites: Vec<Site> = indices
+                .iter()
+                .map(|&i| Site {
+                    x: points[i].x,
+                    y: points[i].y,
+                    weight: weights.get(i).copied().unwrap_or(0.0),
+                    value: values.get(i).copied().unwrap_or(0.0),
+                    index: i as u32,
+                })
+                .collect();
+            let starts: Vec<u32> = starts.iter().map(|&start| start as u32).collect();
+            let positions: Vec<[f32; 2]> = if warped {
+                let position = sample_positions(params);
+                (0..pixels)
+                    .into_par_iter()
+                    .map(|pixel| {
+                        let p = position(pixel as u32 % size, pixel as u32 / size);
+                        [p.x, p.y]
+                    })
+                    .collect()
+            } else {
+                // Bindings cannot be empty
+                vec![[0.0; 2]]
+            };
+            let uniforms = VoronoiUniforms {
+                size,
+                count: sites.len() as u32,
+                resolution: resolution as u32,
+                feature: match params.feature {
+                    Feature::F1 => 0,
+                    Feature::F2 => 1,
+                    Feature::F2MinusF1 => 2,
+                    Feature::CellValue => 3,
+                },
+                metric: match params.metric {
+                    DistanceMetric::Euclidean => 0,
+                    DistanceMetric::Manhattan => 1,
+                    DistanceMetric::Chebyshev => 2,
+                    DistanceMetric::Minkowski(_) => 3,
+                },
+                weighting: match params.weighting {
+                    Weighting::None => 0,
+                    Weighting::Additive => 1,
+                    Weighting::Multiplicative => 2,
+                },
+                warped: warped as u32,
+                exponent: match params.metric {
+                    DistanceMetric::Minkowski(p) => p,
+                    _ => 1.0,
+                },
+                value_smoothing: params.value_smoothing,
+                scale_x: params.cell_scale.0,
+                scale_y: params.cell_scale.1,
+                max_weight: weights.iter().copied().fold(0.0, f32::max),
+            };
+            let inputs = [
+                bytemuck::cast_slice(&sites),
+                bytemuck::cast_slice(&starts),
+                bytemuck::cast_slice(&positions),
+            ];
+            let features = self.run(&self.voronoi, bytemuck::bytes_of(&uniforms), &inputs, (size, size));
+            FieldBuffer::from_vec(size, size, features)
+        })
+    }
+
+    /// [`directional_blur_field_with_length`], or without a `length` map
+    /// [`directional_blur_field_with`](crate::filter::directional_blur_field_with),
+    /// with the taps read on the GPU
+    ///
+    /// # Panics
+    ///
+    /// Panics if `length` does not have the dimensions of `field`.
+    pub fn directional_blur(
+        &self,
+        field: &FieldBuffer,
+        direction: &FieldBuffer,
+        length: Option<&FieldBuffer>,
+        params: &BlurParams,
+    ) -> FieldBuffer {
+        if let Some(length) = length {
+            assert_eq!(
+                length.dimensions(),
+                field.dimensions(),
+                "the length map must have the dimensions of the field"
+            );
+        }
+        let (width, height) = field.dimensions();
+        let pixels = width as usize * height as usize;
+        let first_tap = params.mapping.first_tap(params.radius);
+        let taps = (params.radius as i64 - first_tap as i64 + 1).max(0) as usize;
+        if pixels == 0
+            || taps == 0
+            || direction.dimensions() != field.dimensions()
+            || !self.fits(&[pixels * 4, pixels * 8, taps * 4, pixels * 4], width, height)
+        {
+            return directional_blur_field_with_progress(field, direction, length, params, |_| {});
+        }
+        warn_wide_blur("directional blur radius", params.radius, width, height);
+        let details = format_args!(
+            "{width}x{height}, radius {}, {} kernel, {} sampling, {} mapping{}, on {}",
+            params.radius,
+            params.kernel,
+            params.sampling,
+            params.mapping,
+            if length.is_some() { ", scaled by a length map" } else { "" },
+            self.info.name
+        );
+        crate::stage("directional blur", details, || {
+            let weights = params.kernel.raw_weights(params.radius);
+            let weights = &weights[(first_tap + params.radius) as usize..];
+            let scale = |pixel: usize| length.map_or(1.0, |length| length.as_slice()[pixel].clamp(0.0, 1.0));
+            let steps: Vec<[f32; 2]> = direction
+                .as_slice()
+                .par_iter()
+                .enumerate()
+                .map(|(pixel, &value)| {
+                    let (step_x, step_y) = params.mapping.step(value);
+                    [step_x * scale(pixel), step_y * scale(pixel)]
+                })
+                .collect();
+            let uniforms = BlurUniforms {
+                width,
+                height,
+                first_tap,
+                last_tap: params.radius,
+                nearest: (params.sampling == BlurSampling::Nearest) as u32,
+                total_weight: weights.iter().sum(),
+                padding: [0; 2],
+            };
+            let inputs = [
+                bytemuck::cast_slice(field.as_slice()),
+                bytemuck::cast_slice(&steps),
+                bytemuck::cast_slice(weights),
+            ];
+            let mut blurred = self.run(&self.blur, bytemuck::bytes_of(&uniforms), &inputs, (width, height));
+            // Samples of length 0 keep the input exactly, like on the CPU
+            let kept = blurred.par_iter_mut().enumerate().filter(|(pixel, _)| scale(*pixel) == 0.0);
+            kept.for_each(|(pixel, value)| *value = field.as_slice()[pixel]);
+            FieldBuffer::from_vec(width, height, blurred)
+        })
+    }
+
+    /// Whether buffers of `sizes` bytes can be bound and a `width` x
+    /// `height` grid of pixels dispatched
+    fn fits(&self, sizes: &[usize], width: u32, height: u32) -> bool {
+        let limits = self.device.limits();
+        let largest = (limits.max_storage_buffer_binding_size as u64).min(limits.max_buffer_size);
+        let groups = width.max(height).div_ceil(WORKGROUP_SIZE);
+        sizes.iter().all(|&size| size as u64 <= largest) && groups <= limits.max_compute_workgroups_per_dimension
+    }
+
+    /// Run `pipeline` over a `width` x `height` grid of pixels with the
+    /// `uniforms` and the storage buffers `inputs` bound in order, and read
+    /// back the output buffer bound after them, one sample per pixel
+    fn run(
+        &self,
+        pipeline: &wgpu::ComputePipeline,
+        uniforms: &[u8],
+        inputs: &[&[u8]],
+        (width, height): (u32, u32),
+    ) -> Vec<f32> {
+        let buffer = |label, contents, usage| {
+            self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor { label: Some(label), contents, usage })
+        };
+        let uniforms = buffer("uniforms", uniforms, wgpu::BufferUsages::UNIFORM);
+        let inputs: Vec<wgpu::Buffer> =
+            inputs.iter().map(|&contents| buffer("input", contents, wgpu::BufferUsages::STORAGE)).collect();
+        let output_size = width as u64 * height as u64 * 4;
+        let output = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("output"),
+            size: output_size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size: output_size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let entries: Vec<wgpu::BindGroupEntry> = std::iter::once(&uniforms)
+            .chain(&inputs)
+            .chain([&output])
+            .enumerate()
+            .map(|(binding, buffer)| wgpu::BindGroupEntry {
+                binding: binding as u32,
+                resource: buffer.as_entire_binding(),
+            })
+            .collect();
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &entries,
+        });
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        {
+            let mut pass = encoder.begin_compute_pass(&Default::default());
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(width.div_ceil(WORKGROUP_SIZE), height.div_ceil(WORKGROUP_SIZE), 1);
+        }
+        encoder.copy_buffer_to_buffer(&output, 0, &readback, 0, output_size);
+        self.queue.submit([encoder.finish()]);
+
+        let slice = readback.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::PollType::wait_indefinitely()).expect("the GPU finishes the pass");
+        receiver.recv().expect("the readback is mapped").expect("the readback is mapped");
+        bytemuck::pod_collect_to_vec(&slice.get_mapped_range())
+    }
+}
@@ -0,0 +1,151 @@
+//! Wood grain and marble veins: periodic ramps warped by turbulence.
+//!
+//! Both sum a ramp, the distance from the center for the rings of wood and
+//! the x coordinate for the veins of marble, with tileable billow noise and
+//! run the sum through a cosine wave. The ramps rise by whole periods across
+//! the texture, so the waves meet themselves across the seams.
+
+use std::f32::consts::{PI, TAU};
+
+use crate::error::CellsError;
+use crate::field::FieldBuffer;
+use crate::noise::{generate_fbm_field, FbmParams, NoiseStyle};
+
+/// Parameters of wood and marble textures
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GrainParams {
+    /// Width and height of the texture in pixels
+    pub size: u32,
+    /// Number of wood rings from the center to an edge, or of marble veins
+    /// across the texture
+    pub count: u32,
+    /// How far the turbulence shifts the rings or veins, in periods
+    pub turbulence: f32,
+    /// Base frequency of the turbulence, in noise features across the texture
+    pub frequency: f64,
+    /// Seed of the turbulence
+    pub seed: u64,
+}
+
+impl Default for GrainParams {
+    fn default() -> Self {
+        GrainParams {
+            size: 512,
+            count: 8,
+            turbulence: 1.0,
+            frequency: 4.0,
+            seed: 0,
+        }
+    }
+}
+
+impl GrainParams {
+    /// Check that the texture has rings or veins and finite turbulence
+    ///
+    /// # Returns
+    ///
+    /// [`CellsError::InvalidParameter`] if the size or count is 0, the
+    /// turbulence is negative or not finite, or the frequency is not
+    /// positive and finite
+    pub fn validate(&self) -> Result<(), CellsError> {
+        let invalid = |name, reason: String| Err(CellsError::InvalidParameter { name, reason });
+        for (name, value) in [("size", self.size), ("count", self.count)] {
+            if value == 0 {
+                return invalid(name, "must be above 0".to_string());
+            }
+        }
+        if !(self.turbulence.is_finite() && self.turbulence >= 0.0) {
+            return invalid("turbulence", format!("must be at least 0 and finite, got {}", self.turbulence));
+        }
+        if !(self.frequency.is_finite() && self.frequency > 0.0) {
+            return invalid("frequency", format!("must be positive and finite, got {}", self.frequency));
+        }
+        Ok(())
+    }
+}
+
+/// Generate tileable wood grain, rings around the center of the texture
+///
+/// The rings follow a distance that wraps around the torus smoothly,
+/// `sqrt(sin²(π dx) + sin²(π dy)) / 2`: it is 0 in the center, 0.5 at the
+/// middle of every edge and round like the Euclidean distance near the
+/// center, but squarer towards the edges instead of folding over at them.
+/// Values run from 0 on the rings to 1 between them.
+///
+/// # Arguments
+///
+/// * `params` - The rings and their turbulence
+///
+/// # Returns
+///
+/// A `FieldBuffer` with values from 0 to 1
+///
+/// # Panics
+///
+/// Panics if the parameters fail [`GrainParams::validate`].
+///
+/// # Example
+///
+/// ```rust
+/// use cells::grain::{generate_wood, GrainParams};
+/// use cells::tiling::{assert_tileable, interior_discontinuity};
+///
+/// let params = GrainParams { size: 64, count: 4, turbulence: 0.0, ..Default::default() };
+/// let rings = generate_wood(&params);
+/// // Undisturbed rings are symmetric about the center
+/// assert!((rings.get(40, 32) - rings.get(23, 32)).abs() < 1e-5);
+/// assert_tileable(&rings, interior_discontinuity(&rings));
+/// ```
+pub fn generate_wood(params: &GrainParams) -> FieldBuffer {
+    grain(params, |u, v| {
+        let (dx, dy) = (u - 0.5, v - 0.5);
+        let distance = ((PI * dx).sin().powi(2) + (PI * dy).sin().powi(2)).sqrt() / 2.0;
+        2.0 * params.count as f32 * distance
+    })
+}
+
+/// Generate tileable marble, veins running down the texture
+///
+/// `params.count` veins cross the texture from left to right, bent by the
+/// turbulence. Values run from 0 in the veins to 1 between them.
+///
+/// # Panics
+///
+/// Panics if the parameters fail [`GrainParams::validate`].
+///
+/// # Example
+///
+/// ```rust
+/// use cells::grain::{generate_marble, GrainParams};
+/// use cells::tiling::{assert_tileable, interior_discontinuity};
+///
+/// let params = GrainParams { size: 64, seed: 2, ..Default::default() };
+/// let marble = generate_marble(&params);
+/// assert_eq!(marble, generate_marble(&params));
+/// assert_tileable(&marble, interior_discontinuity(&marble));
+/// ```
+pub fn generate_marble(params: &GrainParams) -> FieldBuffer {
+    grain(params, |u, _| params.count as f32 * u)
+}
+
+/// The cosine wave of `ramp` plus turbulence, in periods, at every pixel
+fn grain(params: &GrainParams, ramp: impl Fn(f32, f32) -> f32 + Sync) -> FieldBuffer {
+    if let Err(err) = params.validate() {
+        panic!("{err}");
+    }
+    let turbulence = generate_fbm_field(
+        params.size,
+        &FbmParams {
+            frequency: params.frequency,
+            style: NoiseStyle::Billow,
+            seed: params.seed,
+            ..Default::default()
+        },
+    );
+    let size = params.size as f32;
+    FieldBuffer::from_par_fn(params.size, params.size, |x, y| {
+        let (u, v) = ((x as f32 + 0.5) / size, (y as f32 + 0.5) / size);
+        let phase = ramp(u, v) + params.turbulence * turbulence.get(x, y);
+        0.5 - 0.5 * (TAU * phase).cos()
+    })
+}
@@ -0,0 +1,530 @@
+//! Toroidal uniform grid accelerating nearest-point queries.
+//!
+//! The coordinates of the points are stored per axis in cell order, so the
+//! points of a row of cells lie next to each other in memory. On x86-64 CPUs
+//! with AVX, unweighted Euclidean, Manhattan and Chebyshev queries measure
+//! eight of them per instruction; elsewhere, and for the other metrics and
+//! weighted points, a scalar loop measures one at a time. Both compute every
+//! distance with the same operations in the same order, so they find the very
+//! same points.
+
+use std::ops::Range;
+
+use crate::geometry::{DistanceMetric, Point, Weighting};
+
+/// Points measured per instruction by the SIMD kernel
+#[cfg(target_arch = "x86_64")]
+const LANES: usize = 8;
+
+/// NaN coordinates after the last point, so the SIMD kernel can always read
+/// whole vectors
+const SIMD_PADDING: usize = 7;
+
+/// Slack on the ring termination bound, absorbing f32 rounding in the
+/// distance computations so grid results stay equal to a brute-force scan
+const BOUND_SLACK: f32 = 0.999;
+
+/// Points bucketed into an `N x N` grid over the wrapping unit square
+///
+/// Each point is stored in the cell that contains it. Queries start in the cell
+/// of the query position and visit square rings of cells around it, wrapping at
+/// the edges, until no unvisited cell can contain a closer point. Distances
+/// may be stretched per axis with [`PointGrid::with_scale`].
+#[derive(Clone, Debug)]
+pub struct PointGrid {
+    points: Vec<Point>,
+    metric: DistanceMetric,
+    /// Per-point weights, empty without weighting
+    weights: Vec<f32>,
+    weighting: Weighting,
+    max_weight: f32,
+    /// The factors of the x and y offsets in every distance
+    scale: (f32, f32),
+    resolution: usize,
+    /// Cell `c` holds the point indices `indices[starts[c]..starts[c + 1]]`
+    starts: Vec<usize>,
+    indices: Vec<usize>,
+    /// The coordinates of the points `indices`, in the same order
+    xs: Vec<f32>,
+    ys: Vec<f32>,
+    /// Whether queries run the SIMD kernel
+    simd: bool,
+}
+
+impl PointGrid {
+    /// Bucket points into a grid with roughly one point per cell
+    ///
+    /// Queries on the grid use the Euclidean toroidal distance.
+    ///
+    /// # Arguments
+    ///
+    /// * `points` - The points to index, all inside the unit square
+    ///
+    /// # Returns
+    ///
+    /// A `PointGrid` owning a copy of the points
+    pub fn new(points: &[Point]) -> Self {
+        PointGrid::with_metric(points, DistanceMetric::Euclidean)
+    }
+
+    /// Bucket points into a grid whose queries use `metric`
+    ///
+    /// Every supported metric is at least the per-axis Chebyshev distance,
+    /// which is what the ring search relies on to stop early.
+    ///
+    /// # Arguments
+    ///
+    /// * `points` - The points to index, all inside the unit square
+    /// * `metric` - The metric used by all queries
+    ///
+    /// # Returns
+    ///
+    /// A `PointGrid` owning a copy of the points
+    pub fn with_metric(points: &[Point], metric: DistanceMetric) -> Self {
+        PointGrid::with_weights(points, metric, &[], Weighting::None)
+    }
+
+    /// Bucket weighted points into a grid
+    ///
+    /// Query distances are the toroidal `metric` distances modified by the
+    /// weight of each point, and may be negative with additive weighting. The
+    /// search bound accounts for the largest weight, so results still equal a
+    /// brute-force scan.
+    ///
+    /// # Arguments
+    ///
+    /// * `points` - The points to index, all inside the unit square
+    /// * `metric` - The metric used by all queries
+    /// * `weights` - One weight per point, ignored with [`Weighting::None`]
+    /// * `weighting` - How the weights modify the distances
+    ///
+    /// # Returns
+    ///
+    /// A `PointGrid` owning a copy of the points and weights
+    ///
+    /// # Panics
+    ///
+    /// Panics if weighting is enabled and `weights` does not hold one weight
+    /// per point, or if multiplicative weights are not positive.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::grid::PointGrid;
+    /// use cells::{DistanceMetric, Point, Weighting};
+    ///
+    /// let points = [Point { x: 0.2, y: 0.5 }, Point { x: 0.6, y: 0.5 }];
+    /// let grid = PointGrid::with_weights(&points, DistanceMetric::Euclidean, &[0.0, 0.2], Weighting::Additive);
+    /// // Closer to the first point, but the heavy second point wins
+    /// let (index, distance) = grid.nearest(Point { x: 0.35, y: 0.5 }).unwrap();
+    /// assert_eq!(index, 1);
+    /// assert!((distance - 0.05).abs() < 1e-6);
+    /// ```
+    pub fn with_weights(points: &[Point], metric: DistanceMetric, weights: &[f32], weighting: Weighting) -> Self {
+        let weights = match weighting {
+            Weighting::None => Vec::new(),
+            _ => {
+                assert_eq!(weights.len(), points.len(), "expected one weight per point");
+                weights.to_vec()
+            }
+        };
+        if weighting == Weighting::Multiplicative {
+            assert!(weights.iter().all(|&w| w > 0.0), "multiplicative weights must be positive");
+        }
+        let max_weight = weights.iter().copied().fold(0.0, f32::max);
+        let resolution = ((points.len() as f64).sqrt().ceil() as usize).max(1);
+        let cell_of = |p: &Point| {
+            let (cx, cy) = cell_coords(*p, resolution);
+            cy * resolution + cx
+        };
+
+        // Counting sort of the point indices by cell
+        let mut starts = vec![0; resolution * resolution + 1];
+        for p in points {
+            starts[cell_of(p) + 1] += 1;
+        }
+        for c in 0..resolution * resolution {
+            starts[c + 1] += starts[c];
+        }
+        let mut fill = starts.clone();
+        let mut indices = vec![0; points.len()];
+        for (i, p) in points.iter().enumerate() {
+            let c = cell_of(p);
+            indices[fill[c]] = i;
+            fill[c] += 1;
+        }
+
+        // NaN padding lets the SIMD kernel read whole vectors past the last
+        // point, and never picks them
+        let padding = std::iter::repeat_n(f32::NAN, SIMD_PADDING);
+        let xs: Vec<f32> = indices.iter().map(|&i| points[i].x).chain(padding.clone()).collect();
+        let ys: Vec<f32> = indices.iter().map(|&i| points[i].y).chain(padding).collect();
+
+        let grid = PointGrid {
+            points: points.to_vec(),
+            metric,
+            weights,
+            weighting,
+            max_weight,
+            scale: (1.0, 1.0),
+            resolution,
+            starts,
+            indices,
+            xs,
+            ys,
+            simd: false,
+        };
+        grid.with_simd(true)
+    }
+
+    /// Enable or disable the SIMD kernel of the queries
+    ///
+    /// Grids use the SIMD kernel whenever the CPU and the metric support it;
+    /// disabling it is mainly useful to compare both kernels. Enabling it has
+    /// no effect where it is not supported.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::grid::PointGrid;
+    /// use cells::{DistanceMetric, Point};
+    ///
+    /// let points: Vec<Point> = (0..50).map(|i| Point { x: (i as f32 * 0.37) % 1.0, y: i as f32 / 50.0 }).collect();
+    /// let simd = PointGrid::new(&points);
+    /// let scalar = simd.clone().with_simd(false);
+    /// assert!(!scalar.uses_simd());
+    /// let p = Point { x: 0.3, y: 0.8 };
+    /// assert_eq!(simd.nearest(p), scalar.nearest(p));
+    /// // Minkowski distances have no SIMD kernel
+    /// assert!(!PointGrid::with_metric(&points, DistanceMetric::Minkowski(3.0)).uses_simd());
+    /// ```
+    pub fn with_simd(mut self, enabled: bool) -> Self {
+        let metric_supported = !matches!(self.metric, DistanceMetric::Minkowski(_));
+        self.simd = enabled && metric_supported && self.weighting == Weighting::None && simd_available();
+        self
+    }
+
+    /// Stretch the distances of all queries by multiplying the wrapped
+    /// offsets along x and y by `sx` and `sy`, see
+    /// [`DistanceMetric::toroidal_reduced_scaled`]
+    ///
+    /// The search bound shrinks by the smaller factor, so results still
+    /// equal a brute-force scan of the scaled distances.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sx` or `sy` is not positive and finite.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::grid::PointGrid;
+    /// use cells::{DistanceMetric, Point};
+    /// use rand::{Rng, SeedableRng};
+    ///
+    /// let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+    /// let points: Vec<Point> = (0..150).map(|_| Point { x: rng.gen(), y: rng.gen() }).collect();
+    /// let grid = PointGrid::new(&points).with_scale(0.5, 4.0);
+    /// for _ in 0..500 {
+    ///     let p = Point { x: rng.gen(), y: rng.gen() };
+    ///     let brute_force = points
+    ///         .iter()
+    ///         .map(|&q| DistanceMetric::Euclidean.toroidal_reduced_scaled(p, q, (0.5, 4.0)).sqrt())
+    ///         .fold(f32::INFINITY, f32::min);
+    ///     assert_eq!(grid.nearest(p).unwrap().1, brute_force);
+    /// }
+    /// ```
+    pub fn with_scale(mut self, sx: f32, sy: f32) -> Self {
+        let valid = |factor: f32| factor.is_finite() && factor > 0.0;
+        assert!(valid(sx) && valid(sy), "scale factors must be positive and finite, got {sx}, {sy}");
+        self.scale = (sx, sy);
+        self
+    }
+
+    /// Whether queries run the SIMD kernel
+    pub fn uses_simd(&self) -> bool {
+        self.simd
+    }
+
+    /// The indexed points, in their original order
+    pub fn points(&self) -> &[Point] {
+        &self.points
+    }
+
+    /// The weights of the points, in their order; empty without weighting
+    #[cfg(feature = "gpu")]
+    pub(crate) fn weights(&self) -> &[f32] {
+        &self.weights
+    }
+
+    /// The resolution of the grid, the first position of every cell in
+    /// `indices` followed by their count, and the point indices in cell order
+    #[cfg(feature = "gpu")]
+    pub(crate) fn cells(&self) -> (usize, &[usize], &[usize]) {
+        (self.resolution, &self.starts, &self.indices)
+    }
+
+    /// Find the point closest to `p` under the grid's toroidal distance
+    ///
+    /// The result is exactly what a brute-force scan over all points returns.
+    /// The search compares [reduced distances](DistanceMetric::toroidal_reduced),
+    /// squared ones for the Euclidean metric, and ties between them are
+    /// resolved in favor of the lowest point index.
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - The query position in the unit square
+    ///
+    /// # Returns
+    ///
+    /// The index of the nearest point and its distance, or `None` when the grid
+    /// holds no points
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::grid::PointGrid;
+    /// use cells::{toroidal_distance, Point};
+    /// use rand::{Rng, SeedableRng};
+    ///
+    /// for seed in 0..8 {
+    ///     let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+    ///     let points: Vec<Point> = (0..200).map(|_| Point { x: rng.gen(), y: rng.gen() }).collect();
+    ///     let grid = PointGrid::new(&points);
+    ///     for _ in 0..500 {
+    ///         let p = Point { x: rng.gen(), y: rng.gen() };
+    ///         let brute_force = points
+    ///             .iter()
+    ///             .map(|&q| toroidal_distance(p, q))
+    ///             .fold(f32::INFINITY, f32::min);
+    ///         assert_eq!(grid.nearest(p).unwrap().1, brute_force);
+    ///     }
+    /// }
+    /// ```
+    pub fn nearest(&self, p: Point) -> Option<(usize, f32)> {
+        let [first] = self.query::<1>(p)?;
+        Some(first)
+    }
+
+    /// Find the two points closest to `p` under the grid's toroidal distance
+    ///
+    /// These are the points defining the classic Worley F1 and F2 features.
+    /// Like [`PointGrid::nearest`], the result equals a brute-force scan.
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - The query position in the unit square
+    ///
+    /// # Returns
+    ///
+    /// The index and distance of the nearest and second-nearest point, or
+    /// `None` when the grid holds fewer than two points
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::grid::PointGrid;
+    /// use cells::{toroidal_distance, Point};
+    /// use rand::{Rng, SeedableRng};
+    ///
+    /// let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+    /// let points: Vec<Point> = (0..100).map(|_| Point { x: rng.gen(), y: rng.gen() }).collect();
+    /// let grid = PointGrid::new(&points);
+    /// for _ in 0..500 {
+    ///     let p = Point { x: rng.gen(), y: rng.gen() };
+    ///     let mut brute_force: Vec<f32> = points.iter().map(|&q| toroidal_distance(p, q)).collect();
+    ///     brute_force.sort_by(f32::total_cmp);
+    ///     let [(_, f1), (_, f2)] = grid.nearest_two(p).unwrap();
+    ///     assert_eq!((f1, f2), (brute_force[0], brute_force[1]));
+    /// }
+    /// ```
+    pub fn nearest_two(&self, p: Point) -> Option<[(usize, f32); 2]> {
+        self.query::<2>(p)
+    }
+
+    /// The `K` nearest points to `p`, closest first
+    fn query<const K: usize>(&self, p: Point) -> Option<[(usize, f32); K]> {
+        // Both kernels measure from inside the unit square
+        let inside = |v: f32| (0.0..1.0).contains(&v);
+        let p = if inside(p.x) && inside(p.y) { p } else { p.wrapped() };
+        #[cfg(target_arch = "x86_64")]
+        if self.simd {
+            // SAFETY: `simd` is only set after detecting AVX at runtime
+            return unsafe { self.query_avx(p) };
+        }
+        self.search(p, |positions, best| self.visit_scalar(positions, p, best))
+    }
+
+    /// [`PointGrid::query`] compiled for AVX, with the SIMD kernel
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx")]
+    unsafe fn query_avx<const K: usize>(&self, p: Point) -> Option<[(usize, f32); K]> {
+        self.search(p, |positions, best| unsafe { self.visit_avx(positions, p, best) })
+    }
+
+    /// The ring search of [`PointGrid::query`], handing the positions in cell
+    /// order of the candidates to `visit`
+    ///
+    /// Without weights the search compares reduced distances, and only expands
+    /// those of the winners.
+    #[inline(always)]
+    fn search<const K: usize>(
+        &self,
+        p: Point,
+        mut visit: impl FnMut(Range<usize>, &mut [(usize, f32); K]),
+    ) -> Option<[(usize, f32); K]> {
+        if self.points.len() < K {
+            return None;
+        }
+        let n = self.resolution;
+        let cell_size = 1.0 / n as f32;
+        let (cx, cy) = cell_coords(p, n);
+        let mut best = [(usize::MAX, f32::INFINITY); K];
+
+        for ring in 0.. {
+            if 2 * ring + 1 > n {
+                // The ring would wrap onto itself: finish with a full scan
+                visit(0..self.points.len(), &mut best);
+                break;
+            }
+            let r = ring as isize;
+            // Rings are narrower than the grid, so one wrap is all it takes
+            let wrap = |v: usize, d: isize| {
+                let w = v as isize + d;
+                (if w < 0 { w + n as isize } else if w >= n as isize { w - n as isize } else { w }) as usize
+            };
+            for dy in -r..=r {
+                let gy = wrap(cy, dy);
+                if dy.abs() == r {
+                    // The top and bottom rows are runs of adjacent cells, split
+                    // in two where they wrap
+                    let (first, len) = (wrap(cx, -r), 2 * ring + 1);
+                    let end = (first + len).min(n);
+                    visit(self.cell_range(gy, first..end), &mut best);
+                    visit(self.cell_range(gy, 0..first + len - end), &mut best);
+                } else {
+                    // Interior rows of the ring only contribute their two end cells
+                    for gx in [wrap(cx, -r), wrap(cx, r)] {
+                        visit(self.cell_range(gy, gx..gx + 1), &mut best);
+                    }
+                }
+            }
+            // Points outside the visited rings are at least `ring` cells away
+            // along one axis, scaled by at least the smaller factor
+            let min_scale = self.scale.0.min(self.scale.1);
+            let bound = self.weighting.apply(ring as f32 * cell_size * min_scale * BOUND_SLACK, self.max_weight);
+            let bound = if self.weighting == Weighting::None { self.metric.reduce(bound) } else { bound };
+            if 2 * ring + 1 == n || best[K - 1].1 <= bound {
+                break;
+            }
+        }
+        if self.weighting == Weighting::None {
+            for (_, distance) in &mut best {
+                *distance = self.metric.expand(*distance);
+            }
+        }
+        Some(best)
+    }
+
+    /// The positions in cell order of the points in columns `columns` of row
+    /// `row` of the grid
+    fn cell_range(&self, row: usize, columns: Range<usize>) -> Range<usize> {
+        let base = row * self.resolution;
+        self.starts[base + columns.start]..self.starts[base + columns.end]
+    }
+
+    /// Insert the closer of the points at `positions` into the sorted `best`
+    /// list, measuring one point at a time
+    fn visit_scalar<const K: usize>(&self, positions: Range<usize>, p: Point, best: &mut [(usize, f32); K]) {
+        for j in positions {
+            let (i, q) = (self.indices[j], Point { x: self.xs[j], y: self.ys[j] });
+            let distance = match self.weighting {
+                Weighting::None => self.metric.toroidal_reduced_scaled(p, q, self.scale),
+                weighting => {
+                    let distance = self.metric.expand(self.metric.toroidal_reduced_scaled(p, q, self.scale));
+                    weighting.apply(distance, self.weights[i])
+                }
+            };
+            insert(best, (i, distance));
+        }
+    }
+
+    /// [`PointGrid::visit_scalar`] measuring eight unweighted points per
+    /// instruction
+    ///
+    /// Only points no farther than the current K-th best are handed to
+    /// [`insert`], which keeps the exact order of the scalar kernel.
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx")]
+    unsafe fn visit_avx<const K: usize>(&self, positions: Range<usize>, p: Point, best: &mut [(usize, f32); K]) {
+        use std::arch::x86_64::*;
+
+        let (px, py) = (_mm256_set1_ps(p.x), _mm256_set1_ps(p.y));
+        let (one, sign) = (_mm256_set1_ps(1.0), _mm256_set1_ps(-0.0));
+        let (sx, sy) = (_mm256_set1_ps(self.scale.0), _mm256_set1_ps(self.scale.1));
+        let mut j = positions.start;
+        // Lanes past the end hold the points of the next cells or the NaN
+        // padding, which only ever add needless candidates. The padding keeps
+        // every load inside the coordinates.
+        while j < positions.end {
+            // The same operations as `DistanceMetric::toroidal_reduced_scaled` on
+            // points inside the unit square, lane by lane
+            let dx = _mm256_andnot_ps(sign, _mm256_sub_ps(px, _mm256_loadu_ps(self.xs.as_ptr().add(j))));
+            let dy = _mm256_andnot_ps(sign, _mm256_sub_ps(py, _mm256_loadu_ps(self.ys.as_ptr().add(j))));
+            let dx = _mm256_mul_ps(_mm256_min_ps(dx, _mm256_sub_ps(one, dx)), sx);
+            let dy = _mm256_mul_ps(_mm256_min_ps(dy, _mm256_sub_ps(one, dy)), sy);
+            let distance = match self.metric {
+                DistanceMetric::Manhattan => _mm256_add_ps(dx, dy),
+                DistanceMetric::Chebyshev => _mm256_max_ps(dx, dy),
+                _ => _mm256_add_ps(_mm256_mul_ps(dx, dx), _mm256_mul_ps(dy, dy)),
+            };
+            let worst = _mm256_set1_ps(best[K - 1].1);
+            let mut mask = _mm256_movemask_ps(_mm256_cmp_ps::<_CMP_LE_OQ>(distance, worst));
+            if mask != 0 {
+                let mut distances = [0.0; LANES];
+                _mm256_storeu_ps(distances.as_mut_ptr(), distance);
+                while mask != 0 {
+                    let lane = mask.trailing_zeros() as usize;
+                    insert(best, (self.indices[j + lane], distances[lane]));
+                    mask &= mask - 1;
+                }
+            }
+            j += LANES;
+        }
+    }
+}
+
+/// Insert a candidate into the sorted `best` list if it is closer than the
+/// last entry, breaking distance ties by the lower index
+fn insert<const K: usize>(best: &mut [(usize, f32); K], candidate: (usize, f32)) {
+    let closer = |a: &(usize, f32), b: &(usize, f32)| a.1 < b.1 || (a.1 == b.1 && a.0 < b.0);
+    // Full scans may see a point a second time
+    if !closer(&candidate, &best[K - 1]) || best.iter().any(|b| b.0 == candidate.0) {
+        return;
+    }
+    let mut slot = K - 1;
+    while slot > 0 && closer(&candidate, &best[slot - 1]) {
+        best[slot] = best[slot - 1];
+        slot -= 1;
+    }
+    best[slot] = candidate;
+}
+
+/// Whether the CPU runs the SIMD kernel
+fn simd_available() -> bool {
+    #[cfg(target_arch = "x86_64")]
+    {
+        is_x86_feature_detected!("avx")
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        false
+    }
+}
+
+/// Grid cell containing `p`, wrapping positions outside the unit square
+fn cell_coords(p: Point, resolution: usize) -> (usize, usize) {
+    let p = p.wrapped();
+    let to_cell = |v: f32| ((v * resolution as f32) as usize).min(resolution - 1);
+    (to_cell(p.x), to_cell(p.y))
+}
@@ -0,0 +1,1027 @@
+//! Reading and writing fields: quantized images, file formats that keep float
+//! precision, animated images of frame sequences, volumes of slices, and text
+//! metadata of PNG files.
+//!
+//! [`save_field`] is the entry point writing a field in any [`FileFormat`],
+//! picked from the file extension unless given; [`save_field_with`] also
+//! encodes the quantized samples with a transfer function such as sRGB.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write as _};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use exr::prelude::*;
+use image::{EncodableLayout, ExtendedColorType, ImageBuffer, ImageFormat, PixelWithColorType};
+
+use crate::band::{bands, BandSource};
+use crate::color::TransferFunction;
+use crate::error::CellsError;
+use crate::field::FieldBuffer;
+use crate::texture::{save_dds, save_ktx2, Texture, TextureFormat};
+
+/// The file format a field is saved in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FileFormat {
+    /// Grayscale PNG quantized to 8 or 16 bits
+    #[default]
+    Png,
+    /// Grayscale Truevision TGA quantized to 8 bits
+    Tga,
+    /// Grayscale TIFF quantized to 8 or 16 bits
+    Tiff,
+    /// Grayscale BMP quantized to 8 bits
+    Bmp,
+    /// Single-channel 32-bit float OpenEXR, see [`save_exr`]
+    Exr,
+    /// Little-endian 32-bit floats with a JSON sidecar, see [`save_raw`]
+    Raw,
+    /// Radiance HDR, see [`save_hdr`]
+    Hdr,
+    /// Uncompressed 8-bit DDS texture, see [`crate::texture::save_dds`]
+    Dds,
+    /// Uncompressed 8-bit KTX2 texture, see [`crate::texture::save_ktx2`]
+    Ktx2,
+}
+
+impl FileFormat {
+    /// Every format, in the order they are listed in messages
+    const ALL: [FileFormat; 9] = [
+        FileFormat::Png,
+        FileFormat::Tga,
+        FileFormat::Tiff,
+        FileFormat::Bmp,
+        FileFormat::Exr,
+        FileFormat::Raw,
+        FileFormat::Hdr,
+        FileFormat::Dds,
+        FileFormat::Ktx2,
+    ];
+
+    /// The format of a path by its extension, ignoring case, or `None` for
+    /// extensions of no supported format
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::io::FileFormat;
+    ///
+    /// assert_eq!(FileFormat::from_path("height.RAW"), Some(FileFormat::Raw));
+    /// assert_eq!(FileFormat::from_path("maps/sky.hdr"), Some(FileFormat::Hdr));
+    /// assert_eq!(FileFormat::from_path("noise.tif"), Some(FileFormat::Tiff));
+    /// assert_eq!(FileFormat::from_path("noise.jpg"), None);
+    /// ```
+    pub fn from_path(path: impl AsRef<Path>) -> Option<FileFormat> {
+        let extension = path.as_ref().extension()?.to_str()?;
+        if extension.eq_ignore_ascii_case("tif") {
+            return Some(FileFormat::Tiff);
+        }
+        FileFormat::ALL
+            .into_iter()
+            .find(|format| extension.eq_ignore_ascii_case(format.extension()))
+    }
+
+    /// The usual file extension of the format, without the dot
+    pub fn extension(self) -> &'static str {
+        match self {
+            FileFormat::Png => "png",
+            FileFormat::Tga => "tga",
+            FileFormat::Tiff => "tiff",
+            FileFormat::Bmp => "bmp",
+            FileFormat::Exr => "exr",
+            FileFormat::Raw => "raw",
+            FileFormat::Hdr => "hdr",
+            FileFormat::Dds => "dds",
+            FileFormat::Ktx2 => "ktx2",
+        }
+    }
+
+    /// The bits per sample the format can quantize to, empty for the float
+    /// formats, which keep the samples as they are
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::io::FileFormat;
+    ///
+    /// assert_eq!(FileFormat::Tiff.bit_depths(), [8, 16]);
+    /// assert_eq!(FileFormat::Bmp.bit_depths(), [8]);
+    /// assert!(FileFormat::Exr.bit_depths().is_empty());
+    /// ```
+    pub fn bit_depths(self) -> &'static [u8] {
+        match self {
+            FileFormat::Png | FileFormat::Tiff => &[8, 16],
+            FileFormat::Tga | FileFormat::Bmp | FileFormat::Dds | FileFormat::Ktx2 => &[8],
+            FileFormat::Exr | FileFormat::Raw | FileFormat::Hdr => &[],
+        }
+    }
+
+    /// Check that the format can store samples of `bit_depth` bits; float
+    /// formats accept any bit depth and ignore it
+    ///
+    /// # Returns
+    ///
+    /// [`CellsError::InvalidParameter`] if the format quantizes but not to
+    /// `bit_depth` bits
+    pub fn check_bit_depth(self, bit_depth: u8) -> std::result::Result<(), CellsError> {
+        let depths = self.bit_depths();
+        if depths.is_empty() || depths.contains(&bit_depth) {
+            return Ok(());
+        }
+        Err(CellsError::InvalidParameter {
+            name: "bit depth",
+            reason: format!("{self} has no {bit_depth}-bit grayscale, use png or tiff"),
+        })
+    }
+
+    /// Check that the format can store samples encoded with `transfer`: the
+    /// float formats and GPU textures only store linear samples
+    ///
+    /// # Returns
+    ///
+    /// [`CellsError::InvalidParameter`] for a transfer function other than
+    /// [`TransferFunction::Linear`] in a format of linear samples, or an
+    /// invalid gamma
+    pub fn check_transfer(self, transfer: TransferFunction) -> std::result::Result<(), CellsError> {
+        transfer.validate()?;
+        match self {
+            FileFormat::Png | FileFormat::Tga | FileFormat::Tiff | FileFormat::Bmp => Ok(()),
+            _ if transfer == TransferFunction::Linear => Ok(()),
+            _ => Err(CellsError::InvalidParameter {
+                name: "transfer",
+                reason: format!("{self} stores linear samples, cannot encode them with {transfer}"),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for FileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+impl FromStr for FileFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match FileFormat::ALL.into_iter().find(|format| format.extension() == s) {
+            Some(format) => Ok(format),
+            None => Err(format!("unknown file format `{s}`, expected png, tga, tiff, bmp, exr, raw, hdr, dds or ktx2")),
+        }
+    }
+}
+
+/// Save a field in a file format, by default the one of the path's extension
+///
+/// PNG, TGA, TIFF and BMP quantize linearly to grayscale of `bit_depth` bits
+/// per sample, DDS and KTX2 to an uncompressed single-level 8-bit texture (see
+/// [`crate::texture`] for mip levels and compression); the float formats keep
+/// the samples as they are, except that HDR cannot store negative values. Raw
+/// files get a sidecar without generator parameters, use [`save_raw`] to
+/// record them.
+///
+/// # Arguments
+///
+/// * `field` - The field to save
+/// * `path` - The file to write
+/// * `format` - The file format, or `None` for [`FileFormat::from_path`]
+/// * `bit_depth` - Bits per quantized sample, 8 or 16
+///
+/// # Returns
+///
+/// [`CellsError::InvalidParameter`] if no format is given and the extension
+/// is of none, or the format cannot store `bit_depth` bits (see
+/// [`FileFormat::check_bit_depth`]); [`CellsError::EmptyInput`] for a field
+/// without samples; [`CellsError::Encode`] if the field cannot be encoded and
+/// [`CellsError::Io`] if the file cannot be written
+///
+/// # Example
+///
+/// ```rust
+/// use cells::io::{load_raw, save_field, FileFormat};
+/// use cells::{CellsError, FieldBuffer};
+///
+/// let field = FieldBuffer::from_par_fn(8, 4, |x, y| x as f32 - y as f32 * 0.5);
+/// let dir = std::env::temp_dir();
+/// save_field(&field, dir.join("cells_doc_save_field.raw"), None, 8).unwrap();
+/// assert_eq!(load_raw(dir.join("cells_doc_save_field.raw")).unwrap(), field);
+/// // An explicit format wins over the extension
+/// save_field(&field, dir.join("cells_doc_save_field.bin"), Some(FileFormat::Raw), 8).unwrap();
+/// save_field(&field, dir.join("cells_doc_save_field.tif"), None, 16).unwrap();
+/// // BMP has no 16-bit grayscale
+/// assert!(save_field(&field, dir.join("cells_doc_save_field.bmp"), None, 16).is_err());
+/// let empty = save_field(&FieldBuffer::new(0, 4), dir.join("cells_doc_save_field_empty.png"), None, 8);
+/// assert_eq!(empty, Err(CellsError::EmptyInput { input: "field" }));
+/// ```
+pub fn save_field(
+    field: &FieldBuffer,
+    path: impl AsRef<Path>,
+    format: Option<FileFormat>,
+    bit_depth: u8,
+) -> std::result::Result<(), CellsError> {
+    save_field_with(field, path, format, bit_depth, TransferFunction::Linear)
+}
+
+/// Save a field like [`save_field`], encoding the samples with a transfer
+/// function as they are quantized
+///
+/// The samples are clamped to 0-1 and encoded right before quantization, so
+/// the field and every filter before stay linear. PNG files record the
+/// transfer function: a `gAMA` chunk of 1 for linear samples, of `1 / gamma`
+/// for [`TransferFunction::Gamma`], and an `sRGB` chunk for
+/// [`TransferFunction::Srgb`]. TGA, TIFF and BMP cannot record it.
+///
+/// # Returns
+///
+/// The errors of [`save_field`], and [`CellsError::InvalidParameter`] if the
+/// format cannot store samples encoded with `transfer` (see
+/// [`FileFormat::check_transfer`])
+///
+/// # Example
+///
+/// ```rust
+/// use cells::color::TransferFunction;
+/// use cells::io::save_field_with;
+/// use cells::FieldBuffer;
+///
+/// let gray = FieldBuffer::from_par_fn(4, 4, |_, _| 0.5);
+/// let path = std::env::temp_dir().join("cells_doc_save_field_with.png");
+/// save_field_with(&gray, &path, None, 8, TransferFunction::Srgb).unwrap();
+/// assert_eq!(image::open(&path).unwrap().to_luma8().get_pixel(0, 0)[0], 188);
+/// let exr = std::env::temp_dir().join("cells_doc_save_field_with.exr");
+/// assert!(save_field_with(&gray, exr, None, 8, TransferFunction::Srgb).is_err());
+/// ```
+pub fn save_field_with(
+    field: &FieldBuffer,
+    path: impl AsRef<Path>,
+    format: Option<FileFormat>,
+    bit_depth: u8,
+    transfer: TransferFunction,
+) -> std::result::Result<(), CellsError> {
+    let path = path.as_ref();
+    let format = format.or_else(|| FileFormat::from_path(path)).ok_or_else(|| {
+        let extensions = ".png, .tga, .tiff, .bmp, .exr, .raw, .hdr, .dds or .ktx2";
+        CellsError::InvalidParameter {
+            name: "file format",
+            reason: format!("cannot tell the file format of {}, use a {extensions} extension", path.display()),
+        }
+    })?;
+    format.check_bit_depth(bit_depth)?;
+    format.check_transfer(transfer)?;
+    if field.width() == 0 || field.height() == 0 {
+        return Err(CellsError::EmptyInput { input: "field" });
+    }
+    write_field(field, path, format, bit_depth, transfer)?;
+    log::info!("wrote {} ({}x{}, {bit_depth}-bit, {transfer})", path.display(), field.width(), field.height());
+    Ok(())
+}
+
+/// Write a non-empty field in `format` for [`save_field_with`]
+fn write_field(
+    field: &FieldBuffer,
+    path: &Path,
+    format: FileFormat,
+    bit_depth: u8,
+    transfer: TransferFunction,
+) -> std::result::Result<(), CellsError> {
+    let image_format = match format {
+        FileFormat::Png => {
+            let encoded = encode(field, transfer);
+            let saved = match bit_depth {
+                16 => save_png(&encoded.to_luma16_image(), path, transfer),
+                _ => save_png(&encoded.to_luma_image(), path, transfer),
+            };
+            return saved.map_err(|err| io_error(path, err));
+        }
+        FileFormat::Tga => ImageFormat::Tga,
+        FileFormat::Tiff => ImageFormat::Tiff,
+        FileFormat::Bmp => ImageFormat::Bmp,
+        FileFormat::Exr => {
+            return save_exr(field, path).map_err(|err| match err {
+                Error::Io(err) => io_error(path, err),
+                err => CellsError::Encode { path: path.to_path_buf(), reason: err.to_string() },
+            })
+        }
+        FileFormat::Raw => return save_raw(field, path, &[]).map_err(|err| io_error(path, err)),
+        FileFormat::Hdr => return save_hdr(field, path).map_err(|err| io_error(path, err)),
+        FileFormat::Dds | FileFormat::Ktx2 => {
+            let texture = Texture::encode(TextureFormat::R8, &[vec![field.clone()]])?;
+            let saved = match format {
+                FileFormat::Dds => save_dds(&texture, path),
+                _ => save_ktx2(&texture, path),
+            };
+            return saved.map_err(|err| io_error(path, err));
+        }
+    };
+    let field = encode(field, transfer);
+    match bit_depth {
+        16 => field.to_luma16_image().save_with_format(path, image_format),
+        _ => field.to_luma_image().save_with_format(path, image_format),
+    }
+    .map_err(|err| match err {
+        image::ImageError::IoError(err) => io_error(path, err),
+        err => CellsError::Encode { path: path.to_path_buf(), reason: err.to_string() },
+    })
+}
+
+/// Save a field as little-endian 32-bit floats with a JSON sidecar
+///
+/// The samples are written row by row as they are. The sidecar is written
+/// next to the file with the extension `json` and records the dimensions, the
+/// range of the samples and the parameters the field was generated with, e.g.
+/// the command line:
+///
+/// ```json
+/// {"width": 512, "height": 512, "min": -0.93, "max": 0.97, "format": "f32le", "parameters": ["--seed", "7"]}
+/// ```
+///
+/// # Arguments
+///
+/// * `field` - The field to save
+/// * `path` - The file the samples are written to
+/// * `parameters` - Free-form parameters recorded in the sidecar
+///
+/// # Returns
+///
+/// An error if a file cannot be written
+///
+/// # Example
+///
+/// ```rust
+/// use cells::io::{load_raw, save_raw};
+/// use cells::FieldBuffer;
+///
+/// let field = FieldBuffer::from_par_fn(3, 2, |x, y| x as f32 * 0.25 - y as f32);
+/// let path = std::env::temp_dir().join("cells_doc_save_raw.raw");
+/// save_raw(&field, &path, &["--seed".to_string(), "7".to_string()]).unwrap();
+/// let sidecar = std::fs::read_to_string(path.with_extension("json")).unwrap();
+/// assert!(sidecar.contains(r#""min": -1, "max": 0.5"#));
+/// assert!(sidecar.contains(r#""parameters": ["--seed", "7"]"#));
+/// assert_eq!(load_raw(&path).unwrap(), field);
+/// ```
+pub fn save_raw(field: &FieldBuffer, path: impl AsRef<Path>, parameters: &[String]) -> io::Result<()> {
+    let path = path.as_ref();
+    let bytes: Vec<u8> = field.as_slice().iter().flat_map(|value| value.to_le_bytes()).collect();
+    fs::write(path, bytes)?;
+
+    let (width, height) = field.dimensions();
+    let (min, max) = field.min_max().map_or(("null".to_string(), "null".to_string()), |(min, max)| {
+        (json_number(min), json_number(max))
+    });
+    let parameters: Vec<String> = parameters.iter().map(|parameter| json_string(parameter)).collect();
+    let sidecar = format!(
+        "{{\"width\": {width}, \"height\": {height}, \"min\": {min}, \"max\": {max}, \"format\": \"f32le\", \
+         \"parameters\": [{}]}}\n",
+        parameters.join(", ")
+    );
+    fs::write(path.with_extension("json"), sidecar)
+}
+
+/// Load a field saved by [`save_raw`], taking its dimensions from the sidecar
+///
+/// # Returns
+///
+/// The field, or an error if a file cannot be read, the sidecar has no
+/// dimensions, or the file does not hold exactly that many samples
+pub fn load_raw(path: impl AsRef<Path>) -> io::Result<FieldBuffer> {
+    let path = path.as_ref();
+    let sidecar = fs::read_to_string(path.with_extension("json"))?;
+    let dimension = |key: &str| -> io::Result<u32> {
+        let value = sidecar
+            .split_once(&format!("\"{key}\":"))
+            .and_then(|(_, rest)| rest.trim_start().split(|c: char| !c.is_ascii_digit()).next())
+            .and_then(|digits| digits.parse().ok());
+        value.ok_or_else(|| invalid_input(format!("the sidecar has no {key}")))
+    };
+    let (width, height) = (dimension("width")?, dimension("height")?);
+    let bytes = fs::read(path)?;
+    if bytes.len() as u64 != width as u64 * height as u64 * 4 {
+        return Err(invalid_input(format!(
+            "{width}x{height} samples need {} bytes, the file has {}",
+            width as u64 * height as u64 * 4,
+            bytes.len()
+        )));
+    }
+    let values = bytes
+        .chunks_exact(4)
+        .map(|sample| f32::from_le_bytes(sample.try_into().expect("chunks of 4 bytes")))
+        .collect();
+    Ok(FieldBuffer::from_vec(width, height, values))
+}
+
+/// Save a field as a Radiance HDR image
+///
+/// Every sample becomes a gray pixel. HDR keeps a shared exponent per pixel,
+/// so values far beyond 0-1 survive, but negative values are stored as 0.
+///
+/// # Returns
+///
+/// An error if the file cannot be encoded or written
+///
+/// # Example
+///
+/// ```rust
+/// use cells::io::save_hdr;
+/// use cells::FieldBuffer;
+///
+/// let field = FieldBuffer::from_par_fn(8, 8, |x, _| x as f32 * 10.0);
+/// let path = std::env::temp_dir().join("cells_doc_save_hdr.hdr");
+/// save_hdr(&field, &path).unwrap();
+/// let loaded = image::open(&path).unwrap().to_rgb32f();
+/// assert_eq!(loaded.get_pixel(7, 0)[0], 70.0);
+/// ```
+pub fn save_hdr(field: &FieldBuffer, path: impl AsRef<Path>) -> io::Result<()> {
+    let (width, height) = field.dimensions();
+    let pixels: Vec<image::Rgb<f32>> = field.as_slice().iter().map(|&value| image::Rgb([value.max(0.0); 3])).collect();
+    image::codecs::hdr::HdrEncoder::new(BufWriter::new(File::create(path)?))
+        .encode(&pixels, width as usize, height as usize)
+        .map_err(io::Error::other)
+}
+
+/// Save a field as a single-channel 32-bit float OpenEXR image
+///
+/// The samples are written as they are, without clamping or normalization, in
+/// a luminance channel named `Y`. This keeps raw data such as toroidal
+/// distances or fBm values in [-1, 1] intact for VFX tools.
+///
+/// # Arguments
+///
+/// * `field` - The field to save
+/// * `path` - The file to write
+///
+/// # Returns
+///
+/// An error if the file cannot be encoded or written
+///
+/// # Example
+///
+/// ```rust
+/// use cells::io::{load_exr, save_exr};
+/// use cells::FieldBuffer;
+///
+/// let field = FieldBuffer::from_par_fn(16, 8, |x, y| x as f32 * 0.37 - y as f32 * 2.5);
+/// let path = std::env::temp_dir().join("cells_doc_save_exr.exr");
+/// save_exr(&field, &path).unwrap();
+/// let loaded = load_exr(&path).unwrap();
+/// assert_eq!(loaded.dimensions(), field.dimensions());
+/// for (a, b) in loaded.as_slice().iter().zip(field.as_slice()) {
+///     assert!((a - b).abs() <= f32::EPSILON);
+/// }
+/// ```
+pub fn save_exr(field: &FieldBuffer, path: impl AsRef<Path>) -> Result<()> {
+    let (width, height) = field.dimensions();
+    let pixels = SpecificChannels::build()
+        .with_channel("Y")
+        .with_pixel_fn(|position: Vec2<usize>| (field.get(position.x() as u32, position.y() as u32),));
+    Image::from_channels((width as usize, height as usize), pixels)
+        .write()
+        .to_file(path)
+}
+
+/// Load the `Y` channel of an OpenEXR image into a field
+///
+/// # Arguments
+///
+/// * `path` - The file to read
+///
+/// # Returns
+///
+/// The field with the samples of the first layer containing a `Y` channel, or
+/// an error if the file cannot be read or has no such layer
+pub fn load_exr(path: impl AsRef<Path>) -> Result<FieldBuffer> {
+    let image = read()
+        .no_deep_data()
+        .largest_resolution_level()
+        .specific_channels()
+        .required("Y")
+        .collect_pixels(
+            |resolution, _| FieldBuffer::new(resolution.width() as u32, resolution.height() as u32),
+            |field, position, (y,): (f32,)| {
+                let width = field.width() as usize;
+                field.as_mut_slice()[position.y() * width + position.x()] = y;
+            },
+        )
+        .first_valid_layer()
+        .all_attributes()
+        .from_file(path)?;
+    Ok(image.layer_data.channel_data.pixels)
+}
+
+/// Save frames as an endlessly looping animated GIF
+///
+/// The frames are quantized to 8-bit gray as by
+/// [`FieldBuffer::to_luma_image`] and share one global palette of all 256
+/// gray levels, so no frame is quantized on its own and a looping animation
+/// does not flicker. GIF stores delays in hundredths of a second, so `delay`
+/// is rounded to them.
+///
+/// # Arguments
+///
+/// * `frames` - The frames in playing order, all of the same size
+/// * `path` - The file to write
+/// * `delay` - How long every frame is shown
+///
+/// # Returns
+///
+/// An error if there are no frames, they differ in size or exceed 65535
+/// pixels along an axis, or the file cannot be encoded or written
+///
+/// # Example
+///
+/// ```rust
+/// use std::time::Duration;
+/// use cells::io::save_gif;
+/// use cells::FieldBuffer;
+///
+/// let frames: Vec<FieldBuffer> = (0..4).map(|i| FieldBuffer::from_vec(8, 8, vec![i as f32 / 3.0; 64])).collect();
+/// let path = std::env::temp_dir().join("cells_doc_save_gif.gif");
+/// save_gif(&frames, &path, Duration::from_millis(40)).unwrap();
+/// assert!(save_gif(&[], &path, Duration::from_millis(40)).is_err());
+/// ```
+pub fn save_gif(frames: &[FieldBuffer], path: impl AsRef<Path>, delay: Duration) -> io::Result<()> {
+    let (width, height) = frame_dimensions(frames)?;
+    let (width, height) = match (u16::try_from(width), u16::try_from(height)) {
+        (Ok(width), Ok(height)) => (width, height),
+        _ => {
+            let reason = format!("GIF frames are at most 65535 pixels wide and high, got {width}x{height}");
+            return Err(invalid_input(reason));
+        }
+    };
+    let palette: Vec<u8> = (0..=255).flat_map(|gray| [gray; 3]).collect();
+    let delay = (delay.as_millis() as f64 / 10.0).round().min(u16::MAX as f64) as u16;
+
+    let mut encoder = gif::Encoder::new(BufWriter::new(File::create(path)?), width, height, &palette)
+        .map_err(io::Error::other)?;
+    encoder.set_repeat(gif::Repeat::Infinite).map_err(io::Error::other)?;
+    for field in frames {
+        let frame = gif::Frame {
+            width,
+            height,
+            delay,
+            buffer: Cow::Owned(field.to_luma_image().into_raw()),
+            ..Default::default()
+        };
+        encoder.write_frame(&frame).map_err(io::Error::other)?;
+    }
+    Ok(())
+}
+
+/// Save frames as an endlessly looping animated PNG (APNG)
+///
+/// The frames are quantized to 8-bit gray as by
+/// [`FieldBuffer::to_luma_image`]. Viewers without APNG support show the
+/// first frame.
+///
+/// # Arguments
+///
+/// * `frames` - The frames in playing order, all of the same size
+/// * `path` - The file to write
+/// * `delay` - How long every frame is shown, rounded to milliseconds
+///
+/// # Returns
+///
+/// An error if there are no frames or they differ in size, or the file cannot
+/// be encoded or written
+///
+/// # Example
+///
+/// ```rust
+/// use std::time::Duration;
+/// use cells::io::save_apng;
+/// use cells::FieldBuffer;
+///
+/// let frames: Vec<FieldBuffer> = (0..4).map(|i| FieldBuffer::from_vec(8, 8, vec![i as f32 / 3.0; 64])).collect();
+/// let path = std::env::temp_dir().join("cells_doc_save_apng.png");
+/// save_apng(&frames, &path, Duration::from_millis(40)).unwrap();
+/// // Viewers without APNG support see the first frame
+/// assert_eq!(image::open(&path).unwrap().to_luma8().get_pixel(0, 0)[0], 0);
+/// ```
+pub fn save_apng(frames: &[FieldBuffer], path: impl AsRef<Path>, delay: Duration) -> io::Result<()> {
+    let (width, height) = frame_dimensions(frames)?;
+    let delay = delay.as_millis().min(u16::MAX as u128) as u16;
+
+    let path = path.as_ref();
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(frames.len() as u32, 0).map_err(io::Error::other)?;
+    encoder.set_frame_delay(delay, 1000).map_err(io::Error::other)?;
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    for field in frames {
+        writer.write_image_data(field.to_luma_image().as_raw()).map_err(io::Error::other)?;
+    }
+    writer.finish().map_err(io::Error::other)
+}
+
+/// Save slices streamed in Z order as a raw 32-bit float volume with a JSON
+/// header
+///
+/// The samples are written as they are, little-endian with x running fastest,
+/// then y, then z, so the volume loads directly as a `[depth][height][width]`
+/// float array. Only one slice is held at a time. The header is written next
+/// to the volume at `path` with the extension `json`, e.g. `volume.json` for
+/// `volume.raw`:
+///
+/// ```json
+/// {"width": 128, "height": 128, "depth": 128, "format": "f32le", "order": "xyz"}
+/// ```
+///
+/// # Arguments
+///
+/// * `slices` - The slices from the front to the back, all of the same size
+/// * `path` - The file the samples are written to
+///
+/// # Returns
+///
+/// The `(width, height, depth)` of the volume, or an error if there are no
+/// slices, they differ in size, or a file cannot be written
+///
+/// # Example
+///
+/// ```rust
+/// use cells::io::save_raw_volume;
+/// use cells::FieldBuffer;
+///
+/// let slices = (0..3).map(|z| FieldBuffer::from_par_fn(4, 2, move |x, y| (x + 4 * y + 8 * z) as f32));
+/// let path = std::env::temp_dir().join("cells_doc_volume.raw");
+/// assert_eq!(save_raw_volume(slices, &path).unwrap(), (4, 2, 3));
+/// let bytes = std::fs::read(&path).unwrap();
+/// assert_eq!(bytes.len(), 4 * 2 * 3 * 4);
+/// assert_eq!(f32::from_le_bytes(bytes[4 * 9..4 * 10].try_into().unwrap()), 9.0);
+/// let header = std::fs::read_to_string(path.with_extension("json")).unwrap();
+/// assert!(header.contains(r#""depth": 3"#));
+/// ```
+pub fn save_raw_volume(
+    slices: impl IntoIterator<Item = FieldBuffer>,
+    path: impl AsRef<Path>,
+) -> io::Result<(u32, u32, u32)> {
+    let path = path.as_ref();
+    let mut file = BufWriter::new(File::create(path)?);
+    let mut dimensions = None;
+    let mut depth = 0;
+    for slice in slices {
+        match dimensions {
+            None => dimensions = Some(slice.dimensions()),
+            Some(expected) if expected != slice.dimensions() => return Err(mismatched(slice.dimensions(), expected)),
+            Some(_) => {}
+        }
+        let bytes: Vec<u8> = slice.as_slice().iter().flat_map(|value| value.to_le_bytes()).collect();
+        file.write_all(&bytes)?;
+        depth += 1;
+    }
+    file.flush()?;
+    let (width, height) = dimensions.ok_or_else(|| invalid_input("a volume needs at least one slice".to_string()))?;
+    let header = format!(
+        "{{\"width\": {width}, \"height\": {height}, \"depth\": {depth}, \"format\": \"f32le\", \"order\": \"xyz\"}}\n"
+    );
+    fs::write(path.with_extension("json"), header)?;
+    Ok((width, height, depth))
+}
+
+/// Save slices streamed in Z order as one grayscale PNG atlas of `cols` x
+/// `rows` tiles
+///
+/// The slices fill the atlas row by row from the top left, quantized like
+/// [`FieldBuffer::to_luma_image`] or, with a `bit_depth` of 16,
+/// [`FieldBuffer::to_luma16_image`]. The atlas is encoded one row of tiles at
+/// a time, so only `cols` slices are held at once however large it gets.
+///
+/// # Arguments
+///
+/// * `slices` - Exactly `cols * rows` slices, all of the same size
+/// * `cols` - The number of tiles across the atlas
+/// * `rows` - The number of tiles down the atlas
+/// * `path` - The file to write
+/// * `bit_depth` - Bits per sample, 8 or 16
+///
+/// # Returns
+///
+/// An error if the number of slices does not fill the atlas, they differ in
+/// size, or the file cannot be encoded or written
+///
+/// # Example
+///
+/// ```rust
+/// use cells::io::save_slice_atlas;
+/// use cells::FieldBuffer;
+///
+/// let slices = (0..6).map(|z| FieldBuffer::from_vec(4, 4, vec![z as f32 / 5.0; 16]));
+/// let path = std::env::temp_dir().join("cells_doc_atlas.png");
+/// save_slice_atlas(slices, 3, 2, &path, 8).unwrap();
+/// let atlas = image::open(&path).unwrap().to_luma8();
+/// assert_eq!(atlas.dimensions(), (12, 8));
+/// assert_eq!([atlas.get_pixel(0, 0)[0], atlas.get_pixel(11, 7)[0]], [0, 255]);
+/// ```
+pub fn save_slice_atlas(
+    slices: impl IntoIterator<Item = FieldBuffer>,
+    cols: u32,
+    rows: u32,
+    path: impl AsRef<Path>,
+    bit_depth: u8,
+) -> io::Result<()> {
+    let tiles = cols as u64 * rows as u64;
+    let mut slices = slices.into_iter();
+    let first = slices.next().ok_or_else(|| invalid_input("an atlas needs at least one slice".to_string()))?;
+    let (width, height) = first.dimensions();
+    let (atlas_width, atlas_height) = match (width.checked_mul(cols), height.checked_mul(rows)) {
+        (Some(atlas_width), Some(atlas_height)) if tiles > 0 => (atlas_width, atlas_height),
+        _ => return Err(invalid_input(format!("cannot lay out {width}x{height} slices in a {cols}x{rows} atlas"))),
+    };
+
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), atlas_width, atlas_height);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(match bit_depth {
+        16 => png::BitDepth::Sixteen,
+        _ => png::BitDepth::Eight,
+    });
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    let mut stream = writer.stream_writer().map_err(io::Error::other)?;
+    let sample_bytes = |slice: &FieldBuffer| -> Vec<u8> {
+        match bit_depth {
+            16 => slice.to_luma16_image().into_raw().into_iter().flat_map(u16::to_be_bytes).collect(),
+            _ => slice.to_luma_image().into_raw(),
+        }
+    };
+    let row_bytes = width as usize * if bit_depth == 16 { 2 } else { 1 };
+
+    let mut slices = std::iter::once(first).chain(slices);
+    let mut written = 0;
+    for _ in 0..rows {
+        let mut tile_row = Vec::with_capacity(cols as usize);
+        for slice in slices.by_ref().take(cols as usize) {
+            if slice.dimensions() != (width, height) {
+                return Err(mismatched(slice.dimensions(), (width, height)));
+            }
+            tile_row.push(sample_bytes(&slice));
+        }
+        written += tile_row.len() as u64;
+        if tile_row.len() < cols as usize {
+            return Err(invalid_input(format!("a {cols}x{rows} atlas holds {tiles} slices, got {written}")));
+        }
+        for y in 0..height as usize {
+            for tile in &tile_row {
+                stream.write_all(&tile[y * row_bytes..(y + 1) * row_bytes])?;
+            }
+        }
+    }
+    if slices.next().is_some() {
+        return Err(invalid_input(format!("a {cols}x{rows} atlas holds {tiles} slices, got more")));
+    }
+    stream.finish().map_err(io::Error::other)
+}
+
+/// Save a texture rendered band by band as a grayscale PNG file
+///
+/// The bands are quantized like [`FieldBuffer::to_luma_image`] or, with a
+/// `bit_depth` of 16, [`FieldBuffer::to_luma16_image`], and encoded as they
+/// are rendered, so only one band of `band_rows` rows is held at once. The
+/// file decodes to the same samples as the whole texture saved at once.
+///
+/// # Arguments
+///
+/// * `source` - The texture to render, see [`crate::band`]
+/// * `band_rows` - The number of rows rendered at a time
+/// * `path` - The file to write
+/// * `bit_depth` - Bits per sample, 8 or 16
+///
+/// # Returns
+///
+/// An error if the texture is empty or the file cannot be encoded or written
+///
+/// # Example
+///
+/// ```rust
+/// use cells::io::save_png_bands;
+/// use cells::FieldBuffer;
+///
+/// let field = FieldBuffer::from_par_fn(20, 30, |x, y| (x + y) as f32 / 48.0);
+/// let path = std::env::temp_dir().join("cells_doc_png_bands.png");
+/// save_png_bands(&field, 7, &path, 16).unwrap();
+/// assert_eq!(image::open(&path).unwrap().to_luma16(), field.to_luma16_image());
+/// ```
+pub fn save_png_bands(
+    source: &impl BandSource,
+    band_rows: u32,
+    path: impl AsRef<Path>,
+    bit_depth: u8,
+) -> io::Result<()> {
+    let (width, height) = source.dimensions();
+    if width == 0 || height == 0 {
+        return Err(invalid_input(format!("cannot save an empty {width}x{height} texture as a PNG")));
+    }
+    let path = path.as_ref();
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(match bit_depth {
+        16 => png::BitDepth::Sixteen,
+        _ => png::BitDepth::Eight,
+    });
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    let mut stream = writer.stream_writer().map_err(io::Error::other)?;
+    for rows in bands(height, band_rows) {
+        log::trace!("rendering rows {rows:?} of {}", path.display());
+        let band = source.render(rows);
+        let bytes: Vec<u8> = match bit_depth {
+            16 => band.to_luma16_image().into_raw().into_iter().flat_map(u16::to_be_bytes).collect(),
+            _ => band.to_luma_image().into_raw(),
+        };
+        stream.write_all(&bytes)?;
+    }
+    stream.finish().map_err(io::Error::other)?;
+    log::info!("wrote {} ({width}x{height}, {bit_depth}-bit, in bands of {band_rows} rows)", path.display());
+    Ok(())
+}
+
+/// Add text chunks of keyword and text pairs to a PNG file
+///
+/// The chunks go right after the header, where every decoder sees them
+/// before the image data. Texts in Latin-1 are stored as `tEXt` chunks, the
+/// others as uncompressed UTF-8 `iTXt` chunks.
+///
+/// # Returns
+///
+/// An error if the file is not a PNG file, a keyword is not 1 to 79 Latin-1
+/// characters, or the file cannot be read or written
+///
+/// # Example
+///
+/// ```rust
+/// use cells::io::{add_png_text, read_png_text};
+/// use cells::FieldBuffer;
+///
+/// let path = std::env::temp_dir().join("cells_doc_add_png_text.png");
+/// FieldBuffer::new(4, 4).to_luma_image().save(&path).unwrap();
+/// add_png_text(&path, &[("Software".into(), "cells".into()), ("Title".into(), "Dünen 砂".into())]).unwrap();
+/// let text = read_png_text(&path).unwrap();
+/// assert!(text.contains(&("Title".to_string(), "Dünen 砂".to_string())));
+/// assert!(text.contains(&("Software".to_string(), "cells".to_string())));
+/// ```
+pub fn add_png_text(path: impl AsRef<Path>, entries: &[(String, String)]) -> io::Result<()> {
+    use png::text_metadata::{EncodableTextChunk, ITXtChunk, TEXtChunk};
+
+    let path = path.as_ref();
+    let bytes = fs::read(path)?;
+    if bytes.len() < 33 || !bytes.starts_with(b"\x89PNG\r\n\x1a\n") || &bytes[12..16] != b"IHDR" {
+        return Err(invalid_input(format!("{} is not a PNG file", path.display())));
+    }
+    let header_end = 20 + u32::from_be_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize;
+    let mut chunks = Vec::new();
+    for (keyword, text) in entries {
+        let encoded = match text.chars().all(|c| (c as u32) < 256) {
+            true => TEXtChunk::new(keyword.as_str(), text.as_str()).encode(&mut chunks),
+            false => ITXtChunk::new(keyword.as_str(), text.as_str()).encode(&mut chunks),
+        };
+        encoded.map_err(|err| invalid_input(format!("cannot store `{keyword}` in a PNG file: {err}")))?;
+    }
+    fs::write(path, [&bytes[..header_end], &chunks, &bytes[header_end..]].concat())
+}
+
+/// Save an 8-bit or 16-bit grayscale or RGB image, with or without alpha,
+/// as a PNG file recording the transfer function its samples are encoded with
+///
+/// Linear samples get a `gAMA` chunk of 1 and [`TransferFunction::Gamma`] one
+/// of `1 / gamma`; sRGB samples get an `sRGB` chunk with the `gAMA` and
+/// `cHRM` chunks matching it, for decoders that ignore it.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::color::TransferFunction;
+/// use cells::io::save_png;
+/// use image::{Rgb, RgbImage};
+///
+/// let path = std::env::temp_dir().join("cells_doc_save_png.png");
+/// save_png(&RgbImage::from_pixel(4, 2, Rgb([188, 96, 0])), &path, TransferFunction::Srgb).unwrap();
+/// let decoder = png::Decoder::new(std::fs::File::open(&path).unwrap());
+/// assert!(decoder.read_info().unwrap().info().srgb.is_some());
+/// assert_eq!(image::open(&path).unwrap().to_rgb8().get_pixel(3, 1), &Rgb([188, 96, 0]));
+/// ```
+pub fn save_png<P>(
+    image: &ImageBuffer<P, Vec<P::Subpixel>>,
+    path: impl AsRef<Path>,
+    transfer: TransferFunction,
+) -> io::Result<()>
+where
+    P: PixelWithColorType,
+    [P::Subpixel]: EncodableLayout,
+{
+    let (color, depth) = match P::COLOR_TYPE {
+        ExtendedColorType::L8 => (png::ColorType::Grayscale, png::BitDepth::Eight),
+        ExtendedColorType::La8 => (png::ColorType::GrayscaleAlpha, png::BitDepth::Eight),
+        ExtendedColorType::Rgb8 => (png::ColorType::Rgb, png::BitDepth::Eight),
+        ExtendedColorType::Rgba8 => (png::ColorType::Rgba, png::BitDepth::Eight),
+        ExtendedColorType::L16 => (png::ColorType::Grayscale, png::BitDepth::Sixteen),
+        ExtendedColorType::La16 => (png::ColorType::GrayscaleAlpha, png::BitDepth::Sixteen),
+        ExtendedColorType::Rgb16 => (png::ColorType::Rgb, png::BitDepth::Sixteen),
+        ExtendedColorType::Rgba16 => (png::ColorType::Rgba, png::BitDepth::Sixteen),
+        color => return Err(invalid_input(format!("PNG files cannot store {color:?} samples"))),
+    };
+    let mut data = image.as_raw().as_bytes().to_vec();
+    if depth == png::BitDepth::Sixteen {
+        // PNG samples are big-endian
+        for sample in data.chunks_exact_mut(2) {
+            let value = u16::from_ne_bytes([sample[0], sample[1]]);
+            sample.copy_from_slice(&value.to_be_bytes());
+        }
+    }
+
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), image.width(), image.height());
+    encoder.set_color(color);
+    encoder.set_depth(depth);
+    match transfer {
+        TransferFunction::Linear => encoder.set_source_gamma(png::ScaledFloat::new(1.0)),
+        TransferFunction::Srgb => encoder.set_srgb(png::SrgbRenderingIntent::Perceptual),
+        TransferFunction::Gamma(gamma) => encoder.set_source_gamma(png::ScaledFloat::new(1.0 / gamma)),
+    }
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    writer.write_image_data(&data).map_err(io::Error::other)?;
+    writer.finish().map_err(io::Error::other)
+}
+
+/// Read the keyword and text of every `tEXt`, `zTXt` and `iTXt` chunk before
+/// the image data of a PNG file
+pub fn read_png_text(path: impl AsRef<Path>) -> io::Result<Vec<(String, String)>> {
+    let decoder = png::Decoder::new(io::BufReader::new(File::open(path)?));
+    let reader = decoder.read_info().map_err(io::Error::other)?;
+    let info = reader.info();
+    let mut text: Vec<(String, String)> =
+        info.uncompressed_latin1_text.iter().map(|chunk| (chunk.keyword.clone(), chunk.text.clone())).collect();
+    for chunk in &info.compressed_latin1_text {
+        text.push((chunk.keyword.clone(), chunk.get_text().map_err(io::Error::other)?));
+    }
+    for chunk in &info.utf8_text {
+        text.push((chunk.keyword.clone(), chunk.get_text().map_err(io::Error::other)?));
+    }
+    Ok(text)
+}
+
+/// The samples of a field as they are quantized with `transfer`
+fn encode(field: &FieldBuffer, transfer: TransferFunction) -> Cow<'_, FieldBuffer> {
+    match transfer {
+        TransferFunction::Linear => Cow::Borrowed(field),
+        _ => Cow::Owned(transfer.encode_field(field)),
+    }
+}
+
+/// The shared dimensions of a non-empty sequence of frames
+fn frame_dimensions(frames: &[FieldBuffer]) -> io::Result<(u32, u32)> {
+    let first = frames.first().ok_or_else(|| invalid_input("an animation needs at least one frame".to_string()))?;
+    let dimensions = first.dimensions();
+    match frames.iter().find(|frame| frame.dimensions() != dimensions) {
+        Some(frame) => Err(mismatched(frame.dimensions(), dimensions)),
+        None => Ok(dimensions),
+    }
+}
+
+/// An error rejecting a frame or slice of the `found` dimensions among ones of
+/// the `expected` dimensions
+fn mismatched(found: (u32, u32), expected: (u32, u32)) -> io::Error {
+    invalid_input(format!(
+        "an image is {}x{} but the first is {}x{}",
+        found.0, found.1, expected.0, expected.1
+    ))
+}
+
+/// A sample as a JSON number, or `null` if it is not finite
+fn json_number(value: f32) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+/// A quoted and escaped JSON string
+fn json_string(s: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The [`CellsError`] of an I/O error of `path`: the encoders report what
+/// they cannot encode as invalid input or data
+fn io_error(path: &Path, err: io::Error) -> CellsError {
+    match err.kind() {
+        io::ErrorKind::InvalidInput | io::ErrorKind::InvalidData => {
+            CellsError::Encode { path: path.to_path_buf(), reason: err.to_string() }
+        }
+        _ => CellsError::io(path, &err),
+    }
+}
+
+/// An invalid input error for `reason`
+fn invalid_input(reason: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, reason)
+}
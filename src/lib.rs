@@ -0,0 +1,141 @@
+//! # Cells
+//!
+//! A simple naive implementation of Aardbei Textuur Gevaarte 2.0 /
+//! Solar Texture Studio 1.5 - Style Cells.
+//!
+//! The crate generates seamless, tileable textures:
+//!
+//! * [`voronoi`] - Voronoi cell textures based on toroidal distances
+//! * [`noise`] - fractal Perlin noise textures
+//! * [`volume`] - 3D noise volumes tiling along every axis, slice by slice
+//! * [`gabor`] - sparse Gabor noise with controllable orientation
+//! * [`grain`] - wood grain and marble veins warped by turbulence
+//! * [`pattern`] - regular patterns such as brick walls
+//! * [`cracks`] - crack masks along the borders of Voronoi cells
+//! * [`speckle`] - scattered splats for dust, stars and imperfections
+//! * [`scratches`] - soft line segments for wear masks
+//! * [`reaction`] - Gray–Scott reaction–diffusion textures
+//! * [`color`] - palettes and random colors of cell mosaics
+//! * [`filter`] - image filters such as Ile's directional blur
+//! * [`smoothing`] - edge-preserving median, bilateral and Kuwahara filters
+//! * [`flow`] - flow fields, blurs that follow them and warps along them
+//! * [`material`] - normal maps and other material maps from heightmaps
+//! * [`resample`] - resampling fields to other sizes and mip chains
+//! * [`pack`] - packing several maps into the channels of one texture
+//! * [`texture`] - DDS and KTX2 containers with mip levels, optionally BC4/BC5 compressed
+//! * [`blend`] - compositing two fields with blend modes
+//! * [`morphology`] - dilation, erosion, opening and closing
+//! * [`distance`] - distance transforms turning masks into falloff fields
+//! * [`automata`] - cellular automata such as the cave smoothing of masks
+//! * [`tone`] - per-sample tone adjustments such as levels, curves and masks
+//! * [`animate`] - numbered frames that loop in time, and flipbook sprite sheets
+//! * [`band`] - rendering very large textures band by band within bounded memory
+//! * [`pipeline`] - graphs of generators and filters described in TOML
+//! * [`manifest`] - records of the parameters a texture was made with, to make it again
+//! * [`serve`] - an HTTP server previewing generators with parameters from the URL
+//! * [`tiling`] - checks that textures tile without visible seams, and tiled previews
+//! * [`transform`] - lossless transforms such as scrolling with wraparound
+//! * [`ffi`] - a C interface to the generators, declared in `include/cells.h`
+//! * [`wasm`] - RGBA textures for JavaScript, exported through `wasm-bindgen`
+//! * `python` - numpy arrays for Python, built by `maturin` with the `python` feature
+//! * `gpu` - Voronoi diagrams and directional blurs computed with wgpu, with the `gpu` feature
+//!
+//! [`Point`] and [`toroidal_distance`] are exported so downstream code can
+//! build its own samplers on the same wrapping unit square.
+//!
+//! The crate logs through the [`log`](https://docs.rs/log) facade and never
+//! prints: the start and duration of every heavy stage at debug level, files
+//! written at info level and suspicious parameters, such as blurs wider than
+//! the texture, as warnings.
+//!
+//! Reading and writing files, the [`io`], [`manifest`] and [`texture`]
+//! modules, needs the `file-io` feature, which the default `cli` feature of
+//! the command line tool enables.
+
+/// Implement `Serialize` and `Deserialize` through `Display` and `FromStr`,
+/// so configuration files spell values like the command line does
+macro_rules! serde_via_str {
+    ($($type:ty),+) => {$(
+        impl serde::Serialize for $type {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $type {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> ::std::result::Result<Self, D::Error> {
+                let s = <String as serde::Deserialize>::deserialize(deserializer)?;
+                s.parse().map_err(serde::de::Error::custom)
+            }
+        }
+    )+};
+}
+
+/// Run a heavy stage of the crate, logging its start with `details` and its
+/// duration at debug level
+///
+/// The stage is timed only while debug messages are enabled, so embedding
+/// applications without a logger pay nothing.
+pub(crate) fn stage<T>(name: &str, details: std::fmt::Arguments<'_>, work: impl FnOnce() -> T) -> T {
+    if !log::log_enabled!(log::Level::Debug) {
+        return work();
+    }
+    log::debug!("{name}: {details}");
+    let start = std::time::Instant::now();
+    let result = work();
+    log::debug!("{name}: done in {:.2?}", start.elapsed());
+    result
+}
+
+pub mod animate;
+pub mod automata;
+pub mod band;
+#[cfg(feature = "block-compression")]
+pub mod bc;
+pub mod blend;
+pub mod color;
+pub mod cracks;
+pub mod distance;
+pub mod error;
+pub mod ffi;
+pub mod field;
+pub mod filter;
+pub mod flow;
+pub mod gabor;
+pub mod geometry;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+pub mod grain;
+pub mod grid;
+#[cfg(feature = "file-io")]
+pub mod io;
+#[cfg(feature = "file-io")]
+pub mod manifest;
+pub mod material;
+pub mod morphology;
+pub mod noise;
+pub mod pack;
+pub mod pattern;
+pub mod pipeline;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod reaction;
+pub mod resample;
+pub mod sampling;
+pub mod scratches;
+#[cfg(feature = "serve")]
+pub mod serve;
+pub mod smoothing;
+pub mod speckle;
+#[cfg(feature = "file-io")]
+pub mod texture;
+pub mod tiling;
+pub mod tone;
+pub mod transform;
+pub mod volume;
+pub mod voronoi;
+pub mod wasm;
+
+pub use error::CellsError;
+pub use field::{FieldBuffer, OutputChannels};
+pub use geometry::{toroidal_distance, toroidal_distance_sq, toroidal_distance_with, DistanceMetric, Point, Weighting};
@@ -1,334 +1,3240 @@
-use rayon::prelude::*;
-use rand::Rng;
-use image::{ImageBuffer, Rgb};
-use noise::{NoiseFn, Perlin};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::str::FromStr;
+use std::ops::ControlFlow;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
 
-const SIZE: u32 = 512;
-const NUM_POINTS: usize = 240;
-const BLUR_RADIUS: i32 = 3;
+use clap::builder::RangedU64ValueParser;
+use clap::{ArgAction, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use image::DynamicImage;
+use indicatif::{ProgressBar, ProgressStyle};
 
-#[derive(Clone, Copy)]
-struct Point { x: f32, y: f32 }
+use cells::animate::{flipbook, frame_path};
+use cells::automata::{cellular_smooth_with, DEFAULT_BIRTH_LIMIT, DEFAULT_DEATH_LIMIT};
+use cells::band::{BandSource, GaussianBlurred, Stretched};
+use cells::blend::{blend, blend_masked, BlendMode};
+use cells::color::{colormap, CellColoring, Colormap, HsvRanges, Palette, TransferFunction};
+use cells::cracks::{generate_cracks, CrackParams};
+use cells::distance::distance_transform;
+use cells::filter::{
+    directional_blur_field_with_progress, equalize_histogram, gaussian_blur, iterated_blur, normalize_field,
+    normalize_percentile, parse_percentiles, BlurKernel, BlurParams, BlurSampling, DirectionMapping, Normalization,
+    Sharpen,
+};
+use cells::flow::{angle_offsets, curl_flow, flow_blur_field_with_progress, signed_offsets, sobel, warp};
+#[cfg(feature = "gpu")]
+use cells::gpu::Gpu;
+use cells::gabor::{generate_gabor, generate_gabor_along, GaborOrientation, GaborParams};
+use cells::grain::{generate_marble, generate_wood, GrainParams};
+use cells::io::{
+    load_exr, load_raw, save_apng, save_field_with, save_gif, save_png, save_png_bands, save_raw, save_raw_volume,
+    save_slice_atlas, FileFormat,
+};
+use cells::manifest::{Generators, Manifest};
+use cells::material::{curvature, height_to_ao, height_to_normal_with, signed_curvature, NormalConvention};
+use cells::morphology::{Morphology, StructuringElement};
+use cells::noise::{
+    generate_fbm_field, generate_perlin_field_with_progress, generate_perlin_frame_with_progress, warn_aliased_octaves,
+    FbmParams, NoiseKind, NoiseStyle, PerlinBands,
+};
+use cells::pack::pack_channels;
+use cells::pattern::{generate_bricks, generate_truchet, BrickParams, TruchetStyle};
+use cells::pipeline::{NodeCache, Pipeline};
+use cells::reaction::{generate_reaction_diffusion_with, Chemical, ReactionDiffusionParams, ReactionPreset, Seeding};
+use cells::resample::{mip_chain, resample, ResampleFilter};
+use cells::sampling::{check_hex_lattice, PointDistribution};
+use cells::scratches::{generate_scratches, ScratchParams};
+#[cfg(feature = "serve")]
+use cells::serve::PreviewServer;
+use cells::smoothing::{bilateral_filter, kuwahara, median_filter};
+use cells::speckle::{generate_speckle, SpeckleParams, SplatShape};
+use cells::texture::{save_dds, save_ktx2, Texture, TextureFormat};
+use cells::tiling::{interior_discontinuity, seam_discontinuity, tile_preview, tile_preview_field};
+use cells::tone::{invert, posterize, posterize_dithered, Levels, Mask, ToneCurve};
+use cells::transform::{offset, offset_field};
+use cells::{DistanceMetric, FieldBuffer, OutputChannels, Weighting};
+use cells::volume::{generate_volume_slice, generate_volume_texture_slice};
+use cells::voronoi::{
+    generate_colored_cells, generate_voronoi_cell_ids, generate_voronoi_cells, generate_voronoi_features,
+    generate_voronoi_field_with_progress, generate_voronoi_frame_with_progress, Feature, MAX_OCTAVES, VoronoiBackend,
+    VoronoiBands, VoronoiParams, WorleyReturn,
+};
 
-/// Calculate the toroidal distance between two points
-///
-/// This function ensures that the distance wraps around the edges of the texture,
-/// creating a seamless, tileable result.
-///
-/// # Arguments
-///
-/// * `p1` - The first point
-/// * `p2` - The second point
-///
-/// # Returns
-///
-/// The toroidal distance between the two points
-///
-/// # Example
-///
-/// ```rust
-/// let p1 = Point { x: 0.1, y: 0.1 };
-/// let p2 = Point { x: 0.9, y: 0.9 };
-/// let distance = toroidal_distance(p1, p2);
-/// assert!(distance < 0.3); // The wrapped distance should be small
-/// ```
-fn toroidal_distance(p1: Point, p2: Point) -> f32 {
-    let dx = (p1.x - p2.x).abs();
-    let dy = (p1.y - p2.y).abs();
-    let dx = dx.min(1.0 - dx);
-    let dy = dy.min(1.0 - dy);
-    (dx * dx + dy * dy).sqrt()
-}
-
-/// Generate a tileable Voronoi diagram
-///
-/// This function creates a Voronoi diagram that can be tiled seamlessly.
-/// The resulting image uses only the red channel, with brighter values
-/// representing areas further from Voronoi cell centers.
-///
-/// # Algorithm
+/// Generate tileable ATG2 / STS1.5 style cell textures
+#[derive(Parser, Debug)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    generate: GenerateArgs,
+
+    #[command(flatten)]
+    output: OutputArgs,
+
+    /// Report more on stderr: -v notes such as the files the library writes, -vv every stage with its parameters
+    /// and duration, -vvv everything
+    #[arg(short, long, action = ArgAction::Count, global = true, conflicts_with = "quiet")]
+    verbose: u8,
+
+    /// Report less on stderr: -q only errors and no progress bars, -qq nothing
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    quiet: u8,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Pack generated maps or image files into the channels of one RGBA texture
+    Pack(Box<PackArgs>),
+    /// Print how strongly image files jump across their wrap-around seams
+    Verify(VerifyArgs),
+    /// Run filters over an image file, in the order given
+    Filter(FilterArgs),
+    /// Write an ambient occlusion map of a heightmap image
+    Ao(AoArgs),
+    /// Write curvature maps of a heightmap image, e.g. as wear masks
+    Curvature(CurvatureArgs),
+    /// Write Sobel edge maps of an image file: edge strength and a direction map for the blur
+    Sobel(SobelArgs),
+    /// Displace an image file along two offset maps or an angle map, e.g. to melt cells into organic shapes
+    Warp(WarpArgs),
+    /// Blend two generated maps or image files with a blend mode, optionally through a mask
+    Blend(Box<BlendArgs>),
+    /// Run a pipeline of generators and filters described in a TOML file
+    Run(RunArgs),
+    /// Write a generated map as numbered frames that loop in time, or as one flipbook sprite sheet
+    Animate(Box<AnimateArgs>),
+    /// Write a fractal noise volume tiling in x, y and z as slice images, a slice atlas or raw floats
+    Volume(VolumeArgs),
+    /// Write a Voronoi or Perlin map to a PNG band by band, so 8K textures and larger fit into little memory
+    Stream(Box<StreamArgs>),
+    /// Write a brick wall heightmap with mortar, beveled edges and varying brick brightness
+    Bricks(BricksArgs),
+    /// Write random soft scratches for wear masks
+    Scratches(ScratchesArgs),
+    /// Write scattered splats for dust, stars or surface imperfections
+    Speckle(SpeckleArgs),
+    /// Write wood grain, turbulent rings around the center of the texture
+    Wood(GrainArgs),
+    /// Write marble, turbulent veins across the texture
+    Marble(GrainArgs),
+    /// Write a crack mask along Voronoi cell borders, fading out in places
+    Cracks(CracksArgs),
+    /// Write Voronoi cells on a hexagonal lattice, such as honeycomb or scales
+    Hex(HexArgs),
+    /// Write Truchet tiles, arcs or diagonals turned at random that join into paths
+    Truchet(TruchetArgs),
+    /// Write Gabor noise, isotropic or streaked along an angle or an orientation map
+    Gabor(GaborArgs),
+    /// Write a Gray–Scott reaction–diffusion texture, such as coral or worms
+    ReactionDiffusion(ReactionDiffusionArgs),
+    /// Smooth a mask image into rounded caves with a cellular automaton
+    Cellular(CellularArgs),
+    /// Write a preview of an image file repeated COLSxROWS times, to check its seams by eye
+    Tile(TileArgs),
+    /// Print the parameters a PNG texture or manifest records it was made with
+    Info(InfoArgs),
+    /// Make the textures of a manifest or PNG texture again, exactly as recorded
+    Rerun(RerunArgs),
+    /// Answer HTTP requests such as /voronoi?size=512&seed=42 with PNG previews, to tune parameters in a browser
+    #[cfg(feature = "serve")]
+    Serve(ServeArgs),
+    /// Manage the cache of pipeline node outputs kept by `run`
+    Cache(CacheArgs),
+}
+
+/// Options shaping the generated maps
+#[derive(clap::Args, Debug)]
+struct GenerateArgs {
+    /// Width and height of the generated textures in pixels
+    #[arg(long, default_value_t = 512, value_parser = clap::value_parser!(u32).range(1..))]
+    size: u32,
+
+    /// Number of Voronoi cell centers
+    #[arg(long, default_value_t = 240, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    points: usize,
+
+    /// Base radius of the directional blur, multiplied by --blur-growth on every blur step
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(i32).range(0..))]
+    blur_radius: i32,
+
+    /// Number of blur and normalize steps producing the blurred texture
+    #[arg(long, default_value_t = 4)]
+    blur_steps: u32,
+
+    /// Factor the blur radius grows by on every step
+    #[arg(long, default_value_t = 2.0, value_parser = parse_positive)]
+    blur_growth: f64,
+
+    /// How the texture is normalized after every blur step: stretch (min-max), clip:<low>,<high>
+    /// (stretch between percentiles), equalize (flat histogram) or none
+    #[arg(long, default_value_t = Normalization::Stretch)]
+    normalize: Normalization,
+
+    /// Stretch the Voronoi distances and every blur step between these percentiles, e.g. 1,99, so
+    /// a few outlier pixels don't crush the contrast
+    #[arg(long, value_parser = parse_percentiles, conflicts_with = "normalize")]
+    clip: Option<(f32, f32)>,
+
+    /// Levels of the blurred texture: IN_BLACK,IN_WHITE,GAMMA,OUT_BLACK,OUT_WHITE such as
+    /// 0.1,0.9,1.6,0,1
+    #[arg(long)]
+    levels: Option<Levels>,
+
+    /// Tone curve of the blurred texture, applied after --levels: IN:OUT points such as
+    /// 0:0,0.5:0.3,1:1
+    #[arg(long)]
+    curve: Option<ToneCurve>,
+
+    /// Sharpen the blurred texture after the last blur step with an unsharp mask: SIGMA,AMOUNT or
+    /// SIGMA,AMOUNT,THRESHOLD such as 2,0.8, the threshold keeping faint details unamplified
+    #[arg(long)]
+    sharpen: Option<Sharpen>,
+
+    /// Reduce the blurred texture to this many evenly spaced levels after the tone adjustments,
+    /// e.g. 6 for cell shading
+    #[arg(long, value_parser = parse_levels)]
+    posterize: Option<u32>,
+
+    /// Break up the bands of --posterize with a seamless 8x8 ordered dither
+    #[arg(long, requires = "posterize")]
+    dither: bool,
+
+    /// Blend the Perlin texture onto the Voronoi texture before blurring, e.g. multiply to modulate
+    /// its brightness: add, multiply, screen, overlay, min, max, difference or lerp
+    #[arg(long)]
+    perlin_blend: Option<BlendMode>,
+
+    /// Strength of --perlin-blend from 0 (no effect) to 1
+    #[arg(long, default_value_t = 1.0, value_parser = parse_non_negative, requires = "perlin_blend")]
+    perlin_blend_opacity: f32,
+
+    /// How blur taps between pixels are read: bilinear (smooth) or nearest (the original look)
+    #[arg(long, default_value_t = BlurSampling::Bilinear)]
+    blur_sampling: BlurSampling,
+
+    /// Weighting of the taps along each streak: box, triangle or gaussian:<sigma> (sigma in taps)
+    #[arg(long, default_value_t = BlurKernel::Box)]
+    blur_kernel: BlurKernel,
+
+    /// How the Voronoi values steer the blur: full360 (the default pipeline), half180 or signed-flow,
+    /// which streaks one way with the Perlin map setting the streak length
+    #[arg(long, default_value_t = DirectionMapping::Full360)]
+    direction_mapping: DirectionMapping,
+
+    /// Scale the blur length of every pixel by a grayscale image of the texture size, from no blur at black to
+    /// the full radius at white, e.g. an f2-f1 map to streak inside the cells only; replaces the Perlin map of
+    /// signed-flow
+    #[arg(long, value_name = "MAP", conflicts_with = "flow")]
+    blur_length_map: Option<PathBuf>,
+
+    /// Trace the blur along the contour lines of the Perlin map instead of steering it by the Voronoi values;
+    /// with signed-flow only forwards
+    #[arg(long)]
+    flow: bool,
+
+    /// Placement of the cell centers: uniform, poisson:<min-dist>, grid:<cols>x<rows>:<jitter> or
+    /// hex:<cols>x<rows>:<jitter>; --points is ignored unless uniform
+    #[arg(long, default_value_t = PointDistribution::Uniform)]
+    distribution: PointDistribution,
+
+    /// Place the cell centers on a jittered COLSxROWS lattice, e.g. 16x16
+    #[arg(long, value_parser = parse_dimensions, conflicts_with = "distribution")]
+    grid: Option<(u32, u32)>,
+
+    /// Displacement of the lattice points, from 0 (regular cells) to 1 (random within their cell)
+    #[arg(long, default_value_t = 1.0, requires = "grid", value_parser = parse_unit_interval)]
+    jitter: f32,
+
+    /// Lloyd relaxation iterations evening out the cell sizes
+    #[arg(long, default_value_t = 0)]
+    relax: u32,
+
+    /// Voronoi feature: the distances f1, f2 or f2-f1, or cell-value, a random value per cell
+    #[arg(long, default_value_t = Feature::F1)]
+    feature: Feature,
+
+    /// Values of the cells of --feature cell-value instead of random ones, by point and repeating, e.g. 0.2,0.5,0.9
+    #[arg(long, value_delimiter = ',', allow_negative_numbers = true)]
+    cell_values: Vec<f32>,
+
+    /// Blend --feature cell-value into the neighboring cell over this F2-F1 distance in texture widths, such as
+    /// 0.02, so the values meet without steps on the cell boundaries
+    #[arg(long, default_value_t = 0.0)]
+    value_smoothing: f32,
+
+    /// Distance metric: euclidean, manhattan, chebyshev or minkowski:<p>
+    #[arg(long, default_value_t = DistanceMetric::Euclidean)]
+    metric: DistanceMetric,
+
+    /// Multiply the x and y offsets of the distances by SX,SY, e.g. 1,3 for cells three times
+    /// as wide as they are tall, like bark or muscle fiber
+    #[arg(long, default_value = "1,1", value_parser = parse_scale)]
+    cell_scale: (f32, f32),
+
+    /// Number of Voronoi diagrams summed into the texture, each with four times the points and
+    /// --voronoi-persistence times the weight of the one before, for fractal cells
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..=MAX_OCTAVES as i64))]
+    voronoi_octaves: u32,
+
+    /// Weight factor between Voronoi octaves; lower keeps the large cells, near 1 is rougher
+    #[arg(long, default_value_t = 0.5, value_parser = parse_positive_f32)]
+    voronoi_persistence: f32,
+
+    /// Give every cell a random weight from MIN..MAX to vary the cell sizes, e.g. 0.0..0.05
+    #[arg(long, value_parser = parse_range)]
+    weight_range: Option<(f32, f32)>,
+
+    /// How the weights change the distances: additive (distance - weight, curved edges)
+    /// or multiplicative (distance / weight, round edges, positive weights only)
+    #[arg(long, default_value_t = Weighting::Additive, requires = "weight_range")]
+    weighting: Weighting,
+
+    /// Make the Voronoi cell centers bright and the edges dark
+    #[arg(long)]
+    invert: bool,
+
+    /// Move every Voronoi sample position by up to this fraction of the texture along
+    /// Perlin noise, bending the cell edges; 0 disables the warp
+    #[arg(long, default_value_t = 0.0, value_parser = parse_non_negative)]
+    warp_strength: f32,
+
+    /// Base frequency of the noise steering --warp-strength
+    #[arg(long, default_value_t = 4.0, value_parser = parse_positive)]
+    warp_frequency: f64,
+
+    /// Implementation of the Voronoi texture: grid or cpu (own point placement, all features and
+    /// metrics), gpu (the same on the GPU, also running the directional blur there), auto (gpu
+    /// when a hardware adapter is found, cpu otherwise) or noise-worley (the noise crate's Worley
+    /// noise). gpu falls back to the CPU with a warning without an adapter or the gpu feature
+    #[arg(long, value_enum, default_value_t = Backend::Grid)]
+    backend: Backend,
+
+    /// Number of Worley cells across the texture with --backend noise-worley
+    #[arg(long, default_value_t = 16.0, value_parser = parse_positive)]
+    worley_frequency: f64,
+
+    /// What --backend noise-worley records: distance (to the nearest cell point) or value (per cell)
+    #[arg(long, default_value_t = WorleyReturn::Distance)]
+    worley_return: WorleyReturn,
+
+    /// Number of noise octaves; each adds finer, fainter detail
+    #[arg(long, default_value_t = 6)]
+    octaves: u32,
+
+    /// Amplitude factor between noise octaves; lower is smoother, near 1 is rougher
+    #[arg(long, default_value_t = 0.5)]
+    persistence: f64,
+
+    /// Frequency factor between noise octaves, at least 1
+    #[arg(long, default_value_t = 2.0)]
+    lacunarity: f64,
+
+    /// Base frequency of the noise texture, the number of noise features across the texture
+    #[arg(long, default_value_t = 1.0)]
+    frequency: f64,
+
+    /// Base noise of the noise texture: perlin, simplex or open-simplex
+    #[arg(long, default_value_t = NoiseKind::Perlin)]
+    noise: NoiseKind,
+
+    /// Accumulation of the noise octaves: fbm, ridged (sharp ridge lines) or billow (round bulges)
+    #[arg(long, default_value_t = NoiseStyle::Fbm)]
+    noise_style: NoiseStyle,
+
+    /// Seed for all random generation; a random seed is picked and printed when omitted
+    #[arg(long)]
+    seed: Option<u64>,
+}
+
+/// Options selecting which files the default pipeline writes
+#[derive(clap::Args, Debug)]
+struct OutputArgs {
+    /// Also write a map of flat-colored cells to voronoi_cells.png
+    #[arg(long)]
+    cells: bool,
+
+    /// Give every cell in voronoi_cells.png a random RGB color
+    #[arg(long, requires = "cells")]
+    cell_color: bool,
+
+    /// Write voronoi_cells.png with cells colored from a palette at random: a text file of one hex color such as
+    /// #c0ffee per line, or a PNG strip whose middle row holds the colors, such as a gradient
+    #[arg(long, value_name = "PALETTE", conflicts_with_all = ["cell_color", "color_random"])]
+    color: Option<PathBuf>,
+
+    /// Write voronoi_cells.png with cells of random HSV colors from these ranges, the hue in degrees, e.g.
+    /// h=0..360,s=0.3..0.6,v=0.7..0.9; omitted ranges keep their defaults
+    #[arg(long, value_name = "RANGES", conflicts_with = "cell_color")]
+    color_random: Option<HsvRanges>,
+
+    /// Redraw the colors of --color and --color-random, best effort, so adjacent cells differ
+    #[arg(long)]
+    distinct_neighbors: bool,
+
+    /// Also write the exact 16-bit nearest-point index of every pixel to voronoi_cell_ids.png
+    #[arg(long)]
+    cell_ids: bool,
+
+    /// Bits per sample of the generated PNG and TIFF textures: 8 or 16
+    #[arg(long, default_value_t = 8, value_parser = parse_bit_depth)]
+    bit_depth: u8,
+
+    /// Map the grayscale PNG textures through a gradient into 8-bit RGB: viridis, magma, grayscale or stops such as
+    /// 0:#000000,0.5:#ff8800,1:#ffffff, blended in linear light
+    #[arg(long, value_name = "MAP")]
+    colormap: Option<Colormap>,
+
+    /// Transfer function the grayscale textures are encoded with as they are quantized: linear for data such as
+    /// heights and masks, srgb or gamma:<gamma> for viewing; colormapped textures are always sRGB
+    #[arg(long, value_name = "FUNCTION", default_value_t = TransferFunction::Linear, conflicts_with = "colormap")]
+    transfer: TransferFunction,
+
+    /// Channel layout of 8-bit PNG textures: luma (grayscale) or rgb-red (the value in the red
+    /// channel, with the original *_red.png file names)
+    #[arg(long, default_value_t = OutputChannels::Luma)]
+    channels: OutputChannels,
+
+    /// File format of the generated textures: png, tga, tiff, bmp (tga and bmp 8-bit only), exr
+    /// (32-bit float OpenEXR), raw (little-endian 32-bit floats with a JSON sidecar), hdr (Radiance HDR),
+    /// or dds or ktx2 (GPU textures, holding the whole mip chain with --emit-mips)
+    #[arg(long, default_value_t = FileFormat::Png)]
+    format: FileFormat,
+
+    /// Block compress DDS and KTX2 textures: bc4 for the grayscale textures, bc5 for the two-channel normal
+    /// map, or both as bc4,bc5
+    #[arg(long, value_delimiter = ',')]
+    compress: Vec<Compression>,
+
+    /// Write the raw toroidal distances and fBm values in [-1, 1] instead of normalized 0-1 data;
+    /// requires --format exr or raw, the blurred texture stays normalized
+    #[arg(long)]
+    raw: bool,
+
+    /// Write the Voronoi, Perlin and blurred maps into the R, G and B channels of
+    /// packed_texture.png instead of three separate files
+    #[arg(long, conflicts_with_all = ["bit_depth", "channels", "format", "raw"])]
+    pack: bool,
+
+    /// Also write a tangent-space normal map of the blurred texture to blurred_voronoi_normal.png
+    #[arg(long)]
+    normal_map: bool,
+
+    /// Height of the blurred texture's 0-1 range in pixels; larger values give steeper normals
+    #[arg(long, default_value_t = 8.0, value_parser = parse_non_negative, requires = "normal_map")]
+    normal_strength: f32,
+
+    /// Green channel direction of the normal map: opengl (Y+) or directx (Y-)
+    #[arg(long, default_value_t = NormalConvention::OpenGl, requires = "normal_map")]
+    normal_convention: NormalConvention,
+
+    /// Also write a mask of the Voronoi texture to voronoi_mask: threshold:<t> or smoothstep:<edge0>,<edge1>
+    #[arg(long)]
+    mask: Option<Mask>,
+
+    /// Flip the mask, making it 1 below the threshold
+    #[arg(long, requires = "mask")]
+    invert_mask: bool,
+
+    /// Also write an ambient occlusion map of the blurred texture to blurred_voronoi_ao
+    #[arg(long)]
+    ao_map: bool,
+
+    #[command(flatten)]
+    ao: AoOptions,
+
+    /// Also write the blurred texture after every blur step, as blurred_voronoi_texture_step_<n>
+    #[arg(long)]
+    save_intermediates: bool,
+
+    /// Scroll every texture by DX,DY pixels with wraparound, e.g. 128,256, to move features away from the
+    /// tile border
+    #[arg(long, value_parser = parse_offset, allow_hyphen_values = true)]
+    offset: Option<(i64, i64)>,
+
+    /// Also write every texture repeated COLSxROWS times, e.g. 2x2, as <texture>_tiled to check the seams
+    #[arg(long, value_parser = parse_dimensions)]
+    preview_tiled: Option<(u32, u32)>,
+
+    /// Print how long every stage of the pipeline took
+    #[arg(long)]
+    timing: bool,
+
+    /// Write the grayscale textures at these sizes instead, e.g. 2048,1024,512, as <texture>_<size>;
+    /// the maps are rendered at the largest size and downsampled
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_parser = clap::value_parser!(u32).range(1..),
+        conflicts_with_all = ["size", "emit_mips"]
+    )]
+    sizes: Vec<u32>,
+
+    /// Write the grayscale textures as mip chains down to 1x1 instead, as <texture>_<size>
+    #[arg(long)]
+    emit_mips: bool,
+
+    /// Filter downsampling --sizes and --emit-mips: box or lanczos3
+    #[arg(long, default_value_t = ResampleFilter::Box)]
+    mip_filter: ResampleFilter,
+
+    /// Directory the textures are written to, created if missing
+    #[arg(long, default_value = ".")]
+    out_dir: PathBuf,
+
+    /// Also write the parameters the textures were made with to this JSON file, for `cells rerun`
+    #[arg(long)]
+    manifest: Option<PathBuf>,
+}
+
+/// Options of the `pack` subcommand
+#[derive(clap::Args, Debug)]
+struct PackArgs {
+    /// Source of the red channel: voronoi, perlin, blurred or the path of an image file
+    #[arg(long)]
+    r: Source,
+
+    /// Source of the green channel
+    #[arg(long)]
+    g: Source,
+
+    /// Source of the blue channel
+    #[arg(long)]
+    b: Source,
+
+    /// Source of the alpha channel; fully opaque when omitted
+    #[arg(long)]
+    a: Option<Source>,
+
+    /// File the packed texture is written to
+    #[arg(short, long)]
+    output: PathBuf,
+
+    #[command(flatten)]
+    generate: GenerateArgs,
+}
+
+/// Options of the `animate` subcommand
+#[derive(clap::Args, Debug)]
+struct AnimateArgs {
+    /// The animated map: perlin (the noise drifts), voronoi or blurred (the cell centers circle)
+    #[arg(long, value_enum, default_value_t = AnimatedMap::Perlin)]
+    map: AnimatedMap,
+
+    /// Number of frames in the loop
+    #[arg(long, default_value_t = 64, value_parser = clap::value_parser!(u32).range(1..))]
+    frames: u32,
+
+    /// Frame files to write, with {} or a zero-padded {:03} placeholder for the frame number such as
+    /// frames/noise_{:03}.png; the plain path of the sprite sheet with --flipbook or of the animation
+    /// with --format gif or apng
+    #[arg(short, long)]
+    out: String,
+
+    /// How the frames are written: png (numbered files), gif or apng (one looping animated image)
+    #[arg(long, value_enum, default_value_t = AnimationFormat::Png)]
+    format: AnimationFormat,
+
+    /// How long every frame of a gif or apng animation is shown, in milliseconds
+    #[arg(long, default_value_t = 40)]
+    delay: u64,
+
+    /// Pack the frames row by row into one COLSxROWS sprite sheet, e.g. 8x8 for 64 frames
+    #[arg(long, value_parser = parse_dimensions)]
+    flipbook: Option<(u32, u32)>,
+
+    /// Radius of the circle the noise travels along over one loop, in noise features
+    #[arg(long, default_value_t = 0.5, value_parser = parse_positive)]
+    motion: f64,
+
+    /// Radius of the circle every cell center travels along over one loop, in texture widths
+    #[arg(long, default_value_t = 0.02, value_parser = parse_non_negative)]
+    orbit: f32,
+
+    /// Bits per sample of PNG frames: 8 or 16
+    #[arg(long, default_value_t = 8, value_parser = parse_bit_depth)]
+    bit_depth: u8,
+
+    #[command(flatten)]
+    generate: GenerateArgs,
+}
+
+/// Options of the `stream` subcommand
+#[derive(clap::Args, Debug)]
+struct StreamArgs {
+    /// The PNG file to write
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// The map to write: voronoi or perlin (the normalized noise)
+    #[arg(long, value_enum, default_value_t = StreamedMap::Voronoi)]
+    map: StreamedMap,
+
+    /// Blur the map with a Gaussian of this standard deviation in pixels, rendering the rows it
+    /// reaches into above and below every band
+    #[arg(long, value_parser = parse_non_negative)]
+    gaussian: Option<f32>,
+
+    /// Number of rows rendered at a time [default: 256]
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    tile_rows: Option<u32>,
+
+    /// Memory for one band of rows, such as 512M or 2G, picking the number of rows rendered at a time
+    #[arg(long, value_parser = parse_memory, conflicts_with = "tile_rows")]
+    max_memory: Option<u64>,
+
+    /// Bits per sample: 8 or 16
+    #[arg(long, default_value_t = 8, value_parser = parse_bit_depth)]
+    bit_depth: u8,
+
+    #[command(flatten)]
+    generate: GenerateArgs,
+}
+
+/// Options of the `volume` subcommand
+#[derive(clap::Args, Debug)]
+struct VolumeArgs {
+    /// Slice files to write, with {} or a zero-padded {:03} placeholder for the slice number such as
+    /// slices/noise_{:03}.png; the plain path of the atlas with --atlas, or of a raw little-endian
+    /// f32 volume with a .json header beside it for .raw paths
+    #[arg(short, long)]
+    output: String,
+
+    /// Width and height of the slices in pixels
+    #[arg(long, default_value_t = 128, value_parser = clap::value_parser!(u32).range(1..))]
+    size: u32,
+
+    /// Number of slices
+    #[arg(long, default_value_t = 128, value_parser = clap::value_parser!(u32).range(1..))]
+    depth: u32,
+
+    /// Lay the slices out row by row in one COLSxROWS atlas image, e.g. 16x8 for 128 slices
+    #[arg(long, value_parser = parse_dimensions)]
+    atlas: Option<(u32, u32)>,
+
+    /// Number of noise octaves; each adds finer, fainter detail
+    #[arg(long, default_value_t = 6)]
+    octaves: u32,
+
+    /// Amplitude factor between noise octaves; lower is smoother, near 1 is rougher
+    #[arg(long, default_value_t = 0.5)]
+    persistence: f64,
+
+    /// Frequency factor between noise octaves, at least 1; every octave frequency is rounded to a
+    /// whole number so the volume tiles
+    #[arg(long, default_value_t = 2.0)]
+    lacunarity: f64,
+
+    /// Base frequency, the number of noise features across the volume
+    #[arg(long, default_value_t = 4.0, value_parser = parse_positive)]
+    frequency: f64,
+
+    /// Accumulation of the noise octaves: fbm, ridged (sharp ridge lines) or billow (round bulges)
+    #[arg(long, default_value_t = NoiseStyle::Fbm)]
+    noise_style: NoiseStyle,
+
+    /// Seed of the noise; random if not given
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Bits per sample of PNG slices and atlases: 8 or 16
+    #[arg(long, default_value_t = 8, value_parser = parse_bit_depth)]
+    bit_depth: u8,
+}
+
+/// Options of the `blend` subcommand
+#[derive(clap::Args, Debug)]
+struct BlendArgs {
+    /// Source of the base: voronoi, perlin, blurred or the path of an image file
+    #[arg(long)]
+    a: Source,
+
+    /// Source of the layer blended onto the base
+    #[arg(long)]
+    b: Source,
+
+    /// How the layer combines with the base: add, multiply, screen, overlay, min, max, difference
+    /// or lerp
+    #[arg(long, default_value_t = BlendMode::Lerp)]
+    mode: BlendMode,
+
+    /// Strength of the blend from 0 (the base) to 1
+    #[arg(long, default_value_t = 1.0, value_parser = parse_non_negative)]
+    opacity: f32,
+
+    /// Source of a mask limiting the blend to where it is bright, e.g. a thresholded Perlin map
+    #[arg(long)]
+    mask: Option<Source>,
+
+    /// Gaussian feathering of the mask edges in pixels
+    #[arg(long, default_value_t = 0.0, value_parser = parse_non_negative, requires = "mask")]
+    feather: f32,
+
+    /// Blend where the mask is dark instead
+    #[arg(long, requires = "mask")]
+    invert_mask: bool,
+
+    /// File the blended texture is written to; EXR keeps float precision
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Bits per sample of PNG output: 8 or 16
+    #[arg(long, default_value_t = 8, value_parser = parse_bit_depth)]
+    bit_depth: u8,
+
+    #[command(flatten)]
+    generate: GenerateArgs,
+}
+
+/// Options of the `run` subcommand
+#[derive(clap::Args, Debug)]
+struct RunArgs {
+    /// Pipeline file with one [[node]] table per generator or filter
+    pipeline: PathBuf,
+
+    /// Directory the saved nodes are written to
+    #[arg(long, default_value = ".")]
+    out_dir: PathBuf,
+
+    /// Bits per sample of PNG output: 8 or 16
+    #[arg(long, default_value_t = 8, value_parser = parse_bit_depth)]
+    bit_depth: u8,
+
+    /// Also write the pipeline and its parameters to this JSON file, for `cells rerun`
+    #[arg(long)]
+    manifest: Option<PathBuf>,
+
+    /// Run the pipeline again whenever the file changes, recomputing only the nodes that changed
+    #[arg(long)]
+    watch: bool,
+
+    /// Compute every node instead of loading unchanged ones from the cache directory
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Directory node outputs are cached in, as raw floats keyed on their parameters and inputs
+    #[arg(long, default_value = DEFAULT_CACHE_DIR)]
+    cache_dir: PathBuf,
+
+    /// Size limit of the cache directory in MiB; the least recently used outputs are removed beyond it
+    #[arg(long, default_value_t = 1024)]
+    cache_size: u64,
+}
+
+/// Options of the `cache` subcommand
+#[derive(clap::Args, Debug)]
+struct CacheArgs {
+    #[command(subcommand)]
+    action: CacheAction,
+
+    /// Directory node outputs are cached in
+    #[arg(long, global = true, default_value = DEFAULT_CACHE_DIR)]
+    cache_dir: PathBuf,
+}
+
+/// What the `cache` subcommand does
+#[derive(Subcommand, Debug)]
+enum CacheAction {
+    /// Remove every cached node output
+    Clear,
+}
+
+/// Options of the `verify` subcommand
+#[derive(clap::Args, Debug)]
+struct VerifyArgs {
+    /// Image files to check, PNG or EXR
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+
+    /// Fail if a seam discontinuity exceeds this value, in 0-1 sample units
+    #[arg(long)]
+    tolerance: Option<f32>,
+}
+
+/// Options of the `tile` subcommand
+#[derive(clap::Args, Debug)]
+struct TileArgs {
+    /// Image file to repeat, PNG or EXR
+    input: PathBuf,
+
+    /// How often the image repeats across and down, e.g. 3x3
+    #[arg(value_parser = parse_dimensions)]
+    copies: (u32, u32),
+
+    /// Where to write the preview; EXR for .exr paths, otherwise the format follows the extension
+    #[arg(short, long)]
+    output: PathBuf,
+}
+
+/// Options of the `info` subcommand
+#[derive(clap::Args, Debug)]
+struct InfoArgs {
+    /// PNG texture with embedded parameters, or a JSON manifest
+    file: PathBuf,
+}
+
+/// Options of the `rerun` subcommand
+#[derive(clap::Args, Debug)]
+struct RerunArgs {
+    /// JSON manifest written with --manifest, or a PNG texture with embedded parameters
+    manifest: PathBuf,
+
+    /// Directory the textures are written to instead of the recorded one
+    #[arg(long)]
+    out_dir: Option<PathBuf>,
+}
+
+/// Options of the `serve` subcommand
+#[cfg(feature = "serve")]
+#[derive(clap::Args, Debug)]
+struct ServeArgs {
+    /// Port to listen on
+    #[arg(long, default_value_t = 8080)]
+    port: u16,
+
+    /// Address to listen on; 0.0.0.0 answers other machines too
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+
+    /// Largest texture size answered, in pixels across
+    #[arg(long, default_value_t = 2048, value_parser = clap::value_parser!(u32).range(1..))]
+    max_size: u32,
+
+    /// Number of recently requested textures kept in memory
+    #[arg(long, default_value_t = 64)]
+    cache: usize,
+}
+
+/// Options of the `filter` subcommand
+#[derive(clap::Args, Debug)]
+struct FilterArgs {
+    /// Image file to filter, PNG or EXR
+    input: PathBuf,
+
+    /// Filters applied one after another: gaussian:<sigma>, normalize, clip:<low>,<high>, equalize,
+    /// levels:<in-black>,<in-white>,<gamma>,<out-black>,<out-white>, curve:<in>:<out>,..., threshold:<t>,
+    /// smoothstep:<edge0>,<edge1>, invert,
+    /// posterize:<levels>, dither:<levels> (posterize with ordered dithering), distance[:<radius>]
+    /// (pixels to the nearest pixel at or above 0.5, divided by the radius and clamped to 1),
+    /// dilate:<element>, erode:<element>, open:<element> or close:<element> with the element
+    /// square:<radius> or disc:<radius>, median:<radius> or bilateral:<sigma-spatial>,<sigma-range>
+    /// (edge-preserving smoothing), sharpen:<sigma>,<amount>[,<threshold>] (unsharp mask) or
+    /// kuwahara:<radius>[,<sectors>] (painterly flattening with 4 or 8 sectors)
+    #[arg(required = true)]
+    steps: Vec<FilterStep>,
+
+    /// File the filtered texture is written to; EXR keeps float precision
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Bits per sample of PNG output: 8 or 16
+    #[arg(long, default_value_t = 8, value_parser = parse_bit_depth)]
+    bit_depth: u8,
+}
+
+/// Options of the `ao` subcommand
+#[derive(clap::Args, Debug)]
+struct AoArgs {
+    /// Heightmap image file, PNG or EXR
+    input: PathBuf,
+
+    /// File the AO map is written to; EXR keeps float precision
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Bits per sample of PNG output: 8 or 16
+    #[arg(long, default_value_t = 8, value_parser = parse_bit_depth)]
+    bit_depth: u8,
+
+    #[command(flatten)]
+    ao: AoOptions,
+}
+
+/// Options of the `bricks` subcommand
+#[derive(clap::Args, Debug)]
+struct BricksArgs {
+    /// File the heightmap is written to; EXR keeps float precision
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Width and height of the texture in pixels
+    #[arg(long, default_value_t = 512, value_parser = clap::value_parser!(u32).range(1..))]
+    size: u32,
+
+    /// Number of brick rows
+    #[arg(long, default_value_t = 8, value_parser = clap::value_parser!(u32).range(1..))]
+    rows: u32,
+
+    /// Number of bricks in every row
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u32).range(1..))]
+    cols: u32,
+
+    /// Shift of every row in brick widths; the wall tiles when rows times offset is a whole number
+    #[arg(long, default_value_t = 0.5, allow_hyphen_values = true)]
+    offset: f32,
+
+    /// Width of the mortar joints as a fraction of the texture size
+    #[arg(long, default_value_t = 0.02, value_parser = parse_unit_interval)]
+    mortar: f32,
+
+    /// Share of every brick that is beveled, from 0 (sharp edges) to 1 (fully rounded)
+    #[arg(long, default_value_t = 0.1, value_parser = parse_unit_interval)]
+    bevel: f32,
+
+    /// How much darker bricks may randomly be, from 0 to 1
+    #[arg(long, default_value_t = 0.2, value_parser = parse_unit_interval)]
+    variation: f32,
+
+    /// Seed of the brick brightnesses; random if not given
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Bits per sample of PNG output: 8 or 16
+    #[arg(long, default_value_t = 8, value_parser = parse_bit_depth)]
+    bit_depth: u8,
+}
+
+/// Options of the `scratches` subcommand
+#[derive(clap::Args, Debug)]
+struct ScratchesArgs {
+    /// File the scratches are written to; EXR keeps float precision
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Width and height of the texture in pixels
+    #[arg(long, default_value_t = 512, value_parser = clap::value_parser!(u32).range(1..))]
+    size: u32,
+
+    /// Number of scratches
+    #[arg(long, default_value_t = 300)]
+    count: usize,
+
+    /// Range of the scratch lengths as fractions of the texture size, at most 1
+    #[arg(long, default_value = "0.02..0.15", value_parser = parse_range)]
+    length: (f32, f32),
+
+    /// Preferred direction of the scratches in degrees, counterclockwise from the x axis
+    #[arg(long, default_value_t = 0.0, allow_hyphen_values = true)]
+    angle: f32,
+
+    /// Deviation of the scratches from --angle, up to this many degrees either way; 90 gives all directions
+    #[arg(long, default_value_t = 90.0, value_parser = parse_non_negative)]
+    spread: f32,
+
+    /// Width of the scratches as a fraction of the texture size
+    #[arg(long, default_value_t = 0.002, value_parser = parse_positive_f32)]
+    width: f32,
+
+    /// Range of the scratch intensities
+    #[arg(long, default_value = "0.3..1", value_parser = parse_range)]
+    intensity: (f32, f32),
+
+    /// Seed of the scratches; random if not given
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Bits per sample of PNG output: 8 or 16
+    #[arg(long, default_value_t = 8, value_parser = parse_bit_depth)]
+    bit_depth: u8,
+}
+
+/// Options of the `speckle` subcommand
+#[derive(clap::Args, Debug)]
+struct SpeckleArgs {
+    /// File the speckle is written to; EXR keeps float precision and overlapping splats above 1
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Width and height of the texture in pixels
+    #[arg(long, default_value_t = 512, value_parser = clap::value_parser!(u32).range(1..))]
+    size: u32,
+
+    /// Number of splats
+    #[arg(long, default_value_t = 400)]
+    count: usize,
+
+    /// Placement of the splats: uniform, poisson:<min-dist>, grid:<cols>x<rows>:<jitter> or
+    /// hex:<cols>x<rows>:<jitter>; placements with more points are thinned at random to --count
+    #[arg(long, default_value_t = PointDistribution::Uniform)]
+    distribution: PointDistribution,
+
+    /// Range of the splat radii as fractions of the texture size, at most 0.5
+    #[arg(long, default_value = "0.002..0.006", value_parser = parse_range)]
+    radius: (f32, f32),
+
+    /// Range of the splat intensities
+    #[arg(long, default_value = "0.3..1", value_parser = parse_range)]
+    intensity: (f32, f32),
+
+    /// Profile of the splats: gaussian or disc
+    #[arg(long, default_value_t = SplatShape::Gaussian)]
+    shape: SplatShape,
+
+    /// Seed of the positions, radii and intensities; random if not given
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Bits per sample of PNG output: 8 or 16
+    #[arg(long, default_value_t = 8, value_parser = parse_bit_depth)]
+    bit_depth: u8,
+}
+
+/// Options of the `wood` and `marble` subcommands
+#[derive(clap::Args, Debug)]
+struct GrainArgs {
+    /// File the texture is written to; EXR keeps float precision
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Width and height of the texture in pixels
+    #[arg(long, default_value_t = 512, value_parser = clap::value_parser!(u32).range(1..))]
+    size: u32,
+
+    /// Number of wood rings from the center to an edge, or of marble veins across the texture
+    #[arg(long, default_value_t = 8, value_parser = clap::value_parser!(u32).range(1..))]
+    count: u32,
+
+    /// How far the turbulence shifts the rings or veins, in periods
+    #[arg(long, default_value_t = 1.0, value_parser = parse_non_negative)]
+    turbulence: f32,
+
+    /// Base frequency of the turbulence, in noise features across the texture
+    #[arg(long, default_value_t = 4.0, value_parser = parse_positive)]
+    frequency: f64,
+
+    /// Seed of the turbulence; random if not given
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Bits per sample of PNG output: 8 or 16
+    #[arg(long, default_value_t = 8, value_parser = parse_bit_depth)]
+    bit_depth: u8,
+}
+
+/// Options of the `cracks` subcommand
+#[derive(clap::Args, Debug)]
+struct CracksArgs {
+    /// File the mask is written to, 1 in the cracks; EXR keeps float precision
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Width and height of the texture in pixels
+    #[arg(long, default_value_t = 512, value_parser = clap::value_parser!(u32).range(1..))]
+    size: u32,
+
+    /// Number of cells the cracks surround
+    #[arg(long, default_value_t = 60, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    points: usize,
+
+    /// Lloyd relaxation iterations evening out the cells
+    #[arg(long, default_value_t = 2)]
+    relax: u32,
+
+    /// Width of the cracks as a fraction of the texture size
+    #[arg(long, default_value_t = 0.006, value_parser = parse_positive_f32)]
+    width: f32,
+
+    /// Frequency of the noise thinning the cracks, in cycles across the texture
+    #[arg(long, default_value_t = 3.0, value_parser = parse_positive)]
+    fade_scale: f64,
+
+    /// How far the noise thins the cracks, from 0 (even width) to 1 (fading out entirely)
+    #[arg(long, default_value_t = 0.7, value_parser = parse_unit_interval)]
+    fade: f32,
+
+    /// Probability of leaving out the border between two cells
+    #[arg(long, default_value_t = 0.2, value_parser = parse_unit_interval)]
+    removal: f32,
+
+    /// Seed of the cells, fade noise and removed borders; random if not given
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Bits per sample of PNG output: 8 or 16
+    #[arg(long, default_value_t = 8, value_parser = parse_bit_depth)]
+    bit_depth: u8,
+}
+
+/// Options of the `hex` subcommand
+#[derive(clap::Args, Debug)]
+struct HexArgs {
+    /// File the cells are written to; EXR keeps float precision
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Width and height of the texture in pixels
+    #[arg(long, default_value_t = 512, value_parser = clap::value_parser!(u32).range(1..))]
+    size: u32,
+
+    /// Columns and rows of the lattice; the rows must be even and about 1.15 times the columns
+    #[arg(long, default_value = "12x14", value_parser = parse_dimensions)]
+    cells: (u32, u32),
+
+    /// Displacement of the lattice points, from 0 (regular hexagons) to 1 (random within their cell)
+    #[arg(long, default_value_t = 0.0, value_parser = parse_unit_interval)]
+    jitter: f32,
+
+    /// Voronoi feature: the distances f1, f2 or f2-f1, or cell-value, a random value per cell
+    #[arg(long, default_value_t = Feature::F1)]
+    feature: Feature,
+
+    /// Make the cell centers bright and the edges dark
+    #[arg(long)]
+    invert: bool,
+
+    /// Seed of the jitter; random if not given
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Bits per sample of PNG output: 8 or 16
+    #[arg(long, default_value_t = 8, value_parser = parse_bit_depth)]
+    bit_depth: u8,
+}
+
+/// Options of the `truchet` subcommand
+#[derive(clap::Args, Debug)]
+struct TruchetArgs {
+    /// File the pattern is written to; EXR keeps float precision
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Width and height of the texture in pixels
+    #[arg(long, default_value_t = 512, value_parser = clap::value_parser!(u32).range(1..))]
+    size: u32,
+
+    /// Number of cells across and down
+    #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u32).range(1..))]
+    grid: u32,
+
+    /// Width of the lines as a fraction of a cell, at most 1
+    #[arg(long, default_value_t = 0.15, value_parser = parse_positive_f32)]
+    line_width: f32,
+
+    /// Motif of every cell: arcs or diagonals
+    #[arg(long, default_value_t = TruchetStyle::Arcs)]
+    style: TruchetStyle,
+
+    /// Seed of the motif orientations; random if not given
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Bits per sample of PNG output: 8 or 16
+    #[arg(long, default_value_t = 8, value_parser = parse_bit_depth)]
+    bit_depth: u8,
+}
+
+/// Options of the `gabor` subcommand
+#[derive(clap::Args, Debug)]
+struct GaborArgs {
+    /// File the normalized noise is written to; EXR keeps float precision
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Width and height of the texture in pixels
+    #[arg(long, default_value_t = 512, value_parser = clap::value_parser!(u32).range(1..))]
+    size: u32,
+
+    /// Frequency of the waves in cycles across the texture
+    #[arg(long, default_value_t = 24.0, value_parser = parse_non_negative)]
+    frequency: f32,
+
+    /// Radius of every kernel as a fraction of the texture size, at most 0.5
+    #[arg(long, default_value_t = 0.06, value_parser = parse_positive_f32)]
+    radius: f32,
+
+    /// Number of kernels in the texture
+    #[arg(long, default_value_t = 2000, value_parser = clap::value_parser!(u32).range(1..))]
+    kernels: u32,
+
+    /// Orientation of the waves: isotropic or fixed:<degrees>, counterclockwise from the x axis
+    #[arg(long, default_value_t = GaborOrientation::Isotropic)]
+    orientation: GaborOrientation,
+
+    /// Image file whose values orient the kernels, 0 to 1 mapping to 0° to 180°; overrides --orientation
+    #[arg(long)]
+    orientation_map: Option<PathBuf>,
+
+    /// Random deviation of every kernel from its orientation in degrees
+    #[arg(long, default_value_t = 0.0, value_parser = parse_non_negative)]
+    spread: f32,
+
+    /// Seed of the kernels; random if not given
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Bits per sample of PNG output: 8 or 16
+    #[arg(long, default_value_t = 8, value_parser = parse_bit_depth)]
+    bit_depth: u8,
+}
+
+/// Options of the `reaction-diffusion` subcommand
+#[derive(clap::Args, Debug)]
+struct ReactionDiffusionArgs {
+    /// File the normalized texture is written to; EXR keeps float precision
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Width and height of the texture in pixels
+    #[arg(long, default_value_t = 256, value_parser = clap::value_parser!(u32).range(1..))]
+    size: u32,
+
+    /// Feed and kill rates: mitosis, coral or worms
+    #[arg(long, default_value_t = ReactionPreset::Coral)]
+    preset: ReactionPreset,
+
+    /// Feed rate replacing the preset's, e.g. 0.055
+    #[arg(long, value_parser = parse_unit_interval)]
+    feed: Option<f32>,
+
+    /// Kill rate replacing the preset's, e.g. 0.062
+    #[arg(long, value_parser = parse_unit_interval)]
+    kill: Option<f32>,
+
+    /// Number of simulation steps; patterns need a few thousand to settle
+    #[arg(long, default_value_t = 5000)]
+    steps: u32,
+
+    /// Where the pattern starts growing: spots (random squares) or noise (Perlin blobs)
+    #[arg(long, default_value_t = Seeding::Spots)]
+    seeding: Seeding,
+
+    /// Chemical written: v (the pattern) or u (its inverse)
+    #[arg(long, default_value_t = Chemical::V)]
+    chemical: Chemical,
+
+    /// Seed of the initial spots; random if not given
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Bits per sample of PNG output: 8 or 16
+    #[arg(long, default_value_t = 8, value_parser = parse_bit_depth)]
+    bit_depth: u8,
+}
+
+/// Options of the `cellular` subcommand
+#[derive(clap::Args, Debug)]
+struct CellularArgs {
+    /// Mask image file, PNG or EXR; pixels at or above 0.5 are solid
+    input: PathBuf,
+
+    /// File the smoothed mask is written to
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// How many times the rule is applied
+    #[arg(long, default_value_t = 5)]
+    iterations: u32,
+
+    /// Solid neighbors, out of 8, an empty pixel needs to become solid
+    #[arg(long, default_value_t = DEFAULT_BIRTH_LIMIT, value_parser = clap::value_parser!(u32).range(0..=8))]
+    birth: u32,
+
+    /// Solid neighbors, out of 8, a solid pixel needs to stay solid
+    #[arg(long, default_value_t = DEFAULT_DEATH_LIMIT, value_parser = clap::value_parser!(u32).range(0..=8))]
+    death: u32,
+
+    /// Also write every iteration next to the output as <output>_iteration_<n>
+    #[arg(long)]
+    save_iterations: bool,
+}
+
+/// Options of the `curvature` subcommand
+#[derive(clap::Args, Debug)]
+struct CurvatureArgs {
+    /// Heightmap image file, PNG or EXR
+    input: PathBuf,
+
+    /// File the signed curvature is written to: ridges above and cavities below 0.5
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Also write the separately normalized ridge mask to this file
+    #[arg(long)]
+    ridges: Option<PathBuf>,
+
+    /// Also write the separately normalized cavity mask to this file
+    #[arg(long)]
+    cavities: Option<PathBuf>,
+
+    /// Gaussian pre-blur in pixels; larger values detect larger features
+    #[arg(long, default_value_t = 0.0, value_parser = parse_non_negative)]
+    sigma: f32,
+
+    /// Bits per sample of PNG output: 8 or 16
+    #[arg(long, default_value_t = 8, value_parser = parse_bit_depth)]
+    bit_depth: u8,
+}
+
+/// Options of the `sobel` subcommand
+#[derive(clap::Args, Debug)]
+struct SobelArgs {
+    /// Image file to detect edges in, PNG or EXR
+    input: PathBuf,
+
+    /// File the edge strength is written to, stretched between its 1st and 99th percentiles
+    #[arg(long)]
+    magnitude: Option<PathBuf>,
+
+    /// File the edge direction is written to, 0-1 over a full turn as read by the directional blur
+    #[arg(long)]
+    direction: Option<PathBuf>,
+
+    /// Turn the direction a quarter turn to run along the edges instead of across them
+    #[arg(long)]
+    along_edges: bool,
+
+    /// Bits per sample of PNG output: 8 or 16; 16 keeps the direction map smooth
+    #[arg(long, default_value_t = 8, value_parser = parse_bit_depth)]
+    bit_depth: u8,
+}
+
+/// Options of the `warp` subcommand
+#[derive(clap::Args, Debug)]
+struct WarpArgs {
+    /// Image file to displace, PNG or EXR
+    input: PathBuf,
+
+    /// File the displaced image is written to
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Image of the x offsets of the size of the input: 0.5 reads the pixel itself, 0 and 1 the pixel --strength
+    /// pixels left and right
+    #[arg(long, requires = "dy", required_unless_present = "angle", conflicts_with = "angle")]
+    dx: Option<PathBuf>,
+
+    /// Image of the y offsets of the size of the input: 0.5 reads the pixel itself, 0 and 1 the pixel --strength
+    /// pixels up and down
+    #[arg(long, requires = "dx")]
+    dy: Option<PathBuf>,
+
+    /// Image of the offset directions of the size of the input, 0-1 over a full turn as read by the directional
+    /// blur, every offset --strength pixels long
+    #[arg(long)]
+    angle: Option<PathBuf>,
+
+    /// Length of the longest offsets in pixels
+    #[arg(long, default_value_t = 8.0, value_parser = parse_non_negative)]
+    strength: f32,
+
+    /// Bits per sample of PNG output: 8 or 16
+    #[arg(long, default_value_t = 8, value_parser = parse_bit_depth)]
+    bit_depth: u8,
+}
+
+/// Options of the ambient occlusion approximation
+#[derive(clap::Args, Debug)]
+struct AoOptions {
+    /// Distance in pixels up to which higher surroundings occlude a pixel
+    #[arg(long, default_value_t = 8.0, value_parser = parse_positive_f32)]
+    ao_radius: f32,
+
+    /// Number of directions sampled around every pixel
+    #[arg(long, default_value_t = 12, value_parser = clap::value_parser!(u32).range(1..))]
+    ao_samples: u32,
+
+    /// How strongly higher surroundings darken a pixel
+    #[arg(long, default_value_t = 4.0, value_parser = parse_non_negative)]
+    ao_strength: f32,
+}
+
+impl AoOptions {
+    fn apply(&self, height: &FieldBuffer) -> FieldBuffer {
+        height_to_ao(height, self.ao_radius, self.ao_samples, self.ao_strength)
+    }
+}
+
+/// Implementation of the Voronoi texture
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Backend {
+    /// The point grid supporting every feature, metric and weighting
+    Grid,
+    /// The point grid on the CPU, the same as grid
+    Cpu,
+    /// The point grid and the directional blur on the GPU, or on the CPU without an adapter
+    Gpu,
+    /// The GPU when a hardware adapter is found, the CPU otherwise
+    Auto,
+    /// Worley noise from the noise crate
+    NoiseWorley,
+}
+
+/// A map of the default pipeline the `animate` subcommand writes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum AnimatedMap {
+    /// The normalized Perlin noise texture
+    Perlin,
+    /// The Voronoi texture
+    Voronoi,
+    /// The blurred Voronoi texture after the tone adjustments
+    Blurred,
+}
+
+/// A map the `stream` subcommand writes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum StreamedMap {
+    /// The Voronoi texture
+    Voronoi,
+    /// The normalized Perlin noise texture
+    Perlin,
+}
+
+/// Bytes held per pixel of a band while streaming: the samples, the halo copies of a blur and the
+/// quantized row bytes, rounded up
+const STREAM_BYTES_PER_PIXEL: u64 = 16;
+
+/// Rows rendered at a time when neither --tile-rows nor --max-memory is given
+const DEFAULT_TILE_ROWS: u32 = 256;
+
+/// Block compression of DDS and KTX2 textures
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Compression {
+    /// BC4 for single-channel textures
+    Bc4,
+    /// BC5 for the X and Y of normal maps
+    Bc5,
+}
+
+/// File format of the `animate` subcommand
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum AnimationFormat {
+    /// One PNG file per frame, or a PNG sprite sheet with --flipbook
+    Png,
+    /// One endlessly looping animated GIF sharing a gray palette across the frames
+    Gif,
+    /// One endlessly looping animated PNG
+    Apng,
+}
+
+/// A map to pack into a channel, generated or read from a file
+#[derive(Clone, Debug)]
+enum Source {
+    Voronoi,
+    Perlin,
+    Blurred,
+    /// An image file whose first channel is used, or the `Y` channel of an EXR
+    File(PathBuf),
+}
+
+impl FromStr for Source {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "voronoi" => Ok(Source::Voronoi),
+            "perlin" => Ok(Source::Perlin),
+            "blurred" => Ok(Source::Blurred),
+            "" => Err("expected voronoi, perlin, blurred or an image path".to_string()),
+            path => Ok(Source::File(PathBuf::from(path))),
+        }
+    }
+}
+
+/// A filter of the `filter` subcommand
+#[derive(Clone, Debug)]
+enum FilterStep {
+    /// Seamless Gaussian blur with the standard deviation in pixels
+    Gaussian(f32),
+    /// Stretch to the full 0-1 range
+    Normalize,
+    /// Stretch between two percentiles
+    Clip(f32, f32),
+    /// Flatten the histogram
+    Equalize,
+    /// Black point, white point and gamma adjustment
+    Levels(Levels),
+    /// Piecewise-linear tone curve
+    Curve(ToneCurve),
+    /// Hard or feathered mask
+    Mask(Mask),
+    /// Flip 0-1 values
+    Invert,
+    /// Reduce to a number of levels, optionally dithered
+    Posterize { levels: u32, dither: bool },
+    /// Distance in pixels to the nearest pixel at or above 0.5, divided by a
+    /// radius and clamped to 1 if one is given
+    Distance(Option<f32>),
+    /// Dilation, erosion, opening or closing with a structuring element
+    Morphology(Morphology, StructuringElement),
+    /// Median of the square window with the radius in pixels
+    Median(u32),
+    /// Bilateral filter with the spatial sigma in pixels and the range sigma
+    Bilateral(f32, f32),
+    /// Unsharp mask
+    Sharpen(Sharpen),
+    /// Kuwahara filter with the radius in pixels and 4 or 8 sectors
+    Kuwahara { radius: u32, sectors: u32 },
+}
+
+impl FilterStep {
+    fn apply(&self, field: &FieldBuffer) -> FieldBuffer {
+        match self {
+            FilterStep::Gaussian(sigma) => gaussian_blur(field, *sigma),
+            FilterStep::Normalize => normalize_field(field),
+            FilterStep::Clip(low, high) => normalize_percentile(field, *low, *high),
+            FilterStep::Equalize => equalize_histogram(field),
+            FilterStep::Levels(levels) => levels.apply(field),
+            FilterStep::Curve(curve) => curve.apply(field),
+            FilterStep::Mask(mask) => mask.apply(field),
+            FilterStep::Invert => invert(field),
+            FilterStep::Posterize { levels, dither: false } => posterize(field, *levels),
+            FilterStep::Posterize { levels, dither: true } => posterize_dithered(field, *levels),
+            FilterStep::Distance(None) => distance_transform(field),
+            FilterStep::Distance(Some(radius)) => distance_transform(field).map(|d| (d / radius).min(1.0)),
+            FilterStep::Morphology(morphology, element) => morphology.apply(field, *element),
+            FilterStep::Median(radius) => median_filter(field, *radius),
+            FilterStep::Bilateral(sigma_spatial, sigma_range) => bilateral_filter(field, *sigma_spatial, *sigma_range),
+            FilterStep::Sharpen(sharpen) => sharpen.apply(field),
+            FilterStep::Kuwahara { radius, sectors } => kuwahara(field, *radius, *sectors),
+        }
+    }
+}
+
+impl FromStr for FilterStep {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = s.split_once(':').unwrap_or((s, ""));
+        match (name, value) {
+            ("gaussian", sigma) => {
+                let sigma = parse_non_negative(sigma).map_err(|err| format!("invalid Gaussian sigma: {err}"))?;
+                Ok(FilterStep::Gaussian(sigma))
+            }
+            ("normalize", "") => Ok(FilterStep::Normalize),
+            ("clip", percentiles) => {
+                let (low, high) = parse_percentiles(percentiles)?;
+                Ok(FilterStep::Clip(low, high))
+            }
+            ("equalize", "") => Ok(FilterStep::Equalize),
+            ("levels", levels) => Ok(FilterStep::Levels(levels.parse()?)),
+            ("curve", curve) => Ok(FilterStep::Curve(curve.parse()?)),
+            ("threshold" | "smoothstep", _) => Ok(FilterStep::Mask(s.parse()?)),
+            ("invert", "") => Ok(FilterStep::Invert),
+            ("posterize" | "dither", levels) => Ok(FilterStep::Posterize {
+                levels: parse_levels(levels)?,
+                dither: name == "dither",
+            }),
+            ("distance", "") => Ok(FilterStep::Distance(None)),
+            ("distance", radius) => {
+                let radius = parse_positive_f32(radius).map_err(|err| format!("invalid distance radius: {err}"))?;
+                Ok(FilterStep::Distance(Some(radius)))
+            }
+            ("dilate" | "erode" | "open" | "close", element) => {
+                Ok(FilterStep::Morphology(name.parse()?, element.parse()?))
+            }
+            ("median", radius) => {
+                let radius = radius.parse().map_err(|_| format!("invalid median radius `{radius}`"))?;
+                Ok(FilterStep::Median(radius))
+            }
+            ("bilateral", sigmas) => {
+                let invalid = || format!("expected bilateral:<sigma-spatial>,<sigma-range>, got `{s}`");
+                let (spatial, range) = sigmas.split_once(',').ok_or_else(invalid)?;
+                let spatial = parse_non_negative(spatial).map_err(|err| format!("invalid spatial sigma: {err}"))?;
+                let range = parse_positive_f32(range).map_err(|err| format!("invalid range sigma: {err}"))?;
+                Ok(FilterStep::Bilateral(spatial, range))
+            }
+            ("sharpen", sharpen) => Ok(FilterStep::Sharpen(sharpen.parse()?)),
+            ("kuwahara", settings) => {
+                let invalid = || format!("expected kuwahara:<radius> or kuwahara:<radius>,<4|8>, got `{s}`");
+                let (radius, sectors) = settings.split_once(',').unwrap_or((settings, "4"));
+                let radius = radius.trim().parse().map_err(|_| invalid())?;
+                match sectors.trim().parse() {
+                    Ok(sectors @ (4 | 8)) => Ok(FilterStep::Kuwahara { radius, sectors }),
+                    _ => Err(invalid()),
+                }
+            }
+            _ => Err(format!(
+                "unknown filter `{s}`, expected gaussian:<sigma>, normalize, clip:<low>,<high>, equalize, \
+                 levels:<...>, curve:<...>, threshold:<t>, smoothstep:<edge0>,<edge1>, invert, posterize:<levels>, \
+                 dither:<levels>, distance[:<radius>], dilate, erode, open or close:<square|disc>:<radius>, \
+                 median:<radius>, bilateral:<sigma-spatial>,<sigma-range>, sharpen:<sigma>,<amount>[,<threshold>] \
+                 or kuwahara:<radius>[,<sectors>]"
+            )),
+        }
+    }
+}
+
+/// The parameters of every generator in the pipeline
+struct Params {
+    voronoi: VoronoiParams,
+    fbm: FbmParams,
+    /// The blur of the first step; the radius grows by `blur_growth` on every further step
+    blur: BlurParams,
+    blur_steps: u32,
+    blur_growth: f64,
+    normalization: Normalization,
+    /// Unsharp mask of the blurred texture after the blur steps
+    sharpen: Option<Sharpen>,
+    /// Tone adjustments of the blurred texture
+    levels: Option<Levels>,
+    curve: Option<ToneCurve>,
+    /// Number of levels the blurred texture is reduced to, and whether it is dithered
+    posterize: Option<u32>,
+    dither: bool,
+    /// Blend of the Perlin texture onto the Voronoi texture, and its opacity
+    perlin_blend: Option<(BlendMode, f32)>,
+    /// Whether the blur follows the flow of the Perlin map
+    flow: bool,
+    /// The map scaling the blur length of every pixel from 0 to 1
+    blur_length: Option<FieldBuffer>,
+    /// The position in the loop, noise motion and point orbit of an animation frame
+    frame: Option<Frame>,
+    /// The GPU running the Voronoi texture and the directional blur, see [`open_gpu`]
+    gpu: Option<Gpu>,
+}
+
+/// The GPU of builds without the `gpu` feature, which never open one
+#[cfg(not(feature = "gpu"))]
+enum Gpu {}
+
+#[cfg(not(feature = "gpu"))]
+impl Gpu {
+    fn voronoi_field(&self, _: &VoronoiParams) -> FieldBuffer {
+        match *self {}
+    }
+
+    fn voronoi_frame(&self, _: &VoronoiParams, _: f64, _: f32) -> FieldBuffer {
+        match *self {}
+    }
+
+    fn directional_blur(
+        &self,
+        _: &FieldBuffer,
+        _: &FieldBuffer,
+        _: Option<&FieldBuffer>,
+        _: &BlurParams,
+    ) -> FieldBuffer {
+        match *self {}
+    }
+}
+
+/// One frame of an animation looping in time
+#[derive(Clone, Copy, Debug)]
+struct Frame {
+    /// The position in the loop from 0 to 1
+    phase: f64,
+    /// The radius of the circle the noise travels along
+    motion: f64,
+    /// The radius of the circle every cell center travels along
+    orbit: f32,
+}
+
+/// The maps the default pipeline generates
+struct Maps {
+    voronoi: FieldBuffer,
+    perlin: FieldBuffer,
+    blurred: FieldBuffer,
+}
+
+/// The manifest embedded into every PNG file written, once the parameters are resolved
+static MANIFEST: Mutex<Option<Manifest>> = Mutex::new(None);
+
+/// Main function to generate and process textures
 ///
-/// 1. Generate random points in a unit square
-/// 2. For each pixel in the output image:
-///    a. Calculate the toroidal distance to each Voronoi point
-///    b. Find the minimum distance
-/// 3. Normalize the minimum distances across the entire image
-/// 4. Invert the normalized distances (so cell centers are dark and edges are bright)
-/// 5. Map the inverted distances to grayscale values (0-255)
+/// Without a subcommand this function orchestrates the texture generation
+/// process:
+/// 1. Generates a Voronoi texture
+/// 2. Generates a Perlin noise texture
+/// 3. Applies directional blur to the Voronoi texture
+/// 4. Saves the resulting textures as PNG or EXR images
+fn main() -> ExitCode {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    init_logging(cli.verbose, cli.quiet);
+    let command = std::env::args().skip(1).collect();
+    let result = match &cli.command {
+        Some(Command::Pack(args)) => pack(args),
+        Some(Command::Verify(args)) => verify(args),
+        Some(Command::Filter(args)) => filter(args),
+        Some(Command::Ao(args)) => ao(args),
+        Some(Command::Curvature(args)) => curvature_maps(args),
+        Some(Command::Sobel(args)) => sobel_maps(args),
+        Some(Command::Warp(args)) => warp_image(args),
+        Some(Command::Blend(args)) => blend_maps(args),
+        Some(Command::Run(args)) => run_pipeline(args, record(command, &matches)),
+        Some(Command::Animate(args)) => animate(args),
+        Some(Command::Volume(args)) => volume(args),
+        Some(Command::Stream(args)) => stream(args, record(command, &matches)),
+        Some(Command::Bricks(args)) => bricks(args),
+        Some(Command::Scratches(args)) => scratches(args),
+        Some(Command::Speckle(args)) => speckle(args),
+        Some(Command::Wood(args)) => grain(args, generate_wood),
+        Some(Command::Marble(args)) => grain(args, generate_marble),
+        Some(Command::Cracks(args)) => cracks(args),
+        Some(Command::Hex(args)) => hex(args),
+        Some(Command::Truchet(args)) => truchet(args),
+        Some(Command::Gabor(args)) => gabor(args),
+        Some(Command::ReactionDiffusion(args)) => reaction_diffusion(args),
+        Some(Command::Cellular(args)) => cellular(args),
+        Some(Command::Tile(args)) => tile(args),
+        Some(Command::Info(args)) => info(args),
+        Some(Command::Rerun(args)) => rerun(args),
+        #[cfg(feature = "serve")]
+        Some(Command::Serve(args)) => serve(args),
+        Some(Command::Cache(args)) => cache(args),
+        None => run(&cli.generate, &cli.output, record(command, &matches)),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            log::error!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Report the log records of the library and the binary on stderr, prefixed with their level
+struct StderrLogger;
+
+impl log::Log for StderrLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.target().starts_with("cells") && metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let level = match record.level() {
+            log::Level::Warn => "warning".to_string(),
+            level => level.as_str().to_lowercase(),
+        };
+        eprintln!("{level}: {}", record.args());
+    }
+
+    fn flush(&self) {}
+}
+
+/// Log to stderr at the level of `--verbose` and `--quiet`: warnings and errors by default
+fn init_logging(verbose: u8, quiet: u8) {
+    let level = match (verbose, quiet) {
+        (0, 0) => log::LevelFilter::Warn,
+        (0, 1) => log::LevelFilter::Error,
+        (0, _) => log::LevelFilter::Off,
+        (1, _) => log::LevelFilter::Info,
+        (2, _) => log::LevelFilter::Debug,
+        _ => log::LevelFilter::Trace,
+    };
+    log::set_max_level(level);
+    log::set_logger(&StderrLogger).expect("the logger is set once");
+}
+
+/// A manifest of a command line and every option of the command it runs, with its default unless given; the
+/// `--manifest` file itself is left out
+fn record(command: Vec<String>, matches: &ArgMatches) -> Manifest {
+    let mut command = command;
+    for option in UNRECORDED_OPTIONS {
+        command = without_option(&command, &format!("--{option}"));
+    }
+    command.retain(|arg| !UNRECORDED_FLAGS.iter().any(|flag| arg.strip_prefix("--") == Some(flag)));
+    // Short `-v` and `-q` may be repeated, as in `-vv`
+    command.retain(|arg| {
+        !arg.strip_prefix('-').is_some_and(|flags| !flags.is_empty() && flags.chars().all(|c| c == 'v' || c == 'q'))
+    });
+    let mut manifest = Manifest::new(command);
+    let (cli, matches) = match matches.subcommand() {
+        Some((name, sub_matches)) => (Cli::command().find_subcommand(name).cloned(), sub_matches),
+        None => (Some(Cli::command()), matches),
+    };
+    for arg in cli.iter().flat_map(|cli| cli.get_arguments()) {
+        let id = arg.get_id().as_str();
+        let Ok(Some(values)) = matches.try_get_raw(id) else {
+            continue;
+        };
+        let value = values.map(|value| value.to_string_lossy()).collect::<Vec<_>>().join(",");
+        // Flags are only recorded when set
+        let name = id.replace('_', "-");
+        let unrecorded = UNRECORDED_OPTIONS.contains(&name.as_str()) || UNRECORDED_FLAGS.contains(&name.as_str());
+        if unrecorded || !arg.get_action().takes_values() && value == "false" {
+            continue;
+        }
+        manifest.parameters.insert(name, value);
+    }
+    manifest
+}
+
+/// Options changing how textures are made but not what is made, left out of manifests
+const UNRECORDED_OPTIONS: [&str; 3] = ["manifest", "cache-dir", "cache-size"];
+
+/// Flags changing how textures are made but not what is made, left out of manifests
+const UNRECORDED_FLAGS: [&str; 4] = ["watch", "no-cache", "verbose", "quiet"];
+
+/// A command line without an option and its value, given as `--name value` or `--name=value`
+fn without_option(command: &[String], name: &str) -> Vec<String> {
+    let mut kept = Vec::with_capacity(command.len());
+    let mut args = command.iter();
+    while let Some(arg) = args.next() {
+        if arg == name {
+            args.next();
+        } else if !arg.strip_prefix(name).is_some_and(|rest| rest.starts_with('=')) {
+            kept.push(arg.clone());
+        }
+    }
+    kept
+}
+
+fn run(args: &GenerateArgs, output: &OutputArgs, mut manifest: Manifest) -> Result<(), Box<dyn Error>> {
+    if output.raw && !matches!(output.format, FileFormat::Exr | FileFormat::Raw) {
+        return Err("--raw needs a float format keeping negative values, use --format exr or raw".into());
+    }
+    if output.channels == OutputChannels::RgbRed && output.format == FileFormat::Png && output.bit_depth == 16 {
+        return Err("16-bit textures are always grayscale, drop --channels rgb-red".into());
+    }
+    output.format.check_bit_depth(output.bit_depth)?;
+    if output.colormap.is_some() && (output.format != FileFormat::Png || output.bit_depth != 8) {
+        return Err("--colormap writes 8-bit RGB PNGs, drop --format and --bit-depth".into());
+    }
+    output.format.check_transfer(output.transfer)?;
+    if !output.compress.is_empty() && !is_gpu_texture(output.format) {
+        return Err("--compress needs a GPU texture format, use --format dds or ktx2".into());
+    }
+    let coloring = cell_coloring(output)?;
+    if output.distinct_neighbors && coloring.is_none() {
+        return Err("--distinct-neighbors needs --color or --color-random".into());
+    }
+    if (output.cells || output.cell_ids || coloring.is_some()) && args.backend == Backend::NoiseWorley {
+        return Err("--cells, --cell-ids, --color and --color-random need the point grid, use --backend grid".into());
+    }
+    fs::create_dir_all(&output.out_dir)
+        .map_err(|err| format!("cannot create output directory {}: {err}", output.out_dir.display()))?;
+
+    let mut params = params(args)?;
+    params.gpu = open_gpu(args.backend);
+    if args.seed.is_none() {
+        manifest.command.extend(["--seed".to_string(), params.voronoi.seed.to_string()]);
+        manifest.parameters.insert("seed".to_string(), params.voronoi.seed.to_string());
+    }
+    if let Some(&largest) = output.sizes.iter().max() {
+        params.voronoi.size = largest;
+    }
+    manifest.generators = Some(Generators {
+        voronoi: params.voronoi.clone(),
+        fbm: params.fbm,
+        blur: params.blur,
+    });
+    if let Some(path) = &output.manifest {
+        manifest.save_json(path).map_err(|err| format!("cannot write {}: {err}", path.display()))?;
+        println!("wrote {}", path.display());
+    }
+    set_manifest(manifest);
+    // Single-field outputs of the red channel layout keep their original names
+    let red = match output.channels {
+        OutputChannels::Luma => "",
+        OutputChannels::RgbRed => "_red",
+    };
+    let mut stages = Stages::default();
+    let mut intermediate_error = None;
+    let maps = generate_maps(&params, &mut stages, |i, blurred| {
+        if output.save_intermediates && intermediate_error.is_none() {
+            let stem = format!("blurred_voronoi_texture{red}_step_{}", i + 1);
+            intermediate_error = save_map(blurred, output, &stem).err();
+        }
+    });
+    if let Some(err) = intermediate_error {
+        return Err(err);
+    }
+    let writing = Instant::now();
+
+    if output.pack {
+        let packed = pack_channels(&maps.voronoi, &maps.perlin, &maps.blurred, None)?;
+        save_image(&packed, output, "packed_texture")?;
+    } else {
+        if output.raw {
+            save_texture(&generate_voronoi_features(&params.voronoi), output, &format!("voronoi_texture{red}"))?;
+            save_texture(&generate_fbm_field(params.voronoi.size, &params.fbm), output, "perlin_noise_texture")?;
+        } else {
+            save_texture(&maps.voronoi, output, &format!("voronoi_texture{red}"))?;
+            save_texture(&maps.perlin, output, "perlin_noise_texture")?;
+        }
+        save_texture(&maps.blurred, output, &format!("blurred_voronoi_texture{red}"))?;
+    }
+
+    if output.normal_map {
+        let normals = height_to_normal_with(&maps.blurred, output.normal_strength, output.normal_convention);
+        if is_gpu_texture(output.format) {
+            save_normal_texture(&normals, output, "blurred_voronoi_normal")?;
+        } else {
+            save_image(&normals, output, "blurred_voronoi_normal")?;
+        }
+    }
+
+    if let Some(mask) = output.mask {
+        let mask = mask.apply(&maps.voronoi);
+        let mask = if output.invert_mask { invert(&mask) } else { mask };
+        save_texture(&mask, output, "voronoi_mask")?;
+    }
+    if output.ao_map {
+        save_texture(&output.ao.apply(&maps.blurred), output, "blurred_voronoi_ao")?;
+    }
+
+    if let Some(coloring) = &coloring {
+        let cells = generate_colored_cells(&params.voronoi, coloring, output.distinct_neighbors);
+        save_image(&cells, output, "voronoi_cells")?;
+    } else if output.cells {
+        let cells = generate_voronoi_cells(&params.voronoi, output.cell_color);
+        save_image(&cells, output, "voronoi_cells")?;
+    }
+    if output.cell_ids {
+        if args.points > 1 << 16 {
+            return Err(format!("--cell-ids supports at most 65536 points, got {}", args.points).into());
+        }
+        save_image(&generate_voronoi_cell_ids(&params.voronoi), output, "voronoi_cell_ids")?;
+    }
+
+    if output.timing {
+        stages.record("write", writing);
+        stages.print_timings();
+    }
+    Ok(())
+}
+
+/// Pack generated maps and image files into one RGBA texture
+fn pack(args: &PackArgs) -> Result<(), Box<dyn Error>> {
+    let sources = [Some(&args.r), Some(&args.g), Some(&args.b), args.a.as_ref()];
+    let fields = load_sources(sources.into_iter().flatten(), &args.generate)?;
+    let packed = pack_channels(&fields[0], &fields[1], &fields[2], fields.get(3))?;
+    save(&packed, &args.output)
+}
+
+/// Write the blend of two generated maps or image files
+fn blend_maps(args: &BlendArgs) -> Result<(), Box<dyn Error>> {
+    let sources = [Some(&args.a), Some(&args.b), args.mask.as_ref()];
+    let fields = load_sources(sources.into_iter().flatten(), &args.generate)?;
+    let mut blended = blend(&fields[0], &fields[1], args.mode, args.opacity)?;
+    if let Some(mask) = fields.get(2) {
+        let mask = gaussian_blur(mask, args.feather);
+        let mask = if args.invert_mask { invert(&mask) } else { mask };
+        blended = blend_masked(&fields[0], &blended, &mask)?;
+    }
+    write_field(&blended, &args.output, args.bit_depth)
+}
+
+/// Run a pipeline file, writing every node with a `save` file
+fn run_pipeline(args: &RunArgs, manifest: Manifest) -> Result<(), Box<dyn Error>> {
+    if args.watch {
+        return watch_pipeline(args, manifest);
+    }
+    let description = fs::read_to_string(&args.pipeline)
+        .map_err(|err| format!("cannot read {}: {err}", args.pipeline.display()))?;
+    execute_pipeline(&description, args, manifest)
+}
+
+/// Run a pipeline description with the options of `args`, writing every node with a `save` file
+fn execute_pipeline(description: &str, args: &RunArgs, manifest: Manifest) -> Result<(), Box<dyn Error>> {
+    let pipeline = prepare_pipeline(description, args, manifest)?;
+    let outputs = pipeline.execute_with(&mut node_cache(args), |_| ControlFlow::Continue(()))?;
+    save_pipeline(&pipeline, &outputs.expect("runs that are never stopped finish"), args)
+}
+
+/// The default directory of the pipeline node cache
+const DEFAULT_CACHE_DIR: &str = ".cells-cache";
+
+/// The node cache of a pipeline run: in memory only with --no-cache, otherwise also in the cache directory
+fn node_cache(args: &RunArgs) -> NodeCache {
+    if args.no_cache {
+        NodeCache::default()
+    } else {
+        NodeCache::with_directory(&args.cache_dir, args.cache_size.saturating_mul(1 << 20))
+    }
+}
+
+/// Manage the node cache directory
+fn cache(args: &CacheArgs) -> Result<(), Box<dyn Error>> {
+    match args.action {
+        CacheAction::Clear if !args.cache_dir.exists() => println!("{} holds no cache", args.cache_dir.display()),
+        CacheAction::Clear => {
+            NodeCache::clear_directory(&args.cache_dir)
+                .map_err(|err| format!("cannot clear {}: {err}", args.cache_dir.display()))?;
+            println!("cleared {}", args.cache_dir.display());
+        }
+    }
+    Ok(())
+}
+
+/// Parse a pipeline description and record it in the manifest of the files written
+fn prepare_pipeline(description: &str, args: &RunArgs, mut manifest: Manifest) -> Result<Pipeline, Box<dyn Error>> {
+    let pipeline = Pipeline::from_toml(description)?;
+    manifest.pipeline = Some(description.to_string());
+    if let Some(path) = &args.manifest {
+        manifest.save_json(path).map_err(|err| format!("cannot write {}: {err}", path.display()))?;
+        println!("wrote {}", path.display());
+    }
+    set_manifest(manifest);
+    Ok(pipeline)
+}
+
+/// Write every node of a pipeline with a `save` file
+fn save_pipeline(
+    pipeline: &Pipeline,
+    outputs: &HashMap<String, FieldBuffer>,
+    args: &RunArgs,
+) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(&args.out_dir)
+        .map_err(|err| format!("cannot create output directory {}: {err}", args.out_dir.display()))?;
+    for node in pipeline.nodes() {
+        if let Some(save) = &node.save {
+            write_field(&outputs[&node.name], &args.out_dir.join(save), args.bit_depth)?;
+        }
+    }
+    Ok(())
+}
+
+/// How often a watched pipeline file is checked for changes
+const WATCH_POLL: Duration = Duration::from_millis(200);
+
+/// How long a changed pipeline file must stay unchanged before it is run, so a save in several writes runs once
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// The modification time and length of a file, or `None` while it cannot be read
+fn file_version(path: &Path) -> Option<(std::time::SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Run a pipeline whenever its file changes, reusing the outputs of unchanged nodes and stopping a
+/// run as soon as a newer change arrives
+fn watch_pipeline(args: &RunArgs, manifest: Manifest) -> Result<(), Box<dyn Error>> {
+    let mut cache = node_cache(args);
+    let mut seen = None;
+    loop {
+        // Wait for a version that differs from the last one run and has settled
+        let version = loop {
+            let version = file_version(&args.pipeline);
+            if version.is_some() && version != seen {
+                thread::sleep(WATCH_DEBOUNCE);
+                if file_version(&args.pipeline) == version {
+                    break version;
+                }
+            }
+            thread::sleep(WATCH_POLL);
+        };
+        seen = version;
+        let started = Instant::now();
+        match watched_run(args, manifest.clone(), &mut cache, || file_version(&args.pipeline) != version) {
+            Ok(true) => println!("finished in {:.2?}", started.elapsed()),
+            Ok(false) => println!("{} changed, starting over", args.pipeline.display()),
+            Err(err) => log::error!("{err}"),
+        }
+        println!("watching {} for changes, press Ctrl-C to stop", args.pipeline.display());
+    }
+}
+
+/// Run the pipeline file once with the outputs of `cache`, printing the time of every node
 ///
 /// # Returns
 ///
-/// An `ImageBuffer` containing the Voronoi diagram
-///
-/// # Performance
-///
-/// This function has O(SIZE^2 * NUM_POINTS) complexity. For large images or
-/// many Voronoi points, consider parallelizing the pixel generation process.
-///
-/// # Example
-///
-/// ```rust
-/// let voronoi_texture = generate_tileable_voronoi();
-/// save_image(&voronoi_texture, "voronoi_texture.png").unwrap();
-/// ```
-fn generate_tileable_voronoi() -> ImageBuffer<Rgb<u8>, Vec<u8>> {
-    let mut rng = rand::thread_rng();
-    let points: Vec<Point> = (0..NUM_POINTS)
-        .map(|_| Point { x: rng.gen(), y: rng.gen() })
-        .collect();
-
-    // First pass: find the maximum distance
-    let max_distance = (0..SIZE)
-        .into_par_iter()
-        .flat_map(|x| (0..SIZE).into_par_iter().map(move |y| (x, y)))
-        .map(|(x, y)| {
-            let current = Point {
-                x: x as f32 / SIZE as f32,
-                y: y as f32 / SIZE as f32,
-            };
-            points
-                .par_iter()
-                .map(|&p| toroidal_distance(current, p))
-                .min_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
-                .unwrap()
-        })
-        .max_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
-        .unwrap();
-
-    // Second pass: generate the image
-    ImageBuffer::from_par_fn(SIZE, SIZE, |x, y| {
-        let current_point = Point {
-            x: x as f32 / SIZE as f32,
-            y: y as f32 / SIZE as f32,
+/// `false` if the run was stopped because `changed` turned true after a node
+fn watched_run(
+    args: &RunArgs,
+    manifest: Manifest,
+    cache: &mut NodeCache,
+    changed: impl Fn() -> bool,
+) -> Result<bool, Box<dyn Error>> {
+    let description = fs::read_to_string(&args.pipeline)
+        .map_err(|err| format!("cannot read {}: {err}", args.pipeline.display()))?;
+    let pipeline = prepare_pipeline(&description, args, manifest)?;
+    let outputs = pipeline.execute_with(cache, |run| {
+        let source = if run.cached { " (cached)" } else { "" };
+        println!("  {:<24} {:>10.2?}{source}", run.name, run.elapsed);
+        if changed() {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    })?;
+    let Some(outputs) = outputs else {
+        return Ok(false);
+    };
+    save_pipeline(&pipeline, &outputs, args)?;
+    Ok(true)
+}
+
+/// Load the field of every source, generating the maps only if a source needs them
+fn load_sources<'a>(
+    sources: impl IntoIterator<Item = &'a Source>,
+    generate: &GenerateArgs,
+) -> Result<Vec<FieldBuffer>, Box<dyn Error>> {
+    let sources: Vec<&Source> = sources.into_iter().collect();
+    let maps = if sources.iter().any(|source| !matches!(source, Source::File(_))) {
+        Some(generate_maps(&params(generate)?, &mut Stages::default(), |_, _| {}))
+    } else {
+        None
+    };
+    let mut fields = Vec::new();
+    for source in sources {
+        fields.push(match (source, &maps) {
+            (Source::File(path), _) => load_field(path)?,
+            (Source::Voronoi, Some(maps)) => maps.voronoi.clone(),
+            (Source::Perlin, Some(maps)) => maps.perlin.clone(),
+            (Source::Blurred, Some(maps)) => maps.blurred.clone(),
+            (_, None) => unreachable!("maps are generated when a source needs them"),
+        });
+    }
+    Ok(fields)
+}
+
+/// Write the frames of a generated map looping in time, as numbered files or one flipbook
+fn animate(args: &AnimateArgs) -> Result<(), Box<dyn Error>> {
+    if args.map != AnimatedMap::Perlin && args.generate.backend == Backend::NoiseWorley {
+        return Err("animated cells need the points of the grid backend, use --backend grid".into());
+    }
+    let animated = args.format != AnimationFormat::Png;
+    if animated && args.flipbook.is_some() {
+        return Err("--flipbook packs the frames into one PNG, drop --format".into());
+    }
+    if animated && args.bit_depth == 16 {
+        return Err("gif and apng frames are 8-bit, drop --bit-depth 16".into());
+    }
+    match args.flipbook {
+        Some((cols, rows)) if cols as u64 * rows as u64 != args.frames as u64 => {
+            let cells = cols as u64 * rows as u64;
+            return Err(format!("a {cols}x{rows} flipbook holds {cells} frames, pass --frames {cells}").into());
+        }
+        Some(_) => {}
+        None if animated => {}
+        None => {
+            frame_path(&args.out, 0)?;
+        }
+    }
+
+    let mut params = params(&args.generate)?;
+    params.gpu = open_gpu(args.generate.backend);
+    if args.map != AnimatedMap::Voronoi {
+        warn_aliased_octaves(params.voronoi.size, &params.fbm);
+    }
+    let mut stages = Stages::default();
+    let mut frames = Vec::new();
+    for index in 0..args.frames {
+        params.frame = Some(Frame {
+            phase: index as f64 / args.frames as f64,
+            motion: args.motion,
+            orbit: args.orbit,
+        });
+        let field = match args.map {
+            AnimatedMap::Perlin => perlin_map(&params, |_| {}),
+            AnimatedMap::Voronoi => generate_maps(&params, &mut stages, |_, _| {}).voronoi,
+            AnimatedMap::Blurred => generate_maps(&params, &mut stages, |_, _| {}).blurred,
         };
-        let min_distance = points
-            .par_iter()
-            .map(|&p| toroidal_distance(current_point, p))
-            .min_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
-            .unwrap();
-
-        // Normalize the distance and invert it (distant = brighter)
-        let normalized_distance = 1.0 - (min_distance / max_distance);
-        // Map to 0-255 range for the red channel
-        let red_value = 255 - (normalized_distance * 255.0) as u8;
-        Rgb([red_value, 0, 0]) // Only red channel, others set to 0
-    })
+        if animated || args.flipbook.is_some() {
+            frames.push(field);
+            continue;
+        }
+        let path = PathBuf::from(frame_path(&args.out, index)?);
+        create_parent_dir(&path)?;
+        write_field(&field, &path, args.bit_depth)?;
+    }
+    if let Some((cols, rows)) = args.flipbook {
+        write_field(&flipbook(&frames, cols, rows)?, Path::new(&args.out), args.bit_depth)?;
+    }
+    let delay = Duration::from_millis(args.delay);
+    let saved = match args.format {
+        AnimationFormat::Png => return Ok(()),
+        AnimationFormat::Gif => save_gif(&frames, &args.out, delay),
+        AnimationFormat::Apng => save_apng(&frames, &args.out, delay),
+    };
+    saved.map_err(|err| format!("cannot write {}: {err}", args.out))?;
+    println!("wrote {}", args.out);
+    Ok(())
 }
 
-/// Apply directional blur to an image
-///
-/// This function applies a directional blur to the input image, using another image
-/// as a direction map. The blur direction for each pixel is determined by the
-/// corresponding pixel value in the direction map.
-///
-/// # Algorithm
-///
-/// 1. For each pixel in the input image:
-///    a. Determine the blur direction from the direction map
-///    b. Sample pixels along this direction within the blur radius
-///    c. Calculate the average of the sampled pixels
-///    d. Set the output pixel to this average value
-/// 2. Wrap around image edges to ensure seamless tiling
-///
-/// # Arguments
-///
-/// * `img` - The input image to be blurred
-/// * `direction_channel` - The image used as a direction map for the blur
-/// * `blur_radius` - The radius of the blur effect
-///
-/// # Returns
-///
-/// An `ImageBuffer` containing the blurred image
-///
-/// # Performance
-///
-/// This function has O(width * height * blur_radius) complexity. For large images
-/// or large blur radii, consider parallelizing the pixel processing.
-///
-/// # Example
-///
-/// ```rust
-/// let input_image = generate_tileable_voronoi();
-/// let direction_map = generate_perlin_noise();
-/// let blurred_image = directional_blur(&input_image, &direction_map, 5);
-/// save_image(&blurred_image, "blurred_image.png").unwrap();
-/// ```
-fn directional_blur(
-    img: &ImageBuffer<Rgb<u8>, Vec<u8>>,
-    direction_channel: &ImageBuffer<Rgb<u8>, Vec<u8>>,
-    blur_radius: i32,
-) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
-    let (width, height) = img.dimensions();
-    let output = ImageBuffer::from_par_fn(width, height, |x, y| {
-        let direction = direction_channel.get_pixel(x, y)[0] as f32 / 255.0 * 360.0;
-        let angle = direction.to_radians();
-        
-        let sum_red: f32 = (-blur_radius..=blur_radius)
-            .map(|i| {
-                let delta_x = (i as f32 * angle.cos()).round() as i32;
-                let delta_y = (i as f32 * angle.sin()).round() as i32;
-                let sample_x = (x as i32 + delta_x).rem_euclid(width as i32) as u32;
-                let sample_y = (y as i32 + delta_y).rem_euclid(height as i32) as u32;
-                img.get_pixel(sample_x, sample_y)[0] as f32
-            })
-            .sum();
-
-        let count = (2 * blur_radius + 1) as f32;
-        let blurred_value = (sum_red / count).round() as u8;
-        Rgb([blurred_value, 0, 0])
+/// Write a Voronoi or Perlin map to a PNG band by band, optionally blurred
+fn stream(args: &StreamArgs, manifest: Manifest) -> Result<(), Box<dyn Error>> {
+    if FileFormat::from_path(&args.output) != Some(FileFormat::Png) {
+        return Err("streamed textures are written as PNG, use a .png output".into());
+    }
+    if args.generate.perlin_blend.is_some() {
+        return Err("--perlin-blend needs both whole maps, drop it to stream".into());
+    }
+    let halo = args.gaussian.map_or(0, |sigma| ((3.0 * sigma).ceil() as u64).min(args.generate.size as u64));
+    let rows = match (args.tile_rows, args.max_memory) {
+        (Some(rows), _) => rows,
+        (None, Some(bytes)) => {
+            let row_bytes = args.generate.size as u64 * STREAM_BYTES_PER_PIXEL;
+            match (bytes / row_bytes).checked_sub(2 * halo) {
+                Some(rows @ 1..) => rows.min(args.generate.size as u64) as u32,
+                _ => {
+                    let needed = (2 * halo + 1) * row_bytes;
+                    return Err(format!("--max-memory fits no band, at least {needed} bytes are needed").into());
+                }
+            }
+        }
+        (None, None) => DEFAULT_TILE_ROWS,
+    };
+    set_manifest(manifest);
+    let params = params(&args.generate)?;
+    match args.map {
+        StreamedMap::Voronoi => stream_source(VoronoiBands::new(&params.voronoi)?, args, rows)?,
+        StreamedMap::Perlin => {
+            let perlin = PerlinBands::new(params.voronoi.size, &params.fbm)?;
+            stream_source(Stretched::new(perlin, rows), args, rows)?
+        }
+    }
+    embed_manifest(&args.output)?;
+    println!("wrote {}", args.output.display());
+    Ok(())
+}
+
+/// Save a source band by band to the output of `args`, blurred if asked for
+fn stream_source(source: impl BandSource, args: &StreamArgs, rows: u32) -> Result<(), Box<dyn Error>> {
+    let saved = match args.gaussian {
+        Some(sigma) => save_png_bands(&GaussianBlurred::new(source, sigma), rows, &args.output, args.bit_depth),
+        None => save_png_bands(&source, rows, &args.output, args.bit_depth),
+    };
+    Ok(saved.map_err(|err| format!("cannot write {}: {err}", args.output.display()))?)
+}
+
+/// Write a tileable noise volume slice by slice, as numbered images, an atlas or raw floats
+fn volume(args: &VolumeArgs) -> Result<(), Box<dyn Error>> {
+    let raw = Path::new(&args.output).extension().is_some_and(|ext| ext.eq_ignore_ascii_case("raw"));
+    match args.atlas {
+        Some(_) if raw => return Err("--atlas writes an image, not a .raw volume".into()),
+        Some((cols, rows)) if cols as u64 * rows as u64 != args.depth as u64 => {
+            let tiles = cols as u64 * rows as u64;
+            return Err(format!("a {cols}x{rows} atlas holds {tiles} slices, pass --depth {tiles}").into());
+        }
+        Some(_) => {}
+        None if raw => {}
+        None => {
+            frame_path(&args.output, 0)?;
+        }
+    }
+    let seed = args.seed.unwrap_or_else(rand::random);
+    println!("seed {seed}");
+    let params = FbmParams {
+        octaves: args.octaves,
+        persistence: args.persistence,
+        lacunarity: args.lacunarity,
+        frequency: args.frequency,
+        style: args.noise_style,
+        seed,
+        ..Default::default()
+    };
+    params.validate()?;
+
+    let (size, depth) = (args.size, args.depth);
+    if raw {
+        let slices = (0..depth).map(|z| generate_volume_slice(size, depth, z, &params));
+        save_raw_volume(slices, &args.output).map_err(|err| format!("cannot write {}: {err}", args.output))?;
+        println!("wrote {}", args.output);
+        return Ok(());
+    }
+    let slices = (0..depth).map(|z| generate_volume_texture_slice(size, depth, z, &params));
+    if let Some((cols, rows)) = args.atlas {
+        save_slice_atlas(slices, cols, rows, &args.output, args.bit_depth)
+            .map_err(|err| format!("cannot write {}: {err}", args.output))?;
+        println!("wrote {}", args.output);
+        return Ok(());
+    }
+    for (z, slice) in slices.enumerate() {
+        let path = PathBuf::from(frame_path(&args.output, z as u32)?);
+        create_parent_dir(&path)?;
+        write_field(&slice, &path, args.bit_depth)?;
+    }
+    Ok(())
+}
+
+/// Create the directory a numbered frame or slice is written to
+fn create_parent_dir(path: &Path) -> Result<(), Box<dyn Error>> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(|err| format!("cannot create directory {}: {err}", dir.display()))?;
+    }
+    Ok(())
+}
+
+/// Report the seam discontinuity of every file, failing above the tolerance
+fn verify(args: &VerifyArgs) -> Result<(), Box<dyn Error>> {
+    let mut seamed = 0;
+    for path in &args.files {
+        let field = load_field(path)?;
+        let seam = seam_discontinuity(&field);
+        println!(
+            "{}: max seam discontinuity {seam:.6}, max interior step {:.6}",
+            path.display(),
+            interior_discontinuity(&field)
+        );
+        if args.tolerance.is_some_and(|tolerance| seam > tolerance) {
+            seamed += 1;
+        }
+    }
+    if let Some(tolerance) = args.tolerance.filter(|_| seamed > 0) {
+        return Err(format!("{seamed} file(s) exceed the seam tolerance {tolerance}").into());
+    }
+    Ok(())
+}
+
+/// Run the filter steps over an image file
+fn filter(args: &FilterArgs) -> Result<(), Box<dyn Error>> {
+    let mut field = load_field(&args.input)?;
+    for step in &args.steps {
+        field = step.apply(&field);
+    }
+    write_field(&field, &args.output, args.bit_depth)
+}
+
+/// Write a brick wall heightmap
+fn bricks(args: &BricksArgs) -> Result<(), Box<dyn Error>> {
+    let seed = args.seed.unwrap_or_else(rand::random);
+    println!("seed {seed}");
+    let params = BrickParams {
+        size: args.size,
+        rows: args.rows,
+        cols: args.cols,
+        offset: args.offset,
+        mortar: args.mortar,
+        bevel: args.bevel,
+        variation: args.variation,
+        seed,
+    };
+    params.validate()?;
+    let shift = args.rows as f32 * args.offset;
+    if shift != shift.round() {
+        log::warn!("{} rows shifted by {} bricks do not tile vertically", args.rows, args.offset);
+    }
+    write_field(&generate_bricks(&params), &args.output, args.bit_depth)
+}
+
+/// Write a scratch mask
+fn scratches(args: &ScratchesArgs) -> Result<(), Box<dyn Error>> {
+    let seed = args.seed.unwrap_or_else(rand::random);
+    println!("seed {seed}");
+    let params = ScratchParams {
+        size: args.size,
+        count: args.count,
+        length: args.length,
+        angle: args.angle,
+        spread: args.spread,
+        width: args.width,
+        intensity: args.intensity,
+    };
+    params.validate()?;
+    write_field(&generate_scratches(&params, seed), &args.output, args.bit_depth)
+}
+
+/// Write a speckle texture
+fn speckle(args: &SpeckleArgs) -> Result<(), Box<dyn Error>> {
+    let seed = args.seed.unwrap_or_else(rand::random);
+    println!("seed {seed}");
+    let params = SpeckleParams {
+        size: args.size,
+        count: args.count,
+        distribution: args.distribution,
+        radius: args.radius,
+        intensity: args.intensity,
+        shape: args.shape,
+        seed,
+    };
+    params.validate()?;
+    write_field(&generate_speckle(&params), &args.output, args.bit_depth)
+}
+
+/// Write a wood or marble texture made by `generate`
+fn grain(args: &GrainArgs, generate: fn(&GrainParams) -> FieldBuffer) -> Result<(), Box<dyn Error>> {
+    let seed = args.seed.unwrap_or_else(rand::random);
+    println!("seed {seed}");
+    let params = GrainParams {
+        size: args.size,
+        count: args.count,
+        turbulence: args.turbulence,
+        frequency: args.frequency,
+        seed,
+    };
+    params.validate()?;
+    write_field(&generate(&params), &args.output, args.bit_depth)
+}
+
+/// Write a crack mask
+fn cracks(args: &CracksArgs) -> Result<(), Box<dyn Error>> {
+    let seed = args.seed.unwrap_or_else(rand::random);
+    println!("seed {seed}");
+    let params = CrackParams {
+        size: args.size,
+        num_points: args.points,
+        relax_iterations: args.relax,
+        width: args.width,
+        fade_scale: args.fade_scale,
+        fade: args.fade,
+        removal: args.removal,
+        seed,
+    };
+    params.validate()?;
+    write_field(&generate_cracks(&params), &args.output, args.bit_depth)
+}
+
+/// Write hexagonal Voronoi cells
+fn hex(args: &HexArgs) -> Result<(), Box<dyn Error>> {
+    let (cols, rows) = args.cells;
+    check_hex_lattice(cols, rows)?;
+    let seed = args.seed.unwrap_or_else(rand::random);
+    println!("seed {seed}");
+    let params = VoronoiParams {
+        size: args.size,
+        distribution: PointDistribution::HexGrid { cols, rows, jitter: args.jitter },
+        seed,
+        feature: args.feature,
+        invert: args.invert,
+        ..Default::default()
+    };
+    let field = Stages::default().run("voronoi", |progress| generate_voronoi_field_with_progress(&params, progress));
+    write_field(&field, &args.output, args.bit_depth)
+}
+
+/// Write Truchet tiles
+fn truchet(args: &TruchetArgs) -> Result<(), Box<dyn Error>> {
+    if args.line_width > 1.0 {
+        return Err(format!("the line width must be at most 1, got {}", args.line_width).into());
+    }
+    let seed = args.seed.unwrap_or_else(rand::random);
+    println!("seed {seed}");
+    let field = generate_truchet(args.size, args.grid, args.line_width, args.style, seed);
+    write_field(&field, &args.output, args.bit_depth)
+}
+
+/// Write Gabor noise
+fn gabor(args: &GaborArgs) -> Result<(), Box<dyn Error>> {
+    let seed = args.seed.unwrap_or_else(rand::random);
+    println!("seed {seed}");
+    let params = GaborParams {
+        size: args.size,
+        frequency: args.frequency,
+        radius: args.radius,
+        kernels: args.kernels,
+        orientation: args.orientation,
+        spread: args.spread,
+    };
+    params.validate()?;
+    let field = match &args.orientation_map {
+        Some(path) => generate_gabor_along(&params, seed, &load_field(path)?),
+        None => generate_gabor(&params, seed),
+    };
+    write_field(&field, &args.output, args.bit_depth)
+}
+
+/// Write a normalized reaction–diffusion texture
+fn reaction_diffusion(args: &ReactionDiffusionArgs) -> Result<(), Box<dyn Error>> {
+    let seed = args.seed.unwrap_or_else(rand::random);
+    println!("seed {seed}");
+    let (feed, kill) = args.preset.rates();
+    let params = ReactionDiffusionParams {
+        size: args.size,
+        feed: args.feed.unwrap_or(feed),
+        kill: args.kill.unwrap_or(kill),
+        steps: args.steps,
+        seeding: args.seeding,
+        chemical: args.chemical,
+        seed,
+        ..Default::default()
+    };
+    params.validate()?;
+    let field = Stages::default().run("reaction", |progress| generate_reaction_diffusion_with(&params, progress));
+    write_field(&normalize_field(&field), &args.output, args.bit_depth)
+}
+
+/// Write the cellular automaton smoothing of a mask image
+fn cellular(args: &CellularArgs) -> Result<(), Box<dyn Error>> {
+    let mask = load_field(&args.input)?;
+    let mut iteration_error = None;
+    let smoothed = cellular_smooth_with(&mask, args.iterations, args.birth, args.death, |i, cells| {
+        if args.save_iterations && iteration_error.is_none() {
+            let stem = args.output.file_stem().unwrap_or_default().to_string_lossy();
+            let mut path = args.output.with_file_name(format!("{stem}_iteration_{}", i + 1));
+            if let Some(extension) = args.output.extension() {
+                path.set_extension(extension);
+            }
+            iteration_error = write_field(cells, &path, 8).err();
+        }
     });
+    if let Some(err) = iteration_error {
+        return Err(err);
+    }
+    write_field(&smoothed, &args.output, 8)
+}
 
-    output
+/// Write a tiled preview of an image file, keeping its channels and bit depth
+fn tile(args: &TileArgs) -> Result<(), Box<dyn Error>> {
+    let (cols, rows) = args.copies;
+    let path = &args.input;
+    if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("exr")) {
+        let field = load_exr(path).map_err(|err| format!("cannot read {}: {err}", path.display()))?;
+        return write_field(&tile_preview_field(&field, cols, rows), &args.output, 16);
+    }
+    let img = image::open(path).map_err(|err| format!("cannot read {}: {err}", path.display()))?;
+    match img {
+        DynamicImage::ImageLuma8(img) => save(&tile_preview(&img, cols, rows), &args.output),
+        DynamicImage::ImageLumaA8(img) => save(&tile_preview(&img, cols, rows), &args.output),
+        DynamicImage::ImageRgb8(img) => save(&tile_preview(&img, cols, rows), &args.output),
+        DynamicImage::ImageRgba8(img) => save(&tile_preview(&img, cols, rows), &args.output),
+        DynamicImage::ImageLuma16(img) => save(&tile_preview(&img, cols, rows), &args.output),
+        DynamicImage::ImageLumaA16(img) => save(&tile_preview(&img, cols, rows), &args.output),
+        DynamicImage::ImageRgb16(img) => save(&tile_preview(&img, cols, rows), &args.output),
+        img => save(&tile_preview(&img.to_rgba16(), cols, rows), &args.output),
+    }
 }
 
-/// Generate Perlin noise texture
-///
-/// This function creates a texture using Perlin noise with multiple octaves,
-/// resulting in a fractal-like pattern. The noise is normalized to use only
-/// the red channel of the image.
-///
-/// # Algorithm
-///
-/// 1. Initialize a Perlin noise generator
-/// 2. For each pixel in the output image:
-///    a. Generate fractal Brownian motion (fBm) noise:
-///       - Sum multiple octaves of Perlin noise
-///       - For each octave, increase frequency and decrease amplitude
-///    b. Normalize the resulting noise value to the range [0, 1]
-///    c. Map the normalized value to a grayscale intensity (0-255)
-/// 3. Set the red channel of each pixel to the calculated intensity
-///
-/// # Returns
-///
-/// An `ImageBuffer` containing the Perlin noise texture
-///
-/// # Performance
-///
-/// The complexity is O(SIZE^2 * octaves). Consider parallelizing the pixel
-/// generation process for large images or many octaves.
-///
-/// # Example
-///
-/// ```rust
-/// let perlin_texture = generate_perlin_noise();
-/// save_image(&perlin_texture, "perlin_texture.png").unwrap();
-/// ```
-fn generate_perlin_noise() -> ImageBuffer<Rgb<u8>, Vec<u8>> {
-    let perlin = Perlin::new(0);
-    let octaves = 6;
-    let persistence = 0.5;
-    let lacunarity = 2.0;
-
-    ImageBuffer::from_fn(SIZE, SIZE, |x, y| {
-        let mut noise_value = 0.0;
-        let mut amplitude = 1.0;
-        let mut frequency = 1.0;
-        let mut max_value = 0.0;
-
-        for _ in 0..octaves {
-            let normalized_x = x as f64 / SIZE as f64 * frequency;
-            let normalized_y = y as f64 / SIZE as f64 * frequency;
-
-            noise_value += perlin.get([normalized_x, normalized_y]) * amplitude;
-            
-            max_value += amplitude;
-            amplitude *= persistence;
-            frequency *= lacunarity;
-        }
-
-        // Normalize the noise value
-        noise_value = (noise_value / max_value + 1.0) / 2.0;
-        let intensity = (noise_value * 255.0) as u8;
-
-        Rgb([intensity, 0, 0])
+/// Write the ambient occlusion map of a heightmap image
+fn ao(args: &AoArgs) -> Result<(), Box<dyn Error>> {
+    let height = load_field(&args.input)?;
+    write_field(&args.ao.apply(&height), &args.output, args.bit_depth)
+}
+
+/// Write the curvature maps of a heightmap image
+fn curvature_maps(args: &CurvatureArgs) -> Result<(), Box<dyn Error>> {
+    let height = gaussian_blur(&load_field(&args.input)?, args.sigma);
+    write_field(&signed_curvature(&height), &args.output, args.bit_depth)?;
+    if args.ridges.is_some() || args.cavities.is_some() {
+        let (ridges, cavities) = curvature(&height);
+        for (path, mask) in [(&args.ridges, ridges), (&args.cavities, cavities)] {
+            if let Some(path) = path {
+                write_field(&mask, path, args.bit_depth)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Write the Sobel edge strength and direction of an image file
+fn sobel_maps(args: &SobelArgs) -> Result<(), Box<dyn Error>> {
+    if args.magnitude.is_none() && args.direction.is_none() {
+        return Err("nothing to write, pass --magnitude, --direction or both".into());
+    }
+    let (magnitude, mut direction) = sobel(&load_field(&args.input)?);
+    if args.along_edges {
+        direction = direction.map(|d| (d + 0.25) % 1.0);
+    }
+    for (path, map) in [(&args.magnitude, magnitude), (&args.direction, direction)] {
+        if let Some(path) = path {
+            write_field(&map, path, args.bit_depth)?;
+        }
+    }
+    Ok(())
+}
+
+/// Displace an image file along the offset maps or the angle map of the `warp` subcommand
+fn warp_image(args: &WarpArgs) -> Result<(), Box<dyn Error>> {
+    let input = load_field(&args.input)?;
+    let map = |path: &Path| -> Result<FieldBuffer, Box<dyn Error>> {
+        let map = load_field(path)?;
+        if map.dimensions() != input.dimensions() {
+            let ((width, height), (input_width, input_height)) = (map.dimensions(), input.dimensions());
+            let path = path.display();
+            return Err(format!("{path} is {width}x{height}, not {input_width}x{input_height} like the input").into());
+        }
+        Ok(map)
+    };
+    let (dx, dy) = match (&args.dx, &args.dy, &args.angle) {
+        (Some(dx), Some(dy), _) => (signed_offsets(&map(dx)?), signed_offsets(&map(dy)?)),
+        (_, _, Some(angle)) => angle_offsets(&map(angle)?),
+        _ => unreachable!("clap requires --dx and --dy, or --angle"),
+    };
+    write_field(&warp(&input, &dx, &dy, args.strength), &args.output, args.bit_depth)
+}
+
+/// Build the generator parameters, picking and printing a seed if none was given
+fn params(args: &GenerateArgs) -> Result<Params, Box<dyn Error>> {
+    let seed = args.seed.unwrap_or_else(rand::random);
+    println!("seed {seed}");
+
+    let fbm_params = FbmParams {
+        octaves: args.octaves,
+        persistence: args.persistence,
+        lacunarity: args.lacunarity,
+        frequency: args.frequency,
+        kind: args.noise,
+        style: args.noise_style,
+        seed,
+    };
+    fbm_params.validate()?;
+
+    let (weighting, weight_range) = match args.weight_range {
+        Some((min, _)) if args.weighting == Weighting::Multiplicative && min <= 0.0 => {
+            return Err("multiplicative weights must be positive, use a --weight-range above 0".into());
+        }
+        Some(range) => (args.weighting, range),
+        None => (Weighting::None, (0.0, 0.0)),
+    };
+    let backend = match args.backend {
+        Backend::Grid | Backend::Cpu | Backend::Gpu | Backend::Auto => VoronoiBackend::Grid,
+        Backend::NoiseWorley if args.feature != Feature::F1 => {
+            return Err(format!("--feature {} needs --backend grid", args.feature).into());
+        }
+        Backend::NoiseWorley => VoronoiBackend::NoiseWorley {
+            frequency: args.worley_frequency,
+            return_type: args.worley_return,
+        },
+    };
+    let voronoi_params = VoronoiParams {
+        size: args.size,
+        num_points: args.points,
+        distribution: match args.grid {
+            Some((cols, rows)) => PointDistribution::JitteredGrid { cols, rows, jitter: args.jitter },
+            None => args.distribution,
+        },
+        relax_iterations: args.relax,
+        seed,
+        feature: args.feature,
+        cell_values: args.cell_values.clone(),
+        value_smoothing: args.value_smoothing,
+        metric: args.metric,
+        cell_scale: args.cell_scale,
+        weighting,
+        weight_range,
+        invert: args.invert,
+        warp_strength: args.warp_strength,
+        warp_frequency: args.warp_frequency,
+        backend,
+        clip: args.clip,
+        octaves: args.voronoi_octaves,
+        persistence: args.voronoi_persistence,
+    };
+    voronoi_params.validate()?;
+    let blur_params = BlurParams {
+        radius: args.blur_radius,
+        sampling: args.blur_sampling,
+        kernel: args.blur_kernel,
+        mapping: args.direction_mapping,
+    };
+    Ok(Params {
+        voronoi: voronoi_params,
+        fbm: fbm_params,
+        blur: blur_params,
+        blur_steps: args.blur_steps,
+        blur_growth: args.blur_growth,
+        normalization: match args.clip {
+            Some((low, high)) => Normalization::Percentile { low, high },
+            None => args.normalize,
+        },
+        sharpen: args.sharpen,
+        levels: args.levels,
+        curve: args.curve.clone(),
+        posterize: args.posterize,
+        dither: args.dither,
+        perlin_blend: args.perlin_blend.map(|mode| (mode, args.perlin_blend_opacity)),
+        flow: args.flow,
+        blur_length: args.blur_length_map.as_deref().map(|path| blur_length_map(path, args.size)).transpose()?,
+        frame: None,
+        gpu: None,
     })
 }
-/// Normalize an image to use the full 0-255 range
-///
-/// This function adjusts the pixel values of the input image to span the full
-/// 0-255 range, improving contrast. It operates only on the red channel.
-///
-/// # Algorithm
-///
-/// 1. Find the minimum and maximum pixel values in the input image
-/// 2. For each pixel:
-///    a. Apply the formula: new_value = (old_value - min) / (max - min) * 255
-///    b. Round the result and set it as the new pixel value
-///
-/// # Arguments
-///
-/// * `img` - The input image to be normalized
-///
-/// # Returns
-///
-/// An `ImageBuffer` containing the normalized image
-///
-/// # Performance
-///
-/// This function has O(width * height) complexity. For very large images,
-/// consider processing the image in chunks to reduce memory usage.
+
+/// Open the GPU `--backend gpu` and `--backend auto` run on
 ///
-/// # Example
+/// `gpu` takes any adapter, software ones included, and `auto` only hardware
+/// ones. Without an adapter, or in builds without the `gpu` feature, both run
+/// on the CPU, and `gpu` warns.
+fn open_gpu(backend: Backend) -> Option<Gpu> {
+    if !matches!(backend, Backend::Gpu | Backend::Auto) {
+        return None;
+    }
+    #[cfg(feature = "gpu")]
+    match Gpu::new() {
+        Some(gpu) if backend == Backend::Auto && gpu.adapter_info().device_type == wgpu::DeviceType::Cpu => {
+            log::info!("{} is a software adapter, running on the CPU", gpu.adapter_info().name);
+            None
+        }
+        Some(gpu) => {
+            let info = gpu.adapter_info();
+            log::info!("running on {} through {}", info.name, info.backend);
+            Some(gpu)
+        }
+        None => {
+            if backend == Backend::Gpu {
+                log::warn!("no GPU adapter runs compute shaders, running on the CPU");
+            }
+            None
+        }
+    }
+    #[cfg(not(feature = "gpu"))]
+    {
+        if backend == Backend::Gpu {
+            log::warn!("this build has no gpu feature, running on the CPU");
+        }
+        None
+    }
+}
+
+/// Read the `--blur-length-map` of textures of `size`
+fn blur_length_map(path: &Path, size: u32) -> Result<FieldBuffer, Box<dyn Error>> {
+    let length = load_field(path)?;
+    if length.dimensions() != (size, size) {
+        let (width, height) = length.dimensions();
+        return Err(format!("the blur length map {} is {width}x{height}, not {size}x{size}", path.display()).into());
+    }
+    Ok(length)
+}
+
+/// Generate the Voronoi, normalized Perlin and blurred Voronoi maps
 ///
-/// ```rust
-/// let input_image = generate_perlin_noise();
-/// let normalized_image = normalize_image(&input_image);
-/// save_image(&normalized_image, "normalized_perlin.png").unwrap();
-/// ```
-fn normalize_image(img: &ImageBuffer<Rgb<u8>, Vec<u8>>) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
-    let (width, height) = img.dimensions();
-    let mut min_value = 255;
-    let mut max_value = 0;
+/// `on_step` sees the blurred texture after every blur step.
+fn generate_maps(params: &Params, stages: &mut Stages, on_step: impl FnMut(u32, &FieldBuffer)) -> Maps {
+    // Generate the Voronoi texture
+    let mut voronoi_texture = stages.run("voronoi", |progress| match (&params.gpu, params.frame) {
+        (Some(gpu), frame) => {
+            let field = match frame {
+                Some(frame) => gpu.voronoi_frame(&params.voronoi, frame.phase, frame.orbit),
+                None => gpu.voronoi_field(&params.voronoi),
+            };
+            progress(1.0);
+            field
+        }
+        (None, Some(frame)) => {
+            generate_voronoi_frame_with_progress(&params.voronoi, frame.phase, frame.orbit, progress)
+        }
+        (None, None) => generate_voronoi_field_with_progress(&params.voronoi, progress),
+    });
+
+    // Generate the Perlin noise texture
+    let perlin_texture = stages.run("perlin", |progress| perlin_map(params, progress));
 
-    // Find min and max values
-    for pixel in img.pixels() {
-        let value = pixel[0];
-        min_value = min_value.min(value);
-        max_value = max_value.max(value);
+    if let Some((mode, opacity)) = params.perlin_blend {
+        voronoi_texture =
+            blend(&voronoi_texture, &perlin_texture, mode, opacity).expect("both maps have the texture size");
     }
 
-    // Normalize the image
-    ImageBuffer::from_fn(width, height, |x, y| {
-        let pixel = img.get_pixel(x, y);
-        let normalized_value = if max_value > min_value {
-            (pixel[0] as f32 - min_value as f32) / (max_value as f32 - min_value as f32) * 255.0
-        } else {
-            pixel[0] as f32
+    // Apply directional blur using the Voronoi texture as both input and data channel,
+    // or along the flow of the Perlin texture
+    let flow = params.flow.then(|| curl_flow(&perlin_texture));
+    let mut blur_step = 0;
+    let blur = |field: &FieldBuffer, step: &BlurParams| {
+        blur_step += 1;
+        stages.run(format!("blur step {blur_step}"), |progress| {
+            let length = match step.mapping {
+                DirectionMapping::SignedFlow => Some(params.blur_length.as_ref().unwrap_or(&perlin_texture)),
+                _ => params.blur_length.as_ref(),
+            };
+            match (&flow, &params.gpu) {
+                (Some((flow_x, flow_y)), _) => flow_blur_field_with_progress(field, flow_x, flow_y, step, progress),
+                (None, Some(gpu)) => {
+                    let blurred = gpu.directional_blur(field, &voronoi_texture, length, step);
+                    progress(1.0);
+                    blurred
+                }
+                (None, None) => directional_blur_field_with_progress(field, &voronoi_texture, length, step, progress),
+            }
+        })
+    };
+    let blurred_texture = iterated_blur(
+        &voronoi_texture,
+        &params.blur,
+        params.blur_steps,
+        params.blur_growth,
+        params.normalization,
+        blur,
+        on_step,
+    );
+    let blurred_texture = match &params.sharpen {
+        Some(sharpen) => stages.run("sharpen", |progress| {
+            let sharpened = sharpen.apply(&blurred_texture);
+            progress(1.0);
+            sharpened
+        }),
+        None => blurred_texture,
+    };
+    let blurred_texture = stages.run("tone", |progress| {
+        let blurred_texture = match &params.levels {
+            Some(levels) => levels.apply(&blurred_texture),
+            None => blurred_texture,
+        };
+        let blurred_texture = match &params.curve {
+            Some(curve) => curve.apply(&blurred_texture),
+            None => blurred_texture,
         };
-        Rgb([normalized_value.round() as u8, 0, 0])
+        let blurred_texture = match params.posterize {
+            Some(levels) if params.dither => posterize_dithered(&blurred_texture, levels),
+            Some(levels) => posterize(&blurred_texture, levels),
+            None => blurred_texture,
+        };
+        progress(1.0);
+        blurred_texture
+    });
+
+    Maps {
+        voronoi: voronoi_texture,
+        perlin: perlin_texture,
+        blurred: blurred_texture,
+    }
+}
+
+/// The normalized Perlin noise texture of the default pipeline, reporting progress
+fn perlin_map(params: &Params, progress: impl FnMut(f32) + Send) -> FieldBuffer {
+    let size = params.voronoi.size;
+    normalize_field(&match params.frame {
+        Some(frame) => generate_perlin_frame_with_progress(size, &params.fbm, frame.phase, frame.motion, progress),
+        None => generate_perlin_field_with_progress(size, &params.fbm, progress),
     })
 }
 
-/// Main function to generate and process textures
+/// Progress bars and durations of the pipeline stages
+#[derive(Default)]
+struct Stages {
+    durations: Vec<(String, Duration)>,
+}
+
+impl Stages {
+    /// Run one stage behind a progress bar on stderr and record its duration
+    ///
+    /// `work` reports its progress from 0 to 1 through the callback it is given.
+    fn run<T>(&mut self, name: impl Into<String>, work: impl FnOnce(&mut (dyn FnMut(f32) + Send)) -> T) -> T {
+        let name = name.into();
+        // The bar would garble the stage records of -vv, and -q silences it
+        let bar = match log::log_enabled!(log::Level::Debug) || !log::log_enabled!(log::Level::Warn) {
+            true => ProgressBar::hidden(),
+            false => ProgressBar::new(PROGRESS_STEPS),
+        };
+        bar.set_style(
+            ProgressStyle::with_template("{msg:>12} [{bar:40}] {percent:>3}%")
+                .expect("the progress template is valid")
+                .progress_chars("=> "),
+        );
+        bar.set_message(name.clone());
+        let start = Instant::now();
+        let result = work(&mut |done| bar.set_position((done * PROGRESS_STEPS as f32) as u64));
+        bar.finish_and_clear();
+        self.durations.push((name, start.elapsed()));
+        result
+    }
+
+    /// Record the duration of a stage run without a progress bar
+    fn record(&mut self, name: &str, start: Instant) {
+        self.durations.push((name.to_string(), start.elapsed()));
+    }
+
+    /// Print the duration of every stage and their total
+    fn print_timings(&self) {
+        let total: Duration = self.durations.iter().map(|(_, duration)| *duration).sum();
+        for (name, duration) in &self.durations {
+            println!("{name:>12} {:>9.3}s", duration.as_secs_f64());
+        }
+        println!("{:>12} {:>9.3}s", "total", total.as_secs_f64());
+    }
+}
+
+/// Resolution of the progress bars
+const PROGRESS_STEPS: u64 = 1000;
+
+/// The coloring of the cells of `--color` or `--color-random`, if given
+fn cell_coloring(output: &OutputArgs) -> Result<Option<CellColoring>, Box<dyn Error>> {
+    if let Some(ranges) = &output.color_random {
+        return Ok(Some(CellColoring::RandomHsv(ranges.clone())));
+    }
+    let Some(path) = &output.color else {
+        return Ok(None);
+    };
+    let cannot_read = |err: &dyn std::fmt::Display| format!("cannot read {}: {err}", path.display());
+    let palette = if path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("png")) {
+        Palette::from_image(&image::open(path).map_err(|err| cannot_read(&err))?.to_rgb8())
+    } else {
+        Palette::parse(&fs::read_to_string(path).map_err(|err| cannot_read(&err))?)
+    };
+    Ok(Some(CellColoring::Palette(palette.map_err(|err| cannot_read(&err))?)))
+}
+
+/// Save a final field with [`save_map`], scrolled by `--offset`, and its tiled preview as `<stem>_tiled` with
+/// `--preview-tiled`
+fn save_texture(field: &FieldBuffer, output: &OutputArgs, stem: &str) -> Result<(), Box<dyn Error>> {
+    let scrolled;
+    let field = match output.offset {
+        Some((dx, dy)) => {
+            scrolled = offset_field(field, dx, dy);
+            &scrolled
+        }
+        None => field,
+    };
+    save_map(field, output, stem)?;
+    match output.preview_tiled {
+        Some((cols, rows)) => save_level(&tile_preview_field(field, cols, rows), output, &format!("{stem}_tiled")),
+        None => Ok(()),
+    }
+}
+
+/// Save a final image as `<stem>.png` in the output directory, scrolled by `--offset`, and its tiled preview as
+/// `<stem>_tiled.png` with `--preview-tiled`
+fn save_image<P>(
+    img: &image::ImageBuffer<P, Vec<P::Subpixel>>,
+    output: &OutputArgs,
+    stem: &str,
+) -> Result<(), Box<dyn Error>>
+where
+    P: image::PixelWithColorType,
+    [P::Subpixel]: image::EncodableLayout,
+{
+    let scrolled;
+    let img = match output.offset {
+        Some((dx, dy)) => {
+            scrolled = offset(img, dx, dy);
+            &scrolled
+        }
+        None => img,
+    };
+    save(img, &output.out_dir.join(format!("{stem}.png")))?;
+    match output.preview_tiled {
+        Some((cols, rows)) => save(&tile_preview(img, cols, rows), &output.out_dir.join(format!("{stem}_tiled.png"))),
+        None => Ok(()),
+    }
+}
+
+/// Save a field as `<stem>.<format>` in the output directory, e.g. `<stem>.png`
 ///
-/// This function orchestrates the texture generation process:
-/// 1. Generates a Voronoi texture
-/// 2. Generates a Perlin noise texture
-/// 3. Applies directional blur to the Voronoi texture
-/// 4. Saves the resulting textures as PNG images
+/// With `--sizes` or `--emit-mips` the field is downsampled and every size is
+/// saved as `<stem>_<size>` instead, except that GPU textures hold the whole
+/// mip chain of `--emit-mips` in one file.
+fn save_map(field: &FieldBuffer, output: &OutputArgs, stem: &str) -> Result<(), Box<dyn Error>> {
+    if output.emit_mips && is_gpu_texture(output.format) {
+        let levels: Vec<_> = mip_chain(field, output.mip_filter).into_iter().map(|level| vec![level]).collect();
+        return write_texture(&levels, output, stem);
+    }
+    let levels = if output.emit_mips {
+        mip_chain(field, output.mip_filter)
+    } else {
+        let resize = |size| {
+            if size == field.width() {
+                field.clone()
+            } else {
+                resample(field, size, size, output.mip_filter)
+            }
+        };
+        output.sizes.iter().map(|&size| resize(size)).collect()
+    };
+    if levels.is_empty() {
+        return save_level(field, output, stem);
+    }
+    for level in &levels {
+        save_level(level, output, &format!("{stem}_{}", level.width()))?;
+    }
+    Ok(())
+}
+
+/// Save one size of a field as `<stem>.<format>`
 ///
+/// PNG output is encoded with `--transfer` and quantized into an 8-bit texture
+/// with the requested channel layout or a 16-bit grayscale texture, or mapped
+/// through `--colormap` into sRGB; the float formats keep the samples.
+fn save_level(field: &FieldBuffer, output: &OutputArgs, stem: &str) -> Result<(), Box<dyn Error>> {
+    match output.format {
+        FileFormat::Png => {
+            let path = output.out_dir.join(format!("{stem}.png"));
+            if let Some(map) = &output.colormap {
+                return save_encoded(&colormap(field, map), &path, TransferFunction::Srgb);
+            }
+            let field = output.transfer.encode_field(field);
+            match (output.bit_depth, output.channels) {
+                (16, _) => save_encoded(&field.to_luma16_image(), &path, output.transfer),
+                (_, OutputChannels::Luma) => save_encoded(&field.to_luma_image(), &path, output.transfer),
+                (_, OutputChannels::RgbRed) => save_encoded(&field.to_rgb_image(), &path, output.transfer),
+            }
+        }
+        FileFormat::Dds | FileFormat::Ktx2 => write_texture(&[vec![field.clone()]], output, stem),
+        format => {
+            let path = output.out_dir.join(format!("{stem}.{format}"));
+            write_field_with(field, &path, output.bit_depth, output.transfer)
+        }
+    }
+}
 
-fn main() {
-    // Generate the Voronoi texture
-    let voronoi_texture = generate_tileable_voronoi();
-    voronoi_texture.save("voronoi_texture_red.png").unwrap();
-
-    // Generate and save the Perlin noise texture
-    let mut perlin_texture = generate_perlin_noise();
-    perlin_texture = normalize_image(&perlin_texture);
-    perlin_texture.save("perlin_noise_texture.png").unwrap();
-    
-    // Apply directional blur using the Voronoi texture as both input and data channel
-    let mut blurred_texture = voronoi_texture.clone();
-    
-    for i in 0..4 {
-        blurred_texture = directional_blur(&blurred_texture, &voronoi_texture, BLUR_RADIUS * 2i32.pow(i));
-        blurred_texture = normalize_image(&blurred_texture);
-        
-        // Save intermediate results (optional)
-        //blurred_texture.save(format!("blurred_voronoi_texture_red_step_{}.png", i+1)).unwrap();
-    }
-
-    // Save the final result
-    blurred_texture.save("blurred_voronoi_texture_red.png").unwrap();
-}
\ No newline at end of file
+/// Whether a format is a GPU texture container holding mip levels
+fn is_gpu_texture(format: FileFormat) -> bool {
+    matches!(format, FileFormat::Dds | FileFormat::Ktx2)
+}
+
+/// Save a normal map as a two-channel `<stem>.<format>` GPU texture of its X and Y, scrolled by `--offset`, with
+/// the mip chain of both channels with `--emit-mips` and its tiled preview as `<stem>_tiled` with `--preview-tiled`
+fn save_normal_texture(normals: &image::RgbImage, output: &OutputArgs, stem: &str) -> Result<(), Box<dyn Error>> {
+    let channel = |c: usize| {
+        let field = FieldBuffer::from_par_fn(normals.width(), normals.height(), |x, y| {
+            normals.get_pixel(x, y)[c] as f32 / 255.0
+        });
+        match output.offset {
+            Some((dx, dy)) => offset_field(&field, dx, dy),
+            None => field,
+        }
+    };
+    let (x, y) = (channel(0), channel(1));
+    let levels: Vec<_> = match output.emit_mips {
+        true => mip_chain(&x, output.mip_filter)
+            .into_iter()
+            .zip(mip_chain(&y, output.mip_filter))
+            .map(|(x, y)| vec![x, y])
+            .collect(),
+        false => vec![vec![x.clone(), y.clone()]],
+    };
+    write_texture(&levels, output, stem)?;
+    match output.preview_tiled {
+        Some((cols, rows)) => {
+            let preview = vec![tile_preview_field(&x, cols, rows), tile_preview_field(&y, cols, rows)];
+            write_texture(&[preview], output, &format!("{stem}_tiled"))
+        }
+        None => Ok(()),
+    }
+}
+
+/// Save the levels of a GPU texture as `<stem>.dds` or `<stem>.ktx2`, one or two channels per level, block
+/// compressed as requested by `--compress`
+fn write_texture(levels: &[Vec<FieldBuffer>], output: &OutputArgs, stem: &str) -> Result<(), Box<dyn Error>> {
+    let format = match levels.first().map_or(1, Vec::len) {
+        1 if output.compress.contains(&Compression::Bc4) => TextureFormat::Bc4,
+        1 => TextureFormat::R8,
+        _ if output.compress.contains(&Compression::Bc5) => TextureFormat::Bc5,
+        _ => TextureFormat::Rg8,
+    };
+    let texture = Texture::encode(format, levels)?;
+    let path = output.out_dir.join(format!("{stem}.{}", output.format));
+    let saved = match output.format {
+        FileFormat::Dds => save_dds(&texture, &path),
+        _ => save_ktx2(&texture, &path),
+    };
+    saved.map_err(|err| format!("cannot write {}: {err}", path.display()))?;
+    println!("wrote {}", path.display());
+    Ok(())
+}
+
+/// Save a field to `path` in the format of its extension, quantized to grayscale of `bit_depth` unless a float
+/// format; raw files record the command line in their sidecar
+fn write_field(field: &FieldBuffer, path: &Path, bit_depth: u8) -> Result<(), Box<dyn Error>> {
+    write_field_with(field, path, bit_depth, TransferFunction::Linear)
+}
+
+/// Save a field like [`write_field`], encoding the quantized samples with `transfer`
+fn write_field_with(
+    field: &FieldBuffer,
+    path: &Path,
+    bit_depth: u8,
+    transfer: TransferFunction,
+) -> Result<(), Box<dyn Error>> {
+    match FileFormat::from_path(path) {
+        Some(FileFormat::Raw) => save_raw(field, path, &std::env::args().skip(1).collect::<Vec<_>>())
+            .map_err(|err| format!("cannot write {}: {err}", path.display()))?,
+        format => save_field_with(field, path, format, bit_depth, transfer)?,
+    }
+    embed_manifest(path)?;
+    println!("wrote {}", path.display());
+    Ok(())
+}
+
+/// Read the first channel of an image file, the `Y` channel of an EXR or a raw float file into a field
+fn load_field(path: &Path) -> Result<FieldBuffer, Box<dyn Error>> {
+    let cannot_read = |err: &dyn Error| format!("cannot read {}: {err}", path.display());
+    match FileFormat::from_path(path) {
+        Some(FileFormat::Exr) => return Ok(load_exr(path).map_err(|err| cannot_read(&err))?),
+        Some(FileFormat::Raw) => return Ok(load_raw(path).map_err(|err| cannot_read(&err))?),
+        _ => {}
+    }
+    let img = image::open(path).map_err(|err| cannot_read(&err))?.to_rgba32f();
+    let (width, height) = img.dimensions();
+    Ok(FieldBuffer::from_vec(width, height, img.pixels().map(|p| p[0]).collect()))
+}
+
+/// Save a texture and report the path it was written to
+fn save<P>(img: &image::ImageBuffer<P, Vec<P::Subpixel>>, path: &Path) -> Result<(), Box<dyn Error>>
+where
+    P: image::PixelWithColorType,
+    [P::Subpixel]: image::EncodableLayout,
+{
+    img.save(path)
+        .map_err(|err| format!("cannot write {}: {err}", path.display()))?;
+    embed_manifest(path)?;
+    println!("wrote {}", path.display());
+    Ok(())
+}
+
+/// Save a PNG texture whose samples are encoded with `transfer`, recording it in the file, and report the path
+fn save_encoded<P>(
+    img: &image::ImageBuffer<P, Vec<P::Subpixel>>,
+    path: &Path,
+    transfer: TransferFunction,
+) -> Result<(), Box<dyn Error>>
+where
+    P: image::PixelWithColorType,
+    [P::Subpixel]: image::EncodableLayout,
+{
+    save_png(img, path, transfer).map_err(|err| format!("cannot write {}: {err}", path.display()))?;
+    embed_manifest(path)?;
+    println!("wrote {}", path.display());
+    Ok(())
+}
+
+/// Make `manifest` the one embedded into the files written from now on
+fn set_manifest(manifest: Manifest) {
+    *MANIFEST.lock().expect("the manifest is not poisoned") = Some(manifest);
+}
+
+/// Embed the manifest of the run into a PNG file, if there is one
+fn embed_manifest(path: &Path) -> Result<(), Box<dyn Error>> {
+    let manifest = MANIFEST.lock().expect("the manifest is not poisoned").clone();
+    match (manifest, FileFormat::from_path(path)) {
+        (Some(manifest), Some(FileFormat::Png)) => {
+            Ok(manifest.embed_png(path).map_err(|err| format!("cannot write {}: {err}", path.display()))?)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Print the manifest a PNG texture or JSON manifest records
+fn info(args: &InfoArgs) -> Result<(), Box<dyn Error>> {
+    let manifest = Manifest::load(&args.file).map_err(|err| format!("cannot read {}: {err}", args.file.display()))?;
+    println!("made by cells {}", manifest.version);
+    println!("command: {}", manifest.command.join(" "));
+    for (name, value) in &manifest.parameters {
+        println!("{name} = {value}");
+    }
+    if let Some(pipeline) = &manifest.pipeline {
+        println!("pipeline:\n{}", pipeline.trim_end());
+    }
+    if let Some(generators) = &manifest.generators {
+        println!("generators: {}", serde_json::to_string(generators)?);
+    }
+    Ok(())
+}
+
+/// Answer preview requests until the listener fails
+#[cfg(feature = "serve")]
+fn serve(args: &ServeArgs) -> Result<(), Box<dyn Error>> {
+    let address = format!("{}:{}", args.host, args.port);
+    let listener =
+        std::net::TcpListener::bind(&address).map_err(|err| format!("cannot listen on {address}: {err}"))?;
+    println!("serving previews on http://{address}/, e.g. http://{address}/voronoi?size=512&points=300&seed=42");
+    let server = std::sync::Arc::new(PreviewServer::new(args.max_size, args.cache));
+    Ok(server.serve(listener)?)
+}
+
+/// Run the command line of a manifest again, with its recorded pipeline rather than the file
+fn rerun(args: &RerunArgs) -> Result<(), Box<dyn Error>> {
+    let recorded =
+        Manifest::load(&args.manifest).map_err(|err| format!("cannot read {}: {err}", args.manifest.display()))?;
+    if recorded.version != env!("CARGO_PKG_VERSION") {
+        log::warn!(
+            "made by cells {}, this is {}; the textures may differ",
+            recorded.version,
+            env!("CARGO_PKG_VERSION")
+        );
+    }
+    let mut command = recorded.command.clone();
+    if let Some(out_dir) = &args.out_dir {
+        command = without_option(&command, "--out-dir");
+        command.extend(["--out-dir".to_string(), out_dir.display().to_string()]);
+    }
+    let invalid = |err: clap::Error| {
+        let message = err.to_string();
+        let first_line = message.lines().next().unwrap_or_default();
+        format!("invalid recorded command: {}", first_line.trim_start_matches("error: "))
+    };
+    let matches = Cli::command()
+        .try_get_matches_from(std::iter::once("cells".to_string()).chain(command.clone()))
+        .map_err(invalid)?;
+    let cli = Cli::from_arg_matches(&matches).map_err(invalid)?;
+    match (&cli.command, &recorded.pipeline) {
+        (None, _) => run(&cli.generate, &cli.output, record(command, &matches)),
+        (Some(Command::Run(run_args)), Some(pipeline)) => {
+            execute_pipeline(pipeline, run_args, record(command, &matches))
+        }
+        _ => Err("the manifest records no textures or pipeline to make again".into()),
+    }
+}
+
+/// Parse non-zero `COLSxROWS` dimensions such as `16x16`
+fn parse_dimensions(s: &str) -> Result<(u32, u32), String> {
+    let invalid = || format!("expected COLSxROWS such as 16x16, got `{s}`");
+    let (cols, rows) = s.split_once('x').ok_or_else(invalid)?;
+    let cols: u32 = cols.parse().map_err(|_| invalid())?;
+    let rows: u32 = rows.parse().map_err(|_| invalid())?;
+    if cols == 0 || rows == 0 {
+        return Err(format!("dimensions must be non-zero, got `{s}`"));
+    }
+    Ok((cols, rows))
+}
+
+/// Parse a number between 0 and 1
+fn parse_unit_interval(s: &str) -> Result<f32, String> {
+    let value: f32 = s.parse().map_err(|err| format!("`{s}` is not a number: {err}"))?;
+    if !(0.0..=1.0).contains(&value) {
+        return Err(format!("must be between 0 and 1, got {value}"));
+    }
+    Ok(value)
+}
+
+/// Parse a finite number of at least 0
+fn parse_non_negative(s: &str) -> Result<f32, String> {
+    let value: f32 = s.parse().map_err(|err| format!("`{s}` is not a number: {err}"))?;
+    if !(value.is_finite() && value >= 0.0) {
+        return Err(format!("must be at least 0, got {value}"));
+    }
+    Ok(value)
+}
+
+/// Parse a finite number above 0
+fn parse_positive(s: &str) -> Result<f64, String> {
+    let value: f64 = s.parse().map_err(|err| format!("`{s}` is not a number: {err}"))?;
+    if !(value.is_finite() && value > 0.0) {
+        return Err(format!("must be above 0, got {value}"));
+    }
+    Ok(value)
+}
+
+/// Parse a finite `f32` above 0
+fn parse_positive_f32(s: &str) -> Result<f32, String> {
+    parse_positive(s).map(|value| value as f32)
+}
+
+/// Parse a posterize level count of at least 2
+fn parse_levels(s: &str) -> Result<u32, String> {
+    let levels: u32 = s.parse().map_err(|err| format!("`{s}` is not a level count: {err}"))?;
+    if levels < 2 {
+        return Err(format!("posterizing needs at least 2 levels, got {levels}"));
+    }
+    Ok(levels)
+}
+
+/// Parse an inclusive `MIN..MAX` range such as `0.0..0.05`
+fn parse_range(s: &str) -> Result<(f32, f32), String> {
+    let invalid = || format!("expected MIN..MAX such as 0.0..0.05, got `{s}`");
+    let (min, max) = s.split_once("..").ok_or_else(invalid)?;
+    let min: f32 = min.parse().map_err(|_| invalid())?;
+    let max: f32 = max.parse().map_err(|_| invalid())?;
+    if !(min.is_finite() && max.is_finite() && min <= max) {
+        return Err(format!("range minimum must not exceed its maximum, got `{s}`"));
+    }
+    Ok((min, max))
+}
+
+/// Parse a `SX,SY` pair of positive scale factors such as `1,3`
+fn parse_scale(s: &str) -> Result<(f32, f32), String> {
+    let invalid = || format!("expected SX,SY such as 1,3, got `{s}`");
+    let (sx, sy) = s.split_once(',').ok_or_else(invalid)?;
+    let (sx, sy): (f32, f32) = (sx.parse().map_err(|_| invalid())?, sy.parse().map_err(|_| invalid())?);
+    if !(sx.is_finite() && sx > 0.0 && sy.is_finite() && sy > 0.0) {
+        return Err(format!("scale factors must be positive, got `{s}`"));
+    }
+    Ok((sx, sy))
+}
+
+/// Parse a `DX,DY` pixel offset such as `128,-64`
+fn parse_offset(s: &str) -> Result<(i64, i64), String> {
+    let invalid = || format!("expected DX,DY such as 128,256, got `{s}`");
+    let (dx, dy) = s.split_once(',').ok_or_else(invalid)?;
+    Ok((dx.parse().map_err(|_| invalid())?, dy.parse().map_err(|_| invalid())?))
+}
+
+/// Parse a number of bytes with an optional K, M or G suffix of powers of 1024, such as 512M
+fn parse_memory(s: &str) -> Result<u64, String> {
+    let invalid = || format!("expected a number of bytes such as 512M or 2G, got `{s}`");
+    let (digits, shift) = match s.as_bytes().last().map(u8::to_ascii_uppercase) {
+        Some(b'K') => (&s[..s.len() - 1], 10),
+        Some(b'M') => (&s[..s.len() - 1], 20),
+        Some(b'G') => (&s[..s.len() - 1], 30),
+        _ => (s, 0),
+    };
+    let value: u64 = digits.parse().map_err(|_| invalid())?;
+    value.checked_mul(1 << shift).ok_or_else(invalid)
+}
+
+/// Parse a supported bit depth, 8 or 16
+fn parse_bit_depth(s: &str) -> Result<u8, String> {
+    match s {
+        "8" => Ok(8),
+        "16" => Ok(16),
+        _ => Err(format!("bit depth must be 8 or 16, got `{s}`")),
+    }
+}
@@ -0,0 +1,197 @@
+//! Manifests recording how a texture was made, so it can be made again.
+//!
+//! A [`Manifest`] holds the command line that generated a texture, every
+//! parameter it resolved to, including the seed when none was given, and the
+//! version of the crate. Textures of a [`Pipeline`](crate::pipeline::Pipeline)
+//! also record the TOML description of the pipeline itself, so the manifest
+//! stays complete when the pipeline file changes.
+//!
+//! The default command also records the resolved [`Generators`] it ran,
+//! serialized from the same parameter types that back the pipeline nodes.
+//!
+//! Manifests are written as JSON sidecars and embedded into PNG files as text
+//! chunks: the command line as JSON in `cells:command`, every parameter in a
+//! `cells:parameter:<name>` chunk, the generators as JSON in
+//! `cells:generators` and the crate version in `Software`.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::CellsError;
+use crate::filter::BlurParams;
+use crate::io::{add_png_text, read_png_text};
+use crate::noise::FbmParams;
+use crate::voronoi::VoronoiParams;
+
+/// The prefix of the PNG text keywords of a manifest
+const KEYWORD_PREFIX: &str = "cells:";
+
+/// How a texture was made: the command line, its resolved parameters and the
+/// crate version
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// The version of the crate that made the texture
+    pub version: String,
+    /// The command line arguments after the program name
+    pub command: Vec<String>,
+    /// Every parameter by name, with defaults and resolved random values
+    #[serde(default)]
+    pub parameters: BTreeMap<String, String>,
+    /// The TOML description of the pipeline the texture came from, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pipeline: Option<String>,
+    /// The resolved parameters of the default textures, if the texture came
+    /// from the default command
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generators: Option<Generators>,
+}
+
+/// The parameters the default command makes its textures with: the Voronoi
+/// cells, the noise and the first step of the directional blur
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Generators {
+    /// The Voronoi texture
+    pub voronoi: VoronoiParams,
+    /// The noise texture
+    pub fbm: FbmParams,
+    /// The first blur step of the blurred Voronoi texture
+    pub blur: BlurParams,
+}
+
+impl Manifest {
+    /// A manifest of a command line of this version of the crate, without
+    /// parameters yet
+    pub fn new(command: Vec<String>) -> Manifest {
+        Manifest {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            command,
+            ..Default::default()
+        }
+    }
+
+    /// The manifest as pretty-printed JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("manifests serialize to JSON")
+    }
+
+    /// Parse a manifest from JSON
+    ///
+    /// # Returns
+    ///
+    /// [`CellsError::InvalidParameter`] if `s` is not the JSON of a manifest
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::manifest::Manifest;
+    ///
+    /// let mut manifest = Manifest::new(vec!["--seed".into(), "7".into()]);
+    /// manifest.parameters.insert("seed".into(), "7".into());
+    /// assert_eq!(Manifest::from_json(&manifest.to_json()).unwrap(), manifest);
+    /// assert!(Manifest::from_json(r#"{"command": []}"#).is_err());
+    /// ```
+    pub fn from_json(s: &str) -> Result<Manifest, CellsError> {
+        serde_json::from_str(s)
+            .map_err(|err| CellsError::InvalidParameter { name: "manifest", reason: err.to_string() })
+    }
+
+    /// Write the manifest as a JSON file
+    pub fn save_json(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_json() + "\n")
+    }
+
+    /// Read a manifest from a JSON file or from the text chunks of a PNG file,
+    /// by the extension of the path
+    ///
+    /// # Returns
+    ///
+    /// An error if the file cannot be read or holds no manifest
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Manifest> {
+        let path = path.as_ref();
+        let is_png = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("png"));
+        if is_png {
+            return Manifest::from_png(path)?.ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("{} holds no cells manifest", path.display()))
+            });
+        }
+        Manifest::from_json(&fs::read_to_string(path)?).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Embed the manifest into the text chunks of a PNG file
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::manifest::{Generators, Manifest};
+    /// use cells::FieldBuffer;
+    ///
+    /// let path = std::env::temp_dir().join("cells_doc_manifest.png");
+    /// FieldBuffer::new(8, 8).to_luma_image().save(&path).unwrap();
+    /// let mut manifest = Manifest::new(vec!["--size".into(), "8".into(), "--out-dir".into(), "my textures".into()]);
+    /// manifest.parameters.insert("size".into(), "8".into());
+    /// manifest.generators = Some(Generators::default());
+    /// manifest.embed_png(&path).unwrap();
+    /// assert_eq!(Manifest::from_png(&path).unwrap(), Some(manifest));
+    /// ```
+    pub fn embed_png(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let keyword = |name: &str| format!("{KEYWORD_PREFIX}{name}");
+        let command = serde_json::to_string(&self.command).expect("strings serialize to JSON");
+        let mut entries = vec![
+            ("Software".to_string(), format!("cells {}", self.version)),
+            (keyword("command"), command),
+        ];
+        entries.extend(self.pipeline.iter().map(|pipeline| (keyword("pipeline"), pipeline.clone())));
+        if let Some(generators) = &self.generators {
+            let generators = serde_json::to_string(generators).expect("generators serialize to JSON");
+            entries.push((keyword("generators"), generators));
+        }
+        for (name, value) in &self.parameters {
+            entries.push((keyword(&format!("parameter:{name}")), value.clone()));
+        }
+        add_png_text(path, &entries)
+    }
+
+    /// Read the manifest embedded into a PNG file by [`Manifest::embed_png`]
+    ///
+    /// # Returns
+    ///
+    /// `None` if the file holds no manifest, or an error if it cannot be read
+    pub fn from_png(path: impl AsRef<Path>) -> io::Result<Option<Manifest>> {
+        let mut manifest = Manifest::default();
+        let mut found = false;
+        for (keyword, text) in read_png_text(path)? {
+            if keyword == "Software" {
+                if let Some(version) = text.strip_prefix("cells ") {
+                    manifest.version = version.to_string();
+                }
+                continue;
+            }
+            let Some(name) = keyword.strip_prefix(KEYWORD_PREFIX) else {
+                continue;
+            };
+            match name.strip_prefix("parameter:") {
+                Some(parameter) => {
+                    manifest.parameters.insert(parameter.to_string(), text);
+                }
+                None if name == "command" => {
+                    manifest.command = serde_json::from_str(&text)
+                        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                    found = true;
+                }
+                None if name == "pipeline" => manifest.pipeline = Some(text),
+                None if name == "generators" => {
+                    manifest.generators = Some(
+                        serde_json::from_str(&text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?,
+                    );
+                }
+                None => {}
+            }
+        }
+        Ok(found.then_some(manifest))
+    }
+}
@@ -0,0 +1,277 @@
+//! Material maps derived from heightmaps.
+
+use std::fmt;
+use std::str::FromStr;
+
+use image::RgbImage;
+use rayon::prelude::*;
+
+use crate::field::FieldBuffer;
+use crate::flow::gradient;
+
+/// Direction of the green channel of a tangent-space normal map
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NormalConvention {
+    /// Green points up the texture (Y+), as in OpenGL, Blender and Unity
+    #[default]
+    OpenGl,
+    /// Green points down the texture (Y-), as in DirectX and Unreal Engine
+    DirectX,
+}
+
+impl fmt::Display for NormalConvention {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            NormalConvention::OpenGl => "opengl",
+            NormalConvention::DirectX => "directx",
+        })
+    }
+}
+
+impl FromStr for NormalConvention {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "opengl" => Ok(NormalConvention::OpenGl),
+            "directx" => Ok(NormalConvention::DirectX),
+            _ => Err(format!("unknown normal map convention `{s}`, expected opengl or directx")),
+        }
+    }
+}
+
+/// Compute an OpenGL tangent-space normal map from a heightmap
+///
+/// This is [`height_to_normal_with`] with [`NormalConvention::OpenGl`].
+pub fn height_to_normal(field: &FieldBuffer, strength: f32) -> RgbImage {
+    height_to_normal_with(field, strength, NormalConvention::OpenGl)
+}
+
+/// Compute a tangent-space normal map from a heightmap
+///
+/// The slopes of the heightmap are central differences wrapping around the
+/// edges, so the normal map tiles like the heightmap. Every normal is
+/// `(-dx * strength, -dy * strength, 1)` normalized, with `dy` measured up
+/// the texture, and mapped from -1..1 to 0-255; flat areas are
+/// `(128, 128, 255)`.
+///
+/// # Arguments
+///
+/// * `field` - The heightmap, 0 to 1 from lowest to highest
+/// * `strength` - The height of the full 0-1 range in pixels; larger values
+///   give steeper normals
+/// * `convention` - The direction of the green channel
+///
+/// # Returns
+///
+/// An `RgbImage` containing the normal map
+///
+/// # Example
+///
+/// ```rust
+/// use cells::material::{height_to_normal_with, NormalConvention};
+/// use cells::FieldBuffer;
+///
+/// let flat = FieldBuffer::from_par_fn(16, 16, |_, _| 0.7);
+/// let normals = height_to_normal_with(&flat, 8.0, NormalConvention::OpenGl);
+/// assert!(normals.pixels().all(|p| p.0 == [128, 128, 255]));
+///
+/// // Heights growing down the texture tilt the normals up it
+/// let slope = FieldBuffer::from_par_fn(16, 16, |_, y| (y as f32 / 15.0 * std::f32::consts::PI).sin());
+/// let opengl = height_to_normal_with(&slope, 8.0, NormalConvention::OpenGl);
+/// let directx = height_to_normal_with(&slope, 8.0, NormalConvention::DirectX);
+/// let (gl, dx) = (opengl.get_pixel(4, 2).0, directx.get_pixel(4, 2).0);
+/// assert!(gl[1] > 128 && dx[1] < 128);
+/// assert_eq!((gl[0], gl[2]), (dx[0], dx[2]));
+/// ```
+pub fn height_to_normal_with(field: &FieldBuffer, strength: f32, convention: NormalConvention) -> RgbImage {
+    let (dx, dy) = gradient(field);
+    // The gradient is measured down the texture, so its slope up the texture
+    // is -dy; DirectX normals point down the texture again
+    let y_sign = match convention {
+        NormalConvention::OpenGl => 1.0,
+        NormalConvention::DirectX => -1.0,
+    };
+    let encode = |v: f32| ((v + 1.0) / 2.0 * 255.0).round().clamp(0.0, 255.0) as u8;
+    let mut img = RgbImage::new(field.width(), field.height());
+    img.par_chunks_exact_mut(3)
+        .zip(dx.as_slice().par_iter().zip(dy.as_slice()))
+        .for_each(|(pixel, (&dx, &dy))| {
+            let normal = [-dx * strength, y_sign * dy * strength, 1.0];
+            let length = normal.iter().map(|v| v * v).sum::<f32>().sqrt();
+            pixel.copy_from_slice(&normal.map(|v| encode(v / length)));
+        });
+    img
+}
+
+/// Rings of samples between the pixel and the AO radius
+const AO_RINGS: u32 = 4;
+
+/// Approximate the ambient occlusion of a heightmap
+///
+/// Every pixel looks at its surroundings along `samples` evenly spaced
+/// directions, at several distances up to `radius`, reading the heightmap
+/// bilinearly and wrapping around the edges. The more the surroundings rise
+/// above the pixel, the darker it gets: the output is
+/// `1 - strength * mean(max(0, neighbor - height))`, clamped to 0-1.
+///
+/// # Arguments
+///
+/// * `field` - The heightmap, 0 to 1 from lowest to highest
+/// * `radius` - The distance in pixels up to which neighbors occlude
+/// * `samples` - The number of directions sampled around every pixel
+/// * `strength` - How strongly rising surroundings darken a pixel
+///
+/// # Returns
+///
+/// A `FieldBuffer` with 1 for unoccluded and 0 for fully occluded pixels,
+/// tileable like the heightmap
+///
+/// # Panics
+///
+/// Panics if `radius` is not positive and finite, or `samples` is 0.
+///
+/// # Performance
+///
+/// This function has O(width * height * samples) complexity. Rows are
+/// processed in parallel with rayon.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::material::height_to_ao;
+/// use cells::FieldBuffer;
+///
+/// let flat = FieldBuffer::from_par_fn(16, 16, |_, _| 0.3);
+/// assert!(height_to_ao(&flat, 4.0, 8, 2.0).as_slice().iter().all(|&v| v == 1.0));
+///
+/// // The bottom of a pit is darker than its rim
+/// let pit = FieldBuffer::from_par_fn(32, 32, |x, y| {
+///     let (dx, dy) = (x as f32 - 16.0, y as f32 - 16.0);
+///     (dx * dx + dy * dy).sqrt().min(8.0) / 8.0
+/// });
+/// let ao = height_to_ao(&pit, 6.0, 8, 2.0);
+/// assert!(ao.get(16, 16) < ao.get(16, 24));
+/// assert_eq!(ao.get(0, 0), 1.0);
+/// ```
+pub fn height_to_ao(field: &FieldBuffer, radius: f32, samples: u32, strength: f32) -> FieldBuffer {
+    assert!(radius.is_finite() && radius > 0.0, "AO radius must be positive, got {radius}");
+    assert!(samples > 0, "AO needs at least one sample direction");
+    let offsets: Vec<(f32, f32)> = (0..samples)
+        .flat_map(|direction| {
+            let angle = direction as f32 / samples as f32 * std::f32::consts::TAU;
+            (1..=AO_RINGS).map(move |ring| {
+                let distance = radius * ring as f32 / AO_RINGS as f32;
+                (angle.cos() * distance, angle.sin() * distance)
+            })
+        })
+        .collect();
+    FieldBuffer::from_par_fn(field.width(), field.height(), |x, y| {
+        let height = field.get(x, y);
+        let rise: f32 = offsets
+            .iter()
+            .map(|(dx, dy)| (field.sample_bilinear_wrapped(x as f32 + dx, y as f32 + dy) - height).max(0.0))
+            .sum();
+        (1.0 - strength * rise / offsets.len() as f32).clamp(0.0, 1.0)
+    })
+}
+
+/// The Laplacian of a heightmap, wrapping around the edges
+///
+/// This is the five-point stencil: the sum of the four neighbors minus four
+/// times the sample. It is positive in cavities, where the surroundings are
+/// higher, and negative on ridges and edges.
+///
+/// # Example
+///
+/// The Laplacian of a tileable field sums to zero, since every sample enters
+/// the stencils of its neighbors as often as its own:
+///
+/// ```rust
+/// use cells::material::laplacian;
+/// use cells::voronoi::{generate_voronoi_field, VoronoiParams};
+///
+/// let voronoi = generate_voronoi_field(&VoronoiParams { size: 64, num_points: 20, ..Default::default() });
+/// let total: f64 = laplacian(&voronoi).as_slice().iter().map(|&v| v as f64).sum();
+/// assert!(total.abs() < 1e-4);
+/// ```
+pub fn laplacian(field: &FieldBuffer) -> FieldBuffer {
+    FieldBuffer::from_par_fn(field.width(), field.height(), |x, y| {
+        let (x, y) = (x as i64, y as i64);
+        field.get_wrapped(x - 1, y) + field.get_wrapped(x + 1, y) + field.get_wrapped(x, y - 1)
+            + field.get_wrapped(x, y + 1)
+            - 4.0 * field.get_wrapped(x, y)
+    })
+}
+
+/// Split the curvature of a heightmap into ridge and cavity masks
+///
+/// Ridges are where the [`laplacian`] is negative and cavities where it is
+/// positive. Each mask is scaled separately so its strongest sample is 1, so
+/// shallow cavities still show next to sharp ridges. Pre-blur the heightmap,
+/// e.g. with [`gaussian_blur`](crate::filter::gaussian_blur), to detect
+/// larger features.
+///
+/// # Arguments
+///
+/// * `field` - The heightmap, 0 to 1 from lowest to highest
+///
+/// # Returns
+///
+/// The ridge mask and the cavity mask, both 0 where the surface is flat
+///
+/// # Example
+///
+/// ```rust
+/// use cells::material::curvature;
+/// use cells::FieldBuffer;
+///
+/// let bump = FieldBuffer::from_par_fn(32, 32, |x, y| {
+///     let (dx, dy) = (x as f32 - 16.0, y as f32 - 16.0);
+///     (-(dx * dx + dy * dy) / 20.0).exp()
+/// });
+/// let (ridges, cavities) = curvature(&bump);
+/// assert_eq!(ridges.get(16, 16), 1.0);
+/// assert_eq!(cavities.get(16, 16), 0.0);
+/// // The foot of the bump curves upwards
+/// assert!(cavities.get(16, 24) > 0.0);
+/// ```
+pub fn curvature(field: &FieldBuffer) -> (FieldBuffer, FieldBuffer) {
+    let laplacian = laplacian(field);
+    let mask = |sign: f32| {
+        let mask = laplacian.map(|v| (sign * v).max(0.0));
+        let peak = mask.min_max().map_or(0.0, |(_, max)| max);
+        if peak > 0.0 {
+            mask.map(|v| v / peak)
+        } else {
+            mask
+        }
+    };
+    (mask(-1.0), mask(1.0))
+}
+
+/// The curvature of a heightmap as one field centered at 0.5
+///
+/// Ridges are brighter and cavities darker than 0.5, scaled together so the
+/// strongest curvature reaches 0 or 1. Flat heightmaps give a uniform 0.5.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::material::signed_curvature;
+/// use cells::FieldBuffer;
+///
+/// let wave = FieldBuffer::from_par_fn(16, 16, |x, _| (x as f32 / 16.0 * std::f32::consts::TAU).sin());
+/// let signed = signed_curvature(&wave);
+/// assert!(signed.get(4, 0) > 0.99 && signed.get(12, 0) < 0.01);
+/// assert!((signed.get(0, 0) - 0.5).abs() < 1e-6);
+/// ```
+pub fn signed_curvature(field: &FieldBuffer) -> FieldBuffer {
+    let laplacian = laplacian(field);
+    let peak = laplacian.min_max().map_or(0.0, |(min, max)| max.max(-min));
+    if peak > 0.0 {
+        laplacian.map(|v| 0.5 - 0.5 * v / peak)
+    } else {
+        laplacian.map(|_| 0.5)
+    }
+}
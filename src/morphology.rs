@@ -0,0 +1,246 @@
+//! Grayscale morphology: dilation, erosion, opening and closing.
+//!
+//! Dilation takes the largest sample under a structuring element centered on
+//! every pixel, erosion the smallest, both with wraparound so tileable fields
+//! stay tileable. Square elements are separable, and every line is swept with
+//! the van Herk/Gil-Werman algorithm in a constant number of comparisons per
+//! sample whatever the radius. Discs are the union of one horizontal run per
+//! row, so they cost one sweep per row of the disc.
+
+use std::fmt;
+use std::str::FromStr;
+
+use rayon::prelude::*;
+
+use crate::field::FieldBuffer;
+
+/// The neighborhood a morphological filter looks at around every pixel
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StructuringElement {
+    /// The pixels up to the radius away along both axes
+    Square(u32),
+    /// The pixels whose centers are at most the radius away
+    Disc(u32),
+}
+
+impl fmt::Display for StructuringElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StructuringElement::Square(radius) => write!(f, "square:{radius}"),
+            StructuringElement::Disc(radius) => write!(f, "disc:{radius}"),
+        }
+    }
+}
+
+impl FromStr for StructuringElement {
+    type Err = String;
+
+    /// Parse `square:<radius>` or `disc:<radius>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("expected square:<radius> or disc:<radius>, got `{s}`");
+        let (shape, radius) = s.split_once(':').ok_or_else(invalid)?;
+        let radius = radius.trim().parse().map_err(|_| invalid())?;
+        match shape {
+            "square" => Ok(StructuringElement::Square(radius)),
+            "disc" => Ok(StructuringElement::Disc(radius)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// A morphological filter, see [`dilate`], [`erode`], [`open`] and [`close`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Morphology {
+    /// The largest sample under the element
+    #[default]
+    Dilate,
+    /// The smallest sample under the element
+    Erode,
+    /// Erosion followed by dilation, removing bright details smaller than the
+    /// element
+    Open,
+    /// Dilation followed by erosion, filling dark details smaller than the
+    /// element
+    Close,
+}
+
+impl Morphology {
+    /// Filter `field` with `element`
+    pub fn apply(self, field: &FieldBuffer, element: StructuringElement) -> FieldBuffer {
+        match self {
+            Morphology::Dilate => dilate(field, element),
+            Morphology::Erode => erode(field, element),
+            Morphology::Open => open(field, element),
+            Morphology::Close => close(field, element),
+        }
+    }
+}
+
+impl fmt::Display for Morphology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Morphology::Dilate => write!(f, "dilate"),
+            Morphology::Erode => write!(f, "erode"),
+            Morphology::Open => write!(f, "open"),
+            Morphology::Close => write!(f, "close"),
+        }
+    }
+}
+
+impl FromStr for Morphology {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dilate" => Ok(Morphology::Dilate),
+            "erode" => Ok(Morphology::Erode),
+            "open" => Ok(Morphology::Open),
+            "close" => Ok(Morphology::Close),
+            _ => Err(format!("unknown morphology `{s}`, expected dilate, erode, open or close")),
+        }
+    }
+}
+
+/// Grow the bright parts of a field: every pixel becomes the largest sample
+/// under `element` centered on it, with wraparound
+///
+/// # Example
+///
+/// ```rust
+/// use cells::morphology::{dilate, StructuringElement};
+/// use cells::FieldBuffer;
+///
+/// // A dot on the corner grows into a disc reaching over the edges
+/// let dot = FieldBuffer::from_par_fn(8, 8, |x, y| if (x, y) == (0, 0) { 1.0 } else { 0.0 });
+/// let disc = dilate(&dot, StructuringElement::Disc(2));
+/// assert_eq!([disc.get(2, 0), disc.get(7, 7), disc.get(6, 0), disc.get(6, 6)], [1.0, 1.0, 1.0, 0.0]);
+/// assert_eq!(dilate(&dot, StructuringElement::Square(2)).get(6, 6), 1.0);
+/// ```
+pub fn dilate(field: &FieldBuffer, element: StructuringElement) -> FieldBuffer {
+    filter(field, element, f32::max)
+}
+
+/// Shrink the bright parts of a field: every pixel becomes the smallest
+/// sample under `element` centered on it, with wraparound
+///
+/// # Example
+///
+/// ```rust
+/// use cells::morphology::{erode, StructuringElement};
+/// use cells::FieldBuffer;
+///
+/// // A 5 pixel wide band eroded by a radius of 2 keeps its middle line
+/// let band = FieldBuffer::from_par_fn(8, 8, |x, _| if (1..6).contains(&x) { 1.0 } else { 0.0 });
+/// let line = erode(&band, StructuringElement::Disc(2));
+/// assert_eq!(line, FieldBuffer::from_par_fn(8, 8, |x, _| if x == 3 { 1.0 } else { 0.0 }));
+/// ```
+pub fn erode(field: &FieldBuffer, element: StructuringElement) -> FieldBuffer {
+    filter(field, element, f32::min)
+}
+
+/// Remove bright details smaller than `element`, eroding and then dilating
+///
+/// Opening never brightens a pixel, and opening twice changes nothing.
+pub fn open(field: &FieldBuffer, element: StructuringElement) -> FieldBuffer {
+    dilate(&erode(field, element), element)
+}
+
+/// Fill dark details smaller than `element`, dilating and then eroding
+///
+/// Closing never darkens a pixel, and closing twice changes nothing.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::morphology::{close, StructuringElement};
+/// use cells::FieldBuffer;
+///
+/// // A one pixel gap in a bright field fills
+/// let gap = FieldBuffer::from_par_fn(8, 8, |x, y| if (x, y) == (3, 4) { 0.0 } else { 1.0 });
+/// assert_eq!(close(&gap, StructuringElement::Square(1)).as_slice(), [1.0; 64]);
+/// ```
+pub fn close(field: &FieldBuffer, element: StructuringElement) -> FieldBuffer {
+    erode(&dilate(field, element), element)
+}
+
+/// Every pixel set to the samples under `element` around it combined with
+/// `op`
+fn filter(field: &FieldBuffer, element: StructuringElement, op: fn(f32, f32) -> f32) -> FieldBuffer {
+    let (width, height) = field.dimensions();
+    let (w, h) = (width as usize, height as usize);
+    if w == 0 || h == 0 {
+        return field.clone();
+    }
+    let rows = |y: usize| &field.as_slice()[y * w..(y + 1) * w];
+    match element {
+        StructuringElement::Square(radius) => {
+            // Along the rows, then down the columns of the result
+            let mut horizontal = vec![0.0; w * h];
+            horizontal.par_chunks_mut(w).enumerate().for_each(|(y, out)| {
+                sweep(rows(y), radius as usize, op, out);
+            });
+            let columns: Vec<Vec<f32>> = (0..w)
+                .into_par_iter()
+                .map(|x| {
+                    let column: Vec<f32> = (0..h).map(|y| horizontal[y * w + x]).collect();
+                    let mut out = vec![0.0; h];
+                    sweep(&column, radius as usize, op, &mut out);
+                    out
+                })
+                .collect();
+            FieldBuffer::from_par_fn(width, height, |x, y| columns[x as usize][y as usize])
+        }
+        StructuringElement::Disc(radius) => {
+            let r = radius as i64;
+            let mut values = vec![0.0; w * h];
+            values.par_chunks_mut(w).enumerate().for_each(|(y, out)| {
+                let mut run = vec![0.0; w];
+                for dy in -r..=r {
+                    // The half width of the disc's run in this row
+                    let half = ((r * r - dy * dy) as f64).sqrt().floor() as usize;
+                    let source = (y as i64 + dy).rem_euclid(h as i64) as usize;
+                    sweep(rows(source), half, op, &mut run);
+                    if dy == -r {
+                        out.copy_from_slice(&run);
+                    } else {
+                        for (out, &value) in out.iter_mut().zip(&run) {
+                            *out = op(*out, value);
+                        }
+                    }
+                }
+            });
+            FieldBuffer::from_vec(width, height, values)
+        }
+    }
+}
+
+/// Set every `out[i]` to `op` over `line[i - radius..=i + radius]`, with the
+/// line wrapping around (van Herk/Gil-Werman)
+fn sweep(line: &[f32], radius: usize, op: fn(f32, f32) -> f32, out: &mut [f32]) {
+    let n = line.len();
+    let window = 2 * radius + 1;
+    if window >= n {
+        let all = line.iter().copied().reduce(op).unwrap_or_default();
+        out.fill(all);
+        return;
+    }
+    // The line padded by the radius on both sides, cut into blocks of the
+    // window length; every window spans the end of one block and the start
+    // of the next
+    let padded: Vec<f32> = (0..n + 2 * radius).map(|i| line[(i + n - radius) % n]).collect();
+    let mut from_start = padded.clone();
+    let mut to_end = padded.clone();
+    for i in 1..padded.len() {
+        if i % window != 0 {
+            from_start[i] = op(from_start[i - 1], padded[i]);
+        }
+    }
+    for i in (0..padded.len() - 1).rev() {
+        if (i + 1) % window != 0 {
+            to_end[i] = op(to_end[i + 1], padded[i]);
+        }
+    }
+    for (i, out) in out.iter_mut().enumerate() {
+        *out = op(to_end[i], from_start[i + window - 1]);
+    }
+}
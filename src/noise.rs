@@ -0,0 +1,848 @@
+//! Fractal noise textures, and white and blue noise masks for dithering.
+
+use std::f64::consts::TAU;
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+use ::noise::{NoiseFn, OpenSimplex, Perlin, Simplex};
+use image::{ImageBuffer, Rgb};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::band::BandSource;
+use crate::error::CellsError;
+use crate::field::FieldBuffer;
+
+/// Highest supported octave frequency; beyond it octaves are finer than any
+/// texture can show and the noise input loses precision
+const MAX_FREQUENCY: f64 = 1e6;
+
+/// Standard deviation in pixels of the void-and-cluster energy filter
+const BLUE_NOISE_SIGMA: f32 = 1.5;
+
+/// The energy filter is cut off beyond this many standard deviations
+const BLUE_NOISE_EXTENT: f32 = 3.0;
+
+/// Share of pixels set in the initial void-and-cluster pattern
+const BLUE_NOISE_INITIAL_SHARE: usize = 10;
+
+/// How strongly a ridged octave suppresses the next one away from its ridges
+const RIDGED_GAIN: f64 = 2.0;
+
+/// The base noise function summed into fractal noise
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NoiseKind {
+    /// Classic gradient noise on a square lattice, with faint axis-aligned
+    /// artifacts
+    #[default]
+    Perlin,
+    /// Simplex noise on a skewed lattice, fewer directional artifacts
+    Simplex,
+    /// OpenSimplex noise, an isotropic simplex variant with smooth gradients
+    OpenSimplex,
+}
+
+impl fmt::Display for NoiseKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NoiseKind::Perlin => write!(f, "perlin"),
+            NoiseKind::Simplex => write!(f, "simplex"),
+            NoiseKind::OpenSimplex => write!(f, "open-simplex"),
+        }
+    }
+}
+
+impl FromStr for NoiseKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "perlin" => Ok(NoiseKind::Perlin),
+            "simplex" => Ok(NoiseKind::Simplex),
+            "open-simplex" => Ok(NoiseKind::OpenSimplex),
+            _ => Err(format!("unknown noise `{s}`, expected perlin, simplex or open-simplex")),
+        }
+    }
+}
+
+serde_via_str!(NoiseKind);
+
+/// How the octaves of fractal noise are accumulated
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NoiseStyle {
+    /// Plain fBm, the sum of the signed octaves in [-1, 1]
+    #[default]
+    Fbm,
+    /// Ridged multifractal: every octave contributes `(1 - |noise|)^2`, sharp
+    /// ridges where the noise crosses zero, weighted by the previous octave
+    /// so detail gathers along the ridges; values in [0, 1]
+    Ridged,
+    /// Billow: every octave contributes `|noise|`, round bulges with creases
+    /// in between; values in [0, 1]
+    Billow,
+}
+
+impl fmt::Display for NoiseStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NoiseStyle::Fbm => write!(f, "fbm"),
+            NoiseStyle::Ridged => write!(f, "ridged"),
+            NoiseStyle::Billow => write!(f, "billow"),
+        }
+    }
+}
+
+impl FromStr for NoiseStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fbm" => Ok(NoiseStyle::Fbm),
+            "ridged" => Ok(NoiseStyle::Ridged),
+            "billow" => Ok(NoiseStyle::Billow),
+            _ => Err(format!("unknown noise style `{s}`, expected fbm, ridged or billow")),
+        }
+    }
+}
+
+serde_via_str!(NoiseStyle);
+
+/// Parameters of fractal Brownian motion (fBm) noise
+///
+/// Octave `i` samples the noise at `frequency * lacunarity^i` and weighs it
+/// with `persistence^i`, so the sum keeps the large shapes of the first octave
+/// and adds ever finer and fainter detail.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FbmParams {
+    /// Number of noise layers summed; more octaves add finer detail at the
+    /// cost of one noise evaluation each
+    pub octaves: u32,
+    /// Amplitude factor from one octave to the next; small values give smooth
+    /// noise dominated by the first octave, values near or above 1 rough noise
+    pub persistence: f64,
+    /// Frequency factor from one octave to the next; 2 doubles the detail of
+    /// every octave, values closer to 1 make the octaves overlap more
+    pub lacunarity: f64,
+    /// Base frequency, the number of noise features across the texture in the
+    /// first octave
+    pub frequency: f64,
+    /// The base noise function
+    pub kind: NoiseKind,
+    /// How the octaves are accumulated
+    pub style: NoiseStyle,
+    /// Seed the noise permutation table is derived from
+    pub seed: u64,
+}
+
+impl Default for FbmParams {
+    fn default() -> Self {
+        FbmParams {
+            octaves: 6,
+            persistence: 0.5,
+            lacunarity: 2.0,
+            frequency: 1.0,
+            kind: NoiseKind::Perlin,
+            style: NoiseStyle::Fbm,
+            seed: 0,
+        }
+    }
+}
+
+impl FbmParams {
+    /// Check that the parameters describe finite, non-empty noise
+    ///
+    /// # Returns
+    ///
+    /// [`CellsError::InvalidParameter`] if there are no octaves, a factor is
+    /// not positive and finite, the lacunarity is below 1, or the amplitude
+    /// or frequency of the last octave overflows
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::noise::FbmParams;
+    ///
+    /// assert!(FbmParams::default().validate().is_ok());
+    /// assert!(FbmParams { octaves: 0, ..Default::default() }.validate().is_err());
+    /// assert!(FbmParams { octaves: 200, persistence: 1e3, ..Default::default() }.validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<(), CellsError> {
+        let invalid = |name, reason: String| Err(CellsError::InvalidParameter { name, reason });
+        if self.octaves == 0 {
+            return invalid("octaves", "at least one octave is needed".to_string());
+        }
+        for (name, value) in [
+            ("persistence", self.persistence),
+            ("lacunarity", self.lacunarity),
+            ("frequency", self.frequency),
+        ] {
+            if !(value.is_finite() && value > 0.0) {
+                return invalid(name, format!("must be positive and finite, got {value}"));
+            }
+        }
+        if self.lacunarity < 1.0 {
+            return invalid("lacunarity", format!("must be at least 1, got {}", self.lacunarity));
+        }
+        let last = self.octaves as i32 - 1;
+        let total_amplitude: f64 = (0..self.octaves as i32).map(|i| self.persistence.powi(i)).sum();
+        if !total_amplitude.is_finite() {
+            return invalid(
+                "persistence",
+                format!("{:?} overflows over {} octaves", self.persistence, self.octaves),
+            );
+        }
+        let top_frequency = self.frequency * self.lacunarity.powi(last);
+        if top_frequency > MAX_FREQUENCY {
+            return invalid(
+                "lacunarity",
+                format!(
+                    "the last of {} octaves reaches frequency {top_frequency:e}, above {MAX_FREQUENCY:e}",
+                    self.octaves
+                ),
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Fractal noise parameters set one at a time, checked by
+/// [`FbmBuilder::build`]
+///
+/// Unset parameters keep the defaults of [`FbmParams`], so
+/// `FbmBuilder::new().generate(size)` is the default
+/// [`generate_perlin_field`].
+///
+/// # Example
+///
+/// ```rust
+/// use cells::noise::{generate_perlin_field, FbmBuilder, FbmParams, NoiseKind};
+///
+/// let noise = FbmBuilder::new().octaves(4).frequency(3.0).kind(NoiseKind::Simplex).seed(2).generate(32).unwrap();
+/// let params = FbmParams { octaves: 4, frequency: 3.0, kind: NoiseKind::Simplex, seed: 2, ..Default::default() };
+/// assert_eq!(noise, generate_perlin_field(32, &params));
+/// assert!(FbmBuilder::new().octaves(0).build().is_err());
+/// assert!(FbmBuilder::new().generate(0).is_err());
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FbmBuilder {
+    params: FbmParams,
+}
+
+impl FbmBuilder {
+    /// A builder of the default [`FbmParams`]
+    pub fn new() -> FbmBuilder {
+        FbmBuilder::default()
+    }
+
+    /// The number of noise layers summed, at least 1
+    pub fn octaves(mut self, octaves: u32) -> FbmBuilder {
+        self.params.octaves = octaves;
+        self
+    }
+
+    /// The amplitude factor from one octave to the next, positive and finite
+    pub fn persistence(mut self, persistence: f64) -> FbmBuilder {
+        self.params.persistence = persistence;
+        self
+    }
+
+    /// The frequency factor from one octave to the next, at least 1 and finite
+    pub fn lacunarity(mut self, lacunarity: f64) -> FbmBuilder {
+        self.params.lacunarity = lacunarity;
+        self
+    }
+
+    /// The number of noise features across the texture in the first octave,
+    /// positive, and small enough that the last octave stays at most 1e6
+    pub fn frequency(mut self, frequency: f64) -> FbmBuilder {
+        self.params.frequency = frequency;
+        self
+    }
+
+    /// The base noise function
+    pub fn kind(mut self, kind: NoiseKind) -> FbmBuilder {
+        self.params.kind = kind;
+        self
+    }
+
+    /// How the octaves are accumulated
+    pub fn style(mut self, style: NoiseStyle) -> FbmBuilder {
+        self.params.style = style;
+        self
+    }
+
+    /// The seed the noise permutation table is derived from, any value
+    pub fn seed(mut self, seed: u64) -> FbmBuilder {
+        self.params.seed = seed;
+        self
+    }
+
+    /// The parameters, if [`FbmParams::validate`] accepts them
+    pub fn build(self) -> Result<FbmParams, CellsError> {
+        self.params.validate()?;
+        Ok(self.params)
+    }
+
+    /// Generate the noise with [`generate_perlin_field`], `size` pixels wide
+    /// and high
+    ///
+    /// # Returns
+    ///
+    /// The noise, the error of [`FbmBuilder::build`], or
+    /// [`CellsError::InvalidParameter`] for a zero size
+    pub fn generate(self, size: u32) -> Result<FieldBuffer, CellsError> {
+        let params = self.build()?;
+        if size == 0 {
+            return Err(CellsError::InvalidParameter { name: "size", reason: "must be above 0".to_string() });
+        }
+        Ok(generate_perlin_field(size, &params))
+    }
+}
+
+/// Generate raw, tileable fractal Brownian motion (fBm) noise
+///
+/// This is [`generate_fractal_noise`] over the base noise selected by
+/// `params.kind`, seeded with a noise seed derived from `params.seed`.
+///
+/// # Arguments
+///
+/// * `size` - The width and height of the output field in pixels
+/// * `params` - The base noise, octave structure and seed of the noise
+///
+/// # Returns
+///
+/// A `FieldBuffer` containing the raw fBm values
+///
+/// # Panics
+///
+/// Panics if [`FbmParams::validate`] rejects `params`.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::noise::{generate_fbm_field, FbmParams};
+///
+/// let params = FbmParams { frequency: 4.0, seed: 7, ..Default::default() };
+/// let (min, max) = generate_fbm_field(64, &params).min_max().unwrap();
+/// assert!(-1.0 <= min && min < 0.0 && 0.0 < max && max <= 1.0);
+/// ```
+pub fn generate_fbm_field(size: u32, params: &FbmParams) -> FieldBuffer {
+    warn_aliased_octaves(size, params);
+    fbm_field(size, params, [0.0; 4], |_| {})
+}
+
+/// Generate one frame of raw fBm noise that loops in time
+///
+/// The torus every octave is sampled on is moved through the 4D noise along
+/// a circle of radius `motion`, to the angle `phase * 2π` from the start. The offset is the
+/// same for every pixel, so every frame still tiles, and phases one apart
+/// sample the same noise, so frame `i` of `n` at phase `i / n` follows frame
+/// `n - 1` as smoothly as any other. A phase of 0 is [`generate_fbm_field`],
+/// except that frames leave the aliasing check to [`warn_aliased_octaves`].
+///
+/// # Arguments
+///
+/// * `size` - The width and height of the output field in pixels
+/// * `params` - The base noise, octave structure and seed of the noise
+/// * `phase` - The position in the loop, from 0 to 1
+/// * `motion` - The radius of the circle in noise features, the same for
+///   every octave; larger values change the frames faster
+///
+/// # Returns
+///
+/// A `FieldBuffer` containing the raw fBm values of the frame
+///
+/// # Panics
+///
+/// Panics if [`FbmParams::validate`] rejects `params`.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::noise::{generate_fbm_field, generate_fbm_frame, FbmParams};
+///
+/// let params = FbmParams { frequency: 4.0, seed: 7, ..Default::default() };
+/// let start = generate_fbm_frame(32, &params, 0.0, 0.5);
+/// assert_eq!(start, generate_fbm_field(32, &params));
+/// assert_ne!(generate_fbm_frame(32, &params, 0.25, 0.5), start);
+/// // A full loop comes back to the start, up to rounding of the angle
+/// let end = generate_fbm_frame(32, &params, 1.0, 0.5);
+/// assert!(end.as_slice().iter().zip(start.as_slice()).all(|(a, b)| (a - b).abs() < 1e-5));
+/// ```
+pub fn generate_fbm_frame(size: u32, params: &FbmParams, phase: f64, motion: f64) -> FieldBuffer {
+    fbm_field(size, params, loop_offset(phase, motion), |_| {})
+}
+
+/// Generate one frame of a looping Perlin noise field, reporting progress
+///
+/// This is [`generate_fbm_frame`] mapped to 0-1 like
+/// [`generate_perlin_field_with_progress`].
+///
+/// # Panics
+///
+/// Panics if [`FbmParams::validate`] rejects `params`.
+pub fn generate_perlin_frame_with_progress(
+    size: u32,
+    params: &FbmParams,
+    phase: f64,
+    motion: f64,
+    progress: impl FnMut(f32) + Send,
+) -> FieldBuffer {
+    perlin_from_fbm(fbm_field(size, params, loop_offset(phase, motion), progress), params)
+}
+
+/// The offset of the noise position at `phase` of a loop of radius `motion`
+///
+/// The circle passes through the origin at phase 0 and is tilted across both
+/// planes of the torus, so the noise does not just move along one of its
+/// axes.
+fn loop_offset(phase: f64, motion: f64) -> [f64; 4] {
+    let (sin, cos) = (phase * TAU).sin_cos();
+    let radius = motion / 2f64.sqrt();
+    [(cos - 1.0) * radius, sin * radius, -sin * radius, (cos - 1.0) * radius]
+}
+
+/// Warn that the last octave of `params` has more than one feature per two
+/// pixels of a `size` x `size` field, so it aliases
+///
+/// The whole fields of this module check this themselves. Frames do not, so
+/// an animation can check it once instead of for every frame.
+pub fn warn_aliased_octaves(size: u32, params: &FbmParams) {
+    let top_frequency = params.frequency * params.lacunarity.powi(params.octaves as i32 - 1);
+    if top_frequency > size as f64 / 2.0 {
+        log::warn!(
+            "the last of {} noise octaves has {top_frequency} features across {size} pixels, \
+             more than one per two pixels, so it aliases",
+            params.octaves
+        );
+    }
+}
+
+/// The noise of [`generate_fbm_field`] with every sample position moved by
+/// `offset`, reporting the finished share of the rows to `progress`
+fn fbm_field(size: u32, params: &FbmParams, offset: [f64; 4], progress: impl FnMut(f32) + Send) -> FieldBuffer {
+    let details = format_args!(
+        "{size}x{size}, {} {} octaves from frequency {}, persistence {}, lacunarity {}",
+        params.octaves, params.kind, params.frequency, params.persistence, params.lacunarity
+    );
+    crate::stage("noise", details, || fbm_rows(size, 0..size, params, offset, progress))
+}
+
+/// The rows `rows` of the noise of [`fbm_field`]
+fn fbm_rows(
+    size: u32,
+    rows: Range<u32>,
+    params: &FbmParams,
+    offset: [f64; 4],
+    progress: impl FnMut(f32) + Send,
+) -> FieldBuffer {
+    let noise_seed = StdRng::seed_from_u64(params.seed).gen();
+    match params.kind {
+        NoiseKind::Perlin => fractal_noise(size, rows, params, &Perlin::new(noise_seed), offset, progress),
+        NoiseKind::Simplex => fractal_noise(size, rows, params, &Simplex::new(noise_seed), offset, progress),
+        NoiseKind::OpenSimplex => fractal_noise(size, rows, params, &OpenSimplex::new(noise_seed), offset, progress),
+    }
+}
+
+/// Generate raw, tileable fractal noise over any 4D base noise
+///
+/// This function sums multiple octaves of the base noise into a float field,
+/// each shaped by the [`NoiseStyle`] of the params. The sum is divided by the
+/// total amplitude of all octaves, so values lie in [-1, 1] for plain fBm and
+/// in [0, 1] for the ridged and billow styles, but are otherwise left as they
+/// are.
+///
+/// Plain 2D noise does not wrap, so the field is sampled from 4D noise on a
+/// torus instead: the x and y positions become angles on two circles whose
+/// circumference equals the octave frequency. Every octave is periodic over
+/// the field, which therefore tiles seamlessly.
+///
+/// # Algorithm
+///
+/// 1. For each pixel in the output field:
+///    a. Map x and y to the angles u and v on two circles
+///    b. Sample octaves of 4D noise at (cos u, sin u, cos v, sin v) * radius
+///    c. Shape each sample by the noise style and sum it
+///    d. Scale frequency by the lacunarity and amplitude by the persistence
+///    e. Divide the sum by the total amplitude
+///
+/// # Arguments
+///
+/// * `size` - The width and height of the output field in pixels
+/// * `params` - The octave structure of the noise; `params.kind` and
+///   `params.seed` are ignored in favor of the already seeded `noise`
+/// * `noise` - The base noise function
+///
+/// # Returns
+///
+/// A `FieldBuffer` containing the raw fractal noise values
+///
+/// # Panics
+///
+/// Panics if [`FbmParams::validate`] rejects `params`.
+///
+/// # Performance
+///
+/// The complexity is O(size^2 * octaves). Rows are generated in parallel with
+/// rayon.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::noise::{generate_fractal_noise, FbmParams};
+/// use noise::Value;
+///
+/// let params = FbmParams { frequency: 4.0, ..Default::default() };
+/// let field = generate_fractal_noise(64, &params, &Value::new(3));
+/// assert!(field.as_slice().iter().all(|v| (-1.0..=1.0).contains(v)));
+/// ```
+pub fn generate_fractal_noise<N>(size: u32, params: &FbmParams, noise: &N) -> FieldBuffer
+where
+    N: NoiseFn<f64, 4> + Sync,
+{
+    fractal_noise(size, 0..size, params, noise, [0.0; 4], |_| {})
+}
+
+/// The rows `rows` of the noise of [`generate_fractal_noise`] with every
+/// sample position moved by `offset`, reporting the finished share of the
+/// rows to `progress`
+fn fractal_noise<N>(
+    size: u32,
+    rows: Range<u32>,
+    params: &FbmParams,
+    noise: &N,
+    offset: [f64; 4],
+    progress: impl FnMut(f32) + Send,
+) -> FieldBuffer
+where
+    N: NoiseFn<f64, 4> + Sync,
+{
+    if let Err(err) = params.validate() {
+        panic!("{err}");
+    }
+
+    let sample = |x: u32, y: u32| {
+        let u = x as f64 / size as f64 * TAU;
+        let v = y as f64 / size as f64 * TAU;
+        sum_octaves(params, |_, frequency| {
+            let radius = frequency / TAU;
+            noise.get([
+                u.cos() * radius + offset[0],
+                u.sin() * radius + offset[1],
+                v.cos() * radius + offset[2],
+                v.sin() * radius + offset[3],
+            ])
+        })
+    };
+    FieldBuffer::from_par_fn_with_progress(size, rows.len() as u32, |x, y| sample(x, rows.start + y), progress)
+}
+
+/// Sum the octaves of `params` shaped by its style, sampling octave `i` from
+/// `noise(i, frequency)`, and divide by the total amplitude
+pub(crate) fn sum_octaves(params: &FbmParams, mut noise: impl FnMut(u32, f64) -> f64) -> f32 {
+    let mut noise_value = 0.0;
+    let mut amplitude = 1.0;
+    let mut frequency = params.frequency;
+    let mut max_value = 0.0;
+    let mut weight = 1.0;
+
+    for octave in 0..params.octaves {
+        let sample = noise(octave, frequency);
+        let signal = match params.style {
+            NoiseStyle::Fbm => sample,
+            NoiseStyle::Billow => sample.abs(),
+            NoiseStyle::Ridged => {
+                let ridge = (1.0 - sample.abs()).powi(2) * weight;
+                weight = (ridge * RIDGED_GAIN).clamp(0.0, 1.0);
+                ridge
+            }
+        };
+
+        noise_value += signal * amplitude;
+
+        max_value += amplitude;
+        amplitude *= params.persistence;
+        frequency *= params.lacunarity;
+    }
+
+    (noise_value / max_value) as f32
+}
+
+/// Generate Perlin noise texture
+///
+/// This function creates a texture using Perlin noise with multiple octaves,
+/// resulting in a fractal-like pattern. It is [`generate_fbm_field`] as a
+/// float field with values from 0 to 1, mapping plain fBm from [-1, 1].
+///
+/// # Arguments
+///
+/// * `size` - The width and height of the output image in pixels
+/// * `params` - The octave structure and seed of the noise
+///
+/// # Returns
+///
+/// A `FieldBuffer` containing the Perlin noise texture
+///
+/// # Example
+///
+/// ```rust
+/// use cells::noise::{generate_perlin_field, FbmParams};
+/// use cells::tiling::{assert_tileable, interior_discontinuity};
+///
+/// let params = FbmParams { frequency: 4.0, seed: 7, ..Default::default() };
+/// let perlin_texture = generate_perlin_field(64, &params);
+/// assert_eq!(perlin_texture.dimensions(), (64, 64));
+/// assert_eq!(perlin_texture, generate_perlin_field(64, &params));
+/// assert_tileable(&perlin_texture, interior_discontinuity(&perlin_texture));
+/// ```
+pub fn generate_perlin_field(size: u32, params: &FbmParams) -> FieldBuffer {
+    generate_perlin_field_with_progress(size, params, |_| {})
+}
+
+/// Generate a tileable Perlin noise field, reporting progress
+///
+/// This is [`generate_perlin_field`] calling `progress` with the finished
+/// share of the rows, from 0 to 1, as they complete.
+///
+/// # Panics
+///
+/// Panics if [`FbmParams::validate`] rejects `params`.
+pub fn generate_perlin_field_with_progress(
+    size: u32,
+    params: &FbmParams,
+    progress: impl FnMut(f32) + Send,
+) -> FieldBuffer {
+    warn_aliased_octaves(size, params);
+    perlin_from_fbm(fbm_field(size, params, [0.0; 4], progress), params)
+}
+
+/// Map raw plain fBm from [-1, 1] to 0-1, leaving the other styles as they are
+pub(crate) fn perlin_from_fbm(field: FieldBuffer, params: &FbmParams) -> FieldBuffer {
+    match params.style {
+        NoiseStyle::Fbm => field.map(|value| (value + 1.0) / 2.0),
+        NoiseStyle::Ridged | NoiseStyle::Billow => field,
+    }
+}
+
+/// The noise of [`generate_perlin_field`] rendered in bands of rows, see
+/// [`crate::band`]
+///
+/// # Example
+///
+/// ```rust
+/// use cells::band::render_all;
+/// use cells::noise::{generate_perlin_field, FbmParams, NoiseStyle, PerlinBands};
+///
+/// let params = FbmParams { frequency: 4.0, style: NoiseStyle::Ridged, seed: 7, ..Default::default() };
+/// let bands = PerlinBands::new(40, &params).unwrap();
+/// assert_eq!(render_all(&bands, 6), generate_perlin_field(40, &params));
+/// assert!(PerlinBands::new(40, &FbmParams { octaves: 0, ..params }).is_err());
+/// ```
+#[derive(Clone, Debug)]
+pub struct PerlinBands {
+    size: u32,
+    params: FbmParams,
+}
+
+impl PerlinBands {
+    /// The noise of a texture of `size` pixels across
+    ///
+    /// # Returns
+    ///
+    /// The error of [`FbmParams::validate`] if it rejects `params`
+    pub fn new(size: u32, params: &FbmParams) -> Result<PerlinBands, CellsError> {
+        params.validate()?;
+        Ok(PerlinBands { size, params: *params })
+    }
+}
+
+impl BandSource for PerlinBands {
+    fn dimensions(&self) -> (u32, u32) {
+        (self.size, self.size)
+    }
+
+    fn render(&self, rows: Range<u32>) -> FieldBuffer {
+        perlin_from_fbm(fbm_rows(self.size, rows, &self.params, [0.0; 4], |_| {}), &self.params)
+    }
+}
+
+/// Generate Perlin noise texture as an 8-bit image
+///
+/// This is [`generate_perlin_field`] quantized into the red channel.
+///
+/// # Arguments
+///
+/// * `size` - The width and height of the output image in pixels
+/// * `params` - The octave structure and seed of the noise
+///
+/// # Returns
+///
+/// An `ImageBuffer` containing the Perlin noise texture
+///
+/// # Example
+///
+/// ```rust
+/// use cells::noise::{generate_perlin_noise, FbmParams};
+///
+/// let perlin_texture = generate_perlin_noise(64, &FbmParams { seed: 7, ..Default::default() });
+/// assert_eq!(perlin_texture.dimensions(), (64, 64));
+/// ```
+pub fn generate_perlin_noise(size: u32, params: &FbmParams) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    generate_perlin_field(size, params).to_rgb_image()
+}
+
+/// Generate white noise, an independent uniform random value in 0-1 per pixel
+///
+/// # Example
+///
+/// ```rust
+/// use cells::noise::generate_white_noise;
+///
+/// let noise = generate_white_noise(16, 3);
+/// assert_eq!(noise, generate_white_noise(16, 3));
+/// assert!(noise.as_slice().iter().all(|value| (0.0..1.0).contains(value)));
+/// ```
+pub fn generate_white_noise(size: u32, seed: u64) -> FieldBuffer {
+    let mut rng = StdRng::seed_from_u64(seed);
+    FieldBuffer::from_vec(size, size, (0..size as usize * size as usize).map(|_| rng.gen()).collect())
+}
+
+/// Generate a tileable blue noise mask with the void-and-cluster algorithm
+///
+/// Every pixel holds its rank divided by the pixel count, so thresholding the
+/// mask at `t` sets a `t` share of the pixels, and at every threshold the set
+/// pixels are spread evenly, without the clumps and gaps of white noise. This
+/// makes the mask suited to dithering and stochastic transparency.
+///
+/// The energy filter that finds the clusters and voids wraps around the
+/// edges, so the mask tiles. Every pixel is ranked by a search over all
+/// pixels, so the run time grows with the square of the pixel count: 64x64
+/// takes a moment, 256x256 a while.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::noise::generate_blue_noise;
+///
+/// let mask = generate_blue_noise(16, 3);
+/// let mut ranks: Vec<f32> = mask.as_slice().iter().map(|value| value * 256.0).collect();
+/// ranks.sort_by(f32::total_cmp);
+/// assert_eq!(ranks, (0..256).map(|rank| rank as f32).collect::<Vec<_>>());
+/// ```
+pub fn generate_blue_noise(size: u32, seed: u64) -> FieldBuffer {
+    crate::stage("blue noise", format_args!("{size}x{size}"), || blue_noise(size, seed))
+}
+
+/// Generate the blue noise of [`generate_blue_noise`]
+fn blue_noise(size: u32, seed: u64) -> FieldBuffer {
+    let side = size as usize;
+    let count = side * side;
+    if count == 0 {
+        return FieldBuffer::new(size, size);
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut indices: Vec<usize> = (0..count).collect();
+    indices.shuffle(&mut rng);
+
+    let mut pattern = EnergyPattern::new(side);
+    let initial = (count / BLUE_NOISE_INITIAL_SHARE).max(1);
+    for &index in &indices[..initial] {
+        pattern.toggle(index);
+    }
+    // Move the tightest cluster into the largest void until that no longer
+    // changes anything; every pixel moving once is plenty
+    for _ in 0..count {
+        let cluster = pattern.tightest_cluster();
+        pattern.toggle(cluster);
+        let void = pattern.largest_void();
+        if void == cluster {
+            pattern.toggle(cluster);
+            break;
+        }
+        pattern.toggle(void);
+    }
+
+    let mut ranks = vec![0; count];
+    // Rank the initial pattern by taking its clusters away one by one
+    let mut removing = pattern.clone();
+    for rank in (0..initial).rev() {
+        let cluster = removing.tightest_cluster();
+        removing.toggle(cluster);
+        ranks[cluster] = rank;
+    }
+    // and the other pixels by filling the voids. Past half the pixels the
+    // largest void of the set pixels is the tightest cluster of the unset
+    // ones, so this also spreads the unset pixels evenly
+    for rank in initial..count {
+        let void = pattern.largest_void();
+        pattern.toggle(void);
+        ranks[void] = rank;
+    }
+    FieldBuffer::from_vec(size, size, ranks.into_iter().map(|rank| rank as f32 / count as f32).collect())
+}
+
+/// A binary pattern on a torus with the filtered energy of its set pixels
+#[derive(Clone)]
+struct EnergyPattern {
+    side: usize,
+    set: Vec<bool>,
+    energy: Vec<f32>,
+    /// Gaussian weights by wrapped offset, `(dx, dy, weight)`
+    kernel: Vec<(usize, usize, f32)>,
+}
+
+impl EnergyPattern {
+    fn new(side: usize) -> Self {
+        let extent = (BLUE_NOISE_SIGMA * BLUE_NOISE_EXTENT).ceil() as i64;
+        let mut kernel = Vec::new();
+        for dy in -extent..=extent {
+            for dx in -extent..=extent {
+                let weight = (-((dx * dx + dy * dy) as f32) / (2.0 * BLUE_NOISE_SIGMA * BLUE_NOISE_SIGMA)).exp();
+                let wrap = |d: i64| d.rem_euclid(side as i64) as usize;
+                kernel.push((wrap(dx), wrap(dy), weight));
+            }
+        }
+        EnergyPattern {
+            side,
+            set: vec![false; side * side],
+            energy: vec![0.0; side * side],
+            kernel,
+        }
+    }
+
+    /// Set or unset a pixel, spreading or taking back its energy
+    fn toggle(&mut self, index: usize) {
+        self.set[index] = !self.set[index];
+        let sign = if self.set[index] { 1.0 } else { -1.0 };
+        let (x, y) = (index % self.side, index / self.side);
+        for &(dx, dy, weight) in &self.kernel {
+            let (nx, ny) = ((x + dx) % self.side, (y + dy) % self.side);
+            self.energy[ny * self.side + nx] += sign * weight;
+        }
+    }
+
+    /// The set pixel with the most energy
+    fn tightest_cluster(&self) -> usize {
+        self.extreme(true, |energy, best| energy > best)
+    }
+
+    /// The unset pixel with the least energy
+    fn largest_void(&self) -> usize {
+        self.extreme(false, |energy, best| energy < best)
+    }
+
+    /// The first pixel of the given state whose energy beats all others
+    fn extreme(&self, state: bool, beats: impl Fn(f32, f32) -> bool) -> usize {
+        let mut best: Option<usize> = None;
+        for (index, &energy) in self.energy.iter().enumerate() {
+            if self.set[index] == state && best.is_none_or(|best| beats(energy, self.energy[best])) {
+                best = Some(index);
+            }
+        }
+        best.expect("the pattern has pixels of both states")
+    }
+}
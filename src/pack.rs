@@ -0,0 +1,68 @@
+//! Packing several fields into the channels of one texture.
+
+use image::{ImageBuffer, Rgba};
+
+use crate::error::CellsError;
+use crate::field::{quantize, FieldBuffer};
+
+/// Pack three or four fields into the channels of one RGBA texture
+///
+/// Game engines sample packed textures such as roughness, ambient occlusion
+/// and height from a single file. Every field is quantized into its channel
+/// like [`FieldBuffer::to_image`] does; without an alpha field the alpha
+/// channel is fully opaque.
+///
+/// # Arguments
+///
+/// * `r` - The field written to the red channel
+/// * `g` - The field written to the green channel
+/// * `b` - The field written to the blue channel
+/// * `a` - The field written to the alpha channel, if any
+///
+/// # Returns
+///
+/// An `ImageBuffer` containing the packed texture, or
+/// [`CellsError::DimensionMismatch`] when the fields differ in size
+///
+/// # Example
+///
+/// ```rust
+/// use cells::pack::pack_channels;
+/// use cells::FieldBuffer;
+///
+/// let r = FieldBuffer::from_vec(2, 1, vec![0.0, 1.0]);
+/// let g = FieldBuffer::from_vec(2, 1, vec![0.5, 0.5]);
+/// let b = FieldBuffer::from_vec(2, 1, vec![1.0, 0.0]);
+/// let packed = pack_channels(&r, &g, &b, None).unwrap();
+/// assert_eq!(packed.get_pixel(0, 0).0, [0, 128, 255, 255]);
+///
+/// let small = FieldBuffer::new(1, 1);
+/// let err = pack_channels(&r, &g, &b, Some(&small)).unwrap_err();
+/// assert_eq!(err.to_string(), "alpha is 1x1 but the other inputs are 2x1");
+/// ```
+pub fn pack_channels(
+    r: &FieldBuffer,
+    g: &FieldBuffer,
+    b: &FieldBuffer,
+    a: Option<&FieldBuffer>,
+) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, CellsError> {
+    let expected = r.dimensions();
+    let channels = [("green", Some(g)), ("blue", Some(b)), ("alpha", a)];
+    for (input, field) in channels {
+        match field {
+            Some(field) if field.dimensions() != expected => {
+                return Err(CellsError::DimensionMismatch { input, expected, found: field.dimensions() });
+            }
+            _ => {}
+        }
+    }
+
+    let (width, height) = expected;
+    let mut img = ImageBuffer::new(width, height);
+    let sample = |field: &FieldBuffer, i: usize| quantize(field.as_slice()[i]);
+    for (i, pixel) in img.pixels_mut().enumerate() {
+        let alpha = a.map_or(255, |a| sample(a, i));
+        *pixel = Rgba([sample(r, i), sample(g, i), sample(b, i), alpha]);
+    }
+    Ok(img)
+}
@@ -0,0 +1,368 @@
+//! Regular structured patterns such as bricks and Truchet tiles,
+//! complementing the irregular Voronoi cells, and primitives such as
+//! checkerboards, stripes and radial gradients to mask them with.
+//!
+//! Samples are taken at pixel centers, so `(x + 0.5) / size` is the position
+//! of pixel `x` in the unit square.
+
+use std::fmt;
+use std::str::FromStr;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::error::CellsError;
+use crate::field::FieldBuffer;
+use crate::geometry::{toroidal_distance, Point};
+
+/// Parameters of a brick wall texture
+///
+/// Lengths are fractions of the texture size, so the wall looks the same at
+/// any resolution.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BrickParams {
+    /// Width and height of the texture in pixels
+    pub size: u32,
+    /// Number of brick rows from top to bottom
+    pub rows: u32,
+    /// Number of bricks in every row
+    pub cols: u32,
+    /// Shift of every row against the one above, in brick widths; 0.5 gives
+    /// the classic running bond
+    pub offset: f32,
+    /// Width of the mortar joints
+    pub mortar: f32,
+    /// Share of the distance from a brick's edge to its center that is
+    /// beveled, from 0 (sharp edges) to 1 (rounded all the way up)
+    pub bevel: f32,
+    /// How much darker than 1 a brick may randomly be, from 0 to 1
+    pub variation: f32,
+    /// Seed of the brick brightnesses
+    pub seed: u64,
+}
+
+impl Default for BrickParams {
+    fn default() -> Self {
+        BrickParams {
+            size: 512,
+            rows: 8,
+            cols: 4,
+            offset: 0.5,
+            mortar: 0.02,
+            bevel: 0.1,
+            variation: 0.2,
+            seed: 0,
+        }
+    }
+}
+
+impl BrickParams {
+    /// Check that the wall has bricks and the lengths are in range
+    ///
+    /// # Returns
+    ///
+    /// [`CellsError::InvalidParameter`] if the size, rows or columns are 0,
+    /// the offset is not finite, or the mortar, bevel or variation is outside
+    /// 0-1
+    pub fn validate(&self) -> Result<(), CellsError> {
+        let invalid = |name, reason: String| Err(CellsError::InvalidParameter { name, reason });
+        for (name, value) in [("size", self.size), ("rows", self.rows), ("cols", self.cols)] {
+            if value == 0 {
+                return invalid(name, "must be above 0".to_string());
+            }
+        }
+        if !self.offset.is_finite() {
+            return invalid("offset", format!("must be finite, got {}", self.offset));
+        }
+        for (name, value) in [("mortar", self.mortar), ("bevel", self.bevel), ("variation", self.variation)] {
+            if !(0.0..=1.0).contains(&value) {
+                return invalid(name, format!("must be between 0 and 1, got {value}"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Generate a brick wall as a heightmap
+///
+/// Mortar is 0 and every brick rises over its bevel to its own brightness,
+/// between `1 - variation` and 1. Rows wrap around horizontally whatever
+/// their offset, so the wall tiles exactly when `rows * offset` is a whole
+/// number of bricks, e.g. an offset of 0.5 with an even number of rows.
+///
+/// # Arguments
+///
+/// * `params` - The layout and look of the wall
+///
+/// # Returns
+///
+/// A `FieldBuffer` with values from 0 to 1
+///
+/// # Panics
+///
+/// Panics if the parameters fail [`BrickParams::validate`].
+///
+/// # Example
+///
+/// ```rust
+/// use cells::pattern::{generate_bricks, BrickParams};
+///
+/// let params = BrickParams { size: 64, rows: 4, cols: 2, variation: 0.0, ..Default::default() };
+/// let wall = generate_bricks(&params);
+/// // Mortar along the top edge, the full height in the middle of a brick
+/// assert_eq!((wall.get(20, 0), wall.get(20, 8)), (0.0, 1.0));
+/// // The second row is shifted by half a brick
+/// assert_eq!((wall.get(0, 8), wall.get(0, 24)), (0.0, 1.0));
+/// ```
+pub fn generate_bricks(params: &BrickParams) -> FieldBuffer {
+    if let Err(err) = params.validate() {
+        panic!("{err}");
+    }
+    let (rows, cols) = (params.rows as usize, params.cols as usize);
+    let mut rng = StdRng::seed_from_u64(params.seed);
+    let brightness: Vec<f32> = (0..rows * cols).map(|_| 1.0 - params.variation * rng.gen::<f32>()).collect();
+    let half_mortar = params.mortar / 2.0;
+    // Distance from the edge of the mortar to the center of a brick
+    let inner = (0.5 / params.cols as f32).min(0.5 / params.rows as f32) - half_mortar;
+    let size = params.size as f32;
+    FieldBuffer::from_par_fn(params.size, params.size, |x, y| {
+        let v = (y as f32 + 0.5) / size * params.rows as f32;
+        let row = v.floor();
+        let u = (x as f32 + 0.5) / size * params.cols as f32 - row * params.offset;
+        let col = u.floor();
+        let (fx, fy) = (u - col, v - row);
+        // Distance to the nearest joint in texture units
+        let edge = ((fx.min(1.0 - fx)) / params.cols as f32).min(fy.min(1.0 - fy) / params.rows as f32);
+        let depth = edge - half_mortar;
+        if depth <= 0.0 || inner <= 0.0 {
+            return 0.0;
+        }
+        let height = if params.bevel == 0.0 {
+            1.0
+        } else {
+            let t = (depth / inner / params.bevel).min(1.0);
+            t * t * (3.0 - 2.0 * t)
+        };
+        let index = row as usize % rows * cols + (col as i64).rem_euclid(cols as i64) as usize;
+        height * brightness[index]
+    })
+}
+
+/// The motif drawn in every cell of a Truchet pattern
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TruchetStyle {
+    /// Two quarter circles joining the midpoints of adjacent edges, which
+    /// connect into winding paths
+    #[default]
+    Arcs,
+    /// One diagonal from corner to corner, which connects into a maze
+    Diagonals,
+}
+
+impl fmt::Display for TruchetStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TruchetStyle::Arcs => write!(f, "arcs"),
+            TruchetStyle::Diagonals => write!(f, "diagonals"),
+        }
+    }
+}
+
+impl FromStr for TruchetStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "arcs" => Ok(TruchetStyle::Arcs),
+            "diagonals" => Ok(TruchetStyle::Diagonals),
+            _ => Err(format!("unknown Truchet style `{s}`, expected arcs or diagonals")),
+        }
+    }
+}
+
+/// Generate Truchet tiles, lines of one motif turned at random in every cell
+///
+/// The texture is split into `grid` x `grid` cells, each showing the motif
+/// of `style` either as is or mirrored. The motifs end where cells meet, so
+/// the lines join into paths across cells and across the wrap-around edges.
+/// Lines are 1 and the background 0, with edges anti-aliased by the exact
+/// distance to the motifs.
+///
+/// # Arguments
+///
+/// * `size` - The width and height of the texture in pixels
+/// * `grid` - The number of cells across and down
+/// * `line_width` - The width of the lines as a fraction of a cell
+/// * `style` - The motif of the cells
+/// * `seed` - Seed of the motif orientations
+///
+/// # Panics
+///
+/// Panics if `grid` is 0 or `line_width` is not in (0, 1].
+///
+/// # Example
+///
+/// ```rust
+/// use cells::pattern::{generate_truchet, TruchetStyle};
+///
+/// let truchet = generate_truchet(64, 4, 0.2, TruchetStyle::Arcs, 7);
+/// // Every motif passes through the midpoints of all four cell edges
+/// assert_eq!((truchet.get(8, 0), truchet.get(0, 8), truchet.get(24, 15)), (1.0, 1.0, 1.0));
+/// // and the cell corners stay empty
+/// assert_eq!((truchet.get(0, 0), truchet.get(16, 16)), (0.0, 0.0));
+/// ```
+pub fn generate_truchet(size: u32, grid: u32, line_width: f32, style: TruchetStyle, seed: u64) -> FieldBuffer {
+    assert!(grid > 0, "a Truchet pattern needs at least one cell");
+    assert!(line_width > 0.0 && line_width <= 1.0, "the line width must be in (0, 1], got {line_width}");
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mirrored: Vec<bool> = (0..grid as usize * grid as usize).map(|_| rng.gen()).collect();
+    let pixels_per_cell = size as f32 / grid as f32;
+    FieldBuffer::from_par_fn(size, size, |x, y| {
+        let u = (x as f32 + 0.5) / pixels_per_cell;
+        let v = (y as f32 + 0.5) / pixels_per_cell;
+        let (col, row) = (u.floor() as i64, v.floor() as i64);
+        // Lines reach into the neighboring cells, diagonals with round caps
+        let mut distance = f32::INFINITY;
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let (cell_col, cell_row) = (col + dx, row + dy);
+                let index = cell_row.rem_euclid(grid as i64) * grid as i64 + cell_col.rem_euclid(grid as i64);
+                let (mut fx, fy) = (u - cell_col as f32, v - cell_row as f32);
+                if mirrored[index as usize] {
+                    fx = 1.0 - fx;
+                }
+                distance = distance.min(truchet_distance(fx, fy, style));
+            }
+        }
+        ((line_width / 2.0 - distance) * pixels_per_cell + 0.5).clamp(0.0, 1.0)
+    })
+}
+
+/// Distance from a point in cell units to the unmirrored motif of a cell
+/// spanning 0-1
+fn truchet_distance(fx: f32, fy: f32, style: TruchetStyle) -> f32 {
+    match style {
+        TruchetStyle::Arcs => {
+            // Quarter circles around the top left and bottom right corners;
+            // the nearest point of a circle lies on the quarter inside the
+            // cell only for points in the quadrant the quarter spans
+            let arc = |cx: f32, cy: f32, sx: f32, sy: f32| {
+                let (ox, oy) = ((fx - cx) * sx, (fy - cy) * sy);
+                if ox >= 0.0 && oy >= 0.0 {
+                    ((ox * ox + oy * oy).sqrt() - 0.5).abs()
+                } else {
+                    // Otherwise the nearest point is an end of the arc
+                    let end = |ex: f32, ey: f32| ((ox - ex).powi(2) + (oy - ey).powi(2)).sqrt();
+                    end(0.5, 0.0).min(end(0.0, 0.5))
+                }
+            };
+            arc(0.0, 0.0, 1.0, 1.0).min(arc(1.0, 1.0, -1.0, -1.0))
+        }
+        TruchetStyle::Diagonals => {
+            // The segment from (0, 0) to (1, 1)
+            let t = ((fx + fy) / 2.0).clamp(0.0, 1.0);
+            ((fx - t).powi(2) + (fy - t).powi(2)).sqrt()
+        }
+    }
+}
+
+/// Generate a checkerboard of `cols` x `rows` squares
+///
+/// The top left square is 1 and its neighbors 0. Odd counts tile too, but put
+/// two equal squares side by side across the seam.
+///
+/// # Panics
+///
+/// Panics if `cols` or `rows` is 0.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::pattern::generate_checker;
+///
+/// let checker = generate_checker(8, 2, 4);
+/// assert_eq!((checker.get(0, 0), checker.get(4, 0)), (1.0, 0.0));
+/// assert_eq!((checker.get(0, 2), checker.get(7, 3)), (0.0, 1.0));
+/// ```
+pub fn generate_checker(size: u32, cols: u32, rows: u32) -> FieldBuffer {
+    assert!(cols > 0 && rows > 0, "a checkerboard needs at least 1x1 squares, got {cols}x{rows}");
+    FieldBuffer::from_par_fn(size, size, |x, y| {
+        let (col, row) = (x as u64 * cols as u64 / size as u64, y as u64 * rows as u64 / size as u64);
+        if (col + row) % 2 == 0 {
+            1.0
+        } else {
+            0.0
+        }
+    })
+}
+
+/// Generate `count` parallel stripes running at `angle` degrees
+///
+/// The angle is counterclockwise from horizontal stripes, and each stripe is
+/// 1 for the `duty_cycle` share of its period and 0 for the rest. To tile,
+/// the stripes must cross every edge a whole number of times, so the wave
+/// vector `count * (sin angle, cos angle)` is rounded to whole numbers:
+/// angles snap to the nearest tileable one, more finely the more stripes
+/// there are, and diagonal stripes come out slightly denser or sparser.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::pattern::generate_stripes;
+///
+/// // Two horizontal stripes, each 1 for its upper half
+/// let stripes = generate_stripes(8, 2, 0.0, 0.5);
+/// assert_eq!((0..4).map(|y| stripes.get(5, y)).collect::<Vec<_>>(), [1.0, 1.0, 0.0, 0.0]);
+/// // Vertical stripes, and diagonals stepping one pixel per row
+/// assert_eq!(generate_stripes(8, 2, 90.0, 0.5).get(2, 0), 0.0);
+/// let diagonal = generate_stripes(8, 1, 45.0, 0.5);
+/// assert_eq!((diagonal.get(2, 0), diagonal.get(3, 0)), (1.0, 0.0));
+/// assert_eq!((diagonal.get(1, 1), diagonal.get(2, 1)), (1.0, 0.0));
+/// ```
+pub fn generate_stripes(size: u32, count: u32, angle: f32, duty_cycle: f32) -> FieldBuffer {
+    let (sin, cos) = angle.to_radians().sin_cos();
+    let (a, b) = ((count as f32 * sin).round(), (count as f32 * cos).round());
+    FieldBuffer::from_par_fn(size, size, |x, y| {
+        let (u, v) = ((x as f32 + 0.5) / size as f32, (y as f32 + 0.5) / size as f32);
+        // Whole numbers keep the phase exact on every pixel row and column
+        let phase = (a * u + b * v).rem_euclid(1.0);
+        if phase < duty_cycle {
+            1.0
+        } else {
+            0.0
+        }
+    })
+}
+
+/// Generate a radial gradient falling from 1 at `center` to 0 at the
+/// toroidal distance `falloff`
+///
+/// The distance wraps around the edges, so a gradient near one edge
+/// continues on the opposite one.
+///
+/// # Panics
+///
+/// Panics if `falloff` is not positive and finite.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::pattern::generate_radial;
+/// use cells::Point;
+///
+/// let radial = generate_radial(8, Point { x: 0.5625, y: 0.5625 }, 0.5);
+/// assert_eq!((radial.get(4, 4), radial.get(6, 4), radial.get(0, 4)), (1.0, 0.5, 0.0));
+/// // A gradient in the top left corner reaches into the top right one
+/// let corner = generate_radial(8, Point { x: 0.0625, y: 0.0625 }, 0.5);
+/// assert_eq!(corner.get(7, 0), 0.75);
+/// ```
+pub fn generate_radial(size: u32, center: Point, falloff: f32) -> FieldBuffer {
+    assert!(falloff.is_finite() && falloff > 0.0, "the falloff must be positive, got {falloff}");
+    FieldBuffer::from_par_fn(size, size, |x, y| {
+        let position = Point {
+            x: (x as f32 + 0.5) / size as f32,
+            y: (y as f32 + 0.5) / size as f32,
+        };
+        (1.0 - toroidal_distance(center, position) / falloff).max(0.0)
+    })
+}
@@ -0,0 +1,1507 @@
+//! Declarative pipelines of generators and filters read from TOML.
+//!
+//! A pipeline is a list of named nodes. Every node runs one generator or
+//! filter, reads the outputs of other nodes by name and may be saved to a
+//! file. Nodes run in dependency order, whatever order they are listed in:
+//!
+//! ```toml
+//! [[node]]
+//! name = "cells"
+//! type = "voronoi"
+//! size = 256
+//! points = 60
+//!
+//! [[node]]
+//! name = "streaks"
+//! type = "directional_blur"
+//! input = "cells"
+//! direction = "cells"
+//! radius = 6
+//! save = "streaks.png"
+//! ```
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::{Deserialize, Deserializer};
+
+use crate::automata::{cellular_smooth, DEFAULT_BIRTH_LIMIT, DEFAULT_DEATH_LIMIT};
+use crate::blend::{blend, blend_masked, BlendMode};
+use crate::cracks::{generate_cracks, CrackParams};
+use crate::distance::distance_transform;
+use crate::error::CellsError;
+use crate::field::FieldBuffer;
+use crate::filter::{
+    directional_blur_field_with, directional_blur_field_with_length, gaussian_blur, unsharp_mask, BlurKernel,
+    BlurParams, BlurSampling, DirectionMapping, Normalization,
+};
+use crate::flow::{angle_offsets, signed_offsets, sobel, warp, SobelOutput};
+use crate::gabor::{generate_gabor, generate_gabor_along, GaborOrientation, GaborParams};
+use crate::geometry::Point;
+use crate::grain::{generate_marble, generate_wood, GrainParams};
+use crate::morphology::{Morphology, StructuringElement};
+use crate::noise::{
+    generate_blue_noise, generate_perlin_field, generate_white_noise, FbmParams, NoiseKind, NoiseStyle,
+};
+use crate::pattern::{
+    generate_bricks, generate_checker, generate_radial, generate_stripes, generate_truchet, BrickParams, TruchetStyle,
+};
+use crate::reaction::{
+    generate_reaction_diffusion_with, Chemical, ReactionDiffusionParams, ReactionPreset, Seeding,
+};
+use crate::sampling::PointDistribution;
+use crate::scratches::{generate_scratches, ScratchParams};
+use crate::smoothing::{bilateral_filter, kuwahara, median_filter};
+use crate::speckle::{generate_speckle, SpeckleParams, SplatShape};
+use crate::tone::{invert, posterize, posterize_dithered, Levels, Mask, ToneCurve};
+use crate::transform::{from_polar_with, offset_field, symmetrize, to_polar_with, RadialExtension, Symmetry, Transform};
+use crate::voronoi::{generate_voronoi_field, VoronoiParams};
+
+/// A graph of generator and filter nodes
+#[derive(Clone, Debug)]
+pub struct Pipeline {
+    nodes: Vec<Node>,
+}
+
+/// One generator or filter of a [`Pipeline`]
+#[derive(Clone, Debug)]
+pub struct Node {
+    /// The name other nodes refer to the output by
+    pub name: String,
+    /// The file the output is written to, if any
+    pub save: Option<PathBuf>,
+    /// What the node computes
+    pub operation: Operation,
+}
+
+/// A node of a pipeline run by [`Pipeline::execute_with`], as it finishes
+#[derive(Clone, Copy, Debug)]
+pub struct NodeRun<'a> {
+    /// The name of the node
+    pub name: &'a str,
+    /// How long computing or fetching the output took
+    pub elapsed: Duration,
+    /// Whether the output came from the cache
+    pub cached: bool,
+}
+
+/// The outputs of the last run of a pipeline, reused by the next run for
+/// every node whose operation and inputs did not change
+///
+/// A cache made with [`NodeCache::with_directory`] also keeps every output it
+/// sees as a file of raw `f32` samples, named after the hash of the node, so
+/// later processes reuse them too. The least recently used files are removed
+/// once the directory grows beyond its size limit. A directory that cannot be
+/// read or written only costs the reuse, never the run. Files of builds whose
+/// operations computed different outputs are never reused, as long as those
+/// changes bumped the cache version; clear the directory with
+/// [`NodeCache::clear_directory`] after running builds of your own changes.
+#[derive(Clone, Debug, Default)]
+pub struct NodeCache {
+    outputs: HashMap<u64, FieldBuffer>,
+    directory: Option<(PathBuf, u64)>,
+}
+
+impl NodeCache {
+    /// A cache that also keeps outputs in `directory`, created when needed,
+    /// removing the least recently used files beyond `max_bytes`
+    pub fn with_directory(directory: impl Into<PathBuf>, max_bytes: u64) -> NodeCache {
+        NodeCache {
+            outputs: HashMap::new(),
+            directory: Some((directory.into(), max_bytes)),
+        }
+    }
+
+    /// Remove the cached outputs from a cache directory, and the directory if
+    /// nothing else is left in it
+    pub fn clear_directory(directory: impl AsRef<Path>) -> io::Result<()> {
+        let directory = directory.as_ref();
+        for (path, _, _) in cache_files(directory)? {
+            fs::remove_file(path)?;
+        }
+        if fs::read_dir(directory)?.next().is_none() {
+            fs::remove_dir(directory)?;
+        }
+        Ok(())
+    }
+
+    /// The number of outputs cached in memory
+    pub fn len(&self) -> usize {
+        self.outputs.len()
+    }
+
+    /// Whether no outputs are cached in memory
+    pub fn is_empty(&self) -> bool {
+        self.outputs.is_empty()
+    }
+
+    /// The cached output of a node, from memory or else from the directory
+    fn get(&self, key: u64) -> Option<FieldBuffer> {
+        if let Some(output) = self.outputs.get(&key) {
+            return Some(output.clone());
+        }
+        let (directory, _) = self.directory.as_ref()?;
+        let path = directory.join(cache_file_name(key));
+        let output = read_cached_field(&path).ok()?;
+        // Reading counts as a use for the eviction order
+        let _ = File::options().append(true).open(&path).and_then(|file| file.set_modified(SystemTime::now()));
+        Some(output)
+    }
+
+    /// Keep a freshly computed output in the directory, if there is one
+    fn store(&self, key: u64, output: &FieldBuffer) {
+        if let Some((directory, max_bytes)) = &self.directory {
+            let path = directory.join(cache_file_name(key));
+            if fs::create_dir_all(directory).and_then(|()| write_cached_field(&path, output)).is_ok() {
+                let _ = evict(directory, *max_bytes);
+            }
+        }
+    }
+
+    /// Hold the directory to its size limit, which may have been lowered
+    /// since the outputs were written
+    fn trim(&self) {
+        if let Some((directory, max_bytes)) = &self.directory {
+            let _ = evict(directory, *max_bytes);
+        }
+    }
+}
+
+/// The version of the outputs of the operations, part of every node key
+///
+/// Bump it with every change that makes any operation compute different
+/// samples for the same parameters. The crate version is part of the key too,
+/// but it does not change between releases, so without a bump cache
+/// directories of earlier builds hand out their stale outputs until they are
+/// cleared.
+const CACHE_VERSION: u32 = 1;
+
+/// The magic bytes starting a cached output
+const CACHE_MAGIC: &[u8; 4] = b"CFLD";
+
+/// The extension of cached output files
+const CACHE_EXTENSION: &str = "field";
+
+/// The file name of a cached output
+fn cache_file_name(key: u64) -> String {
+    format!("{key:016x}.{CACHE_EXTENSION}")
+}
+
+/// Write a field as the magic bytes, its width and height and its samples,
+/// all little-endian, through a temporary file so readers never see half of it
+fn write_cached_field(path: &Path, field: &FieldBuffer) -> io::Result<()> {
+    let mut bytes = Vec::with_capacity(12 + field.as_slice().len() * 4);
+    bytes.extend_from_slice(CACHE_MAGIC);
+    bytes.extend_from_slice(&field.width().to_le_bytes());
+    bytes.extend_from_slice(&field.height().to_le_bytes());
+    bytes.extend(field.as_slice().iter().flat_map(|v| v.to_le_bytes()));
+    let partial = path.with_extension("partial");
+    fs::write(&partial, bytes)?;
+    fs::rename(partial, path)
+}
+
+/// Read a field written by [`write_cached_field`]
+fn read_cached_field(path: &Path) -> io::Result<FieldBuffer> {
+    let bytes = fs::read(path)?;
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "not a cached field");
+    let (header, samples) = bytes.split_at_checked(12).ok_or_else(invalid)?;
+    let word = |i: usize| u32::from_le_bytes(header[i..i + 4].try_into().expect("4 bytes"));
+    let (width, height) = (word(4), word(8));
+    if &header[..4] != CACHE_MAGIC || samples.len() as u64 != width as u64 * height as u64 * 4 {
+        return Err(invalid());
+    }
+    let samples = samples.chunks_exact(4).map(|v| f32::from_le_bytes(v.try_into().expect("4 bytes"))).collect();
+    Ok(FieldBuffer::from_vec(width, height, samples))
+}
+
+/// The cached outputs in a directory with their sizes and last uses
+fn cache_files(directory: &Path) -> io::Result<Vec<(PathBuf, u64, SystemTime)>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|extension| extension == CACHE_EXTENSION) {
+            let metadata = fs::metadata(&path)?;
+            files.push((path, metadata.len(), metadata.modified()?));
+        }
+    }
+    Ok(files)
+}
+
+/// Remove the least recently used outputs until the directory holds at most
+/// `max_bytes` of them
+fn evict(directory: &Path, max_bytes: u64) -> io::Result<()> {
+    let mut files = cache_files(directory)?;
+    files.sort_by_key(|&(_, _, used)| std::cmp::Reverse(used));
+    let mut total = 0;
+    for (path, size, _) in files {
+        total += size;
+        if total > max_bytes {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// The generator or filter a [`Node`] runs, selected by its `type` key
+///
+/// Inputs name other nodes; all other keys are optional and default to the
+/// values of the corresponding command line options. Enumerated values use
+/// the command line syntax, e.g. `kernel = "gaussian:1.5"`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Operation {
+    /// A Voronoi texture, normalized to 0-1, with the keys of the fields of
+    /// [`VoronoiParams`]; `points` and `relax` stand for `num_points` and
+    /// `relax_iterations`
+    Voronoi(VoronoiParams),
+    /// A fractal noise texture in roughly -1 to 1, see
+    /// [`generate_perlin_field`]
+    Perlin {
+        #[serde(default = "default_size")]
+        size: u32,
+        #[serde(default)]
+        seed: u64,
+        #[serde(default = "default_octaves")]
+        octaves: u32,
+        #[serde(default = "default_persistence")]
+        persistence: f64,
+        #[serde(default = "default_lacunarity")]
+        lacunarity: f64,
+        #[serde(default = "default_frequency")]
+        frequency: f64,
+        #[serde(default, deserialize_with = "parsed")]
+        kind: NoiseKind,
+        #[serde(default, deserialize_with = "parsed")]
+        style: NoiseStyle,
+    },
+    /// Uniform random values in 0-1, independent per pixel
+    WhiteNoise {
+        #[serde(default = "default_size")]
+        size: u32,
+        #[serde(default)]
+        seed: u64,
+    },
+    /// A blue noise mask for dithering, see [`generate_blue_noise`]; it is
+    /// slow to generate, so the default size is that of a typical mask tile
+    BlueNoise {
+        #[serde(default = "default_blue_noise_size")]
+        size: u32,
+        #[serde(default)]
+        seed: u64,
+    },
+    /// A brick wall heightmap, see [`generate_bricks`]
+    Bricks {
+        #[serde(default = "default_size")]
+        size: u32,
+        #[serde(default = "default_brick_rows")]
+        rows: u32,
+        #[serde(default = "default_brick_cols")]
+        cols: u32,
+        #[serde(default = "default_brick_offset")]
+        offset: f32,
+        #[serde(default = "default_mortar")]
+        mortar: f32,
+        #[serde(default = "default_bevel")]
+        bevel: f32,
+        #[serde(default = "default_variation")]
+        variation: f32,
+        #[serde(default)]
+        seed: u64,
+    },
+    /// Soft scratches with wraparound, see [`generate_scratches`]; lengths
+    /// and intensities are drawn from `[min, max]` ranges
+    Scratches {
+        #[serde(default = "default_size")]
+        size: u32,
+        #[serde(default = "default_scratch_count")]
+        count: usize,
+        #[serde(default = "default_scratch_length")]
+        length: [f32; 2],
+        #[serde(default)]
+        angle: f32,
+        #[serde(default = "default_scratch_spread")]
+        spread: f32,
+        #[serde(default = "default_scratch_width")]
+        width: f32,
+        #[serde(default = "default_scratch_intensity")]
+        intensity: [f32; 2],
+        #[serde(default)]
+        seed: u64,
+    },
+    /// Splats scattered with wraparound, see [`generate_speckle`]; radii and
+    /// intensities are drawn from `[min, max]` ranges
+    Speckle {
+        #[serde(default = "default_size")]
+        size: u32,
+        #[serde(default = "default_speckle_count")]
+        count: usize,
+        #[serde(default, deserialize_with = "parsed")]
+        distribution: PointDistribution,
+        #[serde(default = "default_splat_radius")]
+        radius: [f32; 2],
+        #[serde(default = "default_splat_intensity")]
+        intensity: [f32; 2],
+        #[serde(default, deserialize_with = "parsed")]
+        shape: SplatShape,
+        #[serde(default)]
+        seed: u64,
+    },
+    /// Wood grain, see [`generate_wood`]
+    Wood {
+        #[serde(default = "default_size")]
+        size: u32,
+        #[serde(default = "default_grain_count")]
+        count: u32,
+        #[serde(default = "default_turbulence")]
+        turbulence: f32,
+        #[serde(default = "default_turbulence_frequency")]
+        frequency: f64,
+        #[serde(default)]
+        seed: u64,
+    },
+    /// Marble veins, see [`generate_marble`]
+    Marble {
+        #[serde(default = "default_size")]
+        size: u32,
+        #[serde(default = "default_grain_count")]
+        count: u32,
+        #[serde(default = "default_turbulence")]
+        turbulence: f32,
+        #[serde(default = "default_turbulence_frequency")]
+        frequency: f64,
+        #[serde(default)]
+        seed: u64,
+    },
+    /// A crack mask along the cell borders, 1 in the cracks, see
+    /// [`generate_cracks`]
+    Cracks {
+        #[serde(default = "default_size")]
+        size: u32,
+        #[serde(default = "default_crack_points")]
+        points: usize,
+        #[serde(default = "default_crack_relax")]
+        relax: u32,
+        #[serde(default = "default_crack_width")]
+        width: f32,
+        #[serde(default = "default_fade_scale")]
+        fade_scale: f64,
+        #[serde(default = "default_fade")]
+        fade: f32,
+        #[serde(default = "default_removal")]
+        removal: f32,
+        #[serde(default)]
+        seed: u64,
+    },
+    /// Truchet tiles in a `grid` x `grid` of cells, see [`generate_truchet`]
+    Truchet {
+        #[serde(default = "default_size")]
+        size: u32,
+        #[serde(default = "default_truchet_grid")]
+        grid: u32,
+        #[serde(default = "default_line_width")]
+        line_width: f32,
+        #[serde(default, deserialize_with = "parsed")]
+        style: TruchetStyle,
+        #[serde(default)]
+        seed: u64,
+    },
+    /// A checkerboard of `cols` x `rows` squares
+    Checker {
+        #[serde(default = "default_size")]
+        size: u32,
+        #[serde(default = "default_squares")]
+        cols: u32,
+        #[serde(default = "default_squares")]
+        rows: u32,
+    },
+    /// Stripes at `angle` degrees, snapped so they tile, that are 1 for the
+    /// `duty` share of their period
+    Stripes {
+        #[serde(default = "default_size")]
+        size: u32,
+        #[serde(default = "default_squares")]
+        count: u32,
+        #[serde(default)]
+        angle: f32,
+        #[serde(default = "default_duty")]
+        duty: f32,
+    },
+    /// A radial gradient from 1 at `center` to 0 at the wrapped distance
+    /// `falloff`
+    Radial {
+        #[serde(default = "default_size")]
+        size: u32,
+        #[serde(default = "default_center")]
+        center: [f32; 2],
+        #[serde(default = "default_falloff")]
+        falloff: f32,
+    },
+    /// A Gray–Scott reaction–diffusion texture of raw concentrations, see
+    /// [`generate_reaction_diffusion_with`]; `feed` and `kill` override the
+    /// rates of the `preset`
+    ReactionDiffusion {
+        #[serde(default = "default_reaction_size")]
+        size: u32,
+        #[serde(default, deserialize_with = "parsed")]
+        preset: ReactionPreset,
+        feed: Option<f32>,
+        kill: Option<f32>,
+        #[serde(default = "default_reaction_steps")]
+        steps: u32,
+        #[serde(default)]
+        seed: u64,
+        #[serde(default, deserialize_with = "parsed")]
+        seeding: Seeding,
+        #[serde(default, deserialize_with = "parsed")]
+        chemical: Chemical,
+    },
+    /// Gabor noise normalized to 0-1, see [`generate_gabor`]; the kernels
+    /// follow the optional `along` field instead of the `orientation`
+    Gabor {
+        #[serde(default = "default_size")]
+        size: u32,
+        #[serde(default = "default_gabor_frequency")]
+        frequency: f32,
+        #[serde(default = "default_gabor_radius")]
+        radius: f32,
+        #[serde(default = "default_kernels")]
+        kernels: u32,
+        #[serde(default, deserialize_with = "parsed")]
+        orientation: GaborOrientation,
+        #[serde(default)]
+        spread: f32,
+        #[serde(default)]
+        seed: u64,
+        along: Option<String>,
+    },
+    /// A directional blur of `input` steered by `direction`, with streaks
+    /// scaled by the optional `length` map
+    DirectionalBlur {
+        input: String,
+        direction: String,
+        length: Option<String>,
+        #[serde(default = "default_radius")]
+        radius: i32,
+        #[serde(default, deserialize_with = "parsed")]
+        sampling: BlurSampling,
+        #[serde(default, deserialize_with = "parsed")]
+        kernel: BlurKernel,
+        #[serde(default, deserialize_with = "parsed")]
+        mapping: DirectionMapping,
+    },
+    /// A seamless Gaussian blur with the standard deviation in pixels
+    GaussianBlur { input: String, sigma: f32 },
+    /// A normalization such as `stretch` or `equalize`
+    Normalize {
+        input: String,
+        #[serde(default, deserialize_with = "parsed")]
+        method: Normalization,
+    },
+    /// Layer `b` blended onto `a`, limited to the bright parts of the
+    /// optional `mask`
+    Blend {
+        a: String,
+        b: String,
+        mask: Option<String>,
+        #[serde(default, deserialize_with = "parsed")]
+        mode: BlendMode,
+        #[serde(default = "default_opacity")]
+        opacity: f32,
+    },
+    /// Levels such as `"0.1,0.9,1.6,0,1"`
+    Levels {
+        input: String,
+        #[serde(deserialize_with = "parsed")]
+        levels: Levels,
+    },
+    /// A tone curve such as `"0:0,0.5:0.3,1:1"`
+    Curve {
+        input: String,
+        #[serde(deserialize_with = "parsed")]
+        points: ToneCurve,
+    },
+    /// A mask such as `"threshold:0.5"` or `"smoothstep:0.2,0.4"`
+    Mask {
+        input: String,
+        #[serde(deserialize_with = "parsed")]
+        mask: Mask,
+    },
+    /// The input with 0-1 values flipped
+    Invert { input: String },
+    /// The input thresholded at 0.5 and smoothed by a cave-style cellular
+    /// automaton, see [`cellular_smooth`]
+    CellularSmooth {
+        input: String,
+        #[serde(default = "default_iterations")]
+        iterations: u32,
+        #[serde(default = "default_birth_limit")]
+        birth: u32,
+        #[serde(default = "default_death_limit")]
+        death: u32,
+    },
+    /// The distance in pixels from every sample to the nearest one at or
+    /// above 0.5, divided by the optional `radius` and clamped to 1, see
+    /// [`distance_transform`]
+    DistanceTransform { input: String, radius: Option<f32> },
+    /// The largest sample under a structuring element such as `"disc:3"`,
+    /// see [`Morphology`]
+    Dilate {
+        input: String,
+        #[serde(deserialize_with = "parsed")]
+        element: StructuringElement,
+    },
+    /// The smallest sample under a structuring element
+    Erode {
+        input: String,
+        #[serde(deserialize_with = "parsed")]
+        element: StructuringElement,
+    },
+    /// Erosion followed by dilation
+    Open {
+        input: String,
+        #[serde(deserialize_with = "parsed")]
+        element: StructuringElement,
+    },
+    /// Dilation followed by erosion
+    Close {
+        input: String,
+        #[serde(deserialize_with = "parsed")]
+        element: StructuringElement,
+    },
+    /// An unsharp mask, see [`unsharp_mask`]
+    Sharpen {
+        input: String,
+        sigma: f32,
+        amount: f32,
+        #[serde(default)]
+        threshold: f32,
+    },
+    /// The median of the square window `radius` pixels either way, see
+    /// [`median_filter`]
+    Median { input: String, radius: u32 },
+    /// An edge-preserving blur, see [`bilateral_filter`]
+    Bilateral { input: String, sigma_spatial: f32, sigma_range: f32 },
+    /// Cells flattened into near-constant tone, see [`kuwahara`]
+    Kuwahara {
+        input: String,
+        radius: u32,
+        #[serde(default = "default_kuwahara_sectors")]
+        sectors: u32,
+    },
+    /// Sobel edge detection outputting `"magnitude"`, `"direction"` or
+    /// `"edge-direction"`, see [`sobel`]
+    Sobel {
+        input: String,
+        #[serde(default, deserialize_with = "parsed")]
+        output: SobelOutput,
+    },
+    /// The input scrolled right by `dx` and down by `dy` samples with
+    /// wraparound
+    Offset {
+        input: String,
+        #[serde(default)]
+        dx: i64,
+        #[serde(default)]
+        dy: i64,
+    },
+    /// The input displaced by `strength` pixels along the offset maps `dx`
+    /// and `dy`, where 0.5 is no offset (see [`signed_offsets`]), or along
+    /// the angles of an `angle` map, see [`warp`]
+    Warp {
+        input: String,
+        dx: Option<String>,
+        dy: Option<String>,
+        angle: Option<String>,
+        strength: f32,
+    },
+    /// A rotation or mirroring such as `"rotate90"` or `"flip-h"`; quarter
+    /// turns swap the width and height
+    Transform {
+        input: String,
+        #[serde(deserialize_with = "parsed")]
+        transform: Transform,
+    },
+    /// The input averaged with its mirrored or rotated copies, such as
+    /// `"mirror-xy"` for a 4-fold kaleidoscope
+    Symmetrize {
+        input: String,
+        #[serde(default, deserialize_with = "parsed")]
+        symmetry: Symmetry,
+    },
+    /// The input remapped to polar coordinates around `center`, angles
+    /// across and radii down, reading rays past its edges by `extension`;
+    /// see [`to_polar_with`]
+    ToPolar {
+        input: String,
+        #[serde(default = "default_center")]
+        center: [f32; 2],
+        #[serde(default, deserialize_with = "parsed")]
+        extension: RadialExtension,
+    },
+    /// A polar input around `center` remapped back to Cartesian
+    /// coordinates, see [`from_polar_with`]
+    FromPolar {
+        input: String,
+        #[serde(default = "default_center")]
+        center: [f32; 2],
+        #[serde(default, deserialize_with = "parsed")]
+        extension: RadialExtension,
+    },
+    /// The input reduced to a number of levels, optionally dithered
+    Posterize {
+        input: String,
+        levels: u32,
+        #[serde(default)]
+        dither: bool,
+    },
+}
+
+fn default_size() -> u32 {
+    VoronoiParams::default().size
+}
+
+fn default_octaves() -> u32 {
+    FbmParams::default().octaves
+}
+
+fn default_persistence() -> f64 {
+    FbmParams::default().persistence
+}
+
+fn default_lacunarity() -> f64 {
+    FbmParams::default().lacunarity
+}
+
+fn default_frequency() -> f64 {
+    FbmParams::default().frequency
+}
+
+fn default_blue_noise_size() -> u32 {
+    64
+}
+
+fn default_brick_rows() -> u32 {
+    BrickParams::default().rows
+}
+
+fn default_brick_cols() -> u32 {
+    BrickParams::default().cols
+}
+
+fn default_brick_offset() -> f32 {
+    BrickParams::default().offset
+}
+
+fn default_mortar() -> f32 {
+    BrickParams::default().mortar
+}
+
+fn default_bevel() -> f32 {
+    BrickParams::default().bevel
+}
+
+fn default_variation() -> f32 {
+    BrickParams::default().variation
+}
+
+fn default_scratch_count() -> usize {
+    ScratchParams::default().count
+}
+
+fn default_scratch_length() -> [f32; 2] {
+    let (min, max) = ScratchParams::default().length;
+    [min, max]
+}
+
+fn default_scratch_spread() -> f32 {
+    ScratchParams::default().spread
+}
+
+fn default_scratch_width() -> f32 {
+    ScratchParams::default().width
+}
+
+fn default_scratch_intensity() -> [f32; 2] {
+    let (min, max) = ScratchParams::default().intensity;
+    [min, max]
+}
+
+fn default_speckle_count() -> usize {
+    SpeckleParams::default().count
+}
+
+fn default_splat_radius() -> [f32; 2] {
+    let (min, max) = SpeckleParams::default().radius;
+    [min, max]
+}
+
+fn default_splat_intensity() -> [f32; 2] {
+    let (min, max) = SpeckleParams::default().intensity;
+    [min, max]
+}
+
+fn default_grain_count() -> u32 {
+    GrainParams::default().count
+}
+
+fn default_turbulence() -> f32 {
+    GrainParams::default().turbulence
+}
+
+fn default_turbulence_frequency() -> f64 {
+    GrainParams::default().frequency
+}
+
+fn default_crack_points() -> usize {
+    CrackParams::default().num_points
+}
+
+fn default_crack_relax() -> u32 {
+    CrackParams::default().relax_iterations
+}
+
+fn default_crack_width() -> f32 {
+    CrackParams::default().width
+}
+
+fn default_fade_scale() -> f64 {
+    CrackParams::default().fade_scale
+}
+
+fn default_fade() -> f32 {
+    CrackParams::default().fade
+}
+
+fn default_removal() -> f32 {
+    CrackParams::default().removal
+}
+
+fn default_truchet_grid() -> u32 {
+    16
+}
+
+fn default_line_width() -> f32 {
+    0.15
+}
+
+fn default_squares() -> u32 {
+    8
+}
+
+fn default_duty() -> f32 {
+    0.5
+}
+
+fn default_center() -> [f32; 2] {
+    [0.5, 0.5]
+}
+
+fn default_falloff() -> f32 {
+    0.5
+}
+
+fn default_reaction_size() -> u32 {
+    ReactionDiffusionParams::default().size
+}
+
+fn default_reaction_steps() -> u32 {
+    ReactionDiffusionParams::default().steps
+}
+
+fn default_gabor_frequency() -> f32 {
+    GaborParams::default().frequency
+}
+
+fn default_gabor_radius() -> f32 {
+    GaborParams::default().radius
+}
+
+fn default_kernels() -> u32 {
+    GaborParams::default().kernels
+}
+
+fn default_radius() -> i32 {
+    BlurParams::default().radius
+}
+
+fn default_opacity() -> f32 {
+    1.0
+}
+
+fn default_kuwahara_sectors() -> u32 {
+    4
+}
+
+fn default_iterations() -> u32 {
+    5
+}
+
+fn default_birth_limit() -> u32 {
+    DEFAULT_BIRTH_LIMIT
+}
+
+fn default_death_limit() -> u32 {
+    DEFAULT_DEATH_LIMIT
+}
+
+/// Deserialize a value from its command line syntax
+fn parsed<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    let s = String::deserialize(deserializer)?;
+    s.parse().map_err(serde::de::Error::custom)
+}
+
+/// The document layout, nodes are deserialized one by one to name the
+/// offending node in errors
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Document {
+    #[serde(default)]
+    node: Vec<toml::Table>,
+}
+
+impl Operation {
+    /// The names of the nodes this operation reads
+    pub fn inputs(&self) -> Vec<&str> {
+        match self {
+            Operation::Voronoi(_)
+            | Operation::Perlin { .. }
+            | Operation::WhiteNoise { .. }
+            | Operation::BlueNoise { .. }
+            | Operation::Bricks { .. }
+            | Operation::Scratches { .. }
+            | Operation::Speckle { .. }
+            | Operation::Wood { .. }
+            | Operation::Marble { .. }
+            | Operation::Cracks { .. }
+            | Operation::Truchet { .. }
+            | Operation::Checker { .. }
+            | Operation::Stripes { .. }
+            | Operation::Radial { .. }
+            | Operation::ReactionDiffusion { .. } => Vec::new(),
+            Operation::Gabor { along, .. } => along.iter().map(String::as_str).collect(),
+            Operation::DirectionalBlur { input, direction, length, .. } => {
+                let mut inputs = vec![input.as_str(), direction.as_str()];
+                inputs.extend(length.as_deref());
+                inputs
+            }
+            Operation::Blend { a, b, mask, .. } => {
+                let mut inputs = vec![a.as_str(), b.as_str()];
+                inputs.extend(mask.as_deref());
+                inputs
+            }
+            Operation::Warp { input, dx, dy, angle, .. } => {
+                let mut inputs = vec![input.as_str()];
+                inputs.extend(dx.iter().chain(dy).chain(angle).map(String::as_str));
+                inputs
+            }
+            Operation::GaussianBlur { input, .. }
+            | Operation::Normalize { input, .. }
+            | Operation::Levels { input, .. }
+            | Operation::Curve { input, .. }
+            | Operation::Mask { input, .. }
+            | Operation::Invert { input }
+            | Operation::CellularSmooth { input, .. }
+            | Operation::DistanceTransform { input, .. }
+            | Operation::Dilate { input, .. }
+            | Operation::Erode { input, .. }
+            | Operation::Open { input, .. }
+            | Operation::Close { input, .. }
+            | Operation::Sobel { input, .. }
+            | Operation::Sharpen { input, .. }
+            | Operation::Median { input, .. }
+            | Operation::Bilateral { input, .. }
+            | Operation::Kuwahara { input, .. }
+            | Operation::Offset { input, .. }
+            | Operation::Transform { input, .. }
+            | Operation::Symmetrize { input, .. }
+            | Operation::ToPolar { input, .. }
+            | Operation::FromPolar { input, .. }
+            | Operation::Posterize { input, .. } => vec![input.as_str()],
+        }
+    }
+
+    /// The width and height of the texture a generator makes, or `None` for
+    /// filters, whose output has the size of their inputs
+    pub fn size(&self) -> Option<u32> {
+        match self {
+            Operation::Voronoi(VoronoiParams { size, .. })
+            | Operation::Perlin { size, .. }
+            | Operation::WhiteNoise { size, .. }
+            | Operation::BlueNoise { size, .. }
+            | Operation::Bricks { size, .. }
+            | Operation::Scratches { size, .. }
+            | Operation::Speckle { size, .. }
+            | Operation::Wood { size, .. }
+            | Operation::Marble { size, .. }
+            | Operation::Cracks { size, .. }
+            | Operation::Truchet { size, .. }
+            | Operation::Checker { size, .. }
+            | Operation::Stripes { size, .. }
+            | Operation::Radial { size, .. }
+            | Operation::ReactionDiffusion { size, .. }
+            | Operation::Gabor { size, .. } => Some(*size),
+            _ => None,
+        }
+    }
+
+    /// Compute the output from the already computed `inputs`, in the order of
+    /// [`Operation::inputs`]
+    fn execute(&self, inputs: &[&FieldBuffer]) -> Result<FieldBuffer, String> {
+        let same_size = |names: &[&str]| match inputs.iter().position(|f| f.dimensions() != inputs[0].dimensions()) {
+            Some(i) => Err(format!("{} and {} differ in size", names[0], names[i])),
+            None => Ok(()),
+        };
+        match self {
+            Operation::Voronoi(params) => {
+                params.validate().map_err(|err| err.to_string())?;
+                Ok(generate_voronoi_field(params))
+            }
+            Operation::Perlin { size, seed, octaves, persistence, lacunarity, frequency, kind, style } => {
+                let params = FbmParams {
+                    octaves: *octaves,
+                    persistence: *persistence,
+                    lacunarity: *lacunarity,
+                    frequency: *frequency,
+                    kind: *kind,
+                    style: *style,
+                    seed: *seed,
+                };
+                params.validate().map_err(|err| err.to_string())?;
+                Ok(generate_perlin_field(*size, &params))
+            }
+            Operation::WhiteNoise { size, seed } => Ok(generate_white_noise(*size, *seed)),
+            Operation::BlueNoise { size, seed } => Ok(generate_blue_noise(*size, *seed)),
+            Operation::Bricks { size, rows, cols, offset, mortar, bevel, variation, seed } => {
+                let params = BrickParams {
+                    size: *size,
+                    rows: *rows,
+                    cols: *cols,
+                    offset: *offset,
+                    mortar: *mortar,
+                    bevel: *bevel,
+                    variation: *variation,
+                    seed: *seed,
+                };
+                params.validate().map_err(|err| err.to_string())?;
+                Ok(generate_bricks(&params))
+            }
+            Operation::Scratches { size, count, length: [l0, l1], angle, spread, width, intensity: [i0, i1], seed } => {
+                let params = ScratchParams {
+                    size: *size,
+                    count: *count,
+                    length: (*l0, *l1),
+                    angle: *angle,
+                    spread: *spread,
+                    width: *width,
+                    intensity: (*i0, *i1),
+                };
+                params.validate().map_err(|err| err.to_string())?;
+                Ok(generate_scratches(&params, *seed))
+            }
+            Operation::Speckle { size, count, distribution, radius: [r0, r1], intensity: [i0, i1], shape, seed } => {
+                let params = SpeckleParams {
+                    size: *size,
+                    count: *count,
+                    distribution: *distribution,
+                    radius: (*r0, *r1),
+                    intensity: (*i0, *i1),
+                    shape: *shape,
+                    seed: *seed,
+                };
+                params.validate().map_err(|err| err.to_string())?;
+                Ok(generate_speckle(&params))
+            }
+            Operation::Wood { size, count, turbulence, frequency, seed }
+            | Operation::Marble { size, count, turbulence, frequency, seed } => {
+                let params = GrainParams {
+                    size: *size,
+                    count: *count,
+                    turbulence: *turbulence,
+                    frequency: *frequency,
+                    seed: *seed,
+                };
+                params.validate().map_err(|err| err.to_string())?;
+                Ok(match self {
+                    Operation::Wood { .. } => generate_wood(&params),
+                    _ => generate_marble(&params),
+                })
+            }
+            Operation::Cracks { size, points, relax, width, fade_scale, fade, removal, seed } => {
+                let params = CrackParams {
+                    size: *size,
+                    num_points: *points,
+                    relax_iterations: *relax,
+                    width: *width,
+                    fade_scale: *fade_scale,
+                    fade: *fade,
+                    removal: *removal,
+                    seed: *seed,
+                };
+                params.validate().map_err(|err| err.to_string())?;
+                Ok(generate_cracks(&params))
+            }
+            Operation::Truchet { size, grid, line_width, style, seed } => {
+                if *grid == 0 {
+                    return Err("grid must be above 0".to_string());
+                }
+                if !(*line_width > 0.0 && *line_width <= 1.0) {
+                    return Err(format!("line_width must be above 0 and at most 1, got {line_width}"));
+                }
+                Ok(generate_truchet(*size, *grid, *line_width, *style, *seed))
+            }
+            Operation::Checker { size, cols, rows } => {
+                if *cols == 0 || *rows == 0 {
+                    return Err("cols and rows must be above 0".to_string());
+                }
+                Ok(generate_checker(*size, *cols, *rows))
+            }
+            Operation::Stripes { size, count, angle, duty } => Ok(generate_stripes(*size, *count, *angle, *duty)),
+            Operation::Radial { size, center: [x, y], falloff } => {
+                if !(falloff.is_finite() && *falloff > 0.0) {
+                    return Err(format!("falloff must be positive, got {falloff}"));
+                }
+                Ok(generate_radial(*size, Point { x: *x, y: *y }, *falloff))
+            }
+            Operation::ReactionDiffusion { size, preset, feed, kill, steps, seed, seeding, chemical } => {
+                let (preset_feed, preset_kill) = preset.rates();
+                let params = ReactionDiffusionParams {
+                    size: *size,
+                    feed: feed.unwrap_or(preset_feed),
+                    kill: kill.unwrap_or(preset_kill),
+                    steps: *steps,
+                    seeding: *seeding,
+                    chemical: *chemical,
+                    seed: *seed,
+                    ..Default::default()
+                };
+                params.validate().map_err(|err| err.to_string())?;
+                Ok(generate_reaction_diffusion_with(&params, |_| {}))
+            }
+            Operation::Gabor { size, frequency, radius, kernels, orientation, spread, seed, .. } => {
+                let params = GaborParams {
+                    size: *size,
+                    frequency: *frequency,
+                    radius: *radius,
+                    kernels: *kernels,
+                    orientation: *orientation,
+                    spread: *spread,
+                };
+                params.validate().map_err(|err| err.to_string())?;
+                Ok(match inputs {
+                    [along] => generate_gabor_along(&params, *seed, along),
+                    _ => generate_gabor(&params, *seed),
+                })
+            }
+            Operation::DirectionalBlur { radius, sampling, kernel, mapping, .. } => {
+                same_size(&["input", "direction", "length"])?;
+                if *radius < 0 {
+                    return Err(format!("radius must be at least 0, got {radius}"));
+                }
+                let params = BlurParams {
+                    radius: *radius,
+                    sampling: *sampling,
+                    kernel: *kernel,
+                    mapping: *mapping,
+                };
+                Ok(match inputs {
+                    [field, direction, length] => directional_blur_field_with_length(field, direction, length, &params),
+                    _ => directional_blur_field_with(inputs[0], inputs[1], &params),
+                })
+            }
+            Operation::GaussianBlur { sigma, .. } => {
+                if !(sigma.is_finite() && *sigma >= 0.0) {
+                    return Err(format!("sigma must be at least 0, got {sigma}"));
+                }
+                Ok(gaussian_blur(inputs[0], *sigma))
+            }
+            Operation::Normalize { method, .. } => Ok(method.apply(inputs[0])),
+            Operation::Blend { mode, opacity, .. } => {
+                same_size(&["a", "b", "mask"])?;
+                let blended = blend(inputs[0], inputs[1], *mode, *opacity).map_err(|err| err.to_string())?;
+                match inputs.get(2) {
+                    Some(mask) => blend_masked(inputs[0], &blended, mask).map_err(|err| err.to_string()),
+                    None => Ok(blended),
+                }
+            }
+            Operation::Levels { levels, .. } => Ok(levels.apply(inputs[0])),
+            Operation::Curve { points, .. } => Ok(points.apply(inputs[0])),
+            Operation::Mask { mask, .. } => Ok(mask.apply(inputs[0])),
+            Operation::Invert { .. } => Ok(invert(inputs[0])),
+            Operation::CellularSmooth { iterations, birth, death, .. } => {
+                Ok(cellular_smooth(inputs[0], *iterations, *birth, *death))
+            }
+            Operation::DistanceTransform { radius: None, .. } => Ok(distance_transform(inputs[0])),
+            Operation::DistanceTransform { radius: Some(radius), .. } => {
+                if !(radius.is_finite() && *radius > 0.0) {
+                    return Err(format!("radius must be positive, got {radius}"));
+                }
+                Ok(distance_transform(inputs[0]).map(|d| (d / radius).min(1.0)))
+            }
+            Operation::Dilate { element, .. } => Ok(Morphology::Dilate.apply(inputs[0], *element)),
+            Operation::Erode { element, .. } => Ok(Morphology::Erode.apply(inputs[0], *element)),
+            Operation::Open { element, .. } => Ok(Morphology::Open.apply(inputs[0], *element)),
+            Operation::Close { element, .. } => Ok(Morphology::Close.apply(inputs[0], *element)),
+            Operation::Sharpen { sigma, amount, threshold, .. } => {
+                for (name, value) in [("sigma", sigma), ("threshold", threshold)] {
+                    if !(value.is_finite() && *value >= 0.0) {
+                        return Err(format!("{name} must be at least 0, got {value}"));
+                    }
+                }
+                if !amount.is_finite() {
+                    return Err(format!("amount must be finite, got {amount}"));
+                }
+                Ok(unsharp_mask(inputs[0], *sigma, *amount, *threshold))
+            }
+            Operation::Median { radius, .. } => Ok(median_filter(inputs[0], *radius)),
+            Operation::Bilateral { sigma_spatial, sigma_range, .. } => {
+                if !(sigma_spatial.is_finite() && *sigma_spatial >= 0.0) {
+                    return Err(format!("sigma_spatial must be at least 0, got {sigma_spatial}"));
+                }
+                if !(sigma_range.is_finite() && *sigma_range > 0.0) {
+                    return Err(format!("sigma_range must be positive, got {sigma_range}"));
+                }
+                Ok(bilateral_filter(inputs[0], *sigma_spatial, *sigma_range))
+            }
+            Operation::Kuwahara { radius, sectors, .. } => match sectors {
+                4 | 8 => Ok(kuwahara(inputs[0], *radius, *sectors)),
+                _ => Err(format!("Kuwahara filters have 4 or 8 sectors, got {sectors}")),
+            },
+            Operation::Sobel { output, .. } => {
+                let (magnitude, direction) = sobel(inputs[0]);
+                Ok(match output {
+                    SobelOutput::Magnitude => magnitude,
+                    SobelOutput::Direction => direction,
+                    SobelOutput::EdgeDirection => direction.map(|d| (d + 0.25) % 1.0),
+                })
+            }
+            Operation::Offset { dx, dy, .. } => Ok(offset_field(inputs[0], *dx, *dy)),
+            Operation::Warp { dx, dy, angle, strength, .. } => {
+                if !strength.is_finite() {
+                    return Err(format!("strength must be finite, got {strength}"));
+                }
+                let (dx, dy) = match (dx, dy, angle, inputs) {
+                    (Some(_), Some(_), None, [_, dx, dy]) => {
+                        same_size(&["input", "dx", "dy"])?;
+                        (signed_offsets(dx), signed_offsets(dy))
+                    }
+                    (None, None, Some(_), [_, angle]) => {
+                        same_size(&["input", "angle"])?;
+                        angle_offsets(angle)
+                    }
+                    _ => return Err("warps need the offset maps `dx` and `dy`, or an `angle` map".to_string()),
+                };
+                Ok(warp(inputs[0], &dx, &dy, *strength))
+            }
+            Operation::Transform { transform, .. } => Ok(transform.apply(inputs[0])),
+            Operation::Symmetrize { symmetry, .. } => symmetrize(inputs[0], *symmetry).map_err(|err| err.to_string()),
+            Operation::ToPolar { center: [x, y], extension, .. }
+            | Operation::FromPolar { center: [x, y], extension, .. } => {
+                if !(x.is_finite() && y.is_finite()) {
+                    return Err(format!("center must be finite, got [{x}, {y}]"));
+                }
+                let center = Point { x: *x, y: *y };
+                Ok(match self {
+                    Operation::ToPolar { .. } => to_polar_with(inputs[0], center, *extension),
+                    _ => from_polar_with(inputs[0], center, *extension),
+                })
+            }
+            Operation::Posterize { levels, dither, .. } => match (*levels, dither) {
+                (0 | 1, _) => Err(format!("posterizing needs at least 2 levels, got {levels}")),
+                (levels, true) => Ok(posterize_dithered(inputs[0], levels)),
+                (levels, false) => Ok(posterize(inputs[0], levels)),
+            },
+        }
+    }
+}
+
+impl Pipeline {
+    /// Read a pipeline from a TOML document with one `[[node]]` table per node
+    ///
+    /// Every node needs a unique `name` and a `type`, one of the
+    /// [`Operation`] variants in snake case, and may set `save` to a file
+    /// name. The graph itself is checked by [`Pipeline::order`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::pipeline::Pipeline;
+    ///
+    /// let err = Pipeline::from_toml("[[node]]\nname = \"a\"\ntype = \"emboss\"").unwrap_err();
+    /// assert!(err.to_string().starts_with("node `a`: unknown variant `emboss`"));
+    /// ```
+    pub fn from_toml(s: &str) -> Result<Self, CellsError> {
+        let invalid = |node: Option<&str>, reason: String| CellsError::InvalidPipeline {
+            node: node.map(str::to_string),
+            reason,
+        };
+        let document: Document = toml::from_str(s).map_err(|err| invalid(None, err.to_string()))?;
+        let mut nodes: Vec<Node> = Vec::new();
+        for (i, mut table) in document.node.into_iter().enumerate() {
+            let name = match table.remove("name") {
+                Some(toml::Value::String(name)) => name,
+                _ => return Err(invalid(None, format!("node {} has no name", i + 1))),
+            };
+            if nodes.iter().any(|node| node.name == name) {
+                return Err(invalid(Some(&name), "is defined twice".to_string()));
+            }
+            let save = match table.remove("save") {
+                Some(toml::Value::String(path)) => Some(PathBuf::from(path)),
+                Some(_) => return Err(invalid(Some(&name), "save must be a file name".to_string())),
+                None => None,
+            };
+            let operation = table.try_into().map_err(|err: toml::de::Error| invalid(Some(&name), err.to_string()))?;
+            nodes.push(Node { name, save, operation });
+        }
+        Ok(Pipeline { nodes })
+    }
+
+    /// The nodes in the order they were declared
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// The indices of the nodes in an order where every node follows its
+    /// inputs
+    ///
+    /// # Returns
+    ///
+    /// The node indices, or [`CellsError::InvalidPipeline`] when a node reads
+    /// a missing node or the nodes form a cycle
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::pipeline::Pipeline;
+    ///
+    /// let pipeline = Pipeline::from_toml(
+    ///     r#"
+    /// [[node]]
+    /// name = "soft"
+    /// type = "gaussian_blur"
+    /// input = "hard"
+    /// sigma = 2.0
+    ///
+    /// [[node]]
+    /// name = "hard"
+    /// type = "invert"
+    /// input = "soft"
+    /// "#,
+    /// )
+    /// .unwrap();
+    /// let err = pipeline.order().unwrap_err();
+    /// assert_eq!(err.to_string(), "node `soft`: depends on itself through soft -> hard -> soft");
+    /// ```
+    pub fn order(&self) -> Result<Vec<usize>, CellsError> {
+        let index: HashMap<&str, usize> = self.nodes.iter().enumerate().map(|(i, n)| (n.name.as_str(), i)).collect();
+        let mut edges = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            let mut inputs = Vec::new();
+            for input in node.operation.inputs() {
+                match index.get(input) {
+                    Some(&i) => inputs.push(i),
+                    None => {
+                        return Err(CellsError::InvalidPipeline {
+                            node: Some(node.name.clone()),
+                            reason: format!("reads `{input}`, which is not a node"),
+                        });
+                    }
+                }
+            }
+            edges.push(inputs);
+        }
+
+        // Depth-first search, keeping the path to report cycles
+        #[derive(Clone, Copy, PartialEq)]
+        enum State {
+            Unvisited,
+            OnPath,
+            Done,
+        }
+        let mut state = vec![State::Unvisited; self.nodes.len()];
+        let mut order = Vec::with_capacity(self.nodes.len());
+        for start in 0..self.nodes.len() {
+            if state[start] != State::Unvisited {
+                continue;
+            }
+            let mut path = vec![(start, 0)];
+            state[start] = State::OnPath;
+            while let Some((node, next)) = path.last_mut() {
+                let node = *node;
+                match edges[node].get(*next) {
+                    Some(&input) => {
+                        *next += 1;
+                        match state[input] {
+                            State::Unvisited => {
+                                state[input] = State::OnPath;
+                                path.push((input, 0));
+                            }
+                            State::OnPath => {
+                                let first = path.iter().position(|&(n, _)| n == input).unwrap_or(0);
+                                let mut cycle: Vec<&str> =
+                                    path[first..].iter().map(|&(n, _)| self.nodes[n].name.as_str()).collect();
+                                cycle.push(&self.nodes[input].name);
+                                return Err(CellsError::InvalidPipeline {
+                                    node: Some(self.nodes[input].name.clone()),
+                                    reason: format!("depends on itself through {}", cycle.join(" -> ")),
+                                });
+                            }
+                            State::Done => {}
+                        }
+                    }
+                    None => {
+                        state[node] = State::Done;
+                        order.push(node);
+                        path.pop();
+                    }
+                }
+            }
+        }
+        Ok(order)
+    }
+
+    /// Run every node after its inputs
+    ///
+    /// # Returns
+    ///
+    /// The output of every node by name, or [`CellsError::InvalidPipeline`]
+    /// naming the node whose graph position or parameters are invalid
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::pipeline::Pipeline;
+    ///
+    /// let pipeline = Pipeline::from_toml(
+    ///     r#"
+    /// [[node]]
+    /// name = "cells"
+    /// type = "voronoi"
+    /// size = 32
+    /// points = 8
+    ///
+    /// [[node]]
+    /// name = "mask"
+    /// type = "mask"
+    /// input = "cells"
+    /// mask = "threshold:0.5"
+    /// "#,
+    /// )
+    /// .unwrap();
+    /// let outputs = pipeline.execute().unwrap();
+    /// assert!(outputs["mask"].as_slice().iter().all(|&v| v == 0.0 || v == 1.0));
+    /// ```
+    pub fn execute(&self) -> Result<HashMap<String, FieldBuffer>, CellsError> {
+        let outputs = self.execute_with(&mut NodeCache::default(), |_| ControlFlow::Continue(()))?;
+        Ok(outputs.expect("runs that are never stopped finish"))
+    }
+
+    /// Run every node like [`Pipeline::execute`], taking the outputs of
+    /// unchanged nodes from `cache` and reporting every node as it finishes
+    ///
+    /// A node is unchanged when its operation, with every parameter, and the
+    /// nodes it reads are. Returning [`ControlFlow::Break`] from `observe`
+    /// stops the run after that node. Afterwards `cache` holds the outputs of
+    /// this run, plus those of the last run when it was stopped.
+    ///
+    /// # Returns
+    ///
+    /// The output of every node by name, `None` if `observe` stopped the run,
+    /// or [`CellsError::InvalidPipeline`] like [`Pipeline::execute`]
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::ops::ControlFlow;
+    /// use cells::pipeline::{NodeCache, Pipeline};
+    ///
+    /// let pipeline = |sigma| {
+    ///     let cells = "[[node]]\nname = \"cells\"\ntype = \"voronoi\"\nsize = 32\n";
+    ///     let soft = "[[node]]\nname = \"soft\"\ntype = \"gaussian_blur\"\ninput = \"cells\"\n";
+    ///     Pipeline::from_toml(&format!("{cells}{soft}sigma = {sigma}")).unwrap()
+    /// };
+    /// let mut cache = NodeCache::default();
+    /// let mut cached = Vec::new();
+    /// pipeline(1.0).execute_with(&mut cache, |_| ControlFlow::Continue(())).unwrap();
+    /// pipeline(2.0).execute_with(&mut cache, |run| {
+    ///     cached.push((run.name.to_string(), run.cached));
+    ///     ControlFlow::Continue(())
+    /// }).unwrap();
+    /// assert_eq!(cached, [("cells".to_string(), true), ("soft".to_string(), false)]);
+    /// ```
+    pub fn execute_with(
+        &self,
+        cache: &mut NodeCache,
+        mut observe: impl FnMut(NodeRun<'_>) -> ControlFlow<()>,
+    ) -> Result<Option<HashMap<String, FieldBuffer>>, CellsError> {
+        let mut outputs: HashMap<String, FieldBuffer> = HashMap::new();
+        let mut keys: HashMap<&str, u64> = HashMap::new();
+        let mut used = HashMap::new();
+        for i in self.order()? {
+            let node = &self.nodes[i];
+            let names = node.operation.inputs();
+            let key = node_key(&node.operation, names.iter().map(|&input| keys[input]));
+            let started = Instant::now();
+            let (output, cached) = match cache.get(key) {
+                Some(output) => {
+                    log::debug!("node `{}`: reused the cached output", node.name);
+                    (output, true)
+                }
+                None => {
+                    log::debug!("node `{}`: {:?}", node.name, node.operation);
+                    let inputs: Vec<&FieldBuffer> = names.iter().map(|&input| &outputs[input]).collect();
+                    let output = node.operation.execute(&inputs).map_err(|reason| CellsError::InvalidPipeline {
+                        node: Some(node.name.clone()),
+                        reason,
+                    })?;
+                    cache.store(key, &output);
+                    (output, false)
+                }
+            };
+            let run = NodeRun { name: &node.name, elapsed: started.elapsed(), cached };
+            used.insert(key, output.clone());
+            keys.insert(&node.name, key);
+            outputs.insert(node.name.clone(), output);
+            if observe(run).is_break() {
+                cache.outputs.extend(used);
+                return Ok(None);
+            }
+        }
+        cache.outputs = used;
+        cache.trim();
+        Ok(Some(outputs))
+    }
+}
+
+/// A hash of an operation with all its parameters and of the keys of its
+/// inputs, FNV-1a over the debug representation, which prints every float
+/// exactly
+///
+/// The hash is the same in every process, so cache files stay valid between
+/// runs; the crate version and [`CACHE_VERSION`] are part of it, since the
+/// generators may change.
+fn node_key(operation: &Operation, inputs: impl IntoIterator<Item = u64>) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    let mut add = |bytes: &[u8]| {
+        for &byte in bytes {
+            hash = (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    };
+    add(env!("CARGO_PKG_VERSION").as_bytes());
+    add(&CACHE_VERSION.to_le_bytes());
+    add(format!("{operation:?}").as_bytes());
+    for input in inputs {
+        add(&input.to_le_bytes());
+    }
+    hash
+}
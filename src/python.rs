@@ -0,0 +1,138 @@
+//! Python bindings, returning the textures as numpy arrays.
+//!
+//! `maturin develop --release` builds this module with the `python` feature
+//! into `cells._native`, which the `cells` package in `python/` re-exports.
+//! The generators return square `float32` arrays of shape `(size, size)`, and
+//! the filters take any 2D array of numbers and return a `float32` array of
+//! the same shape. Every call validates its parameters with the GIL held,
+//! raising `cells.CellsError`, and then releases the GIL while the texture is
+//! computed, so the rayon threads of a call run alongside other Python
+//! threads.
+
+use numpy::{AllowTypeChange, PyArray1, PyArray2, PyArrayLike2, PyArrayMethods};
+use pyo3::create_exception;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::error::CellsError as Error;
+use crate::field::FieldBuffer;
+use crate::filter::{directional_blur_field, gaussian_blur as blur};
+use crate::noise::{generate_perlin_field, FbmParams};
+use crate::voronoi::{generate_voronoi_field, VoronoiParams};
+
+create_exception!(cells._native, CellsError, PyValueError, "A call the crate rejected, with the reason");
+
+/// Raise an error of the crate as a `CellsError`
+fn raise(err: impl ToString) -> PyErr {
+    CellsError::new_err(err.to_string())
+}
+
+/// Copy a 2D array of any numeric type into a field
+fn field_from(array: &PyArrayLike2<'_, f32, AllowTypeChange>) -> FieldBuffer {
+    let view = array.as_array();
+    let (height, width) = view.dim();
+    FieldBuffer::from_vec(width as u32, height as u32, view.iter().copied().collect())
+}
+
+/// Hand the samples of a field to Python as a `float32` array of shape
+/// `(height, width)`
+fn into_array(py: Python<'_>, field: FieldBuffer) -> PyResult<Bound<'_, PyArray2<f32>>> {
+    let (width, height) = field.dimensions();
+    PyArray1::from_vec(py, field.as_slice().to_vec()).reshape([height as usize, width as usize])
+}
+
+/// A tileable Voronoi texture of `points` cells
+///
+/// `feature` is one of f1, f2, f2-f1 or cell-value, and `metric` one of
+/// euclidean, manhattan, chebyshev or minkowski:<p>.
+#[pyfunction]
+#[pyo3(signature = (size, points, seed=0, *, feature="f1", metric="euclidean", invert=false))]
+fn voronoi<'py>(
+    py: Python<'py>,
+    size: u32,
+    points: usize,
+    seed: u64,
+    feature: &str,
+    metric: &str,
+    invert: bool,
+) -> PyResult<Bound<'py, PyArray2<f32>>> {
+    let params = VoronoiParams {
+        size,
+        num_points: points,
+        seed,
+        feature: feature.parse().map_err(raise)?,
+        metric: metric.parse().map_err(raise)?,
+        invert,
+        ..Default::default()
+    };
+    params.validate().map_err(raise)?;
+    into_array(py, py.detach(|| generate_voronoi_field(&params)))
+}
+
+/// Tileable fractal Perlin noise of `octaves` layers
+#[pyfunction]
+#[pyo3(signature = (size, octaves=6, seed=0, *, persistence=0.5, lacunarity=2.0, frequency=1.0))]
+fn fbm<'py>(
+    py: Python<'py>,
+    size: u32,
+    octaves: u32,
+    seed: u64,
+    persistence: f64,
+    lacunarity: f64,
+    frequency: f64,
+) -> PyResult<Bound<'py, PyArray2<f32>>> {
+    let params = FbmParams { octaves, persistence, lacunarity, frequency, seed, ..Default::default() };
+    if size == 0 {
+        return Err(raise(Error::InvalidParameter { name: "size", reason: "must be above 0".to_string() }));
+    }
+    params.validate().map_err(raise)?;
+    into_array(py, py.detach(|| generate_perlin_field(size, &params)))
+}
+
+/// Blur a 2D array with a Gaussian of standard deviation `sigma`, wrapping
+/// around the edges
+#[pyfunction]
+fn gaussian_blur<'py>(
+    py: Python<'py>,
+    field: PyArrayLike2<'py, f32, AllowTypeChange>,
+    sigma: f32,
+) -> PyResult<Bound<'py, PyArray2<f32>>> {
+    if !(sigma.is_finite() && sigma >= 0.0) {
+        let reason = format!("must be non-negative, got {sigma}");
+        return Err(raise(Error::InvalidParameter { name: "sigma", reason }));
+    }
+    let field = field_from(&field);
+    into_array(py, py.detach(|| blur(&field, sigma)))
+}
+
+/// Blur a 2D array along the directions of a second array of the same
+/// shape, whose values choose the blur angle
+#[pyfunction]
+fn directional_blur<'py>(
+    py: Python<'py>,
+    field: PyArrayLike2<'py, f32, AllowTypeChange>,
+    direction: PyArrayLike2<'py, f32, AllowTypeChange>,
+    radius: i32,
+) -> PyResult<Bound<'py, PyArray2<f32>>> {
+    let (field, direction) = (field_from(&field), field_from(&direction));
+    if direction.dimensions() != field.dimensions() {
+        let (expected, found) = (field.dimensions(), direction.dimensions());
+        return Err(raise(Error::DimensionMismatch { input: "direction", expected, found }));
+    }
+    if radius < 0 {
+        let reason = format!("must be non-negative, got {radius}");
+        return Err(raise(Error::InvalidParameter { name: "radius", reason }));
+    }
+    into_array(py, py.detach(|| directional_blur_field(&field, &direction, radius)))
+}
+
+/// The `cells._native` extension module
+#[pymodule]
+fn _native(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add("CellsError", module.py().get_type::<CellsError>())?;
+    module.add_function(wrap_pyfunction!(voronoi, module)?)?;
+    module.add_function(wrap_pyfunction!(fbm, module)?)?;
+    module.add_function(wrap_pyfunction!(gaussian_blur, module)?)?;
+    module.add_function(wrap_pyfunction!(directional_blur, module)?)?;
+    Ok(())
+}
@@ -0,0 +1,397 @@
+//! Gray–Scott reaction–diffusion textures.
+//!
+//! Two chemicals spread over a toroidal grid: `U` is fed in everywhere while
+//! `V` consumes it to reproduce and slowly dies off. Depending on the feed and
+//! kill rates the `V` concentration settles into spots, coral-like branches
+//! or labyrinths of worms. The Laplacian wraps around the edges, so every
+//! pattern tiles.
+
+use std::fmt;
+use std::str::FromStr;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+
+use crate::error::CellsError;
+use crate::field::FieldBuffer;
+use crate::noise::{generate_perlin_field, FbmParams};
+
+/// Weight of the 4 direct neighbors in the Laplacian
+const LAPLACIAN_ADJACENT: f32 = 0.2;
+
+/// Weight of the 4 diagonal neighbors in the Laplacian
+const LAPLACIAN_DIAGONAL: f32 = 0.05;
+
+/// Perlin values above this start with `V` when seeding from noise
+const NOISE_SEED_THRESHOLD: f32 = 0.6;
+
+/// Size of the first noise octave in pixels when seeding from noise
+const NOISE_BLOB_SIZE: u32 = 32;
+
+/// Feed and kill rates known to give distinct patterns
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReactionPreset {
+    /// Spots that keep dividing until they fill the texture
+    Mitosis,
+    /// Branching coral growth
+    #[default]
+    Coral,
+    /// A labyrinth of meandering worms
+    Worms,
+}
+
+impl ReactionPreset {
+    /// The `(feed, kill)` rates of the preset
+    pub fn rates(self) -> (f32, f32) {
+        match self {
+            ReactionPreset::Mitosis => (0.0367, 0.0649),
+            ReactionPreset::Coral => (0.0545, 0.062),
+            ReactionPreset::Worms => (0.078, 0.061),
+        }
+    }
+}
+
+impl fmt::Display for ReactionPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReactionPreset::Mitosis => write!(f, "mitosis"),
+            ReactionPreset::Coral => write!(f, "coral"),
+            ReactionPreset::Worms => write!(f, "worms"),
+        }
+    }
+}
+
+impl FromStr for ReactionPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mitosis" => Ok(ReactionPreset::Mitosis),
+            "coral" => Ok(ReactionPreset::Coral),
+            "worms" => Ok(ReactionPreset::Worms),
+            _ => Err(format!("unknown reaction preset `{s}`, expected mitosis, coral or worms")),
+        }
+    }
+}
+
+/// Where `V` is present when the simulation starts
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Seeding {
+    /// Randomly placed small squares
+    #[default]
+    Spots,
+    /// The bright blobs of a Perlin noise texture
+    Noise,
+}
+
+impl fmt::Display for Seeding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Seeding::Spots => write!(f, "spots"),
+            Seeding::Noise => write!(f, "noise"),
+        }
+    }
+}
+
+impl FromStr for Seeding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "spots" => Ok(Seeding::Spots),
+            "noise" => Ok(Seeding::Noise),
+            _ => Err(format!("unknown seeding `{s}`, expected spots or noise")),
+        }
+    }
+}
+
+/// The chemical whose concentration is returned
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Chemical {
+    /// The fed chemical, high where the pattern is absent
+    U,
+    /// The reproducing chemical forming the pattern
+    #[default]
+    V,
+}
+
+impl fmt::Display for Chemical {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Chemical::U => write!(f, "u"),
+            Chemical::V => write!(f, "v"),
+        }
+    }
+}
+
+impl FromStr for Chemical {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "u" => Ok(Chemical::U),
+            "v" => Ok(Chemical::V),
+            _ => Err(format!("unknown chemical `{s}`, expected u or v")),
+        }
+    }
+}
+
+/// Parameters of a Gray–Scott simulation
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReactionDiffusionParams {
+    /// Width and height of the grid in samples
+    pub size: u32,
+    /// Rate `U` is replenished at
+    pub feed: f32,
+    /// Rate `V` dies off at, on top of the feed rate
+    pub kill: f32,
+    /// Diffusion rate of `U`, at most 1
+    pub diffusion_u: f32,
+    /// Diffusion rate of `V`, at most 1
+    pub diffusion_v: f32,
+    /// Number of simulation steps; patterns need a few thousand to settle
+    pub steps: u32,
+    /// Where `V` starts
+    pub seeding: Seeding,
+    /// The chemical returned
+    pub chemical: Chemical,
+    /// Seed of the initial spots or noise
+    pub seed: u64,
+}
+
+impl Default for ReactionDiffusionParams {
+    fn default() -> Self {
+        let (feed, kill) = ReactionPreset::default().rates();
+        ReactionDiffusionParams {
+            size: 256,
+            feed,
+            kill,
+            diffusion_u: 1.0,
+            diffusion_v: 0.5,
+            steps: 5000,
+            seeding: Seeding::Spots,
+            chemical: Chemical::V,
+            seed: 0,
+        }
+    }
+}
+
+impl ReactionDiffusionParams {
+    /// Check that the simulation is stable and non-empty
+    ///
+    /// # Returns
+    ///
+    /// [`CellsError::InvalidParameter`] if the size is 0, or a rate is not
+    /// finite or outside 0-1; faster diffusion makes the explicit steps blow
+    /// up
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::reaction::ReactionDiffusionParams;
+    ///
+    /// assert!(ReactionDiffusionParams::default().validate().is_ok());
+    /// let err = ReactionDiffusionParams { diffusion_u: 1.5, ..Default::default() }.validate().unwrap_err();
+    /// assert_eq!(err.to_string(), "invalid diffusion_u: must be between 0 and 1, got 1.5");
+    /// ```
+    pub fn validate(&self) -> Result<(), CellsError> {
+        if self.size == 0 {
+            return Err(CellsError::InvalidParameter {
+                name: "size",
+                reason: "must be above 0".to_string(),
+            });
+        }
+        for (name, value) in [
+            ("feed", self.feed),
+            ("kill", self.kill),
+            ("diffusion_u", self.diffusion_u),
+            ("diffusion_v", self.diffusion_v),
+        ] {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(CellsError::InvalidParameter {
+                    name,
+                    reason: format!("must be between 0 and 1, got {value}"),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Generate a tileable reaction–diffusion texture
+///
+/// This runs [`generate_reaction_diffusion_with`] from random spots and
+/// returns the `V` concentration, roughly 0-0.5; normalize the result to use
+/// it as a texture.
+///
+/// # Arguments
+///
+/// * `size` - The width and height of the texture in pixels
+/// * `feed` - Rate `U` is replenished at, see [`ReactionPreset::rates`]
+/// * `kill` - Rate `V` dies off at
+/// * `steps` - Number of simulation steps
+/// * `seed` - Seed of the initial spots
+///
+/// # Returns
+///
+/// A `FieldBuffer` of the `V` concentration
+///
+/// # Panics
+///
+/// Panics if the parameters fail [`ReactionDiffusionParams::validate`].
+///
+/// # Example
+///
+/// ```rust
+/// use cells::reaction::{generate_reaction_diffusion, ReactionPreset};
+///
+/// let (feed, kill) = ReactionPreset::Coral.rates();
+/// let coral = generate_reaction_diffusion(32, feed, kill, 200, 1);
+/// assert_eq!(coral.dimensions(), (32, 32));
+/// assert_eq!(coral, generate_reaction_diffusion(32, feed, kill, 200, 1));
+/// ```
+pub fn generate_reaction_diffusion(size: u32, feed: f32, kill: f32, steps: u32, seed: u64) -> FieldBuffer {
+    let params = ReactionDiffusionParams {
+        size,
+        feed,
+        kill,
+        steps,
+        seed,
+        ..Default::default()
+    };
+    generate_reaction_diffusion_with(&params, |_| {})
+}
+
+/// Generate a tileable reaction–diffusion texture, reporting progress
+///
+/// Every step diffuses both chemicals with a 3x3 Laplacian that wraps around
+/// the edges and lets them react:
+///
+/// ```text
+/// u' = u + Du ∇²u - u v² + feed (1 - u)
+/// v' = v + Dv ∇²v + u v² - (feed + kill) v
+/// ```
+///
+/// The grid starts with `U` at 1 and `V` at 0, except where
+/// [`ReactionDiffusionParams::seeding`] places `V`. Rows are updated in
+/// parallel, and `progress` is called with the finished share of the steps
+/// after each one.
+///
+/// # Panics
+///
+/// Panics if the parameters fail [`ReactionDiffusionParams::validate`].
+///
+/// # Example
+///
+/// ```rust
+/// use cells::reaction::{generate_reaction_diffusion_with, Chemical, ReactionDiffusionParams, Seeding};
+///
+/// let params = ReactionDiffusionParams { size: 32, steps: 100, seeding: Seeding::Noise, ..Default::default() };
+/// let mut last = 0.0;
+/// let v = generate_reaction_diffusion_with(&params, |done| last = done);
+/// let u = generate_reaction_diffusion_with(&ReactionDiffusionParams { chemical: Chemical::U, ..params }, |_| {});
+/// assert_eq!(last, 1.0);
+/// assert!(v.as_slice().iter().zip(u.as_slice()).all(|(v, u)| (0.0..=1.0).contains(v) && (0.0..=1.0).contains(u)));
+/// ```
+pub fn generate_reaction_diffusion_with(params: &ReactionDiffusionParams, progress: impl FnMut(f32)) -> FieldBuffer {
+    if let Err(err) = params.validate() {
+        panic!("{err}");
+    }
+    let details = format_args!(
+        "{size}x{size}, {} steps of feed {} and kill {}",
+        params.steps,
+        params.feed,
+        params.kill,
+        size = params.size
+    );
+    crate::stage("reaction-diffusion", details, || simulate(params, progress))
+}
+
+/// Run the simulation of [`generate_reaction_diffusion_with`] on valid
+/// parameters
+fn simulate(params: &ReactionDiffusionParams, mut progress: impl FnMut(f32)) -> FieldBuffer {
+    let size = params.size as usize;
+    let (mut u, mut v) = initial_state(params);
+    let (mut next_u, mut next_v) = (vec![0.0; size * size], vec![0.0; size * size]);
+    let laplacian = |c: &[f32], row: usize, above: usize, below: usize, x: usize| {
+        let (left, right) = ((x + size - 1) % size, (x + 1) % size);
+        let adjacent = c[row + left] + c[row + right] + c[above + x] + c[below + x];
+        let diagonal = c[above + left] + c[above + right] + c[below + left] + c[below + right];
+        adjacent * LAPLACIAN_ADJACENT + diagonal * LAPLACIAN_DIAGONAL - c[row + x]
+    };
+    for step in 0..params.steps {
+        next_u
+            .par_chunks_mut(size)
+            .zip(next_v.par_chunks_mut(size))
+            .enumerate()
+            .for_each(|(y, (row_u, row_v))| {
+                let row = y * size;
+                let above = (y + size - 1) % size * size;
+                let below = (y + 1) % size * size;
+                for x in 0..size {
+                    let (cu, cv) = (u[row + x], v[row + x]);
+                    let reaction = cu * cv * cv;
+                    row_u[x] = cu + params.diffusion_u * laplacian(&u, row, above, below, x) - reaction
+                        + params.feed * (1.0 - cu);
+                    row_v[x] = cv + params.diffusion_v * laplacian(&v, row, above, below, x) + reaction
+                        - (params.feed + params.kill) * cv;
+                }
+            });
+        std::mem::swap(&mut u, &mut next_u);
+        std::mem::swap(&mut v, &mut next_v);
+        progress((step + 1) as f32 / params.steps as f32);
+    }
+    if params.steps == 0 {
+        progress(1.0);
+    }
+    let data = match params.chemical {
+        Chemical::U => u,
+        Chemical::V => v,
+    };
+    FieldBuffer::from_vec(params.size, params.size, data)
+}
+
+/// The `U` and `V` grids before the first step
+fn initial_state(params: &ReactionDiffusionParams) -> (Vec<f32>, Vec<f32>) {
+    let size = params.size as usize;
+    let mut u = vec![1.0; size * size];
+    let mut v = vec![0.0; size * size];
+    let mut seed_at = |x: usize, y: usize| {
+        u[y * size + x] = 0.5;
+        v[y * size + x] = 1.0;
+    };
+    match params.seeding {
+        Seeding::Spots => {
+            let mut rng = StdRng::seed_from_u64(params.seed);
+            let radius = (params.size as i64 / 64).max(2);
+            let wrap = |c: i64| c.rem_euclid(size as i64) as usize;
+            for _ in 0..(size * size / 1024).max(1) {
+                let (cx, cy): (i64, i64) = (rng.gen_range(0..size as i64), rng.gen_range(0..size as i64));
+                for y in cy - radius..=cy + radius {
+                    for x in cx - radius..=cx + radius {
+                        seed_at(wrap(x), wrap(y));
+                    }
+                }
+            }
+        }
+        Seeding::Noise => {
+            // Blobs keep their size in pixels, a few pattern widths across
+            let noise = generate_perlin_field(
+                params.size,
+                &FbmParams {
+                    octaves: 3,
+                    frequency: (params.size / NOISE_BLOB_SIZE).max(1) as f64,
+                    seed: params.seed,
+                    ..Default::default()
+                },
+            );
+            for (i, &value) in noise.as_slice().iter().enumerate() {
+                if value > NOISE_SEED_THRESHOLD {
+                    seed_at(i % size, i / size);
+                }
+            }
+        }
+    }
+    (u, v)
+}
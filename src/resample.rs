@@ -0,0 +1,172 @@
+//! Resampling fields to other sizes and building mip chains.
+
+use std::f32::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::field::FieldBuffer;
+
+/// Half-width of the Lanczos kernel in source samples at scale 1
+const LANCZOS_LOBES: f32 = 3.0;
+
+/// The filter weighting source samples when a field is resampled
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ResampleFilter {
+    /// The average of the source samples each output sample covers; halving
+    /// averages 2x2 blocks
+    #[default]
+    Box,
+    /// The windowed sinc with three lobes, keeping more detail than the box
+    /// filter at the cost of slight ringing at hard edges
+    Lanczos3,
+}
+
+impl ResampleFilter {
+    /// The wrapped source indices and weights of every output sample along
+    /// one axis, resampling `from` samples to `to`
+    fn taps(self, from: u32, to: u32) -> Vec<Vec<(i64, f32)>> {
+        let scale = from as f32 / to as f32;
+        (0..to)
+            .map(|i| {
+                let mut taps: Vec<(i64, f32)> = match self {
+                    ResampleFilter::Box => {
+                        let (start, end) = (i as f32 * scale, (i + 1) as f32 * scale);
+                        (start.floor() as i64..end.ceil() as i64)
+                            .map(|j| (j, end.min((j + 1) as f32) - start.max(j as f32)))
+                            .collect()
+                    }
+                    ResampleFilter::Lanczos3 => {
+                        // Downsampling stretches the kernel over the covered samples
+                        let width = scale.max(1.0);
+                        let center = (i as f32 + 0.5) * scale;
+                        let reach = LANCZOS_LOBES * width;
+                        ((center - reach - 0.5).floor() as i64..=(center + reach - 0.5).ceil() as i64)
+                            .map(|j| (j, lanczos((j as f32 + 0.5 - center) / width)))
+                            .collect()
+                    }
+                };
+                taps.retain(|&(_, weight)| weight != 0.0);
+                let total: f32 = taps.iter().map(|&(_, weight)| weight).sum();
+                for (_, weight) in &mut taps {
+                    *weight /= total;
+                }
+                taps
+            })
+            .collect()
+    }
+}
+
+/// The Lanczos kernel with [`LANCZOS_LOBES`] lobes
+///
+/// The kernel is exactly 0 at non-zero integers, so resampling to the same
+/// size returns the field unchanged.
+fn lanczos(x: f32) -> f32 {
+    let sinc = |x: f32| (PI * x).sin() / (PI * x);
+    if x == 0.0 {
+        1.0
+    } else if x.fract() == 0.0 || x.abs() >= LANCZOS_LOBES {
+        0.0
+    } else {
+        sinc(x) * sinc(x / LANCZOS_LOBES)
+    }
+}
+
+impl fmt::Display for ResampleFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResampleFilter::Box => write!(f, "box"),
+            ResampleFilter::Lanczos3 => write!(f, "lanczos3"),
+        }
+    }
+}
+
+impl FromStr for ResampleFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "box" => Ok(ResampleFilter::Box),
+            "lanczos3" => Ok(ResampleFilter::Lanczos3),
+            _ => Err(format!("unknown resample filter `{s}`, expected box or lanczos3")),
+        }
+    }
+}
+
+/// Resample a field to `width` x `height` samples
+///
+/// The filter reads across the edges of the field as if it repeated, so a
+/// tileable field stays tileable at any size. Resampling works on the float
+/// samples, so quantizing the result bands no more than quantizing the
+/// original. Lanczos filtering may overshoot 0-1 slightly next to hard edges.
+///
+/// # Arguments
+///
+/// * `field` - The field to resample
+/// * `width` - The width of the result in samples
+/// * `height` - The height of the result in samples
+/// * `filter` - How source samples are weighted
+///
+/// # Returns
+///
+/// A `FieldBuffer` of the requested size
+///
+/// # Panics
+///
+/// Panics if a dimension of `field` or of the result is 0.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::resample::{resample, ResampleFilter};
+/// use cells::FieldBuffer;
+///
+/// let field = FieldBuffer::from_vec(4, 2, vec![0.0, 1.0, 0.5, 0.5, 1.0, 0.0, 0.5, 0.5]);
+/// assert_eq!(resample(&field, 2, 1, ResampleFilter::Box).as_slice(), [0.5, 0.5]);
+/// assert_eq!(resample(&field, 4, 2, ResampleFilter::Lanczos3), field);
+/// ```
+pub fn resample(field: &FieldBuffer, width: u32, height: u32, filter: ResampleFilter) -> FieldBuffer {
+    let (source_width, source_height) = field.dimensions();
+    assert!(
+        source_width > 0 && source_height > 0 && width > 0 && height > 0,
+        "cannot resample {source_width}x{source_height} to {width}x{height}"
+    );
+    let columns = filter.taps(source_width, width);
+    let rows = filter.taps(source_height, height);
+    let horizontal = FieldBuffer::from_par_fn(width, source_height, |x, y| {
+        columns[x as usize]
+            .iter()
+            .map(|&(j, weight)| weight * field.get_wrapped(j, y as i64))
+            .sum()
+    });
+    FieldBuffer::from_par_fn(width, height, |x, y| {
+        rows[y as usize]
+            .iter()
+            .map(|&(j, weight)| weight * horizontal.get_wrapped(x as i64, j))
+            .sum()
+    })
+}
+
+/// The mip chain of a field, from the field itself down to a single sample
+///
+/// Every level halves the dimensions of the previous one, rounding down but
+/// never below 1, and is resampled from it with `filter`.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::resample::{mip_chain, ResampleFilter};
+/// use cells::FieldBuffer;
+///
+/// let field = FieldBuffer::from_par_fn(16, 4, |x, y| (x + y) as f32);
+/// let sizes: Vec<_> = mip_chain(&field, ResampleFilter::Box).iter().map(FieldBuffer::dimensions).collect();
+/// assert_eq!(sizes, [(16, 4), (8, 2), (4, 1), (2, 1), (1, 1)]);
+/// ```
+pub fn mip_chain(field: &FieldBuffer, filter: ResampleFilter) -> Vec<FieldBuffer> {
+    let mut chain = vec![field.clone()];
+    while let Some(last) = chain.last().filter(|level| level.width() > 1 || level.height() > 1) {
+        let (width, height) = last.dimensions();
+        let next = resample(last, (width / 2).max(1), (height / 2).max(1), filter);
+        chain.push(next);
+    }
+    chain
+}
@@ -0,0 +1,511 @@
+//! Point distributions on the wrapping unit square.
+
+use std::f32::consts::{SQRT_2, TAU};
+use std::f64::consts::TAU as TAU_F64;
+use std::fmt;
+use std::str::FromStr;
+
+use rand::Rng;
+use rayon::prelude::*;
+
+use crate::geometry::{toroidal_distance, DistanceMetric, Point};
+use crate::grid::PointGrid;
+
+/// Candidates tried around an active sample before it is retired
+const POISSON_ATTEMPTS: usize = 30;
+
+/// How the Voronoi cell centers are placed in the unit square
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PointDistribution {
+    /// Independent uniformly random points; clumps and gaps are common
+    #[default]
+    Uniform,
+    /// Points no closer than `min_dist` to each other, filling the square
+    /// until no more fit; the requested point count is ignored
+    PoissonDisk { min_dist: f32 },
+    /// One point per cell of a `cols` x `rows` lattice, displaced from the cell
+    /// center by up to `jitter` (0 to 1) times the cell size; the requested
+    /// point count is ignored
+    JitteredGrid { cols: u32, rows: u32, jitter: f32 },
+    /// One point per cell of a hexagonal `cols` x `rows` lattice whose odd
+    /// rows are shifted by half a column, displaced by up to `jitter` (0 to 1)
+    /// times the cell size; see [`check_hex_lattice`] for the valid counts.
+    /// The requested point count is ignored
+    HexGrid { cols: u32, rows: u32, jitter: f32 },
+}
+
+impl PointDistribution {
+    /// Generate points following this distribution
+    ///
+    /// # Arguments
+    ///
+    /// * `num_points` - The number of points for distributions with a fixed count
+    /// * `rng` - The random number generator driving the placement
+    ///
+    /// # Returns
+    ///
+    /// The generated points, all inside the unit square
+    pub fn generate(self, num_points: usize, rng: &mut impl Rng) -> Vec<Point> {
+        match self {
+            PointDistribution::Uniform => (0..num_points)
+                .map(|_| Point { x: rng.gen(), y: rng.gen() })
+                .collect(),
+            PointDistribution::PoissonDisk { min_dist } => poisson_disk(min_dist, rng),
+            PointDistribution::JitteredGrid { cols, rows, jitter } => jittered_grid(cols, rows, jitter, rng),
+            PointDistribution::HexGrid { cols, rows, jitter } => hex_grid(cols, rows, jitter, rng),
+        }
+    }
+
+    /// Check that the distribution can place points: a Poisson-disk distance
+    /// between 0.001 and 1, or a lattice of at least one column and row,
+    /// jittered by 0 to 1 and accepted by [`check_hex_lattice`] if hexagonal
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::sampling::PointDistribution;
+    ///
+    /// assert!(PointDistribution::JitteredGrid { cols: 8, rows: 8, jitter: 1.0 }.check().is_ok());
+    /// assert_eq!(
+    ///     PointDistribution::JitteredGrid { cols: 8, rows: 8, jitter: 1.5 }.check().unwrap_err(),
+    ///     "jitter must be between 0 and 1, got 1.5"
+    /// );
+    /// ```
+    pub fn check(self) -> Result<(), String> {
+        let (cols, rows, jitter) = match self {
+            PointDistribution::Uniform => return Ok(()),
+            PointDistribution::PoissonDisk { min_dist } if !(0.001..=1.0).contains(&min_dist) => {
+                return Err(format!("Poisson-disk distance must be between 0.001 and 1, got {min_dist}"));
+            }
+            PointDistribution::PoissonDisk { .. } => return Ok(()),
+            PointDistribution::JitteredGrid { cols, rows, jitter } => (cols, rows, jitter),
+            PointDistribution::HexGrid { cols, rows, jitter } => (cols, rows, jitter),
+        };
+        if cols == 0 || rows == 0 {
+            return Err(format!("a lattice needs at least one column and row, got {cols}x{rows}"));
+        }
+        if !(0.0..=1.0).contains(&jitter) {
+            return Err(format!("jitter must be between 0 and 1, got {jitter}"));
+        }
+        match self {
+            PointDistribution::HexGrid { .. } => check_hex_lattice(cols, rows),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for PointDistribution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PointDistribution::Uniform => f.write_str("uniform"),
+            PointDistribution::PoissonDisk { min_dist } => write!(f, "poisson:{min_dist}"),
+            PointDistribution::JitteredGrid { cols, rows, jitter } => write!(f, "grid:{cols}x{rows}:{jitter}"),
+            PointDistribution::HexGrid { cols, rows, jitter } => write!(f, "hex:{cols}x{rows}:{jitter}"),
+        }
+    }
+}
+
+impl FromStr for PointDistribution {
+    type Err = String;
+
+    /// Parse `uniform`, `poisson:<min-dist>`, `grid:<cols>x<rows>:<jitter>`
+    /// or `hex:<cols>x<rows>:<jitter>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "uniform" {
+            return Ok(PointDistribution::Uniform);
+        }
+        for (prefix, hex) in [("grid:", false), ("hex:", true)] {
+            let Some(spec) = s.strip_prefix(prefix) else {
+                continue;
+            };
+            let invalid = || format!("invalid lattice `{s}`, expected {prefix}<cols>x<rows>:<jitter>");
+            let (dims, jitter) = spec.split_once(':').ok_or_else(invalid)?;
+            let (cols, rows) = dims.split_once('x').ok_or_else(invalid)?;
+            let cols = cols.parse::<u32>().map_err(|_| invalid())?;
+            let rows = rows.parse::<u32>().map_err(|_| invalid())?;
+            let jitter = jitter.parse::<f32>().map_err(|_| invalid())?;
+            let distribution = if hex {
+                PointDistribution::HexGrid { cols, rows, jitter }
+            } else {
+                PointDistribution::JitteredGrid { cols, rows, jitter }
+            };
+            distribution.check()?;
+            return Ok(distribution);
+        }
+        let min_dist = s
+            .strip_prefix("poisson:")
+            .ok_or_else(|| {
+                format!(
+                    "unknown distribution `{s}`, expected uniform, poisson:<min-dist>, grid:<cols>x<rows>:<jitter> \
+                     or hex:<cols>x<rows>:<jitter>"
+                )
+            })?
+            .parse::<f32>()
+            .map_err(|err| format!("invalid Poisson-disk distance in `{s}`: {err}"))?;
+        let distribution = PointDistribution::PoissonDisk { min_dist };
+        distribution.check()?;
+        Ok(distribution)
+    }
+}
+
+serde_via_str!(PointDistribution);
+
+/// Generate one jittered point per cell of a regular lattice
+///
+/// Without jitter the points sit on the cell centers and produce perfectly
+/// regular cells; with jitter 1 each point lands anywhere in its cell. Jittered
+/// points leaving the unit square wrap around instead of being clamped, so the
+/// tile stays seamless.
+///
+/// # Arguments
+///
+/// * `cols` - The number of lattice columns
+/// * `rows` - The number of lattice rows
+/// * `jitter` - The displacement from the cell center, 0 to 1 times the cell size
+/// * `rng` - The random number generator driving the displacement
+///
+/// # Returns
+///
+/// `cols * rows` points, all inside the unit square
+///
+/// # Example
+///
+/// ```rust
+/// use cells::sampling::jittered_grid;
+/// use cells::Point;
+/// use rand::SeedableRng;
+///
+/// let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+/// let points = jittered_grid(4, 2, 0.0, &mut rng);
+/// assert_eq!(points.len(), 8);
+/// assert_eq!(points[0], Point { x: 0.125, y: 0.25 });
+/// assert_eq!(points[7], Point { x: 0.875, y: 0.75 });
+/// ```
+pub fn jittered_grid(cols: u32, rows: u32, jitter: f32, rng: &mut impl Rng) -> Vec<Point> {
+    let (cell_w, cell_h) = (1.0 / cols as f32, 1.0 / rows as f32);
+    (0..rows)
+        .flat_map(|row| (0..cols).map(move |col| (col, row)))
+        .map(|(col, row)| {
+            let offset_x: f32 = rng.gen_range(-0.5..=0.5);
+            let offset_y: f32 = rng.gen_range(-0.5..=0.5);
+            Point {
+                x: (col as f32 + 0.5 + jitter * offset_x) * cell_w,
+                y: (row as f32 + 0.5 + jitter * offset_y) * cell_h,
+            }
+            .wrapped()
+        })
+        .collect()
+}
+
+/// Check that a hexagonal lattice wraps around the unit square and makes
+/// hexagons close to regular
+///
+/// The half-column shift of the odd rows only repeats across the wrap-around
+/// edge with an even number of rows. Regular hexagons need `2 / √3` times as
+/// many rows as columns, which no whole counts match exactly; row counts
+/// more than a fifth off squash the hexagons visibly and are rejected.
+///
+/// # Returns
+///
+/// An error naming the nearest valid row count if the lattice is invalid
+///
+/// # Example
+///
+/// ```rust
+/// use cells::sampling::check_hex_lattice;
+///
+/// assert!(check_hex_lattice(12, 14).is_ok());
+/// assert_eq!(
+///     check_hex_lattice(12, 13).unwrap_err(),
+///     "a hex lattice needs an even number of rows to wrap, e.g. 12x14 instead of 12x13"
+/// );
+/// ```
+pub fn check_hex_lattice(cols: u32, rows: u32) -> Result<(), String> {
+    let regular_rows = cols as f32 * 2.0 / 3f32.sqrt();
+    let suggested = ((regular_rows / 2.0).round() as u32).max(1) * 2;
+    if !rows.is_multiple_of(2) {
+        return Err(format!(
+            "a hex lattice needs an even number of rows to wrap, e.g. {cols}x{suggested} instead of {cols}x{rows}"
+        ));
+    }
+    let squash = rows as f32 / regular_rows;
+    if !(0.8..=1.25).contains(&squash) {
+        return Err(format!(
+            "{cols}x{rows} hexagons are squashed by a factor {squash:.2}, use about {regular_rows:.1} rows \
+             for regular ones, e.g. {cols}x{suggested}"
+        ));
+    }
+    Ok(())
+}
+
+/// Generate one jittered point per cell of a hexagonal lattice
+///
+/// Rows are evenly spaced and the odd ones shifted by half a column, so
+/// without jitter every point has six neighbors and the Voronoi cells are
+/// identical hexagons, regular up to the squash of [`check_hex_lattice`].
+/// Jittered points wrap around the unit square like those of
+/// [`jittered_grid`].
+///
+/// # Panics
+///
+/// Panics if [`check_hex_lattice`] rejects the counts.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::sampling::hex_grid;
+/// use cells::Point;
+/// use rand::SeedableRng;
+///
+/// let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+/// let points = hex_grid(4, 4, 0.0, &mut rng);
+/// assert_eq!(points.len(), 16);
+/// assert_eq!(points[0], Point { x: 0.125, y: 0.125 });
+/// assert_eq!(points[4], Point { x: 0.25, y: 0.375 });
+/// ```
+pub fn hex_grid(cols: u32, rows: u32, jitter: f32, rng: &mut impl Rng) -> Vec<Point> {
+    if let Err(err) = check_hex_lattice(cols, rows) {
+        panic!("{err}");
+    }
+    let (cell_w, cell_h) = (1.0 / cols as f32, 1.0 / rows as f32);
+    (0..rows)
+        .flat_map(|row| (0..cols).map(move |col| (col, row)))
+        .map(|(col, row)| {
+            let offset_x: f32 = rng.gen_range(-0.5..=0.5);
+            let offset_y: f32 = rng.gen_range(-0.5..=0.5);
+            let shift = if row % 2 == 1 { 0.5 } else { 0.0 };
+            Point {
+                x: (col as f32 + 0.5 + shift + jitter * offset_x) * cell_w,
+                y: (row as f32 + 0.5 + jitter * offset_y) * cell_h,
+            }
+            .wrapped()
+        })
+        .collect()
+}
+
+/// Generate a toroidal Poisson-disk point set with Bridson's algorithm
+///
+/// Candidates are drawn in the annulus between `min_dist` and `2 * min_dist`
+/// around active samples and wrapped back into the unit square. The background
+/// grid used for rejection wraps at the edges as well, so points near the seam
+/// are tested against their neighbors on the opposite side and the tile border
+/// shows no clustering.
+///
+/// # Arguments
+///
+/// * `min_dist` - The minimum toroidal distance between any two points
+/// * `rng` - The random number generator driving the placement
+///
+/// # Returns
+///
+/// The generated points, all inside the unit square
+///
+/// # Panics
+///
+/// Panics if `min_dist` is not between 0.001 and 1.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::sampling::poisson_disk;
+/// use cells::toroidal_distance;
+/// use rand::SeedableRng;
+///
+/// let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+/// let points = poisson_disk(0.05, &mut rng);
+/// assert!(points.len() > 100);
+/// for (i, &a) in points.iter().enumerate() {
+///     for &b in &points[i + 1..] {
+///         assert!(toroidal_distance(a, b) >= 0.05);
+///     }
+/// }
+/// ```
+pub fn poisson_disk(min_dist: f32, rng: &mut impl Rng) -> Vec<Point> {
+    assert!(
+        (0.001..=1.0).contains(&min_dist),
+        "Poisson-disk distance must be between 0.001 and 1, got {min_dist}"
+    );
+    // Cells no larger than min_dist / sqrt(2) hold at most one point
+    let n = (SQRT_2 / min_dist).ceil() as usize;
+    let reach = (min_dist * n as f32).ceil() as isize;
+    let cell_of = |p: Point| {
+        let to_cell = |v: f32| ((v * n as f32) as usize).min(n - 1);
+        (to_cell(p.x), to_cell(p.y))
+    };
+
+    let mut grid: Vec<Option<usize>> = vec![None; n * n];
+    let mut points = Vec::new();
+    let mut active = Vec::new();
+
+    let fits = |grid: &[Option<usize>], points: &[Point], candidate: Point| {
+        let (cx, cy) = cell_of(candidate);
+        let far_enough = |c: usize| grid[c].is_none_or(|i| toroidal_distance(points[i], candidate) >= min_dist);
+        if 2 * reach + 1 >= n as isize {
+            return (0..n * n).all(far_enough);
+        }
+        (-reach..=reach).all(|dy| {
+            (-reach..=reach).all(|dx| {
+                let gx = (cx as isize + dx).rem_euclid(n as isize) as usize;
+                let gy = (cy as isize + dy).rem_euclid(n as isize) as usize;
+                far_enough(gy * n + gx)
+            })
+        })
+    };
+    let insert = |grid: &mut [Option<usize>], points: &mut Vec<Point>, active: &mut Vec<usize>, p: Point| {
+        let (cx, cy) = cell_of(p);
+        grid[cy * n + cx] = Some(points.len());
+        active.push(points.len());
+        points.push(p);
+    };
+
+    insert(&mut grid, &mut points, &mut active, Point { x: rng.gen(), y: rng.gen() });
+    while !active.is_empty() {
+        let slot = rng.gen_range(0..active.len());
+        let center = points[active[slot]];
+        let found = (0..POISSON_ATTEMPTS).find_map(|_| {
+            let radius = rng.gen_range(min_dist..2.0 * min_dist);
+            let angle = rng.gen_range(0.0..TAU);
+            let candidate = Point {
+                x: center.x + radius * angle.cos(),
+                y: center.y + radius * angle.sin(),
+            }
+            .wrapped();
+            fits(&grid, &points, candidate).then_some(candidate)
+        });
+        match found {
+            Some(candidate) => insert(&mut grid, &mut points, &mut active, candidate),
+            None => {
+                active.swap_remove(slot);
+            }
+        }
+    }
+    points
+}
+
+/// Even out cell sizes with Lloyd's algorithm on the torus
+///
+/// Every iteration assigns the samples of a regular grid over the unit square
+/// to their nearest point and moves each point to the centroid of its cell.
+/// Cells may straddle the tile edges, so the centroid is the circular mean of
+/// each axis: the sample coordinates are mapped onto a circle, averaged as
+/// vectors and mapped back with `atan2`. Points whose cell caught no samples
+/// stay where they are.
+///
+/// The result only depends on the input points, so relaxation is as
+/// deterministic as the point generation before it.
+///
+/// # Arguments
+///
+/// * `points` - The points to relax, all inside the unit square
+/// * `iterations` - The number of Lloyd iterations
+/// * `metric` - The metric assigning samples to points
+///
+/// # Returns
+///
+/// The relaxed points, in the same order as `points`
+///
+/// # Performance
+///
+/// Each iteration performs one nearest-point query per sample, with the
+/// sample grid scaled to about 256 samples per cell and at most 1024 x 1024
+/// samples.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::sampling::{lloyd_relax, PointDistribution};
+/// use cells::{toroidal_distance, DistanceMetric, Point};
+/// use rand::SeedableRng;
+///
+/// let nearest_neighbor_variance = |points: &[Point]| {
+///     let nn: Vec<f32> = points
+///         .iter()
+///         .enumerate()
+///         .map(|(i, &a)| {
+///             points.iter().enumerate().filter(|&(j, _)| j != i)
+///                 .map(|(_, &b)| toroidal_distance(a, b))
+///                 .fold(f32::INFINITY, f32::min)
+///         })
+///         .collect();
+///     let mean = nn.iter().sum::<f32>() / nn.len() as f32;
+///     nn.iter().map(|d| (d - mean).powi(2)).sum::<f32>() / nn.len() as f32
+/// };
+///
+/// let mut rng = rand::rngs::StdRng::seed_from_u64(5);
+/// let points = PointDistribution::Uniform.generate(100, &mut rng);
+/// let relaxed = lloyd_relax(&points, 3, DistanceMetric::Euclidean);
+/// assert!(nearest_neighbor_variance(&relaxed) < nearest_neighbor_variance(&points));
+/// ```
+pub fn lloyd_relax(points: &[Point], iterations: u32, metric: DistanceMetric) -> Vec<Point> {
+    lloyd_relax_with(points, iterations, metric, (1.0, 1.0))
+}
+
+/// Even out cell sizes with Lloyd's algorithm on the torus, assigning the
+/// samples to points by the distances of `metric` with the x and y offsets
+/// multiplied by `scale`, see [`PointGrid::with_scale`]
+///
+/// This is [`lloyd_relax`] for stretched cells, which a relaxation with
+/// unscaled distances would round off again.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::sampling::{lloyd_relax, lloyd_relax_with, PointDistribution};
+/// use cells::DistanceMetric;
+/// use rand::SeedableRng;
+///
+/// let points = PointDistribution::Uniform.generate(50, &mut rand::rngs::StdRng::seed_from_u64(2));
+/// let metric = DistanceMetric::Euclidean;
+/// assert_eq!(lloyd_relax_with(&points, 2, metric, (1.0, 1.0)), lloyd_relax(&points, 2, metric));
+/// assert_ne!(lloyd_relax_with(&points, 2, metric, (1.0, 3.0)), lloyd_relax(&points, 2, metric));
+/// ```
+pub fn lloyd_relax_with(points: &[Point], iterations: u32, metric: DistanceMetric, scale: (f32, f32)) -> Vec<Point> {
+    let samples = ((16.0 * (points.len() as f64).sqrt()).ceil() as u32).clamp(64, 1024);
+    let sample_at = |i: u32| (i as f32 + 0.5) / samples as f32;
+
+    let mut points = points.to_vec();
+    if iterations == 0 {
+        return points;
+    }
+    let details = format_args!("{iterations} iterations of {} points", points.len());
+    crate::stage("lloyd relaxation", details, || {
+        for _ in 0..iterations {
+            let grid = PointGrid::with_metric(&points, metric).with_scale(scale.0, scale.1);
+            let owners: Vec<Option<usize>> = (0..samples * samples)
+                .into_par_iter()
+                .map(|i| {
+                    let p = Point {
+                        x: sample_at(i % samples),
+                        y: sample_at(i / samples),
+                    };
+                    grid.nearest(p).map(|(index, _)| index)
+                })
+                .collect();
+
+            // Accumulate sequentially so the sums do not depend on thread count
+            let mut sums = vec![[0.0f64; 5]; points.len()];
+            for (i, owner) in owners.into_iter().enumerate() {
+                let Some(owner) = owner else { continue };
+                let ax = sample_at(i as u32 % samples) as f64 * TAU_F64;
+                let ay = sample_at(i as u32 / samples) as f64 * TAU_F64;
+                let sum = &mut sums[owner];
+                sum[0] += ax.cos();
+                sum[1] += ax.sin();
+                sum[2] += ay.cos();
+                sum[3] += ay.sin();
+                sum[4] += 1.0;
+            }
+            for (point, sum) in points.iter_mut().zip(&sums) {
+                if sum[4] > 0.0 {
+                    let circular_mean = |cos: f64, sin: f64| (sin.atan2(cos) / TAU_F64) as f32;
+                    *point = Point {
+                        x: circular_mean(sum[0], sum[1]),
+                        y: circular_mean(sum[2], sum[3]),
+                    }
+                    .wrapped();
+                }
+            }
+        }
+        points
+    })
+}
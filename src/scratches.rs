@@ -0,0 +1,248 @@
+//! Scratch masks: thin, soft line segments for wear and grunge.
+//!
+//! Segments may run over the edges of the texture and continue on the
+//! opposite side. They are bucketed into a grid of cells, together with the
+//! whole-texture shift that brings them into each cell, so every pixel only
+//! measures its distance to the few segments passing near it.
+
+use std::f32::consts::PI;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::error::CellsError;
+use crate::field::FieldBuffer;
+use crate::geometry::Point;
+
+/// Pixels per side of a grid cell, roughly
+const CELL_PIXELS: u32 = 8;
+
+/// Most grid cells across the texture
+const MAX_CELLS: u32 = 64;
+
+/// One scratch, a straight segment
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Scratch {
+    /// Start in the unit square
+    pub from: Point,
+    /// End, outside the unit square where the scratch runs over an edge
+    pub to: Point,
+    /// Value at the middle of the scratch
+    pub intensity: f32,
+}
+
+/// Parameters of a scratch mask
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScratchParams {
+    /// Width and height of the texture in pixels
+    pub size: u32,
+    /// Number of scratches
+    pub count: usize,
+    /// Inclusive range of the scratch lengths as fractions of the texture size
+    pub length: (f32, f32),
+    /// Preferred direction of the scratches in degrees, counterclockwise from
+    /// the x axis
+    pub angle: f32,
+    /// Deviation of the scratches from `angle`, up to this many degrees either
+    /// way; 90 or more gives all directions
+    pub spread: f32,
+    /// Width of the scratches as a fraction of the texture size
+    pub width: f32,
+    /// Inclusive range of the scratch intensities
+    pub intensity: (f32, f32),
+}
+
+impl Default for ScratchParams {
+    fn default() -> Self {
+        ScratchParams {
+            size: 512,
+            count: 300,
+            length: (0.02, 0.15),
+            angle: 0.0,
+            spread: 90.0,
+            width: 0.002,
+            intensity: (0.3, 1.0),
+        }
+    }
+}
+
+impl ScratchParams {
+    /// Check that the ranges are ordered and the scratches fit the texture
+    ///
+    /// # Returns
+    ///
+    /// [`CellsError::InvalidParameter`] if the size is 0, a range is not
+    /// finite or its minimum exceeds its maximum, the lengths are outside
+    /// (0, 1], the angle or spread is not finite, the spread is negative, or
+    /// the width is not positive and finite
+    pub fn validate(&self) -> Result<(), CellsError> {
+        let invalid = |name, reason: String| Err(CellsError::InvalidParameter { name, reason });
+        if self.size == 0 {
+            return invalid("size", "must be above 0".to_string());
+        }
+        for (name, (min, max)) in [("length", self.length), ("intensity", self.intensity)] {
+            if !(min.is_finite() && max.is_finite()) {
+                return invalid(name, format!("must be finite, got {min} to {max}"));
+            }
+            if min > max {
+                return invalid(name, format!("the minimum {min} exceeds the maximum {max}"));
+            }
+        }
+        let (min, max) = self.length;
+        if !(min > 0.0 && max <= 1.0) {
+            return invalid("length", format!("must be above 0 and at most 1, got {min} to {max}"));
+        }
+        if !self.angle.is_finite() {
+            return invalid("angle", format!("must be finite, got {}", self.angle));
+        }
+        if !(self.spread.is_finite() && self.spread >= 0.0) {
+            return invalid("spread", format!("must be at least 0 and finite, got {}", self.spread));
+        }
+        if !(self.width.is_finite() && self.width > 0.0) {
+            return invalid("width", format!("must be positive and finite, got {}", self.width));
+        }
+        Ok(())
+    }
+}
+
+/// Generate a tileable scratch mask
+///
+/// Scatters `params.count` scratches with lengths and intensities drawn
+/// uniformly from their ranges and directions within `params.spread` of
+/// `params.angle`, and draws them with [`render_scratches`].
+///
+/// # Arguments
+///
+/// * `params` - The scratches and their look
+/// * `seed` - Seed of the positions, lengths, directions and intensities
+///
+/// # Returns
+///
+/// A `FieldBuffer` with values from 0 to the largest intensity
+///
+/// # Panics
+///
+/// Panics if the parameters fail [`ScratchParams::validate`].
+///
+/// # Example
+///
+/// ```rust
+/// use cells::scratches::{generate_scratches, ScratchParams};
+/// use cells::tiling::{assert_tileable, interior_discontinuity};
+///
+/// let params = ScratchParams { size: 64, count: 40, width: 0.02, ..Default::default() };
+/// let scratches = generate_scratches(&params, 5);
+/// assert_eq!(scratches, generate_scratches(&params, 5));
+/// assert_tileable(&scratches, interior_discontinuity(&scratches));
+/// ```
+pub fn generate_scratches(params: &ScratchParams, seed: u64) -> FieldBuffer {
+    if let Err(err) = params.validate() {
+        panic!("{err}");
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+    let spread = params.spread.min(90.0);
+    let scratches: Vec<Scratch> = (0..params.count)
+        .map(|_| {
+            let from = Point { x: rng.gen(), y: rng.gen() };
+            let length = rng.gen_range(params.length.0..=params.length.1);
+            let angle = params.angle + spread * rng.gen_range(-1.0..=1.0);
+            let intensity = rng.gen_range(params.intensity.0..=params.intensity.1);
+            let (sin, cos) = angle.to_radians().sin_cos();
+            // The y axis points down, so counterclockwise angles go up
+            let to = Point { x: from.x + length * cos, y: from.y - length * sin };
+            Scratch { from, to, intensity }
+        })
+        .collect();
+    render_scratches(params.size, &scratches, params.width)
+}
+
+/// Draw scratches of `width` into a `size` x `size` field with wraparound
+///
+/// Across its width every scratch falls off smoothly from its intensity in
+/// the middle to 0 at its sides, and where scratches cross the brighter one
+/// wins. Scratches narrower than two pixels are drawn two pixels wide and
+/// proportionally fainter, so they do not break up into dots.
+///
+/// # Panics
+///
+/// Panics if `width` is not positive and finite.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::scratches::{render_scratches, Scratch};
+/// use cells::Point;
+///
+/// // A horizontal scratch over the right edge continues on the left
+/// let scratch = Scratch { from: Point { x: 0.75, y: 0.5 }, to: Point { x: 1.25, y: 0.5 }, intensity: 1.0 };
+/// let field = render_scratches(16, &[scratch], 0.25);
+/// assert_eq!((field.get(14, 8), field.get(2, 8)), (field.get(13, 8), field.get(1, 8)));
+/// assert!(field.get(2, 8) > 0.5);
+/// assert_eq!(field.get(8, 8), 0.0);
+/// ```
+pub fn render_scratches(size: u32, scratches: &[Scratch], width: f32) -> FieldBuffer {
+    assert!(width.is_finite() && width > 0.0, "the scratch width must be positive, got {width}");
+    let scale = size as f32;
+    let half_width = (width / 2.0).max(1.0 / scale);
+    let fade = width / 2.0 / half_width;
+    let cells = (size / CELL_PIXELS).clamp(1, MAX_CELLS) as i64;
+    let cell_size = 1.0 / cells as f32;
+
+    // Every grid cell lists the scratches reaching into it, with the shift
+    // that moves them next to the cell
+    let mut grid: Vec<Vec<(usize, Point)>> = vec![Vec::new(); (cells * cells) as usize];
+    for (index, scratch) in scratches.iter().enumerate() {
+        let reach = half_width + cell_size / 2.0 * 2f32.sqrt();
+        let cell_range = |a: f32, b: f32| {
+            let low = ((a.min(b) - reach) / cell_size).floor() as i64;
+            let high = ((a.max(b) + reach) / cell_size).floor() as i64;
+            low..=high
+        };
+        for cy in cell_range(scratch.from.y, scratch.to.y) {
+            for cx in cell_range(scratch.from.x, scratch.to.x) {
+                let center = Point {
+                    x: (cx as f32 + 0.5) * cell_size,
+                    y: (cy as f32 + 0.5) * cell_size,
+                };
+                if segment_distance(center, scratch.from, scratch.to) > reach {
+                    continue;
+                }
+                let shift = Point {
+                    x: cx.div_euclid(cells) as f32,
+                    y: cy.div_euclid(cells) as f32,
+                };
+                grid[(cy.rem_euclid(cells) * cells + cx.rem_euclid(cells)) as usize].push((index, shift));
+            }
+        }
+    }
+
+    FieldBuffer::from_par_fn(size, size, |x, y| {
+        let p = Point { x: (x as f32 + 0.5) / scale, y: (y as f32 + 0.5) / scale };
+        let cell = |c: f32| ((c / cell_size) as i64).min(cells - 1);
+        let mut value: f32 = 0.0;
+        for &(index, shift) in &grid[(cell(p.y) * cells + cell(p.x)) as usize] {
+            let scratch = &scratches[index];
+            let shifted = Point { x: p.x + shift.x, y: p.y + shift.y };
+            let t = segment_distance(shifted, scratch.from, scratch.to) / half_width;
+            if t < 1.0 {
+                let profile = 0.5 + 0.5 * (PI * t).cos();
+                value = value.max(scratch.intensity * fade * profile);
+            }
+        }
+        value
+    })
+}
+
+/// The Euclidean distance from `p` to the segment from `a` to `b`
+fn segment_distance(p: Point, a: Point, b: Point) -> f32 {
+    let (abx, aby) = (b.x - a.x, b.y - a.y);
+    let (apx, apy) = (p.x - a.x, p.y - a.y);
+    let length_squared = abx * abx + aby * aby;
+    let t = if length_squared > 0.0 {
+        ((apx * abx + apy * aby) / length_squared).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let (dx, dy) = (apx - t * abx, apy - t * aby);
+    (dx * dx + dy * dy).sqrt()
+}
@@ -0,0 +1,447 @@
+//! A small HTTP server previewing generators with parameters from the query.
+//!
+//! `GET /voronoi?size=512&points=300&seed=42&feature=f2-f1` answers with the
+//! texture as a PNG. The path names a generator of a
+//! [`Pipeline`](crate::pipeline::Pipeline) node, such as `voronoi`, `perlin`
+//! or `bricks`, and the query sets its keys with the same names and syntax,
+//! so a preview turns into a pipeline node by copying its parameters.
+//!
+//! Invalid parameters are answered with `400 Bad Request` and the reason as
+//! plain text, and textures larger than the maximum size of the server or
+//! needing more than a fixed amount of work, such as too many octaves, points
+//! or steps, are rejected before they are generated. A bounded number of
+//! clients is answered at a time, and clients that do not send their request
+//! in time are dropped. The most recently requested textures
+//! stay in memory, keyed on their parameters in any order, so switching back
+//! and forth between parameter sets in a browser costs nothing. This module
+//! needs the `serve` feature, enabled by default.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use image::ImageFormat;
+
+use crate::noise::FbmParams;
+use crate::pipeline::{Operation, Pipeline};
+use crate::sampling::PointDistribution;
+use crate::voronoi::VoronoiParams;
+
+/// The longest request head read from a client, in bytes
+const MAX_HEAD_BYTES: u64 = 16 * 1024;
+
+/// The most work a preview may take, in units of one reaction–diffusion step
+/// of one pixel; the default reaction–diffusion texture takes a third of it
+const MAX_WORK: f64 = 1e9;
+
+/// The work of one sample of one noise octave
+const NOISE_SAMPLE: f64 = 5.0;
+
+/// The work of finding the nearest points to one pixel
+const NEAREST_SEARCH: f64 = 8.0;
+
+/// The work of placing and indexing one point
+const POINT: f64 = 12.0;
+
+/// The number of clients answered at a time unless set otherwise
+const DEFAULT_CONNECTIONS: usize = 32;
+
+/// How long a client has to send its request unless set otherwise
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The name of the single node the requests are turned into
+const NODE: &str = "preview";
+
+/// An HTTP response of the server
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Response {
+    /// The HTTP status code
+    pub status: u16,
+    /// The MIME type of the body
+    pub content_type: &'static str,
+    /// The PNG or the message, shared with the cache
+    pub body: Arc<Vec<u8>>,
+}
+
+impl Response {
+    /// A plain text response, for errors and usage
+    fn text(status: u16, message: impl Into<String>) -> Response {
+        Response {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: Arc::new(message.into().into_bytes()),
+        }
+    }
+
+    /// The reason phrase of the status code
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            405 => "Method Not Allowed",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+/// A preview server generating textures on request and caching the recent
+/// ones
+///
+/// # Example
+///
+/// ```rust
+/// use cells::serve::PreviewServer;
+///
+/// let server = PreviewServer::new(256, 8);
+/// let png = server.respond("/voronoi?size=64&points=20&feature=f2-f1");
+/// assert_eq!((png.status, png.content_type), (200, "image/png"));
+/// assert_eq!(server.respond("/voronoi?size=1024").status, 400);
+/// assert!(String::from_utf8_lossy(&server.respond("/perlin?size=64&octaves=0").body).contains("octave"));
+/// ```
+#[derive(Debug)]
+pub struct PreviewServer {
+    max_size: u32,
+    capacity: usize,
+    cache: Mutex<VecDeque<(String, Arc<Vec<u8>>)>>,
+    connections: usize,
+    timeout: Duration,
+}
+
+impl PreviewServer {
+    /// A server generating textures of at most `max_size` pixels across and
+    /// keeping the `cache_entries` most recently requested ones
+    pub fn new(max_size: u32, cache_entries: usize) -> PreviewServer {
+        PreviewServer {
+            max_size,
+            capacity: cache_entries,
+            cache: Mutex::new(VecDeque::with_capacity(cache_entries)),
+            connections: DEFAULT_CONNECTIONS,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// The server answering at most `connections` clients at a time, at
+    /// least one, and dropping clients that have not sent their request
+    /// after `timeout`; by default 32 clients and 10 seconds
+    pub fn with_connections(mut self, connections: usize, timeout: Duration) -> PreviewServer {
+        self.connections = connections.max(1);
+        self.timeout = timeout;
+        self
+    }
+
+    /// Answer a request for `target`, the path and query of the URL
+    pub fn respond(&self, target: &str) -> Response {
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let generator = path.trim_matches('/');
+        if generator.is_empty() {
+            return Response::text(200, usage(self.max_size));
+        }
+        let parameters = match parse_query(query) {
+            Ok(parameters) => parameters,
+            Err(reason) => return Response::text(400, reason),
+        };
+        let key = format!("{generator}?{}", canonical_query(&parameters));
+        if let Some(png) = self.cached(&key) {
+            return Response { status: 200, content_type: "image/png", body: png };
+        }
+        match self.generate(generator, parameters) {
+            Ok(png) => {
+                let png = Arc::new(png);
+                self.insert(key, png.clone());
+                Response { status: 200, content_type: "image/png", body: png }
+            }
+            Err(reason) => Response::text(400, reason),
+        }
+    }
+
+    /// Answer the clients of `listener` until accepting fails, one thread per
+    /// connection
+    ///
+    /// Once the maximum number of clients is being answered, further clients
+    /// wait in the backlog of the listener until one of them is done.
+    pub fn serve(self: Arc<Self>, listener: TcpListener) -> io::Result<()> {
+        let slots = Arc::new(Slots { free: Mutex::new(self.connections), freed: Condvar::new() });
+        loop {
+            let slot = slots.take();
+            let (server, (stream, _)) = (self.clone(), listener.accept()?);
+            thread::spawn(move || {
+                let _slot = slot;
+                // A client hanging up early is its own business
+                let _ = server.answer(stream);
+            });
+        }
+    }
+
+    /// Read one request from a client and write the response
+    fn answer(&self, stream: TcpStream) -> io::Result<()> {
+        stream.set_write_timeout(Some(self.timeout))?;
+        let deadline = Deadline { stream: stream.try_clone()?, deadline: Instant::now() + self.timeout };
+        let mut reader = BufReader::new(deadline.take(MAX_HEAD_BYTES));
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        // Skip the headers, nothing here depends on them
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 2 {
+            line.clear();
+        }
+        let response = match request_line.split_whitespace().collect::<Vec<_>>()[..] {
+            ["GET", target, _] => self.respond(target),
+            [_, _, _] => Response::text(405, "only GET requests are answered"),
+            _ => Response::text(400, "malformed request line"),
+        };
+        let mut stream = stream;
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\n\
+             Connection: close\r\n\r\n",
+            response.status,
+            response.reason(),
+            response.content_type,
+            response.body.len()
+        )?;
+        stream.write_all(&response.body)?;
+        stream.flush()
+    }
+
+    /// Generate the texture of a generator node with the given keys as a PNG
+    fn generate(&self, generator: &str, parameters: BTreeMap<String, String>) -> Result<Vec<u8>, String> {
+        let mut node = toml::Table::new();
+        for (name, value) in parameters {
+            if matches!(name.as_str(), "name" | "type" | "save") {
+                return Err(format!("`{name}` cannot be set in a preview"));
+            }
+            node.insert(name, toml_value(value));
+        }
+        node.insert("name".to_string(), NODE.into());
+        node.insert("type".to_string(), generator.into());
+        let document = toml::Table::from_iter([("node".to_string(), toml::Value::Array(vec![node.into()]))]);
+        let pipeline = Pipeline::from_toml(&document.to_string()).map_err(|err| err.to_string())?;
+        // The size bounds the memory of a request and the work estimate its
+        // run time, whatever the counts and iterations
+        let operation = &pipeline.nodes()[0].operation;
+        if let Some(size) = operation.size().filter(|&size| size > self.max_size) {
+            return Err(format!("size {size} is above the maximum of {} of this server", self.max_size));
+        }
+        if let Some(work) = work(operation).filter(|&work| work > MAX_WORK) {
+            // Rounded up, so a request just over the limit does not read as 1.0
+            let times = (work / MAX_WORK * 10.0).ceil() / 10.0;
+            return Err(format!(
+                "the texture needs about {times:.1} times the work a preview may take, \
+                 lower its size, counts or iterations"
+            ));
+        }
+        let field = pipeline.execute().map_err(|err| err.to_string())?.remove(NODE).expect("the node was executed");
+        let mut png = Vec::new();
+        field
+            .to_luma_image()
+            .write_to(&mut io::Cursor::new(&mut png), ImageFormat::Png)
+            .map_err(|err| format!("cannot encode the PNG: {err}"))?;
+        Ok(png)
+    }
+
+    /// The cached PNG of a parameter set, now the most recently used one
+    fn cached(&self, key: &str) -> Option<Arc<Vec<u8>>> {
+        let mut cache = self.cache.lock().expect("the cache is not poisoned");
+        let index = cache.iter().position(|(cached, _)| cached == key)?;
+        let entry = cache.remove(index).expect("the index was just found");
+        let png = entry.1.clone();
+        cache.push_front(entry);
+        Some(png)
+    }
+
+    /// Cache a PNG, dropping the least recently used one when full
+    fn insert(&self, key: String, png: Arc<Vec<u8>>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut cache = self.cache.lock().expect("the cache is not poisoned");
+        cache.retain(|(cached, _)| *cached != key);
+        cache.truncate(self.capacity - 1);
+        cache.push_front((key, png));
+    }
+}
+
+/// The clients a server may still answer, and a signal when one is done
+struct Slots {
+    free: Mutex<usize>,
+    freed: Condvar,
+}
+
+impl Slots {
+    /// Wait for a free slot and take it until the returned guard is dropped
+    fn take(self: &Arc<Self>) -> Slot {
+        let free = self.free.lock().expect("the slots are not poisoned");
+        let mut free = self.freed.wait_while(free, |free| *free == 0).expect("the slots are not poisoned");
+        *free -= 1;
+        Slot(self.clone())
+    }
+}
+
+/// A taken slot, given back when dropped, even by a panicking generator
+struct Slot(Arc<Slots>);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        *self.0.free.lock().expect("the slots are not poisoned") += 1;
+        self.0.freed.notify_one();
+    }
+}
+
+/// A client stream whose reads fail once the deadline has passed, so a
+/// client sending its request slowly cannot hold a connection either
+struct Deadline {
+    stream: TcpStream,
+    deadline: Instant,
+}
+
+impl Read for Deadline {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let left = self.deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        self.stream.set_read_timeout(Some(left))?;
+        self.stream.read(buf)
+    }
+}
+
+/// The rough work of generating the texture of an operation, in the units
+/// of [`MAX_WORK`], or `None` for filters
+fn work(operation: &Operation) -> Option<f64> {
+    let size = operation.size()? as f64;
+    let pixels = size * size;
+    let fbm_octaves = FbmParams::default().octaves as f64;
+    let work = match operation {
+        Operation::Voronoi(params) => voronoi_work(params, pixels),
+        Operation::Perlin { octaves, .. } => pixels * *octaves as f64 * NOISE_SAMPLE,
+        // Every pixel is ranked by a search over all pixels
+        Operation::BlueNoise { .. } => pixels * pixels / 4.0,
+        Operation::Scratches { count, length, width, .. } => {
+            // The pixels near every scratch and the grid cells around it
+            let (along, across) = (length[1] as f64 * size, *width as f64 * size);
+            pixels + *count as f64 * ((along + 16.0) * (across + 16.0) + (along / 8.0 + 2.0).powi(2))
+        }
+        Operation::Speckle { count, distribution, radius, .. } => {
+            let reach = 2.0 * (radius[1] as f64 * size + 2.0);
+            pixels + point_count(*distribution, *count) * reach * reach
+        }
+        Operation::Wood { .. } | Operation::Marble { .. } => pixels * fbm_octaves * NOISE_SAMPLE,
+        Operation::Cracks { points, relax, .. } => {
+            let relax = *relax as f64;
+            pixels * (NEAREST_SEARCH + fbm_octaves * NOISE_SAMPLE)
+                + *points as f64 * (relax + 1.0) * POINT
+                + relax * lloyd_work(*points as f64)
+        }
+        Operation::ReactionDiffusion { steps, .. } => pixels * *steps as f64,
+        Operation::Gabor { radius, kernels, .. } => {
+            let reach = 2.0 * *radius as f64 * size + 1.0;
+            pixels + *kernels as f64 * reach * reach * 2.0
+        }
+        _ => pixels,
+    };
+    Some(work)
+}
+
+/// The rough work of a Voronoi texture of `pixels` pixels, summed over its
+/// octaves, each with four times the points of the one before
+fn voronoi_work(params: &VoronoiParams, pixels: f64) -> f64 {
+    // The warp turns every pixel with two fBm fields
+    let warp = if params.warp_strength != 0.0 { 2.0 * FbmParams::default().octaves as f64 * NOISE_SAMPLE } else { 0.0 };
+    let relax = params.relax_iterations as f64;
+    let points = point_count(params.distribution, params.num_points);
+    (0..params.octaves.clamp(1, 64))
+        .map(|octave| {
+            let points = points * 4f64.powi(octave as i32);
+            pixels * (NEAREST_SEARCH + warp) + points * (relax + 1.0) * POINT + relax * lloyd_work(points)
+        })
+        .sum()
+}
+
+/// The rough number of points a distribution places, given `num_points` for
+/// those with a fixed count
+fn point_count(distribution: PointDistribution, num_points: usize) -> f64 {
+    match distribution {
+        PointDistribution::Uniform => num_points as f64,
+        PointDistribution::PoissonDisk { min_dist } => 1.0 / (min_dist as f64 * min_dist as f64),
+        PointDistribution::JitteredGrid { cols, rows, .. } | PointDistribution::HexGrid { cols, rows, .. } => {
+            cols as f64 * rows as f64
+        }
+    }
+}
+
+/// The rough work of one Lloyd relaxation of `points` points, which assigns
+/// a raster of samples growing with the point count to the nearest points
+fn lloyd_work(points: f64) -> f64 {
+    let samples = (16.0 * points.sqrt()).ceil().clamp(64.0, 1024.0);
+    samples * samples * NEAREST_SEARCH
+}
+
+/// The keys of a query string, percent-decoded
+fn parse_query(query: &str) -> Result<BTreeMap<String, String>, String> {
+    let mut parameters = BTreeMap::new();
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let (name, value) = (percent_decode(name)?, percent_decode(value)?);
+        if parameters.insert(name.clone(), value).is_some() {
+            return Err(format!("`{name}` is given twice"));
+        }
+    }
+    Ok(parameters)
+}
+
+/// The parameters in key order, the same for every order of the query
+fn canonical_query(parameters: &BTreeMap<String, String>) -> String {
+    parameters.iter().map(|(name, value)| format!("{name}={value}")).collect::<Vec<_>>().join("&")
+}
+
+/// Decode `%XX` escapes and `+` for spaces
+fn percent_decode(s: &str) -> Result<String, String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut rest = s.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        match byte {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let hex = rest.get(..2).and_then(|hex| std::str::from_utf8(hex).ok());
+                let decoded = hex.and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                bytes.push(decoded.ok_or_else(|| format!("invalid escape in `{s}`"))?);
+                rest = &rest[2..];
+            }
+            _ => bytes.push(byte),
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| format!("`{s}` is not UTF-8"))
+}
+
+/// A query value as the TOML value a pipeline node expects: integers, floats
+/// and booleans by their syntax, anything else as a string
+fn toml_value(value: String) -> toml::Value {
+    if let Ok(integer) = value.parse::<i64>() {
+        return integer.into();
+    }
+    if let Ok(float) = value.parse::<f64>() {
+        if float.is_finite() {
+            return float.into();
+        }
+    }
+    match value.as_str() {
+        "true" => true.into(),
+        "false" => false.into(),
+        _ => value.into(),
+    }
+}
+
+/// The text answering requests for `/`
+fn usage(max_size: u32) -> String {
+    format!(
+        "cells preview server\n\n\
+         GET /<generator>?<key>=<value>&... answers with a PNG of a pipeline generator node,\n\
+         e.g. /voronoi?size=512&points=300&seed=42&feature=f2-f1 or /perlin?octaves=4&frequency=2\n\
+         Textures are at most {max_size} pixels across, and requests needing too much work to\n\
+         generate, such as many octaves, points or steps, are refused.\n"
+    )
+}
@@ -0,0 +1,81 @@
+// The directional blur of `blur_along` in src/filter.rs, one invocation per
+// pixel. The steps between the taps come from the CPU, so the directions do
+// not depend on the trigonometry of the device.
+
+struct Params {
+    width: u32,
+    height: u32,
+    first_tap: i32,
+    last_tap: i32,
+    // Whether taps read the nearest pixel instead of interpolating
+    nearest: u32,
+    total_weight: f32,
+    _padding: vec2<u32>,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> field: array<f32>;
+@group(0) @binding(2) var<storage, read> steps: array<vec2<f32>>;
+// The weights of the taps from `first_tap` to `last_tap`
+@group(0) @binding(3) var<storage, read> weights: array<f32>;
+@group(0) @binding(4) var<storage, read_write> blurred: array<f32>;
+
+// `v.rem_euclid(n)`; the remainder of a negative integer is not portable
+fn wrap(v: i32, n: u32) -> u32 {
+    if v < 0 {
+        return n - 1u - u32(-(v + 1)) % n;
+    }
+    return u32(v) % n;
+}
+
+fn get_wrapped(x: i32, y: i32) -> f32 {
+    return field[wrap(y, params.height) * params.width + wrap(x, params.width)];
+}
+
+fn sample_bilinear_wrapped(x: f32, y: f32) -> f32 {
+    let x0 = floor(x);
+    let y0 = floor(y);
+    let tx = x - x0;
+    let ty = y - y0;
+    let ix = i32(x0);
+    let iy = i32(y0);
+    let top = mix_exact(get_wrapped(ix, iy), get_wrapped(ix + 1, iy), tx);
+    let bottom = mix_exact(get_wrapped(ix, iy + 1), get_wrapped(ix + 1, iy + 1), tx);
+    return mix_exact(top, bottom, ty);
+}
+
+// `a + (b - a) * t` like the CPU, which `mix` does not promise
+fn mix_exact(a: f32, b: f32, t: f32) -> f32 {
+    return a + (b - a) * t;
+}
+
+// Round half away from zero like `f32::round`
+fn round_away(v: f32) -> i32 {
+    let t = trunc(v);
+    if abs(v - t) >= 0.5 {
+        return i32(t + sign(v));
+    }
+    return i32(t);
+}
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= params.width || id.y >= params.height {
+        return;
+    }
+    let pixel = id.y * params.width + id.x;
+    let step = steps[pixel];
+    let x = i32(id.x);
+    let y = i32(id.y);
+    var sum = 0.0;
+    for (var i = params.first_tap; i <= params.last_tap; i++) {
+        let weight = weights[u32(i - params.first_tap)];
+        let along = f32(i) * step;
+        if params.nearest != 0u {
+            sum += weight * get_wrapped(x + round_away(along.x), y + round_away(along.y));
+        } else {
+            sum += weight * sample_bilinear_wrapped(f32(x) + along.x, f32(y) + along.y);
+        }
+    }
+    blurred[pixel] = sum / params.total_weight;
+}
@@ -0,0 +1,290 @@
+// The Voronoi features of `PointGrid` queries, one invocation per pixel.
+//
+// The ring search and the distances follow src/grid.rs and
+// src/geometry.rs operation by operation, so the nearest points are the
+// ones the CPU finds up to the rounding of the device.
+
+struct Params {
+    size: u32,
+    // The number of points
+    count: u32,
+    resolution: u32,
+    // 0 F1, 1 F2, 2 F2 - F1, 3 cell value
+    feature: u32,
+    // 0 Euclidean, 1 Manhattan, 2 Chebyshev, 3 Minkowski of `exponent`
+    metric: u32,
+    // 0 none, 1 additive, 2 multiplicative
+    weighting: u32,
+    // Whether the pixels sample the diagram at `positions`
+    warped: u32,
+    exponent: f32,
+    value_smoothing: f32,
+    scale_x: f32,
+    scale_y: f32,
+    max_weight: f32,
+}
+
+// A point in cell order, with its weight, its cell value and its index
+struct Site {
+    x: f32,
+    y: f32,
+    weight: f32,
+    value: f32,
+    index: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> sites: array<Site>;
+// Cell `c` holds the sites `starts[c]..starts[c + 1]`
+@group(0) @binding(2) var<storage, read> starts: array<u32>;
+@group(0) @binding(3) var<storage, read> positions: array<vec2<f32>>;
+@group(0) @binding(4) var<storage, read_write> features: array<f32>;
+
+// The slack of the ring termination bound of src/grid.rs
+const BOUND_SLACK: f32 = 0.999;
+
+// The `wanted` nearest sites so far, closest first
+var<private> wanted: u32;
+var<private> best_index: array<u32, 2>;
+var<private> best_distance: array<f32, 2>;
+var<private> best_value: array<f32, 2>;
+
+// `pow` is undefined for a zero base
+fn power(x: f32, p: f32) -> f32 {
+    if x == 0.0 {
+        return 0.0;
+    }
+    return pow(x, p);
+}
+
+// The wrapped offset along one axis of a distance from `d` >= 0
+fn wrap(d: f32) -> f32 {
+    var w = d;
+    if w >= 1.0 {
+        w = w - floor(w);
+    }
+    return min(w, 1.0 - w);
+}
+
+fn reduced(p: vec2<f32>, q: vec2<f32>) -> f32 {
+    let dx = wrap(abs(p.x - q.x)) * params.scale_x;
+    let dy = wrap(abs(p.y - q.y)) * params.scale_y;
+    switch params.metric {
+        case 1u: {
+            return dx + dy;
+        }
+        case 2u: {
+            return max(dx, dy);
+        }
+        case 3u: {
+            return power(dx, params.exponent) + power(dy, params.exponent);
+        }
+        default: {
+            return dx * dx + dy * dy;
+        }
+    }
+}
+
+fn expand(r: f32) -> f32 {
+    switch params.metric {
+        case 0u: {
+            return sqrt(r);
+        }
+        case 3u: {
+            return power(r, 1.0 / params.exponent);
+        }
+        default: {
+            return r;
+        }
+    }
+}
+
+fn reduce(d: f32) -> f32 {
+    switch params.metric {
+        case 0u: {
+            return d * d;
+        }
+        case 3u: {
+            return power(d, params.exponent);
+        }
+        default: {
+            return d;
+        }
+    }
+}
+
+fn apply_weight(d: f32, weight: f32) -> f32 {
+    switch params.weighting {
+        case 1u: {
+            return d - weight;
+        }
+        case 2u: {
+            return d / weight;
+        }
+        default: {
+            return d;
+        }
+    }
+}
+
+fn closer(index: u32, d: f32, slot: u32) -> bool {
+    return d < best_distance[slot] || (d == best_distance[slot] && index < best_index[slot]);
+}
+
+// Insert a site into the best list like `insert` of src/grid.rs
+fn insert(index: u32, d: f32, value: f32) {
+    let last = wanted - 1u;
+    if !closer(index, d, last) {
+        return;
+    }
+    for (var slot = 0u; slot < wanted; slot++) {
+        if best_index[slot] == index {
+            return;
+        }
+    }
+    var slot = last;
+    while slot > 0u && closer(index, d, slot - 1u) {
+        best_index[slot] = best_index[slot - 1u];
+        best_distance[slot] = best_distance[slot - 1u];
+        best_value[slot] = best_value[slot - 1u];
+        slot -= 1u;
+    }
+    best_index[slot] = index;
+    best_distance[slot] = d;
+    best_value[slot] = value;
+}
+
+fn visit(first: u32, end: u32, p: vec2<f32>) {
+    for (var j = first; j < end; j++) {
+        let site = sites[j];
+        var d = reduced(p, vec2<f32>(site.x, site.y));
+        if params.weighting != 0u {
+            d = apply_weight(expand(d), site.weight);
+        }
+        insert(site.index, d, site.value);
+    }
+}
+
+fn visit_cells(row: u32, first: u32, end: u32, p: vec2<f32>) {
+    let base = row * params.resolution;
+    visit(starts[base + first], starts[base + end], p);
+}
+
+fn wrap_cell(v: u32, d: i32) -> u32 {
+    let n = i32(params.resolution);
+    var w = i32(v) + d;
+    if w < 0 {
+        w += n;
+    } else if w >= n {
+        w -= n;
+    }
+    return u32(w);
+}
+
+// The ring search of `PointGrid::search` for the `k` nearest sites to `p`,
+// false when there are fewer sites
+fn search(p: vec2<f32>, k: u32) -> bool {
+    wanted = k;
+    for (var slot = 0u; slot < 2u; slot++) {
+        best_index[slot] = 0xffffffffu;
+        best_distance[slot] = bitcast<f32>(0x7f800000u);
+        best_value[slot] = 0.0;
+    }
+    if params.count < k {
+        return false;
+    }
+    let n = params.resolution;
+    let cell_size = 1.0 / f32(n);
+    let cx = min(u32(p.x * f32(n)), n - 1u);
+    let cy = min(u32(p.y * f32(n)), n - 1u);
+    for (var ring = 0u; ; ring++) {
+        if 2u * ring + 1u > n {
+            visit(0u, params.count, p);
+            break;
+        }
+        let r = i32(ring);
+        for (var dy = -r; dy <= r; dy++) {
+            let gy = wrap_cell(cy, dy);
+            if abs(dy) == r {
+                let first = wrap_cell(cx, -r);
+                let len = 2u * ring + 1u;
+                let end = min(first + len, n);
+                visit_cells(gy, first, end, p);
+                visit_cells(gy, 0u, first + len - end, p);
+            } else {
+                let left = wrap_cell(cx, -r);
+                let right = wrap_cell(cx, r);
+                visit_cells(gy, left, left + 1u, p);
+                visit_cells(gy, right, right + 1u, p);
+            }
+        }
+        let min_scale = min(params.scale_x, params.scale_y);
+        var bound = apply_weight(f32(ring) * cell_size * min_scale * BOUND_SLACK, params.max_weight);
+        if params.weighting == 0u {
+            bound = reduce(bound);
+        }
+        if 2u * ring + 1u == n || best_distance[k - 1u] <= bound {
+            break;
+        }
+    }
+    if params.weighting == 0u {
+        for (var slot = 0u; slot < k; slot++) {
+            best_distance[slot] = expand(best_distance[slot]);
+        }
+    }
+    return true;
+}
+
+fn cell_value(p: vec2<f32>) -> f32 {
+    let width = params.value_smoothing;
+    if width > 0.0 && search(p, 2u) {
+        let t = clamp((best_distance[1] - best_distance[0]) / width, 0.0, 1.0);
+        let weight = 0.5 * (1.0 - t * t * (3.0 - 2.0 * t));
+        return best_value[0] + (best_value[1] - best_value[0]) * weight;
+    }
+    search(p, 1u);
+    return best_value[0];
+}
+
+fn feature(p: vec2<f32>) -> f32 {
+    if params.feature == 3u {
+        return cell_value(p);
+    }
+    var f1: f32;
+    var f2: f32;
+    if params.feature != 0u && search(p, 2u) {
+        f1 = best_distance[0];
+        f2 = best_distance[1];
+    } else {
+        search(p, 1u);
+        f1 = best_distance[0];
+        f2 = f1;
+    }
+    f1 = max(f1, 0.0);
+    f2 = max(f2, 0.0);
+    switch params.feature {
+        case 1u: {
+            return f2;
+        }
+        case 2u: {
+            return f2 - f1;
+        }
+        default: {
+            return f1;
+        }
+    }
+}
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= params.size || id.y >= params.size {
+        return;
+    }
+    let pixel = id.y * params.size + id.x;
+    // The top-left corner of the pixel, like `pixel_position`
+    var p = vec2<f32>(f32(id.x), f32(id.y)) / f32(params.size);
+    if params.warped != 0u {
+        p = positions[pixel];
+    }
+    features[pixel] = feature(p);
+}
@@ -0,0 +1,244 @@
+//! Speckle textures: scattered splats for dust, stars and surface
+//! imperfections.
+//!
+//! Every splat is drawn around its center with wraparound, so splats
+//! crossing an edge continue on the opposite side and the texture tiles.
+
+use std::fmt;
+use std::str::FromStr;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::error::CellsError;
+use crate::field::FieldBuffer;
+use crate::geometry::Point;
+use crate::sampling::PointDistribution;
+
+/// The profile of a splat
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SplatShape {
+    /// A Gaussian bump with a standard deviation of a third of the radius,
+    /// cut off at the radius
+    #[default]
+    Gaussian,
+    /// A flat disc with an anti-aliased rim
+    Disc,
+}
+
+impl fmt::Display for SplatShape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SplatShape::Gaussian => write!(f, "gaussian"),
+            SplatShape::Disc => write!(f, "disc"),
+        }
+    }
+}
+
+impl FromStr for SplatShape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gaussian" => Ok(SplatShape::Gaussian),
+            "disc" => Ok(SplatShape::Disc),
+            _ => Err(format!("unknown splat shape `{s}`, expected gaussian or disc")),
+        }
+    }
+}
+
+/// One splat of a speckle texture
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Splat {
+    /// Center in the unit square
+    pub center: Point,
+    /// Radius as a fraction of the texture size, at most 0.5
+    pub radius: f32,
+    /// Peak value added at the center
+    pub intensity: f32,
+}
+
+/// Parameters of a speckle texture
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpeckleParams {
+    /// Width and height of the texture in pixels
+    pub size: u32,
+    /// Number of splats
+    pub count: usize,
+    /// How the splat centers are placed; distributions making their own
+    /// number of points, such as Poisson-disk spacing, are thinned at random
+    /// down to `count`
+    pub distribution: PointDistribution,
+    /// Inclusive range of the splat radii as fractions of the texture size
+    pub radius: (f32, f32),
+    /// Inclusive range of the splat intensities
+    pub intensity: (f32, f32),
+    /// The profile of every splat
+    pub shape: SplatShape,
+    /// Seed of the positions, radii and intensities
+    pub seed: u64,
+}
+
+impl Default for SpeckleParams {
+    fn default() -> Self {
+        SpeckleParams {
+            size: 512,
+            count: 400,
+            distribution: PointDistribution::Uniform,
+            radius: (0.002, 0.006),
+            intensity: (0.3, 1.0),
+            shape: SplatShape::Gaussian,
+            seed: 0,
+        }
+    }
+}
+
+impl SpeckleParams {
+    /// Check that the ranges are ordered and the splats fit the texture
+    ///
+    /// # Returns
+    ///
+    /// [`CellsError::InvalidParameter`] if the size is 0, a range is not
+    /// finite or its minimum exceeds its maximum, or the radii are outside
+    /// (0, 0.5]
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::speckle::SpeckleParams;
+    ///
+    /// let err = SpeckleParams { radius: (0.01, 0.005), ..Default::default() }.validate().unwrap_err();
+    /// assert_eq!(err.to_string(), "invalid radius: the minimum 0.01 exceeds the maximum 0.005");
+    /// ```
+    pub fn validate(&self) -> Result<(), CellsError> {
+        let invalid = |name, reason: String| Err(CellsError::InvalidParameter { name, reason });
+        if self.size == 0 {
+            return invalid("size", "must be above 0".to_string());
+        }
+        for (name, (min, max)) in [("radius", self.radius), ("intensity", self.intensity)] {
+            if !(min.is_finite() && max.is_finite()) {
+                return invalid(name, format!("must be finite, got {min} to {max}"));
+            }
+            if min > max {
+                return invalid(name, format!("the minimum {min} exceeds the maximum {max}"));
+            }
+        }
+        let (min, max) = self.radius;
+        if !(min > 0.0 && max <= 0.5) {
+            return invalid("radius", format!("must be above 0 and at most 0.5, got {min} to {max}"));
+        }
+        Ok(())
+    }
+}
+
+/// Generate a tileable speckle texture
+///
+/// Scatters `params.count` splats with radii and intensities drawn
+/// uniformly from their ranges and sums them with [`render_splats`].
+///
+/// # Arguments
+///
+/// * `params` - The splats and their placement
+///
+/// # Returns
+///
+/// A `FieldBuffer` that is 0 between the splats and sums overlapping ones
+///
+/// # Panics
+///
+/// Panics if the parameters fail [`SpeckleParams::validate`].
+///
+/// # Example
+///
+/// ```rust
+/// use cells::speckle::{generate_speckle, SpeckleParams};
+/// use cells::tiling::{assert_tileable, interior_discontinuity};
+///
+/// let params = SpeckleParams { size: 64, count: 40, radius: (0.02, 0.05), ..Default::default() };
+/// let dust = generate_speckle(&params);
+/// assert_eq!(dust, generate_speckle(&params));
+/// assert_tileable(&dust, interior_discontinuity(&dust));
+/// ```
+pub fn generate_speckle(params: &SpeckleParams) -> FieldBuffer {
+    if let Err(err) = params.validate() {
+        panic!("{err}");
+    }
+    let mut rng = StdRng::seed_from_u64(params.seed);
+    let mut centers = params.distribution.generate(params.count, &mut rng);
+    if centers.len() > params.count {
+        centers.shuffle(&mut rng);
+        centers.truncate(params.count);
+    }
+    let splats: Vec<Splat> = centers
+        .into_iter()
+        .map(|center| Splat {
+            center,
+            radius: rng.gen_range(params.radius.0..=params.radius.1),
+            intensity: rng.gen_range(params.intensity.0..=params.intensity.1),
+        })
+        .collect();
+    render_splats(params.size, &splats, params.shape)
+}
+
+/// Sum splats into a `size` x `size` field with wraparound
+///
+/// Splats reaching over an edge continue on the opposite side, exactly as if
+/// the texture were tiled, so a splat moved by whole pixels renders as the
+/// scrolled original.
+///
+/// # Panics
+///
+/// Panics if a radius is not in (0, 0.5].
+///
+/// # Example
+///
+/// ```rust
+/// use cells::speckle::{render_splats, Splat, SplatShape};
+/// use cells::Point;
+///
+/// // A disc on the top left corner pixel shows in all four corners
+/// let corner = Splat {
+///     center: Point { x: 0.5 / 16.0, y: 0.5 / 16.0 },
+///     radius: 0.15,
+///     intensity: 1.0,
+/// };
+/// let field = render_splats(16, &[corner], SplatShape::Disc);
+/// assert_eq!([field.get(0, 0), field.get(15, 0), field.get(0, 15), field.get(15, 15)], [1.0; 4]);
+/// assert_eq!(field.get(8, 8), 0.0);
+/// ```
+pub fn render_splats(size: u32, splats: &[Splat], shape: SplatShape) -> FieldBuffer {
+    if size == 0 {
+        return FieldBuffer::new(size, size);
+    }
+    let mut values = vec![0.0; size as usize * size as usize];
+    let scale = size as f32;
+    for splat in splats {
+        assert!(
+            splat.radius > 0.0 && splat.radius <= 0.5,
+            "splat radii must be above 0 and at most 0.5, got {}",
+            splat.radius
+        );
+        // The pixels around the center, beyond the edges where it is close
+        let (cx, cy) = (splat.center.x * scale, splat.center.y * scale);
+        let reach = (splat.radius * scale).ceil() as i64 + 1;
+        let (x0, y0) = ((cx - 0.5).round() as i64, (cy - 0.5).round() as i64);
+        for y in y0 - reach..=y0 + reach {
+            for x in x0 - reach..=x0 + reach {
+                let (dx, dy) = ((x as f32 + 0.5 - cx) / scale, (y as f32 + 0.5 - cy) / scale);
+                let distance = (dx * dx + dy * dy).sqrt();
+                let value = match shape {
+                    SplatShape::Gaussian if distance < splat.radius => {
+                        let sigma = splat.radius / 3.0;
+                        (-distance * distance / (2.0 * sigma * sigma)).exp()
+                    }
+                    SplatShape::Gaussian => continue,
+                    SplatShape::Disc => ((splat.radius - distance) * scale + 0.5).clamp(0.0, 1.0),
+                };
+                let (wx, wy) = (x.rem_euclid(size as i64) as usize, y.rem_euclid(size as i64) as usize);
+                values[wy * size as usize + wx] += splat.intensity * value;
+            }
+        }
+    }
+    FieldBuffer::from_vec(size, size, values)
+}
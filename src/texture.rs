@@ -0,0 +1,383 @@
+//! GPU texture containers: DDS and KTX2 files holding a texture and its mip
+//! levels, uncompressed or block compressed.
+//!
+//! Engines upload the levels of these containers as they are, so a texture is
+//! encoded once into the bytes of its [`TextureFormat`] by
+//! [`Texture::encode`] and then written by [`save_dds`] or [`save_ktx2`].
+//! The block compressed formats need the `block-compression` feature, see
+//! [`crate::bc`].
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::error::CellsError;
+use crate::field::FieldBuffer;
+
+/// The layout of the texels of a [`Texture`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextureFormat {
+    /// One 8-bit unsigned normalized channel per texel
+    #[default]
+    R8,
+    /// Two 8-bit unsigned normalized channels per texel
+    Rg8,
+    /// One channel in BC4 blocks of 4x4 texels
+    Bc4,
+    /// Two channels in BC5 blocks of 4x4 texels
+    Bc5,
+}
+
+impl TextureFormat {
+    /// The number of channels, and so of fields, of every level
+    pub fn channels(self) -> usize {
+        match self {
+            TextureFormat::R8 | TextureFormat::Bc4 => 1,
+            TextureFormat::Rg8 | TextureFormat::Bc5 => 2,
+        }
+    }
+
+    /// Whether the format stores blocks of 4x4 texels
+    pub fn is_compressed(self) -> bool {
+        matches!(self, TextureFormat::Bc4 | TextureFormat::Bc5)
+    }
+
+    /// Bytes of one texel, or of one 4x4 block of compressed formats
+    fn block_bytes(self) -> usize {
+        match self {
+            TextureFormat::R8 => 1,
+            TextureFormat::Rg8 => 2,
+            TextureFormat::Bc4 => 8,
+            TextureFormat::Bc5 => 16,
+        }
+    }
+
+    /// Bytes of a level of `width` x `height` texels
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::texture::TextureFormat;
+    ///
+    /// assert_eq!(TextureFormat::Rg8.level_bytes(5, 3), 30);
+    /// // Compressed levels round up to whole blocks
+    /// assert_eq!(TextureFormat::Bc4.level_bytes(5, 3), 16);
+    /// assert_eq!(TextureFormat::Bc5.level_bytes(1, 1), 16);
+    /// ```
+    pub fn level_bytes(self, width: u32, height: u32) -> usize {
+        let (width, height) = match self.is_compressed() {
+            true => (width.div_ceil(4), height.div_ceil(4)),
+            false => (width, height),
+        };
+        width as usize * height as usize * self.block_bytes()
+    }
+
+    /// The `DXGI_FORMAT` of the DDS DX10 header
+    fn dxgi_format(self) -> u32 {
+        match self {
+            TextureFormat::R8 => 61,
+            TextureFormat::Rg8 => 49,
+            TextureFormat::Bc4 => 80,
+            TextureFormat::Bc5 => 83,
+        }
+    }
+
+    /// The `VkFormat` of the KTX2 header
+    fn vk_format(self) -> u32 {
+        match self {
+            TextureFormat::R8 => 9,
+            TextureFormat::Rg8 => 16,
+            TextureFormat::Bc4 => 139,
+            TextureFormat::Bc5 => 141,
+        }
+    }
+}
+
+impl fmt::Display for TextureFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TextureFormat::R8 => "r8",
+            TextureFormat::Rg8 => "rg8",
+            TextureFormat::Bc4 => "bc4",
+            TextureFormat::Bc5 => "bc5",
+        })
+    }
+}
+
+impl FromStr for TextureFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "r8" => Ok(TextureFormat::R8),
+            "rg8" => Ok(TextureFormat::Rg8),
+            "bc4" => Ok(TextureFormat::Bc4),
+            "bc5" => Ok(TextureFormat::Bc5),
+            _ => Err(format!("unknown texture format `{s}`, expected r8, rg8, bc4 or bc5")),
+        }
+    }
+}
+
+/// A texture and its mip levels, encoded in one [`TextureFormat`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Texture {
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+    levels: Vec<Vec<u8>>,
+}
+
+impl Texture {
+    /// Encode a texture from its levels, each given as one field per channel
+    ///
+    /// Level 0 is the full texture and every further level halves the one
+    /// before, rounding down but to at least 1, as returned by
+    /// [`mip_chain`](crate::resample::mip_chain). Values are clamped to
+    /// [0, 1] and quantized to 8 bits.
+    ///
+    /// # Returns
+    ///
+    /// [`CellsError::InvalidParameter`] if there are no levels, a level does
+    /// not hold [`TextureFormat::channels`] fields, or the format is block
+    /// compressed and the crate is built without the `block-compression`
+    /// feature; [`CellsError::DimensionMismatch`] if a level is not half the
+    /// size of the one before
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::resample::{mip_chain, ResampleFilter};
+    /// use cells::texture::{Texture, TextureFormat};
+    /// use cells::FieldBuffer;
+    ///
+    /// let field = FieldBuffer::from_par_fn(8, 8, |x, y| (x ^ y) as f32 / 7.0);
+    /// let levels: Vec<_> = mip_chain(&field, ResampleFilter::Box).into_iter().map(|level| vec![level]).collect();
+    /// let texture = Texture::encode(TextureFormat::R8, &levels).unwrap();
+    /// assert_eq!(texture.levels().iter().map(Vec::len).collect::<Vec<_>>(), [64, 16, 4, 1]);
+    /// assert!(Texture::encode(TextureFormat::Rg8, &levels).is_err());
+    /// ```
+    pub fn encode(format: TextureFormat, levels: &[Vec<FieldBuffer>]) -> Result<Texture, CellsError> {
+        let invalid = |reason: String| CellsError::InvalidParameter { name: "texture levels", reason };
+        let (width, height) = levels
+            .first()
+            .and_then(|level| level.first())
+            .map(FieldBuffer::dimensions)
+            .ok_or_else(|| invalid("a texture needs at least one level".to_string()))?;
+        let mut encoded = Vec::with_capacity(levels.len());
+        for (i, level) in levels.iter().enumerate() {
+            if level.len() != format.channels() {
+                return Err(invalid(format!("{format} has {} channels, got {}", format.channels(), level.len())));
+            }
+            let expected = ((width >> i).max(1), (height >> i).max(1));
+            if let Some(field) = level.iter().find(|field| field.dimensions() != expected) {
+                return Err(CellsError::DimensionMismatch { input: "mip level", expected, found: field.dimensions() });
+            }
+            encoded.push(encode_level(format, level)?);
+        }
+        Ok(Texture { format, width, height, levels: encoded })
+    }
+
+    /// The format of the texels
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    /// The `(width, height)` of level 0
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// The encoded bytes of every level, from level 0 down
+    pub fn levels(&self) -> &[Vec<u8>] {
+        &self.levels
+    }
+}
+
+/// Encode one level of fields in a format
+fn encode_level(format: TextureFormat, fields: &[FieldBuffer]) -> Result<Vec<u8>, CellsError> {
+    let quantize = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+    match format {
+        TextureFormat::R8 => Ok(fields[0].as_slice().iter().map(|&v| quantize(v)).collect()),
+        TextureFormat::Rg8 => Ok(fields[0]
+            .as_slice()
+            .iter()
+            .zip(fields[1].as_slice())
+            .flat_map(|(&r, &g)| [quantize(r), quantize(g)])
+            .collect()),
+        #[cfg(feature = "block-compression")]
+        TextureFormat::Bc4 => Ok(crate::bc::encode_bc4(&fields[0])),
+        #[cfg(feature = "block-compression")]
+        TextureFormat::Bc5 => crate::bc::encode_bc5(&fields[0], &fields[1]),
+        #[cfg(not(feature = "block-compression"))]
+        TextureFormat::Bc4 | TextureFormat::Bc5 => Err(CellsError::InvalidParameter {
+            name: "texture format",
+            reason: format!("{format} needs the block-compression feature"),
+        }),
+    }
+}
+
+/// Write a texture and its mip levels as a DDS file with a DX10 header
+///
+/// # Example
+///
+/// ```rust
+/// use cells::texture::{save_dds, Texture, TextureFormat};
+/// use cells::FieldBuffer;
+///
+/// let texture = Texture::encode(TextureFormat::R8, &[vec![FieldBuffer::new(4, 4)]]).unwrap();
+/// let path = std::env::temp_dir().join("cells_doc_save_dds.dds");
+/// save_dds(&texture, &path).unwrap();
+/// assert_eq!(std::fs::metadata(&path).unwrap().len(), 4 + 124 + 20 + 16);
+/// ```
+pub fn save_dds(texture: &Texture, path: impl AsRef<Path>) -> io::Result<()> {
+    const CAPS: u32 = 0x1;
+    const HEIGHT: u32 = 0x2;
+    const WIDTH: u32 = 0x4;
+    const PITCH: u32 = 0x8;
+    const PIXEL_FORMAT: u32 = 0x1000;
+    const MIPMAP_COUNT: u32 = 0x2_0000;
+    const LINEAR_SIZE: u32 = 0x8_0000;
+    const FOURCC: u32 = 0x4;
+    const CAPS_COMPLEX: u32 = 0x8;
+    const CAPS_TEXTURE: u32 = 0x1000;
+    const CAPS_MIPMAP: u32 = 0x40_0000;
+
+    let format = texture.format;
+    let mipped = texture.levels.len() > 1;
+    let (size_flag, pitch_or_size) = match format.is_compressed() {
+        true => (LINEAR_SIZE, texture.levels[0].len() as u32),
+        false => (PITCH, texture.width * format.block_bytes() as u32),
+    };
+    let mut header = Vec::with_capacity(148);
+    let mut word = |value: u32| header.extend_from_slice(&value.to_le_bytes());
+    word(124);
+    word(CAPS | HEIGHT | WIDTH | PIXEL_FORMAT | size_flag | if mipped { MIPMAP_COUNT } else { 0 });
+    word(texture.height);
+    word(texture.width);
+    word(pitch_or_size);
+    word(0);
+    word(texture.levels.len() as u32);
+    (0..11).for_each(|_| word(0));
+    // DDS_PIXELFORMAT: the DX10 header that follows describes the format
+    word(32);
+    word(FOURCC);
+    word(u32::from_le_bytes(*b"DX10"));
+    (0..5).for_each(|_| word(0));
+    word(CAPS_TEXTURE | if mipped { CAPS_COMPLEX | CAPS_MIPMAP } else { 0 });
+    (0..4).for_each(|_| word(0));
+    // DDS_HEADER_DXT10: a single 2D texture
+    word(format.dxgi_format());
+    word(3);
+    word(0);
+    word(1);
+    word(0);
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(b"DDS ")?;
+    writer.write_all(&header)?;
+    for level in &texture.levels {
+        writer.write_all(level)?;
+    }
+    writer.flush()
+}
+
+/// Write a texture and its mip levels as a KTX2 file
+///
+/// The levels are stored without supercompression, smallest first as the
+/// format recommends, after a basic data format descriptor of the texels.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::texture::{save_ktx2, Texture, TextureFormat};
+/// use cells::FieldBuffer;
+///
+/// let level = vec![FieldBuffer::new(2, 2), FieldBuffer::new(2, 2)];
+/// let texture = Texture::encode(TextureFormat::Rg8, &[level]).unwrap();
+/// let path = std::env::temp_dir().join("cells_doc_save_ktx2.ktx2");
+/// save_ktx2(&texture, &path).unwrap();
+/// assert_eq!(std::fs::read(&path).unwrap()[..12], *b"\xABKTX 20\xBB\r\n\x1A\n");
+/// ```
+pub fn save_ktx2(texture: &Texture, path: impl AsRef<Path>) -> io::Result<()> {
+    let format = texture.format;
+    let dfd = data_format_descriptor(format);
+    let level_count = texture.levels.len();
+    let dfd_offset = 80 + 24 * level_count;
+    // Levels start at multiples of the least common multiple of the block
+    // size and 4
+    let alignment = format.block_bytes().max(4);
+    let mut offsets = vec![0; level_count];
+    let mut end = dfd_offset + dfd.len();
+    for (i, level) in texture.levels.iter().enumerate().rev() {
+        offsets[i] = end.next_multiple_of(alignment);
+        end = offsets[i] + level.len();
+    }
+
+    let mut header = Vec::with_capacity(dfd_offset);
+    header.extend_from_slice(b"\xABKTX 20\xBB\r\n\x1A\n");
+    let mut word = |value: u32| header.extend_from_slice(&value.to_le_bytes());
+    word(format.vk_format());
+    word(1);
+    word(texture.width);
+    word(texture.height);
+    word(0);
+    word(0);
+    word(1);
+    word(level_count as u32);
+    word(0);
+    word(dfd_offset as u32);
+    word(dfd.len() as u32);
+    // No key/value data and no supercompression global data
+    word(0);
+    word(0);
+    header.extend_from_slice(&[0; 16]);
+    for (offset, level) in offsets.iter().zip(&texture.levels) {
+        for value in [*offset, level.len(), level.len()] {
+            header.extend_from_slice(&(value as u64).to_le_bytes());
+        }
+    }
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(&header)?;
+    writer.write_all(&dfd)?;
+    let mut position = dfd_offset + dfd.len();
+    for (offset, level) in offsets.iter().zip(&texture.levels).rev() {
+        writer.write_all(&vec![0; offset - position])?;
+        writer.write_all(level)?;
+        position = offset + level.len();
+    }
+    writer.flush()
+}
+
+/// The data format descriptor of a KTX2 file: one basic descriptor block
+/// with a sample per channel, linear BT.709 without alpha
+fn data_format_descriptor(format: TextureFormat) -> Vec<u8> {
+    let samples = format.channels();
+    let block_size = 24 + 16 * samples;
+    let (model, block_dimensions) = match format {
+        TextureFormat::R8 | TextureFormat::Rg8 => (1, [0; 4]),
+        TextureFormat::Bc4 => (131, [3, 3, 0, 0]),
+        TextureFormat::Bc5 => (132, [3, 3, 0, 0]),
+    };
+    let mut dfd = Vec::with_capacity(4 + block_size);
+    dfd.extend_from_slice(&(4 + block_size as u32).to_le_bytes());
+    // Khronos vendor, basic descriptor type, version 2
+    dfd.extend_from_slice(&0u32.to_le_bytes());
+    dfd.extend_from_slice(&(2 | (block_size as u32) << 16).to_le_bytes());
+    dfd.extend_from_slice(&[model, 1, 1, 0]);
+    dfd.extend_from_slice(&block_dimensions);
+    dfd.extend_from_slice(&[format.block_bytes() as u8, 0, 0, 0, 0, 0, 0, 0]);
+    for channel in 0..samples {
+        let (offset, bits, upper) = match format.is_compressed() {
+            true => (64 * channel as u16, 63, u32::MAX),
+            false => (8 * channel as u16, 7, 255),
+        };
+        dfd.extend_from_slice(&offset.to_le_bytes());
+        dfd.extend_from_slice(&[bits, channel as u8, 0, 0, 0, 0]);
+        dfd.extend_from_slice(&0u32.to_le_bytes());
+        dfd.extend_from_slice(&upper.to_le_bytes());
+    }
+    dfd
+}
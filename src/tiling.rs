@@ -0,0 +1,160 @@
+//! Checks that textures tile without visible seams, and previews of the
+//! repeated texture.
+
+use image::{ImageBuffer, Pixel};
+
+use crate::field::FieldBuffer;
+
+/// Largest jump between opposite edges of a field
+///
+/// When a tileable texture is repeated, its last column continues into its
+/// first column and its last row into its first row. This measures the
+/// largest difference between those neighbors, which is exactly the
+/// discontinuity along the seam lines of the field offset by half its size.
+///
+/// # Arguments
+///
+/// * `field` - The field to check
+///
+/// # Returns
+///
+/// The maximum absolute difference across the wrap-around seams, 0 for empty
+/// fields
+///
+/// # Example
+///
+/// ```rust
+/// use cells::tiling::seam_discontinuity;
+/// use cells::FieldBuffer;
+///
+/// let ramp = FieldBuffer::from_par_fn(64, 64, |x, _| x as f32 / 63.0);
+/// assert_eq!(seam_discontinuity(&ramp), 1.0);
+/// let wave = FieldBuffer::from_par_fn(64, 64, |x, _| (x as f32 / 64.0 * std::f32::consts::TAU).sin());
+/// assert!(seam_discontinuity(&wave) < 0.1);
+/// ```
+pub fn seam_discontinuity(field: &FieldBuffer) -> f32 {
+    let (width, height) = field.dimensions();
+    if width == 0 || height == 0 {
+        return 0.0;
+    }
+    let vertical = (0..height).map(|y| (field.get(width - 1, y) - field.get(0, y)).abs());
+    let horizontal = (0..width).map(|x| (field.get(x, height - 1) - field.get(x, 0)).abs());
+    vertical.chain(horizontal).fold(0.0, f32::max)
+}
+
+/// Largest jump between horizontally or vertically adjacent samples inside
+/// a field
+///
+/// This is the scale [`seam_discontinuity`] should be compared to: a seam is
+/// invisible when its jump is no larger than the steps the texture already
+/// takes between neighbors.
+///
+/// # Arguments
+///
+/// * `field` - The field to check
+///
+/// # Returns
+///
+/// The maximum absolute difference between neighbors not separated by a seam
+pub fn interior_discontinuity(field: &FieldBuffer) -> f32 {
+    let (width, height) = field.dimensions();
+    let mut max_step: f32 = 0.0;
+    for y in 0..height {
+        for x in 0..width {
+            let value = field.get(x, y);
+            if x + 1 < width {
+                max_step = max_step.max((field.get(x + 1, y) - value).abs());
+            }
+            if y + 1 < height {
+                max_step = max_step.max((field.get(x, y + 1) - value).abs());
+            }
+        }
+    }
+    max_step
+}
+
+/// Assert that a field tiles seamlessly
+///
+/// # Arguments
+///
+/// * `field` - The field to check
+/// * `tolerance` - The largest jump allowed across the wrap-around seams
+///
+/// # Panics
+///
+/// Panics if [`seam_discontinuity`] exceeds `tolerance`.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::tiling::assert_tileable;
+/// use cells::voronoi::{generate_voronoi_field, VoronoiParams};
+///
+/// let voronoi = generate_voronoi_field(&VoronoiParams { size: 128, num_points: 40, ..Default::default() });
+/// assert_tileable(&voronoi, 0.2);
+/// ```
+pub fn assert_tileable(field: &FieldBuffer, tolerance: f32) {
+    let seam = seam_discontinuity(field);
+    assert!(
+        seam <= tolerance,
+        "field does not tile: seam discontinuity {seam} exceeds {tolerance} (largest interior step {})",
+        interior_discontinuity(field)
+    );
+}
+
+/// Repeat an image into a mosaic of `cols` x `rows` copies
+///
+/// Seams of a texture that does not tile show up as lines through the mosaic.
+/// Pixels are copied exactly, so any pixel type works, including 16-bit and
+/// float images.
+///
+/// # Panics
+///
+/// Panics if `cols` or `rows` is 0.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::tiling::tile_preview;
+/// use image::{GrayImage, Luma};
+///
+/// let img = GrayImage::from_fn(3, 2, |x, y| Luma([(x + 3 * y) as u8]));
+/// let mosaic = tile_preview(&img, 2, 3);
+/// assert_eq!(mosaic.dimensions(), (6, 6));
+/// assert_eq!(mosaic.get_pixel(4, 5), img.get_pixel(1, 1));
+/// ```
+pub fn tile_preview<P: Pixel>(
+    img: &ImageBuffer<P, Vec<P::Subpixel>>,
+    cols: u32,
+    rows: u32,
+) -> ImageBuffer<P, Vec<P::Subpixel>> {
+    assert!(cols > 0 && rows > 0, "a tiled preview needs at least 1x1 copies, got {cols}x{rows}");
+    let (width, height) = img.dimensions();
+    ImageBuffer::from_fn(width * cols, height * rows, |x, y| *img.get_pixel(x % width, y % height))
+}
+
+/// Repeat a field into a mosaic of `cols` x `rows` copies, like
+/// [`tile_preview`]
+///
+/// # Panics
+///
+/// Panics if `cols` or `rows` is 0.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::tiling::{seam_discontinuity, tile_preview_field};
+/// use cells::FieldBuffer;
+///
+/// let ramp = FieldBuffer::from_par_fn(4, 4, |x, _| x as f32 / 3.0);
+/// let mosaic = tile_preview_field(&ramp, 2, 2);
+/// assert_eq!(mosaic.dimensions(), (8, 8));
+/// // The seam of the ramp is now inside the mosaic
+/// assert_eq!((mosaic.get(3, 0), mosaic.get(4, 0)), (1.0, 0.0));
+/// assert_eq!(seam_discontinuity(&mosaic), seam_discontinuity(&ramp));
+/// ```
+pub fn tile_preview_field(field: &FieldBuffer, cols: u32, rows: u32) -> FieldBuffer {
+    assert!(cols > 0 && rows > 0, "a tiled preview needs at least 1x1 copies, got {cols}x{rows}");
+    let (width, height) = field.dimensions();
+    FieldBuffer::from_par_fn(width * cols, height * rows, |x, y| field.get(x % width, y % height))
+}
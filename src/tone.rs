@@ -0,0 +1,422 @@
+//! Per-sample tone adjustments of fields.
+//!
+//! These remap every sample on its own, so they keep fields tileable.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::CellsError;
+use crate::field::FieldBuffer;
+
+/// Photoshop-style levels, see [`levels`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Levels {
+    /// The input value mapped to `out_black`
+    pub in_black: f32,
+    /// The input value mapped to `out_white`
+    pub in_white: f32,
+    /// The midtone exponent; above 1 brightens and below 1 darkens the midtones
+    pub gamma: f32,
+    /// The output value of `in_black` and darker inputs
+    pub out_black: f32,
+    /// The output value of `in_white` and brighter inputs
+    pub out_white: f32,
+}
+
+impl Default for Levels {
+    fn default() -> Self {
+        Levels {
+            in_black: 0.0,
+            in_white: 1.0,
+            gamma: 1.0,
+            out_black: 0.0,
+            out_white: 1.0,
+        }
+    }
+}
+
+impl Levels {
+    /// Check that the points are finite, the input range is not empty and gamma is positive
+    pub fn validate(&self) -> Result<(), CellsError> {
+        let invalid = |name, reason: String| Err(CellsError::InvalidParameter { name, reason });
+        let points = [self.in_black, self.in_white, self.out_black, self.out_white];
+        if !(points.iter().all(|v| v.is_finite()) && self.in_black < self.in_white) {
+            return invalid(
+                "levels",
+                format!("the input black point {} must be below the white point {}", self.in_black, self.in_white),
+            );
+        }
+        if !(self.gamma.is_finite() && self.gamma > 0.0) {
+            return invalid("levels gamma", format!("must be positive, got {}", self.gamma));
+        }
+        Ok(())
+    }
+
+    /// Apply the levels to every sample of `field`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the levels do not [validate](Levels::validate).
+    pub fn apply(&self, field: &FieldBuffer) -> FieldBuffer {
+        levels(field, self.in_black, self.in_white, self.gamma, self.out_black, self.out_white)
+    }
+}
+
+impl fmt::Display for Levels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{},{},{},{},{}",
+            self.in_black, self.in_white, self.gamma, self.out_black, self.out_white
+        )
+    }
+}
+
+impl FromStr for Levels {
+    type Err = String;
+
+    /// Parse `IN_BLACK,IN_WHITE,GAMMA,OUT_BLACK,OUT_WHITE` such as `0.1,0.9,1.6,0,1`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("expected IN_BLACK,IN_WHITE,GAMMA,OUT_BLACK,OUT_WHITE such as 0.1,0.9,1.6,0,1, got `{s}`");
+        let values = s
+            .split(',')
+            .map(|value| value.trim().parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| invalid())?;
+        let [in_black, in_white, gamma, out_black, out_white] = values[..] else {
+            return Err(invalid());
+        };
+        let levels = Levels { in_black, in_white, gamma, out_black, out_white };
+        levels.validate().map_err(|err| err.to_string())?;
+        Ok(levels)
+    }
+}
+
+/// Adjust the black point, white point and midtones of a field
+///
+/// Every sample is mapped from `in_black..in_white` to 0-1, clamping values
+/// outside, raised to `1 / gamma` and mapped to `out_black..out_white`. The
+/// default [`Levels`] only clamp to 0-1.
+///
+/// # Arguments
+///
+/// * `field` - The input field to be adjusted
+/// * `in_black` - The input value mapped to `out_black`
+/// * `in_white` - The input value mapped to `out_white`
+/// * `gamma` - The midtone exponent; above 1 brightens and below 1 darkens
+/// * `out_black` - The output value of `in_black` and darker inputs
+/// * `out_white` - The output value of `in_white` and brighter inputs
+///
+/// # Returns
+///
+/// A `FieldBuffer` containing the adjusted field
+///
+/// # Panics
+///
+/// Panics unless `in_black < in_white` and `gamma` is positive and finite.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::tone::{levels, Levels};
+/// use cells::FieldBuffer;
+///
+/// let ramp = FieldBuffer::from_par_fn(11, 1, |x, _| x as f32 / 10.0);
+/// assert_eq!(Levels::default().apply(&ramp), ramp);
+///
+/// let adjusted = levels(&ramp, 0.2, 0.6, 1.0, 0.0, 0.5);
+/// assert_eq!(adjusted.get(1, 0), 0.0);
+/// assert!((adjusted.get(4, 0) - 0.25).abs() < 1e-6);
+/// assert_eq!(adjusted.get(9, 0), 0.5);
+///
+/// // Gamma above 1 lifts the midtones and keeps the end points
+/// let brighter = levels(&ramp, 0.0, 1.0, 2.0, 0.0, 1.0);
+/// assert!(brighter.get(5, 0) > 0.7);
+/// assert_eq!((brighter.get(0, 0), brighter.get(10, 0)), (0.0, 1.0));
+/// ```
+pub fn levels(field: &FieldBuffer, in_black: f32, in_white: f32, gamma: f32, out_black: f32, out_white: f32) -> FieldBuffer {
+    let params = Levels { in_black, in_white, gamma, out_black, out_white };
+    if let Err(err) = params.validate() {
+        panic!("{err}");
+    }
+    let exponent = 1.0 / gamma;
+    field.map(|value| {
+        let t = ((value - in_black) / (in_white - in_black)).clamp(0.0, 1.0);
+        out_black + t.powf(exponent) * (out_white - out_black)
+    })
+}
+
+/// A piecewise-linear tone curve through control points
+///
+/// # Example
+///
+/// ```rust
+/// use cells::tone::ToneCurve;
+///
+/// let curve: ToneCurve = "0:0,0.5:0.3,1:1".parse().unwrap();
+/// assert!((curve.eval(0.25) - 0.15).abs() < 1e-6);
+/// assert!((curve.eval(0.75) - 0.65).abs() < 1e-6);
+/// // Values outside the control points keep the end values
+/// assert_eq!((curve.eval(-1.0), curve.eval(2.0)), (0.0, 1.0));
+/// assert!("0:0,0.5:0.3,0.4:1".parse::<ToneCurve>().is_err());
+///
+/// let identity: ToneCurve = "0:0,1:1".parse().unwrap();
+/// let ramp = cells::FieldBuffer::from_par_fn(11, 1, |x, _| x as f32 / 10.0);
+/// assert_eq!(identity.apply(&ramp), ramp);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct ToneCurve {
+    points: Vec<(f32, f32)>,
+}
+
+impl ToneCurve {
+    /// Create a curve through `(input, output)` control points
+    ///
+    /// The inputs must be finite and strictly increasing, and there must be
+    /// at least two points.
+    pub fn new(points: Vec<(f32, f32)>) -> Result<Self, CellsError> {
+        let invalid = |reason: String| Err(CellsError::InvalidParameter { name: "curve", reason });
+        if points.len() < 2 {
+            return invalid(format!("needs at least two points, got {}", points.len()));
+        }
+        if points.iter().any(|(x, y)| !(x.is_finite() && y.is_finite())) {
+            return invalid("points must be finite".to_string());
+        }
+        if let Some(pair) = points.windows(2).find(|pair| pair[0].0 >= pair[1].0) {
+            return invalid(format!(
+                "point inputs must increase, but {} follows {}",
+                pair[1].0, pair[0].0
+            ));
+        }
+        Ok(ToneCurve { points })
+    }
+
+    /// The control points, by increasing input
+    pub fn points(&self) -> &[(f32, f32)] {
+        &self.points
+    }
+
+    /// The output of the curve for `value`, interpolated linearly between the
+    /// neighboring control points
+    pub fn eval(&self, value: f32) -> f32 {
+        let next = self.points.partition_point(|&(x, _)| x <= value);
+        match next {
+            0 => self.points[0].1,
+            n if n == self.points.len() => self.points[n - 1].1,
+            n => {
+                let ((x0, y0), (x1, y1)) = (self.points[n - 1], self.points[n]);
+                y0 + (value - x0) / (x1 - x0) * (y1 - y0)
+            }
+        }
+    }
+
+    /// Apply the curve to every sample of `field`
+    pub fn apply(&self, field: &FieldBuffer) -> FieldBuffer {
+        field.map(|value| self.eval(value))
+    }
+}
+
+impl fmt::Display for ToneCurve {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let points: Vec<String> = self.points.iter().map(|(x, y)| format!("{x}:{y}")).collect();
+        f.write_str(&points.join(","))
+    }
+}
+
+impl FromStr for ToneCurve {
+    type Err = String;
+
+    /// Parse `IN:OUT` control points separated by commas, such as `0:0,0.5:0.3,1:1`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let points = s
+            .split(',')
+            .map(|point| {
+                let (x, y) = point.split_once(':')?;
+                Some((x.trim().parse().ok()?, y.trim().parse().ok()?))
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| format!("expected IN:OUT points such as 0:0,0.5:0.3,1:1, got `{s}`"))?;
+        ToneCurve::new(points).map_err(|err| err.to_string())
+    }
+}
+
+/// Turn a field into a hard mask
+///
+/// Samples at or above `t` become 1 and the others 0.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::tone::threshold;
+/// use cells::FieldBuffer;
+///
+/// let ramp = FieldBuffer::from_par_fn(10, 1, |x, _| (x as f32 + 0.5) / 10.0);
+/// assert!(threshold(&ramp, 0.0).as_slice().iter().all(|&v| v == 1.0));
+/// assert!(threshold(&ramp, 1.0).as_slice().iter().all(|&v| v == 0.0));
+/// assert_eq!(threshold(&ramp, 0.5).as_slice()[4..6], [0.0, 1.0]);
+/// ```
+pub fn threshold(field: &FieldBuffer, t: f32) -> FieldBuffer {
+    field.map(|value| if value >= t { 1.0 } else { 0.0 })
+}
+
+/// Turn a field into a feathered mask
+///
+/// Samples below `edge0` become 0, samples at or above `edge1` become 1, and
+/// the samples between follow the smooth Hermite step `3x^2 - 2x^3`. With
+/// equal edges this is [`threshold`].
+///
+/// # Panics
+///
+/// Panics if `edge0` is above `edge1`; use [`invert`] for falling masks.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::tone::{smoothstep, threshold};
+/// use cells::FieldBuffer;
+///
+/// let ramp = FieldBuffer::from_par_fn(10, 1, |x, _| (x as f32 + 0.5) / 10.0);
+/// assert_eq!(smoothstep(&ramp, 0.45, 0.45), threshold(&ramp, 0.45));
+/// let soft = smoothstep(&ramp, 0.2, 0.8);
+/// assert_eq!((soft.get(0, 0), soft.get(9, 0)), (0.0, 1.0));
+/// assert!((soft.get(4, 0) + soft.get(5, 0) - 1.0).abs() < 1e-6);
+/// ```
+pub fn smoothstep(field: &FieldBuffer, edge0: f32, edge1: f32) -> FieldBuffer {
+    assert!(edge0 <= edge1, "smoothstep edges must not decrease, got {edge0} and {edge1}");
+    if edge0 == edge1 {
+        return threshold(field, edge0);
+    }
+    field.map(|value| {
+        let t = ((value - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    })
+}
+
+/// Flip a 0-1 field, mapping every sample `v` to `1 - v`
+pub fn invert(field: &FieldBuffer) -> FieldBuffer {
+    field.map(|value| 1.0 - value)
+}
+
+/// A mask extracted from a field, see [`threshold`] and [`smoothstep`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mask {
+    /// 1 at or above the value, 0 below
+    Threshold(f32),
+    /// A smooth step from 0 at the first edge to 1 at the second
+    Smoothstep(f32, f32),
+}
+
+impl Mask {
+    /// Extract the mask from `field`
+    pub fn apply(self, field: &FieldBuffer) -> FieldBuffer {
+        match self {
+            Mask::Threshold(t) => threshold(field, t),
+            Mask::Smoothstep(edge0, edge1) => smoothstep(field, edge0, edge1),
+        }
+    }
+}
+
+impl fmt::Display for Mask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mask::Threshold(t) => write!(f, "threshold:{t}"),
+            Mask::Smoothstep(edge0, edge1) => write!(f, "smoothstep:{edge0},{edge1}"),
+        }
+    }
+}
+
+impl FromStr for Mask {
+    type Err = String;
+
+    /// Parse `threshold:<t>` or `smoothstep:<edge0>,<edge1>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("expected threshold:<t> or smoothstep:<edge0>,<edge1>, got `{s}`");
+        let number = |v: &str| v.trim().parse::<f32>().ok().filter(|v| v.is_finite()).ok_or_else(invalid);
+        match s.split_once(':') {
+            Some(("threshold", t)) => Ok(Mask::Threshold(number(t)?)),
+            Some(("smoothstep", edges)) => {
+                let (edge0, edge1) = edges.split_once(',').ok_or_else(invalid)?;
+                let (edge0, edge1) = (number(edge0)?, number(edge1)?);
+                if edge0 > edge1 {
+                    return Err(format!("smoothstep edges must not decrease, got {edge0},{edge1}"));
+                }
+                Ok(Mask::Smoothstep(edge0, edge1))
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// The 8x8 Bayer matrix of ordered dithering, holding every rank 0-63 once
+const BAYER_8X8: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+/// Reduce a 0-1 field to `levels` evenly spaced values, including 0 and 1
+///
+/// Samples are rounded to the nearest level, so banding appears where the
+/// field changes slowly. 256 levels match the steps of 8-bit output and leave
+/// saved textures unchanged.
+///
+/// # Panics
+///
+/// Panics if `levels` is below 2.
+///
+/// # Example
+///
+/// ```rust
+/// use std::collections::HashSet;
+/// use cells::tone::posterize;
+/// use cells::FieldBuffer;
+///
+/// let ramp = FieldBuffer::from_par_fn(100, 1, |x, _| x as f32 / 99.0);
+/// let bands: HashSet<u32> = posterize(&ramp, 6).as_slice().iter().map(|v| v.to_bits()).collect();
+/// assert_eq!(bands.len(), 6);
+/// assert_eq!(posterize(&ramp, 256).to_luma_image(), ramp.to_luma_image());
+/// ```
+pub fn posterize(field: &FieldBuffer, levels: u32) -> FieldBuffer {
+    assert!(levels >= 2, "posterizing needs at least 2 levels, got {levels}");
+    let steps = (levels - 1) as f32;
+    field.map(|value| (value.clamp(0.0, 1.0) * steps).round() / steps)
+}
+
+/// Reduce a 0-1 field to `levels` values with 8x8 ordered dithering
+///
+/// Each sample is rounded up or down by the Bayer matrix at its position, so
+/// the share of samples on the upper level follows the value in between and
+/// the banding of [`posterize`] turns into a regular pattern. The pattern
+/// repeats every 8 samples and stays seamless on fields whose dimensions are
+/// multiples of 8.
+///
+/// # Panics
+///
+/// Panics if `levels` is below 2.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::tone::posterize_dithered;
+/// use cells::FieldBuffer;
+///
+/// let gray = FieldBuffer::from_par_fn(8, 8, |_, _| 0.25);
+/// let dithered = posterize_dithered(&gray, 2);
+/// let white = dithered.as_slice().iter().filter(|&&v| v == 1.0).count();
+/// assert_eq!(white, 16);
+/// ```
+pub fn posterize_dithered(field: &FieldBuffer, levels: u32) -> FieldBuffer {
+    assert!(levels >= 2, "posterizing needs at least 2 levels, got {levels}");
+    let steps = (levels - 1) as f32;
+    FieldBuffer::from_par_fn(field.width(), field.height(), |x, y| {
+        let rank = BAYER_8X8[(y % 8) as usize][(x % 8) as usize];
+        let offset = (rank as f32 + 0.5) / 64.0;
+        let value = field.get(x, y).clamp(0.0, 1.0);
+        (value * steps + offset).floor().min(steps) / steps
+    })
+}
@@ -0,0 +1,431 @@
+//! Lossless transforms of tileable textures, such as scrolling them to move
+//! the seam, rotations and mirroring.
+//!
+//! Every transform maps the wrap-around edges onto wrap-around edges, so a
+//! tileable texture stays tileable. The polar remaps [`to_polar`] and
+//! [`from_polar`] are the exception: they resample the field bilinearly and
+//! only the angular axis of the polar layout wraps.
+
+use std::f32::consts::TAU;
+use std::fmt;
+use std::str::FromStr;
+
+use image::{ImageBuffer, Pixel};
+
+use crate::error::CellsError;
+use crate::field::FieldBuffer;
+use crate::geometry::Point;
+
+/// Scroll an image by `(dx, dy)` pixels, wrapping the pixels that leave one
+/// edge around to the opposite edge
+///
+/// Because tileable textures repeat, scrolling only changes where the tile
+/// border falls, e.g. to move an eye-catching feature away from it. Offsets
+/// may be negative or larger than the image; only their remainder matters.
+///
+/// # Arguments
+///
+/// * `img` - The image to scroll
+/// * `dx` - How far the content moves right, in pixels
+/// * `dy` - How far the content moves down, in pixels
+///
+/// # Returns
+///
+/// An image of the same size where the pixel at `(x, y)` is the pixel of
+/// `img` at `(x - dx, y - dy)`, wrapped
+///
+/// # Example
+///
+/// ```rust
+/// use cells::transform::offset;
+/// use image::{GrayImage, Luma};
+///
+/// let img = GrayImage::from_fn(4, 3, |x, y| Luma([(x + 4 * y) as u8]));
+/// let scrolled = offset(&img, 1, -1);
+/// assert_eq!(scrolled.get_pixel(1, 0), img.get_pixel(0, 1));
+/// assert_eq!(offset(&img, -7, 9), offset(&img, 1, 0));
+/// ```
+pub fn offset<P: Pixel>(img: &ImageBuffer<P, Vec<P::Subpixel>>, dx: i64, dy: i64) -> ImageBuffer<P, Vec<P::Subpixel>> {
+    let (width, height) = img.dimensions();
+    ImageBuffer::from_fn(width, height, |x, y| {
+        let source_x = (x as i64 - dx).rem_euclid(width as i64) as u32;
+        let source_y = (y as i64 - dy).rem_euclid(height as i64) as u32;
+        *img.get_pixel(source_x, source_y)
+    })
+}
+
+/// Scroll a field by `(dx, dy)` samples with wraparound, like [`offset`]
+///
+/// # Example
+///
+/// ```rust
+/// use cells::transform::offset_field;
+/// use cells::FieldBuffer;
+///
+/// let field = FieldBuffer::from_par_fn(4, 4, |x, y| (x + 4 * y) as f32);
+/// let scrolled = offset_field(&field, 2, 3);
+/// assert_eq!(scrolled.get(2, 3), field.get(0, 0));
+/// assert_eq!(scrolled.get(0, 0), field.get(2, 1));
+/// ```
+pub fn offset_field(field: &FieldBuffer, dx: i64, dy: i64) -> FieldBuffer {
+    let (width, height) = field.dimensions();
+    FieldBuffer::from_par_fn(width, height, |x, y| field.get_wrapped(x as i64 - dx, y as i64 - dy))
+}
+
+/// A rotation or mirroring of a whole field
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transform {
+    /// A quarter turn clockwise, swapping width and height
+    Rotate90,
+    /// A half turn
+    Rotate180,
+    /// A quarter turn counterclockwise, swapping width and height
+    Rotate270,
+    /// Mirror left to right
+    FlipH,
+    /// Mirror top to bottom
+    FlipV,
+}
+
+impl Transform {
+    /// Transform a field
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::transform::Transform;
+    /// use cells::FieldBuffer;
+    ///
+    /// // 0 1 2
+    /// // 3 4 5
+    /// let field = FieldBuffer::from_par_fn(3, 2, |x, y| (x + 3 * y) as f32);
+    /// let rotated = Transform::Rotate90.apply(&field);
+    /// assert_eq!(rotated.dimensions(), (2, 3));
+    /// assert_eq!(rotated.as_slice(), [3.0, 0.0, 4.0, 1.0, 5.0, 2.0]);
+    /// assert_eq!(Transform::FlipH.apply(&field).as_slice(), [2.0, 1.0, 0.0, 5.0, 4.0, 3.0]);
+    /// ```
+    pub fn apply(self, field: &FieldBuffer) -> FieldBuffer {
+        let (width, height) = field.dimensions();
+        match self {
+            Transform::Rotate90 => FieldBuffer::from_par_fn(height, width, |x, y| field.get(y, height - 1 - x)),
+            Transform::Rotate180 => {
+                FieldBuffer::from_par_fn(width, height, |x, y| field.get(width - 1 - x, height - 1 - y))
+            }
+            Transform::Rotate270 => FieldBuffer::from_par_fn(height, width, |x, y| field.get(width - 1 - y, x)),
+            Transform::FlipH => FieldBuffer::from_par_fn(width, height, |x, y| field.get(width - 1 - x, y)),
+            Transform::FlipV => FieldBuffer::from_par_fn(width, height, |x, y| field.get(x, height - 1 - y)),
+        }
+    }
+}
+
+impl fmt::Display for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Transform::Rotate90 => "rotate90",
+            Transform::Rotate180 => "rotate180",
+            Transform::Rotate270 => "rotate270",
+            Transform::FlipH => "flip-h",
+            Transform::FlipV => "flip-v",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for Transform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rotate90" => Ok(Transform::Rotate90),
+            "rotate180" => Ok(Transform::Rotate180),
+            "rotate270" => Ok(Transform::Rotate270),
+            "flip-h" => Ok(Transform::FlipH),
+            "flip-v" => Ok(Transform::FlipV),
+            _ => Err(format!(
+                "unknown transform `{s}`, expected rotate90, rotate180, rotate270, flip-h or flip-v"
+            )),
+        }
+    }
+}
+
+/// The symmetry [`symmetrize`] forces onto a field
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Symmetry {
+    /// Mirrored left to right
+    MirrorX,
+    /// Mirrored top to bottom
+    MirrorY,
+    /// Mirrored along both axes, the 4-fold kaleidoscope of ornamental tiles
+    #[default]
+    MirrorXy,
+    /// Unchanged by quarter turns; square fields only
+    Rotate4,
+}
+
+impl Symmetry {
+    /// The transforms whose results are averaged with the field itself
+    fn copies(self) -> &'static [Transform] {
+        match self {
+            Symmetry::MirrorX => &[Transform::FlipH],
+            Symmetry::MirrorY => &[Transform::FlipV],
+            Symmetry::MirrorXy => &[Transform::FlipH, Transform::FlipV, Transform::Rotate180],
+            Symmetry::Rotate4 => &[Transform::Rotate90, Transform::Rotate180, Transform::Rotate270],
+        }
+    }
+}
+
+impl fmt::Display for Symmetry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Symmetry::MirrorX => "mirror-x",
+            Symmetry::MirrorY => "mirror-y",
+            Symmetry::MirrorXy => "mirror-xy",
+            Symmetry::Rotate4 => "rotate4",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for Symmetry {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mirror-x" => Ok(Symmetry::MirrorX),
+            "mirror-y" => Ok(Symmetry::MirrorY),
+            "mirror-xy" => Ok(Symmetry::MirrorXy),
+            "rotate4" => Ok(Symmetry::Rotate4),
+            _ => Err(format!("unknown symmetry `{s}`, expected mirror-x, mirror-y, mirror-xy or rotate4")),
+        }
+    }
+}
+
+/// Force a symmetry onto a field by averaging it with its mirrored or
+/// rotated copies
+///
+/// The copies are transforms of the whole field, so the result keeps the
+/// field's tileability and repeats as a symmetric ornament.
+///
+/// # Arguments
+///
+/// * `field` - The field to symmetrize
+/// * `symmetry` - The symmetry of the result
+///
+/// # Returns
+///
+/// The symmetric field, or [`CellsError::InvalidParameter`] when
+/// [`Symmetry::Rotate4`] is asked of a field that is not square
+///
+/// # Example
+///
+/// ```rust
+/// use cells::transform::{symmetrize, Symmetry, Transform};
+/// use cells::FieldBuffer;
+///
+/// let field = FieldBuffer::from_par_fn(4, 4, |x, y| (x * x + y) as f32);
+/// let ornament = symmetrize(&field, Symmetry::MirrorXy).unwrap();
+/// assert_eq!(Transform::FlipH.apply(&ornament), ornament);
+/// assert_eq!(Transform::FlipV.apply(&ornament), ornament);
+///
+/// let err = symmetrize(&FieldBuffer::new(4, 2), Symmetry::Rotate4).unwrap_err();
+/// assert_eq!(err.to_string(), "invalid symmetry: rotate4 needs a square field, got 4x2");
+/// ```
+pub fn symmetrize(field: &FieldBuffer, symmetry: Symmetry) -> Result<FieldBuffer, CellsError> {
+    let (width, height) = field.dimensions();
+    if symmetry == Symmetry::Rotate4 && width != height {
+        return Err(CellsError::InvalidParameter {
+            name: "symmetry",
+            reason: format!("rotate4 needs a square field, got {width}x{height}"),
+        });
+    }
+    let copies: Vec<FieldBuffer> = symmetry.copies().iter().map(|transform| transform.apply(field)).collect();
+    let scale = 1.0 / (copies.len() + 1) as f32;
+    Ok(FieldBuffer::from_par_fn(width, height, |x, y| {
+        let sum: f32 = copies.iter().map(|copy| copy.get(x, y)).sum();
+        (field.get(x, y) + sum) * scale
+    }))
+}
+
+/// How the polar remaps read samples past the edge of the field along the
+/// radius
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RadialExtension {
+    /// Repeat the outermost samples
+    #[default]
+    Clamp,
+    /// Reflect the field at its edges, repeating the outermost samples once
+    Mirror,
+}
+
+impl RadialExtension {
+    /// The index of the sample read for `index` in a row or column of `len`
+    /// samples
+    fn resolve(self, index: i64, len: u32) -> u32 {
+        let len = len as i64;
+        match self {
+            RadialExtension::Clamp => index.clamp(0, len - 1) as u32,
+            RadialExtension::Mirror => {
+                let folded = index.rem_euclid(2 * len);
+                (if folded < len { folded } else { 2 * len - 1 - folded }) as u32
+            }
+        }
+    }
+}
+
+impl fmt::Display for RadialExtension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            RadialExtension::Clamp => "clamp",
+            RadialExtension::Mirror => "mirror",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for RadialExtension {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "clamp" => Ok(RadialExtension::Clamp),
+            "mirror" => Ok(RadialExtension::Mirror),
+            _ => Err(format!("unknown radial extension `{s}`, expected clamp or mirror")),
+        }
+    }
+}
+
+/// The bilinear interpolation at `(x, y)` of the samples `read` returns for
+/// integer positions
+fn bilinear(x: f32, y: f32, read: impl Fn(i64, i64) -> f32) -> f32 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (tx, ty) = (x - x0, y - y0);
+    let (x0, y0) = (x0 as i64, y0 as i64);
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let top = lerp(read(x0, y0), read(x0 + 1, y0), tx);
+    let bottom = lerp(read(x0, y0 + 1), read(x0 + 1, y0 + 1), tx);
+    lerp(top, bottom, ty)
+}
+
+/// The polar layout of a field around `center`, in pixels, and the radius of
+/// its last row, the distance to the farthest corner
+fn polar_frame(field: &FieldBuffer, center: Point) -> (f32, f32, f32) {
+    let (width, height) = field.dimensions();
+    let (cx, cy) = (center.x * width as f32, center.y * height as f32);
+    let (far_x, far_y) = (cx.max(width as f32 - cx), cy.max(height as f32 - cy));
+    (cx, cy, far_x.hypot(far_y))
+}
+
+/// Remap a field to polar coordinates around `center`, clamping reads past
+/// its edges; see [`to_polar_with`]
+pub fn to_polar(field: &FieldBuffer, center: Point) -> FieldBuffer {
+    to_polar_with(field, center, RadialExtension::Clamp)
+}
+
+/// Remap a field to polar coordinates around `center`
+///
+/// Columns of the result are angles, clockwise from the right over the
+/// whole turn, so its left and right edges meet seamlessly. Rows are radii,
+/// from `center` in the first to the farthest corner of the field in the
+/// last, so [`from_polar_with`] can rebuild every pixel. Blurring the polar
+/// field horizontally and converting it back streaks it around `center`,
+/// vertically along rays, as in sunbursts.
+///
+/// # Arguments
+///
+/// * `field` - The field to remap; the result has the same size
+/// * `center` - The pole, in texture units where (0.5, 0.5) is the middle
+/// * `extension` - How rays past the edge of `field` read it
+///
+/// # Panics
+///
+/// Panics if `center` is not finite.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::transform::{to_polar_with, RadialExtension};
+/// use cells::{FieldBuffer, Point};
+///
+/// let field = FieldBuffer::from_par_fn(8, 8, |x, _| x as f32);
+/// let center = Point { x: 0.5, y: 0.5 };
+/// let polar = to_polar_with(&field, center, RadialExtension::Mirror);
+/// // The first row is the center, read from between the four middle pixels
+/// assert!(polar.as_slice()[..8].iter().all(|&v| v == 3.5));
+/// // The ray to the right (column 0) passes the right edge and mirrors back,
+/// // while the ray to the left (column 4) falls towards 0
+/// assert_eq!((polar.get(0, 5), polar.get(4, 5)), (7.0, 0.0));
+/// assert!(polar.get(0, 7) < 6.0 && polar.get(4, 7) > 1.0);
+/// assert_eq!(to_polar_with(&field, center, RadialExtension::Clamp).get(0, 7), 7.0);
+/// ```
+pub fn to_polar_with(field: &FieldBuffer, center: Point, extension: RadialExtension) -> FieldBuffer {
+    assert!(center.x.is_finite() && center.y.is_finite(), "the center must be finite, got {center:?}");
+    let (width, height) = field.dimensions();
+    let (cx, cy, max_radius) = polar_frame(field, center);
+    let radius_step = max_radius / (height.max(2) - 1) as f32;
+    crate::stage("to polar", format_args!("{width}x{height}, around ({}, {})", center.x, center.y), || {
+        FieldBuffer::from_par_fn(width, height, |u, v| {
+            let (sin, cos) = (TAU * u as f32 / width as f32).sin_cos();
+            let radius = v as f32 * radius_step;
+            // Pixel centers are half a pixel from the edges of the texture
+            let (x, y) = (cx + radius * cos - 0.5, cy + radius * sin - 0.5);
+            bilinear(x, y, |x, y| field.get(extension.resolve(x, width), extension.resolve(y, height)))
+        })
+    })
+}
+
+/// Remap a polar field around `center` back to Cartesian coordinates,
+/// clamping radii past its last row; see [`from_polar_with`]
+pub fn from_polar(polar: &FieldBuffer, center: Point) -> FieldBuffer {
+    from_polar_with(polar, center, RadialExtension::Clamp)
+}
+
+/// Remap a polar field around `center` back to Cartesian coordinates, the
+/// inverse of [`to_polar_with`]
+///
+/// Every pixel reads the polar field at its angle, wrapping around the
+/// full turn, and its radius. The pixel at `center` itself has no angle and
+/// reads the first column of the first row.
+///
+/// # Arguments
+///
+/// * `polar` - A polar field laid out like the results of [`to_polar_with`];
+///   the result has the same size
+/// * `center` - The pole, in texture units where (0.5, 0.5) is the middle
+/// * `extension` - How radii between and past the first and last rows read
+///   them
+///
+/// # Panics
+///
+/// Panics if `center` is not finite.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::transform::{from_polar, to_polar};
+/// use cells::{FieldBuffer, Point};
+///
+/// // Rows of a polar field are rings around the center
+/// let rings = FieldBuffer::from_par_fn(9, 9, |_, v| v as f32);
+/// let center = Point { x: 0.5, y: 0.5 };
+/// let cartesian = from_polar(&rings, center);
+/// assert_eq!(cartesian.get(4, 4), 0.0);
+/// assert!(cartesian.get(0, 0) > 7.0 && cartesian.get(0, 0) == cartesian.get(8, 8));
+/// // A field of one value stays one value either way
+/// let gray = FieldBuffer::from_par_fn(9, 9, |_, _| 0.25);
+/// assert_eq!(from_polar(&to_polar(&gray, center), center), gray);
+/// ```
+pub fn from_polar_with(polar: &FieldBuffer, center: Point, extension: RadialExtension) -> FieldBuffer {
+    assert!(center.x.is_finite() && center.y.is_finite(), "the center must be finite, got {center:?}");
+    let (width, height) = polar.dimensions();
+    let (cx, cy, max_radius) = polar_frame(polar, center);
+    let rows_per_pixel = (height.max(2) - 1) as f32 / max_radius;
+    crate::stage("from polar", format_args!("{width}x{height}, around ({}, {})", center.x, center.y), || {
+        FieldBuffer::from_par_fn(width, height, |x, y| {
+            let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+            // atan2 is 0 rather than NaN at the center itself
+            let u = dy.atan2(dx).rem_euclid(TAU) / TAU * width as f32;
+            let v = dx.hypot(dy) * rows_per_pixel;
+            bilinear(u, v, |u, v| polar.get(u.rem_euclid(width as i64) as u32, extension.resolve(v, height)))
+        })
+    })
+}
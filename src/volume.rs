@@ -0,0 +1,134 @@
+//! Fractal noise volumes tiling along all three axes, one Z slice at a time.
+//!
+//! The 4D torus trick of [`crate::noise`] would need six noise dimensions to
+//! wrap along a third axis, so volumes use their own lattice gradient noise
+//! instead: improved Perlin noise whose integer lattice wraps around after a
+//! whole number of cells along every axis. Every octave therefore has a whole
+//! number of noise features across the volume, and slices are evaluated
+//! independently, so a volume of any depth is generated without ever holding
+//! more than one slice.
+
+use crate::field::FieldBuffer;
+use crate::noise::{perlin_from_fbm, sum_octaves, FbmParams};
+
+/// Generate one Z slice of a raw fractal noise volume tiling along x, y and z
+///
+/// Octaves are summed and shaped as by
+/// [`generate_fbm_field`](crate::noise::generate_fbm_field), but every octave
+/// frequency is rounded to a whole number of at least 1, since the lattice
+/// only wraps after whole cells. `params.kind` is ignored: the base noise is
+/// always the periodic Perlin noise of this module, seeded by `params.seed`.
+///
+/// # Arguments
+///
+/// * `size` - The width and height of the slice in pixels
+/// * `depth` - The number of slices in the volume
+/// * `z` - The index of the slice, from 0 to `depth - 1`
+/// * `params` - The octave structure, style and seed of the noise
+///
+/// # Returns
+///
+/// A `FieldBuffer` containing the raw fBm values of the slice
+///
+/// # Panics
+///
+/// Panics if [`FbmParams::validate`] rejects `params`.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::noise::FbmParams;
+/// use cells::volume::generate_volume_slice;
+///
+/// let params = FbmParams { frequency: 2.0, octaves: 3, seed: 5, ..Default::default() };
+/// let slice = generate_volume_slice(32, 16, 3, &params);
+/// assert_eq!(slice.dimensions(), (32, 32));
+/// assert!(slice.as_slice().iter().all(|v| (-1.0..=1.0).contains(v)));
+/// // The slice after the last is the first again
+/// assert_eq!(generate_volume_slice(32, 16, 16, &params), generate_volume_slice(32, 16, 0, &params));
+/// ```
+pub fn generate_volume_slice(size: u32, depth: u32, z: u32, params: &FbmParams) -> FieldBuffer {
+    if let Err(err) = params.validate() {
+        panic!("{err}");
+    }
+    let w = z as f64 / depth as f64;
+    FieldBuffer::from_par_fn(size, size, |x, y| {
+        let (u, v) = (x as f64 / size as f64, y as f64 / size as f64);
+        sum_octaves(params, |octave, frequency| {
+            let period = frequency.round().max(1.0);
+            let seed = params.seed ^ (octave as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+            // A seeded shift keeps the lattice points, where the noise is 0,
+            // off the pixel grid
+            let shift = hash([u64::MAX; 3], seed);
+            let [sx, sy, sz] = [0, 21, 42].map(|bits| (shift >> bits & 0xFFFF) as f64 / 65536.0);
+            periodic_perlin([u * period + sx, v * period + sy, w * period + sz], period as i64, seed)
+        })
+    })
+}
+
+/// Generate one Z slice of a tileable noise volume with values from 0 to 1
+///
+/// This is [`generate_volume_slice`] mapping plain fBm from [-1, 1] like
+/// [`generate_perlin_field`](crate::noise::generate_perlin_field).
+///
+/// # Panics
+///
+/// Panics if [`FbmParams::validate`] rejects `params`.
+pub fn generate_volume_texture_slice(size: u32, depth: u32, z: u32, params: &FbmParams) -> FieldBuffer {
+    perlin_from_fbm(generate_volume_slice(size, depth, z, params), params)
+}
+
+/// Improved Perlin noise at `p` on an integer lattice wrapping after `period`
+/// cells along every axis, clamped to [-1, 1]
+fn periodic_perlin(p: [f64; 3], period: i64, seed: u64) -> f64 {
+    let cell = p.map(|c| c.floor());
+    let [fx, fy, fz] = [p[0] - cell[0], p[1] - cell[1], p[2] - cell[2]];
+    let [cx, cy, cz] = cell.map(|c| c as i64);
+    let corner = |dx: i64, dy: i64, dz: i64| {
+        let lattice = [cx + dx, cy + dy, cz + dz].map(|c| c.rem_euclid(period) as u64);
+        gradient(hash(lattice, seed), fx - dx as f64, fy - dy as f64, fz - dz as f64)
+    };
+    let lerp = |t: f64, a: f64, b: f64| a + t * (b - a);
+    let (sx, sy, sz) = (fade(fx), fade(fy), fade(fz));
+
+    let near = lerp(sy, lerp(sx, corner(0, 0, 0), corner(1, 0, 0)), lerp(sx, corner(0, 1, 0), corner(1, 1, 0)));
+    let far = lerp(sy, lerp(sx, corner(0, 0, 1), corner(1, 0, 1)), lerp(sx, corner(0, 1, 1), corner(1, 1, 1)));
+    lerp(sz, near, far).clamp(-1.0, 1.0)
+}
+
+/// The quintic smoothstep easing the interpolation between lattice points
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+/// The dot product of the offset with one of the 12 edge gradients of
+/// improved Perlin noise, picked by `hash`
+fn gradient(hash: u64, x: f64, y: f64, z: f64) -> f64 {
+    match hash % 12 {
+        0 => x + y,
+        1 => -x + y,
+        2 => x - y,
+        3 => -x - y,
+        4 => x + z,
+        5 => -x + z,
+        6 => x - z,
+        7 => -x - z,
+        8 => y + z,
+        9 => -y + z,
+        10 => y - z,
+        _ => -y - z,
+    }
+}
+
+/// Scramble a lattice point and seed into well-distributed bits (SplitMix64
+/// finalizer)
+fn hash(lattice: [u64; 3], seed: u64) -> u64 {
+    let mut h = seed;
+    for c in lattice {
+        h = (h ^ c).wrapping_add(0x9E37_79B9_7F4A_7C15);
+        h = (h ^ (h >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        h = (h ^ (h >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        h ^= h >> 31;
+    }
+    h
+}
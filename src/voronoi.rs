@@ -0,0 +1,1355 @@
+//! Tileable Voronoi cell textures.
+
+use std::collections::BTreeSet;
+use std::f64::consts::TAU;
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+use ::noise::core::worley::ReturnType;
+use ::noise::{NoiseFn, Worley};
+use image::{ImageBuffer, Luma, Rgb};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::band::BandSource;
+use crate::color::CellColoring;
+use crate::error::CellsError;
+use crate::field::FieldBuffer;
+use crate::filter::percentile_range;
+use crate::geometry::{DistanceMetric, Point, Weighting};
+use crate::grid::PointGrid;
+use crate::noise::{generate_fbm_field, FbmParams};
+use crate::sampling::{lloyd_relax_with, PointDistribution};
+
+/// Most octaves of a fractal diagram; the eighth has 16384 times the points
+/// of the first
+pub const MAX_OCTAVES: u32 = 8;
+
+/// The Worley feature recorded for every pixel
+///
+/// `F1` is the distance to the nearest point and `F2` the distance to the
+/// second-nearest point. With a single point `F2` equals `F1`. `CellValue` is
+/// no distance but the value of the nearest point, see
+/// [`VoronoiParams::cell_values`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Feature {
+    /// Distance to the nearest point, the classic cell look
+    #[default]
+    F1,
+    /// Distance to the second-nearest point
+    F2,
+    /// Difference between F2 and F1, zero exactly on the cell boundaries
+    F2MinusF1,
+    /// The value of the nearest point, constant over every cell unless
+    /// smoothed by [`VoronoiParams::value_smoothing`]
+    CellValue,
+}
+
+impl Feature {
+    /// Combine the nearest and second-nearest distances into this feature;
+    /// [`Feature::CellValue`] combines no distances and gives `f1`
+    pub fn value(self, f1: f32, f2: f32) -> f32 {
+        match self {
+            Feature::F1 | Feature::CellValue => f1,
+            Feature::F2 => f2,
+            Feature::F2MinusF1 => f2 - f1,
+        }
+    }
+
+    /// Whether the feature is smallest on the cell boundaries rather than at
+    /// the cell centers
+    fn small_at_edges(self) -> bool {
+        self == Feature::F2MinusF1
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Feature::F1 => "f1",
+            Feature::F2 => "f2",
+            Feature::F2MinusF1 => "f2-f1",
+            Feature::CellValue => "cell-value",
+        })
+    }
+}
+
+impl FromStr for Feature {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "f1" => Ok(Feature::F1),
+            "f2" => Ok(Feature::F2),
+            "f2-f1" => Ok(Feature::F2MinusF1),
+            "cell-value" => Ok(Feature::CellValue),
+            _ => Err(format!("unknown feature `{s}`, expected one of f1, f2, f2-f1, cell-value")),
+        }
+    }
+}
+
+serde_via_str!(Feature);
+
+/// What `noise::Worley` returns for every pixel
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WorleyReturn {
+    /// Distance to the nearest feature point, like [`Feature::F1`]
+    #[default]
+    Distance,
+    /// A random value per cell, like flat-colored cells
+    Value,
+}
+
+impl fmt::Display for WorleyReturn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            WorleyReturn::Distance => "distance",
+            WorleyReturn::Value => "value",
+        })
+    }
+}
+
+impl FromStr for WorleyReturn {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "distance" => Ok(WorleyReturn::Distance),
+            "value" => Ok(WorleyReturn::Value),
+            _ => Err(format!("unknown Worley return type `{s}`, expected distance or value")),
+        }
+    }
+}
+
+serde_via_str!(WorleyReturn);
+
+/// The implementation computing the Voronoi features
+///
+/// # Example
+///
+/// Both backends record raw distances in texture widths, so they can stand in
+/// for each other:
+///
+/// ```rust
+/// use cells::voronoi::{generate_voronoi_features, VoronoiBackend, VoronoiParams, WorleyReturn};
+///
+/// let grid = VoronoiParams { size: 64, num_points: 64, ..Default::default() };
+/// let worley = VoronoiParams {
+///     backend: VoronoiBackend::NoiseWorley { frequency: 8.0, return_type: WorleyReturn::Distance },
+///     ..grid.clone()
+/// };
+/// let max_distance = |params| generate_voronoi_features(params).min_max().unwrap().1;
+/// let (grid_max, worley_max) = (max_distance(&grid), max_distance(&worley));
+/// assert!(worley_max > grid_max / 4.0 && worley_max < grid_max * 4.0);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum VoronoiBackend {
+    /// The crate's own point placement and toroidal [`PointGrid`] queries,
+    /// supporting every feature, metric and weighting
+    #[default]
+    Grid,
+    /// `noise::Worley` sampled on the same 4D torus as the fractal noise, with
+    /// its own implicit feature points; useful to cross-check the grid
+    /// backend
+    NoiseWorley {
+        /// The number of Worley cells across the texture along each axis
+        frequency: f64,
+        /// Whether to record distances or per-cell values
+        return_type: WorleyReturn,
+    },
+}
+
+/// Parameters of the tileable Voronoi generator
+///
+/// The parameters serialize with the command line syntax of their values, and
+/// missing keys take their defaults:
+///
+/// ```rust
+/// use cells::voronoi::{Feature, VoronoiParams};
+///
+/// let params: VoronoiParams = toml::from_str("points = 60\nfeature = \"f2-f1\"").unwrap();
+/// assert_eq!(params, VoronoiParams { num_points: 60, feature: Feature::F2MinusF1, ..Default::default() });
+/// assert_eq!(toml::from_str::<VoronoiParams>(&toml::to_string(&params).unwrap()).unwrap(), params);
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VoronoiParams {
+    /// The width and height of the output image in pixels
+    pub size: u32,
+    /// The number of Voronoi cell centers
+    #[serde(alias = "points")]
+    pub num_points: usize,
+    /// How the cell centers are placed
+    pub distribution: PointDistribution,
+    /// Lloyd relaxation iterations evening out the cell sizes, 0 to disable
+    #[serde(alias = "relax")]
+    pub relax_iterations: u32,
+    /// Seed for the point placement; equal seeds give identical textures
+    pub seed: u64,
+    /// The distance feature recorded for every pixel
+    pub feature: Feature,
+    /// The values of the points for [`Feature::CellValue`], by point index
+    /// and repeating when there are more points; empty to draw a value from
+    /// 0 to 1 for every point from the seed
+    pub cell_values: Vec<f32>,
+    /// The F2 - F1 distance, in texture widths, over which
+    /// [`Feature::CellValue`] blends into the value of the second-nearest
+    /// point, reaching their average on the cell boundaries; 0 for flat cells
+    pub value_smoothing: f32,
+    /// The metric measuring distances to the cell centers
+    pub metric: DistanceMetric,
+    /// The factors of the x and y offsets in every distance; a larger
+    /// factor for one axis stretches the cells along the other, such as
+    /// `(1.0, 3.0)` for cells three times as wide as they are tall
+    pub cell_scale: (f32, f32),
+    /// How random per-point weights vary the cell sizes
+    pub weighting: Weighting,
+    /// The inclusive range per-point weights are drawn from when weighting
+    pub weight_range: (f32, f32),
+    /// Make the cell centers bright and the edges dark instead of the reverse
+    pub invert: bool,
+    /// Largest distance, in texture widths, a pixel's sample position is
+    /// moved by the domain warp, 0 to disable
+    pub warp_strength: f32,
+    /// Base frequency of the tileable fBm noise steering the domain warp
+    pub warp_frequency: f64,
+    /// The implementation computing the features; the point placement,
+    /// feature, metric, cell scale and weighting only apply to
+    /// [`VoronoiBackend::Grid`]
+    pub backend: VoronoiBackend,
+    /// Low and high percentiles of the features stretched to 0-1, clipping
+    /// outliers beyond them; `None` divides by the largest feature
+    pub clip: Option<(f32, f32)>,
+    /// Number of diagrams summed by [`generate_voronoi_field`], each with four
+    /// times the points of the one before; the raw features and the cell maps
+    /// only use the first
+    pub octaves: u32,
+    /// The weight of every octave relative to the one before
+    pub persistence: f32,
+}
+
+impl Default for VoronoiParams {
+    fn default() -> Self {
+        VoronoiParams {
+            size: 512,
+            num_points: 240,
+            distribution: PointDistribution::Uniform,
+            relax_iterations: 0,
+            seed: 0,
+            feature: Feature::F1,
+            cell_values: Vec::new(),
+            value_smoothing: 0.0,
+            metric: DistanceMetric::Euclidean,
+            cell_scale: (1.0, 1.0),
+            weighting: Weighting::None,
+            weight_range: (0.0, 0.05),
+            invert: false,
+            warp_strength: 0.0,
+            warp_frequency: 4.0,
+            backend: VoronoiBackend::Grid,
+            clip: None,
+            octaves: 1,
+            persistence: 0.5,
+        }
+    }
+}
+
+impl VoronoiParams {
+    /// Check that the parameters describe a diagram that can be generated
+    ///
+    /// # Returns
+    ///
+    /// [`CellsError::InvalidParameter`] naming the first field that is out of
+    /// range: a zero size, no points for uniformly placed centers, a
+    /// distribution [`PointDistribution::check`] rejects, a Minkowski exponent
+    /// below 1, a cell scale that is not positive and finite, non-finite cell
+    /// values, a negative value smoothing, an empty
+    /// or non-finite weight range or one that is not positive for
+    /// multiplicative weights, a negative warp, clip percentiles outside
+    /// `0 <= low <= high <= 100`, no or more than [`MAX_OCTAVES`] octaves, or a
+    /// persistence that is not positive or overflows their total weight
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cells::voronoi::VoronoiParams;
+    /// use cells::CellsError;
+    ///
+    /// assert!(VoronoiParams::default().validate().is_ok());
+    /// let err = VoronoiParams { num_points: 0, ..Default::default() }.validate().unwrap_err();
+    /// assert!(matches!(err, CellsError::InvalidParameter { name: "num_points", .. }));
+    /// assert_eq!(err.to_string(), "invalid num_points: must be above 0");
+    /// ```
+    pub fn validate(&self) -> Result<(), CellsError> {
+        let invalid = |name, reason: String| Err(CellsError::InvalidParameter { name, reason });
+        if self.size == 0 {
+            return invalid("size", "must be above 0".to_string());
+        }
+        if self.num_points == 0 && self.distribution == PointDistribution::Uniform {
+            return invalid("num_points", "must be above 0".to_string());
+        }
+        if let Err(reason) = self.distribution.check() {
+            return invalid("distribution", reason);
+        }
+        if let DistanceMetric::Minkowski(p) = self.metric {
+            if !(p.is_finite() && p >= 1.0) {
+                return invalid("metric", format!("Minkowski exponents must be finite and at least 1, got {p}"));
+            }
+        }
+        let (sx, sy) = self.cell_scale;
+        if !(sx.is_finite() && sx > 0.0 && sy.is_finite() && sy > 0.0) {
+            return invalid("cell_scale", format!("must be positive and finite, got {sx},{sy}"));
+        }
+        if let Some(value) = self.cell_values.iter().find(|value| !value.is_finite()) {
+            return invalid("cell_values", format!("must be finite, got {value}"));
+        }
+        if !(self.value_smoothing.is_finite() && self.value_smoothing >= 0.0) {
+            return invalid("value_smoothing", format!("must be non-negative and finite, got {}", self.value_smoothing));
+        }
+        if self.weighting != Weighting::None {
+            let (min, max) = self.weight_range;
+            if !(min.is_finite() && max.is_finite() && min <= max) {
+                return invalid("weight_range", format!("must be a finite range, got {min} to {max}"));
+            }
+            if self.weighting == Weighting::Multiplicative && min <= 0.0 {
+                return invalid("weight_range", format!("multiplicative weights must be positive, got {min}"));
+            }
+        }
+        if !(self.warp_strength.is_finite() && self.warp_strength >= 0.0) {
+            return invalid("warp_strength", format!("must be non-negative and finite, got {}", self.warp_strength));
+        }
+        if self.warp_strength > 0.0 && !(self.warp_frequency.is_finite() && self.warp_frequency > 0.0) {
+            return invalid("warp_frequency", format!("must be positive and finite, got {}", self.warp_frequency));
+        }
+        if let Some((low, high)) = self.clip {
+            if !(0.0 <= low && low <= high && high <= 100.0) {
+                return invalid("clip", format!("must satisfy 0 <= low <= high <= 100, got {low} to {high}"));
+            }
+        }
+        if !(1..=MAX_OCTAVES).contains(&self.octaves) {
+            return invalid("octaves", format!("must be 1 to {MAX_OCTAVES}, got {}", self.octaves));
+        }
+        if !(self.persistence.is_finite() && self.persistence > 0.0 && octave_weights(self).1.is_finite()) {
+            let (octaves, persistence) = (self.octaves, self.persistence);
+            let reason = format!("must be positive and keep the weights of {octaves} octaves finite");
+            return invalid("persistence", format!("{reason}, got {persistence}"));
+        }
+        Ok(())
+    }
+}
+
+/// Voronoi parameters set one at a time, checked by
+/// [`VoronoiBuilder::build`]
+///
+/// Unset parameters keep the defaults of [`VoronoiParams`], so
+/// `VoronoiBuilder::new().generate()` is the default
+/// [`generate_voronoi_field`].
+///
+/// # Example
+///
+/// ```rust
+/// use cells::sampling::PointDistribution;
+/// use cells::voronoi::{generate_voronoi_field, Feature, VoronoiBuilder, VoronoiParams};
+///
+/// let cells = VoronoiBuilder::new().size(64).num_points(20).feature(Feature::F2).seed(3).generate().unwrap();
+/// let params = VoronoiParams { size: 64, num_points: 20, feature: Feature::F2, seed: 3, ..Default::default() };
+/// assert_eq!(cells, generate_voronoi_field(&params));
+///
+/// let lattice = PointDistribution::JitteredGrid { cols: 8, rows: 8, jitter: 1.5 };
+/// let err = VoronoiBuilder::new().distribution(lattice).build().unwrap_err();
+/// assert_eq!(err.to_string(), "invalid distribution: jitter must be between 0 and 1, got 1.5");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VoronoiBuilder {
+    params: VoronoiParams,
+}
+
+impl VoronoiBuilder {
+    /// A builder of the default [`VoronoiParams`]
+    pub fn new() -> VoronoiBuilder {
+        VoronoiBuilder::default()
+    }
+
+    /// The width and height of the texture in pixels, above 0
+    pub fn size(mut self, size: u32) -> VoronoiBuilder {
+        self.params.size = size;
+        self
+    }
+
+    /// The number of cell centers, above 0; only uniformly placed centers
+    /// use it
+    pub fn num_points(mut self, num_points: usize) -> VoronoiBuilder {
+        self.params.num_points = num_points;
+        self
+    }
+
+    /// How the cell centers are placed; Poisson-disk distances lie between
+    /// 0.001 and 1, lattices have at least one column and row and a jitter
+    /// from 0 to 1
+    pub fn distribution(mut self, distribution: PointDistribution) -> VoronoiBuilder {
+        self.params.distribution = distribution;
+        self
+    }
+
+    /// The Lloyd relaxation iterations, any number, 0 to disable
+    pub fn relax_iterations(mut self, relax_iterations: u32) -> VoronoiBuilder {
+        self.params.relax_iterations = relax_iterations;
+        self
+    }
+
+    /// The seed of the point placement, any value
+    pub fn seed(mut self, seed: u64) -> VoronoiBuilder {
+        self.params.seed = seed;
+        self
+    }
+
+    /// The distance feature recorded for every pixel
+    pub fn feature(mut self, feature: Feature) -> VoronoiBuilder {
+        self.params.feature = feature;
+        self
+    }
+
+    /// The values of the points for [`Feature::CellValue`], finite, repeating
+    /// when there are more points; empty for seeded random values
+    pub fn cell_values(mut self, cell_values: Vec<f32>) -> VoronoiBuilder {
+        self.params.cell_values = cell_values;
+        self
+    }
+
+    /// The F2 - F1 distance over which [`Feature::CellValue`] blends into
+    /// the neighboring cell, 0 or more
+    pub fn value_smoothing(mut self, value_smoothing: f32) -> VoronoiBuilder {
+        self.params.value_smoothing = value_smoothing;
+        self
+    }
+
+    /// The distance metric; Minkowski exponents are finite and at least 1
+    pub fn metric(mut self, metric: DistanceMetric) -> VoronoiBuilder {
+        self.params.metric = metric;
+        self
+    }
+
+    /// Multiply the x and y offsets of every distance by the positive `sx`
+    /// and `sy`, stretching the cells
+    pub fn cell_scale(mut self, sx: f32, sy: f32) -> VoronoiBuilder {
+        self.params.cell_scale = (sx, sy);
+        self
+    }
+
+    /// Weight the points with `weighting` by weights drawn from the finite
+    /// `range`, from its first to its second value; multiplicative weights
+    /// are above 0
+    pub fn weighting(mut self, weighting: Weighting, range: (f32, f32)) -> VoronoiBuilder {
+        (self.params.weighting, self.params.weight_range) = (weighting, range);
+        self
+    }
+
+    /// Whether the cell centers are bright and the edges dark
+    pub fn invert(mut self, invert: bool) -> VoronoiBuilder {
+        self.params.invert = invert;
+        self
+    }
+
+    /// Warp the sample positions by up to `strength` texture widths, 0 or
+    /// more, with noise of the positive base `frequency`
+    pub fn warp(mut self, strength: f32, frequency: f64) -> VoronoiBuilder {
+        (self.params.warp_strength, self.params.warp_frequency) = (strength, frequency);
+        self
+    }
+
+    /// The implementation computing the features
+    pub fn backend(mut self, backend: VoronoiBackend) -> VoronoiBuilder {
+        self.params.backend = backend;
+        self
+    }
+
+    /// Sum `octaves` diagrams, from 1 to [`MAX_OCTAVES`], with four times the
+    /// points of the one before and `persistence` times its positive weight
+    pub fn octaves(mut self, octaves: u32, persistence: f32) -> VoronoiBuilder {
+        (self.params.octaves, self.params.persistence) = (octaves, persistence);
+        self
+    }
+
+    /// Stretch the features between the percentiles `low` and `high`,
+    /// `0 <= low <= high <= 100`, clipping the outliers beyond them
+    pub fn clip(mut self, low: f32, high: f32) -> VoronoiBuilder {
+        self.params.clip = Some((low, high));
+        self
+    }
+
+    /// The parameters, if [`VoronoiParams::validate`] accepts them
+    pub fn build(self) -> Result<VoronoiParams, CellsError> {
+        self.params.validate()?;
+        Ok(self.params)
+    }
+
+    /// Generate the diagram with [`generate_voronoi_field`]
+    ///
+    /// # Returns
+    ///
+    /// The diagram, or the error of [`VoronoiBuilder::build`]
+    pub fn generate(self) -> Result<FieldBuffer, CellsError> {
+        Ok(generate_voronoi_field(&self.build()?))
+    }
+}
+
+/// Generate the raw, un-normalized Voronoi feature field
+///
+/// Every sample holds the un-normalized toroidal distance feature selected by
+/// `params.feature` at the corresponding pixel. Weighted distances below zero
+/// are clamped to zero, so the field is never negative.
+///
+/// With a non-zero `params.warp_strength` the feature is not measured at the
+/// pixel itself but at a position offset by two tileable noise fields, one
+/// for each axis, which bends the straight cell edges into organic shapes.
+/// The offset position wraps around the unit square, so the warped field
+/// still tiles.
+///
+/// # Arguments
+///
+/// * `params` - The generator parameters
+///
+/// # Returns
+///
+/// A `FieldBuffer` of `params.size` x `params.size` feature values
+///
+/// # Panics
+///
+/// Panics if [`VoronoiParams::validate`] rejects `params`.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::voronoi::{generate_voronoi_features, Feature, VoronoiParams};
+///
+/// let params = VoronoiParams { size: 64, num_points: 16, ..Default::default() };
+/// let f1 = generate_voronoi_features(&params);
+/// let f2 = generate_voronoi_features(&VoronoiParams { feature: Feature::F2, ..params });
+/// assert!(f1.as_slice().iter().zip(f2.as_slice()).all(|(a, b)| b >= a));
+/// ```
+///
+/// Warped cells keep tiling:
+///
+/// ```rust
+/// use cells::tiling::{assert_tileable, interior_discontinuity};
+/// use cells::voronoi::{generate_voronoi_features, VoronoiParams};
+///
+/// let params = VoronoiParams { size: 64, num_points: 16, ..Default::default() };
+/// let warped = generate_voronoi_features(&VoronoiParams { warp_strength: 0.08, ..params.clone() });
+/// assert_ne!(warped, generate_voronoi_features(&params));
+/// assert_tileable(&warped, interior_discontinuity(&warped));
+/// ```
+pub fn generate_voronoi_features(params: &VoronoiParams) -> FieldBuffer {
+    voronoi_features(params, None, |_| {})
+}
+
+/// The features of [`generate_voronoi_features`] with the points moved along
+/// their orbits to the `(phase, radius)` of `orbit`, reporting the finished
+/// share of the rows to `progress`
+pub(crate) fn voronoi_features(
+    params: &VoronoiParams,
+    orbit: Option<(f64, f32)>,
+    mut progress: impl FnMut(f32) + Send,
+) -> FieldBuffer {
+    let size = params.size;
+    let details = format_args!(
+        "{size}x{size}, {} {} points, {} {} features, {:?} backend",
+        params.num_points, params.distribution, params.feature, params.metric, params.backend
+    );
+    crate::stage("voronoi", details, || {
+        if let VoronoiBackend::NoiseWorley { frequency, return_type } = params.backend {
+            let features = generate_noise_worley_features(params, frequency, return_type);
+            progress(1.0);
+            return features;
+        }
+        grid_features(params, &build_grid(params, orbit), progress)
+    })
+}
+
+/// The features of `params` at every pixel, measured on the points of `grid`
+pub(crate) fn grid_features(params: &VoronoiParams, grid: &PointGrid, progress: impl FnMut(f32) + Send) -> FieldBuffer {
+    let values = point_values(params, grid.points().len());
+    let position = sample_positions(params);
+
+    let feature = |x, y| feature_at(params, grid, &values, position(x, y));
+    FieldBuffer::from_par_fn_with_progress(params.size, params.size, feature, progress)
+}
+
+/// The feature `params.feature` of the nearest points of `grid` to `p`, with
+/// the values of [`point_values`] for [`Feature::CellValue`]
+fn feature_at(params: &VoronoiParams, grid: &PointGrid, values: &[f32], p: Point) -> f32 {
+    if params.feature == Feature::CellValue {
+        return cell_value_at(params, grid, values, p);
+    }
+    let two_nearest = match params.feature {
+        Feature::F1 => None,
+        _ => grid.nearest_two(p),
+    };
+    let (f1, f2) = two_nearest.map_or_else(
+        || {
+            let f1 = grid.nearest(p).map_or(f32::INFINITY, |(_, d)| d);
+            (f1, f1)
+        },
+        |[(_, f1), (_, f2)]| (f1, f2),
+    );
+    params.feature.value(f1.max(0.0), f2.max(0.0))
+}
+
+/// The value of the nearest point of `grid` to `p`, blended towards the
+/// second-nearest by `params.value_smoothing`
+///
+/// The second value weighs half on the cell boundary, where F2 - F1 is 0,
+/// and fades out along a smoothstep until F2 - F1 reaches the smoothing
+/// width, so the values meet continuously across the boundary.
+fn cell_value_at(params: &VoronoiParams, grid: &PointGrid, values: &[f32], p: Point) -> f32 {
+    let width = params.value_smoothing;
+    if width > 0.0 {
+        if let Some([(i1, f1), (i2, f2)]) = grid.nearest_two(p) {
+            let t = ((f2 - f1) / width).clamp(0.0, 1.0);
+            let weight = 0.5 * (1.0 - t * t * (3.0 - 2.0 * t));
+            return values[i1] + (values[i2] - values[i1]) * weight;
+        }
+    }
+    grid.nearest(p).map_or(0.0, |(index, _)| values[index])
+}
+
+/// The value of each of `count` points for [`Feature::CellValue`]: the
+/// repeated `params.cell_values`, or values from 0 to 1 seeded apart from the
+/// point placement; empty for the distance features
+pub(crate) fn point_values(params: &VoronoiParams, count: usize) -> Vec<f32> {
+    if params.feature != Feature::CellValue {
+        return Vec::new();
+    }
+    if params.cell_values.is_empty() {
+        let mut rng = StdRng::seed_from_u64(params.seed.wrapping_add(4));
+        return (0..count).map(|_| rng.gen()).collect();
+    }
+    params.cell_values.iter().copied().cycle().take(count).collect()
+}
+
+/// Generate a tileable Voronoi diagram
+///
+/// This function creates a Voronoi diagram that can be tiled seamlessly, as a
+/// float field with values from 0 to 1. By default brighter values
+/// represent the cell edges: far from the cell centers for `F1` and `F2`, and
+/// the ridges on the cell boundaries for `F2MinusF1`. With `params.invert` the
+/// cell centers are bright and the edges dark instead. `CellValue` keeps the
+/// values of the cells, clamped to 0-1 unless stretched by `params.clip`, and
+/// inverts them.
+///
+/// # Algorithm
+///
+/// 1. Generate random points in a unit square
+/// 2. For each pixel in the output image:
+///    a. Look up the nearest (and second-nearest) Voronoi point in a toroidal grid
+///    b. Record the requested feature of their toroidal distances
+/// 3. Normalize the features across the entire image to the range [0, 1],
+///    reusing the values recorded by [`generate_voronoi_features`], by the
+///    largest feature or between the `params.clip` percentiles
+/// 4. Turn the normalized features into an edge brightness, which is 1 on the
+///    cell edges and 0 at the centers, and flip it when inverting
+/// 5. With `params.octaves` above 1, repeat for every octave, with four times
+///    the points of the octave before and half its lengths, and average the
+///    octaves weighted by powers of `params.persistence`
+///
+/// # Arguments
+///
+/// * `params` - The generator parameters
+///
+/// # Returns
+///
+/// A `FieldBuffer` containing the Voronoi diagram
+///
+/// # Panics
+///
+/// Panics if [`VoronoiParams::validate`] rejects `params`.
+///
+/// # Performance
+///
+/// Points are bucketed into a [`PointGrid`], so each pixel only inspects the
+/// points in a few neighboring cells and the cost is close to O(size^2) even
+/// for tens of thousands of points. The distance pass is parallelized over
+/// image rows with rayon; the output does not depend on the number of threads.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::sampling::PointDistribution;
+/// use cells::voronoi::{generate_voronoi_field, VoronoiParams};
+///
+/// // Pixel (8, 8) lies exactly on a seed point of a regular 4x4 lattice
+/// let lattice = VoronoiParams {
+///     size: 64,
+///     distribution: PointDistribution::JitteredGrid { cols: 4, rows: 4, jitter: 0.0 },
+///     ..Default::default()
+/// };
+/// assert_eq!(generate_voronoi_field(&lattice).get(8, 8), 0.0);
+/// let inverted = VoronoiParams { invert: true, ..lattice };
+/// assert_eq!(generate_voronoi_field(&inverted).get(8, 8), 1.0);
+/// ```
+///
+/// Octaves add finer cells inside the large ones, and one octave is the
+/// plain diagram:
+///
+/// ```rust
+/// use cells::tiling::{assert_tileable, interior_discontinuity};
+/// use cells::voronoi::{generate_voronoi_field, VoronoiParams};
+///
+/// let params = VoronoiParams { size: 64, num_points: 8, ..Default::default() };
+/// let fractal = generate_voronoi_field(&VoronoiParams { octaves: 3, ..params.clone() });
+/// assert_ne!(fractal, generate_voronoi_field(&params));
+/// let one = VoronoiParams { octaves: 1, persistence: 0.9, ..params.clone() };
+/// assert_eq!(generate_voronoi_field(&one), generate_voronoi_field(&params));
+/// assert_tileable(&fractal, interior_discontinuity(&fractal));
+/// ```
+pub fn generate_voronoi_field(params: &VoronoiParams) -> FieldBuffer {
+    generate_voronoi_field_with_progress(params, |_| {})
+}
+
+/// Generate a tileable Voronoi diagram, reporting progress
+///
+/// This is [`generate_voronoi_field`] calling `progress` with the finished
+/// share of the distance pass, from 0 to 1, as rows complete. The noise
+/// Worley backend only reports completion.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::voronoi::{generate_voronoi_field, generate_voronoi_field_with_progress, VoronoiParams};
+///
+/// let params = VoronoiParams { size: 32, num_points: 8, ..Default::default() };
+/// let mut last = 0.0;
+/// let field = generate_voronoi_field_with_progress(&params, |done| last = done);
+/// assert_eq!(field, generate_voronoi_field(&params));
+/// assert_eq!(last, 1.0);
+/// ```
+pub fn generate_voronoi_field_with_progress(params: &VoronoiParams, progress: impl FnMut(f32) + Send) -> FieldBuffer {
+    fractal_field(params, None, progress)
+}
+
+/// Generate one frame of a tileable Voronoi diagram that loops in time
+///
+/// Every cell center travels along its own circle of `orbit` texture widths
+/// radius, starting where [`generate_voronoi_field`] places it, and is turned
+/// by the angle `phase * 2π`. The start angles and the directions of the
+/// circles are seeded from `params.seed` apart from the placement. Phases one
+/// apart place the points alike, so frame `i` of `n` at phase `i / n` follows
+/// frame `n - 1` as smoothly as any other, and a phase of 0 gives
+/// [`generate_voronoi_field`]. Every frame is normalized on its own.
+///
+/// The noise Worley backend has no points to move and gives the same field
+/// for every phase.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::voronoi::{generate_voronoi_field, generate_voronoi_frame, VoronoiParams};
+///
+/// let params = VoronoiParams { size: 32, num_points: 8, ..Default::default() };
+/// assert_eq!(generate_voronoi_frame(&params, 0.0, 0.02), generate_voronoi_field(&params));
+/// assert_ne!(generate_voronoi_frame(&params, 0.5, 0.02), generate_voronoi_field(&params));
+/// ```
+pub fn generate_voronoi_frame(params: &VoronoiParams, phase: f64, orbit: f32) -> FieldBuffer {
+    generate_voronoi_frame_with_progress(params, phase, orbit, |_| {})
+}
+
+/// Generate one frame of a looping Voronoi diagram, reporting progress
+///
+/// This is [`generate_voronoi_frame`] reporting progress like
+/// [`generate_voronoi_field_with_progress`].
+pub fn generate_voronoi_frame_with_progress(
+    params: &VoronoiParams,
+    phase: f64,
+    orbit: f32,
+    progress: impl FnMut(f32) + Send,
+) -> FieldBuffer {
+    fractal_field(params, Some((phase, orbit)), progress)
+}
+
+/// The weights of the octaves of `params`, starting at 1, and their sum
+fn octave_weights(params: &VoronoiParams) -> (Vec<f32>, f32) {
+    let weights: Vec<f32> = (0..params.octaves as i32).map(|octave| params.persistence.powi(octave)).collect();
+    let total = weights.iter().sum();
+    (weights, total)
+}
+
+/// The parameters of octave `octave` of a fractal diagram: the diagram at
+/// `2^octave` times the frequency, with four times the points of the octave
+/// before and every length in texture widths halved, seeded apart from the
+/// other octaves
+fn octave_params(params: &VoronoiParams, octave: u32) -> VoronoiParams {
+    let (factor, scale) = (1u32 << octave, 0.5f32.powi(octave as i32));
+    let distribution = match params.distribution {
+        PointDistribution::Uniform => PointDistribution::Uniform,
+        PointDistribution::PoissonDisk { min_dist } => PointDistribution::PoissonDisk { min_dist: min_dist * scale },
+        PointDistribution::JitteredGrid { cols, rows, jitter } => {
+            let (cols, rows) = (cols.saturating_mul(factor), rows.saturating_mul(factor));
+            PointDistribution::JitteredGrid { cols, rows, jitter }
+        }
+        PointDistribution::HexGrid { cols, rows, jitter } => {
+            let (cols, rows) = (cols.saturating_mul(factor), rows.saturating_mul(factor));
+            PointDistribution::HexGrid { cols, rows, jitter }
+        }
+    };
+    let weight_range = match params.weighting {
+        Weighting::Additive => (params.weight_range.0 * scale, params.weight_range.1 * scale),
+        _ => params.weight_range,
+    };
+    let backend = match params.backend {
+        VoronoiBackend::NoiseWorley { frequency, return_type } => {
+            VoronoiBackend::NoiseWorley { frequency: frequency * factor as f64, return_type }
+        }
+        backend => backend,
+    };
+    VoronoiParams {
+        num_points: params.num_points.saturating_mul(factor as usize * factor as usize),
+        distribution,
+        // The first octave keeps the seed, so one octave is the plain diagram
+        seed: params.seed.wrapping_add(u64::from(octave).wrapping_mul(0x9E37_79B9_7F4A_7C15)),
+        value_smoothing: params.value_smoothing * scale,
+        weight_range,
+        warp_strength: params.warp_strength * scale,
+        warp_frequency: params.warp_frequency * factor as f64,
+        backend,
+        octaves: 1,
+        ..params.clone()
+    }
+}
+
+/// The normalized diagrams of every octave of `params`, with the points
+/// moved along their orbits like [`voronoi_features`], summed by their
+/// weights and divided by the total weight
+fn fractal_field(params: &VoronoiParams, orbit: Option<(f64, f32)>, progress: impl FnMut(f32) + Send) -> FieldBuffer {
+    fractal_field_with(params, orbit, progress, |params, orbit, report| voronoi_features(params, orbit, report))
+}
+
+/// [`fractal_field`] with the raw features of every octave computed by
+/// `features` from the parameters and the orbit of the octave, reporting the
+/// finished share of the octave
+pub(crate) fn fractal_field_with(
+    params: &VoronoiParams,
+    orbit: Option<(f64, f32)>,
+    mut progress: impl FnMut(f32) + Send,
+    mut features: impl FnMut(&VoronoiParams, Option<(f64, f32)>, &mut (dyn FnMut(f32) + Send)) -> FieldBuffer,
+) -> FieldBuffer {
+    let (weights, total) = octave_weights(params);
+    let mut sum = FieldBuffer::new(params.size, params.size);
+    for (octave, weight) in (0..params.octaves).zip(weights) {
+        let octave_params = octave_params(params, octave);
+        let orbit = orbit.map(|(phase, radius)| (phase, radius * 0.5f32.powi(octave as i32)));
+        let mut report = |done: f32| progress((octave as f32 + done) / params.octaves as f32);
+        let layer = normalize_features(&octave_params, features(&octave_params, orbit, &mut report));
+        sum.as_mut_slice().par_iter_mut().zip(layer.as_slice()).for_each(|(sum, value)| *sum += value * weight);
+    }
+    sum.map(|value| value / total)
+}
+
+/// Normalize raw features into the edge brightness of
+/// [`generate_voronoi_field`]
+fn normalize_features(params: &VoronoiParams, features: FieldBuffer) -> FieldBuffer {
+    let max_feature = features.min_max().map_or(0.0, |(_, max)| max);
+    let clip = params
+        .clip
+        .and_then(|(low_pct, high_pct)| percentile_range(&features, low_pct, high_pct))
+        .filter(|(low, high)| high > low);
+
+    features.map(|feature| {
+        let normalized = match clip {
+            Some((low, high)) => ((feature - low) / (high - low)).clamp(0.0, 1.0),
+            None => unclipped(params, feature, max_feature),
+        };
+        brightness(params, normalized)
+    })
+}
+
+/// A feature normalized without clip percentiles: distances divided by the
+/// largest one, values clamped to 0-1. A field whose largest distance is 0,
+/// such as F2 - F1 of a single point, is flat at 0.
+fn unclipped(params: &VoronoiParams, feature: f32, max_feature: f32) -> f32 {
+    match params.feature {
+        Feature::CellValue => feature.clamp(0.0, 1.0),
+        _ if max_feature <= 0.0 => 0.0,
+        _ => feature / max_feature,
+    }
+}
+
+/// The brightness of a normalized feature, 1 on the cell edges unless
+/// inverting
+fn brightness(params: &VoronoiParams, normalized: f32) -> f32 {
+    let edge_brightness = if params.feature.small_at_edges() {
+        1.0 - normalized
+    } else {
+        normalized
+    };
+    if params.invert {
+        1.0 - edge_brightness
+    } else {
+        edge_brightness
+    }
+}
+
+/// The diagram of [`generate_voronoi_field`] rendered in bands of rows, see
+/// [`crate::band`]
+///
+/// # Example
+///
+/// ```rust
+/// use cells::band::render_all;
+/// use cells::voronoi::{generate_voronoi_field, Feature, VoronoiBands, VoronoiParams};
+///
+/// let params = VoronoiParams { size: 48, num_points: 20, feature: Feature::F2MinusF1, ..Default::default() };
+/// let bands = VoronoiBands::new(&params).unwrap();
+/// assert_eq!(render_all(&bands, 5), generate_voronoi_field(&params));
+/// assert!(VoronoiBands::new(&VoronoiParams { clip: Some((1.0, 99.0)), ..params }).is_err());
+/// ```
+#[derive(Debug)]
+pub struct VoronoiBands {
+    params: VoronoiParams,
+    grid: PointGrid,
+    values: Vec<f32>,
+    max_feature: f32,
+}
+
+impl VoronoiBands {
+    /// Index the points and find the largest feature of the diagram, which
+    /// measures every feature once without keeping them
+    ///
+    /// # Returns
+    ///
+    /// [`CellsError::InvalidParameter`] for the parameters
+    /// [`VoronoiParams::validate`] rejects, and for the options that need the
+    /// whole texture: the `clip` percentiles, the warp, whose noise is
+    /// generated for the whole texture, the noise Worley backend, and more
+    /// than one octave
+    pub fn new(params: &VoronoiParams) -> Result<VoronoiBands, CellsError> {
+        params.validate()?;
+        let unsupported = |name, reason: &str| Err(CellsError::InvalidParameter { name, reason: reason.to_string() });
+        if params.clip.is_some() {
+            return unsupported("clip", "percentiles need the whole texture");
+        }
+        if params.warp_strength != 0.0 {
+            return unsupported("warp_strength", "the warp noise is generated for the whole texture");
+        }
+        if params.backend != VoronoiBackend::Grid {
+            return unsupported("backend", "bands are rendered from the point grid");
+        }
+        if params.octaves != 1 {
+            return unsupported("octaves", "bands are rendered from the points of one octave");
+        }
+        let size = params.size;
+        let grid = build_grid(params, None);
+        let values = point_values(params, grid.points().len());
+        let feature = |x, y| feature_at(params, &grid, &values, pixel_position(x, y, size));
+        let max_feature = (0..size)
+            .into_par_iter()
+            .map(|y| (0..size).map(|x| feature(x, y)).fold(f32::MIN, f32::max))
+            .reduce(|| f32::MIN, f32::max);
+        Ok(VoronoiBands { params: params.clone(), grid, values, max_feature })
+    }
+}
+
+impl BandSource for VoronoiBands {
+    fn dimensions(&self) -> (u32, u32) {
+        (self.params.size, self.params.size)
+    }
+
+    fn render(&self, rows: Range<u32>) -> FieldBuffer {
+        let size = self.params.size;
+        FieldBuffer::from_par_fn(size, rows.len() as u32, |x, y| {
+            let feature = feature_at(&self.params, &self.grid, &self.values, pixel_position(x, rows.start + y, size));
+            brightness(&self.params, unclipped(&self.params, feature, self.max_feature))
+        })
+    }
+}
+
+/// Generate a tileable Voronoi diagram as an 8-bit image
+///
+/// This is [`generate_voronoi_field`] quantized into the red channel.
+///
+/// # Arguments
+///
+/// * `params` - The generator parameters
+///
+/// # Returns
+///
+/// An `ImageBuffer` containing the Voronoi diagram
+///
+/// # Panics
+///
+/// Panics if [`VoronoiParams::validate`] rejects `params`.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io::Cursor;
+/// use cells::voronoi::{generate_tileable_voronoi, VoronoiParams};
+///
+/// let encode = |seed| {
+///     let mut png = Cursor::new(Vec::new());
+///     let params = VoronoiParams { size: 64, num_points: 16, seed, ..Default::default() };
+///     generate_tileable_voronoi(&params)
+///         .write_to(&mut png, image::ImageFormat::Png)
+///         .unwrap();
+///     png.into_inner()
+/// };
+/// // The same seed always produces a byte-identical PNG
+/// assert_eq!(encode(42), encode(42));
+/// assert_ne!(encode(42), encode(43));
+/// ```
+pub fn generate_tileable_voronoi(params: &VoronoiParams) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    generate_voronoi_field(params).to_rgb_image()
+}
+
+/// Generate a tileable map of flat-colored Voronoi cells
+///
+/// Every pixel is filled with a value derived from the index of its nearest
+/// point, so each cell has one constant value, as used for stained-glass and
+/// ID-map workflows. The nearest point is found with the toroidal distance,
+/// so cells wrap seamlessly across the tile edges.
+///
+/// # Arguments
+///
+/// * `params` - The generator parameters; `params.feature` is ignored
+/// * `color` - Give every cell a hashed RGB color instead of a red-channel value
+///
+/// # Returns
+///
+/// An `ImageBuffer` containing the cell map
+///
+/// # Panics
+///
+/// Panics if [`VoronoiParams::validate`] rejects `params`.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::voronoi::{generate_voronoi_cell_ids, generate_voronoi_cells, VoronoiParams};
+///
+/// let params = VoronoiParams { size: 64, num_points: 16, ..Default::default() };
+/// let cells = generate_voronoi_cells(&params, true);
+/// let ids = generate_voronoi_cell_ids(&params);
+/// // Pixels of the same cell share their color
+/// let mut colors = vec![None; 16];
+/// for (id, color) in ids.pixels().zip(cells.pixels()) {
+///     assert!(id[0] < 16);
+///     assert_eq!(*colors[id[0] as usize].get_or_insert(*color), *color);
+/// }
+/// ```
+pub fn generate_voronoi_cells(params: &VoronoiParams, color: bool) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let size = params.size;
+    let grid = build_grid(params, None);
+    let position = sample_positions(params);
+
+    ImageBuffer::from_par_fn(size, size, |x, y| {
+        let index = nearest_index(&grid, position(x, y));
+        let [r, g, b, _] = hash_index(index).to_le_bytes();
+        if color {
+            Rgb([r, g, b])
+        } else {
+            Rgb([r, 0, 0])
+        }
+    })
+}
+
+/// Generate a tileable RGB mosaic of cells colored from a palette or random
+/// HSV ranges
+///
+/// Every cell gets one color drawn by `coloring`, seeded from `params.seed`
+/// apart from the point placement. With `distinct_neighbors` a cell draws
+/// again, a few times at most, while its color equals that of a neighbor
+/// colored before it, so adjacent cells differ unless the palette is too
+/// small. Two cells are neighbors when they are the nearest and
+/// second-nearest cell of some pixel, or the nearest cells of adjacent
+/// pixels.
+///
+/// # Arguments
+///
+/// * `params` - The generator parameters; `params.feature` is ignored
+/// * `coloring` - Where the colors of the cells come from
+/// * `distinct_neighbors` - Avoid giving adjacent cells the same color
+///
+/// # Returns
+///
+/// An `ImageBuffer` containing the mosaic
+///
+/// # Panics
+///
+/// Panics if [`VoronoiParams::validate`] or [`CellColoring::validate`]
+/// rejects its parameters.
+///
+/// # Example
+///
+/// ```rust
+/// use cells::color::{CellColoring, Palette};
+/// use cells::voronoi::{generate_colored_cells, generate_voronoi_cell_ids, VoronoiParams};
+///
+/// let palette = Palette::parse("#000000\n#ff0000\n#00ff00\n#0000ff\n#ffffff\n").unwrap();
+/// let params = VoronoiParams { size: 64, num_points: 20, seed: 2, ..Default::default() };
+/// let mosaic = generate_colored_cells(&params, &CellColoring::Palette(palette.clone()), true);
+/// assert!(mosaic.pixels().all(|color| palette.colors().contains(color)));
+/// // Pixels of the same cell share their color
+/// let ids = generate_voronoi_cell_ids(&params);
+/// let mut colors = vec![None; 20];
+/// for (id, color) in ids.pixels().zip(mosaic.pixels()) {
+///     assert_eq!(*colors[id[0] as usize].get_or_insert(*color), *color);
+/// }
+/// ```
+pub fn generate_colored_cells(
+    params: &VoronoiParams,
+    coloring: &CellColoring,
+    distinct_neighbors: bool,
+) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    if let Err(err) = coloring.validate() {
+        panic!("{err}");
+    }
+    let size = params.size;
+    let grid = build_grid(params, None);
+    let position = sample_positions(params);
+    let neighbors = if distinct_neighbors {
+        cell_neighbors(&grid, size, &position)
+    } else {
+        Vec::new()
+    };
+
+    let mut rng = StdRng::seed_from_u64(params.seed.wrapping_add(5));
+    let mut colors: Vec<Rgb<u8>> = Vec::with_capacity(grid.points().len());
+    for index in 0..grid.points().len() {
+        let taken = |color: &Rgb<u8>| {
+            neighbors.get(index).is_some_and(|cells: &Vec<usize>| {
+                cells.iter().any(|&neighbor| neighbor < index && colors[neighbor] == *color)
+            })
+        };
+        let mut color = coloring.sample(&mut rng);
+        for _ in 0..DISTINCT_ATTEMPTS {
+            if !taken(&color) {
+                break;
+            }
+            color = coloring.sample(&mut rng);
+        }
+        colors.push(color);
+    }
+
+    ImageBuffer::from_par_fn(size, size, |x, y| {
+        let index = nearest_index(&grid, position(x, y));
+        colors.get(index).copied().unwrap_or(Rgb([0, 0, 0]))
+    })
+}
+
+/// How often [`generate_colored_cells`] draws again for a color of a neighbor
+const DISTINCT_ATTEMPTS: usize = 32;
+
+/// The neighbors of every point of `grid`: the nearest and second-nearest
+/// points of the pixels of a `size` x `size` texture, and the nearest points
+/// of adjacent pixels, which also catches cells meeting in short edges
+fn cell_neighbors(grid: &PointGrid, size: u32, position: &(impl Fn(u32, u32) -> Point + Sync)) -> Vec<Vec<usize>> {
+    let width = size as usize;
+    let nearest: Vec<[usize; 2]> = (0..width * width)
+        .into_par_iter()
+        .map(|i| {
+            let p = position((i % width) as u32, (i / width) as u32);
+            grid.nearest_two(p).map_or_else(|| [nearest_index(grid, p); 2], |[(a, _), (b, _)]| [a, b])
+        })
+        .collect();
+    let first = |x: usize, y: usize| nearest[(y % width) * width + x % width][0];
+    let mut pairs = BTreeSet::new();
+    for y in 0..width {
+        for x in 0..width {
+            let [a, b] = nearest[y * width + x];
+            for other in [b, first(x + 1, y), first(x, y + 1)] {
+                if other != a {
+                    pairs.insert((a.min(other), a.max(other)));
+                }
+            }
+        }
+    }
+    let mut neighbors = vec![Vec::new(); grid.points().len()];
+    for (a, b) in pairs {
+        neighbors[a].push(b);
+        neighbors[b].push(a);
+    }
+    neighbors
+}
+
+/// Generate the raw index of the nearest point for every pixel
+///
+/// This is the exact ID map behind [`generate_voronoi_cells`], for programs
+/// that need to know which point owns a pixel.
+///
+/// # Arguments
+///
+/// * `params` - The generator parameters; `params.feature` is ignored
+///
+/// # Returns
+///
+/// A 16-bit grayscale `ImageBuffer` of point indices
+///
+/// # Panics
+///
+/// Panics if [`VoronoiParams::validate`] rejects `params`, or if there are
+/// more than 65536 points, since the indices would not fit in 16 bits.
+pub fn generate_voronoi_cell_ids(params: &VoronoiParams) -> ImageBuffer<Luma<u16>, Vec<u16>> {
+    let size = params.size;
+    let grid = build_grid(params, None);
+    assert!(
+        grid.points().len() <= 1 << 16,
+        "{} points do not fit in a 16-bit index image",
+        grid.points().len()
+    );
+
+    let position = sample_positions(params);
+
+    ImageBuffer::from_par_fn(size, size, |x, y| Luma([nearest_index(&grid, position(x, y)) as u16]))
+}
+
+/// Index the seeded, optionally relaxed and weighted Voronoi cell centers,
+/// moved along their orbits to the `(phase, radius)` of `orbit`
+///
+/// # Panics
+///
+/// Panics if [`VoronoiParams::validate`] rejects `params`.
+pub(crate) fn build_grid(params: &VoronoiParams, orbit: Option<(f64, f32)>) -> PointGrid {
+    if let Err(err) = params.validate() {
+        panic!("{err}");
+    }
+    let pixels = params.size as u64 * params.size as u64;
+    if params.distribution == PointDistribution::Uniform && params.num_points as u64 > pixels {
+        log::warn!(
+            "{} Voronoi points are more than the {} pixels of the texture, most cells are smaller than a pixel",
+            params.num_points,
+            pixels
+        );
+    }
+    let mut rng = StdRng::seed_from_u64(params.seed);
+    let points = params.distribution.generate(params.num_points, &mut rng);
+    let points = lloyd_relax_with(&points, params.relax_iterations, params.metric, params.cell_scale);
+    let points = match orbit {
+        Some((phase, radius)) => orbit_points(&points, params.seed, phase, radius),
+        None => points,
+    };
+    let weights: Vec<f32> = match params.weighting {
+        Weighting::None => Vec::new(),
+        _ => {
+            let (min, max) = params.weight_range;
+            points.iter().map(|_| rng.gen_range(min..=max)).collect()
+        }
+    };
+    let (sx, sy) = params.cell_scale;
+    PointGrid::with_weights(&points, params.metric, &weights, params.weighting).with_scale(sx, sy)
+}
+
+/// Move every point by the angle `phase * 2π` along a circle of `radius`
+/// through it, with the start angles and directions seeded from `seed`
+fn orbit_points(points: &[Point], seed: u64, phase: f64, radius: f32) -> Vec<Point> {
+    let mut rng = StdRng::seed_from_u64(seed.wrapping_add(3));
+    points
+        .iter()
+        .map(|p| {
+            let start = rng.gen_range(0.0..TAU);
+            let turn = if rng.gen() { phase * TAU } else { -phase * TAU };
+            let (dx, dy) = ((start + turn).cos() - start.cos(), (start + turn).sin() - start.sin());
+            Point {
+                x: p.x + dx as f32 * radius,
+                y: p.y + dy as f32 * radius,
+            }
+            .wrapped()
+        })
+        .collect()
+}
+
+/// Generate the features of [`VoronoiBackend::NoiseWorley`]
+///
+/// Distances are scaled from Worley cell units to texture widths, so both
+/// backends produce comparable raw values. Value returns lie in [0, 1].
+fn generate_noise_worley_features(params: &VoronoiParams, frequency: f64, return_type: WorleyReturn) -> FieldBuffer {
+    if let Err(err) = params.validate() {
+        panic!("{err}");
+    }
+    let size = params.size;
+    let position = sample_positions(params);
+    let noise_seed = StdRng::seed_from_u64(params.seed).gen();
+    let radius = frequency / TAU;
+
+    let mut field = FieldBuffer::new(size, size);
+    field
+        .as_mut_slice()
+        .par_chunks_mut(size as usize)
+        .enumerate()
+        .for_each(|(y, row)| {
+            // noise::Worley is not Sync, so every row builds its own
+            let worley = Worley::new(noise_seed).set_return_type(match return_type {
+                WorleyReturn::Distance => ReturnType::Distance,
+                WorleyReturn::Value => ReturnType::Value,
+            });
+            for (x, value) in row.iter_mut().enumerate() {
+                let p = position(x as u32, y as u32);
+                let (u, v) = (p.x as f64 * TAU, p.y as f64 * TAU);
+                let sample = worley.get([u.cos() * radius, u.sin() * radius, v.cos() * radius, v.sin() * radius]);
+                // Worley maps its output from [0, 1] to [-1, 1]
+                let unmapped = (sample + 1.0) / 2.0;
+                *value = match return_type {
+                    WorleyReturn::Distance => (unmapped / frequency) as f32,
+                    WorleyReturn::Value => unmapped as f32,
+                };
+            }
+        });
+    field
+}
+
+/// The position each pixel samples the diagram at, moved by the domain warp
+///
+/// The warp offsets come from two tileable fBm fields in [-1, 1] seeded apart
+/// from the point placement, scaled by `params.warp_strength`.
+pub(crate) fn sample_positions(params: &VoronoiParams) -> impl Fn(u32, u32) -> Point + Sync {
+    let size = params.size;
+    let warp = (params.warp_strength != 0.0).then(|| {
+        let fbm = |seed| {
+            let fbm_params = FbmParams {
+                frequency: params.warp_frequency,
+                seed,
+                ..Default::default()
+            };
+            generate_fbm_field(size, &fbm_params)
+        };
+        (fbm(params.seed.wrapping_add(1)), fbm(params.seed.wrapping_add(2)))
+    });
+    let strength = params.warp_strength;
+
+    move |x, y| {
+        let p = pixel_position(x, y, size);
+        match &warp {
+            Some((dx, dy)) => Point {
+                x: p.x + dx.get(x, y) * strength,
+                y: p.y + dy.get(x, y) * strength,
+            }
+            .wrapped(),
+            None => p,
+        }
+    }
+}
+
+/// Index of the point in `grid` closest to `p`, or 0 for an empty grid
+fn nearest_index(grid: &PointGrid, p: Point) -> usize {
+    grid.nearest(p).map_or(0, |(index, _)| index)
+}
+
+/// Scramble a point index into well-distributed bits (SplitMix64 finalizer)
+fn hash_index(index: usize) -> u32 {
+    let mut h = index as u64 ^ 0x9E37_79B9_7F4A_7C15;
+    h = (h ^ (h >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (h ^ (h >> 31)) as u32
+}
+
+/// Position of pixel `(x, y)` of a `size` x `size` image in the unit square,
+/// at its top-left corner rather than its center, so the pixels of a lattice
+/// of `size` divisible by its points fall exactly on them
+fn pixel_position(x: u32, y: u32, size: u32) -> Point {
+    Point {
+        x: x as f32 / size as f32,
+        y: y as f32 / size as f32,
+    }
+}
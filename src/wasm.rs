@@ -0,0 +1,110 @@
+//! A small JavaScript-friendly API for running the generators in a browser.
+//!
+//! Built with `wasm-pack build --no-default-features` for
+//! `wasm32-unknown-unknown`, the functions of this module are exported
+//! through `wasm-bindgen` and return the texture as RGBA bytes, a
+//! `Uint8Array` on the JavaScript side that fits straight into an
+//! `ImageData`. Seeds are `u32` so they stay plain JavaScript numbers, and
+//! errors are returned as strings, which JavaScript sees as thrown
+//! exceptions.
+//!
+//! The browser has no threads unless they are set up explicitly, so rayon
+//! runs every parallel loop on the calling thread and the textures match the
+//! native ones exactly. On other targets the functions are ordinary Rust
+//! functions.
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+use wasm_bindgen::prelude::*;
+
+use crate::field::FieldBuffer;
+use crate::noise::{generate_perlin_field, FbmParams};
+use crate::voronoi::{generate_voronoi_field, Feature, VoronoiParams};
+
+/// Generate a tileable Voronoi texture as grayscale RGBA bytes
+///
+/// # Arguments
+///
+/// * `size` - The width and height of the texture in pixels
+/// * `points` - The number of Voronoi cells
+/// * `seed` - The seed of the point positions
+/// * `feature` - The Worley feature: `f1`, `f2`, `f2-f1` or `cell-value`
+///
+/// # Returns
+///
+/// `size * size * 4` bytes in row order, or an error if the size or number
+/// of points is zero or the feature is unknown
+///
+/// # Example
+///
+/// ```rust
+/// use cells::wasm::voronoi;
+///
+/// let rgba = voronoi(32, 10, 1, "f2-f1").unwrap();
+/// assert_eq!(rgba.len(), 32 * 32 * 4);
+/// assert!(rgba.chunks(4).all(|pixel| pixel[0] == pixel[2] && pixel[3] == 255));
+/// assert!(voronoi(32, 10, 1, "f3").is_err());
+/// ```
+#[cfg_attr(all(target_arch = "wasm32", target_os = "unknown"), wasm_bindgen)]
+pub fn voronoi(size: u32, points: u32, seed: u32, feature: &str) -> Result<Vec<u8>, String> {
+    let feature: Feature = feature.parse()?;
+    check_size(size)?;
+    if points == 0 {
+        return Err("points: at least one point is needed".to_string());
+    }
+    let params = VoronoiParams {
+        size,
+        num_points: points as usize,
+        seed: seed as u64,
+        feature,
+        ..Default::default()
+    };
+    Ok(rgba(&generate_voronoi_field(&params)))
+}
+
+/// Generate tileable fractal Perlin noise as grayscale RGBA bytes
+///
+/// # Arguments
+///
+/// * `size` - The width and height of the texture in pixels
+/// * `octaves`, `persistence`, `lacunarity`, `frequency` - The
+///   [`FbmParams`] of the noise
+/// * `seed` - The seed of the noise permutation table
+///
+/// # Returns
+///
+/// `size * size * 4` bytes in row order, or the reason the parameters are
+/// invalid
+#[cfg_attr(all(target_arch = "wasm32", target_os = "unknown"), wasm_bindgen)]
+pub fn fbm(
+    size: u32,
+    octaves: u32,
+    persistence: f64,
+    lacunarity: f64,
+    frequency: f64,
+    seed: u32,
+) -> Result<Vec<u8>, String> {
+    check_size(size)?;
+    let params = FbmParams {
+        octaves,
+        persistence,
+        lacunarity,
+        frequency,
+        seed: seed as u64,
+        ..Default::default()
+    };
+    params.validate().map_err(|err| err.to_string())?;
+    Ok(rgba(&generate_perlin_field(size, &params)))
+}
+
+/// Reject empty textures, which have no `ImageData` equivalent
+fn check_size(size: u32) -> Result<(), String> {
+    if size == 0 {
+        return Err("size: the texture needs at least one pixel".to_string());
+    }
+    Ok(())
+}
+
+/// The field as opaque gray RGBA pixels
+fn rgba(field: &FieldBuffer) -> Vec<u8> {
+    field.to_luma_image().into_raw().into_iter().flat_map(|v| [v, v, v, 255]).collect()
+}
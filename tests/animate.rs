@@ -0,0 +1,93 @@
+//! Looping frames must tile like still textures, and the step from the last
+//! frame back to the first must be no larger than the steps between the
+//! others. Animated GIF and APNG files must decode to the frames written.
+
+#[cfg(feature = "file-io")]
+use std::{fs::File, io::BufReader, time::Duration};
+
+#[cfg(feature = "file-io")]
+use cells::io::{save_apng, save_gif};
+use cells::noise::{generate_fbm_frame, FbmParams};
+use cells::tiling::{assert_tileable, interior_discontinuity};
+use cells::voronoi::{generate_voronoi_frame, VoronoiParams};
+use cells::FieldBuffer;
+
+const FRAMES: u32 = 16;
+
+/// The mean absolute difference between two frames
+fn frame_step(a: &FieldBuffer, b: &FieldBuffer) -> f32 {
+    a.as_slice().iter().zip(b.as_slice()).map(|(a, b)| (a - b).abs()).sum::<f32>() / a.as_slice().len() as f32
+}
+
+/// Check that the frames tile and wrap around as smoothly as they step
+fn assert_loops(frame: impl Fn(f64) -> FieldBuffer) {
+    let frames: Vec<FieldBuffer> = (0..FRAMES).map(|i| frame(i as f64 / FRAMES as f64)).collect();
+    for field in &frames {
+        assert_tileable(field, interior_discontinuity(field) + 1e-6);
+    }
+    let steps: Vec<f32> = frames.windows(2).map(|pair| frame_step(&pair[0], &pair[1])).collect();
+    let largest = steps.iter().copied().fold(0.0, f32::max);
+    assert!(steps.iter().all(|&step| step > 0.0), "frames repeat: {steps:?}");
+    let wrap = frame_step(&frames[FRAMES as usize - 1], &frames[0]);
+    assert!(wrap <= largest * 1.5, "the loop jumps by {wrap}, the frames step by at most {largest}");
+    assert!(frame_step(&frame(1.0), &frames[0]) < 1e-4);
+}
+
+#[test]
+fn fbm_frames_loop() {
+    let params = FbmParams { octaves: 3, frequency: 2.0, seed: 11, ..Default::default() };
+    assert_loops(|phase| generate_fbm_frame(64, &params, phase, 0.5));
+}
+
+#[test]
+fn voronoi_frames_loop() {
+    let params = VoronoiParams { size: 64, num_points: 24, seed: 5, ..Default::default() };
+    assert_loops(|phase| generate_voronoi_frame(&params, phase, 0.03));
+}
+
+#[cfg(feature = "file-io")]
+fn animation_frames() -> Vec<FieldBuffer> {
+    let params = FbmParams { octaves: 3, frequency: 2.0, seed: 3, ..Default::default() };
+    (0..6).map(|i| generate_fbm_frame(40, &params, i as f64 / 6.0, 0.5).map(|v| (v + 1.0) / 2.0)).collect()
+}
+
+#[test]
+#[cfg(feature = "file-io")]
+fn gif_holds_every_frame() {
+    use image::codecs::gif::GifDecoder;
+    use image::AnimationDecoder;
+
+    let frames = animation_frames();
+    let path = std::env::temp_dir().join("cells_test_animation.gif");
+    save_gif(&frames, &path, Duration::from_millis(50)).unwrap();
+    let decoded = GifDecoder::new(BufReader::new(File::open(&path).unwrap())).unwrap();
+    let decoded = decoded.into_frames().collect_frames().unwrap();
+    assert_eq!(decoded.len(), frames.len());
+    for (frame, field) in decoded.iter().zip(&frames) {
+        assert_eq!(frame.buffer().dimensions(), (40, 40));
+        assert_eq!(frame.delay().numer_denom_ms(), (50, 1));
+        // The shared gray palette keeps every level exactly
+        let gray: Vec<u8> = frame.buffer().pixels().map(|p| p[0]).collect();
+        assert_eq!(gray, field.to_luma_image().into_raw());
+    }
+}
+
+#[test]
+#[cfg(feature = "file-io")]
+fn apng_holds_every_frame() {
+    use image::codecs::png::PngDecoder;
+    use image::AnimationDecoder;
+
+    let frames = animation_frames();
+    let path = std::env::temp_dir().join("cells_test_animation.png");
+    save_apng(&frames, &path, Duration::from_millis(40)).unwrap();
+    let decoder = PngDecoder::new(BufReader::new(File::open(&path).unwrap())).unwrap();
+    assert!(decoder.is_apng().unwrap());
+    let decoded = decoder.apng().unwrap().into_frames().collect_frames().unwrap();
+    assert_eq!(decoded.len(), frames.len());
+    for (frame, field) in decoded.iter().zip(&frames) {
+        assert_eq!(frame.buffer().dimensions(), (40, 40));
+        let gray: Vec<u8> = frame.buffer().pixels().map(|p| p[0]).collect();
+        assert_eq!(gray, field.to_luma_image().into_raw());
+    }
+}
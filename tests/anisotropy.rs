@@ -0,0 +1,111 @@
+//! Scaled distances must stretch the cells without breaking the grid search
+//! or the tiling, and equal factors must keep the isotropic diagram.
+
+use cells::grid::PointGrid;
+use cells::tiling::{assert_tileable, interior_discontinuity};
+use cells::voronoi::{generate_voronoi_cell_ids, generate_voronoi_field, Feature, VoronoiParams};
+use cells::{DistanceMetric, Point, Weighting};
+use rand::{Rng, SeedableRng};
+
+fn params() -> VoronoiParams {
+    VoronoiParams { size: 96, num_points: 60, seed: 13, ..Default::default() }
+}
+
+#[test]
+fn equal_factors_keep_the_isotropic_diagram() {
+    for feature in [Feature::F1, Feature::F2, Feature::F2MinusF1] {
+        for metric in [DistanceMetric::Euclidean, DistanceMetric::Manhattan, DistanceMetric::Chebyshev] {
+            let isotropic = VoronoiParams { feature, metric, relax_iterations: 1, ..params() };
+            let expected = generate_voronoi_field(&isotropic);
+            // Powers of two scale every distance without rounding
+            for scale in [(1.0, 1.0), (2.0, 2.0), (0.25, 0.25)] {
+                let scaled = VoronoiParams { cell_scale: scale, ..isotropic.clone() };
+                assert_eq!(generate_voronoi_field(&scaled), expected, "{feature}, {metric}, {scale:?}");
+            }
+            let thirds = generate_voronoi_field(&VoronoiParams { cell_scale: (3.0, 3.0), ..isotropic.clone() });
+            let close = thirds.as_slice().iter().zip(expected.as_slice()).all(|(a, b)| (a - b).abs() < 1e-5);
+            assert!(close, "{feature}, {metric}");
+        }
+    }
+}
+
+#[test]
+fn scaled_grids_find_the_brute_force_points() {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(40);
+    let metrics = [DistanceMetric::Euclidean, DistanceMetric::Chebyshev, DistanceMetric::Minkowski(3.0)];
+    for num_points in [3, 40, 500] {
+        let points: Vec<Point> = (0..num_points).map(|_| Point { x: rng.gen(), y: rng.gen() }).collect();
+        let weights: Vec<f32> = points.iter().map(|_| rng.gen_range(0.0..0.05)).collect();
+        for metric in metrics {
+            for (weighting, scale) in [(Weighting::None, (1.0, 5.0)), (Weighting::Additive, (0.3, 1.5))] {
+                let grid = PointGrid::with_weights(&points, metric, &weights, weighting).with_scale(scale.0, scale.1);
+                let scalar = grid.clone().with_simd(false);
+                for _ in 0..500 {
+                    let p = Point { x: rng.gen(), y: rng.gen() };
+                    let mut brute_force: Vec<f32> = points
+                        .iter()
+                        .zip(&weights)
+                        .map(|(&q, &weight)| {
+                            let distance = metric.expand(metric.toroidal_reduced_scaled(p, q, scale));
+                            weighting.apply(distance, weight)
+                        })
+                        .collect();
+                    brute_force.sort_by(f32::total_cmp);
+                    let [(_, f1), (_, f2)] = grid.nearest_two(p).unwrap();
+                    assert_eq!((f1, f2), (brute_force[0], brute_force[1]), "{metric}, {scale:?}");
+                    assert_eq!(grid.nearest_two(p), scalar.nearest_two(p));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn stretched_cells_are_wider_than_tall_and_tile() {
+    let stretched = VoronoiParams { cell_scale: (1.0, 3.0), ..params() };
+    let ids = generate_voronoi_cell_ids(&stretched);
+    let (mut across, mut down) = (0, 0);
+    for y in 0..96 {
+        for x in 0..96 {
+            let id = ids.get_pixel(x, y)[0];
+            across += (id != ids.get_pixel((x + 1) % 96, y)[0]) as u32;
+            down += (id != ids.get_pixel(x, (y + 1) % 96)[0]) as u32;
+        }
+    }
+    assert!(down > 2 * across, "{down} cell borders down, {across} across");
+
+    for feature in [Feature::F1, Feature::F2MinusF1] {
+        let field = generate_voronoi_field(&VoronoiParams { feature, ..stretched.clone() });
+        assert_tileable(&field, interior_discontinuity(&field));
+        // Normalization divides by the largest scaled feature, the brightest
+        // edge of F1 and the darkest center of F2 - F1
+        let (min, max) = field.min_max().unwrap();
+        assert!(min >= 0.0 && max <= 1.0 && (max == 1.0 || min == 0.0), "{feature}: {min} to {max}");
+    }
+}
+
+#[test]
+fn cell_scales_are_validated() {
+    for scale in [(0.0, 1.0), (1.0, -2.0), (f32::NAN, 1.0), (1.0, f32::INFINITY)] {
+        let err = VoronoiParams { cell_scale: scale, ..params() }.validate().unwrap_err();
+        assert!(err.to_string().starts_with("invalid cell_scale: must be positive"), "{err}");
+    }
+    let params: VoronoiParams = toml::from_str("cell_scale = [1.0, 3.0]").unwrap();
+    assert_eq!(params.cell_scale, (1.0, 3.0));
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn the_binary_stretches_cells() {
+    let run = |scale: &str| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_cells"))
+            .args(["--size", "32", "--points", "8", "--cell-scale", scale, "--out-dir"])
+            .arg(std::env::temp_dir().join("cells_test_anisotropy"))
+            .output()
+            .unwrap()
+    };
+    assert!(run("1,3").status.success());
+    let output = run("0,3");
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("scale factors must be positive"));
+}
@@ -0,0 +1,56 @@
+//! The cave smoothing automaton must follow the 4-5 rule across the
+//! wrap-around edges.
+
+use cells::automata::{cellular_smooth, cellular_smooth_with, DEFAULT_BIRTH_LIMIT, DEFAULT_DEATH_LIMIT};
+use cells::tone::threshold;
+use cells::transform::offset_field;
+use cells::voronoi::{generate_voronoi_field, VoronoiParams};
+use cells::FieldBuffer;
+
+fn smooth(mask: &FieldBuffer, iterations: u32) -> FieldBuffer {
+    cellular_smooth(mask, iterations, DEFAULT_BIRTH_LIMIT, DEFAULT_DEATH_LIMIT)
+}
+
+#[test]
+fn solid_masks_stay_solid() {
+    let solid = FieldBuffer::from_vec(16, 16, vec![1.0; 256]);
+    assert_eq!(smooth(&solid, 10), solid);
+}
+
+#[test]
+fn isolated_pixels_die_in_one_iteration() {
+    for (x, y) in [(7, 7), (0, 0), (15, 9)] {
+        let speck = FieldBuffer::from_par_fn(16, 16, |i, j| if (i, j) == (x, y) { 1.0 } else { 0.0 });
+        assert_eq!(smooth(&speck, 1), FieldBuffer::new(16, 16), "speck at ({x}, {y})");
+    }
+}
+
+#[test]
+fn neighbors_wrap_around_the_edges() {
+    // A 3x3 block in the middle and the same block split over all four corners
+    let inside = |x: u32, y: u32| (6..9).contains(&x) && (6..9).contains(&y);
+    let block = FieldBuffer::from_par_fn(16, 16, |x, y| if inside(x, y) { 1.0 } else { 0.0 });
+    let corners = offset_field(&block, -7, -7);
+    assert_eq!(corners.get(0, 0), 1.0);
+    assert_eq!(corners.get(15, 15), 1.0);
+    assert_eq!(smooth(&corners, 3), offset_field(&smooth(&block, 3), -7, -7));
+
+    let voronoi = generate_voronoi_field(&VoronoiParams {
+        size: 64,
+        num_points: 20,
+        seed: 8,
+        ..Default::default()
+    });
+    let mask = threshold(&voronoi, 0.4);
+    assert_eq!(smooth(&offset_field(&mask, 21, 40), 4), offset_field(&smooth(&mask, 4), 21, 40));
+}
+
+#[test]
+fn every_iteration_is_reported() {
+    let mask = FieldBuffer::from_par_fn(16, 16, |x, y| ((x * 7 + y * 13) % 5 < 3) as u32 as f32);
+    let mut iterations = Vec::new();
+    let smoothed = cellular_smooth_with(&mask, 4, 5, 4, |i, cells| iterations.push((i, cells.clone())));
+    assert_eq!(iterations.iter().map(|&(i, _)| i).collect::<Vec<_>>(), [0, 1, 2, 3]);
+    assert_eq!(iterations[0].1, smooth(&mask, 1));
+    assert_eq!(iterations[3].1, smoothed);
+}
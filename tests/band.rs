@@ -0,0 +1,77 @@
+//! Textures rendered in bands must equal the textures rendered at once, for
+//! every band height, including bands of single rows and bands taller than
+//! the texture.
+
+use cells::band::{render_all, GaussianBlurred, Stretched};
+use cells::filter::{gaussian_blur, normalize_field};
+#[cfg(feature = "file-io")]
+use cells::io::save_png_bands;
+use cells::noise::{generate_perlin_field, FbmParams, NoiseKind, NoiseStyle, PerlinBands};
+use cells::sampling::PointDistribution;
+use cells::voronoi::{generate_voronoi_field, Feature, VoronoiBands, VoronoiParams};
+use cells::DistanceMetric;
+
+#[test]
+fn voronoi_bands_match_the_whole_diagram() {
+    for (feature, metric, invert) in [
+        (Feature::F1, DistanceMetric::Euclidean, false),
+        (Feature::F2, DistanceMetric::Manhattan, true),
+        (Feature::F2MinusF1, DistanceMetric::Minkowski(3.0), false),
+    ] {
+        let params = VoronoiParams { size: 61, num_points: 25, seed: 11, feature, metric, invert, ..Default::default() };
+        let whole = generate_voronoi_field(&params);
+        let bands = VoronoiBands::new(&params).unwrap();
+        for rows in [1, 7, 61, 100] {
+            assert_eq!(render_all(&bands, rows), whole, "{feature} {metric} in bands of {rows} rows");
+        }
+    }
+}
+
+#[test]
+fn single_cells_without_a_second_point_are_flat() {
+    // F2 - F1 is 0 everywhere, so there is no largest distance to divide by
+    let lattice = PointDistribution::JitteredGrid { cols: 1, rows: 1, jitter: 0.5 };
+    for distribution in [PointDistribution::Uniform, lattice] {
+        let feature = Feature::F2MinusF1;
+        let params = VoronoiParams { size: 16, num_points: 1, feature, distribution, ..Default::default() };
+        let whole = generate_voronoi_field(&params);
+        assert!(whole.as_slice().iter().all(|&v| v == whole.get(0, 0)), "{distribution:?}");
+        assert_eq!(render_all(&VoronoiBands::new(&params).unwrap(), 5), whole);
+    }
+}
+
+#[test]
+fn blurred_noise_bands_match_the_whole_texture() {
+    for (kind, style) in [(NoiseKind::Perlin, NoiseStyle::Fbm), (NoiseKind::Simplex, NoiseStyle::Billow)] {
+        let params = FbmParams { octaves: 3, frequency: 3.0, kind, style, seed: 2, ..Default::default() };
+        let whole = gaussian_blur(&normalize_field(&generate_perlin_field(45, &params)), 2.5);
+        for rows in [1, 4, 45] {
+            let stretched = Stretched::new(PerlinBands::new(45, &params).unwrap(), rows);
+            // The halo of 8 rows is taller than bands of 1 and 4 rows
+            let blurred = GaussianBlurred::new(stretched, 2.5);
+            assert_eq!(blurred.halo(), 8);
+            assert_eq!(render_all(&blurred, rows), whole, "{kind} {style} in bands of {rows} rows");
+        }
+    }
+}
+
+#[cfg(feature = "file-io")]
+#[test]
+fn streamed_pngs_decode_to_the_whole_texture() {
+    let params = VoronoiParams { size: 50, num_points: 30, seed: 5, feature: Feature::F2MinusF1, ..Default::default() };
+    let whole = generate_voronoi_field(&params);
+    let bands = VoronoiBands::new(&params).unwrap();
+    let path = std::env::temp_dir().join("cells_test_streamed.png");
+    save_png_bands(&bands, 9, &path, 8).unwrap();
+    assert_eq!(image::open(&path).unwrap().to_luma8(), whole.to_luma_image());
+    save_png_bands(&bands, 9, &path, 16).unwrap();
+    assert_eq!(image::open(&path).unwrap().to_luma16(), whole.to_luma16_image());
+}
+
+#[test]
+fn options_needing_the_whole_texture_are_rejected() {
+    let params = VoronoiParams { size: 32, num_points: 10, ..Default::default() };
+    assert!(VoronoiBands::new(&VoronoiParams { clip: Some((1.0, 99.0)), ..params.clone() }).is_err());
+    assert!(VoronoiBands::new(&VoronoiParams { warp_strength: 0.1, ..params.clone() }).is_err());
+    assert!(VoronoiBands::new(&params).is_ok());
+}
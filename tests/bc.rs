@@ -0,0 +1,62 @@
+//! BC4 and BC5 blocks must decode close to their source.
+
+#![cfg(all(feature = "block-compression", feature = "file-io"))]
+
+use cells::bc::{decode_bc4, decode_bc5, encode_bc4, encode_bc5};
+use cells::material::height_to_normal;
+use cells::noise::{generate_perlin_field, FbmParams};
+use cells::resample::{mip_chain, ResampleFilter};
+use cells::texture::{Texture, TextureFormat};
+use cells::FieldBuffer;
+
+/// The peak signal-to-noise ratio of a decoded field against its source in dB
+fn psnr(decoded: &FieldBuffer, source: &FieldBuffer) -> f64 {
+    let squared: f64 = decoded.as_slice().iter().zip(source.as_slice()).map(|(a, b)| ((a - b) as f64).powi(2)).sum();
+    10.0 * (source.as_slice().len() as f64 / squared).log10()
+}
+
+fn height() -> FieldBuffer {
+    generate_perlin_field(62, &FbmParams { seed: 8, ..Default::default() })
+}
+
+#[test]
+fn bc4_keeps_noise_detail() {
+    let field = height();
+    let decoded = decode_bc4(&encode_bc4(&field), 62, 62).unwrap();
+    let quality = psnr(&decoded, &field);
+    assert!(quality > 40.0, "BC4 PSNR {quality:.1} dB");
+    assert!(decode_bc4(&[0; 8], 62, 62).is_err());
+}
+
+#[test]
+fn bc5_keeps_normal_directions() {
+    let normals = height_to_normal(&height(), 8.0);
+    let channel = |c: usize| FieldBuffer::from_par_fn(62, 62, |x, y| normals.get_pixel(x, y)[c] as f32 / 255.0);
+    let (x, y) = (channel(0), channel(1));
+    let (dx, dy) = decode_bc5(&encode_bc5(&x, &y).unwrap(), 62, 62).unwrap();
+    for (decoded, source) in [(dx, x), (dy, y)] {
+        let quality = psnr(&decoded, &source);
+        assert!(quality > 35.0, "BC5 PSNR {quality:.1} dB");
+    }
+}
+
+#[test]
+fn constant_and_extreme_blocks_are_exact() {
+    for value in [0.0, 0.5, 1.0] {
+        let field = FieldBuffer::from_vec(4, 4, vec![value; 16]);
+        let decoded = decode_bc4(&encode_bc4(&field), 4, 4).unwrap();
+        assert!(decoded.as_slice().iter().all(|v| (v - value).abs() <= 0.5 / 255.0 + 1e-6), "{value}: {decoded:?}");
+    }
+    let checker = FieldBuffer::from_par_fn(4, 4, |x, y| ((x + y) % 2) as f32);
+    assert_eq!(decode_bc4(&encode_bc4(&checker), 4, 4).unwrap(), checker);
+}
+
+#[test]
+fn compressed_levels_round_up_to_blocks() {
+    let chain = mip_chain(&height(), ResampleFilter::Box);
+    let levels: Vec<_> = chain.iter().map(|level| vec![level.clone(), level.clone()]).collect();
+    let texture = Texture::encode(TextureFormat::Bc5, &levels).unwrap();
+    assert_eq!(texture.levels().iter().map(Vec::len).collect::<Vec<_>>(), [4096, 1024, 256, 64, 16, 16]);
+    let decoded = decode_bc5(&texture.levels()[2], 15, 15).unwrap().0;
+    assert!(psnr(&decoded, &chain[2]) > 40.0);
+}
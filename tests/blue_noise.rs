@@ -0,0 +1,46 @@
+//! Void-and-cluster blue noise must spread its pixels more evenly than white
+//! noise at every threshold, also across the wrap-around edges.
+
+use cells::noise::{generate_blue_noise, generate_white_noise};
+use cells::transform::offset_field;
+use cells::FieldBuffer;
+
+/// The variance of the `block` x `block` averages of a field, a proxy for its
+/// low-frequency energy
+fn block_variance(field: &FieldBuffer, block: u32) -> f32 {
+    let (width, height) = field.dimensions();
+    let mut averages = Vec::new();
+    for by in (0..height).step_by(block as usize) {
+        for bx in (0..width).step_by(block as usize) {
+            let pixels = (0..block).flat_map(|y| (0..block).map(move |x| (bx + x, by + y)));
+            let sum: f32 = pixels.map(|(x, y)| field.get(x, y)).sum();
+            averages.push(sum / (block * block) as f32);
+        }
+    }
+    let mean = averages.iter().sum::<f32>() / averages.len() as f32;
+    averages.iter().map(|a| (a - mean) * (a - mean)).sum::<f32>() / averages.len() as f32
+}
+
+#[test]
+fn blue_noise_has_less_low_frequency_energy_than_white_noise() {
+    let blue = generate_blue_noise(64, 1);
+    let white = generate_white_noise(64, 1);
+    // Blocks straddling the seam are as even as the others
+    for (dx, dy) in [(0, 0), (2, 2), (5, -3)] {
+        let (blue, white) = (offset_field(&blue, dx, dy), offset_field(&white, dx, dy));
+        assert!(block_variance(&blue, 4) < block_variance(&white, 4) / 4.0);
+        for t in [0.1, 0.5, 0.9] {
+            let dots = |field: &FieldBuffer| field.map(|value| if value < t { 1.0 } else { 0.0 });
+            let (blue, white) = (block_variance(&dots(&blue), 8), block_variance(&dots(&white), 8));
+            assert!(blue < white / 2.0, "threshold {t}: blue {blue}, white {white}");
+        }
+    }
+}
+
+#[test]
+fn blue_noise_is_deterministic_per_seed() {
+    let mask = generate_blue_noise(32, 4);
+    assert_eq!(mask, generate_blue_noise(32, 4));
+    assert_ne!(mask, generate_blue_noise(32, 5));
+    assert_eq!(generate_blue_noise(1, 0).as_slice(), [0.0]);
+}
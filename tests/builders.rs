@@ -0,0 +1,131 @@
+//! The builders must reproduce the default parameters and reject invalid
+//! combinations when building, and the parameter types must round-trip
+//! through JSON and TOML, the formats of manifests and pipelines.
+
+use cells::filter::{BlurBuilder, BlurKernel, BlurParams, BlurSampling, DirectionMapping};
+use cells::noise::{FbmBuilder, FbmParams, NoiseKind, NoiseStyle};
+use cells::pipeline::Pipeline;
+use cells::sampling::PointDistribution;
+use cells::voronoi::{
+    generate_voronoi_field, Feature, VoronoiBackend, VoronoiBuilder, VoronoiParams, WorleyReturn,
+};
+use cells::{CellsError, DistanceMetric, Weighting};
+
+/// The name of the parameter an error rejects
+fn rejected(err: CellsError) -> &'static str {
+    match err {
+        CellsError::InvalidParameter { name, .. } => name,
+        other => panic!("expected an invalid parameter, got {other}"),
+    }
+}
+
+#[test]
+fn builders_default_to_the_default_parameters() {
+    assert_eq!(VoronoiBuilder::new().build().unwrap(), VoronoiParams::default());
+    assert_eq!(FbmBuilder::new().build().unwrap(), FbmParams::default());
+    assert_eq!(BlurBuilder::new().build().unwrap(), BlurParams::default());
+    let params = VoronoiParams { size: 48, ..Default::default() };
+    assert_eq!(VoronoiBuilder::new().size(48).generate().unwrap(), generate_voronoi_field(&params));
+}
+
+#[test]
+fn invalid_combinations_are_rejected_when_building() {
+    assert_eq!(rejected(BlurBuilder::new().radius(-1).build().unwrap_err()), "radius");
+    let narrow = BlurBuilder::new().kernel(BlurKernel::Gaussian { sigma: 0.0 });
+    assert_eq!(rejected(narrow.build().unwrap_err()), "kernel");
+
+    let voronoi = VoronoiBuilder::new().size(32);
+    let distributed = |distribution| (voronoi.clone().distribution(distribution), "distribution");
+    for (builder, name) in [
+        (voronoi.clone().size(0), "size"),
+        (voronoi.clone().num_points(0), "num_points"),
+        distributed(PointDistribution::JitteredGrid { cols: 4, rows: 4, jitter: 1.5 }),
+        distributed(PointDistribution::HexGrid { cols: 12, rows: 13, jitter: 0.0 }),
+        distributed(PointDistribution::PoissonDisk { min_dist: 0.0 }),
+        (voronoi.clone().metric(DistanceMetric::Minkowski(0.5)), "metric"),
+        (voronoi.clone().weighting(Weighting::Multiplicative, (0.0, 1.0)), "weight_range"),
+        (voronoi.clone().weighting(Weighting::Additive, (0.1, f32::NAN)), "weight_range"),
+        (voronoi.clone().warp(-0.1, 4.0), "warp_strength"),
+        (voronoi.clone().warp(0.1, 0.0), "warp_frequency"),
+        (voronoi.clone().clip(60.0, 40.0), "clip"),
+    ] {
+        assert_eq!(rejected(builder.clone().build().unwrap_err()), name, "{builder:?}");
+        assert_eq!(rejected(builder.generate().unwrap_err()), name);
+    }
+    // Lattices ignore the point count
+    let lattice = PointDistribution::JitteredGrid { cols: 4, rows: 4, jitter: 1.0 };
+    assert!(voronoi.distribution(lattice).num_points(0).build().is_ok());
+
+    assert_eq!(rejected(FbmBuilder::new().octaves(0).build().unwrap_err()), "octaves");
+    assert_eq!(rejected(FbmBuilder::new().lacunarity(0.5).build().unwrap_err()), "lacunarity");
+    assert_eq!(rejected(FbmBuilder::new().generate(0).unwrap_err()), "size");
+}
+
+#[test]
+fn parameters_round_trip_through_json_and_toml() {
+    let voronoi = VoronoiBuilder::new()
+        .size(96)
+        .num_points(50)
+        .distribution(PointDistribution::HexGrid { cols: 12, rows: 14, jitter: 0.25 })
+        .feature(Feature::F2MinusF1)
+        .metric(DistanceMetric::Minkowski(3.0))
+        .weighting(Weighting::Additive, (0.0, 0.02))
+        .warp(0.05, 3.0)
+        .backend(VoronoiBackend::NoiseWorley { frequency: 6.0, return_type: WorleyReturn::Value })
+        .clip(2.0, 98.0)
+        .build()
+        .unwrap();
+    let fbm = FbmBuilder::new().octaves(3).kind(NoiseKind::Simplex).style(NoiseStyle::Ridged).build().unwrap();
+    let blur = BlurBuilder::new()
+        .radius(7)
+        .sampling(BlurSampling::Nearest)
+        .kernel(BlurKernel::Gaussian { sigma: 2.5 })
+        .mapping(DirectionMapping::SignedFlow)
+        .build()
+        .unwrap();
+
+    // Manifests record the parameters as JSON
+    #[cfg(feature = "file-io")]
+    assert_eq!(serde_json::from_str::<VoronoiParams>(&serde_json::to_string(&voronoi).unwrap()).unwrap(), voronoi);
+    assert_eq!(toml::from_str::<VoronoiParams>(&toml::to_string(&voronoi).unwrap()).unwrap(), voronoi);
+    assert_eq!(toml::from_str::<FbmParams>(&toml::to_string(&fbm).unwrap()).unwrap(), fbm);
+    assert_eq!(toml::from_str::<BlurParams>(&toml::to_string(&blur).unwrap()).unwrap(), blur);
+
+    // Values use the command line syntax
+    let toml = toml::to_string(&blur).unwrap();
+    assert!(toml.contains(r#"kernel = "gaussian:2.5""#), "{toml}");
+    assert!(toml::from_str::<BlurParams>(r#"kernel = "gaussian:0""#).is_err());
+    assert!(toml::from_str::<VoronoiParams>("colour = 3").is_err());
+}
+
+#[test]
+fn pipeline_voronoi_nodes_take_every_parameter() {
+    let pipeline = Pipeline::from_toml(
+        r#"
+[[node]]
+name = "cells"
+type = "voronoi"
+size = 48
+points = 12
+seed = 3
+weighting = "additive"
+weight_range = [0.0, 0.03]
+warp_strength = 0.04
+"#,
+    )
+    .unwrap();
+    let expected = VoronoiBuilder::new()
+        .size(48)
+        .num_points(12)
+        .seed(3)
+        .weighting(Weighting::Additive, (0.0, 0.03))
+        .warp(0.04, VoronoiParams::default().warp_frequency)
+        .generate()
+        .unwrap();
+    assert_eq!(pipeline.execute().unwrap()["cells"], expected);
+
+    let err = Pipeline::from_toml("[[node]]\nname = \"cells\"\ntype = \"voronoi\"\ncolour = 3\n").unwrap_err();
+    assert!(err.to_string().starts_with("node `cells`: unknown field `colour`"), "{err}");
+    let warped = "[[node]]\nname = \"cells\"\ntype = \"voronoi\"\nsize = 16\nwarp_strength = -1.0\n";
+    assert!(Pipeline::from_toml(warped).unwrap().execute().is_err());
+}
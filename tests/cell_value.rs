@@ -0,0 +1,80 @@
+//! The cell-value feature must give every pixel the value of its nearest
+//! point, drawn from the seed or supplied, and the value smoothing must blend
+//! neighboring cells into a continuous, still tileable texture.
+
+use cells::tiling::{assert_tileable, interior_discontinuity};
+use cells::voronoi::{
+    generate_voronoi_cell_ids, generate_voronoi_features, generate_voronoi_field, Feature, VoronoiBuilder,
+    VoronoiParams,
+};
+use cells::{CellsError, FieldBuffer};
+
+/// The cell-value parameters of 16 points
+fn params() -> VoronoiParams {
+    VoronoiParams { size: 64, num_points: 16, seed: 7, feature: Feature::CellValue, ..Default::default() }
+}
+
+/// The sum of the squared differences of horizontally or vertically adjacent
+/// samples, across the edges too, which a step spread over more pixels lowers
+fn roughness(field: &FieldBuffer) -> f32 {
+    let (width, height) = (field.width() as i64, field.height() as i64);
+    let mut sum = 0.0;
+    for y in 0..height {
+        for x in 0..width {
+            let here = field.get_wrapped(x, y);
+            sum += (here - field.get_wrapped(x + 1, y)).powi(2) + (here - field.get_wrapped(x, y + 1)).powi(2);
+        }
+    }
+    sum
+}
+
+#[test]
+fn pixels_take_the_value_of_their_cell() {
+    let values: Vec<f32> = (0..16).map(|i| i as f32 / 16.0).collect();
+    let supplied = VoronoiParams { cell_values: values.clone(), ..params() };
+    let features = generate_voronoi_features(&supplied);
+    let ids = generate_voronoi_cell_ids(&supplied);
+    for (feature, id) in features.as_slice().iter().zip(ids.pixels()) {
+        assert_eq!(*feature, values[id[0] as usize]);
+    }
+    // Values in 0-1 are kept as they are, or inverted
+    assert_eq!(generate_voronoi_field(&supplied), features);
+    let inverted = generate_voronoi_field(&VoronoiParams { invert: true, ..supplied });
+    assert!(inverted.as_slice().iter().zip(features.as_slice()).all(|(i, f)| (i + f - 1.0).abs() < 1e-6));
+
+    // Fewer values than points repeat
+    let repeated = generate_voronoi_features(&VoronoiParams { cell_values: vec![0.25, 0.75], ..params() });
+    assert!(repeated.as_slice().iter().all(|&value| value == 0.25 || value == 0.75));
+}
+
+#[test]
+fn random_values_follow_the_seed() {
+    let random = generate_voronoi_features(&params());
+    assert_eq!(random, generate_voronoi_features(&params()));
+    assert_ne!(random, generate_voronoi_features(&VoronoiParams { seed: 8, ..params() }));
+    assert!(random.as_slice().iter().all(|value| (0.0..1.0).contains(value)));
+    let (min, max) = random.min_max().unwrap();
+    assert!(max - min > 0.3, "16 cells span only {min} to {max}");
+}
+
+#[test]
+fn smoothing_removes_the_steps_between_cells() {
+    let flat = generate_voronoi_field(&params());
+    let smooth = generate_voronoi_field(&VoronoiParams { value_smoothing: 0.1, ..params() });
+    assert_ne!(smooth, flat);
+    let (flat_roughness, smooth_roughness) = (roughness(&flat), roughness(&smooth));
+    assert!(smooth_roughness < flat_roughness / 3.0, "smoothing leaves {smooth_roughness} of {flat_roughness}");
+    assert_tileable(&smooth, interior_discontinuity(&smooth));
+}
+
+#[test]
+fn invalid_values_and_smoothing_are_rejected() {
+    let rejected = |builder: VoronoiBuilder| match builder.feature(Feature::CellValue).build() {
+        Err(CellsError::InvalidParameter { name, .. }) => name,
+        other => panic!("expected an invalid parameter, got {other:?}"),
+    };
+    assert_eq!(rejected(VoronoiBuilder::new().cell_values(vec![0.5, f32::NAN])), "cell_values");
+    assert_eq!(rejected(VoronoiBuilder::new().value_smoothing(-0.1)), "value_smoothing");
+    assert_eq!("cell-value".parse::<Feature>(), Ok(Feature::CellValue));
+    assert_eq!(Feature::CellValue.to_string(), "cell-value");
+}
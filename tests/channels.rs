@@ -0,0 +1,73 @@
+//! The image filters must treat every color channel on its own, pass alpha
+//! through untouched, and keep the results of single-channel images.
+
+use image::{GrayImage, Rgba, RgbaImage, RgbImage};
+
+use cells::color::{CellColoring, Palette};
+use cells::filter::{directional_blur, normalize_image, normalize_image_with, ChannelNormalization};
+use cells::voronoi::{generate_colored_cells, generate_voronoi_field, VoronoiParams};
+use cells::FieldBuffer;
+
+/// The parameters of the colored fixture
+fn params() -> VoronoiParams {
+    VoronoiParams { size: 48, num_points: 12, seed: 21, ..Default::default() }
+}
+
+/// A mosaic of colored cells with a gradient in alpha
+fn fixture() -> RgbaImage {
+    let palette = ["#335c67", "#fff3b0", "#e09f3e", "#9e2a2b", "#540b0e"].join("\n");
+    let coloring = CellColoring::Palette(Palette::parse(&palette).unwrap());
+    let mosaic = generate_colored_cells(&params(), &coloring, false);
+    RgbaImage::from_fn(48, 48, |x, y| {
+        let [r, g, b] = mosaic.get_pixel(x, y).0;
+        Rgba([r, g, b, (x * 5 + y) as u8])
+    })
+}
+
+/// One channel of an image as a grayscale image
+fn channel(img: &RgbaImage, c: usize) -> GrayImage {
+    FieldBuffer::from_image_channel(img, c).to_luma_image()
+}
+
+#[test]
+fn directional_blur_treats_every_channel_on_its_own() {
+    let colored = fixture();
+    let direction = generate_voronoi_field(&params()).to_luma_image();
+    let blurred = directional_blur(&colored, &direction, 6);
+    for c in 0..3 {
+        assert_eq!(channel(&blurred, c), directional_blur(&channel(&colored, c), &direction, 6), "channel {c}");
+    }
+    assert_eq!(channel(&blurred, 3), channel(&colored, 3));
+    assert_ne!(channel(&blurred, 1), channel(&colored, 1), "the green channel is blurred too");
+
+    // The value of rgb-red images stays in the red channel
+    let red = generate_voronoi_field(&params()).to_rgb_image();
+    let blurred_red: RgbImage = directional_blur(&red, &direction, 6);
+    let gray = directional_blur(&generate_voronoi_field(&params()).to_luma_image(), &direction, 6);
+    assert!(blurred_red.pixels().zip(gray.pixels()).all(|(r, g)| r.0 == [g[0], 0, 0]));
+}
+
+#[test]
+fn normalization_stretches_channels_on_their_own_or_together() {
+    // Every channel spans a different part of 0-255
+    let colored = RgbaImage::from_fn(32, 32, |x, y| Rgba([40 + x as u8, 100 + 3 * y as u8, 200 + x as u8, 9]));
+    let per_channel = normalize_image(&colored);
+    assert_eq!(per_channel, normalize_image_with(&colored, ChannelNormalization::PerChannel));
+    for c in 0..3 {
+        assert_eq!(channel(&per_channel, c), normalize_image(&channel(&colored, c)), "channel {c}");
+        let values: Vec<u8> = per_channel.pixels().map(|p| p[c]).collect();
+        assert_eq!((values.iter().min(), values.iter().max()), (Some(&0), Some(&255)), "channel {c}");
+    }
+    assert!(per_channel.pixels().all(|p| p[3] == 9));
+
+    // One stretch for all channels keeps blue above red
+    let linked = normalize_image_with(&colored, ChannelNormalization::Luminance);
+    assert!(linked.pixels().zip(colored.pixels()).all(|(l, c)| l[3] == c[3] && l[2] >= l[0]));
+    assert_ne!(linked, per_channel);
+
+    // Grayscale images normalize alike either way
+    let gray = channel(&fixture(), 1);
+    assert_eq!(normalize_image_with(&gray, ChannelNormalization::Luminance), normalize_image(&gray));
+    assert_eq!("luminance".parse(), Ok(ChannelNormalization::Luminance));
+    assert_eq!(ChannelNormalization::PerChannel.to_string(), "per-channel");
+}
@@ -0,0 +1,96 @@
+//! Cell mosaics must take their colors from the palette or the HSV ranges,
+//! follow the seed, and keep adjacent cells apart when asked to; palettes
+//! must be parsed with the line numbers of their errors.
+
+use image::{Rgb, RgbImage};
+
+use cells::color::{CellColoring, HsvRanges, Palette};
+use cells::voronoi::{generate_colored_cells, generate_voronoi_cell_ids, VoronoiParams};
+use cells::CellsError;
+
+/// The parameters of a mosaic of 40 cells
+fn params() -> VoronoiParams {
+    VoronoiParams { size: 96, num_points: 40, seed: 11, ..Default::default() }
+}
+
+/// The number of pairs of adjacent pixels, across the edges too, of different
+/// cells with the same color
+fn clashes(mosaic: &RgbImage, params: &VoronoiParams) -> usize {
+    let ids = generate_voronoi_cell_ids(params);
+    let size = params.size;
+    let mut clashes = 0;
+    for y in 0..size {
+        for x in 0..size {
+            for (nx, ny) in [((x + 1) % size, y), (x, (y + 1) % size)] {
+                let different = ids.get_pixel(x, y) != ids.get_pixel(nx, ny);
+                clashes += usize::from(different && mosaic.get_pixel(x, y) == mosaic.get_pixel(nx, ny));
+            }
+        }
+    }
+    clashes
+}
+
+/// A palette of `count` grays
+fn grays(count: u8) -> Palette {
+    Palette::new((0..count).map(|i| Rgb([i * 20; 3])).collect()).unwrap()
+}
+
+#[test]
+fn palettes_report_the_line_of_their_errors() {
+    let palette = Palette::parse("#000000\n\nffffff\n  #ff8800  \n").unwrap();
+    assert_eq!(palette.colors(), [Rgb([0, 0, 0]), Rgb([255, 255, 255]), Rgb([255, 136, 0])]);
+    for (text, line) in [("#000000\n\n#12345\n", 3), ("red\n", 1), ("#000000\n#0000000\n", 2)] {
+        match Palette::parse(text) {
+            Err(CellsError::InvalidPalette { line: found, .. }) => assert_eq!(found, line, "{text:?}"),
+            other => panic!("expected an invalid palette for {text:?}, got {other:?}"),
+        }
+    }
+    assert_eq!(Palette::parse("\n \n"), Err(CellsError::EmptyInput { input: "palette" }));
+
+    let strip = RgbImage::from_fn(4, 3, |x, y| Rgb([x as u8 * 60, y as u8, 0]));
+    assert_eq!(Palette::from_image(&strip).unwrap().colors(), (0..4).map(|x| Rgb([x * 60, 1, 0])).collect::<Vec<_>>());
+}
+
+#[test]
+fn mosaics_use_their_colors_and_follow_the_seed() {
+    let palette = grays(5);
+    let coloring = CellColoring::Palette(palette.clone());
+    let mosaic = generate_colored_cells(&params(), &coloring, false);
+    assert!(mosaic.pixels().all(|color| palette.colors().contains(color)));
+    assert_eq!(mosaic, generate_colored_cells(&params(), &coloring, false));
+    assert_ne!(mosaic, generate_colored_cells(&VoronoiParams { seed: 12, ..params() }, &coloring, false));
+
+    let ranges: HsvRanges = "s=0..0,v=0.5..0.5".parse().unwrap();
+    let gray = generate_colored_cells(&params(), &CellColoring::RandomHsv(ranges), false);
+    assert!(gray.pixels().all(|&color| color == Rgb([128; 3])));
+    let ranges: HsvRanges = "h=100..140,s=1..1,v=1..1".parse().unwrap();
+    let green = generate_colored_cells(&params(), &CellColoring::RandomHsv(ranges), false);
+    assert!(green.pixels().all(|&Rgb([r, g, b])| g == 255 && r < 128 && b < 128));
+}
+
+#[test]
+fn distinct_neighbors_keep_adjacent_cells_apart() {
+    let coloring = CellColoring::Palette(grays(8));
+    let random = generate_colored_cells(&params(), &coloring, false);
+    assert!(clashes(&random, &params()) > 0, "the test needs clashing neighbors to remove");
+    let distinct = generate_colored_cells(&params(), &coloring, true);
+    assert_eq!(clashes(&distinct, &params()), 0);
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn the_binary_reports_the_line_of_palette_errors() {
+    let palette = std::env::temp_dir().join("cells_test_color_palette.txt");
+    std::fs::write(&palette, "#335c67\n#fff3b0\n#e09f3\n").unwrap();
+    let out_dir = std::env::temp_dir().join("cells_test_color_out");
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_cells"))
+        .args(["--size", "32", "--color"])
+        .arg(&palette)
+        .arg("--out-dir")
+        .arg(&out_dir)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("line 3 of the palette: `#e09f3`"), "{stderr}");
+}
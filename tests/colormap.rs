@@ -0,0 +1,58 @@
+//! Colormaps must hit their stop colors exactly, blend monotonically between
+//! monotone stops, and parse the named maps and custom stops.
+
+use image::Rgb;
+
+use cells::color::{colormap, Colormap, Gradient};
+use cells::FieldBuffer;
+
+/// A ramp from 0 to 1 over `width` samples
+fn ramp(width: u32) -> FieldBuffer {
+    FieldBuffer::from_par_fn(width, 1, |x, _| x as f32 / (width - 1) as f32)
+}
+
+#[test]
+fn stops_map_to_their_exact_colors() {
+    let custom = "0:#123456,0.25:#ff8800,1:#fedcba".parse().unwrap();
+    for map in [Colormap::Viridis, Colormap::Magma, Colormap::Grayscale, custom] {
+        let gradient = map.gradient();
+        for &(position, color) in gradient.stops() {
+            assert_eq!(gradient.sample(position), color, "{map} at {position}");
+        }
+        let image = colormap(&ramp(101), &map);
+        let (first, last) = (gradient.stops()[0].1, gradient.stops().last().unwrap().1);
+        assert_eq!((*image.get_pixel(0, 0), *image.get_pixel(100, 0)), (first, last), "{map}");
+    }
+    // Values outside 0-1 are clamped
+    let outside = FieldBuffer::from_par_fn(2, 1, |x, _| if x == 0 { -3.0 } else { 7.0 });
+    let image = colormap(&outside, &Colormap::Grayscale);
+    assert_eq!((*image.get_pixel(0, 0), *image.get_pixel(1, 0)), (Rgb([0; 3]), Rgb([255; 3])));
+}
+
+#[test]
+fn monotone_gradients_blend_monotonically() {
+    let rising: Colormap = "0:#000000,0.3:#401080,0.7:#c080a0,1:#ffffff".parse().unwrap();
+    for map in [Colormap::Grayscale, rising] {
+        let image = colormap(&ramp(512), &map);
+        for c in 0..3 {
+            let channel: Vec<u8> = image.pixels().map(|pixel| pixel[c]).collect();
+            assert!(channel.windows(2).all(|pair| pair[0] <= pair[1]), "{map} channel {c} is not monotone");
+        }
+    }
+    // The blend happens in linear light, brighter than the sRGB average
+    assert_eq!(Colormap::Grayscale.gradient().sample(0.5), Rgb([188; 3]));
+}
+
+#[test]
+fn colormaps_parse_names_and_stops() {
+    for name in ["viridis", "magma", "grayscale"] {
+        assert_eq!(name.parse::<Colormap>().unwrap().to_string(), name);
+    }
+    let custom = "0:#000000,0.5:#ff8800,1:#ffffff";
+    assert_eq!(custom.parse::<Colormap>().unwrap().to_string(), custom);
+    assert_eq!(toml::Value::try_from(Colormap::Magma).unwrap(), toml::Value::String("magma".to_string()));
+    for invalid in ["jet", "0:#000000,1:#fffff", "0.6:#000000,0.4:#ffffff", "x:#000000"] {
+        assert!(invalid.parse::<Colormap>().is_err(), "{invalid}");
+    }
+    assert!(Gradient::new(Vec::new()).is_err());
+}
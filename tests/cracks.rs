@@ -0,0 +1,66 @@
+//! Cracks must tile, and every border between two cells must be kept or
+//! removed as a whole.
+
+use std::collections::HashMap;
+
+use cells::cracks::{generate_cracks, CrackParams};
+use cells::grid::PointGrid;
+use cells::sampling::{lloyd_relax, PointDistribution};
+use cells::tiling::{assert_tileable, interior_discontinuity};
+use cells::{DistanceMetric, Point};
+use rand::SeedableRng;
+
+fn params(removal: f32) -> CrackParams {
+    CrackParams {
+        size: 128,
+        num_points: 40,
+        width: 0.02,
+        removal,
+        seed: 6,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn cracks_tile() {
+    for removal in [0.0, 0.4] {
+        for fade in [0.0, 1.0] {
+            let cracks = generate_cracks(&CrackParams { fade, ..params(removal) });
+            assert!(cracks.as_slice().iter().all(|value| (0.0..=1.0).contains(value)));
+            assert_tileable(&cracks, interior_discontinuity(&cracks));
+        }
+    }
+}
+
+#[test]
+fn borders_are_removed_as_a_whole() {
+    let (all, some) = (generate_cracks(&params(0.0)), generate_cracks(&params(0.5)));
+    // The cells of the cracks, the relaxed uniform points of the seed
+    let mut rng = rand::rngs::StdRng::seed_from_u64(6);
+    let points = PointDistribution::Uniform.generate(40, &mut rng);
+    let grid = PointGrid::new(&lloyd_relax(&points, 2, DistanceMetric::Euclidean));
+
+    let mut borders: HashMap<(usize, usize), (u32, u32)> = HashMap::new();
+    for y in 0..128 {
+        for x in 0..128 {
+            if all.get(x, y) == 0.0 {
+                continue;
+            }
+            let [(a, _), (b, _)] = grid.nearest_two(Point { x: x as f32 / 128.0, y: y as f32 / 128.0 }).unwrap();
+            let (kept, removed) = borders.entry((a.min(b), a.max(b))).or_default();
+            match some.get(x, y) {
+                0.0 => *removed += 1,
+                value => {
+                    assert_eq!(value, all.get(x, y));
+                    *kept += 1;
+                }
+            }
+        }
+    }
+    for (&(a, b), &(kept, removed)) in &borders {
+        assert!(kept == 0 || removed == 0, "border of cells {a} and {b} is both kept and removed");
+    }
+    let removed = borders.values().filter(|&&(kept, _)| kept == 0).count();
+    let share = removed as f32 / borders.len() as f32;
+    assert!((0.3..0.7).contains(&share), "{removed} of {} borders removed", borders.len());
+}
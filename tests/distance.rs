@@ -0,0 +1,64 @@
+//! The distance transform must match the brute-force wrapped distance to the
+//! nearest on pixel exactly, for sparse and dense masks of any shape.
+
+use cells::distance::distance_transform;
+use cells::FieldBuffer;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// The distance from every pixel to the nearest on pixel, over all of them
+fn brute_force(mask: &FieldBuffer) -> FieldBuffer {
+    let (width, height) = mask.dimensions();
+    let on: Vec<(i64, i64)> = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .filter(|&(x, y)| mask.get(x, y) >= 0.5)
+        .map(|(x, y)| (x as i64, y as i64))
+        .collect();
+    FieldBuffer::from_par_fn(width, height, |x, y| {
+        let wrapped = |d: i64, n: u32| d.abs().min(n as i64 - d.abs());
+        on.iter()
+            .map(|&(ox, oy)| {
+                let (dx, dy) = (wrapped(x as i64 - ox, width), wrapped(y as i64 - oy, height));
+                ((dx * dx + dy * dy) as f32).sqrt()
+            })
+            .fold(f32::INFINITY, f32::min)
+    })
+}
+
+fn random_mask(width: u32, height: u32, density: f32, seed: u64) -> FieldBuffer {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let values = (0..width * height).map(|_| if rng.gen::<f32>() < density { 1.0 } else { 0.0 }).collect();
+    FieldBuffer::from_vec(width, height, values)
+}
+
+#[test]
+fn distances_match_brute_force() {
+    for (width, height) in [(16, 16), (23, 9), (1, 12), (31, 1)] {
+        for (seed, density) in [0.002, 0.02, 0.3, 0.9].into_iter().enumerate() {
+            let mask = random_mask(width, height, density, seed as u64);
+            if mask.as_slice().iter().all(|&v| v == 0.0) {
+                continue;
+            }
+            let expected = brute_force(&mask);
+            let distances = distance_transform(&mask);
+            for (i, (a, b)) in distances.as_slice().iter().zip(expected.as_slice()).enumerate() {
+                assert!((a - b).abs() < 1e-4, "{width}x{height} at density {density}, pixel {i}: {a} vs {b}");
+            }
+        }
+    }
+}
+
+#[test]
+fn single_pixels_reach_across_every_edge() {
+    let mask = FieldBuffer::from_par_fn(20, 14, |x, y| if (x, y) == (19, 0) { 1.0 } else { 0.0 });
+    assert_eq!(distance_transform(&mask), brute_force(&mask));
+    assert_eq!(distance_transform(&mask).get(0, 13), 2f32.sqrt());
+}
+
+#[test]
+fn empty_and_full_masks() {
+    let empty = distance_transform(&FieldBuffer::new(8, 5));
+    assert!(empty.as_slice().iter().all(|v| v.is_infinite()));
+    let full = distance_transform(&FieldBuffer::from_vec(8, 5, vec![1.0; 40]));
+    assert_eq!(full.as_slice(), [0.0; 40]);
+}
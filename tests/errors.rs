@@ -0,0 +1,74 @@
+//! Invalid parameters and unwritable outputs must be reported as specific
+//! errors by the library, and as messages with a failing exit status by the
+//! binary.
+
+use cells::voronoi::{VoronoiBands, VoronoiParams};
+use cells::CellsError;
+
+/// A path below a regular file, which cannot be created
+#[cfg(feature = "file-io")]
+fn unwritable_path(name: &str) -> std::path::PathBuf {
+    let file = std::env::temp_dir().join(format!("cells_test_errors_{name}"));
+    std::fs::write(&file, b"not a directory").unwrap();
+    file.join(format!("{name}.png"))
+}
+
+#[test]
+fn zero_points_and_zero_sizes_are_invalid_parameters() {
+    let params = VoronoiParams { size: 32, num_points: 8, ..Default::default() };
+    for (invalid, name) in [
+        (VoronoiParams { num_points: 0, ..params.clone() }, "num_points"),
+        (VoronoiParams { size: 0, ..params.clone() }, "size"),
+        (VoronoiParams { warp_strength: -0.1, ..params.clone() }, "warp_strength"),
+        (VoronoiParams { clip: Some((90.0, 10.0)), ..params.clone() }, "clip"),
+    ] {
+        let err = invalid.validate().unwrap_err();
+        assert!(matches!(err, CellsError::InvalidParameter { name: found, .. } if found == name), "{err}");
+        assert_eq!(VoronoiBands::new(&invalid).unwrap_err(), err);
+    }
+    assert!(params.validate().is_ok());
+}
+
+#[test]
+#[should_panic(expected = "invalid num_points: must be above 0")]
+fn generating_without_points_panics_with_the_reason() {
+    cells::voronoi::generate_voronoi_field(&VoronoiParams { size: 16, num_points: 0, ..Default::default() });
+}
+
+#[cfg(feature = "file-io")]
+#[test]
+fn saving_reports_empty_fields_unwritable_paths_and_unknown_formats() {
+    use cells::io::save_field;
+    use cells::FieldBuffer;
+
+    let path = std::env::temp_dir().join("cells_test_errors_empty.png");
+    assert_eq!(save_field(&FieldBuffer::new(0, 8), &path, None, 8), Err(CellsError::EmptyInput { input: "field" }));
+
+    let field = FieldBuffer::from_par_fn(8, 8, |x, y| (x + y) as f32 / 14.0);
+    let path = unwritable_path("library");
+    match save_field(&field, &path, None, 8) {
+        Err(CellsError::Io { path: failed, .. }) => assert_eq!(failed, path),
+        other => panic!("expected an I/O error, got {other:?}"),
+    }
+    let path = std::env::temp_dir().join("cells_test_errors.xyz");
+    let err = save_field(&field, &path, None, 8).unwrap_err();
+    assert!(matches!(err, CellsError::InvalidParameter { name: "file format", .. }), "{err}");
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn the_binary_fails_with_a_message() {
+    let cells = |args: &[&str]| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_cells")).args(args).output().unwrap();
+        assert!(!output.status.success(), "{args:?} succeeded");
+        String::from_utf8(output.stderr).unwrap()
+    };
+    let out_dir = std::env::temp_dir().join("cells_test_errors_out");
+    let out_dir = out_dir.to_str().unwrap();
+    assert!(cells(&["--points", "0", "--out-dir", out_dir]).contains("--points"));
+    assert!(cells(&["--size", "0", "--out-dir", out_dir]).contains("--size"));
+
+    let path = unwritable_path("binary");
+    let stderr = cells(&["bricks", "--seed", "1", "--output", path.to_str().unwrap()]);
+    assert!(stderr.starts_with(&format!("error: cannot write {}: ", path.display())), "{stderr}");
+}
@@ -0,0 +1,105 @@
+//! Fractal noise output. Golden values lock down the fBm output for fixed
+//! parameters, so changes to the noise sampling show up as test failures
+//! rather than silently different textures.
+
+use std::collections::HashSet;
+
+use cells::filter::normalize_field;
+use cells::noise::{generate_fbm_field, generate_perlin_field, FbmParams, NoiseKind, NoiseStyle};
+
+/// Allowed deviation, covering `sin`/`cos` rounding differences between platforms
+const EPSILON: f32 = 1e-5;
+
+fn golden_params() -> FbmParams {
+    FbmParams {
+        octaves: 8,
+        persistence: 0.45,
+        lacunarity: 2.2,
+        frequency: 4.0,
+        kind: NoiseKind::Perlin,
+        style: NoiseStyle::Fbm,
+        seed: 42,
+    }
+}
+
+#[test]
+fn fbm_matches_golden_samples() {
+    let field = generate_fbm_field(32, &golden_params());
+    let golden = [
+        (0, 0, 0.13061172),
+        (5, 17, 0.1275205),
+        (31, 31, 0.11789225),
+        (16, 8, 0.20214468),
+    ];
+    for (x, y, expected) in golden {
+        let value = field.get(x, y);
+        assert!((value - expected).abs() < EPSILON, "sample ({x}, {y}) is {value}, expected {expected}");
+    }
+    let sum: f32 = field.as_slice().iter().sum();
+    assert!((sum - 21.039_723).abs() < 1e-3, "sample sum is {sum}");
+}
+
+#[test]
+fn fbm_parameters_change_the_output() {
+    let golden = generate_fbm_field(32, &golden_params());
+    let variations = [
+        FbmParams { octaves: 7, ..golden_params() },
+        FbmParams { persistence: 0.5, ..golden_params() },
+        FbmParams { lacunarity: 2.0, ..golden_params() },
+        FbmParams { frequency: 3.0, ..golden_params() },
+        FbmParams { seed: 43, ..golden_params() },
+    ];
+    for params in variations {
+        assert_ne!(generate_fbm_field(32, &params), golden, "{params:?}");
+    }
+}
+
+#[test]
+#[should_panic(expected = "invalid octaves")]
+fn zero_octaves_are_rejected() {
+    generate_fbm_field(8, &FbmParams { octaves: 0, ..golden_params() });
+}
+
+#[test]
+fn ridged_is_non_negative_and_skewed_high() {
+    let params = FbmParams { style: NoiseStyle::Ridged, ..golden_params() };
+    let mut values = generate_fbm_field(128, &params).as_slice().to_vec();
+    values.sort_by(f32::total_cmp);
+    let (min, max) = (values[0], values[values.len() - 1]);
+    assert!(min >= 0.0 && max <= 1.0, "ridged values span {min}..{max}");
+
+    // Most samples sit near the bright ridge plateau, with a tail of dark valleys
+    let mean = values.iter().sum::<f32>() / values.len() as f32;
+    let median = values[values.len() / 2];
+    assert!(median > mean, "median {median} not above mean {mean}");
+    let above_mid = values.iter().filter(|&&v| v > (min + max) / 2.0).count();
+    assert!(above_mid * 2 > values.len(), "only {above_mid} of {} samples above mid-range", values.len());
+}
+
+#[test]
+fn single_octave_styles_shape_the_plain_noise() {
+    let single = |style| FbmParams { octaves: 1, style, ..golden_params() };
+    let fbm = generate_fbm_field(32, &single(NoiseStyle::Fbm));
+    let ridged = generate_fbm_field(32, &single(NoiseStyle::Ridged));
+    let billow = generate_fbm_field(32, &single(NoiseStyle::Billow));
+    for ((&n, &r), &b) in fbm.as_slice().iter().zip(ridged.as_slice()).zip(billow.as_slice()) {
+        // Ridges are brightest where the plain noise crosses zero
+        assert!((r - (1.0 - n.abs()).powi(2)).abs() < EPSILON);
+        assert!((b - n.abs()).abs() < EPSILON);
+    }
+}
+
+#[test]
+fn every_noise_kind_covers_the_output_range() {
+    let kinds = [NoiseKind::Perlin, NoiseKind::Simplex, NoiseKind::OpenSimplex];
+    let fields: Vec<_> = kinds
+        .iter()
+        .map(|&kind| normalize_field(&generate_perlin_field(128, &FbmParams { kind, ..golden_params() })))
+        .collect();
+    for (kind, field) in kinds.iter().zip(&fields) {
+        let levels: HashSet<u8> = field.to_luma_image().pixels().map(|p| p[0]).collect();
+        assert!(levels.len() > 200, "{kind} only uses {} gray levels", levels.len());
+    }
+    assert_ne!(fields[0], fields[1]);
+    assert_ne!(fields[1], fields[2]);
+}
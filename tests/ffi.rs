@@ -0,0 +1,93 @@
+//! The C interface must hand out the same samples as the Rust generators,
+//! keep the struct layout of `include/cells.h` and report bad calls through
+//! its status codes without allocating.
+
+use std::ffi::CStr;
+use std::mem::{offset_of, size_of};
+use std::ptr;
+
+use cells::ffi::*;
+use cells::filter::{directional_blur_field, gaussian_blur};
+use cells::noise::{generate_perlin_field, FbmParams};
+use cells::voronoi::{generate_voronoi_field, Feature, VoronoiParams};
+
+/// Take the samples out of a buffer returned through the C interface
+unsafe fn take(buffer: *mut f32, len: usize) -> Vec<f32> {
+    let samples = std::slice::from_raw_parts(buffer, len).to_vec();
+    cells_free(buffer, len);
+    samples
+}
+
+#[test]
+fn generators_match_the_rust_api() {
+    let mut params = cells_voronoi_default_params();
+    params.size = 48;
+    params.num_points = 20;
+    params.seed = 3;
+    params.feature = CELLS_FEATURE_F2_MINUS_F1;
+    let (mut buffer, mut len) = (ptr::null_mut(), 0);
+    assert_eq!(unsafe { cells_voronoi(&params, &mut buffer, &mut len) }, CellsStatus::Ok);
+    let expected = generate_voronoi_field(&VoronoiParams {
+        size: 48,
+        num_points: 20,
+        seed: 3,
+        feature: Feature::F2MinusF1,
+        ..Default::default()
+    });
+    assert_eq!(unsafe { take(buffer, len) }, expected.as_slice());
+
+    let mut params = cells_fbm_default_params();
+    params.size = 40;
+    params.octaves = 3;
+    params.seed = 11;
+    assert_eq!(unsafe { cells_fbm(&params, &mut buffer, &mut len) }, CellsStatus::Ok);
+    let expected = generate_perlin_field(40, &FbmParams { octaves: 3, seed: 11, ..Default::default() });
+    assert_eq!(unsafe { take(buffer, len) }, expected.as_slice());
+}
+
+#[test]
+fn filters_match_the_rust_api() {
+    let field = generate_perlin_field(24, &FbmParams { octaves: 2, ..Default::default() });
+    let direction = generate_voronoi_field(&VoronoiParams { size: 24, ..Default::default() });
+    let (mut buffer, mut len) = (ptr::null_mut(), 0);
+    let samples = field.as_slice().as_ptr();
+    assert_eq!(unsafe { cells_gaussian_blur(samples, 24, 24, 1.5, &mut buffer, &mut len) }, CellsStatus::Ok);
+    assert_eq!(unsafe { take(buffer, len) }, gaussian_blur(&field, 1.5).as_slice());
+    let directions = direction.as_slice().as_ptr();
+    let status = unsafe { cells_directional_blur(samples, directions, 24, 24, 5, &mut buffer, &mut len) };
+    assert_eq!(status, CellsStatus::Ok);
+    assert_eq!(unsafe { take(buffer, len) }, directional_blur_field(&field, &direction, 5).as_slice());
+    let status = unsafe { cells_gaussian_blur(samples, 24, 24, f32::NAN, &mut buffer, &mut len) };
+    assert_eq!(status, CellsStatus::InvalidParameter);
+}
+
+#[test]
+fn bad_calls_report_a_status() {
+    let (mut buffer, mut len) = (ptr::null_mut(), 0);
+    let mut params = cells_voronoi_default_params();
+    assert_eq!(unsafe { cells_voronoi(ptr::null(), &mut buffer, &mut len) }, CellsStatus::NullPointer);
+    assert_eq!(unsafe { cells_voronoi(&params, ptr::null_mut(), &mut len) }, CellsStatus::NullPointer);
+    params.feature = 7;
+    assert_eq!(unsafe { cells_voronoi(&params, &mut buffer, &mut len) }, CellsStatus::InvalidParameter);
+    let mut params = cells_fbm_default_params();
+    params.octaves = 0;
+    assert_eq!(unsafe { cells_fbm(&params, &mut buffer, &mut len) }, CellsStatus::InvalidParameter);
+    assert!(buffer.is_null() && len == 0);
+    unsafe { cells_free(ptr::null_mut(), 0) };
+
+    let message = unsafe { CStr::from_ptr(cells_status_message(CellsStatus::InvalidParameter)) };
+    assert!(message.to_str().unwrap().contains("parameter"));
+}
+
+#[test]
+fn layouts_match_the_header() {
+    assert_eq!(size_of::<CellsStatus>(), 4);
+    assert_eq!(size_of::<CellsVoronoiParams>(), 32);
+    assert_eq!(
+        [offset_of!(CellsVoronoiParams, seed), offset_of!(CellsVoronoiParams, minkowski_p)],
+        [8, 24]
+    );
+    assert_eq!(offset_of!(CellsVoronoiParams, invert), 28);
+    assert_eq!(size_of::<CellsFbmParams>(), 40);
+    assert_eq!(offset_of!(CellsFbmParams, seed), 32);
+}
@@ -0,0 +1,79 @@
+//! Fractal Voronoi diagrams must average their octaves, tile, and repeat
+//! exactly for the same seed.
+
+use cells::band::render_all;
+use cells::sampling::PointDistribution;
+use cells::tiling::{assert_tileable, interior_discontinuity};
+use cells::voronoi::{
+    generate_voronoi_field, generate_voronoi_frame, Feature, VoronoiBands, VoronoiBuilder, VoronoiParams,
+};
+use cells::FieldBuffer;
+
+fn params() -> VoronoiParams {
+    VoronoiParams { size: 64, num_points: 5, seed: 17, octaves: 3, ..Default::default() }
+}
+
+#[test]
+fn octaves_tile_and_repeat_for_the_same_seed() {
+    for feature in [Feature::F1, Feature::F2MinusF1, Feature::CellValue] {
+        let fractal = VoronoiParams { feature, ..params() };
+        let field = generate_voronoi_field(&fractal);
+        assert_eq!(field, generate_voronoi_field(&fractal), "{feature}");
+        assert_ne!(field, generate_voronoi_field(&VoronoiParams { seed: 18, ..fractal.clone() }), "{feature}");
+        assert_tileable(&field, interior_discontinuity(&field));
+        let (min, max) = field.min_max().unwrap();
+        assert!((0.0..=1.0).contains(&min) && (0.0..=1.0).contains(&max), "{feature}: {min} to {max}");
+    }
+
+    // Lattices double their rows and columns every octave
+    let lattice = PointDistribution::JitteredGrid { cols: 3, rows: 3, jitter: 0.6 };
+    let field = generate_voronoi_field(&VoronoiParams { distribution: lattice, octaves: 4, ..params() });
+    assert_tileable(&field, interior_discontinuity(&field));
+}
+
+#[test]
+fn persistence_weighs_the_finer_octaves() {
+    // Light fine octaves leave the diagram closer to its first octave
+    let smooth = generate_voronoi_field(&VoronoiParams { persistence: 0.1, ..params() });
+    let rough = generate_voronoi_field(&VoronoiParams { persistence: 1.0, ..params() });
+    let base = generate_voronoi_field(&VoronoiParams { octaves: 1, ..params() });
+    let distance = |a: &FieldBuffer, b: &FieldBuffer| {
+        a.as_slice().iter().zip(b.as_slice()).map(|(a, b)| (a - b).abs()).sum::<f32>()
+    };
+    assert!(distance(&smooth, &base) < distance(&rough, &base));
+
+    // Frames move the points of every octave and start at the field
+    assert_eq!(generate_voronoi_frame(&params(), 0.0, 0.03), generate_voronoi_field(&params()));
+    assert_ne!(generate_voronoi_frame(&params(), 0.25, 0.03), generate_voronoi_field(&params()));
+}
+
+#[test]
+fn octaves_are_validated() {
+    for (octaves, persistence, name) in [(0, 0.5, "octaves"), (9, 0.5, "octaves"), (3, 0.0, "persistence")] {
+        let err = VoronoiBuilder::new().octaves(octaves, persistence).build().unwrap_err();
+        assert!(err.to_string().starts_with(&format!("invalid {name}")), "{err}");
+    }
+    let err = VoronoiBuilder::new().octaves(8, 1e20).build().unwrap_err();
+    assert!(err.to_string().contains("finite"), "{err}");
+
+    // Bands render one octave
+    assert!(VoronoiBands::new(&params()).is_err());
+    let single = VoronoiParams { octaves: 1, ..params() };
+    assert_eq!(render_all(&VoronoiBands::new(&single).unwrap(), 3), generate_voronoi_field(&single));
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn the_binary_sums_octaves() {
+    let run = |octaves: &str| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_cells"))
+            .args(["--size", "32", "--points", "4", "--voronoi-octaves", octaves, "--voronoi-persistence", "0.6"])
+            .arg("--out-dir")
+            .arg(std::env::temp_dir().join("cells_test_fractal"))
+            .output()
+            .unwrap()
+    };
+    assert!(run("3").status.success());
+    assert!(!run("0").status.success());
+    assert!(!run("9").status.success());
+}
@@ -0,0 +1,66 @@
+//! Gabor noise is a sum of wrapped kernels, so it must tile, depend on nothing
+//! but its parameters and seed, and run its waves along the orientation.
+
+use cells::gabor::{generate_gabor, generate_gabor_along, GaborOrientation, GaborParams};
+use cells::tiling::{assert_tileable, interior_discontinuity};
+use cells::FieldBuffer;
+
+fn params(orientation: GaborOrientation) -> GaborParams {
+    GaborParams {
+        size: 64,
+        kernels: 400,
+        orientation,
+        ..Default::default()
+    }
+}
+
+/// The mean absolute step between neighbors across and down
+fn steps(field: &FieldBuffer) -> (f32, f32) {
+    let (width, height) = field.dimensions();
+    let (mut across, mut down) = (0.0, 0.0);
+    for y in 0..height - 1 {
+        for x in 0..width - 1 {
+            across += (field.get(x + 1, y) - field.get(x, y)).abs();
+            down += (field.get(x, y + 1) - field.get(x, y)).abs();
+        }
+    }
+    (across, down)
+}
+
+#[test]
+fn gabor_noise_is_deterministic_per_seed() {
+    let noise = generate_gabor(&params(GaborOrientation::Isotropic), 5);
+    assert_eq!(noise, generate_gabor(&params(GaborOrientation::Isotropic), 5));
+    assert_ne!(noise, generate_gabor(&params(GaborOrientation::Isotropic), 6));
+}
+
+#[test]
+fn gabor_noise_tiles() {
+    let orientations = [GaborOrientation::Isotropic, GaborOrientation::Fixed(30.0), GaborOrientation::Fixed(-90.0)];
+    for orientation in orientations {
+        // The largest radii fall back to a single grid cell
+        for radius in [0.03, 0.1, 0.3, 0.5] {
+            let noise = generate_gabor(&GaborParams { radius, spread: 10.0, ..params(orientation) }, 2);
+            assert_tileable(&noise, interior_discontinuity(&noise));
+        }
+    }
+    let swirl = FieldBuffer::from_par_fn(32, 32, |x, y| (x + y) as f32 / 64.0);
+    let along = generate_gabor_along(&params(GaborOrientation::Isotropic), 2, &swirl);
+    assert_tileable(&along, interior_discontinuity(&along));
+}
+
+#[test]
+fn waves_follow_the_orientation() {
+    // Waves travelling along x vary across and streak down, and vice versa
+    let (across, down) = steps(&generate_gabor(&params(GaborOrientation::Fixed(0.0)), 1));
+    assert!(across > 2.0 * down, "{across} across, {down} down");
+    let (across, down) = steps(&generate_gabor(&params(GaborOrientation::Fixed(90.0)), 1));
+    assert!(down > 2.0 * across, "{across} across, {down} down");
+
+    // A constant orientation field of 0.5 turns every kernel by 90°
+    let half = FieldBuffer::from_vec(4, 4, vec![0.5; 16]);
+    assert_eq!(
+        generate_gabor_along(&params(GaborOrientation::Isotropic), 1, &half),
+        generate_gabor(&params(GaborOrientation::Fixed(90.0)), 1)
+    );
+}
@@ -0,0 +1,149 @@
+//! Golden-image regression tests: small renders of the generators and of the
+//! default pipeline, compared against the reference PNGs in `tests/golden`.
+//!
+//! A sample may be off by `TOLERANCE` levels, and up to `MAX_DIFFERING`
+//! pixels may be off by more, so float differences between platforms pass
+//! while changed output fails. A failed comparison writes a heatmap of the
+//! differences to `golden-diff` in the test directory of the target folder.
+//!
+//! After an intended change of the output, write the references again with
+//! `CELLS_UPDATE_GOLDEN=1 cargo test --test golden`.
+
+#![cfg(feature = "file-io")]
+
+use std::path::{Path, PathBuf};
+
+use image::{GrayImage, Rgb, RgbImage};
+
+use cells::cracks::{generate_cracks, CrackParams};
+use cells::gabor::{generate_gabor, GaborOrientation, GaborParams};
+use cells::grain::{generate_marble, generate_wood, GrainParams};
+use cells::noise::{generate_blue_noise, generate_fbm_field, generate_perlin_field, FbmParams, NoiseKind, NoiseStyle};
+use cells::pattern::{generate_bricks, generate_truchet, BrickParams, TruchetStyle};
+use cells::reaction::generate_reaction_diffusion;
+use cells::sampling::PointDistribution;
+use cells::scratches::{generate_scratches, ScratchParams};
+use cells::speckle::{generate_speckle, SpeckleParams};
+use cells::voronoi::{generate_voronoi_field, Feature, VoronoiParams};
+use cells::{DistanceMetric, FieldBuffer};
+
+/// The width and height of every render
+const SIZE: u32 = 128;
+
+/// The largest difference of a sample from the reference that still matches, in 8-bit levels
+const TOLERANCE: u8 = 2;
+
+/// The most pixels that may differ by more than `TOLERANCE`, half a percent of a render
+const MAX_DIFFERING: usize = (SIZE * SIZE / 200) as usize;
+
+/// The environment variable that writes the references instead of comparing
+const UPDATE: &str = "CELLS_UPDATE_GOLDEN";
+
+/// Compare an image against its reference `tests/golden/<name>.png`, or write
+/// the reference when updating
+fn check_image(name: &str, actual: &GrayImage) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{name}.png"));
+    if std::env::var_os(UPDATE).is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        actual.save(&path).unwrap();
+        return;
+    }
+    let expected = image::open(&path)
+        .unwrap_or_else(|err| panic!("cannot read {}: {err}; write it with {UPDATE}=1", path.display()))
+        .to_luma8();
+    assert_eq!(actual.dimensions(), expected.dimensions(), "{name} changed size");
+    let differing = actual.pixels().zip(expected.pixels()).filter(|(a, e)| a[0].abs_diff(e[0]) > TOLERANCE).count();
+    if differing > MAX_DIFFERING {
+        let heatmap = write_heatmap(name, actual, &expected);
+        panic!(
+            "{name}: {differing} pixels differ by more than {TOLERANCE} levels, at most {MAX_DIFFERING} may; \
+             see {}, or write the references again with {UPDATE}=1 if the change is intended",
+            heatmap.display()
+        );
+    }
+}
+
+/// Compare a field quantized to 8 bits against its reference
+fn check(name: &str, field: &FieldBuffer) {
+    check_image(name, &field.to_luma_image());
+}
+
+/// Write a heatmap of the differences of two images: the reference dimmed
+/// where they match within `TOLERANCE`, red growing with the difference where
+/// they do not
+fn write_heatmap(name: &str, actual: &GrayImage, expected: &GrayImage) -> PathBuf {
+    let heatmap = RgbImage::from_fn(actual.width(), actual.height(), |x, y| {
+        let (a, e) = (actual.get_pixel(x, y)[0], expected.get_pixel(x, y)[0]);
+        match a.abs_diff(e) {
+            difference if difference > TOLERANCE => Rgb([128 + difference / 2, 0, 0]),
+            _ => Rgb([e / 4; 3]),
+        }
+    });
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden-diff");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(format!("{name}.png"));
+    heatmap.save(&path).unwrap();
+    path
+}
+
+#[test]
+fn voronoi_features_match_their_references() {
+    let params = VoronoiParams { size: SIZE, num_points: 30, seed: 5, ..Default::default() };
+    check("voronoi_f1", &generate_voronoi_field(&params));
+    let f2_minus_f1 = VoronoiParams { feature: Feature::F2MinusF1, ..params.clone() };
+    check("voronoi_f2_minus_f1", &generate_voronoi_field(&f2_minus_f1));
+    let fractal = VoronoiParams { num_points: 6, octaves: 4, persistence: 0.5, ..params.clone() };
+    check("voronoi_fractal", &generate_voronoi_field(&fractal));
+    let manhattan = VoronoiParams {
+        feature: Feature::F2,
+        metric: DistanceMetric::Manhattan,
+        distribution: PointDistribution::JitteredGrid { cols: 6, rows: 6, jitter: 0.7 },
+        ..params
+    };
+    check("voronoi_f2_manhattan_grid", &generate_voronoi_field(&manhattan));
+}
+
+#[test]
+fn noise_matches_its_references() {
+    let params = FbmParams { octaves: 5, frequency: 3.0, seed: 5, ..Default::default() };
+    check("perlin", &generate_perlin_field(SIZE, &params));
+    let ridged = FbmParams { kind: NoiseKind::Simplex, style: NoiseStyle::Ridged, ..params };
+    check("simplex_ridged", &generate_fbm_field(SIZE, &ridged));
+    check("blue_noise", &generate_blue_noise(64, 5));
+    let orientation = GaborOrientation::Fixed(30.0);
+    check("gabor", &generate_gabor(&GaborParams { size: SIZE, kernels: 400, orientation, ..Default::default() }, 5));
+}
+
+#[test]
+fn patterns_match_their_references() {
+    check("bricks", &generate_bricks(&BrickParams { size: SIZE, seed: 5, ..Default::default() }));
+    check("truchet", &generate_truchet(SIZE, 8, 0.2, TruchetStyle::Arcs, 5));
+    let grain = GrainParams { size: SIZE, seed: 5, ..Default::default() };
+    check("wood", &generate_wood(&grain));
+    check("marble", &generate_marble(&grain));
+}
+
+#[test]
+fn scattered_masks_match_their_references() {
+    check("cracks", &generate_cracks(&CrackParams { size: SIZE, num_points: 20, seed: 5, ..Default::default() }));
+    check("speckle", &generate_speckle(&SpeckleParams { size: SIZE, count: 200, seed: 5, ..Default::default() }));
+    check("scratches", &generate_scratches(&ScratchParams { size: SIZE, count: 60, ..Default::default() }, 5));
+    check("reaction_diffusion", &generate_reaction_diffusion(SIZE, 0.055, 0.062, 500, 5));
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn the_default_pipeline_matches_its_references() {
+    let out_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden-pipeline");
+    let status = std::process::Command::new(env!("CARGO_BIN_EXE_cells"))
+        .args(["--size", &SIZE.to_string(), "--points", "30", "--seed", "5", "--out-dir"])
+        .arg(&out_dir)
+        .stdout(std::process::Stdio::null())
+        .status()
+        .unwrap();
+    assert!(status.success(), "the default pipeline failed with {status}");
+    for name in ["voronoi_texture", "perlin_noise_texture", "blurred_voronoi_texture"] {
+        let texture = image::open(out_dir.join(format!("{name}.png"))).unwrap().to_luma8();
+        check_image(&format!("pipeline_{name}"), &texture);
+    }
+}
@@ -0,0 +1,106 @@
+//! The GPU backend must match the CPU within one 8-bit level, on whatever
+//! adapter the tests find; without one the GPU tests pass vacuously. The
+//! command line must fall back to the CPU with `--backend gpu` in builds
+//! without the `gpu` feature.
+
+#[cfg(feature = "gpu")]
+use cells::filter::{
+    directional_blur_field_with, directional_blur_field_with_length, BlurKernel, BlurParams, BlurSampling,
+    DirectionMapping,
+};
+#[cfg(feature = "gpu")]
+use cells::gpu::Gpu;
+#[cfg(feature = "gpu")]
+use cells::noise::{generate_perlin_field, FbmParams};
+#[cfg(feature = "gpu")]
+use cells::sampling::PointDistribution;
+#[cfg(feature = "gpu")]
+use cells::voronoi::{generate_voronoi_field, generate_voronoi_frame, Feature, VoronoiParams};
+#[cfg(feature = "gpu")]
+use cells::{DistanceMetric, FieldBuffer, Weighting};
+
+/// Check that two fields differ by at most one 8-bit level anywhere
+#[cfg(feature = "gpu")]
+fn assert_close(cpu: &FieldBuffer, gpu: &FieldBuffer, what: &str) {
+    assert_eq!(cpu.dimensions(), gpu.dimensions(), "{what}");
+    let largest = cpu.as_slice().iter().zip(gpu.as_slice()).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max);
+    assert!(largest <= 1.0 / 255.0, "{what}: the GPU is off by {largest}");
+}
+
+#[test]
+#[cfg(feature = "gpu")]
+fn voronoi_diagrams_match_the_cpu() {
+    let Some(gpu) = Gpu::new() else { return };
+    let base = VoronoiParams { size: 96, num_points: 40, seed: 9, ..Default::default() };
+    let variants = [
+        base.clone(),
+        VoronoiParams { feature: Feature::F2, ..base.clone() },
+        VoronoiParams { feature: Feature::F2MinusF1, invert: true, ..base.clone() },
+        VoronoiParams { metric: DistanceMetric::Manhattan, ..base.clone() },
+        VoronoiParams { metric: DistanceMetric::Chebyshev, feature: Feature::F2, ..base.clone() },
+        VoronoiParams { metric: DistanceMetric::Minkowski(3.0), ..base.clone() },
+        VoronoiParams { weighting: Weighting::Additive, weight_range: (0.0, 0.05), ..base.clone() },
+        VoronoiParams { weighting: Weighting::Multiplicative, weight_range: (0.5, 1.5), ..base.clone() },
+        VoronoiParams { cell_scale: (1.0, 3.0), feature: Feature::F2MinusF1, ..base.clone() },
+        VoronoiParams { warp_strength: 0.05, ..base.clone() },
+        VoronoiParams { feature: Feature::CellValue, value_smoothing: 0.02, ..base.clone() },
+        VoronoiParams { octaves: 3, ..base.clone() },
+        VoronoiParams { num_points: 1, feature: Feature::F2MinusF1, ..base.clone() },
+        VoronoiParams {
+            distribution: PointDistribution::JitteredGrid { cols: 6, rows: 6, jitter: 0.0 },
+            ..base.clone()
+        },
+    ];
+    for params in &variants {
+        assert_close(&generate_voronoi_field(params), &gpu.voronoi_field(params), &format!("{params:?}"));
+    }
+    assert_close(&generate_voronoi_frame(&base, 0.3, 0.02), &gpu.voronoi_frame(&base, 0.3, 0.02), "a frame");
+}
+
+#[test]
+#[cfg(feature = "gpu")]
+fn directional_blurs_match_the_cpu() {
+    let Some(gpu) = Gpu::new() else { return };
+    let field = generate_voronoi_field(&VoronoiParams { size: 80, num_points: 30, seed: 4, ..Default::default() });
+    let direction = generate_perlin_field(80, &FbmParams { seed: 2, ..Default::default() }).map(|v| (v + 1.0) / 2.0);
+    let length = generate_perlin_field(80, &FbmParams { seed: 8, ..Default::default() });
+    for sampling in [BlurSampling::Bilinear, BlurSampling::Nearest] {
+        for kernel in [BlurKernel::Box, BlurKernel::Triangle, BlurKernel::Gaussian { sigma: 2.0 }] {
+            for mapping in [DirectionMapping::Full360, DirectionMapping::Half180, DirectionMapping::SignedFlow] {
+                for radius in [0, 3, 9] {
+                    let params = BlurParams { radius, sampling, kernel, mapping };
+                    let what = format!("{params:?}");
+                    let cpu = directional_blur_field_with(&field, &direction, &params);
+                    assert_close(&cpu, &gpu.directional_blur(&field, &direction, None, &params), &what);
+                    let cpu = directional_blur_field_with_length(&field, &direction, &length, &params);
+                    assert_close(&cpu, &gpu.directional_blur(&field, &direction, Some(&length), &params), &what);
+                }
+            }
+        }
+    }
+}
+
+/// The pixels of a texture the command line writes with `backend`
+#[cfg(feature = "cli")]
+fn texture_with(backend: &str) -> Vec<u8> {
+    let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("gpu-backend-{backend}"));
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_cells"))
+        .args(["--size", "64", "--seed", "3", "--points", "20", "--backend", backend, "--out-dir"])
+        .arg(&dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    image::open(dir.join("blurred_voronoi_texture.png")).unwrap().into_luma8().into_raw()
+}
+
+#[test]
+#[cfg(feature = "cli")]
+fn every_backend_writes_the_cpu_texture() {
+    let cpu = texture_with("cpu");
+    assert_eq!(texture_with("grid"), cpu);
+    for backend in ["gpu", "auto"] {
+        let pixels = texture_with(backend);
+        let largest = pixels.iter().zip(&cpu).map(|(a, b)| a.abs_diff(*b)).max().unwrap();
+        assert!(largest <= 1, "--backend {backend} is off by {largest} levels");
+    }
+}
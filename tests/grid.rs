@@ -0,0 +1,83 @@
+//! The SIMD and the scalar kernel of the point grid must find the same
+//! points, down to the bits of their distances, and comparing reduced
+//! distances must find the points a brute-force scan does.
+
+use cells::grid::PointGrid;
+use cells::{toroidal_distance_with, DistanceMetric, Point};
+use rand::{Rng, SeedableRng};
+
+const METRICS: [DistanceMetric; 3] = [DistanceMetric::Euclidean, DistanceMetric::Manhattan, DistanceMetric::Chebyshev];
+
+fn bits<const K: usize>(result: Option<[(usize, f32); K]>) -> Option<[(usize, u32); K]> {
+    result.map(|best| best.map(|(index, distance)| (index, distance.to_bits())))
+}
+
+#[test]
+fn simd_and_scalar_kernels_agree() {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(82);
+    // Few points end in full scans, many in ring searches over long rows
+    for num_points in [1, 3, 9, 40, 1000] {
+        let points: Vec<Point> = (0..num_points).map(|_| Point { x: rng.gen(), y: rng.gen() }).collect();
+        for metric in METRICS {
+            let simd = PointGrid::with_metric(&points, metric);
+            #[cfg(target_arch = "x86_64")]
+            assert_eq!(simd.uses_simd(), std::is_x86_feature_detected!("avx"));
+            let scalar = simd.clone().with_simd(false);
+            for _ in 0..2000 {
+                let p = Point { x: rng.gen(), y: rng.gen() };
+                let nearest = simd.nearest(p);
+                assert_eq!(bits(nearest.map(|n| [n])), bits(scalar.nearest(p).map(|n| [n])));
+                assert_eq!(bits(simd.nearest_two(p)), bits(scalar.nearest_two(p)));
+
+                let brute_force = points
+                    .iter()
+                    .map(|&q| toroidal_distance_with(p, q, metric))
+                    .fold(f32::INFINITY, f32::min);
+                assert_eq!(nearest.unwrap().1.to_bits(), brute_force.to_bits());
+            }
+        }
+    }
+}
+
+#[test]
+fn ties_go_to_the_lowest_index_in_both_kernels() {
+    // Every point is at the same distance from the center
+    let points: Vec<Point> = (0..16)
+        .map(|i| {
+            let (dx, dy) = [(0.25, 0.0), (-0.25, 0.0), (0.0, 0.25), (0.0, -0.25)][i % 4];
+            Point { x: 0.5 + dx, y: 0.5 + dy }
+        })
+        .collect();
+    let center = Point { x: 0.5, y: 0.5 };
+    for metric in METRICS {
+        let simd = PointGrid::with_metric(&points, metric);
+        let scalar = simd.clone().with_simd(false);
+        assert_eq!(simd.nearest_two(center).map(|best| best.map(|(index, _)| index)), Some([0, 1]));
+        assert_eq!(bits(simd.nearest_two(center)), bits(scalar.nearest_two(center)));
+    }
+}
+
+#[test]
+fn reduced_distances_select_the_brute_force_winner() {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(83);
+    for metric in [DistanceMetric::Euclidean, DistanceMetric::Manhattan, DistanceMetric::Minkowski(3.0)] {
+        for num_points in [2, 30, 500] {
+            let points: Vec<Point> = (0..num_points).map(|_| Point { x: rng.gen(), y: rng.gen() }).collect();
+            let grid = PointGrid::with_metric(&points, metric);
+            for _ in 0..2000 {
+                let p = Point { x: rng.gen(), y: rng.gen() };
+                // The lowest index among the smallest reduced distances
+                let reduced: Vec<f32> = points.iter().map(|&q| metric.toroidal_reduced(p, q)).collect();
+                let winner = (0..num_points).min_by(|&a, &b| reduced[a].total_cmp(&reduced[b])).unwrap();
+                let distance = points
+                    .iter()
+                    .map(|&q| toroidal_distance_with(p, q, metric))
+                    .fold(f32::INFINITY, f32::min);
+                let (index, found) = grid.nearest(p).unwrap();
+                assert_eq!(index, winner);
+                assert_eq!(found.to_bits(), distance.to_bits());
+                assert_eq!(toroidal_distance_with(p, points[index], metric), distance);
+            }
+        }
+    }
+}
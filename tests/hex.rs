@@ -0,0 +1,54 @@
+//! Hexagonal lattices must wrap around the torus, and without jitter every
+//! cell must be the same hexagon.
+
+use cells::sampling::{check_hex_lattice, PointDistribution};
+use cells::tiling::{assert_tileable, interior_discontinuity};
+use cells::voronoi::{generate_voronoi_cell_ids, generate_voronoi_field, Feature, VoronoiParams};
+
+fn params(cols: u32, rows: u32, jitter: f32) -> VoronoiParams {
+    VoronoiParams {
+        size: 336,
+        distribution: PointDistribution::HexGrid { cols, rows, jitter },
+        seed: 3,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn regular_hex_cells_have_equal_areas() {
+    // Sizes with whole pixels per column and row, so all cells are sampled alike
+    for (cols, rows, size) in [(12, 14, 336), (6, 6, 240), (5, 6, 240)] {
+        let ids = generate_voronoi_cell_ids(&VoronoiParams { size, ..params(cols, rows, 0.0) });
+        let mut areas = vec![0u32; (cols * rows) as usize];
+        for id in ids.pixels() {
+            areas[id.0[0] as usize] += 1;
+        }
+        let expected = (size * size) as f32 / areas.len() as f32;
+        for (id, &area) in areas.iter().enumerate() {
+            let deviation = (area as f32 - expected).abs() / expected;
+            assert!(deviation < 0.03, "{cols}x{rows} cell {id} covers {area} pixels instead of {expected}");
+        }
+    }
+}
+
+#[test]
+fn hex_cells_tile() {
+    for jitter in [0.0, 0.1, 1.0] {
+        for feature in [Feature::F1, Feature::F2MinusF1] {
+            let hex = generate_voronoi_field(&VoronoiParams { feature, ..params(12, 14, jitter) });
+            assert_tileable(&hex, interior_discontinuity(&hex));
+        }
+    }
+}
+
+#[test]
+fn invalid_hex_lattices_are_rejected() {
+    assert!(check_hex_lattice(12, 13).is_err());
+    assert!(check_hex_lattice(12, 24).is_err());
+    assert!(check_hex_lattice(1, 2).is_err());
+    assert!("hex:12x13:0.1".parse::<PointDistribution>().is_err());
+    assert_eq!(
+        "hex:12x14:0.1".parse::<PointDistribution>(),
+        Ok(PointDistribution::HexGrid { cols: 12, rows: 14, jitter: 0.1 })
+    );
+}
@@ -0,0 +1,102 @@
+//! Raw float files must round-trip every sample bit for bit, quantized
+//! formats every sample to within one level, and the file format must follow
+//! the extension unless given explicitly.
+
+#![cfg(feature = "file-io")]
+
+use std::fs;
+
+use cells::io::{load_raw, save_field, save_raw, FileFormat};
+use cells::FieldBuffer;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("cells_test_io_{name}"))
+}
+
+#[test]
+fn raw_files_round_trip() {
+    let mut rng = StdRng::seed_from_u64(3);
+    for (width, height) in [(1, 1), (17, 5), (64, 64)] {
+        let values = (0..width * height).map(|_| rng.gen_range(-1e4..1e4f32) * rng.gen::<f32>().powi(8)).collect();
+        let field = FieldBuffer::from_vec(width, height, values);
+        let path = temp_path(&format!("{width}x{height}.raw"));
+        save_field(&field, &path, None, 8).unwrap();
+        let loaded = load_raw(&path).unwrap();
+        assert_eq!(loaded.dimensions(), (width, height));
+        let bits = |field: &FieldBuffer| field.as_slice().iter().map(|v| v.to_bits()).collect::<Vec<_>>();
+        assert_eq!(bits(&loaded), bits(&field));
+
+        let sidecar = fs::read_to_string(path.with_extension("json")).unwrap();
+        let (min, max) = field.min_max().unwrap();
+        assert!(sidecar.contains(&format!(r#""width": {width}, "height": {height}, "min": {min}, "max": {max}"#)));
+    }
+}
+
+#[test]
+fn sidecars_record_escaped_parameters() {
+    let field = FieldBuffer::from_vec(2, 1, vec![0.5, f32::INFINITY]);
+    let path = temp_path("parameters.raw");
+    save_raw(&field, &path, &["--label".to_string(), "a \"quoted\" \\ name".to_string()]).unwrap();
+    let sidecar = fs::read_to_string(path.with_extension("json")).unwrap();
+    assert!(sidecar.contains(r#""min": 0.5, "max": null"#), "{sidecar}");
+    assert!(sidecar.contains(r#""parameters": ["--label", "a \"quoted\" \\ name"]"#), "{sidecar}");
+    assert_eq!(load_raw(&path).unwrap().as_slice()[1], f32::INFINITY);
+}
+
+#[test]
+fn truncated_raw_files_are_rejected() {
+    let path = temp_path("truncated.raw");
+    save_raw(&FieldBuffer::new(4, 4), &path, &[]).unwrap();
+    fs::write(&path, [0u8; 60]).unwrap();
+    assert!(load_raw(&path).is_err());
+}
+
+#[test]
+fn formats_follow_the_extension_unless_given() {
+    let field = FieldBuffer::from_par_fn(8, 8, |x, y| (x + y) as f32 / 14.0);
+    let png = temp_path("format.png");
+    save_field(&field, &png, None, 8).unwrap();
+    assert_eq!(image::open(&png).unwrap().to_luma8().into_raw(), field.to_luma_image().into_raw());
+
+    let forced = temp_path("format.dat");
+    save_field(&field, &forced, Some(FileFormat::Raw), 8).unwrap();
+    assert_eq!(load_raw(&forced).unwrap(), field);
+
+    let hdr = temp_path("format.hdr");
+    save_field(&field, &hdr, None, 8).unwrap();
+    let loaded = image::open(&hdr).unwrap().to_rgb32f();
+    for (pixel, value) in loaded.pixels().zip(field.as_slice()) {
+        assert!((pixel[0] - value).abs() <= value / 128.0 + 1e-6);
+    }
+}
+
+#[test]
+fn quantized_formats_round_trip() {
+    let field = FieldBuffer::from_par_fn(13, 7, |x, y| (x * 7 + y) as f32 / 96.0);
+    for (extension, bit_depth) in [("png", 8), ("png", 16), ("tga", 8), ("tiff", 8), ("tif", 16), ("bmp", 8)] {
+        let path = temp_path(&format!("quantized_{bit_depth}.{extension}"));
+        save_field(&field, &path, None, bit_depth).unwrap();
+        let loaded = image::open(&path).unwrap();
+        assert_eq!((loaded.width(), loaded.height()), field.dimensions(), "{extension}");
+        let levels = ((1u32 << bit_depth) - 1) as f32;
+        let pixels = loaded.to_luma32f();
+        for (pixel, value) in pixels.pixels().zip(field.as_slice()) {
+            assert!((pixel[0] - value).abs() <= 1.0 / levels, "{extension} {bit_depth}-bit: {} for {value}", pixel[0]);
+        }
+    }
+}
+
+#[test]
+fn unsupported_combinations_are_rejected() {
+    let field = FieldBuffer::new(4, 4);
+    for extension in ["tga", "bmp"] {
+        let err = save_field(&field, temp_path(&format!("deep.{extension}")), None, 16).unwrap_err();
+        assert!(err.to_string().contains("16-bit"), "{err}");
+    }
+    assert!(save_field(&field, temp_path("unknown.jpg"), None, 8).is_err());
+    assert!(save_field(&field, temp_path("unknown"), None, 8).is_err());
+    assert_eq!("tga".parse(), Ok(FileFormat::Tga));
+    assert!("tif".parse::<FileFormat>().is_err());
+}
@@ -0,0 +1,74 @@
+//! A length map must scale the directional blur of every sample between none,
+//! which copies the input exactly, and the full radius.
+
+use cells::filter::{
+    directional_blur_field_with, directional_blur_field_with_length, BlurKernel, BlurParams, BlurSampling,
+    DirectionMapping,
+};
+use cells::voronoi::{generate_voronoi_field, VoronoiParams};
+use cells::FieldBuffer;
+
+/// A Voronoi field of 48x48 samples
+fn voronoi() -> FieldBuffer {
+    generate_voronoi_field(&VoronoiParams { size: 48, num_points: 12, seed: 9, ..Default::default() })
+}
+
+#[test]
+fn a_zero_length_map_is_the_identity() {
+    let field = voronoi();
+    let zero = FieldBuffer::new(48, 48);
+    for mapping in [DirectionMapping::Full360, DirectionMapping::Half180, DirectionMapping::SignedFlow] {
+        for sampling in [BlurSampling::Bilinear, BlurSampling::Nearest] {
+            for kernel in [BlurKernel::Box, BlurKernel::Triangle, BlurKernel::Gaussian { sigma: 3.0 }] {
+                let params = BlurParams { radius: 7, sampling, kernel, mapping };
+                let blurred = directional_blur_field_with_length(&field, &field, &zero, &params);
+                assert_eq!(blurred, field, "{mapping}, {sampling}, {kernel}");
+            }
+        }
+    }
+}
+
+#[test]
+fn lengths_scale_the_blur_per_sample() {
+    let field = voronoi();
+    let params = BlurParams { radius: 6, ..Default::default() };
+    let full = directional_blur_field_with(&field, &field, &params);
+    let ones = FieldBuffer::from_par_fn(48, 48, |_, _| 1.0);
+    assert_eq!(directional_blur_field_with_length(&field, &field, &ones, &params), full);
+    // Values outside 0-1 are clamped
+    let beyond = FieldBuffer::from_par_fn(48, 48, |x, _| if x < 24 { -2.0 } else { 3.0 });
+    let clamped = directional_blur_field_with_length(&field, &field, &beyond, &params);
+
+    // The left half keeps the input and the right half is blurred over the full radius
+    let half = FieldBuffer::from_par_fn(48, 48, |x, _| if x < 24 { 0.0 } else { 1.0 });
+    let blurred = directional_blur_field_with_length(&field, &field, &half, &params);
+    assert_eq!(blurred, clamped);
+    for y in 0..48 {
+        for x in 0..48 {
+            let expected = if x < 24 { field.get(x, y) } else { full.get(x, y) };
+            assert_eq!(blurred.get(x, y), expected, "({x}, {y})");
+        }
+    }
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn the_binary_reads_a_length_map() {
+    let dir = std::env::temp_dir();
+    let map = dir.join("cells_test_length_map.png");
+    FieldBuffer::from_par_fn(32, 32, |x, _| x as f32 / 31.0).to_luma_image().save(&map).unwrap();
+    let run = |size: &str| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_cells"))
+            .args(["--size", size, "--points", "8", "--blur-length-map"])
+            .arg(&map)
+            .arg("--out-dir")
+            .arg(dir.join("cells_test_length_map_out"))
+            .output()
+            .unwrap()
+    };
+    assert!(run("32").status.success());
+    let output = run("48");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("is 32x32, not 48x48"), "{stderr}");
+}
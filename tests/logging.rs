@@ -0,0 +1,81 @@
+//! The library must log its stages and suspicious parameters through the `log`
+//! facade, and the binary must report them on stderr at the verbosity of
+//! `--verbose` and `--quiet`.
+
+use std::sync::Mutex;
+
+use cells::filter::{directional_blur_field_with, BlurParams};
+use cells::voronoi::{generate_voronoi_field, VoronoiParams};
+use cells::FieldBuffer;
+
+/// A logger keeping the records of this crate
+struct Recorder(Mutex<Vec<(log::Level, String)>>);
+
+impl log::Log for Recorder {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.target().starts_with("cells")
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            self.0.lock().unwrap().push((record.level(), record.args().to_string()));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static RECORDER: Recorder = Recorder(Mutex::new(Vec::new()));
+
+#[test]
+fn stages_and_wide_blurs_are_logged() {
+    log::set_logger(&RECORDER).unwrap();
+    log::set_max_level(log::LevelFilter::Debug);
+
+    let params = VoronoiParams { size: 32, num_points: 6, ..Default::default() };
+    let field = generate_voronoi_field(&params);
+    let direction = FieldBuffer::new(32, 32);
+    directional_blur_field_with(&field, &direction, &BlurParams { radius: 40, ..Default::default() });
+
+    let records = RECORDER.0.lock().unwrap();
+    let logged = |level, start: &str| records.iter().any(|(l, message)| *l == level && message.starts_with(start));
+    assert!(logged(log::Level::Debug, "voronoi: 32x32, 6 uniform points"), "{records:?}");
+    assert!(logged(log::Level::Debug, "voronoi: done in "), "{records:?}");
+    assert!(logged(log::Level::Warn, "directional blur radius 40 is more than half"), "{records:?}");
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn the_binary_reports_at_the_chosen_verbosity() {
+    let out_dir = std::env::temp_dir().join("cells_test_logging");
+    let stderr = |args: &[&str]| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_cells"))
+            .args(["--size", "32", "--points", "6", "--blur-steps", "1", "--blur-radius", "20", "--out-dir"])
+            .arg(&out_dir)
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{args:?} failed");
+        String::from_utf8(output.stderr).unwrap()
+    };
+    let warnings = stderr(&[]);
+    assert!(warnings.contains("warning: directional blur radius 20 is more than half"), "{warnings}");
+    let debug = stderr(&["-vv"]);
+    assert!(debug.contains("debug: voronoi: 32x32, 6 uniform points"), "{debug}");
+    assert!(debug.contains("debug: noise: done in "), "{debug}");
+    assert_eq!(stderr(&["-q"]), "");
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn animations_warn_about_aliasing_once() {
+    let out = std::env::temp_dir().join("cells_test_logging_alias.gif");
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_cells"))
+        .args(["animate", "--size", "32", "--frames", "4", "--format", "gif", "--out"])
+        .arg(&out)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr.matches("so it aliases").count(), 1, "{stderr}");
+}
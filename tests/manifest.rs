@@ -0,0 +1,39 @@
+//! Manifests must survive JSON and PNG text chunks unchanged, and embedding
+//! them must leave the image itself alone.
+
+#![cfg(feature = "file-io")]
+
+use cells::io::read_png_text;
+use cells::manifest::Manifest;
+use cells::noise::{generate_perlin_field, FbmParams};
+
+fn manifest() -> Manifest {
+    let mut manifest = Manifest::new(vec!["--seed".into(), "42".into(), "--out-dir".into(), "Tüten/砂 \"x\"".into()]);
+    manifest.parameters.insert("seed".into(), "42".into());
+    manifest.parameters.insert("metric".into(), "euclidean".into());
+    manifest.pipeline = Some("[[node]]\nname = \"cells\"\ntype = \"voronoi\"\n".into());
+    manifest
+}
+
+#[test]
+fn manifests_round_trip_through_json_files() {
+    let path = std::env::temp_dir().join("cells_test_manifest.json");
+    manifest().save_json(&path).unwrap();
+    assert_eq!(Manifest::load(&path).unwrap(), manifest());
+}
+
+#[test]
+fn manifests_round_trip_through_png_files() {
+    let field = generate_perlin_field(32, &FbmParams { seed: 3, ..Default::default() });
+    let path = std::env::temp_dir().join("cells_test_manifest.png");
+    field.to_luma_image().save(&path).unwrap();
+    assert_eq!(Manifest::from_png(&path).unwrap(), None);
+    assert!(Manifest::load(&path).is_err());
+
+    manifest().embed_png(&path).unwrap();
+    assert_eq!(Manifest::load(&path).unwrap(), manifest());
+    let text = read_png_text(&path).unwrap();
+    assert!(text.contains(&("cells:parameter:seed".to_string(), "42".to_string())), "{text:?}");
+    assert!(text.contains(&("Software".to_string(), format!("cells {}", env!("CARGO_PKG_VERSION")))));
+    assert_eq!(image::open(&path).unwrap().to_luma8().into_raw(), field.to_luma_image().into_raw());
+}
@@ -0,0 +1,89 @@
+//! Dilation and erosion must match a brute-force scan of the structuring
+//! element with wraparound, and opening and closing must be idempotent.
+
+use cells::morphology::{close, dilate, erode, open, StructuringElement};
+use cells::FieldBuffer;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+fn random_field(width: u32, height: u32, seed: u64) -> FieldBuffer {
+    let mut rng = StdRng::seed_from_u64(seed);
+    FieldBuffer::from_vec(width, height, (0..width * height).map(|_| rng.gen()).collect())
+}
+
+/// The offsets covered by `element`
+fn offsets(element: StructuringElement) -> Vec<(i64, i64)> {
+    let (r, disc) = match element {
+        StructuringElement::Square(r) => (r as i64, false),
+        StructuringElement::Disc(r) => (r as i64, true),
+    };
+    (-r..=r)
+        .flat_map(|dy| (-r..=r).map(move |dx| (dx, dy)))
+        .filter(|&(dx, dy)| !disc || dx * dx + dy * dy <= r * r)
+        .collect()
+}
+
+fn brute_force_dilate(field: &FieldBuffer, element: StructuringElement) -> FieldBuffer {
+    let offsets = offsets(element);
+    let (width, height) = field.dimensions();
+    FieldBuffer::from_par_fn(width, height, |x, y| {
+        offsets
+            .iter()
+            .map(|&(dx, dy)| field.get_wrapped(x as i64 + dx, y as i64 + dy))
+            .fold(f32::NEG_INFINITY, f32::max)
+    })
+}
+
+const ELEMENTS: [StructuringElement; 6] = [
+    StructuringElement::Square(0),
+    StructuringElement::Square(1),
+    StructuringElement::Square(4),
+    StructuringElement::Disc(1),
+    StructuringElement::Disc(3),
+    StructuringElement::Disc(7),
+];
+
+#[test]
+fn single_pixels_dilate_into_the_element() {
+    for element in ELEMENTS {
+        let dot = FieldBuffer::from_par_fn(17, 19, |x, y| if (x, y) == (16, 1) { 1.0 } else { 0.0 });
+        let grown = dilate(&dot, element);
+        let expected = FieldBuffer::from_par_fn(17, 19, |x, y| {
+            let covered = offsets(element).into_iter().any(|(dx, dy)| {
+                (16 + dx).rem_euclid(17) == x as i64 && (1 + dy).rem_euclid(19) == y as i64
+            });
+            if covered {
+                1.0
+            } else {
+                0.0
+            }
+        });
+        assert_eq!(grown, expected, "{element}");
+    }
+}
+
+#[test]
+fn filters_match_brute_force() {
+    for (width, height) in [(24, 24), (13, 30), (5, 3)] {
+        let field = random_field(width, height, width as u64);
+        for element in ELEMENTS {
+            assert_eq!(dilate(&field, element), brute_force_dilate(&field, element), "{element} on {width}x{height}");
+            let negated = field.map(|v| -v);
+            let eroded = brute_force_dilate(&negated, element).map(|v| -v);
+            assert_eq!(erode(&field, element), eroded, "{element} on {width}x{height}");
+        }
+    }
+}
+
+#[test]
+fn open_and_close_are_idempotent() {
+    let field = random_field(32, 32, 7);
+    for element in ELEMENTS {
+        let opened = open(&field, element);
+        assert_eq!(open(&opened, element), opened, "{element}");
+        assert!(opened.as_slice().iter().zip(field.as_slice()).all(|(o, f)| o <= f));
+        let closed = close(&field, element);
+        assert_eq!(close(&closed, element), closed, "{element}");
+        assert!(closed.as_slice().iter().zip(field.as_slice()).all(|(c, f)| c >= f));
+    }
+}
@@ -0,0 +1,232 @@
+//! The example pipelines must run and match the library calls they spell out.
+
+use std::ops::ControlFlow;
+
+use cells::filter::{iterated_directional_blur, normalize_field, BlurParams, Normalization};
+use cells::gabor::{generate_gabor, generate_gabor_along, GaborOrientation, GaborParams};
+use cells::noise::{generate_perlin_field, FbmParams};
+use cells::pattern::{generate_checker, generate_radial, generate_stripes, generate_truchet, TruchetStyle};
+use cells::pipeline::{NodeCache, Pipeline};
+use cells::voronoi::{generate_voronoi_field, VoronoiParams};
+use cells::Point;
+
+#[test]
+fn default_pipeline_matches_the_generators() {
+    let pipeline = Pipeline::from_toml(include_str!("../examples/default_pipeline.toml")).unwrap();
+    let outputs = pipeline.execute().unwrap();
+
+    let voronoi = generate_voronoi_field(&VoronoiParams { seed: 1, ..Default::default() });
+    let perlin = normalize_field(&generate_perlin_field(512, &FbmParams { seed: 1, ..Default::default() }));
+    let blurred = iterated_directional_blur(&voronoi, &voronoi, &BlurParams::default(), 4, 2.0, Normalization::Stretch);
+    assert_eq!(outputs["voronoi"], voronoi);
+    assert_eq!(outputs["perlin"], perlin);
+    assert_eq!(outputs["blurred"], blurred);
+
+    let saved: Vec<_> = pipeline.nodes().iter().filter_map(|node| node.save.as_ref()).collect();
+    assert_eq!(saved.len(), 3);
+}
+
+#[test]
+fn missing_inputs_name_the_node() {
+    let pipeline = Pipeline::from_toml(
+        r#"
+[[node]]
+name = "soft"
+type = "gaussian_blur"
+input = "cells"
+sigma = 2.0
+"#,
+    )
+    .unwrap();
+    let err = pipeline.execute().unwrap_err();
+    assert_eq!(err.to_string(), "node `soft`: reads `cells`, which is not a node");
+}
+
+#[test]
+fn invalid_parameters_name_the_node() {
+    let err = Pipeline::from_toml("[[node]]\nname = \"cells\"\ntype = \"voronoi\"\nfeature = \"f3\"").unwrap_err();
+    assert!(err.to_string().starts_with("node `cells`: unknown feature `f3`"), "{err}");
+
+    let err = Pipeline::from_toml("[[node]]\nname = \"cells\"\ntype = \"voronoi\"\npionts = 10").unwrap_err();
+    assert!(err.to_string().starts_with("node `cells`: unknown field `pionts`"), "{err}");
+}
+
+#[test]
+fn primitive_nodes_match_the_generators() {
+    let pipeline = Pipeline::from_toml(
+        r#"
+[[node]]
+name = "checker"
+type = "checker"
+size = 32
+cols = 4
+
+[[node]]
+name = "stripes"
+type = "stripes"
+size = 32
+angle = 30.0
+duty = 0.25
+
+[[node]]
+name = "spot"
+type = "radial"
+size = 32
+center = [0.1, 0.9]
+
+[[node]]
+name = "maze"
+type = "truchet"
+size = 32
+grid = 4
+style = "diagonals"
+seed = 2
+"#,
+    )
+    .unwrap();
+    let outputs = pipeline.execute().unwrap();
+    assert_eq!(outputs["checker"], generate_checker(32, 4, 8));
+    assert_eq!(outputs["stripes"], generate_stripes(32, 8, 30.0, 0.25));
+    assert_eq!(outputs["spot"], generate_radial(32, Point { x: 0.1, y: 0.9 }, 0.5));
+    assert_eq!(outputs["maze"], generate_truchet(32, 4, 0.15, TruchetStyle::Diagonals, 2));
+}
+
+#[test]
+fn gabor_nodes_match_the_generators() {
+    let pipeline = Pipeline::from_toml(
+        r#"
+[[node]]
+name = "brushed"
+type = "gabor"
+size = 32
+kernels = 200
+orientation = "fixed:45"
+seed = 3
+
+[[node]]
+name = "swirled"
+type = "gabor"
+size = 32
+kernels = 200
+along = "brushed"
+"#,
+    )
+    .unwrap();
+    let outputs = pipeline.execute().unwrap();
+    let params = GaborParams { size: 32, kernels: 200, ..Default::default() };
+    let brushed = generate_gabor(&GaborParams { orientation: GaborOrientation::Fixed(45.0), ..params }, 3);
+    assert_eq!(outputs["brushed"], brushed);
+    assert_eq!(outputs["swirled"], generate_gabor_along(&params, 0, &brushed));
+}
+
+fn blurred_cells(sigma: f32) -> Pipeline {
+    Pipeline::from_toml(&format!(
+        r#"
+[[node]]
+name = "soft"
+type = "gaussian_blur"
+input = "cells"
+sigma = {sigma}
+
+[[node]]
+name = "cells"
+type = "voronoi"
+size = 32
+points = 9
+"#
+    ))
+    .unwrap()
+}
+
+#[test]
+fn cached_runs_only_recompute_changed_nodes() {
+    let mut cache = NodeCache::default();
+    let mut runs = Vec::new();
+    let mut observe = |sigma| {
+        runs.clear();
+        blurred_cells(sigma)
+            .execute_with(&mut cache, |run| {
+                runs.push((run.name.to_string(), run.cached));
+                ControlFlow::Continue(())
+            })
+            .unwrap()
+            .unwrap()
+    };
+    let first = observe(1.0);
+    let again = observe(1.0);
+    assert_eq!(again, first);
+    let changed = observe(2.5);
+    assert_eq!(changed["cells"], first["cells"]);
+    assert_eq!(changed, blurred_cells(2.5).execute().unwrap());
+    assert_eq!(runs, [("cells".to_string(), true), ("soft".to_string(), false)]);
+    // Only the outputs of the last run are kept
+    assert_eq!(cache.len(), 2);
+}
+
+#[test]
+fn stopped_runs_keep_their_finished_nodes() {
+    let mut cache = NodeCache::default();
+    let stopped = blurred_cells(1.0).execute_with(&mut cache, |_| ControlFlow::Break(())).unwrap();
+    assert!(stopped.is_none());
+    assert_eq!(cache.len(), 1);
+    let mut cached = Vec::new();
+    blurred_cells(1.0)
+        .execute_with(&mut cache, |run| {
+            cached.push(run.cached);
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+    assert_eq!(cached, [true, false]);
+}
+
+fn cache_directory(name: &str) -> std::path::PathBuf {
+    let directory = std::env::temp_dir().join(format!("cells_test_cache_{name}"));
+    let _ = std::fs::remove_dir_all(&directory);
+    directory
+}
+
+/// Run a pipeline with a fresh cache on `directory`, as a new process would
+fn run_from_disk(pipeline: &Pipeline, directory: &std::path::Path, max_bytes: u64) -> (Vec<bool>, Vec<f32>) {
+    let mut cached = Vec::new();
+    let outputs = pipeline
+        .execute_with(&mut NodeCache::with_directory(directory, max_bytes), |run| {
+            cached.push(run.cached);
+            ControlFlow::Continue(())
+        })
+        .unwrap()
+        .unwrap();
+    (cached, outputs["soft"].as_slice().to_vec())
+}
+
+#[test]
+fn cache_directories_reuse_unchanged_upstream_nodes() {
+    let directory = cache_directory("reuse");
+    assert_eq!(run_from_disk(&blurred_cells(1.0), &directory, u64::MAX).0, [false, false]);
+    let (cached, soft) = run_from_disk(&blurred_cells(2.0), &directory, u64::MAX);
+    assert_eq!(cached, [true, false]);
+    let (cached, again) = run_from_disk(&blurred_cells(2.0), &directory, u64::MAX);
+    assert_eq!(cached, [true, true]);
+    // Cached fields round-trip bit for bit
+    let expected = blurred_cells(2.0).execute().unwrap();
+    let bits = |samples: &[f32]| samples.iter().map(|v| v.to_bits()).collect::<Vec<_>>();
+    assert_eq!(bits(&soft), bits(expected["soft"].as_slice()));
+    assert_eq!(bits(&again), bits(&soft));
+
+    NodeCache::clear_directory(&directory).unwrap();
+    assert!(!directory.exists());
+}
+
+#[test]
+fn cache_directories_keep_to_their_size_limit() {
+    let directory = cache_directory("limit");
+    let file_bytes = 12 + 32 * 32 * 4;
+    let files = || std::fs::read_dir(&directory).unwrap().count();
+    run_from_disk(&blurred_cells(1.0), &directory, file_bytes);
+    assert_eq!(files(), 1);
+    // Room for both outputs keeps both
+    run_from_disk(&blurred_cells(1.0), &directory, 2 * file_bytes);
+    assert_eq!(run_from_disk(&blurred_cells(1.0), &directory, 2 * file_bytes).0, [true, true]);
+    // Lowering the limit trims the directory on the next run
+    run_from_disk(&blurred_cells(1.0), &directory, 0);
+    assert_eq!(files(), 0);
+}
@@ -0,0 +1,114 @@
+//! Polar remaps must round-trip smooth fields closely, wrap seamlessly along
+//! the angle and stay finite at the pole.
+
+use cells::noise::{generate_perlin_field, FbmParams};
+use cells::pipeline::Pipeline;
+use cells::transform::{from_polar, from_polar_with, to_polar, to_polar_with, RadialExtension};
+use cells::{FieldBuffer, Point};
+
+/// Smooth noise of 64x64 samples
+fn smooth() -> FieldBuffer {
+    generate_perlin_field(64, &FbmParams { octaves: 1, frequency: 1.0, seed: 6, ..Default::default() })
+}
+
+/// The mean absolute difference between two fields of the same size
+fn mean_error(a: &FieldBuffer, b: &FieldBuffer) -> f32 {
+    let sum: f32 = a.as_slice().iter().zip(b.as_slice()).map(|(a, b)| (a - b).abs()).sum();
+    sum / a.as_slice().len() as f32
+}
+
+#[test]
+fn smooth_fields_round_trip() {
+    let field = smooth();
+    for center in [Point { x: 0.5, y: 0.5 }, Point { x: 0.3, y: 0.8 }] {
+        for extension in [RadialExtension::Clamp, RadialExtension::Mirror] {
+            let polar = to_polar_with(&field, center, extension);
+            let back = from_polar_with(&polar, center, extension);
+            let error = mean_error(&back, &field);
+            assert!(error < 0.01, "{center:?}, {extension}: {error}");
+        }
+    }
+}
+
+#[test]
+fn the_angle_wraps_around_seamlessly() {
+    let polar = to_polar(&smooth(), Point { x: 0.5, y: 0.5 });
+    let (width, height) = polar.dimensions();
+    let step = |a: u32, b: u32| (0..height).map(|v| (polar.get(a, v) - polar.get(b, v)).abs()).fold(0.0, f32::max);
+    let interior = (1..width).map(|u| step(u - 1, u)).fold(0.0, f32::max);
+    assert!(step(width - 1, 0) <= interior, "{} > {interior}", step(width - 1, 0));
+}
+
+#[test]
+fn the_pole_stays_finite() {
+    // The pole falls on a pixel center, on a pixel corner and outside the field
+    let poles = [(0.5, 0.5), (9.0 / 17.0, 9.0 / 17.0), (0.0, 0.0), (-0.5, 1.25)];
+    for size in [1, 2, 17] {
+        let field = FieldBuffer::from_par_fn(size, size, |x, y| (x * 3 + y) as f32);
+        for (x, y) in poles {
+            let center = Point { x, y };
+            let polar = to_polar(&field, center);
+            assert!(polar.as_slice().iter().all(|v| v.is_finite()), "{size}, {center:?}");
+            assert!(from_polar(&polar, center).as_slice().iter().all(|v| v.is_finite()), "{size}, {center:?}");
+        }
+    }
+    // A single row has only the pole
+    let row = FieldBuffer::from_par_fn(5, 1, |x, _| x as f32);
+    assert!(to_polar(&row, Point { x: 0.5, y: 0.5 }).as_slice().iter().all(|&v| v == 2.0));
+    assert_eq!("mirror".parse(), Ok(RadialExtension::Mirror));
+    assert!("wrap".parse::<RadialExtension>().is_err());
+}
+
+#[test]
+fn polar_nodes_streak_radially() {
+    let pipeline = Pipeline::from_toml(
+        r#"
+[[node]]
+name = "noise"
+type = "perlin"
+size = 64
+frequency = 4.0
+
+[[node]]
+name = "polar"
+type = "to_polar"
+input = "noise"
+center = [0.25, 0.5]
+extension = "mirror"
+
+[[node]]
+name = "back"
+type = "from_polar"
+input = "polar"
+center = [0.25, 0.5]
+extension = "mirror"
+
+[[node]]
+name = "centered"
+type = "to_polar"
+input = "noise"
+"#,
+    )
+    .unwrap();
+    let outputs = pipeline.execute().unwrap();
+    let center = Point { x: 0.25, y: 0.5 };
+    let polar = to_polar_with(&outputs["noise"], center, RadialExtension::Mirror);
+    assert_eq!(outputs["polar"], polar);
+    assert_eq!(outputs["back"], from_polar_with(&polar, center, RadialExtension::Mirror));
+    assert_eq!(outputs["centered"], to_polar(&outputs["noise"], Point { x: 0.5, y: 0.5 }));
+
+    let infinite = r#"
+[[node]]
+name = "noise"
+type = "perlin"
+size = 16
+
+[[node]]
+name = "polar"
+type = "to_polar"
+input = "noise"
+center = [inf, 0.5]
+"#;
+    let err = Pipeline::from_toml(infinite).unwrap().execute().unwrap_err();
+    assert!(err.to_string().contains("center must be finite"), "{err}");
+}
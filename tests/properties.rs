@@ -0,0 +1,205 @@
+//! Properties of the toroidal distance and of the wrap-around helpers, checked
+//! on random cases.
+//!
+//! Every property runs on `CASES` cases drawn from a fixed seed, so failures
+//! reproduce. A failing case is shrunk to a simpler one that still fails,
+//! rounding coordinates and moving integers towards zero, before it is
+//! reported.
+
+use std::fmt::Debug;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use cells::filter::{directional_blur_field_with, BlurParams, BlurSampling};
+use cells::transform::offset_field;
+use cells::{toroidal_distance, toroidal_distance_with, DistanceMetric, FieldBuffer, Point};
+
+/// The number of random cases of every property
+const CASES: usize = 500;
+
+/// Values with simpler candidates to shrink a failing case to
+trait Shrink: Clone {
+    /// Simpler values than this one, simplest first
+    fn simpler(&self) -> Vec<Self>;
+}
+
+impl Shrink for f32 {
+    fn simpler(&self) -> Vec<f32> {
+        let mut candidates = vec![0.0, self.trunc()];
+        candidates.extend([1.0, 10.0, 100.0].map(|scale| (self * scale).round() / scale));
+        candidates.retain(|candidate| candidate != self);
+        candidates.dedup();
+        candidates
+    }
+}
+
+impl Shrink for i64 {
+    fn simpler(&self) -> Vec<i64> {
+        let mut candidates = vec![0, self / 2, self - self.signum()];
+        candidates.retain(|candidate| candidate != self);
+        candidates.dedup();
+        candidates
+    }
+}
+
+impl Shrink for Point {
+    fn simpler(&self) -> Vec<Point> {
+        let xs = self.x.simpler().into_iter().map(|x| Point { x, ..*self });
+        xs.chain(self.y.simpler().into_iter().map(|y| Point { y, ..*self })).collect()
+    }
+}
+
+impl<A: Shrink, B: Shrink> Shrink for (A, B) {
+    fn simpler(&self) -> Vec<(A, B)> {
+        let firsts = self.0.simpler().into_iter().map(|a| (a, self.1.clone()));
+        firsts.chain(self.1.simpler().into_iter().map(|b| (self.0.clone(), b))).collect()
+    }
+}
+
+impl<A: Shrink, B: Shrink, C: Shrink> Shrink for (A, B, C) {
+    fn simpler(&self) -> Vec<(A, B, C)> {
+        let (a, b, c) = self.clone();
+        let firsts = a.simpler().into_iter().map(|a| (a, b.clone(), c.clone()));
+        let seconds = b.simpler().into_iter().map(|b| (a.clone(), b, c.clone()));
+        let thirds = c.simpler().into_iter().map(|c| (a.clone(), b.clone(), c));
+        firsts.chain(seconds).chain(thirds).collect()
+    }
+}
+
+/// Check `property` on `CASES` cases from `draw`, panicking with the simplest
+/// failing case found
+fn check<T: Shrink + Debug>(draw: impl Fn(&mut StdRng) -> T, property: impl Fn(&T) -> Result<(), String>) {
+    let mut rng = StdRng::seed_from_u64(1);
+    for _ in 0..CASES {
+        let case = draw(&mut rng);
+        let Err(mut reason) = property(&case) else {
+            continue;
+        };
+        let mut case = case;
+        // Take the first simpler case that still fails until none does
+        'shrink: for _ in 0..1000 {
+            for candidate in case.simpler() {
+                if let Err(candidate_reason) = property(&candidate) {
+                    (case, reason) = (candidate, candidate_reason);
+                    continue 'shrink;
+                }
+            }
+            break;
+        }
+        panic!("property fails for {case:?}: {reason}");
+    }
+}
+
+/// A point in the unit square
+fn point(rng: &mut StdRng) -> Point {
+    Point { x: rng.gen(), y: rng.gen() }
+}
+
+/// An integer number of textures to move by
+fn textures(rng: &mut StdRng) -> i64 {
+    rng.gen_range(-8..=8)
+}
+
+/// Fail with `reason` unless `holds`
+fn ensure(holds: bool, reason: impl FnOnce() -> String) -> Result<(), String> {
+    if holds {
+        Ok(())
+    } else {
+        Err(reason())
+    }
+}
+
+#[test]
+fn toroidal_distances_are_symmetric_and_bounded() {
+    check(
+        |rng| (point(rng), point(rng)),
+        |&(p1, p2)| {
+            let distance = toroidal_distance(p1, p2);
+            ensure(distance == toroidal_distance(p2, p1), || format!("{distance} one way only"))?;
+            ensure(distance >= 0.0, || format!("{distance} is negative"))?;
+            ensure(distance <= 0.5f32.sqrt(), || format!("{distance} is beyond half a texture in x and y"))
+        },
+    );
+}
+
+#[test]
+fn toroidal_distances_are_zero_only_for_the_same_point() {
+    check(
+        |rng| (point(rng), point(rng)),
+        |&(p1, p2)| {
+            ensure(toroidal_distance(p1, p1) == 0.0, || "a point is away from itself".to_string())?;
+            let distance = toroidal_distance(p1, p2);
+            ensure((distance == 0.0) == (p1 == p2), || format!("{distance} between different points"))
+        },
+    );
+}
+
+#[test]
+fn toroidal_distances_ignore_whole_textures() {
+    for metric in [DistanceMetric::Euclidean, DistanceMetric::Manhattan, DistanceMetric::Chebyshev] {
+        check(
+            |rng| ((point(rng), point(rng)), textures(rng), textures(rng)),
+            |&((p1, p2), dx, dy)| {
+                let moved = Point { x: p1.x + dx as f32, y: p1.y + dy as f32 };
+                let (distance, moved_distance) =
+                    (toroidal_distance_with(p1, p2, metric), toroidal_distance_with(moved, p2, metric));
+                // Adding integers rounds the coordinates to coarser floats
+                ensure((distance - moved_distance).abs() <= 1e-5, || {
+                    format!("{metric} distance {distance} becomes {moved_distance} whole textures away")
+                })
+            },
+        );
+    }
+}
+
+#[test]
+fn wrapped_samples_repeat_every_field_size() {
+    let field = FieldBuffer::from_par_fn(7, 5, |x, y| ((x * 31 + y * 17) % 11) as f32);
+    check(
+        |rng| (rng.gen_range(-50..50_i64), rng.gen_range(-50..50_i64), textures(rng)),
+        |&(x, y, k)| {
+            let moved = field.get_wrapped(x + 7 * k, y - 5 * k);
+            ensure(field.get_wrapped(x, y) == moved, || format!("{moved} differs {k} fields away"))?;
+            let (fx, fy) = (x as f32 / 3.0, y as f32 / 7.0);
+            let (sample, moved) =
+                (field.sample_bilinear_wrapped(fx, fy), field.sample_bilinear_wrapped(fx + 7.0 * k as f32, fy));
+            ensure((sample - moved).abs() <= 1e-3, || format!("bilinear {sample} becomes {moved} {k} fields away"))
+        },
+    );
+}
+
+#[test]
+fn offsets_repeat_every_field_size_and_undo() {
+    let field = FieldBuffer::from_par_fn(6, 4, |x, y| (x * 4 + y) as f32);
+    check(
+        |rng| (rng.gen_range(-20..20_i64), rng.gen_range(-20..20_i64), textures(rng)),
+        |&(dx, dy, k)| {
+            let scrolled = offset_field(&field, dx, dy);
+            ensure(offset_field(&field, dx + 6 * k, dy + 4 * k) == scrolled, || format!("{k} fields further differs"))?;
+            ensure(offset_field(&scrolled, -dx, -dy) == field, || "scrolling back differs".to_string())
+        },
+    );
+}
+
+#[test]
+fn directional_blurs_commute_with_offsets() {
+    let field = FieldBuffer::from_par_fn(16, 12, |x, y| ((x * 7 + y * 3) % 10) as f32 / 10.0);
+    let direction = FieldBuffer::from_par_fn(16, 12, |x, y| ((x * 5 + y * 11) % 16) as f32 / 16.0);
+    for sampling in [BlurSampling::Nearest, BlurSampling::Bilinear] {
+        let params = BlurParams { radius: 5, sampling, ..Default::default() };
+        let blurred = directional_blur_field_with(&field, &direction, &params);
+        check(
+            |rng| (rng.gen_range(-40..40_i64), rng.gen_range(-40..40_i64)),
+            |&(dx, dy)| {
+                let (scrolled, scrolled_direction) = (offset_field(&field, dx, dy), offset_field(&direction, dx, dy));
+                let expected = offset_field(&blurred, dx, dy);
+                let actual = directional_blur_field_with(&scrolled, &scrolled_direction, &params);
+                let error = expected.as_slice().iter().zip(actual.as_slice()).map(|(e, a)| (e - a).abs());
+                let error = error.fold(0.0, f32::max);
+                // Bilinear taps at other pixel positions round their fractions differently
+                ensure(error <= 1e-5, || format!("{sampling} blurs differ by {error} after scrolling"))
+            },
+        );
+    }
+}
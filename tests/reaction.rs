@@ -0,0 +1,40 @@
+//! Reaction–diffusion textures are simulated on a torus, so they must tile and
+//! depend on nothing but their parameters.
+
+use cells::reaction::{
+    generate_reaction_diffusion, generate_reaction_diffusion_with, ReactionDiffusionParams, ReactionPreset, Seeding,
+};
+use cells::tiling::{assert_tileable, interior_discontinuity};
+
+fn params(preset: ReactionPreset) -> ReactionDiffusionParams {
+    let (feed, kill) = preset.rates();
+    ReactionDiffusionParams {
+        size: 64,
+        feed,
+        kill,
+        steps: 1000,
+        seed: 4,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn reaction_diffusion_is_deterministic_per_seed() {
+    let (feed, kill) = ReactionPreset::Coral.rates();
+    let coral = generate_reaction_diffusion(64, feed, kill, 300, 9);
+    assert_eq!(coral, generate_reaction_diffusion(64, feed, kill, 300, 9));
+    assert_ne!(coral, generate_reaction_diffusion(64, feed, kill, 300, 10));
+}
+
+#[test]
+fn reaction_diffusion_tiles() {
+    for preset in [ReactionPreset::Mitosis, ReactionPreset::Coral, ReactionPreset::Worms] {
+        for seeding in [Seeding::Spots, Seeding::Noise] {
+            let params = ReactionDiffusionParams { seeding, ..params(preset) };
+            let field = generate_reaction_diffusion_with(&params, |_| {});
+            let (min, max) = field.min_max().unwrap();
+            assert!(max - min > 0.1, "{preset} from {seeding} died out");
+            assert_tileable(&field, interior_discontinuity(&field) + 1e-6);
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! Scratches crossing an edge must continue on the opposite side without a
+//! seam, wherever the grid buckets them.
+
+use cells::scratches::{generate_scratches, render_scratches, Scratch, ScratchParams};
+use cells::tiling::{assert_tileable, interior_discontinuity, seam_discontinuity};
+use cells::transform::offset_field;
+use cells::{FieldBuffer, Point};
+
+const SIZE: u32 = 64;
+
+fn max_difference(a: &FieldBuffer, b: &FieldBuffer) -> f32 {
+    a.as_slice().iter().zip(b.as_slice()).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max)
+}
+
+/// `scratches` moved by whole pixels
+fn moved(scratches: &[Scratch], dx: i64, dy: i64) -> Vec<Scratch> {
+    let shift = |p: Point| Point { x: p.x + dx as f32 / SIZE as f32, y: p.y + dy as f32 / SIZE as f32 };
+    scratches.iter().map(|s| Scratch { from: shift(s.from), to: shift(s.to), ..*s }).collect()
+}
+
+#[test]
+fn scratches_continue_across_the_right_edge() {
+    let inside = Scratch { from: Point { x: 0.25, y: 0.4 }, to: Point { x: 0.75, y: 0.45 }, intensity: 1.0 };
+    let field = render_scratches(SIZE, &[inside], 0.04);
+    // Moved half a texture right, the scratch crosses the right edge
+    let crossing = render_scratches(SIZE, &moved(&[inside], 32, 0), 0.04);
+    assert!(crossing.get(63, 27) > 0.5 && crossing.get(0, 27) > 0.5);
+    assert!(max_difference(&crossing, &offset_field(&field, 32, 0)) < 1e-5);
+    assert!(seam_discontinuity(&crossing) <= interior_discontinuity(&crossing) + 1e-6);
+}
+
+#[test]
+fn moved_scratches_render_scrolled() {
+    let scratches: Vec<Scratch> = (0..30)
+        .map(|i| {
+            let (x, y) = ((i * 7 % 30) as f32 / 30.0, (i * 11 % 30) as f32 / 30.0);
+            let to = Point { x: x + 0.4 * (i as f32).cos(), y: y + 0.4 * (i as f32).sin() };
+            Scratch { from: Point { x, y }, to, intensity: 0.5 + i as f32 / 60.0 }
+        })
+        .collect();
+    let field = render_scratches(SIZE, &scratches, 0.03);
+    for (dx, dy) in [(17, 0), (-5, 40), (63, 63)] {
+        let scrolled = offset_field(&field, dx, dy);
+        let difference = max_difference(&render_scratches(SIZE, &moved(&scratches, dx, dy), 0.03), &scrolled);
+        assert!(difference < 1e-4, "moved by ({dx}, {dy}) the scratches differ by {difference}");
+    }
+}
+
+#[test]
+fn scratch_masks_tile() {
+    for (angle, spread) in [(0.0, 90.0), (30.0, 10.0), (90.0, 0.0)] {
+        let params = ScratchParams {
+            size: SIZE,
+            count: 300,
+            length: (0.3, 1.0),
+            angle,
+            spread,
+            width: 0.08,
+            ..Default::default()
+        };
+        let scratches = generate_scratches(&params, 2);
+        assert!(scratches.as_slice().iter().all(|value| (0.0..=1.0).contains(value)));
+        assert_tileable(&scratches, interior_discontinuity(&scratches));
+    }
+}
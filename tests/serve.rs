@@ -0,0 +1,132 @@
+//! Preview requests must answer with the texture of the pipeline node they
+//! describe, keep the recent ones whatever the order of their parameters, and
+//! turn every invalid or too expensive request into a 400 with the reason,
+//! and stalled clients must not keep others from being answered.
+
+#![cfg(feature = "serve")]
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use cells::serve::PreviewServer;
+use cells::voronoi::{generate_voronoi_field, Feature, VoronoiParams};
+
+fn message(server: &PreviewServer, target: &str) -> String {
+    let response = server.respond(target);
+    assert_eq!(response.status, 400, "{target}");
+    String::from_utf8(response.body.to_vec()).unwrap()
+}
+
+#[test]
+fn previews_match_the_generators() {
+    let server = PreviewServer::new(512, 4);
+    let response = server.respond("/voronoi?size=48&points=12&seed=3&feature=f2%2Df1");
+    assert_eq!((response.status, response.content_type), (200, "image/png"));
+    let png = image::load_from_memory(&response.body).unwrap().to_luma8();
+    let params = VoronoiParams { size: 48, num_points: 12, seed: 3, feature: Feature::F2MinusF1, ..Default::default() };
+    assert_eq!(png.into_raw(), generate_voronoi_field(&params).to_luma_image().into_raw());
+}
+
+#[test]
+fn recent_previews_are_cached() {
+    let server = PreviewServer::new(512, 1);
+    let first = server.respond("/perlin?size=32&octaves=3&seed=1");
+    let reordered = server.respond("/perlin?seed=1&size=32&octaves=3");
+    assert!(Arc::ptr_eq(&first.body, &reordered.body));
+
+    // With room for one texture, another request evicts the first
+    server.respond("/perlin?size=32&octaves=2");
+    let again = server.respond("/perlin?size=32&octaves=3&seed=1");
+    assert!(!Arc::ptr_eq(&first.body, &again.body));
+    assert_eq!(first.body, again.body);
+}
+
+#[test]
+fn invalid_requests_are_rejected() {
+    let server = PreviewServer::new(64, 4);
+    assert!(message(&server, "/voronoi?size=65").contains("maximum of 64"));
+    // The default size of 512 counts too
+    assert!(message(&server, "/voronoi").contains("size 512"));
+    assert!(message(&server, "/voronoi?size=32&feature=f3").contains("unknown feature"));
+    assert!(message(&server, "/voronoi?size=32&colour=red").contains("unknown field `colour`"));
+    assert!(message(&server, "/voronoi?size=32&seed=1&seed=2").contains("twice"));
+    assert!(message(&server, "/perlin?size=32&octaves=0").contains("octave"));
+    assert!(message(&server, "/gaussian_blur?sigma=2").contains("input"));
+    assert!(message(&server, "/voronoi?size=%zz").contains("escape"));
+}
+
+#[test]
+fn expensive_requests_are_rejected() {
+    let server = PreviewServer::new(64, 4);
+    for target in [
+        "/perlin?size=64&octaves=1000000",
+        "/voronoi?size=64&points=1000000000",
+        "/voronoi?size=64&points=100&relax=1000000",
+        "/voronoi?size=64&octaves=40",
+        "/reaction_diffusion?size=64&steps=1000000000",
+        "/gabor?size=64&kernels=1000000000",
+        "/speckle?size=64&count=1000000000",
+    ] {
+        assert!(message(&server, target).contains("times the work"), "{target}");
+    }
+    // The default counts fit at the largest size of the server
+    assert_eq!(server.respond("/reaction_diffusion?size=64").status, 200);
+    assert_eq!(server.respond("/voronoi?size=64&octaves=4").status, 200);
+}
+
+#[test]
+fn requests_are_answered_over_http() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = Arc::new(PreviewServer::new(128, 4));
+    thread::spawn(move || server.serve(listener));
+
+    let get = |request: &str| {
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).unwrap();
+        response
+    };
+    let response = get("GET /voronoi?size=16&points=4 HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    let head_end = response.windows(4).position(|window| window == b"\r\n\r\n").unwrap();
+    let head = String::from_utf8_lossy(&response[..head_end]);
+    assert!(head.starts_with("HTTP/1.1 200 OK\r\n"), "{head}");
+    assert!(head.contains("Content-Type: image/png"), "{head}");
+    let body = &response[head_end + 4..];
+    assert!(head.contains(&format!("Content-Length: {}", body.len())), "{head}");
+    let png = image::load_from_memory(body).unwrap();
+    assert_eq!((png.width(), png.height()), (16, 16));
+
+    let response = get("GET /voronoi?size=1024 HTTP/1.1\r\n\r\n");
+    assert!(response.starts_with(b"HTTP/1.1 400 Bad Request\r\n"));
+    let response = get("DELETE /voronoi HTTP/1.1\r\n\r\n");
+    assert!(response.starts_with(b"HTTP/1.1 405 Method Not Allowed\r\n"));
+}
+
+#[test]
+fn stalled_clients_are_dropped() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = Arc::new(PreviewServer::new(64, 4).with_connections(1, Duration::from_millis(200)));
+    thread::spawn(move || server.serve(listener));
+
+    // The only connection is taken by a client sending half a request
+    let mut stalled = TcpStream::connect(address).unwrap();
+    stalled.write_all(b"GET /voronoi?size=16").unwrap();
+    let started = Instant::now();
+    let mut stream = TcpStream::connect(address).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    stream.write_all(b"GET /voronoi?size=16&points=4 HTTP/1.1\r\n\r\n").unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    assert!(response.starts_with(b"HTTP/1.1 200 OK\r\n"));
+    assert!(started.elapsed() >= Duration::from_millis(150), "{:?}", started.elapsed());
+
+    // The stalled client was hung up on without an answer
+    let mut rest = Vec::new();
+    assert!(stalled.read_to_end(&mut rest).map_or(true, |_| rest.is_empty()));
+}
@@ -0,0 +1,39 @@
+//! Unsharp masks must leave fields alone at amount 0 and overshoot a step by
+//! the share of the blur kernel reaching across it.
+
+use cells::filter::unsharp_mask;
+use cells::FieldBuffer;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+#[test]
+fn zero_amounts_keep_the_field() {
+    let mut rng = StdRng::seed_from_u64(3);
+    let field = FieldBuffer::from_vec(24, 24, (0..24 * 24).map(|_| rng.gen()).collect());
+    for sigma in [0.0, 1.0, 4.0] {
+        assert_eq!(unsharp_mask(&field, sigma, 0.0, 0.0), field);
+    }
+}
+
+#[test]
+fn steps_overshoot_by_the_kernel_across_them() {
+    let step = FieldBuffer::from_par_fn(48, 4, |x, _| if (16..32).contains(&x) { 0.7 } else { 0.3 });
+    let (sigma, amount) = (1.5f32, 0.8);
+    let radius = (3.0 * sigma).ceil() as i32;
+    let weights: Vec<f32> = (-radius..=radius).map(|i| (-(i * i) as f32 / (2.0 * sigma * sigma)).exp()).collect();
+    let total: f32 = weights.iter().sum();
+    // The share of the kernel more than `offset` pixels to the left
+    let beyond = |offset: i32| weights[..(radius - offset) as usize].iter().sum::<f32>() / total;
+    let sharp = unsharp_mask(&step, sigma, amount, 0.0);
+    for offset in 0..radius {
+        let overshoot = amount * 0.4 * beyond(offset);
+        for y in 0..4 {
+            let (x_bright, x_dark) = (16 + offset as u32, 15 - offset as u32);
+            assert!((sharp.get(x_bright, y) - (0.7 + overshoot)).abs() < 1e-5, "{offset} pixels in");
+            assert!((sharp.get(x_dark, y) - (0.3 - overshoot)).abs() < 1e-5, "{offset} pixels out");
+        }
+    }
+    // Overshoots beyond 0-1 are clamped
+    let strong = unsharp_mask(&step, sigma, 20.0, 0.0);
+    assert_eq!((strong.get(16, 0), strong.get(15, 0)), (1.0, 0.0));
+}
@@ -0,0 +1,124 @@
+//! The median filter must match a sorted window, the bilateral filter must
+//! become the Gaussian blur when values no longer limit the weights, and the
+//! Kuwahara filter must flatten cells without moving their borders.
+
+use std::collections::HashMap;
+
+use cells::filter::gaussian_blur;
+use cells::smoothing::{bilateral_filter, kuwahara, median_filter};
+use cells::voronoi::{generate_voronoi_cell_ids, VoronoiParams};
+use cells::FieldBuffer;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+fn max_difference(a: &FieldBuffer, b: &FieldBuffer) -> f32 {
+    a.as_slice().iter().zip(b.as_slice()).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max)
+}
+
+#[test]
+fn medians_of_constant_fields_are_constant() {
+    let constant = FieldBuffer::from_vec(20, 12, vec![0.37; 240]);
+    for radius in [0, 1, 3, 10] {
+        assert_eq!(median_filter(&constant, radius), constant);
+    }
+}
+
+#[test]
+fn medians_match_sorted_windows() {
+    let mut rng = StdRng::seed_from_u64(4);
+    // 8-bit levels, so every value has a histogram bin of its own
+    let field = FieldBuffer::from_vec(23, 17, (0..23 * 17).map(|_| rng.gen_range(0..=255) as f32 / 255.0).collect());
+    // Radii of 12 and more wrap the window around the field
+    for radius in [1, 2, 5, 12, 20, 31] {
+        let r = radius as i64;
+        let expected = FieldBuffer::from_par_fn(23, 17, |x, y| {
+            let mut window: Vec<f32> = (-r..=r)
+                .flat_map(|dy| (-r..=r).map(move |dx| (dx, dy)))
+                .map(|(dx, dy)| field.get_wrapped(x as i64 + dx, y as i64 + dy))
+                .collect();
+            window.sort_by(f32::total_cmp);
+            window[window.len() / 2]
+        });
+        let difference = max_difference(&median_filter(&field, radius), &expected);
+        assert!(difference < 1e-6, "radius {radius} differs by {difference}");
+    }
+}
+
+#[test]
+fn medians_of_huge_windows_are_the_median_of_the_field() {
+    let mut rng = StdRng::seed_from_u64(8);
+    let field = FieldBuffer::from_vec(16, 16, (0..256).map(|_| rng.gen_range(0..=255) as f32 / 255.0).collect());
+    let mut sorted = field.as_slice().to_vec();
+    sorted.sort_by(f32::total_cmp);
+    // The windows wrap around the field thousands of times, so every sample
+    // counts nearly alike
+    for radius in [100_000, u32::MAX] {
+        let median = median_filter(&field, radius);
+        assert!(median.as_slice().iter().all(|&v| (sorted[127]..=sorted[128]).contains(&v)), "radius {radius}");
+    }
+    assert_eq!(median_filter(&FieldBuffer::new(0, 0), 100_000), FieldBuffer::new(0, 0));
+}
+
+#[test]
+fn bilateral_filters_with_a_huge_range_sigma_blur() {
+    let mut rng = StdRng::seed_from_u64(9);
+    let field = FieldBuffer::from_vec(32, 32, (0..32 * 32).map(|_| rng.gen()).collect());
+    for sigma in [0.5, 1.0, 2.5] {
+        let difference = max_difference(&bilateral_filter(&field, sigma, 1e4), &gaussian_blur(&field, sigma));
+        assert!(difference < 1e-4, "sigma {sigma} differs by {difference}");
+    }
+}
+
+/// Voronoi cells with a tone of their own plus noise, and the cell of every pixel
+fn noisy_cells(size: u32) -> (FieldBuffer, Vec<u16>, impl Fn(u16) -> f32) {
+    let params = VoronoiParams { size, num_points: 24, seed: 5, ..Default::default() };
+    let ids: Vec<u16> = generate_voronoi_cell_ids(&params).into_raw();
+    let tone = |id: u16| 0.1 + 0.8 * (id as f32 * 0.618).fract();
+    let mut rng = StdRng::seed_from_u64(1);
+    let values = ids.iter().map(|&id| tone(id) + rng.gen_range(-0.05..0.05)).collect();
+    (FieldBuffer::from_vec(size, size, values), ids, tone)
+}
+
+/// The summed variance of the pixels of every cell
+fn variance_within_cells(field: &FieldBuffer, ids: &[u16]) -> f64 {
+    let mut cells: HashMap<u16, (f64, f64, f64)> = HashMap::new();
+    for (&id, &value) in ids.iter().zip(field.as_slice()) {
+        let cell = cells.entry(id).or_default();
+        *cell = (cell.0 + value as f64, cell.1 + (value * value) as f64, cell.2 + 1.0);
+    }
+    cells.values().map(|(sum, squares, count)| squares / count - (sum / count).powi(2)).sum()
+}
+
+#[test]
+fn kuwahara_flattens_cells_and_keeps_their_borders() {
+    let size = 96;
+    let (field, ids, tone) = noisy_cells(size);
+    for sectors in [4, 8] {
+        let flat = kuwahara(&field, 3, sectors);
+        assert!(variance_within_cells(&flat, &ids) < variance_within_cells(&field, &ids));
+        // Every pixel takes the tone of a cell at most one pixel away, except
+        // a few where several cells meet in corners too thin for any region
+        let mut off = 0;
+        for y in 0..size as i64 {
+            for x in 0..size as i64 {
+                let nearby = (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (dx, dy))).map(|(dx, dy)| {
+                    let (nx, ny) = ((x + dx).rem_euclid(size as i64), (y + dy).rem_euclid(size as i64));
+                    ids[(ny * size as i64 + nx) as usize]
+                });
+                let value = flat.get(x as u32, y as u32);
+                let closest = nearby.map(|id| (value - tone(id)).abs()).fold(f32::INFINITY, f32::min);
+                off += usize::from(closest > 0.05);
+            }
+        }
+        assert!(off < (size * size / 200) as usize, "{off} pixels with {sectors} sectors are off their cells");
+    }
+}
+
+#[test]
+fn kuwahara_clamps_huge_radii_and_keeps_empty_fields() {
+    let (field, _, _) = noisy_cells(16);
+    for sectors in [4, 8] {
+        assert_eq!(kuwahara(&field, 100_000, sectors), kuwahara(&field, 16, sectors));
+        assert_eq!(kuwahara(&FieldBuffer::new(0, 0), 3, sectors), FieldBuffer::new(0, 0));
+    }
+}
@@ -0,0 +1,31 @@
+//! Sobel edges of a vertical step must peak on the step and point across it,
+//! including the step back down across the wrap-around edge.
+
+use cells::flow::sobel;
+use cells::FieldBuffer;
+
+#[test]
+fn vertical_step_edges_read_horizontal() {
+    let step = FieldBuffer::from_par_fn(32, 16, |x, _| if x < 16 { 0.0 } else { 1.0 });
+    let (magnitude, direction) = sobel(&step);
+    for y in 0..16 {
+        for x in 0..32 {
+            let on_edge = matches!(x, 15 | 16 | 31 | 0);
+            assert_eq!(magnitude.get(x, y), if on_edge { 1.0 } else { 0.0 }, "magnitude at ({x}, {y})");
+        }
+        // Uphill is +x on the step up and -x on the wrapped step down
+        assert_eq!((direction.get(15, y), direction.get(16, y)), (0.0, 0.0));
+        assert_eq!((direction.get(31, y), direction.get(0, y)), (0.5, 0.5));
+    }
+}
+
+#[test]
+fn directions_turn_with_the_edge() {
+    // A diagonal ramp rising towards the bottom right, wrapped into a tile
+    let ramp = FieldBuffer::from_par_fn(32, 32, |x, y| ((x + y) as f32 / 32.0 * std::f32::consts::TAU).sin());
+    let (_, direction) = sobel(&ramp);
+    // On the rising slopes the gradient points down-right, at 45 degrees
+    assert!((direction.get(0, 2) - 0.125).abs() < 1e-5);
+    // On the falling slopes it points up-left
+    assert!((direction.get(10, 10) - 0.625).abs() < 1e-5);
+}
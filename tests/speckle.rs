@@ -0,0 +1,68 @@
+//! Splats crossing an edge must continue on the opposite side, exactly as if
+//! the texture were tiled.
+
+use cells::sampling::PointDistribution;
+use cells::speckle::{generate_speckle, render_splats, SpeckleParams, Splat, SplatShape};
+use cells::tiling::{assert_tileable, interior_discontinuity};
+use cells::transform::offset_field;
+use cells::{FieldBuffer, Point};
+
+const SIZE: u32 = 64;
+
+/// A splat centered on pixel `(x, y)`
+fn splat(x: u32, y: u32, radius: f32) -> Splat {
+    let center = |c: u32| (c as f32 + 0.5) / SIZE as f32;
+    Splat { center: Point { x: center(x), y: center(y) }, radius, intensity: 0.8 }
+}
+
+fn max_difference(a: &FieldBuffer, b: &FieldBuffer) -> f32 {
+    a.as_slice().iter().zip(b.as_slice()).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max)
+}
+
+#[test]
+fn splats_wrap_around_the_edges() {
+    for shape in [SplatShape::Gaussian, SplatShape::Disc] {
+        for radius in [0.05, 0.2, 0.5] {
+            let centered = render_splats(SIZE, &[splat(32, 32, radius)], shape);
+            // On an edge, in a corner and far enough in for no wrapping
+            for (x, y) in [(0, 40), (63, 2), (1, 62), (20, 25)] {
+                let moved = render_splats(SIZE, &[splat(x, y, radius)], shape);
+                let scrolled = offset_field(&centered, x as i64 - 32, y as i64 - 32);
+                let difference = max_difference(&moved, &scrolled);
+                assert!(difference < 1e-5, "{shape} of radius {radius} at ({x}, {y}) differs by {difference}");
+            }
+        }
+    }
+}
+
+#[test]
+fn overlapping_splats_add_up() {
+    let (a, b) = (splat(10, 10, 0.1), splat(14, 10, 0.1));
+    for shape in [SplatShape::Gaussian, SplatShape::Disc] {
+        let (alone_a, alone_b) = (render_splats(SIZE, &[a], shape), render_splats(SIZE, &[b], shape));
+        let both = render_splats(SIZE, &[a, b], shape);
+        let sum = FieldBuffer::from_par_fn(SIZE, SIZE, |x, y| alone_a.get(x, y) + alone_b.get(x, y));
+        assert!(max_difference(&both, &sum) < 1e-6);
+        assert!(both.get(12, 10) > 1.0);
+    }
+}
+
+#[test]
+fn speckle_tiles() {
+    for distribution in [PointDistribution::Uniform, PointDistribution::PoissonDisk { min_dist: 0.05 }] {
+        for shape in [SplatShape::Gaussian, SplatShape::Disc] {
+            let params = SpeckleParams {
+                size: SIZE,
+                count: 60,
+                distribution,
+                radius: (0.01, 0.08),
+                shape,
+                seed: 9,
+                ..Default::default()
+            };
+            let speckle = generate_speckle(&params);
+            assert_eq!(speckle, generate_speckle(&params));
+            assert_tileable(&speckle, interior_discontinuity(&speckle));
+        }
+    }
+}
@@ -0,0 +1,67 @@
+//! DDS and KTX2 containers must describe and hold every mip level in the
+//! layout of their format.
+
+#![cfg(feature = "file-io")]
+
+use cells::noise::{generate_perlin_field, FbmParams};
+use cells::resample::{mip_chain, ResampleFilter};
+use cells::texture::{save_dds, save_ktx2, Texture, TextureFormat};
+use cells::FieldBuffer;
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+fn height() -> FieldBuffer {
+    generate_perlin_field(62, &FbmParams { seed: 8, ..Default::default() })
+}
+
+fn mipped_texture(format: TextureFormat) -> Texture {
+    let levels: Vec<_> = mip_chain(&height(), ResampleFilter::Box).into_iter().map(|level| vec![level]).collect();
+    Texture::encode(format, &levels).unwrap()
+}
+
+#[test]
+fn dds_holds_every_level() {
+    let texture = mipped_texture(TextureFormat::R8);
+    let path = std::env::temp_dir().join("cells_test_texture.dds");
+    save_dds(&texture, &path).unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+    assert_eq!(&bytes[..4], b"DDS ");
+    assert_eq!([u32_at(&bytes, 12), u32_at(&bytes, 16), u32_at(&bytes, 28)], [62, 62, 6]);
+    assert_eq!(&bytes[84..88], b"DX10");
+    assert_eq!(u32_at(&bytes, 128), 61);
+    let data: Vec<u8> = texture.levels().concat();
+    assert_eq!(bytes[148..], data);
+    assert_eq!(texture.levels().iter().map(Vec::len).collect::<Vec<_>>(), [3844, 961, 225, 49, 9, 1]);
+}
+
+#[test]
+fn ktx2_indexes_every_level() {
+    let texture = mipped_texture(TextureFormat::R8);
+    let path = std::env::temp_dir().join("cells_test_texture.ktx2");
+    save_ktx2(&texture, &path).unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+    assert_eq!(&bytes[..12], b"\xABKTX 20\xBB\r\n\x1A\n");
+    assert_eq!([u32_at(&bytes, 12), u32_at(&bytes, 20), u32_at(&bytes, 24), u32_at(&bytes, 40)], [9, 62, 62, 6]);
+    let dfd_offset = u32_at(&bytes, 48) as usize;
+    assert_eq!(u32_at(&bytes, dfd_offset) as usize, u32_at(&bytes, 52) as usize);
+    for (i, level) in texture.levels().iter().enumerate() {
+        let (offset, length) = (u64_at(&bytes, 80 + 24 * i) as usize, u64_at(&bytes, 88 + 24 * i) as usize);
+        assert_eq!(offset % 4, 0);
+        assert_eq!(&bytes[offset..offset + length], level.as_slice(), "level {i}");
+    }
+    // Levels are stored smallest first
+    assert!(u64_at(&bytes, 80) > u64_at(&bytes, 80 + 24));
+}
+
+#[test]
+fn levels_must_halve() {
+    let levels = [vec![FieldBuffer::new(8, 8)], vec![FieldBuffer::new(3, 4)]];
+    assert!(Texture::encode(TextureFormat::R8, &levels).is_err());
+    assert!(Texture::encode(TextureFormat::R8, &[]).is_err());
+}
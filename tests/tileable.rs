@@ -0,0 +1,228 @@
+//! The generated textures must repeat without seams: the jump across the
+//! wrap-around edges may be no larger than the steps taken inside the texture.
+
+use cells::filter::{
+    directional_blur_field, directional_blur_field_with_length, gaussian_blur, normalize_field, BlurParams, DirectionMapping,
+};
+use cells::flow::{curl_flow, flow_blur_field};
+use cells::grain::{generate_marble, generate_wood, GrainParams};
+use cells::material::height_to_ao;
+use cells::noise::{generate_fbm_field, generate_perlin_field, FbmParams, NoiseKind, NoiseStyle};
+use cells::pattern::{
+    generate_bricks, generate_checker, generate_radial, generate_stripes, generate_truchet, BrickParams, TruchetStyle,
+};
+use cells::resample::{mip_chain, ResampleFilter};
+use cells::tiling::{assert_tileable, interior_discontinuity};
+use cells::transform::offset_field;
+use cells::voronoi::{generate_voronoi_field, Feature, VoronoiBackend, VoronoiParams, WorleyReturn};
+use cells::{DistanceMetric, FieldBuffer, Point, Weighting};
+
+/// Assert that the seams of `field` are no rougher than its interior
+fn assert_seamless(field: &FieldBuffer) {
+    assert_tileable(field, interior_discontinuity(field) + 1e-6);
+}
+
+fn params() -> VoronoiParams {
+    VoronoiParams {
+        size: 128,
+        num_points: 50,
+        seed: 11,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn voronoi_features_tile() {
+    for feature in [Feature::F1, Feature::F2, Feature::F2MinusF1] {
+        for invert in [false, true] {
+            assert_seamless(&generate_voronoi_field(&VoronoiParams { feature, invert, ..params() }));
+        }
+    }
+}
+
+#[test]
+fn voronoi_metrics_tile() {
+    for metric in [
+        DistanceMetric::Manhattan,
+        DistanceMetric::Chebyshev,
+        DistanceMetric::Minkowski(3.0),
+    ] {
+        assert_seamless(&generate_voronoi_field(&VoronoiParams { metric, ..params() }));
+    }
+}
+
+#[test]
+fn weighted_voronoi_tiles() {
+    assert_seamless(&generate_voronoi_field(&VoronoiParams {
+        weighting: Weighting::Additive,
+        weight_range: (0.0, 0.05),
+        ..params()
+    }));
+}
+
+#[test]
+fn warped_voronoi_tiles() {
+    for warp_frequency in [1.0, 4.0, 7.5] {
+        assert_seamless(&generate_voronoi_field(&VoronoiParams {
+            warp_strength: 0.08,
+            warp_frequency,
+            ..params()
+        }));
+    }
+}
+
+#[test]
+fn noise_worley_tiles() {
+    for return_type in [WorleyReturn::Distance, WorleyReturn::Value] {
+        let backend = VoronoiBackend::NoiseWorley { frequency: 6.0, return_type };
+        assert_seamless(&generate_voronoi_field(&VoronoiParams { backend, ..params() }));
+    }
+}
+
+#[test]
+fn blurred_voronoi_tiles() {
+    let voronoi = generate_voronoi_field(&params());
+    let mut blurred = voronoi.clone();
+    for i in 0..4 {
+        blurred = normalize_field(&directional_blur_field(&blurred, &voronoi, 3 << i));
+        assert_seamless(&blurred);
+    }
+}
+
+#[test]
+fn signed_flow_blur_tiles() {
+    let voronoi = generate_voronoi_field(&params());
+    let length = normalize_field(&generate_perlin_field(128, &FbmParams { frequency: 4.0, ..Default::default() }));
+    for mapping in [DirectionMapping::Half180, DirectionMapping::SignedFlow] {
+        let params = BlurParams { radius: 6, mapping, ..Default::default() };
+        assert_seamless(&directional_blur_field_with_length(&voronoi, &voronoi, &length, &params));
+    }
+}
+
+#[test]
+fn flow_blur_tiles() {
+    let voronoi = generate_voronoi_field(&params());
+    let perlin = generate_perlin_field(128, &FbmParams { frequency: 4.0, ..Default::default() });
+    let (flow_x, flow_y) = curl_flow(&perlin);
+    let mut blurred = voronoi.clone();
+    for i in 0..3 {
+        let step = BlurParams { radius: 3 << i, ..Default::default() };
+        blurred = normalize_field(&flow_blur_field(&blurred, &flow_x, &flow_y, &step));
+        assert_seamless(&blurred);
+    }
+}
+
+#[test]
+fn gaussian_blur_tiles() {
+    let voronoi = generate_voronoi_field(&params());
+    for sigma in [0.5, 2.0, 7.5] {
+        assert_seamless(&gaussian_blur(&voronoi, sigma));
+    }
+}
+
+#[test]
+fn ao_tiles() {
+    let voronoi = generate_voronoi_field(&params());
+    assert_seamless(&height_to_ao(&voronoi, 6.0, 12, 4.0));
+}
+
+#[test]
+fn mip_chains_tile() {
+    let voronoi = generate_voronoi_field(&params());
+    let blurred = directional_blur_field(&voronoi, &voronoi, 3);
+    for filter in [ResampleFilter::Box, ResampleFilter::Lanczos3] {
+        let chain = mip_chain(&blurred, filter);
+        assert_eq!(chain.len(), 8);
+        for pair in chain.windows(2) {
+            assert_eq!(pair[1].dimensions(), (pair[0].width() / 2, pair[0].height() / 2));
+            assert_seamless(&pair[1]);
+        }
+    }
+}
+
+#[test]
+fn perlin_tiles() {
+    for frequency in [1.0, 2.5, 4.0, 16.0] {
+        let params = FbmParams { frequency, seed: 3, ..Default::default() };
+        assert_seamless(&generate_fbm_field(128, &params));
+        assert_seamless(&normalize_field(&generate_perlin_field(128, &params)));
+    }
+    for kind in [NoiseKind::Simplex, NoiseKind::OpenSimplex] {
+        assert_seamless(&generate_fbm_field(128, &FbmParams { kind, frequency: 4.0, ..Default::default() }));
+    }
+    for style in [NoiseStyle::Ridged, NoiseStyle::Billow] {
+        assert_seamless(&generate_fbm_field(128, &FbmParams { style, frequency: 4.0, ..Default::default() }));
+    }
+}
+
+#[test]
+#[should_panic(expected = "does not tile")]
+fn ramp_does_not_tile() {
+    assert_seamless(&FieldBuffer::from_par_fn(64, 64, |x, _| x as f32 / 63.0));
+}
+
+#[test]
+fn bricks_tile() {
+    for cols in [3, 4] {
+        // Every row layout repeats after `period` rows, also across the seam
+        for (rows, offset, period) in [(8, 0.5, 2), (8, 0.25, 4), (6, 0.0, 1)] {
+            for mortar in [0.0, 0.03] {
+                let params = BrickParams {
+                    size: 96,
+                    rows,
+                    cols,
+                    offset,
+                    mortar,
+                    variation: 0.0,
+                    ..Default::default()
+                };
+                let wall = generate_bricks(&params);
+                assert!(wall.as_slice().iter().all(|value| value.is_finite()));
+                let shifted = offset_field(&wall, 0, (period * 96 / rows) as i64);
+                let pairs = wall.as_slice().iter().zip(shifted.as_slice());
+                let mismatch = pairs.map(|(a, b)| (a - b).abs()).fold(0.0, f32::max);
+                assert!(mismatch < 1e-4, "{rows}x{cols} bricks offset by {offset} jump by {mismatch} at the seam");
+                assert_seamless(&generate_bricks(&BrickParams { variation: 0.3, ..params }));
+            }
+        }
+    }
+}
+
+#[test]
+fn primitives_tile() {
+    assert_seamless(&generate_checker(64, 8, 4));
+    for angle in [0.0, 17.0, 45.0, 90.0, 133.0, -60.0] {
+        for count in [1, 5, 12] {
+            assert_seamless(&generate_stripes(64, count, angle, 0.3));
+        }
+    }
+    for center in [Point { x: 0.5, y: 0.5 }, Point { x: 0.0, y: 0.95 }] {
+        assert_seamless(&generate_radial(64, center, 0.4));
+    }
+}
+
+#[test]
+fn truchet_tiles() {
+    for style in [TruchetStyle::Arcs, TruchetStyle::Diagonals] {
+        // Cells that are not a whole number of pixels wide join up as well
+        for (grid, line_width) in [(4, 0.15), (7, 0.3), (1, 1.0)] {
+            let truchet = generate_truchet(96, grid, line_width, style, 11);
+            assert!(truchet.as_slice().iter().all(|value| (0.0..=1.0).contains(value)));
+            assert_seamless(&truchet);
+        }
+    }
+}
+
+#[test]
+fn wood_and_marble_tile() {
+    for generate in [generate_wood, generate_marble] {
+        for (count, turbulence) in [(1, 0.0), (5, 0.5), (12, 2.0)] {
+            let params = GrainParams { size: 96, count, turbulence, seed: 4, ..Default::default() };
+            let grain = generate(&params);
+            assert_eq!(grain, generate(&params));
+            assert_seamless(&grain);
+        }
+        let params = GrainParams { size: 32, ..Default::default() };
+        assert_ne!(generate(&params), generate(&GrainParams { seed: 1, ..params }));
+    }
+}
@@ -0,0 +1,106 @@
+//! Saved fields must be encoded with their transfer function only as they are
+//! quantized, and PNG files must record it in their color space chunks.
+
+use cells::color::TransferFunction;
+use cells::FieldBuffer;
+
+/// A field of mid-gray 0.5
+fn mid_gray() -> FieldBuffer {
+    FieldBuffer::from_par_fn(8, 8, |_, _| 0.5)
+}
+
+#[test]
+fn mid_gray_encodes_to_its_transfer_function() {
+    let quantized = |transfer: TransferFunction| (transfer.encode(0.5) * 255.0).round();
+    assert_eq!(quantized(TransferFunction::Linear), 128.0);
+    assert_eq!(quantized(TransferFunction::Srgb), 188.0);
+    assert_eq!(quantized(TransferFunction::Gamma(2.2)), 186.0);
+    assert_eq!(TransferFunction::Srgb.encode_field(&mid_gray()).to_luma_image().get_pixel(3, 5)[0], 188);
+    // Encoding clamps the field and leaves it as it is
+    let field = FieldBuffer::from_par_fn(3, 1, |x, _| x as f32 - 0.5);
+    let encoded = TransferFunction::Srgb.encode_field(&field);
+    assert_eq!(encoded.to_luma_image().as_raw(), &[0, 188, 255]);
+    assert_eq!(field.as_slice(), [-0.5, 0.5, 1.5]);
+}
+
+#[test]
+fn transfer_functions_parse() {
+    for name in ["linear", "srgb", "gamma:2.2"] {
+        assert_eq!(name.parse::<TransferFunction>().unwrap().to_string(), name);
+    }
+    assert_eq!(toml::Value::try_from(TransferFunction::Srgb).unwrap(), toml::Value::String("srgb".to_string()));
+    for invalid in ["gamma", "gamma:-1", "gamma:inf", "rec709"] {
+        assert!(invalid.parse::<TransferFunction>().is_err(), "{invalid}");
+    }
+    assert!(TransferFunction::Gamma(0.0).validate().is_err());
+}
+
+#[cfg(feature = "file-io")]
+#[test]
+fn pngs_record_their_transfer_function() {
+    use cells::io::{save_field, save_field_with, FileFormat};
+    use cells::CellsError;
+
+    let dir = std::env::temp_dir();
+    let info = |name: &str| {
+        let decoder = png::Decoder::new(std::fs::File::open(dir.join(name)).unwrap());
+        let reader = decoder.read_info().unwrap();
+        (reader.info().srgb, reader.info().source_gamma.map(|gamma| gamma.into_scaled()))
+    };
+    let first = |name: &str| image::open(dir.join(name)).unwrap().to_luma16().get_pixel(0, 0)[0];
+
+    save_field(&mid_gray(), dir.join("cells_test_transfer_linear.png"), None, 8).unwrap();
+    assert_eq!(first("cells_test_transfer_linear.png"), 128 * 257);
+    assert_eq!(info("cells_test_transfer_linear.png"), (None, Some(100_000)));
+
+    save_field_with(&mid_gray(), dir.join("cells_test_transfer_srgb.png"), None, 8, TransferFunction::Srgb).unwrap();
+    assert_eq!(first("cells_test_transfer_srgb.png"), 188 * 257);
+    let (srgb, gamma) = info("cells_test_transfer_srgb.png");
+    assert!(srgb.is_some());
+    assert_eq!(gamma, Some(45_455));
+
+    let gamma = TransferFunction::Gamma(2.0);
+    save_field_with(&mid_gray(), dir.join("cells_test_transfer_gamma.png"), None, 16, gamma).unwrap();
+    assert_eq!(first("cells_test_transfer_gamma.png"), (0.5f32.sqrt() * 65535.0).round() as u16);
+    assert_eq!(info("cells_test_transfer_gamma.png"), (None, Some(50_000)));
+
+    // Formats of linear samples cannot be encoded
+    for format in [FileFormat::Exr, FileFormat::Raw, FileFormat::Hdr, FileFormat::Dds] {
+        let path = dir.join(format!("cells_test_transfer.{format}"));
+        match save_field_with(&mid_gray(), path, None, 8, TransferFunction::Srgb) {
+            Err(CellsError::InvalidParameter { name, .. }) => assert_eq!(name, "transfer", "{format}"),
+            other => panic!("expected an invalid transfer for {format}, got {other:?}"),
+        }
+    }
+    save_field_with(&mid_gray(), dir.join("cells_test_transfer.tga"), None, 8, TransferFunction::Srgb).unwrap();
+    assert_eq!(first("cells_test_transfer.tga"), 188 * 257);
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn colormapped_textures_are_srgb() {
+    let out_dir = std::env::temp_dir().join("cells_test_transfer_cli");
+    let run = |extra: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_cells"))
+            .args(["--size", "32", "--points", "8", "--seed", "3"])
+            .args(extra)
+            .arg("--out-dir")
+            .arg(&out_dir)
+            .output()
+            .unwrap()
+    };
+    let srgb = |name: &str| {
+        let decoder = png::Decoder::new(std::fs::File::open(out_dir.join(name)).unwrap());
+        decoder.read_info().unwrap().info().srgb.is_some()
+    };
+    assert!(run(&[]).status.success());
+    assert!(!srgb("voronoi_texture.png"));
+    assert!(run(&["--colormap", "magma"]).status.success());
+    assert!(srgb("voronoi_texture.png"));
+    assert!(run(&["--transfer", "srgb"]).status.success());
+    assert!(srgb("voronoi_texture.png"));
+
+    let output = run(&["--transfer", "srgb", "--format", "exr"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("exr stores linear samples"));
+}
@@ -0,0 +1,87 @@
+//! Transforms of tileable textures must not lose any pixels.
+
+use cells::tiling::{assert_tileable, interior_discontinuity};
+use cells::transform::{offset, offset_field, symmetrize, Symmetry, Transform};
+use cells::voronoi::{generate_voronoi_cells, generate_voronoi_field, VoronoiParams};
+use cells::FieldBuffer;
+
+/// A 3x2 field without any symmetry:
+///
+/// ```text
+/// 0 1 2
+/// 3 4 5
+/// ```
+fn fixture() -> FieldBuffer {
+    FieldBuffer::from_vec(3, 2, vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0])
+}
+
+fn params() -> VoronoiParams {
+    VoronoiParams {
+        size: 64,
+        num_points: 20,
+        seed: 3,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn offsetting_by_the_size_is_the_identity() {
+    let field = generate_voronoi_field(&params());
+    let (width, height) = field.dimensions();
+    assert_eq!(offset_field(&field, width as i64, height as i64), field);
+    assert_eq!(offset_field(&field, -3 * width as i64, 2 * height as i64), field);
+
+    let img = generate_voronoi_cells(&params(), true);
+    let (width, height) = img.dimensions();
+    assert_eq!(offset(&img, width as i64, height as i64), img);
+}
+
+#[test]
+fn offsets_undo_each_other() {
+    let field = generate_voronoi_field(&params());
+    let scrolled = offset_field(&field, 100, -37);
+    assert_ne!(scrolled, field);
+    assert_eq!(offset_field(&scrolled, -100, 37), field);
+}
+
+#[test]
+fn transforms_match_the_golden_fixtures() {
+    let expected = [
+        (Transform::Rotate90, (2, 3), [3.0, 0.0, 4.0, 1.0, 5.0, 2.0]),
+        (Transform::Rotate180, (3, 2), [5.0, 4.0, 3.0, 2.0, 1.0, 0.0]),
+        (Transform::Rotate270, (2, 3), [2.0, 5.0, 1.0, 4.0, 0.0, 3.0]),
+        (Transform::FlipH, (3, 2), [2.0, 1.0, 0.0, 5.0, 4.0, 3.0]),
+        (Transform::FlipV, (3, 2), [3.0, 4.0, 5.0, 0.0, 1.0, 2.0]),
+    ];
+    for (transform, dimensions, values) in expected {
+        let transformed = transform.apply(&fixture());
+        assert_eq!(transformed.dimensions(), dimensions, "{transform}");
+        assert_eq!(transformed.as_slice(), values, "{transform}");
+    }
+}
+
+#[test]
+fn symmetries_match_the_golden_fixtures() {
+    let expected = [
+        (Symmetry::MirrorX, [1.0, 1.0, 1.0, 4.0, 4.0, 4.0]),
+        (Symmetry::MirrorY, [1.5, 2.5, 3.5, 1.5, 2.5, 3.5]),
+        (Symmetry::MirrorXy, [2.5; 6]),
+    ];
+    for (symmetry, values) in expected {
+        assert_eq!(symmetrize(&fixture(), symmetry).unwrap().as_slice(), values, "{symmetry}");
+    }
+
+    let squares = FieldBuffer::from_par_fn(3, 3, |x, y| ((x + 3 * y) * (x + 3 * y)) as f32);
+    let rotational = symmetrize(&squares, Symmetry::Rotate4).unwrap();
+    assert_eq!(rotational.as_slice(), [26.0, 21.0, 26.0, 21.0, 16.0, 21.0, 26.0, 21.0, 26.0]);
+    assert!(symmetrize(&fixture(), Symmetry::Rotate4).is_err());
+}
+
+#[test]
+fn symmetric_voronoi_fields_tile() {
+    let field = generate_voronoi_field(&params());
+    for symmetry in [Symmetry::MirrorX, Symmetry::MirrorY, Symmetry::MirrorXy, Symmetry::Rotate4] {
+        let ornament = symmetrize(&field, symmetry).unwrap();
+        assert_tileable(&ornament, interior_discontinuity(&ornament) + 1e-6);
+    }
+}
@@ -0,0 +1,36 @@
+//! Noise volumes must tile along x and y within every slice, and along z from
+//! the last slice back to the first as smoothly as between any two slices.
+
+use cells::noise::{FbmParams, NoiseStyle};
+use cells::tiling::{assert_tileable, interior_discontinuity};
+use cells::volume::generate_volume_slice;
+use cells::FieldBuffer;
+
+/// The largest absolute difference between two slices
+fn slice_step(a: &FieldBuffer, b: &FieldBuffer) -> f32 {
+    a.as_slice().iter().zip(b.as_slice()).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max)
+}
+
+#[test]
+fn volumes_tile_along_every_axis() {
+    for style in [NoiseStyle::Fbm, NoiseStyle::Ridged] {
+        let params = FbmParams { octaves: 3, frequency: 3.0, style, seed: 9, ..Default::default() };
+        let depth = 24;
+        let slices: Vec<FieldBuffer> = (0..depth).map(|z| generate_volume_slice(96, depth, z, &params)).collect();
+        for slice in &slices {
+            assert_tileable(slice, interior_discontinuity(slice) + 1e-6);
+        }
+        let largest = slices.windows(2).map(|pair| slice_step(&pair[0], &pair[1])).fold(0.0, f32::max);
+        let wrap = slice_step(&slices[depth as usize - 1], &slices[0]);
+        assert!(wrap <= largest * 1.5, "{style}: z wraps by {wrap}, slices step by at most {largest}");
+    }
+}
+
+#[test]
+fn fractional_frequencies_still_tile() {
+    let params = FbmParams { octaves: 4, frequency: 2.6, lacunarity: 1.7, seed: 4, ..Default::default() };
+    let (first, last) = (generate_volume_slice(32, 16, 0, &params), generate_volume_slice(32, 16, 16, &params));
+    assert_eq!(first, last);
+    let slice = generate_volume_slice(32, 16, 5, &params);
+    assert_tileable(&slice, interior_discontinuity(&slice) + 1e-6);
+}
@@ -0,0 +1,143 @@
+//! Warps must keep the input at zero strength, move it whole by constant
+//! offsets, and read their offsets from two maps or an angle map.
+
+use cells::flow::{angle_offsets, signed_offsets, warp};
+use cells::noise::{generate_perlin_field, FbmParams};
+use cells::pipeline::Pipeline;
+use cells::tiling::{assert_tileable, interior_discontinuity};
+use cells::transform::offset_field;
+use cells::voronoi::{generate_voronoi_field, VoronoiParams};
+use cells::FieldBuffer;
+
+/// A Voronoi field of 40x40 samples
+fn cells() -> FieldBuffer {
+    generate_voronoi_field(&VoronoiParams { size: 40, num_points: 10, seed: 4, ..Default::default() })
+}
+
+/// A field of one value
+fn constant(value: f32) -> FieldBuffer {
+    FieldBuffer::from_par_fn(40, 40, |_, _| value)
+}
+
+#[test]
+fn zero_strength_is_the_identity() {
+    let noise = generate_perlin_field(40, &FbmParams { frequency: 2.0, seed: 8, ..Default::default() });
+    assert_eq!(warp(&cells(), &noise, &noise.map(|v| 1.0 - v), 0.0), cells());
+    assert_eq!(warp(&cells(), &constant(0.0), &constant(0.0), 25.0), cells());
+}
+
+#[test]
+fn integer_offsets_equal_the_offset_transform() {
+    for (dx, dy, strength) in [(3.0, -2.0, 1.0), (-1.0, 4.0, 5.0), (0.5, 0.25, 8.0)] {
+        let warped = warp(&cells(), &constant(dx), &constant(dy), strength);
+        let (shift_x, shift_y) = ((-dx * strength) as i64, (-dy * strength) as i64);
+        assert_eq!(warped, offset_field(&cells(), shift_x, shift_y), "({dx}, {dy}) * {strength}");
+    }
+}
+
+#[test]
+fn noise_offsets_melt_the_cells_and_stay_tileable() {
+    let fbm = FbmParams { frequency: 3.0, seed: 2, ..Default::default() };
+    let (dx, dy) = (generate_perlin_field(40, &fbm), generate_perlin_field(40, &FbmParams { seed: 3, ..fbm }));
+    let melted = warp(&cells(), &signed_offsets(&dx), &signed_offsets(&dy), 6.0);
+    assert_ne!(melted, cells());
+    assert_tileable(&melted, interior_discontinuity(&melted));
+
+    // An angle map moves every sample by the same distance
+    let (right_x, right_y) = angle_offsets(&constant(0.0));
+    assert_eq!(warp(&cells(), &right_x, &right_y, 2.0), offset_field(&cells(), -2, 0));
+}
+
+#[test]
+fn warp_nodes_take_offset_maps_or_an_angle_map() {
+    let pipeline = Pipeline::from_toml(
+        r#"
+[[node]]
+name = "cells"
+type = "voronoi"
+size = 32
+points = 8
+
+[[node]]
+name = "half"
+type = "checker"
+size = 32
+cols = 1
+rows = 1
+
+[[node]]
+name = "offsets"
+type = "warp"
+input = "cells"
+dx = "half"
+dy = "half"
+strength = 3.0
+
+[[node]]
+name = "angles"
+type = "warp"
+input = "cells"
+angle = "half"
+strength = 3.0
+"#,
+    )
+    .unwrap();
+    let outputs = pipeline.execute().unwrap();
+    let half = &outputs["half"];
+    let (dx, dy) = (signed_offsets(half), signed_offsets(half));
+    assert_eq!(outputs["offsets"], warp(&outputs["cells"], &dx, &dy, 3.0));
+    let (dx, dy) = angle_offsets(half);
+    assert_eq!(outputs["angles"], warp(&outputs["cells"], &dx, &dy, 3.0));
+
+    let both = r#"
+[[node]]
+name = "cells"
+type = "voronoi"
+size = 32
+
+[[node]]
+name = "warped"
+type = "warp"
+input = "cells"
+dx = "cells"
+angle = "cells"
+strength = 1.0
+"#;
+    let err = Pipeline::from_toml(both).unwrap().execute().unwrap_err();
+    assert!(err.to_string().contains("`dx` and `dy`, or an `angle` map"), "{err}");
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn the_binary_warps_image_files() {
+    let dir = std::env::temp_dir();
+    let (input, output) = (dir.join("cells_test_warp_in.png"), dir.join("cells_test_warp_out.png"));
+    let (offsets, angles) = (dir.join("cells_test_warp_dx.png"), dir.join("cells_test_warp_angle.png"));
+    cells().to_luma_image().save(&input).unwrap();
+    // 0.5 everywhere reads every pixel near itself, and an angle of zero points right
+    constant(0.5).to_luma_image().save(&offsets).unwrap();
+    constant(0.0).to_luma_image().save(&angles).unwrap();
+    let warp = |args: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_cells"))
+            .arg("warp")
+            .arg(&input)
+            .args(args)
+            .arg("-o")
+            .arg(&output)
+            .output()
+            .unwrap()
+    };
+    let (offsets, angles) = (offsets.to_str().unwrap(), angles.to_str().unwrap());
+    assert!(warp(&["--dx", offsets, "--dy", offsets, "--strength", "0"]).status.success());
+    assert_eq!(image::open(&output).unwrap().to_luma8(), cells().to_luma_image());
+    assert!(warp(&["--angle", angles, "--strength", "4"]).status.success());
+    assert_eq!(image::open(&output).unwrap().to_luma8(), offset_field(&cells(), -4, 0).to_luma_image());
+    assert!(!warp(&["--dx", offsets]).status.success());
+
+    // Maps must match the input
+    generate_voronoi_field(&VoronoiParams { size: 24, ..Default::default() }).to_luma_image().save(&angles).unwrap();
+    let output = warp(&["--angle", angles]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("is 24x24, not 40x40 like the input"), "{stderr}");
+}
@@ -0,0 +1,23 @@
+//! The JavaScript API must return one opaque RGBA pixel per texel, matching
+//! the native generators.
+
+use cells::noise::{generate_perlin_field, FbmParams};
+use cells::wasm::{fbm, voronoi};
+
+#[test]
+fn textures_are_rgba() {
+    let cells = voronoi(64, 30, 5, "f1").unwrap();
+    assert_eq!(cells.len(), 64 * 64 * 4);
+    let noise = fbm(64, 4, 0.5, 2.0, 2.0, 9).unwrap();
+    assert_eq!(noise.len(), 64 * 64 * 4);
+    let params = FbmParams { octaves: 4, frequency: 2.0, seed: 9, ..Default::default() };
+    let gray = generate_perlin_field(64, &params).to_luma_image().into_raw();
+    assert!(noise.chunks(4).zip(gray).all(|(pixel, v)| pixel == [v, v, v, 255]));
+}
+
+#[test]
+fn invalid_parameters_are_errors() {
+    assert!(voronoi(0, 30, 5, "f1").is_err());
+    assert!(voronoi(64, 0, 5, "f1").is_err());
+    assert!(fbm(64, 0, 0.5, 2.0, 1.0, 0).unwrap_err().contains("octaves"));
+}
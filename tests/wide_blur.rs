@@ -0,0 +1,82 @@
+//! Blurs reaching past the field must clamp to it instead of allocating taps
+//! for the whole radius.
+
+use cells::band::{render_all, GaussianBlurred, Stretched};
+use cells::filter::{
+    directional_blur_field_with, gaussian_blur, iterated_blur, normalize_field, BlurParams, Normalization,
+};
+use cells::noise::{generate_perlin_field, FbmParams, PerlinBands};
+use cells::voronoi::{generate_voronoi_field, VoronoiParams};
+use cells::FieldBuffer;
+
+/// A Voronoi field of 16x16 samples
+fn cells() -> FieldBuffer {
+    generate_voronoi_field(&VoronoiParams { size: 16, num_points: 4, seed: 3, ..Default::default() })
+}
+
+#[test]
+fn iterated_radii_stop_at_the_field_size() {
+    let field = cells();
+    for (steps, growth) in [(40, 2.0), (3, 1e30)] {
+        let mut radii = Vec::new();
+        let blurred = iterated_blur(
+            &field,
+            &BlurParams { radius: 3, ..Default::default() },
+            steps,
+            growth,
+            Normalization::Stretch,
+            |blurred, step| {
+                radii.push(step.radius);
+                directional_blur_field_with(blurred, &field, step)
+            },
+            |_, _| {},
+        );
+        assert_eq!(radii.len(), steps as usize);
+        assert!(radii.iter().all(|&radius| (3..=16).contains(&radius)), "{radii:?}");
+        assert_eq!(radii.last(), Some(&16));
+        assert!(blurred.as_slice().iter().all(|v| v.is_finite()));
+    }
+}
+
+#[test]
+fn gaussian_kernels_stop_at_the_field_size() {
+    // The kernel spans the field, so every sample averages nearly all of it
+    let field = cells();
+    let flat = gaussian_blur(&field, 1e9);
+    let (min, max) = flat.min_max().unwrap();
+    assert!(max - min < 0.05, "{min} to {max}");
+
+    // Bands clamp their halo the same way
+    let params = FbmParams { frequency: 2.0, ..Default::default() };
+    let blurred = GaussianBlurred::new(Stretched::new(PerlinBands::new(16, &params).unwrap(), 4), 1e9);
+    assert_eq!(blurred.halo(), 16);
+    assert_eq!(render_all(&blurred, 4), gaussian_blur(&normalize_field(&generate_perlin_field(16, &params)), 1e9));
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn the_binary_survives_huge_blur_growth() {
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_cells"))
+        .args(["--size", "8", "--points", "4", "--blur-growth", "1e30", "--out-dir"])
+        .arg(std::env::temp_dir().join("cells_test_wide_blur"))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn the_binary_survives_huge_gaussian_sigmas() {
+    let dir = std::env::temp_dir();
+    let (input, output) = (dir.join("cells_test_wide_gaussian_in.png"), dir.join("cells_test_wide_gaussian_out.png"));
+    cells().to_luma_image().save(&input).unwrap();
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_cells"))
+        .arg("filter")
+        .arg(&input)
+        .arg("-o")
+        .arg(&output)
+        .arg("gaussian:1e9")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}